@@ -0,0 +1,206 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! The cost of executing blocks of transfers, and of committing to states with many accounts.
+//!
+//! Run with `cargo bench --bench execute_block`, and compare against a baseline saved on the
+//! parent commit with `--save-baseline` to catch regressions.
+//!
+//! Executing a block here applies its rollup transactions and commits to the new state, as
+//! `State::execute_block` does once it has decoded them. Decoding the namespace and generating
+//! the mock proof are left out, since they need a sequencer block and do not depend on the state.
+//!
+//! The state commitment is cached between changes, and a block only re-encodes the accounts it
+//! changes; the commitment still hashes the encoding of every account, so its format is unchanged.
+//! `commit` measures committing to a fresh state, which encodes every account, and
+//! `commit_after_transfer` committing again after a block with one transfer. No results are
+//! checked in: save a criterion baseline to compare against.
+
+use async_std::task::block_on;
+use commit::{Commitment, Committable};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Address, U256},
+};
+use example_l2::{
+    state::{ChainConfig, State, Weight},
+    transaction::{RollupTransaction, SignedTransaction, Transaction, DEFAULT_VM_ID},
+    RollupVM,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use sequencer::NMTRoot;
+use sequencer_utils::u256_to_commitment;
+
+/// A state funding `accounts`, with no limit on the weight of a block so that every transaction
+/// of the largest block is executed.
+fn state(accounts: &[Address]) -> State {
+    State::from_initial_balances(
+        accounts.iter().map(|address| (*address, 100)),
+        RollupVM::new(1.into()),
+    )
+    .with_chain_config(ChainConfig {
+        max_block_weight: Weight::MAX,
+        ..Default::default()
+    })
+}
+
+/// Wallets for `size` senders, and a block in which each pays the next.
+fn block(size: u64) -> (Vec<Address>, Vec<RollupTransaction>) {
+    let wallets = (0..size)
+        .map(|seed| LocalWallet::new(&mut ChaChaRng::seed_from_u64(seed)))
+        .collect::<Vec<_>>();
+    let transactions = wallets
+        .iter()
+        .enumerate()
+        .map(|(i, wallet)| {
+            let transaction = Transaction {
+                amount: 1,
+                destination: wallets[(i + 1) % wallets.len()].address(),
+                nonce: 1,
+                priority: 0,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            };
+            block_on(SignedTransaction::new_with_wallet(transaction, wallet)).into()
+        })
+        .collect();
+    (
+        wallets.iter().map(|wallet| wallet.address()).collect(),
+        transactions,
+    )
+}
+
+fn execute_block(c: &mut Criterion) {
+    let commitment: Commitment<NMTRoot> = u256_to_commitment(U256::one()).unwrap();
+    let mut group = c.benchmark_group("execute_block");
+    group.sample_size(10);
+    for size in [100, 1_000, 10_000] {
+        let (accounts, transactions) = block(size);
+        for eager in [false, true] {
+            let recovery = if eager { "eager" } else { "lazy" };
+            group.bench_with_input(
+                BenchmarkId::new(format!("{recovery} recovery"), size),
+                &transactions,
+                |b, transactions| {
+                    b.iter_batched(
+                        || {
+                            let mut state = state(&accounts);
+                            state.set_eager_recovery(eager);
+                            (state, transactions.clone())
+                        },
+                        |(mut state, transactions)| {
+                            let outcomes =
+                                state.apply_block_with_transactions(commitment, transactions);
+                            (state.commit(), outcomes)
+                        },
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+/// Executing the largest block with its signatures recovered on more and more threads, which needs
+/// the `parallel` feature.
+fn recovery_threads(c: &mut Criterion) {
+    let commitment: Commitment<NMTRoot> = u256_to_commitment(U256::one()).unwrap();
+    let (accounts, transactions) = block(10_000);
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut group = c.benchmark_group("recovery_threads");
+    group.sample_size(10);
+    for threads in [1, 2, 4, 8].into_iter().filter(|&threads| threads <= cores) {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(threads),
+            &transactions,
+            |b, transactions| {
+                b.iter_batched(
+                    || {
+                        let mut state = state(&accounts);
+                        state.set_eager_recovery(true);
+                        state.set_recovery_threads(threads);
+                        (state, transactions.clone())
+                    },
+                    |(mut state, transactions)| {
+                        let outcomes =
+                            state.apply_block_with_transactions(commitment, transactions);
+                        (state.commit(), outcomes)
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+fn commit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("commit");
+    group.sample_size(10);
+    for size in [10_000, 100_000] {
+        let accounts = (0..size).map(|_| Address::random()).collect::<Vec<_>>();
+        // The commitment is remembered until the state changes, so each iteration commits to a
+        // fresh state.
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &accounts,
+            |b, accounts| {
+                b.iter_batched(
+                    || state(accounts),
+                    |state| state.commit(),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+fn commit_after_transfer(c: &mut Criterion) {
+    let commitment: Commitment<NMTRoot> = u256_to_commitment(U256::one()).unwrap();
+    let (senders, transactions) = block(1);
+    let transactions = transactions[..1].to_vec();
+    let mut group = c.benchmark_group("commit_after_transfer");
+    group.sample_size(10);
+    for size in [10_000, 100_000] {
+        let accounts = senders
+            .iter()
+            .copied()
+            .chain((1..size).map(|_| Address::random()))
+            .collect::<Vec<_>>();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &accounts,
+            |b, accounts| {
+                b.iter_batched(
+                    || {
+                        let state = state(accounts);
+                        state.commit();
+                        (state, transactions.clone())
+                    },
+                    |(mut state, transactions)| {
+                        state.apply_block_with_transactions(commitment, transactions);
+                        state.commit()
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    execute_block,
+    recovery_threads,
+    commit,
+    commit_after_transfer
+);
+criterion_main!(benches);
@@ -0,0 +1,103 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! The cost of recovering transaction senders in a block of 1,000 transfers.
+//!
+//! Run with `cargo bench --bench sender_recovery`.
+
+use async_std::task::block_on;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use ethers::signers::{LocalWallet, Signer};
+use example_l2::{
+    state::State,
+    transaction::{SignedTransaction, Transaction, DEFAULT_VM_ID},
+    RollupVM,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+const BLOCK_SIZE: u64 = 1000;
+
+/// A state funding `BLOCK_SIZE` accounts, and a block in which each pays the next.
+fn block() -> (State, Vec<SignedTransaction>) {
+    let wallets = (0..BLOCK_SIZE)
+        .map(|seed| LocalWallet::new(&mut ChaChaRng::seed_from_u64(seed)))
+        .collect::<Vec<_>>();
+    let state = State::from_initial_balances(
+        wallets.iter().map(|wallet| (wallet.address(), 100)),
+        RollupVM::new(1.into()),
+    );
+    let transactions = wallets
+        .iter()
+        .enumerate()
+        .map(|(i, wallet)| {
+            let transaction = Transaction {
+                amount: 1,
+                destination: wallets[(i + 1) % wallets.len()].address(),
+                nonce: 1,
+                priority: 0,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            };
+            block_on(SignedTransaction::new_with_wallet(transaction, wallet))
+        })
+        .collect();
+    (state, transactions)
+}
+
+fn sender_recovery(c: &mut Criterion) {
+    let (state, transactions) = block();
+    let mut group = c.benchmark_group("1000-transaction block");
+    group.sample_size(10);
+
+    // A transaction's sender is needed when it is submitted, when it is executed and when its
+    // receipt is written.
+    group.bench_function("recover three times", |b| {
+        b.iter(|| {
+            for txn in &transactions {
+                for _ in 0..3 {
+                    txn.verify().unwrap();
+                }
+            }
+        })
+    });
+    group.bench_function("cached sender three times", |b| {
+        b.iter_batched(
+            || transactions.clone(),
+            |transactions| {
+                for txn in &transactions {
+                    for _ in 0..3 {
+                        txn.sender().unwrap();
+                    }
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    for eager in [false, true] {
+        let name = if eager {
+            "execute, eager recovery"
+        } else {
+            "execute, lazy recovery"
+        };
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    let mut state = state.clone();
+                    state.set_eager_recovery(eager);
+                    (state, transactions.clone())
+                },
+                |(mut state, transactions)| state.apply_transactions(transactions),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sender_recovery);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Embed the git commit and time of the build, read by `example_l2::build_info`.
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // Builds from a source archive have no git metadata, but can be told the commit.
+    println!("cargo:rerun-if-env-changed=EXAMPLE_L2_GIT_COMMIT");
+    let commit = std::env::var("EXAMPLE_L2_GIT_COMMIT").ok().or_else(|| {
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().into())
+    });
+    println!(
+        "cargo:rustc-env=EXAMPLE_L2_GIT_COMMIT={}",
+        commit.unwrap_or_else(|| "unknown".into())
+    );
+
+    // This script only reruns when the checked out commit changes, so the timestamp is that of the
+    // first build of each commit.
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    println!("cargo:rustc-env=EXAMPLE_L2_BUILD_TIMESTAMP={timestamp}");
+}
@@ -29,19 +29,33 @@ pub mod example_rollup {
                             ::std::borrow::ToOwned::to_owned("uint256"),
                         ),
                     },
+                    ::ethers::core::abi::ethabi::Param {
+                        name: ::std::borrow::ToOwned::to_owned("challengeWindowSeconds"),
+                        kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                        internal_type: ::core::option::Option::Some(
+                            ::std::borrow::ToOwned::to_owned("uint256"),
+                        ),
+                    },
+                    ::ethers::core::abi::ethabi::Param {
+                        name: ::std::borrow::ToOwned::to_owned("initialSubmitter"),
+                        kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                        internal_type: ::core::option::Option::Some(
+                            ::std::borrow::ToOwned::to_owned("address"),
+                        ),
+                    },
                 ],
             }),
             functions: ::core::convert::From::from([
                 (
-                    ::std::borrow::ToOwned::to_owned("hotshot"),
+                    ::std::borrow::ToOwned::to_owned("MAX_ENQUEUED_BYTES"),
                     ::std::vec![::ethers::core::abi::ethabi::Function {
-                        name: ::std::borrow::ToOwned::to_owned("hotshot"),
+                        name: ::std::borrow::ToOwned::to_owned("MAX_ENQUEUED_BYTES"),
                         inputs: ::std::vec![],
                         outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
                             name: ::std::string::String::new(),
-                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
                             internal_type: ::core::option::Option::Some(
-                                ::std::borrow::ToOwned::to_owned("contract HotShot"),
+                                ::std::borrow::ToOwned::to_owned("uint256"),
                             ),
                         },],
                         constant: ::core::option::Option::None,
@@ -49,9 +63,9 @@ pub mod example_rollup {
                     },],
                 ),
                 (
-                    ::std::borrow::ToOwned::to_owned("numVerifiedBlocks"),
+                    ::std::borrow::ToOwned::to_owned("MAX_LEASE_BLOCKS"),
                     ::std::vec![::ethers::core::abi::ethabi::Function {
-                        name: ::std::borrow::ToOwned::to_owned("numVerifiedBlocks"),
+                        name: ::std::borrow::ToOwned::to_owned("MAX_LEASE_BLOCKS"),
                         inputs: ::std::vec![],
                         outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
                             name: ::std::string::String::new(),
@@ -65,9 +79,9 @@ pub mod example_rollup {
                     },],
                 ),
                 (
-                    ::std::borrow::ToOwned::to_owned("stateCommitment"),
+                    ::std::borrow::ToOwned::to_owned("MAX_TENURE_BLOCKS"),
                     ::std::vec![::ethers::core::abi::ethabi::Function {
-                        name: ::std::borrow::ToOwned::to_owned("stateCommitment"),
+                        name: ::std::borrow::ToOwned::to_owned("MAX_TENURE_BLOCKS"),
                         inputs: ::std::vec![],
                         outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
                             name: ::std::string::String::new(),
@@ -81,36 +95,54 @@ pub mod example_rollup {
                     },],
                 ),
                 (
-                    ::std::borrow::ToOwned::to_owned("verifyBlocks"),
+                    ::std::borrow::ToOwned::to_owned("TENURE_COOLDOWN_BLOCKS"),
                     ::std::vec![::ethers::core::abi::ethabi::Function {
-                        name: ::std::borrow::ToOwned::to_owned("verifyBlocks"),
+                        name: ::std::borrow::ToOwned::to_owned("TENURE_COOLDOWN_BLOCKS"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("authorizedSubmitter"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("authorizedSubmitter"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("challenge"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("challenge"),
                         inputs: ::std::vec![
                             ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("count"),
-                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(64usize),
-                                internal_type: ::core::option::Option::Some(
-                                    ::std::borrow::ToOwned::to_owned("uint64"),
-                                ),
-                            },
-                            ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("nextStateCommitment",),
+                                name: ::std::borrow::ToOwned::to_owned("height"),
                                 kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
                                 internal_type: ::core::option::Option::Some(
                                     ::std::borrow::ToOwned::to_owned("uint256"),
                                 ),
                             },
                             ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("proof"),
-                                kind: ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
-                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
-                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
-                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
-                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
-                                ],),
+                                name: ::std::borrow::ToOwned::to_owned("fraudProof"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Bytes,
                                 internal_type: ::core::option::Option::Some(
-                                    ::std::borrow::ToOwned::to_owned(
-                                        "struct ExampleRollup.BatchProof",
-                                    ),
+                                    ::std::borrow::ToOwned::to_owned("bytes"),
                                 ),
                             },
                         ],
@@ -119,269 +151,1622 @@ pub mod example_rollup {
                         state_mutability: ::ethers::core::abi::ethabi::StateMutability::NonPayable,
                     },],
                 ),
-            ]),
-            events: ::core::convert::From::from([(
-                ::std::borrow::ToOwned::to_owned("StateUpdate"),
-                ::std::vec![::ethers::core::abi::ethabi::Event {
-                    name: ::std::borrow::ToOwned::to_owned("StateUpdate"),
-                    inputs: ::std::vec![
-                        ::ethers::core::abi::ethabi::EventParam {
-                            name: ::std::borrow::ToOwned::to_owned("blockHeight"),
+                (
+                    ::std::borrow::ToOwned::to_owned("challengeWindow"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("challengeWindow"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
                             kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
-                            indexed: false,
-                        },
-                        ::ethers::core::abi::ethabi::EventParam {
-                            name: ::std::borrow::ToOwned::to_owned("stateCommitment"),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("claimSubmitter"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("claimSubmitter"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("leaseBlocks"),
                             kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
-                            indexed: false,
-                        },
-                    ],
-                    anonymous: false,
-                },],
-            )]),
-            errors: ::core::convert::From::from([
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        outputs: ::std::vec![],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::NonPayable,
+                    },],
+                ),
                 (
-                    ::std::borrow::ToOwned::to_owned("InvalidProof"),
-                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
-                        name: ::std::borrow::ToOwned::to_owned("InvalidProof"),
+                    ::std::borrow::ToOwned::to_owned("currentSubmitter"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("currentSubmitter"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("depositERC20"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("depositERC20"),
                         inputs: ::std::vec![
                             ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("firstBlock"),
-                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
-                                internal_type: ::core::option::Option::Some(
-                                    ::std::borrow::ToOwned::to_owned("uint256"),
-                                ),
-                            },
-                            ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("lastBlock"),
-                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
-                                internal_type: ::core::option::Option::Some(
-                                    ::std::borrow::ToOwned::to_owned("uint256"),
-                                ),
-                            },
-                            ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("oldState"),
-                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                name: ::std::borrow::ToOwned::to_owned("token"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Address,
                                 internal_type: ::core::option::Option::Some(
-                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                    ::std::borrow::ToOwned::to_owned("address"),
                                 ),
                             },
                             ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("newState"),
+                                name: ::std::borrow::ToOwned::to_owned("amount"),
                                 kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
                                 internal_type: ::core::option::Option::Some(
                                     ::std::borrow::ToOwned::to_owned("uint256"),
                                 ),
                             },
                             ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("proof"),
-                                kind: ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
-                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
-                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
-                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
-                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
-                                ],),
+                                name: ::std::borrow::ToOwned::to_owned("recipient"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Address,
                                 internal_type: ::core::option::Option::Some(
-                                    ::std::borrow::ToOwned::to_owned(
-                                        "struct ExampleRollup.BatchProof",
-                                    ),
+                                    ::std::borrow::ToOwned::to_owned("address"),
                                 ),
                             },
                         ],
+                        outputs: ::std::vec![],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::NonPayable,
                     },],
                 ),
                 (
-                    ::std::borrow::ToOwned::to_owned("NoBlocks"),
-                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
-                        name: ::std::borrow::ToOwned::to_owned("NoBlocks"),
+                    ::std::borrow::ToOwned::to_owned("depositETH"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("depositETH"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("recipient"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                        outputs: ::std::vec![],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::Payable,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("enqueueTransaction"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("enqueueTransaction"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("transaction"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Bytes,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("bytes"),
+                            ),
+                        },],
+                        outputs: ::std::vec![],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::NonPayable,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("finalizeBlock"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("finalizeBlock"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("height"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        outputs: ::std::vec![],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::NonPayable,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("hotshot"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("hotshot"),
                         inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("contract HotShot"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
                     },],
                 ),
                 (
-                    ::std::borrow::ToOwned::to_owned("NotYetSequenced"),
-                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
-                        name: ::std::borrow::ToOwned::to_owned("NotYetSequenced"),
-                        inputs: ::std::vec![
-                            ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("numVerifiedBlocks"),
-                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
-                                internal_type: ::core::option::Option::Some(
-                                    ::std::borrow::ToOwned::to_owned("uint256"),
-                                ),
-                            },
-                            ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("count"),
-                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(64usize),
-                                internal_type: ::core::option::Option::Some(
-                                    ::std::borrow::ToOwned::to_owned("uint64"),
-                                ),
-                            },
-                            ::ethers::core::abi::ethabi::Param {
-                                name: ::std::borrow::ToOwned::to_owned("blockHeight"),
-                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
-                                internal_type: ::core::option::Option::Some(
-                                    ::std::borrow::ToOwned::to_owned("uint256"),
-                                ),
-                            },
-                        ],
+                    ::std::borrow::ToOwned::to_owned("leaseExpiry"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("leaseExpiry"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
                     },],
                 ),
-            ]),
-            receive: false,
-            fallback: false,
-        }
-    }
-    ///The parsed JSON ABI of the contract.
-    pub static EXAMPLEROLLUP_ABI: ::ethers::contract::Lazy<::ethers::core::abi::Abi> =
-        ::ethers::contract::Lazy::new(__abi);
-    #[rustfmt::skip]
-    const __BYTECODE: &[u8] = b"`\x80`@R4\x80\x15a\0\x10W`\0\x80\xFD[P`@Qa\x05R8\x03\x80a\x05R\x839\x81\x01`@\x81\x90Ra\0/\x91a\0ZV[`\0\x80T`\x01`\x01`\xA0\x1B\x03\x19\x16`\x01`\x01`\xA0\x1B\x03\x93\x90\x93\x16\x92\x90\x92\x17\x82U`\x01U`\x02Ua\0\x94V[`\0\x80`@\x83\x85\x03\x12\x15a\0mW`\0\x80\xFD[\x82Q`\x01`\x01`\xA0\x1B\x03\x81\x16\x81\x14a\0\x84W`\0\x80\xFD[` \x93\x90\x93\x01Q\x92\x94\x92\x93PPPV[a\x04\xAF\x80a\0\xA3`\09`\0\xF3\xFE`\x80`@R4\x80\x15a\0\x10W`\0\x80\xFD[P`\x046\x10a\0LW`\x005`\xE0\x1C\x80c\x03%q\xA9\x14a\0QW\x80c*\xDC\x8Bv\x14a\0fW\x80cA,\xC8\xFE\x14a\0\x96W\x80c\xD8\0t\x1E\x14a\0\xADW[`\0\x80\xFD[a\0da\0_6`\x04a\x03\xC7V[a\0\xB6V[\0[`\0Ta\0y\x90`\x01`\x01`\xA0\x1B\x03\x16\x81V[`@Q`\x01`\x01`\xA0\x1B\x03\x90\x91\x16\x81R` \x01[`@Q\x80\x91\x03\x90\xF3[a\0\x9F`\x02T\x81V[`@Q\x90\x81R` \x01a\0\x8DV[a\0\x9F`\x01T\x81V[\x82g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\0\x03a\0\xE1W`@Qc\x0F\xD4\xB67`\xE3\x1B\x81R`\x04\x01`@Q\x80\x91\x03\x90\xFD[`\0\x80`\0\x90T\x90a\x01\0\n\x90\x04`\x01`\x01`\xA0\x1B\x03\x16`\x01`\x01`\xA0\x1B\x03\x16c\xF4O\xF7\x12`@Q\x81c\xFF\xFF\xFF\xFF\x16`\xE0\x1B\x81R`\x04\x01` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x015W=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x01Y\x91\x90a\x04\x1EV[\x90P\x80\x84g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\x02Ta\x01t\x91\x90a\x04MV[\x11\x15a\x01\xB6W`\x02T`@Qc\xF08Hg`\xE0\x1B\x81R`\x04\x81\x01\x91\x90\x91Rg\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x85\x16`$\x82\x01R`D\x81\x01\x82\x90R`d\x01[`@Q\x80\x91\x03\x90\xFD[`\0\x80T`\x02T`@QcI\xCE\x89\x97`\xE0\x1B\x81R`\x01`\x01`\xA0\x1B\x03\x90\x92\x16\x91cI\xCE\x89\x97\x91a\x01\xEC\x91`\x04\x01\x90\x81R` \x01\x90V[` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x02\tW=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x02-\x91\x90a\x04\x1EV[`\0\x80T`\x02T\x92\x93P\x90\x91`\x01`\x01`\xA0\x1B\x03\x90\x91\x16\x90cI\xCE\x89\x97\x90`\x01\x90a\x02c\x90g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x8B\x16\x90a\x04MV[a\x02m\x91\x90a\x04fV[`@Q\x82c\xFF\xFF\xFF\xFF\x16`\xE0\x1B\x81R`\x04\x01a\x02\x8B\x91\x81R` \x01\x90V[` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x02\xA8W=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x02\xCC\x91\x90a\x04\x1EV[\x90Pa\x02\xDD\x82\x82`\x01T\x88\x88a\x03\xA5V[a\x03;W`\x01T`@\x80QcD\xCC@\r`\xE1\x1B\x81R`\x04\x81\x01\x85\x90R`$\x81\x01\x84\x90R`D\x81\x01\x92\x90\x92R`d\x82\x01\x87\x90R\x855`\x84\x83\x01R` \x86\x015`\xA4\x83\x01R\x85\x015`\xC4\x82\x01R``\x85\x015`\xE4\x82\x01Ra\x01\x04\x01a\x01\xADV[\x85g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\x02`\0\x82\x82Ta\x03W\x91\x90a\x04MV[\x90\x91UPP`\x01\x85\x90U`\x02T`@\x80Q\x91\x82R` \x82\x01\x87\x90R\x7F\x9C:SN\xC4A\xC7c:pv_T\xC0\x02L\xA8\xF9\xA7nz,\xDA\xAC*j\x8C5\x19\xC0\xCA\xF3\x91\x01`@Q\x80\x91\x03\x90\xA1PPPPPPV[`\0\x81`@\x015\x84\x14\x80\x15a\x03\xBDWP\x81``\x015\x83\x14[\x96\x95PPPPPPV[`\0\x80`\0\x83\x85\x03`\xC0\x81\x12\x15a\x03\xDDW`\0\x80\xFD[\x845g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x81\x16\x81\x14a\x03\xF5W`\0\x80\xFD[\x93P` \x85\x015\x92P`\x80`?\x19\x82\x01\x12\x15a\x04\x10W`\0\x80\xFD[P`@\x84\x01\x90P\x92P\x92P\x92V[`\0` \x82\x84\x03\x12\x15a\x040W`\0\x80\xFD[PQ\x91\x90PV[cNH{q`\xE0\x1B`\0R`\x11`\x04R`$`\0\xFD[\x80\x82\x01\x80\x82\x11\x15a\x04`Wa\x04`a\x047V[\x92\x91PPV[\x81\x81\x03\x81\x81\x11\x15a\x04`Wa\x04`a\x047V\xFE\xA2dipfsX\"\x12 3xBI\xC3L\x11\x1B\0\x9Aq\x9DP\xC7\xC9\xC2U\x12(\xF4T\xD0\x97\x8A\xEE*\xD4\xF1y\x1C\x9C\xF2dsolcC\0\x08\x14\x003";
-    /// The bytecode of the contract.
-    pub static EXAMPLEROLLUP_BYTECODE: ::ethers::core::types::Bytes =
-        ::ethers::core::types::Bytes::from_static(__BYTECODE);
-    #[rustfmt::skip]
-    const __DEPLOYED_BYTECODE: &[u8] = b"`\x80`@R4\x80\x15a\0\x10W`\0\x80\xFD[P`\x046\x10a\0LW`\x005`\xE0\x1C\x80c\x03%q\xA9\x14a\0QW\x80c*\xDC\x8Bv\x14a\0fW\x80cA,\xC8\xFE\x14a\0\x96W\x80c\xD8\0t\x1E\x14a\0\xADW[`\0\x80\xFD[a\0da\0_6`\x04a\x03\xC7V[a\0\xB6V[\0[`\0Ta\0y\x90`\x01`\x01`\xA0\x1B\x03\x16\x81V[`@Q`\x01`\x01`\xA0\x1B\x03\x90\x91\x16\x81R` \x01[`@Q\x80\x91\x03\x90\xF3[a\0\x9F`\x02T\x81V[`@Q\x90\x81R` \x01a\0\x8DV[a\0\x9F`\x01T\x81V[\x82g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\0\x03a\0\xE1W`@Qc\x0F\xD4\xB67`\xE3\x1B\x81R`\x04\x01`@Q\x80\x91\x03\x90\xFD[`\0\x80`\0\x90T\x90a\x01\0\n\x90\x04`\x01`\x01`\xA0\x1B\x03\x16`\x01`\x01`\xA0\x1B\x03\x16c\xF4O\xF7\x12`@Q\x81c\xFF\xFF\xFF\xFF\x16`\xE0\x1B\x81R`\x04\x01` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x015W=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x01Y\x91\x90a\x04\x1EV[\x90P\x80\x84g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\x02Ta\x01t\x91\x90a\x04MV[\x11\x15a\x01\xB6W`\x02T`@Qc\xF08Hg`\xE0\x1B\x81R`\x04\x81\x01\x91\x90\x91Rg\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x85\x16`$\x82\x01R`D\x81\x01\x82\x90R`d\x01[`@Q\x80\x91\x03\x90\xFD[`\0\x80T`\x02T`@QcI\xCE\x89\x97`\xE0\x1B\x81R`\x01`\x01`\xA0\x1B\x03\x90\x92\x16\x91cI\xCE\x89\x97\x91a\x01\xEC\x91`\x04\x01\x90\x81R` \x01\x90V[` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x02\tW=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x02-\x91\x90a\x04\x1EV[`\0\x80T`\x02T\x92\x93P\x90\x91`\x01`\x01`\xA0\x1B\x03\x90\x91\x16\x90cI\xCE\x89\x97\x90`\x01\x90a\x02c\x90g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x8B\x16\x90a\x04MV[a\x02m\x91\x90a\x04fV[`@Q\x82c\xFF\xFF\xFF\xFF\x16`\xE0\x1B\x81R`\x04\x01a\x02\x8B\x91\x81R` \x01\x90V[` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x02\xA8W=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x02\xCC\x91\x90a\x04\x1EV[\x90Pa\x02\xDD\x82\x82`\x01T\x88\x88a\x03\xA5V[a\x03;W`\x01T`@\x80QcD\xCC@\r`\xE1\x1B\x81R`\x04\x81\x01\x85\x90R`$\x81\x01\x84\x90R`D\x81\x01\x92\x90\x92R`d\x82\x01\x87\x90R\x855`\x84\x83\x01R` \x86\x015`\xA4\x83\x01R\x85\x015`\xC4\x82\x01R``\x85\x015`\xE4\x82\x01Ra\x01\x04\x01a\x01\xADV[\x85g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\x02`\0\x82\x82Ta\x03W\x91\x90a\x04MV[\x90\x91UPP`\x01\x85\x90U`\x02T`@\x80Q\x91\x82R` \x82\x01\x87\x90R\x7F\x9C:SN\xC4A\xC7c:pv_T\xC0\x02L\xA8\xF9\xA7nz,\xDA\xAC*j\x8C5\x19\xC0\xCA\xF3\x91\x01`@Q\x80\x91\x03\x90\xA1PPPPPPV[`\0\x81`@\x015\x84\x14\x80\x15a\x03\xBDWP\x81``\x015\x83\x14[\x96\x95PPPPPPV[`\0\x80`\0\x83\x85\x03`\xC0\x81\x12\x15a\x03\xDDW`\0\x80\xFD[\x845g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x81\x16\x81\x14a\x03\xF5W`\0\x80\xFD[\x93P` \x85\x015\x92P`\x80`?\x19\x82\x01\x12\x15a\x04\x10W`\0\x80\xFD[P`@\x84\x01\x90P\x92P\x92P\x92V[`\0` \x82\x84\x03\x12\x15a\x040W`\0\x80\xFD[PQ\x91\x90PV[cNH{q`\xE0\x1B`\0R`\x11`\x04R`$`\0\xFD[\x80\x82\x01\x80\x82\x11\x15a\x04`Wa\x04`a\x047V[\x92\x91PPV[\x81\x81\x03\x81\x81\x11\x15a\x04`Wa\x04`a\x047V\xFE\xA2dipfsX\"\x12 3xBI\xC3L\x11\x1B\0\x9Aq\x9DP\xC7\xC9\xC2U\x12(\xF4T\xD0\x97\x8A\xEE*\xD4\xF1y\x1C\x9C\xF2dsolcC\0\x08\x14\x003";
-    /// The deployed bytecode of the contract.
-    pub static EXAMPLEROLLUP_DEPLOYED_BYTECODE: ::ethers::core::types::Bytes =
-        ::ethers::core::types::Bytes::from_static(__DEPLOYED_BYTECODE);
-    pub struct ExampleRollup<M>(::ethers::contract::Contract<M>);
-    impl<M> ::core::clone::Clone for ExampleRollup<M> {
-        fn clone(&self) -> Self {
-            Self(::core::clone::Clone::clone(&self.0))
-        }
-    }
-    impl<M> ::core::ops::Deref for ExampleRollup<M> {
-        type Target = ::ethers::contract::Contract<M>;
+                (
+                    ::std::borrow::ToOwned::to_owned("leaseStart"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("leaseStart"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("numFinalizedBlocks"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("numFinalizedBlocks"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("numVerifiedBlocks"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("numVerifiedBlocks"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("owner"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("owner"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("setOwner"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("setOwner"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("newOwner"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                        outputs: ::std::vec![],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::NonPayable,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("setSubmitter"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("setSubmitter"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("newSubmitter"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                        outputs: ::std::vec![],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::NonPayable,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("stateCommitment"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("stateCommitment"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("submittedAt"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("submittedAt"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("verifyBlocks"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("verifyBlocks"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("height"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("count"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(64usize),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint64"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("nextStateCommitment",),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("proof"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned(
+                                        "struct ExampleRollup.BatchProof",
+                                    ),
+                                ),
+                            },
+                        ],
+                        outputs: ::std::vec![],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::NonPayable,
+                    },],
+                ),
+            ]),
+            events: ::core::convert::From::from([
+                (
+                    ::std::borrow::ToOwned::to_owned("BlocksFinalized"),
+                    ::std::vec![::ethers::core::abi::ethabi::Event {
+                        name: ::std::borrow::ToOwned::to_owned("BlocksFinalized"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::EventParam {
+                            name: ::std::borrow::ToOwned::to_owned("blockHeight"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            indexed: false,
+                        },],
+                        anonymous: false,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("Deposit"),
+                    ::std::vec![::ethers::core::abi::ethabi::Event {
+                        name: ::std::borrow::ToOwned::to_owned("Deposit"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("token"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                                indexed: false,
+                            },
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("sender"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                                indexed: false,
+                            },
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("recipient"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                                indexed: false,
+                            },
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("amount"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                indexed: false,
+                            },
+                        ],
+                        anonymous: false,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("OwnerChanged"),
+                    ::std::vec![::ethers::core::abi::ethabi::Event {
+                        name: ::std::borrow::ToOwned::to_owned("OwnerChanged"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::EventParam {
+                            name: ::std::borrow::ToOwned::to_owned("owner"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            indexed: false,
+                        },],
+                        anonymous: false,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("StateUpdate"),
+                    ::std::vec![::ethers::core::abi::ethabi::Event {
+                        name: ::std::borrow::ToOwned::to_owned("StateUpdate"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("blockHeight"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                indexed: false,
+                            },
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("stateCommitment"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                indexed: false,
+                            },
+                        ],
+                        anonymous: false,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("SubmitterAuthorized"),
+                    ::std::vec![::ethers::core::abi::ethabi::Event {
+                        name: ::std::borrow::ToOwned::to_owned("SubmitterAuthorized"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::EventParam {
+                            name: ::std::borrow::ToOwned::to_owned("submitter"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            indexed: false,
+                        },],
+                        anonymous: false,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("SubmitterClaimed"),
+                    ::std::vec![::ethers::core::abi::ethabi::Event {
+                        name: ::std::borrow::ToOwned::to_owned("SubmitterClaimed"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("submitter"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                                indexed: false,
+                            },
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("leaseExpiry"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                indexed: false,
+                            },
+                        ],
+                        anonymous: false,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("TransactionEnqueued"),
+                    ::std::vec![::ethers::core::abi::ethabi::Event {
+                        name: ::std::borrow::ToOwned::to_owned("TransactionEnqueued"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("sender"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                                indexed: false,
+                            },
+                            ::ethers::core::abi::ethabi::EventParam {
+                                name: ::std::borrow::ToOwned::to_owned("transaction"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Bytes,
+                                indexed: false,
+                            },
+                        ],
+                        anonymous: false,
+                    },],
+                ),
+            ]),
+            errors: ::core::convert::From::from([
+                (
+                    ::std::borrow::ToOwned::to_owned("DepositFailed"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("DepositFailed"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("token"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("InvalidFraudProof"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("InvalidFraudProof"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("height"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("InvalidProof"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("InvalidProof"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("firstBlock"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("lastBlock"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("oldState"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("newState"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("proof"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned(
+                                        "struct ExampleRollup.BatchProof",
+                                    ),
+                                ),
+                            },
+                        ],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("LeaseHeld"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("LeaseHeld"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("submitter"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("address"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("leaseExpiry"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                        ],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("LeaseTooLong"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("LeaseTooLong"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("leaseBlocks"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("maxLeaseBlocks"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                        ],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("NoBlocks"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("NoBlocks"),
+                        inputs: ::std::vec![],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("NotAuthorized"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("NotAuthorized"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("authorizedSubmitter"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("NotFinalizable"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("NotFinalizable"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("height"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("finalizableAt"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                        ],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("NotOwner"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("NotOwner"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("owner"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("NotPending"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("NotPending"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("height"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("NotSubmitter"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("NotSubmitter"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("submitter"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Address,
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("address"),
+                            ),
+                        },],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("NotYetSequenced"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("NotYetSequenced"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("numVerifiedBlocks"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("count"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(64usize),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint64"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("blockHeight"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                        ],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("TenureOver"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("TenureOver"),
+                        inputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::borrow::ToOwned::to_owned("claimableAt"),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned("uint256"),
+                            ),
+                        },],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("TransactionTooLarge"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("TransactionTooLarge"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("size"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("maxSize"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                        ],
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("WrongHeight"),
+                    ::std::vec![::ethers::core::abi::ethabi::AbiError {
+                        name: ::std::borrow::ToOwned::to_owned("WrongHeight"),
+                        inputs: ::std::vec![
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("numVerifiedBlocks"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                            ::ethers::core::abi::ethabi::Param {
+                                name: ::std::borrow::ToOwned::to_owned("height"),
+                                kind: ::ethers::core::abi::ethabi::ParamType::Uint(256usize,),
+                                internal_type: ::core::option::Option::Some(
+                                    ::std::borrow::ToOwned::to_owned("uint256"),
+                                ),
+                            },
+                        ],
+                    },],
+                ),
+            ]),
+            receive: false,
+            fallback: false,
+        }
+    }
+    ///The parsed JSON ABI of the contract.
+    pub static EXAMPLEROLLUP_ABI: ::ethers::contract::Lazy<::ethers::core::abi::Abi> =
+        ::ethers::contract::Lazy::new(__abi);
+    #[rustfmt::skip]
+    const __BYTECODE: &[u8] = b"`\x80`@R4\x80\x15a\0\x10W`\0\x80\xFD[P`@Qa\x05R8\x03\x80a\x05R\x839\x81\x01`@\x81\x90Ra\0/\x91a\0ZV[`\0\x80T`\x01`\x01`\xA0\x1B\x03\x19\x16`\x01`\x01`\xA0\x1B\x03\x93\x90\x93\x16\x92\x90\x92\x17\x82U`\x01U`\x02Ua\0\x94V[`\0\x80`@\x83\x85\x03\x12\x15a\0mW`\0\x80\xFD[\x82Q`\x01`\x01`\xA0\x1B\x03\x81\x16\x81\x14a\0\x84W`\0\x80\xFD[` \x93\x90\x93\x01Q\x92\x94\x92\x93PPPV[a\x04\xAF\x80a\0\xA3`\09`\0\xF3\xFE`\x80`@R4\x80\x15a\0\x10W`\0\x80\xFD[P`\x046\x10a\0LW`\x005`\xE0\x1C\x80c\x03%q\xA9\x14a\0QW\x80c*\xDC\x8Bv\x14a\0fW\x80cA,\xC8\xFE\x14a\0\x96W\x80c\xD8\0t\x1E\x14a\0\xADW[`\0\x80\xFD[a\0da\0_6`\x04a\x03\xC7V[a\0\xB6V[\0[`\0Ta\0y\x90`\x01`\x01`\xA0\x1B\x03\x16\x81V[`@Q`\x01`\x01`\xA0\x1B\x03\x90\x91\x16\x81R` \x01[`@Q\x80\x91\x03\x90\xF3[a\0\x9F`\x02T\x81V[`@Q\x90\x81R` \x01a\0\x8DV[a\0\x9F`\x01T\x81V[\x82g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\0\x03a\0\xE1W`@Qc\x0F\xD4\xB67`\xE3\x1B\x81R`\x04\x01`@Q\x80\x91\x03\x90\xFD[`\0\x80`\0\x90T\x90a\x01\0\n\x90\x04`\x01`\x01`\xA0\x1B\x03\x16`\x01`\x01`\xA0\x1B\x03\x16c\xF4O\xF7\x12`@Q\x81c\xFF\xFF\xFF\xFF\x16`\xE0\x1B\x81R`\x04\x01` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x015W=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x01Y\x91\x90a\x04\x1EV[\x90P\x80\x84g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\x02Ta\x01t\x91\x90a\x04MV[\x11\x15a\x01\xB6W`\x02T`@Qc\xF08Hg`\xE0\x1B\x81R`\x04\x81\x01\x91\x90\x91Rg\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x85\x16`$\x82\x01R`D\x81\x01\x82\x90R`d\x01[`@Q\x80\x91\x03\x90\xFD[`\0\x80T`\x02T`@QcI\xCE\x89\x97`\xE0\x1B\x81R`\x01`\x01`\xA0\x1B\x03\x90\x92\x16\x91cI\xCE\x89\x97\x91a\x01\xEC\x91`\x04\x01\x90\x81R` \x01\x90V[` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x02\tW=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x02-\x91\x90a\x04\x1EV[`\0\x80T`\x02T\x92\x93P\x90\x91`\x01`\x01`\xA0\x1B\x03\x90\x91\x16\x90cI\xCE\x89\x97\x90`\x01\x90a\x02c\x90g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x8B\x16\x90a\x04MV[a\x02m\x91\x90a\x04fV[`@Q\x82c\xFF\xFF\xFF\xFF\x16`\xE0\x1B\x81R`\x04\x01a\x02\x8B\x91\x81R` \x01\x90V[` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x02\xA8W=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x02\xCC\x91\x90a\x04\x1EV[\x90Pa\x02\xDD\x82\x82`\x01T\x88\x88a\x03\xA5V[a\x03;W`\x01T`@\x80QcD\xCC@\r`\xE1\x1B\x81R`\x04\x81\x01\x85\x90R`$\x81\x01\x84\x90R`D\x81\x01\x92\x90\x92R`d\x82\x01\x87\x90R\x855`\x84\x83\x01R` \x86\x015`\xA4\x83\x01R\x85\x015`\xC4\x82\x01R``\x85\x015`\xE4\x82\x01Ra\x01\x04\x01a\x01\xADV[\x85g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\x02`\0\x82\x82Ta\x03W\x91\x90a\x04MV[\x90\x91UPP`\x01\x85\x90U`\x02T`@\x80Q\x91\x82R` \x82\x01\x87\x90R\x7F\x9C:SN\xC4A\xC7c:pv_T\xC0\x02L\xA8\xF9\xA7nz,\xDA\xAC*j\x8C5\x19\xC0\xCA\xF3\x91\x01`@Q\x80\x91\x03\x90\xA1PPPPPPV[`\0\x81`@\x015\x84\x14\x80\x15a\x03\xBDWP\x81``\x015\x83\x14[\x96\x95PPPPPPV[`\0\x80`\0\x83\x85\x03`\xC0\x81\x12\x15a\x03\xDDW`\0\x80\xFD[\x845g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x81\x16\x81\x14a\x03\xF5W`\0\x80\xFD[\x93P` \x85\x015\x92P`\x80`?\x19\x82\x01\x12\x15a\x04\x10W`\0\x80\xFD[P`@\x84\x01\x90P\x92P\x92P\x92V[`\0` \x82\x84\x03\x12\x15a\x040W`\0\x80\xFD[PQ\x91\x90PV[cNH{q`\xE0\x1B`\0R`\x11`\x04R`$`\0\xFD[\x80\x82\x01\x80\x82\x11\x15a\x04`Wa\x04`a\x047V[\x92\x91PPV[\x81\x81\x03\x81\x81\x11\x15a\x04`Wa\x04`a\x047V\xFE\xA2dipfsX\"\x12 3xBI\xC3L\x11\x1B\0\x9Aq\x9DP\xC7\xC9\xC2U\x12(\xF4T\xD0\x97\x8A\xEE*\xD4\xF1y\x1C\x9C\xF2dsolcC\0\x08\x14\x003";
+    /// The bytecode of the contract.
+    pub static EXAMPLEROLLUP_BYTECODE: ::ethers::core::types::Bytes =
+        ::ethers::core::types::Bytes::from_static(__BYTECODE);
+    #[rustfmt::skip]
+    const __DEPLOYED_BYTECODE: &[u8] = b"`\x80`@R4\x80\x15a\0\x10W`\0\x80\xFD[P`\x046\x10a\0LW`\x005`\xE0\x1C\x80c\x03%q\xA9\x14a\0QW\x80c*\xDC\x8Bv\x14a\0fW\x80cA,\xC8\xFE\x14a\0\x96W\x80c\xD8\0t\x1E\x14a\0\xADW[`\0\x80\xFD[a\0da\0_6`\x04a\x03\xC7V[a\0\xB6V[\0[`\0Ta\0y\x90`\x01`\x01`\xA0\x1B\x03\x16\x81V[`@Q`\x01`\x01`\xA0\x1B\x03\x90\x91\x16\x81R` \x01[`@Q\x80\x91\x03\x90\xF3[a\0\x9F`\x02T\x81V[`@Q\x90\x81R` \x01a\0\x8DV[a\0\x9F`\x01T\x81V[\x82g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\0\x03a\0\xE1W`@Qc\x0F\xD4\xB67`\xE3\x1B\x81R`\x04\x01`@Q\x80\x91\x03\x90\xFD[`\0\x80`\0\x90T\x90a\x01\0\n\x90\x04`\x01`\x01`\xA0\x1B\x03\x16`\x01`\x01`\xA0\x1B\x03\x16c\xF4O\xF7\x12`@Q\x81c\xFF\xFF\xFF\xFF\x16`\xE0\x1B\x81R`\x04\x01` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x015W=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x01Y\x91\x90a\x04\x1EV[\x90P\x80\x84g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\x02Ta\x01t\x91\x90a\x04MV[\x11\x15a\x01\xB6W`\x02T`@Qc\xF08Hg`\xE0\x1B\x81R`\x04\x81\x01\x91\x90\x91Rg\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x85\x16`$\x82\x01R`D\x81\x01\x82\x90R`d\x01[`@Q\x80\x91\x03\x90\xFD[`\0\x80T`\x02T`@QcI\xCE\x89\x97`\xE0\x1B\x81R`\x01`\x01`\xA0\x1B\x03\x90\x92\x16\x91cI\xCE\x89\x97\x91a\x01\xEC\x91`\x04\x01\x90\x81R` \x01\x90V[` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x02\tW=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x02-\x91\x90a\x04\x1EV[`\0\x80T`\x02T\x92\x93P\x90\x91`\x01`\x01`\xA0\x1B\x03\x90\x91\x16\x90cI\xCE\x89\x97\x90`\x01\x90a\x02c\x90g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x8B\x16\x90a\x04MV[a\x02m\x91\x90a\x04fV[`@Q\x82c\xFF\xFF\xFF\xFF\x16`\xE0\x1B\x81R`\x04\x01a\x02\x8B\x91\x81R` \x01\x90V[` `@Q\x80\x83\x03\x81\x86Z\xFA\x15\x80\x15a\x02\xA8W=`\0\x80>=`\0\xFD[PPPP`@Q=`\x1F\x19`\x1F\x82\x01\x16\x82\x01\x80`@RP\x81\x01\x90a\x02\xCC\x91\x90a\x04\x1EV[\x90Pa\x02\xDD\x82\x82`\x01T\x88\x88a\x03\xA5V[a\x03;W`\x01T`@\x80QcD\xCC@\r`\xE1\x1B\x81R`\x04\x81\x01\x85\x90R`$\x81\x01\x84\x90R`D\x81\x01\x92\x90\x92R`d\x82\x01\x87\x90R\x855`\x84\x83\x01R` \x86\x015`\xA4\x83\x01R\x85\x015`\xC4\x82\x01R``\x85\x015`\xE4\x82\x01Ra\x01\x04\x01a\x01\xADV[\x85g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x16`\x02`\0\x82\x82Ta\x03W\x91\x90a\x04MV[\x90\x91UPP`\x01\x85\x90U`\x02T`@\x80Q\x91\x82R` \x82\x01\x87\x90R\x7F\x9C:SN\xC4A\xC7c:pv_T\xC0\x02L\xA8\xF9\xA7nz,\xDA\xAC*j\x8C5\x19\xC0\xCA\xF3\x91\x01`@Q\x80\x91\x03\x90\xA1PPPPPPV[`\0\x81`@\x015\x84\x14\x80\x15a\x03\xBDWP\x81``\x015\x83\x14[\x96\x95PPPPPPV[`\0\x80`\0\x83\x85\x03`\xC0\x81\x12\x15a\x03\xDDW`\0\x80\xFD[\x845g\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x81\x16\x81\x14a\x03\xF5W`\0\x80\xFD[\x93P` \x85\x015\x92P`\x80`?\x19\x82\x01\x12\x15a\x04\x10W`\0\x80\xFD[P`@\x84\x01\x90P\x92P\x92P\x92V[`\0` \x82\x84\x03\x12\x15a\x040W`\0\x80\xFD[PQ\x91\x90PV[cNH{q`\xE0\x1B`\0R`\x11`\x04R`$`\0\xFD[\x80\x82\x01\x80\x82\x11\x15a\x04`Wa\x04`a\x047V[\x92\x91PPV[\x81\x81\x03\x81\x81\x11\x15a\x04`Wa\x04`a\x047V\xFE\xA2dipfsX\"\x12 3xBI\xC3L\x11\x1B\0\x9Aq\x9DP\xC7\xC9\xC2U\x12(\xF4T\xD0\x97\x8A\xEE*\xD4\xF1y\x1C\x9C\xF2dsolcC\0\x08\x14\x003";
+    /// The deployed bytecode of the contract.
+    pub static EXAMPLEROLLUP_DEPLOYED_BYTECODE: ::ethers::core::types::Bytes =
+        ::ethers::core::types::Bytes::from_static(__DEPLOYED_BYTECODE);
+    pub struct ExampleRollup<M>(::ethers::contract::Contract<M>);
+    impl<M> ::core::clone::Clone for ExampleRollup<M> {
+        fn clone(&self) -> Self {
+            Self(::core::clone::Clone::clone(&self.0))
+        }
+    }
+    impl<M> ::core::ops::Deref for ExampleRollup<M> {
+        type Target = ::ethers::contract::Contract<M>;
         fn deref(&self) -> &Self::Target {
             &self.0
         }
     }
-    impl<M> ::core::ops::DerefMut for ExampleRollup<M> {
-        fn deref_mut(&mut self) -> &mut Self::Target {
-            &mut self.0
+    impl<M> ::core::ops::DerefMut for ExampleRollup<M> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+    impl<M> ::core::fmt::Debug for ExampleRollup<M> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+            f.debug_tuple(::core::stringify!(ExampleRollup))
+                .field(&self.address())
+                .finish()
+        }
+    }
+    impl<M: ::ethers::providers::Middleware> ExampleRollup<M> {
+        /// Creates a new contract instance with the specified `ethers` client at
+        /// `address`. The contract derefs to a `ethers::Contract` object.
+        pub fn new<T: Into<::ethers::core::types::Address>>(
+            address: T,
+            client: ::std::sync::Arc<M>,
+        ) -> Self {
+            Self(::ethers::contract::Contract::new(
+                address.into(),
+                EXAMPLEROLLUP_ABI.clone(),
+                client,
+            ))
+        }
+        /// Constructs the general purpose `Deployer` instance based on the provided constructor arguments and sends it.
+        /// Returns a new instance of a deployer that returns an instance of this contract after sending the transaction
+        ///
+        /// Notes:
+        /// - If there are no constructor arguments, you should pass `()` as the argument.
+        /// - The default poll duration is 7 seconds.
+        /// - The default number of confirmations is 1 block.
+        ///
+        ///
+        /// # Example
+        ///
+        /// Generate contract bindings with `abigen!` and deploy a new contract instance.
+        ///
+        /// *Note*: this requires a `bytecode` and `abi` object in the `greeter.json` artifact.
+        ///
+        /// ```ignore
+        /// # async fn deploy<M: ethers::providers::Middleware>(client: ::std::sync::Arc<M>) {
+        ///     abigen!(Greeter, "../greeter.json");
+        ///
+        ///    let greeter_contract = Greeter::deploy(client, "Hello world!".to_string()).unwrap().send().await.unwrap();
+        ///    let msg = greeter_contract.greet().call().await.unwrap();
+        /// # }
+        /// ```
+        pub fn deploy<T: ::ethers::core::abi::Tokenize>(
+            client: ::std::sync::Arc<M>,
+            constructor_args: T,
+        ) -> ::core::result::Result<
+            ::ethers::contract::builders::ContractDeployer<M, Self>,
+            ::ethers::contract::ContractError<M>,
+        > {
+            let factory = ::ethers::contract::ContractFactory::new(
+                EXAMPLEROLLUP_ABI.clone(),
+                EXAMPLEROLLUP_BYTECODE.clone().into(),
+                client,
+            );
+            let deployer = factory.deploy(constructor_args)?;
+            let deployer = ::ethers::contract::ContractDeployer::new(deployer);
+            Ok(deployer)
+        }
+        ///Calls the contract's `MAX_ENQUEUED_BYTES` (0xfd611528) function
+        pub fn max_enqueued_bytes(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([253, 97, 21, 40], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `MAX_LEASE_BLOCKS` (0x06044c46) function
+        pub fn max_lease_blocks(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([6, 4, 76, 70], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `MAX_TENURE_BLOCKS` (0xe901091c) function
+        pub fn max_tenure_blocks(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([233, 1, 9, 28], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `TENURE_COOLDOWN_BLOCKS` (0x1b2753bc) function
+        pub fn tenure_cooldown_blocks(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([27, 39, 83, 188], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `authorizedSubmitter` (0x0be75499) function
+        pub fn authorized_submitter(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::Address> {
+            self.0
+                .method_hash([11, 231, 84, 153], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `challenge` (0xa03aafbf) function
+        pub fn challenge(
+            &self,
+            height: ::ethers::core::types::U256,
+            fraud_proof: ::ethers::core::types::Bytes,
+        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
+            self.0
+                .method_hash([160, 58, 175, 191], (height, fraud_proof))
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `challengeWindow` (0x861a1412) function
+        pub fn challenge_window(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([134, 26, 20, 18], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `claimSubmitter` (0xcf337afe) function
+        pub fn claim_submitter(
+            &self,
+            lease_blocks: ::ethers::core::types::U256,
+        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
+            self.0
+                .method_hash([207, 51, 122, 254], lease_blocks)
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `currentSubmitter` (0xe1bdcfb3) function
+        pub fn current_submitter(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::Address> {
+            self.0
+                .method_hash([225, 189, 207, 179], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `depositERC20` (0x1828686a) function
+        pub fn deposit_erc20(
+            &self,
+            token: ::ethers::core::types::Address,
+            amount: ::ethers::core::types::U256,
+            recipient: ::ethers::core::types::Address,
+        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
+            self.0
+                .method_hash([24, 40, 104, 106], (token, amount, recipient))
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `depositETH` (0x2d2da806) function
+        pub fn deposit_eth(
+            &self,
+            recipient: ::ethers::core::types::Address,
+        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
+            self.0
+                .method_hash([45, 45, 168, 6], recipient)
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `enqueueTransaction` (0xaec185fc) function
+        pub fn enqueue_transaction(
+            &self,
+            transaction: ::ethers::core::types::Bytes,
+        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
+            self.0
+                .method_hash([174, 193, 133, 252], transaction)
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `finalizeBlock` (0x114c1137) function
+        pub fn finalize_block(
+            &self,
+            height: ::ethers::core::types::U256,
+        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
+            self.0
+                .method_hash([17, 76, 17, 55], height)
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `hotshot` (0x2adc8b76) function
+        pub fn hotshot(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::Address> {
+            self.0
+                .method_hash([42, 220, 139, 118], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `leaseExpiry` (0x424a5f2d) function
+        pub fn lease_expiry(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([66, 74, 95, 45], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `leaseStart` (0x87822b62) function
+        pub fn lease_start(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([135, 130, 43, 98], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `numFinalizedBlocks` (0xb66cc301) function
+        pub fn num_finalized_blocks(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([182, 108, 195, 1], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `numVerifiedBlocks` (0x412cc8fe) function
+        pub fn num_verified_blocks(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([65, 44, 200, 254], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `owner` (0x8da5cb5b) function
+        pub fn owner(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::Address> {
+            self.0
+                .method_hash([141, 165, 203, 91], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `setOwner` (0x13af4035) function
+        pub fn set_owner(
+            &self,
+            new_owner: ::ethers::core::types::Address,
+        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
+            self.0
+                .method_hash([19, 175, 64, 53], new_owner)
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `setSubmitter` (0x5a91f743) function
+        pub fn set_submitter(
+            &self,
+            new_submitter: ::ethers::core::types::Address,
+        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
+            self.0
+                .method_hash([90, 145, 247, 67], new_submitter)
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `stateCommitment` (0xd800741e) function
+        pub fn state_commitment(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([216, 0, 116, 30], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `submittedAt` (0x5adcd749) function
+        pub fn submitted_at(
+            &self,
+            p0: ::ethers::core::types::U256,
+        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
+            self.0
+                .method_hash([90, 220, 215, 73], p0)
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `verifyBlocks` (0x861e441e) function
+        pub fn verify_blocks(
+            &self,
+            height: ::ethers::core::types::U256,
+            count: u64,
+            next_state_commitment: ::ethers::core::types::U256,
+            proof: BatchProof,
+        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
+            self.0
+                .method_hash(
+                    [134, 30, 68, 30],
+                    (height, count, next_state_commitment, proof),
+                )
+                .expect("method not found (this should never happen)")
+        }
+        ///Gets the contract's `BlocksFinalized` event
+        pub fn blocks_finalized_filter(
+            &self,
+        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, BlocksFinalizedFilter>
+        {
+            self.0.event()
+        }
+        ///Gets the contract's `Deposit` event
+        pub fn deposit_filter(
+            &self,
+        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, DepositFilter> {
+            self.0.event()
+        }
+        ///Gets the contract's `OwnerChanged` event
+        pub fn owner_changed_filter(
+            &self,
+        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, OwnerChangedFilter>
+        {
+            self.0.event()
+        }
+        ///Gets the contract's `StateUpdate` event
+        pub fn state_update_filter(
+            &self,
+        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, StateUpdateFilter>
+        {
+            self.0.event()
+        }
+        ///Gets the contract's `SubmitterAuthorized` event
+        pub fn submitter_authorized_filter(
+            &self,
+        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, SubmitterAuthorizedFilter>
+        {
+            self.0.event()
+        }
+        ///Gets the contract's `SubmitterClaimed` event
+        pub fn submitter_claimed_filter(
+            &self,
+        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, SubmitterClaimedFilter>
+        {
+            self.0.event()
+        }
+        ///Gets the contract's `TransactionEnqueued` event
+        pub fn transaction_enqueued_filter(
+            &self,
+        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, TransactionEnqueuedFilter>
+        {
+            self.0.event()
+        }
+        /// Returns an `Event` builder for all the events of this contract.
+        pub fn events(
+            &self,
+        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, ExampleRollupEvents>
+        {
+            self.0
+                .event_with_filter(::core::default::Default::default())
+        }
+    }
+    impl<M: ::ethers::providers::Middleware> From<::ethers::contract::Contract<M>>
+        for ExampleRollup<M>
+    {
+        fn from(contract: ::ethers::contract::Contract<M>) -> Self {
+            Self::new(contract.address(), contract.client())
+        }
+    }
+    ///Custom Error type `DepositFailed` with signature `DepositFailed(address)` and selector `0xbbf6e988`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "DepositFailed", abi = "DepositFailed(address)")]
+    pub struct DepositFailed {
+        pub token: ::ethers::core::types::Address,
+    }
+    ///Custom Error type `InvalidFraudProof` with signature `InvalidFraudProof(uint256)` and selector `0xe2545af4`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "InvalidFraudProof", abi = "InvalidFraudProof(uint256)")]
+    pub struct InvalidFraudProof {
+        pub height: ::ethers::core::types::U256,
+    }
+    ///Custom Error type `InvalidProof` with signature `InvalidProof(uint256,uint256,uint256,uint256,(uint256,uint256,uint256,uint256))` and selector `0x8998801a`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(
+        name = "InvalidProof",
+        abi = "InvalidProof(uint256,uint256,uint256,uint256,(uint256,uint256,uint256,uint256))"
+    )]
+    pub struct InvalidProof {
+        pub first_block: ::ethers::core::types::U256,
+        pub last_block: ::ethers::core::types::U256,
+        pub old_state: ::ethers::core::types::U256,
+        pub new_state: ::ethers::core::types::U256,
+        pub proof: BatchProof,
+    }
+    ///Custom Error type `LeaseHeld` with signature `LeaseHeld(address,uint256)` and selector `0x32a4ac9f`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "LeaseHeld", abi = "LeaseHeld(address,uint256)")]
+    pub struct LeaseHeld {
+        pub submitter: ::ethers::core::types::Address,
+        pub lease_expiry: ::ethers::core::types::U256,
+    }
+    ///Custom Error type `LeaseTooLong` with signature `LeaseTooLong(uint256,uint256)` and selector `0x85e0d218`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "LeaseTooLong", abi = "LeaseTooLong(uint256,uint256)")]
+    pub struct LeaseTooLong {
+        pub lease_blocks: ::ethers::core::types::U256,
+        pub max_lease_blocks: ::ethers::core::types::U256,
+    }
+    ///Custom Error type `NoBlocks` with signature `NoBlocks()` and selector `0x7ea5b1b8`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "NoBlocks", abi = "NoBlocks()")]
+    pub struct NoBlocks;
+    ///Custom Error type `NotAuthorized` with signature `NotAuthorized(address)` and selector `0x4a0bfec1`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "NotAuthorized", abi = "NotAuthorized(address)")]
+    pub struct NotAuthorized {
+        pub authorized_submitter: ::ethers::core::types::Address,
+    }
+    ///Custom Error type `NotFinalizable` with signature `NotFinalizable(uint256,uint256)` and selector `0x99c6e311`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "NotFinalizable", abi = "NotFinalizable(uint256,uint256)")]
+    pub struct NotFinalizable {
+        pub height: ::ethers::core::types::U256,
+        pub finalizable_at: ::ethers::core::types::U256,
+    }
+    ///Custom Error type `NotOwner` with signature `NotOwner(address)` and selector `0x245aecd3`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "NotOwner", abi = "NotOwner(address)")]
+    pub struct NotOwner {
+        pub owner: ::ethers::core::types::Address,
+    }
+    ///Custom Error type `NotPending` with signature `NotPending(uint256)` and selector `0x4af96ba4`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "NotPending", abi = "NotPending(uint256)")]
+    pub struct NotPending {
+        pub height: ::ethers::core::types::U256,
+    }
+    ///Custom Error type `NotSubmitter` with signature `NotSubmitter(address)` and selector `0x16a049ca`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "NotSubmitter", abi = "NotSubmitter(address)")]
+    pub struct NotSubmitter {
+        pub submitter: ::ethers::core::types::Address,
+    }
+    ///Custom Error type `NotYetSequenced` with signature `NotYetSequenced(uint256,uint64,uint256)` and selector `0xf0384867`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(
+        name = "NotYetSequenced",
+        abi = "NotYetSequenced(uint256,uint64,uint256)"
+    )]
+    pub struct NotYetSequenced {
+        pub num_verified_blocks: ::ethers::core::types::U256,
+        pub count: u64,
+        pub block_height: ::ethers::core::types::U256,
+    }
+    ///Custom Error type `TenureOver` with signature `TenureOver(uint256)` and selector `0x5b2603ef`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "TenureOver", abi = "TenureOver(uint256)")]
+    pub struct TenureOver {
+        pub claimable_at: ::ethers::core::types::U256,
+    }
+    ///Custom Error type `TransactionTooLarge` with signature `TransactionTooLarge(uint256,uint256)` and selector `0x8c02824c`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(
+        name = "TransactionTooLarge",
+        abi = "TransactionTooLarge(uint256,uint256)"
+    )]
+    pub struct TransactionTooLarge {
+        pub size: ::ethers::core::types::U256,
+        pub max_size: ::ethers::core::types::U256,
+    }
+    ///Custom Error type `WrongHeight` with signature `WrongHeight(uint256,uint256)` and selector `0x7efabb1a`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthError,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[etherror(name = "WrongHeight", abi = "WrongHeight(uint256,uint256)")]
+    pub struct WrongHeight {
+        pub num_verified_blocks: ::ethers::core::types::U256,
+        pub height: ::ethers::core::types::U256,
+    }
+    ///Container type for all of the contract's custom errors
+    #[derive(Clone, ::ethers::contract::EthAbiType, Debug, PartialEq, Eq, Hash)]
+    pub enum ExampleRollupErrors {
+        DepositFailed(DepositFailed),
+        InvalidFraudProof(InvalidFraudProof),
+        InvalidProof(InvalidProof),
+        LeaseHeld(LeaseHeld),
+        LeaseTooLong(LeaseTooLong),
+        NoBlocks(NoBlocks),
+        NotAuthorized(NotAuthorized),
+        NotFinalizable(NotFinalizable),
+        NotOwner(NotOwner),
+        NotPending(NotPending),
+        NotSubmitter(NotSubmitter),
+        NotYetSequenced(NotYetSequenced),
+        TenureOver(TenureOver),
+        TransactionTooLarge(TransactionTooLarge),
+        WrongHeight(WrongHeight),
+        /// The standard solidity revert string, with selector
+        /// Error(string) -- 0x08c379a0
+        RevertString(::std::string::String),
+    }
+    impl ::ethers::core::abi::AbiDecode for ExampleRollupErrors {
+        fn decode(
+            data: impl AsRef<[u8]>,
+        ) -> ::core::result::Result<Self, ::ethers::core::abi::AbiError> {
+            let data = data.as_ref();
+            if let Ok(decoded) =
+                <::std::string::String as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::RevertString(decoded));
+            }
+            if let Ok(decoded) = <DepositFailed as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::DepositFailed(decoded));
+            }
+            if let Ok(decoded) = <InvalidFraudProof as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::InvalidFraudProof(decoded));
+            }
+            if let Ok(decoded) = <InvalidProof as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::InvalidProof(decoded));
+            }
+            if let Ok(decoded) = <LeaseHeld as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::LeaseHeld(decoded));
+            }
+            if let Ok(decoded) = <LeaseTooLong as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::LeaseTooLong(decoded));
+            }
+            if let Ok(decoded) = <NoBlocks as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::NoBlocks(decoded));
+            }
+            if let Ok(decoded) = <NotAuthorized as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::NotAuthorized(decoded));
+            }
+            if let Ok(decoded) = <NotFinalizable as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::NotFinalizable(decoded));
+            }
+            if let Ok(decoded) = <NotOwner as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::NotOwner(decoded));
+            }
+            if let Ok(decoded) = <NotPending as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::NotPending(decoded));
+            }
+            if let Ok(decoded) = <NotSubmitter as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::NotSubmitter(decoded));
+            }
+            if let Ok(decoded) = <NotYetSequenced as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::NotYetSequenced(decoded));
+            }
+            if let Ok(decoded) = <TenureOver as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::TenureOver(decoded));
+            }
+            if let Ok(decoded) =
+                <TransactionTooLarge as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::TransactionTooLarge(decoded));
+            }
+            if let Ok(decoded) = <WrongHeight as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::WrongHeight(decoded));
+            }
+            Err(::ethers::core::abi::Error::InvalidData.into())
+        }
+    }
+    impl ::ethers::core::abi::AbiEncode for ExampleRollupErrors {
+        fn encode(self) -> ::std::vec::Vec<u8> {
+            match self {
+                Self::DepositFailed(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::InvalidFraudProof(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::InvalidProof(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::LeaseHeld(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::LeaseTooLong(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::NoBlocks(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::NotAuthorized(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::NotFinalizable(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::NotOwner(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::NotPending(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::NotSubmitter(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::NotYetSequenced(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::TenureOver(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::TransactionTooLarge(element) => {
+                    ::ethers::core::abi::AbiEncode::encode(element)
+                }
+                Self::WrongHeight(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::RevertString(s) => ::ethers::core::abi::AbiEncode::encode(s),
+            }
         }
     }
-    impl<M> ::core::fmt::Debug for ExampleRollup<M> {
+    impl ::ethers::contract::ContractRevert for ExampleRollupErrors {
+        fn valid_selector(selector: [u8; 4]) -> bool {
+            match selector {
+                [0x08, 0xc3, 0x79, 0xa0] => true,
+                _ if selector == <DepositFailed as ::ethers::contract::EthError>::selector() => {
+                    true
+                }
+                _ if selector
+                    == <InvalidFraudProof as ::ethers::contract::EthError>::selector() =>
+                {
+                    true
+                }
+                _ if selector == <InvalidProof as ::ethers::contract::EthError>::selector() => true,
+                _ if selector == <LeaseHeld as ::ethers::contract::EthError>::selector() => true,
+                _ if selector == <LeaseTooLong as ::ethers::contract::EthError>::selector() => true,
+                _ if selector == <NoBlocks as ::ethers::contract::EthError>::selector() => true,
+                _ if selector == <NotAuthorized as ::ethers::contract::EthError>::selector() => {
+                    true
+                }
+                _ if selector == <NotFinalizable as ::ethers::contract::EthError>::selector() => {
+                    true
+                }
+                _ if selector == <NotOwner as ::ethers::contract::EthError>::selector() => true,
+                _ if selector == <NotPending as ::ethers::contract::EthError>::selector() => true,
+                _ if selector == <NotSubmitter as ::ethers::contract::EthError>::selector() => true,
+                _ if selector == <NotYetSequenced as ::ethers::contract::EthError>::selector() => {
+                    true
+                }
+                _ if selector == <TenureOver as ::ethers::contract::EthError>::selector() => true,
+                _ if selector
+                    == <TransactionTooLarge as ::ethers::contract::EthError>::selector() =>
+                {
+                    true
+                }
+                _ if selector == <WrongHeight as ::ethers::contract::EthError>::selector() => true,
+                _ => false,
+            }
+        }
+    }
+    impl ::core::fmt::Display for ExampleRollupErrors {
         fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
-            f.debug_tuple(::core::stringify!(ExampleRollup))
-                .field(&self.address())
-                .finish()
+            match self {
+                Self::DepositFailed(element) => ::core::fmt::Display::fmt(element, f),
+                Self::InvalidFraudProof(element) => ::core::fmt::Display::fmt(element, f),
+                Self::InvalidProof(element) => ::core::fmt::Display::fmt(element, f),
+                Self::LeaseHeld(element) => ::core::fmt::Display::fmt(element, f),
+                Self::LeaseTooLong(element) => ::core::fmt::Display::fmt(element, f),
+                Self::NoBlocks(element) => ::core::fmt::Display::fmt(element, f),
+                Self::NotAuthorized(element) => ::core::fmt::Display::fmt(element, f),
+                Self::NotFinalizable(element) => ::core::fmt::Display::fmt(element, f),
+                Self::NotOwner(element) => ::core::fmt::Display::fmt(element, f),
+                Self::NotPending(element) => ::core::fmt::Display::fmt(element, f),
+                Self::NotSubmitter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::NotYetSequenced(element) => ::core::fmt::Display::fmt(element, f),
+                Self::TenureOver(element) => ::core::fmt::Display::fmt(element, f),
+                Self::TransactionTooLarge(element) => ::core::fmt::Display::fmt(element, f),
+                Self::WrongHeight(element) => ::core::fmt::Display::fmt(element, f),
+                Self::RevertString(s) => ::core::fmt::Display::fmt(s, f),
+            }
         }
     }
-    impl<M: ::ethers::providers::Middleware> ExampleRollup<M> {
-        /// Creates a new contract instance with the specified `ethers` client at
-        /// `address`. The contract derefs to a `ethers::Contract` object.
-        pub fn new<T: Into<::ethers::core::types::Address>>(
-            address: T,
-            client: ::std::sync::Arc<M>,
-        ) -> Self {
-            Self(::ethers::contract::Contract::new(
-                address.into(),
-                EXAMPLEROLLUP_ABI.clone(),
-                client,
-            ))
+    impl ::core::convert::From<::std::string::String> for ExampleRollupErrors {
+        fn from(value: String) -> Self {
+            Self::RevertString(value)
         }
-        /// Constructs the general purpose `Deployer` instance based on the provided constructor arguments and sends it.
-        /// Returns a new instance of a deployer that returns an instance of this contract after sending the transaction
-        ///
-        /// Notes:
-        /// - If there are no constructor arguments, you should pass `()` as the argument.
-        /// - The default poll duration is 7 seconds.
-        /// - The default number of confirmations is 1 block.
-        ///
-        ///
-        /// # Example
-        ///
-        /// Generate contract bindings with `abigen!` and deploy a new contract instance.
-        ///
-        /// *Note*: this requires a `bytecode` and `abi` object in the `greeter.json` artifact.
-        ///
-        /// ```ignore
-        /// # async fn deploy<M: ethers::providers::Middleware>(client: ::std::sync::Arc<M>) {
-        ///     abigen!(Greeter, "../greeter.json");
-        ///
-        ///    let greeter_contract = Greeter::deploy(client, "Hello world!".to_string()).unwrap().send().await.unwrap();
-        ///    let msg = greeter_contract.greet().call().await.unwrap();
-        /// # }
-        /// ```
-        pub fn deploy<T: ::ethers::core::abi::Tokenize>(
-            client: ::std::sync::Arc<M>,
-            constructor_args: T,
-        ) -> ::core::result::Result<
-            ::ethers::contract::builders::ContractDeployer<M, Self>,
-            ::ethers::contract::ContractError<M>,
-        > {
-            let factory = ::ethers::contract::ContractFactory::new(
-                EXAMPLEROLLUP_ABI.clone(),
-                EXAMPLEROLLUP_BYTECODE.clone().into(),
-                client,
-            );
-            let deployer = factory.deploy(constructor_args)?;
-            let deployer = ::ethers::contract::ContractDeployer::new(deployer);
-            Ok(deployer)
+    }
+    impl ::core::convert::From<DepositFailed> for ExampleRollupErrors {
+        fn from(value: DepositFailed) -> Self {
+            Self::DepositFailed(value)
         }
-        ///Calls the contract's `hotshot` (0x2adc8b76) function
-        pub fn hotshot(
-            &self,
-        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::Address> {
-            self.0
-                .method_hash([42, 220, 139, 118], ())
-                .expect("method not found (this should never happen)")
+    }
+    impl ::core::convert::From<InvalidFraudProof> for ExampleRollupErrors {
+        fn from(value: InvalidFraudProof) -> Self {
+            Self::InvalidFraudProof(value)
         }
-        ///Calls the contract's `numVerifiedBlocks` (0x412cc8fe) function
-        pub fn num_verified_blocks(
-            &self,
-        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
-            self.0
-                .method_hash([65, 44, 200, 254], ())
-                .expect("method not found (this should never happen)")
+    }
+    impl ::core::convert::From<InvalidProof> for ExampleRollupErrors {
+        fn from(value: InvalidProof) -> Self {
+            Self::InvalidProof(value)
         }
-        ///Calls the contract's `stateCommitment` (0xd800741e) function
-        pub fn state_commitment(
-            &self,
-        ) -> ::ethers::contract::builders::ContractCall<M, ::ethers::core::types::U256> {
-            self.0
-                .method_hash([216, 0, 116, 30], ())
-                .expect("method not found (this should never happen)")
+    }
+    impl ::core::convert::From<LeaseHeld> for ExampleRollupErrors {
+        fn from(value: LeaseHeld) -> Self {
+            Self::LeaseHeld(value)
         }
-        ///Calls the contract's `verifyBlocks` (0x032571a9) function
-        pub fn verify_blocks(
-            &self,
-            count: u64,
-            next_state_commitment: ::ethers::core::types::U256,
-            proof: BatchProof,
-        ) -> ::ethers::contract::builders::ContractCall<M, ()> {
-            self.0
-                .method_hash([3, 37, 113, 169], (count, next_state_commitment, proof))
-                .expect("method not found (this should never happen)")
+    }
+    impl ::core::convert::From<LeaseTooLong> for ExampleRollupErrors {
+        fn from(value: LeaseTooLong) -> Self {
+            Self::LeaseTooLong(value)
         }
-        ///Gets the contract's `StateUpdate` event
-        pub fn state_update_filter(
-            &self,
-        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, StateUpdateFilter>
-        {
-            self.0.event()
+    }
+    impl ::core::convert::From<NoBlocks> for ExampleRollupErrors {
+        fn from(value: NoBlocks) -> Self {
+            Self::NoBlocks(value)
         }
-        /// Returns an `Event` builder for all the events of this contract.
-        pub fn events(
-            &self,
-        ) -> ::ethers::contract::builders::Event<::std::sync::Arc<M>, M, StateUpdateFilter>
-        {
-            self.0
-                .event_with_filter(::core::default::Default::default())
+    }
+    impl ::core::convert::From<NotAuthorized> for ExampleRollupErrors {
+        fn from(value: NotAuthorized) -> Self {
+            Self::NotAuthorized(value)
         }
     }
-    impl<M: ::ethers::providers::Middleware> From<::ethers::contract::Contract<M>>
-        for ExampleRollup<M>
-    {
-        fn from(contract: ::ethers::contract::Contract<M>) -> Self {
-            Self::new(contract.address(), contract.client())
+    impl ::core::convert::From<NotFinalizable> for ExampleRollupErrors {
+        fn from(value: NotFinalizable) -> Self {
+            Self::NotFinalizable(value)
+        }
+    }
+    impl ::core::convert::From<NotOwner> for ExampleRollupErrors {
+        fn from(value: NotOwner) -> Self {
+            Self::NotOwner(value)
+        }
+    }
+    impl ::core::convert::From<NotPending> for ExampleRollupErrors {
+        fn from(value: NotPending) -> Self {
+            Self::NotPending(value)
+        }
+    }
+    impl ::core::convert::From<NotSubmitter> for ExampleRollupErrors {
+        fn from(value: NotSubmitter) -> Self {
+            Self::NotSubmitter(value)
+        }
+    }
+    impl ::core::convert::From<NotYetSequenced> for ExampleRollupErrors {
+        fn from(value: NotYetSequenced) -> Self {
+            Self::NotYetSequenced(value)
+        }
+    }
+    impl ::core::convert::From<TenureOver> for ExampleRollupErrors {
+        fn from(value: TenureOver) -> Self {
+            Self::TenureOver(value)
+        }
+    }
+    impl ::core::convert::From<TransactionTooLarge> for ExampleRollupErrors {
+        fn from(value: TransactionTooLarge) -> Self {
+            Self::TransactionTooLarge(value)
+        }
+    }
+    impl ::core::convert::From<WrongHeight> for ExampleRollupErrors {
+        fn from(value: WrongHeight) -> Self {
+            Self::WrongHeight(value)
         }
     }
-    ///Custom Error type `InvalidProof` with signature `InvalidProof(uint256,uint256,uint256,uint256,(uint256,uint256,uint256,uint256))` and selector `0x8998801a`
     #[derive(
         Clone,
-        ::ethers::contract::EthError,
+        ::ethers::contract::EthEvent,
         ::ethers::contract::EthDisplay,
         Default,
         Debug,
@@ -389,21 +1774,73 @@ pub mod example_rollup {
         Eq,
         Hash,
     )]
-    #[etherror(
-        name = "InvalidProof",
-        abi = "InvalidProof(uint256,uint256,uint256,uint256,(uint256,uint256,uint256,uint256))"
+    #[ethevent(name = "BlocksFinalized", abi = "BlocksFinalized(uint256)")]
+    pub struct BlocksFinalizedFilter {
+        pub block_height: ::ethers::core::types::U256,
+    }
+    #[derive(
+        Clone,
+        ::ethers::contract::EthEvent,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
     )]
-    pub struct InvalidProof {
-        pub first_block: ::ethers::core::types::U256,
-        pub last_block: ::ethers::core::types::U256,
-        pub old_state: ::ethers::core::types::U256,
-        pub new_state: ::ethers::core::types::U256,
-        pub proof: BatchProof,
+    #[ethevent(name = "Deposit", abi = "Deposit(address,address,address,uint256)")]
+    pub struct DepositFilter {
+        pub token: ::ethers::core::types::Address,
+        pub sender: ::ethers::core::types::Address,
+        pub recipient: ::ethers::core::types::Address,
+        pub amount: ::ethers::core::types::U256,
+    }
+    #[derive(
+        Clone,
+        ::ethers::contract::EthEvent,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethevent(name = "OwnerChanged", abi = "OwnerChanged(address)")]
+    pub struct OwnerChangedFilter {
+        pub owner: ::ethers::core::types::Address,
+    }
+    #[derive(
+        Clone,
+        ::ethers::contract::EthEvent,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethevent(name = "StateUpdate", abi = "StateUpdate(uint256,uint256)")]
+    pub struct StateUpdateFilter {
+        pub block_height: ::ethers::core::types::U256,
+        pub state_commitment: ::ethers::core::types::U256,
+    }
+    #[derive(
+        Clone,
+        ::ethers::contract::EthEvent,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethevent(name = "SubmitterAuthorized", abi = "SubmitterAuthorized(address)")]
+    pub struct SubmitterAuthorizedFilter {
+        pub submitter: ::ethers::core::types::Address,
     }
-    ///Custom Error type `NoBlocks` with signature `NoBlocks()` and selector `0x7ea5b1b8`
     #[derive(
         Clone,
-        ::ethers::contract::EthError,
+        ::ethers::contract::EthEvent,
         ::ethers::contract::EthDisplay,
         Default,
         Debug,
@@ -411,12 +1848,14 @@ pub mod example_rollup {
         Eq,
         Hash,
     )]
-    #[etherror(name = "NoBlocks", abi = "NoBlocks()")]
-    pub struct NoBlocks;
-    ///Custom Error type `NotYetSequenced` with signature `NotYetSequenced(uint256,uint64,uint256)` and selector `0xf0384867`
+    #[ethevent(name = "SubmitterClaimed", abi = "SubmitterClaimed(address,uint256)")]
+    pub struct SubmitterClaimedFilter {
+        pub submitter: ::ethers::core::types::Address,
+        pub lease_expiry: ::ethers::core::types::U256,
+    }
     #[derive(
         Clone,
-        ::ethers::contract::EthError,
+        ::ethers::contract::EthEvent,
         ::ethers::contract::EthDisplay,
         Default,
         Debug,
@@ -424,103 +1863,328 @@ pub mod example_rollup {
         Eq,
         Hash,
     )]
-    #[etherror(
-        name = "NotYetSequenced",
-        abi = "NotYetSequenced(uint256,uint64,uint256)"
+    #[ethevent(
+        name = "TransactionEnqueued",
+        abi = "TransactionEnqueued(address,bytes)"
     )]
-    pub struct NotYetSequenced {
-        pub num_verified_blocks: ::ethers::core::types::U256,
-        pub count: u64,
-        pub block_height: ::ethers::core::types::U256,
+    pub struct TransactionEnqueuedFilter {
+        pub sender: ::ethers::core::types::Address,
+        pub transaction: ::ethers::core::types::Bytes,
     }
-    ///Container type for all of the contract's custom errors
+    ///Container type for all of the contract's events
     #[derive(Clone, ::ethers::contract::EthAbiType, Debug, PartialEq, Eq, Hash)]
-    pub enum ExampleRollupErrors {
-        InvalidProof(InvalidProof),
-        NoBlocks(NoBlocks),
-        NotYetSequenced(NotYetSequenced),
-        /// The standard solidity revert string, with selector
-        /// Error(string) -- 0x08c379a0
-        RevertString(::std::string::String),
+    pub enum ExampleRollupEvents {
+        BlocksFinalizedFilter(BlocksFinalizedFilter),
+        DepositFilter(DepositFilter),
+        OwnerChangedFilter(OwnerChangedFilter),
+        StateUpdateFilter(StateUpdateFilter),
+        SubmitterAuthorizedFilter(SubmitterAuthorizedFilter),
+        SubmitterClaimedFilter(SubmitterClaimedFilter),
+        TransactionEnqueuedFilter(TransactionEnqueuedFilter),
     }
-    impl ::ethers::core::abi::AbiDecode for ExampleRollupErrors {
-        fn decode(
-            data: impl AsRef<[u8]>,
-        ) -> ::core::result::Result<Self, ::ethers::core::abi::AbiError> {
-            let data = data.as_ref();
-            if let Ok(decoded) =
-                <::std::string::String as ::ethers::core::abi::AbiDecode>::decode(data)
-            {
-                return Ok(Self::RevertString(decoded));
+    impl ::ethers::contract::EthLogDecode for ExampleRollupEvents {
+        fn decode_log(
+            log: &::ethers::core::abi::RawLog,
+        ) -> ::core::result::Result<Self, ::ethers::core::abi::Error> {
+            if let Ok(decoded) = BlocksFinalizedFilter::decode_log(log) {
+                return Ok(ExampleRollupEvents::BlocksFinalizedFilter(decoded));
             }
-            if let Ok(decoded) = <InvalidProof as ::ethers::core::abi::AbiDecode>::decode(data) {
-                return Ok(Self::InvalidProof(decoded));
+            if let Ok(decoded) = DepositFilter::decode_log(log) {
+                return Ok(ExampleRollupEvents::DepositFilter(decoded));
             }
-            if let Ok(decoded) = <NoBlocks as ::ethers::core::abi::AbiDecode>::decode(data) {
-                return Ok(Self::NoBlocks(decoded));
+            if let Ok(decoded) = OwnerChangedFilter::decode_log(log) {
+                return Ok(ExampleRollupEvents::OwnerChangedFilter(decoded));
             }
-            if let Ok(decoded) = <NotYetSequenced as ::ethers::core::abi::AbiDecode>::decode(data) {
-                return Ok(Self::NotYetSequenced(decoded));
+            if let Ok(decoded) = StateUpdateFilter::decode_log(log) {
+                return Ok(ExampleRollupEvents::StateUpdateFilter(decoded));
             }
-            Err(::ethers::core::abi::Error::InvalidData.into())
+            if let Ok(decoded) = SubmitterAuthorizedFilter::decode_log(log) {
+                return Ok(ExampleRollupEvents::SubmitterAuthorizedFilter(decoded));
+            }
+            if let Ok(decoded) = SubmitterClaimedFilter::decode_log(log) {
+                return Ok(ExampleRollupEvents::SubmitterClaimedFilter(decoded));
+            }
+            if let Ok(decoded) = TransactionEnqueuedFilter::decode_log(log) {
+                return Ok(ExampleRollupEvents::TransactionEnqueuedFilter(decoded));
+            }
+            Err(::ethers::core::abi::Error::InvalidData)
         }
     }
-    impl ::ethers::core::abi::AbiEncode for ExampleRollupErrors {
-        fn encode(self) -> ::std::vec::Vec<u8> {
+    impl ::core::fmt::Display for ExampleRollupEvents {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
             match self {
-                Self::InvalidProof(element) => ::ethers::core::abi::AbiEncode::encode(element),
-                Self::NoBlocks(element) => ::ethers::core::abi::AbiEncode::encode(element),
-                Self::NotYetSequenced(element) => ::ethers::core::abi::AbiEncode::encode(element),
-                Self::RevertString(s) => ::ethers::core::abi::AbiEncode::encode(s),
+                Self::BlocksFinalizedFilter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::DepositFilter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::OwnerChangedFilter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::StateUpdateFilter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::SubmitterAuthorizedFilter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::SubmitterClaimedFilter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::TransactionEnqueuedFilter(element) => ::core::fmt::Display::fmt(element, f),
             }
         }
     }
-    impl ::ethers::contract::ContractRevert for ExampleRollupErrors {
-        fn valid_selector(selector: [u8; 4]) -> bool {
-            match selector {
-                [0x08, 0xc3, 0x79, 0xa0] => true,
-                _ if selector == <InvalidProof as ::ethers::contract::EthError>::selector() => true,
-                _ if selector == <NoBlocks as ::ethers::contract::EthError>::selector() => true,
-                _ if selector == <NotYetSequenced as ::ethers::contract::EthError>::selector() => {
-                    true
-                }
-                _ => false,
-            }
+    impl ::core::convert::From<BlocksFinalizedFilter> for ExampleRollupEvents {
+        fn from(value: BlocksFinalizedFilter) -> Self {
+            Self::BlocksFinalizedFilter(value)
         }
     }
-    impl ::core::fmt::Display for ExampleRollupErrors {
-        fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
-            match self {
-                Self::InvalidProof(element) => ::core::fmt::Display::fmt(element, f),
-                Self::NoBlocks(element) => ::core::fmt::Display::fmt(element, f),
-                Self::NotYetSequenced(element) => ::core::fmt::Display::fmt(element, f),
-                Self::RevertString(s) => ::core::fmt::Display::fmt(s, f),
-            }
+    impl ::core::convert::From<DepositFilter> for ExampleRollupEvents {
+        fn from(value: DepositFilter) -> Self {
+            Self::DepositFilter(value)
         }
     }
-    impl ::core::convert::From<::std::string::String> for ExampleRollupErrors {
-        fn from(value: String) -> Self {
-            Self::RevertString(value)
+    impl ::core::convert::From<OwnerChangedFilter> for ExampleRollupEvents {
+        fn from(value: OwnerChangedFilter) -> Self {
+            Self::OwnerChangedFilter(value)
         }
     }
-    impl ::core::convert::From<InvalidProof> for ExampleRollupErrors {
-        fn from(value: InvalidProof) -> Self {
-            Self::InvalidProof(value)
+    impl ::core::convert::From<StateUpdateFilter> for ExampleRollupEvents {
+        fn from(value: StateUpdateFilter) -> Self {
+            Self::StateUpdateFilter(value)
         }
     }
-    impl ::core::convert::From<NoBlocks> for ExampleRollupErrors {
-        fn from(value: NoBlocks) -> Self {
-            Self::NoBlocks(value)
+    impl ::core::convert::From<SubmitterAuthorizedFilter> for ExampleRollupEvents {
+        fn from(value: SubmitterAuthorizedFilter) -> Self {
+            Self::SubmitterAuthorizedFilter(value)
         }
     }
-    impl ::core::convert::From<NotYetSequenced> for ExampleRollupErrors {
-        fn from(value: NotYetSequenced) -> Self {
-            Self::NotYetSequenced(value)
+    impl ::core::convert::From<SubmitterClaimedFilter> for ExampleRollupEvents {
+        fn from(value: SubmitterClaimedFilter) -> Self {
+            Self::SubmitterClaimedFilter(value)
+        }
+    }
+    impl ::core::convert::From<TransactionEnqueuedFilter> for ExampleRollupEvents {
+        fn from(value: TransactionEnqueuedFilter) -> Self {
+            Self::TransactionEnqueuedFilter(value)
         }
     }
+    ///Container type for all input parameters for the `MAX_ENQUEUED_BYTES` function with signature `MAX_ENQUEUED_BYTES()` and selector `0xfd611528`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "MAX_ENQUEUED_BYTES", abi = "MAX_ENQUEUED_BYTES()")]
+    pub struct MaxEnqueuedBytesCall;
+    ///Container type for all input parameters for the `MAX_LEASE_BLOCKS` function with signature `MAX_LEASE_BLOCKS()` and selector `0x06044c46`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "MAX_LEASE_BLOCKS", abi = "MAX_LEASE_BLOCKS()")]
+    pub struct MaxLeaseBlocksCall;
+    ///Container type for all input parameters for the `MAX_TENURE_BLOCKS` function with signature `MAX_TENURE_BLOCKS()` and selector `0xe901091c`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "MAX_TENURE_BLOCKS", abi = "MAX_TENURE_BLOCKS()")]
+    pub struct MaxTenureBlocksCall;
+    ///Container type for all input parameters for the `TENURE_COOLDOWN_BLOCKS` function with signature `TENURE_COOLDOWN_BLOCKS()` and selector `0x1b2753bc`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "TENURE_COOLDOWN_BLOCKS", abi = "TENURE_COOLDOWN_BLOCKS()")]
+    pub struct TenureCooldownBlocksCall;
+    ///Container type for all input parameters for the `authorizedSubmitter` function with signature `authorizedSubmitter()` and selector `0x0be75499`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "authorizedSubmitter", abi = "authorizedSubmitter()")]
+    pub struct AuthorizedSubmitterCall;
+    ///Container type for all input parameters for the `challenge` function with signature `challenge(uint256,bytes)` and selector `0xa03aafbf`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "challenge", abi = "challenge(uint256,bytes)")]
+    pub struct ChallengeCall {
+        pub height: ::ethers::core::types::U256,
+        pub fraud_proof: ::ethers::core::types::Bytes,
+    }
+    ///Container type for all input parameters for the `challengeWindow` function with signature `challengeWindow()` and selector `0x861a1412`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "challengeWindow", abi = "challengeWindow()")]
+    pub struct ChallengeWindowCall;
+    ///Container type for all input parameters for the `claimSubmitter` function with signature `claimSubmitter(uint256)` and selector `0xcf337afe`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "claimSubmitter", abi = "claimSubmitter(uint256)")]
+    pub struct ClaimSubmitterCall {
+        pub lease_blocks: ::ethers::core::types::U256,
+    }
+    ///Container type for all input parameters for the `currentSubmitter` function with signature `currentSubmitter()` and selector `0xe1bdcfb3`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "currentSubmitter", abi = "currentSubmitter()")]
+    pub struct CurrentSubmitterCall;
+    ///Container type for all input parameters for the `depositERC20` function with signature `depositERC20(address,uint256,address)` and selector `0x1828686a`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "depositERC20", abi = "depositERC20(address,uint256,address)")]
+    pub struct DepositERC20Call {
+        pub token: ::ethers::core::types::Address,
+        pub amount: ::ethers::core::types::U256,
+        pub recipient: ::ethers::core::types::Address,
+    }
+    ///Container type for all input parameters for the `depositETH` function with signature `depositETH(address)` and selector `0x2d2da806`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "depositETH", abi = "depositETH(address)")]
+    pub struct DepositETHCall {
+        pub recipient: ::ethers::core::types::Address,
+    }
+    ///Container type for all input parameters for the `enqueueTransaction` function with signature `enqueueTransaction(bytes)` and selector `0xaec185fc`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "enqueueTransaction", abi = "enqueueTransaction(bytes)")]
+    pub struct EnqueueTransactionCall {
+        pub transaction: ::ethers::core::types::Bytes,
+    }
+    ///Container type for all input parameters for the `finalizeBlock` function with signature `finalizeBlock(uint256)` and selector `0x114c1137`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "finalizeBlock", abi = "finalizeBlock(uint256)")]
+    pub struct FinalizeBlockCall {
+        pub height: ::ethers::core::types::U256,
+    }
+    ///Container type for all input parameters for the `hotshot` function with signature `hotshot()` and selector `0x2adc8b76`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "hotshot", abi = "hotshot()")]
+    pub struct HotshotCall;
+    ///Container type for all input parameters for the `leaseExpiry` function with signature `leaseExpiry()` and selector `0x424a5f2d`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "leaseExpiry", abi = "leaseExpiry()")]
+    pub struct LeaseExpiryCall;
+    ///Container type for all input parameters for the `leaseStart` function with signature `leaseStart()` and selector `0x87822b62`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "leaseStart", abi = "leaseStart()")]
+    pub struct LeaseStartCall;
+    ///Container type for all input parameters for the `numFinalizedBlocks` function with signature `numFinalizedBlocks()` and selector `0xb66cc301`
     #[derive(
         Clone,
-        ::ethers::contract::EthEvent,
+        ::ethers::contract::EthCall,
         ::ethers::contract::EthDisplay,
         Default,
         Debug,
@@ -528,12 +2192,9 @@ pub mod example_rollup {
         Eq,
         Hash,
     )]
-    #[ethevent(name = "StateUpdate", abi = "StateUpdate(uint256,uint256)")]
-    pub struct StateUpdateFilter {
-        pub block_height: ::ethers::core::types::U256,
-        pub state_commitment: ::ethers::core::types::U256,
-    }
-    ///Container type for all input parameters for the `hotshot` function with signature `hotshot()` and selector `0x2adc8b76`
+    #[ethcall(name = "numFinalizedBlocks", abi = "numFinalizedBlocks()")]
+    pub struct NumFinalizedBlocksCall;
+    ///Container type for all input parameters for the `numVerifiedBlocks` function with signature `numVerifiedBlocks()` and selector `0x412cc8fe`
     #[derive(
         Clone,
         ::ethers::contract::EthCall,
@@ -544,9 +2205,9 @@ pub mod example_rollup {
         Eq,
         Hash,
     )]
-    #[ethcall(name = "hotshot", abi = "hotshot()")]
-    pub struct HotshotCall;
-    ///Container type for all input parameters for the `numVerifiedBlocks` function with signature `numVerifiedBlocks()` and selector `0x412cc8fe`
+    #[ethcall(name = "numVerifiedBlocks", abi = "numVerifiedBlocks()")]
+    pub struct NumVerifiedBlocksCall;
+    ///Container type for all input parameters for the `owner` function with signature `owner()` and selector `0x8da5cb5b`
     #[derive(
         Clone,
         ::ethers::contract::EthCall,
@@ -557,8 +2218,38 @@ pub mod example_rollup {
         Eq,
         Hash,
     )]
-    #[ethcall(name = "numVerifiedBlocks", abi = "numVerifiedBlocks()")]
-    pub struct NumVerifiedBlocksCall;
+    #[ethcall(name = "owner", abi = "owner()")]
+    pub struct OwnerCall;
+    ///Container type for all input parameters for the `setOwner` function with signature `setOwner(address)` and selector `0x13af4035`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "setOwner", abi = "setOwner(address)")]
+    pub struct SetOwnerCall {
+        pub new_owner: ::ethers::core::types::Address,
+    }
+    ///Container type for all input parameters for the `setSubmitter` function with signature `setSubmitter(address)` and selector `0x5a91f743`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "setSubmitter", abi = "setSubmitter(address)")]
+    pub struct SetSubmitterCall {
+        pub new_submitter: ::ethers::core::types::Address,
+    }
     ///Container type for all input parameters for the `stateCommitment` function with signature `stateCommitment()` and selector `0xd800741e`
     #[derive(
         Clone,
@@ -572,7 +2263,20 @@ pub mod example_rollup {
     )]
     #[ethcall(name = "stateCommitment", abi = "stateCommitment()")]
     pub struct StateCommitmentCall;
-    ///Container type for all input parameters for the `verifyBlocks` function with signature `verifyBlocks(uint64,uint256,(uint256,uint256,uint256,uint256))` and selector `0x032571a9`
+    ///Container type for all input parameters for the `submittedAt` function with signature `submittedAt(uint256)` and selector `0x5adcd749`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "submittedAt", abi = "submittedAt(uint256)")]
+    pub struct SubmittedAtCall(pub ::ethers::core::types::U256);
+    ///Container type for all input parameters for the `verifyBlocks` function with signature `verifyBlocks(uint256,uint64,uint256,(uint256,uint256,uint256,uint256))` and selector `0x861e441e`
     #[derive(
         Clone,
         ::ethers::contract::EthCall,
@@ -585,9 +2289,10 @@ pub mod example_rollup {
     )]
     #[ethcall(
         name = "verifyBlocks",
-        abi = "verifyBlocks(uint64,uint256,(uint256,uint256,uint256,uint256))"
+        abi = "verifyBlocks(uint256,uint64,uint256,(uint256,uint256,uint256,uint256))"
     )]
     pub struct VerifyBlocksCall {
+        pub height: ::ethers::core::types::U256,
         pub count: u64,
         pub next_state_commitment: ::ethers::core::types::U256,
         pub proof: BatchProof,
@@ -595,9 +2300,29 @@ pub mod example_rollup {
     ///Container type for all of the contract's call
     #[derive(Clone, ::ethers::contract::EthAbiType, Debug, PartialEq, Eq, Hash)]
     pub enum ExampleRollupCalls {
+        MaxEnqueuedBytes(MaxEnqueuedBytesCall),
+        MaxLeaseBlocks(MaxLeaseBlocksCall),
+        MaxTenureBlocks(MaxTenureBlocksCall),
+        TenureCooldownBlocks(TenureCooldownBlocksCall),
+        AuthorizedSubmitter(AuthorizedSubmitterCall),
+        Challenge(ChallengeCall),
+        ChallengeWindow(ChallengeWindowCall),
+        ClaimSubmitter(ClaimSubmitterCall),
+        CurrentSubmitter(CurrentSubmitterCall),
+        DepositERC20(DepositERC20Call),
+        DepositETH(DepositETHCall),
+        EnqueueTransaction(EnqueueTransactionCall),
+        FinalizeBlock(FinalizeBlockCall),
         Hotshot(HotshotCall),
+        LeaseExpiry(LeaseExpiryCall),
+        LeaseStart(LeaseStartCall),
+        NumFinalizedBlocks(NumFinalizedBlocksCall),
         NumVerifiedBlocks(NumVerifiedBlocksCall),
+        Owner(OwnerCall),
+        SetOwner(SetOwnerCall),
+        SetSubmitter(SetSubmitterCall),
         StateCommitment(StateCommitmentCall),
+        SubmittedAt(SubmittedAtCall),
         VerifyBlocks(VerifyBlocksCall),
     }
     impl ::ethers::core::abi::AbiDecode for ExampleRollupCalls {
@@ -605,19 +2330,102 @@ pub mod example_rollup {
             data: impl AsRef<[u8]>,
         ) -> ::core::result::Result<Self, ::ethers::core::abi::AbiError> {
             let data = data.as_ref();
+            if let Ok(decoded) =
+                <MaxEnqueuedBytesCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::MaxEnqueuedBytes(decoded));
+            }
+            if let Ok(decoded) =
+                <MaxLeaseBlocksCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::MaxLeaseBlocks(decoded));
+            }
+            if let Ok(decoded) =
+                <MaxTenureBlocksCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::MaxTenureBlocks(decoded));
+            }
+            if let Ok(decoded) =
+                <TenureCooldownBlocksCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::TenureCooldownBlocks(decoded));
+            }
+            if let Ok(decoded) =
+                <AuthorizedSubmitterCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::AuthorizedSubmitter(decoded));
+            }
+            if let Ok(decoded) = <ChallengeCall as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::Challenge(decoded));
+            }
+            if let Ok(decoded) =
+                <ChallengeWindowCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::ChallengeWindow(decoded));
+            }
+            if let Ok(decoded) =
+                <ClaimSubmitterCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::ClaimSubmitter(decoded));
+            }
+            if let Ok(decoded) =
+                <CurrentSubmitterCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::CurrentSubmitter(decoded));
+            }
+            if let Ok(decoded) = <DepositERC20Call as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::DepositERC20(decoded));
+            }
+            if let Ok(decoded) = <DepositETHCall as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::DepositETH(decoded));
+            }
+            if let Ok(decoded) =
+                <EnqueueTransactionCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::EnqueueTransaction(decoded));
+            }
+            if let Ok(decoded) = <FinalizeBlockCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::FinalizeBlock(decoded));
+            }
             if let Ok(decoded) = <HotshotCall as ::ethers::core::abi::AbiDecode>::decode(data) {
                 return Ok(Self::Hotshot(decoded));
             }
+            if let Ok(decoded) = <LeaseExpiryCall as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::LeaseExpiry(decoded));
+            }
+            if let Ok(decoded) = <LeaseStartCall as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::LeaseStart(decoded));
+            }
+            if let Ok(decoded) =
+                <NumFinalizedBlocksCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::NumFinalizedBlocks(decoded));
+            }
             if let Ok(decoded) =
                 <NumVerifiedBlocksCall as ::ethers::core::abi::AbiDecode>::decode(data)
             {
                 return Ok(Self::NumVerifiedBlocks(decoded));
             }
+            if let Ok(decoded) = <OwnerCall as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::Owner(decoded));
+            }
+            if let Ok(decoded) = <SetOwnerCall as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::SetOwner(decoded));
+            }
+            if let Ok(decoded) = <SetSubmitterCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::SetSubmitter(decoded));
+            }
             if let Ok(decoded) =
                 <StateCommitmentCall as ::ethers::core::abi::AbiDecode>::decode(data)
             {
                 return Ok(Self::StateCommitment(decoded));
             }
+            if let Ok(decoded) = <SubmittedAtCall as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::SubmittedAt(decoded));
+            }
             if let Ok(decoded) = <VerifyBlocksCall as ::ethers::core::abi::AbiDecode>::decode(data)
             {
                 return Ok(Self::VerifyBlocks(decoded));
@@ -628,9 +2436,37 @@ pub mod example_rollup {
     impl ::ethers::core::abi::AbiEncode for ExampleRollupCalls {
         fn encode(self) -> Vec<u8> {
             match self {
+                Self::MaxEnqueuedBytes(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::MaxLeaseBlocks(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::MaxTenureBlocks(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::TenureCooldownBlocks(element) => {
+                    ::ethers::core::abi::AbiEncode::encode(element)
+                }
+                Self::AuthorizedSubmitter(element) => {
+                    ::ethers::core::abi::AbiEncode::encode(element)
+                }
+                Self::Challenge(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::ChallengeWindow(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::ClaimSubmitter(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::CurrentSubmitter(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::DepositERC20(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::DepositETH(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::EnqueueTransaction(element) => {
+                    ::ethers::core::abi::AbiEncode::encode(element)
+                }
+                Self::FinalizeBlock(element) => ::ethers::core::abi::AbiEncode::encode(element),
                 Self::Hotshot(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::LeaseExpiry(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::LeaseStart(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::NumFinalizedBlocks(element) => {
+                    ::ethers::core::abi::AbiEncode::encode(element)
+                }
                 Self::NumVerifiedBlocks(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::Owner(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::SetOwner(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::SetSubmitter(element) => ::ethers::core::abi::AbiEncode::encode(element),
                 Self::StateCommitment(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::SubmittedAt(element) => ::ethers::core::abi::AbiEncode::encode(element),
                 Self::VerifyBlocks(element) => ::ethers::core::abi::AbiEncode::encode(element),
             }
         }
@@ -638,33 +2474,237 @@ pub mod example_rollup {
     impl ::core::fmt::Display for ExampleRollupCalls {
         fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
             match self {
+                Self::MaxEnqueuedBytes(element) => ::core::fmt::Display::fmt(element, f),
+                Self::MaxLeaseBlocks(element) => ::core::fmt::Display::fmt(element, f),
+                Self::MaxTenureBlocks(element) => ::core::fmt::Display::fmt(element, f),
+                Self::TenureCooldownBlocks(element) => ::core::fmt::Display::fmt(element, f),
+                Self::AuthorizedSubmitter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::Challenge(element) => ::core::fmt::Display::fmt(element, f),
+                Self::ChallengeWindow(element) => ::core::fmt::Display::fmt(element, f),
+                Self::ClaimSubmitter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::CurrentSubmitter(element) => ::core::fmt::Display::fmt(element, f),
+                Self::DepositERC20(element) => ::core::fmt::Display::fmt(element, f),
+                Self::DepositETH(element) => ::core::fmt::Display::fmt(element, f),
+                Self::EnqueueTransaction(element) => ::core::fmt::Display::fmt(element, f),
+                Self::FinalizeBlock(element) => ::core::fmt::Display::fmt(element, f),
                 Self::Hotshot(element) => ::core::fmt::Display::fmt(element, f),
+                Self::LeaseExpiry(element) => ::core::fmt::Display::fmt(element, f),
+                Self::LeaseStart(element) => ::core::fmt::Display::fmt(element, f),
+                Self::NumFinalizedBlocks(element) => ::core::fmt::Display::fmt(element, f),
                 Self::NumVerifiedBlocks(element) => ::core::fmt::Display::fmt(element, f),
+                Self::Owner(element) => ::core::fmt::Display::fmt(element, f),
+                Self::SetOwner(element) => ::core::fmt::Display::fmt(element, f),
+                Self::SetSubmitter(element) => ::core::fmt::Display::fmt(element, f),
                 Self::StateCommitment(element) => ::core::fmt::Display::fmt(element, f),
+                Self::SubmittedAt(element) => ::core::fmt::Display::fmt(element, f),
                 Self::VerifyBlocks(element) => ::core::fmt::Display::fmt(element, f),
             }
         }
     }
+    impl ::core::convert::From<MaxEnqueuedBytesCall> for ExampleRollupCalls {
+        fn from(value: MaxEnqueuedBytesCall) -> Self {
+            Self::MaxEnqueuedBytes(value)
+        }
+    }
+    impl ::core::convert::From<MaxLeaseBlocksCall> for ExampleRollupCalls {
+        fn from(value: MaxLeaseBlocksCall) -> Self {
+            Self::MaxLeaseBlocks(value)
+        }
+    }
+    impl ::core::convert::From<MaxTenureBlocksCall> for ExampleRollupCalls {
+        fn from(value: MaxTenureBlocksCall) -> Self {
+            Self::MaxTenureBlocks(value)
+        }
+    }
+    impl ::core::convert::From<TenureCooldownBlocksCall> for ExampleRollupCalls {
+        fn from(value: TenureCooldownBlocksCall) -> Self {
+            Self::TenureCooldownBlocks(value)
+        }
+    }
+    impl ::core::convert::From<AuthorizedSubmitterCall> for ExampleRollupCalls {
+        fn from(value: AuthorizedSubmitterCall) -> Self {
+            Self::AuthorizedSubmitter(value)
+        }
+    }
+    impl ::core::convert::From<ChallengeCall> for ExampleRollupCalls {
+        fn from(value: ChallengeCall) -> Self {
+            Self::Challenge(value)
+        }
+    }
+    impl ::core::convert::From<ChallengeWindowCall> for ExampleRollupCalls {
+        fn from(value: ChallengeWindowCall) -> Self {
+            Self::ChallengeWindow(value)
+        }
+    }
+    impl ::core::convert::From<ClaimSubmitterCall> for ExampleRollupCalls {
+        fn from(value: ClaimSubmitterCall) -> Self {
+            Self::ClaimSubmitter(value)
+        }
+    }
+    impl ::core::convert::From<CurrentSubmitterCall> for ExampleRollupCalls {
+        fn from(value: CurrentSubmitterCall) -> Self {
+            Self::CurrentSubmitter(value)
+        }
+    }
+    impl ::core::convert::From<DepositERC20Call> for ExampleRollupCalls {
+        fn from(value: DepositERC20Call) -> Self {
+            Self::DepositERC20(value)
+        }
+    }
+    impl ::core::convert::From<DepositETHCall> for ExampleRollupCalls {
+        fn from(value: DepositETHCall) -> Self {
+            Self::DepositETH(value)
+        }
+    }
+    impl ::core::convert::From<EnqueueTransactionCall> for ExampleRollupCalls {
+        fn from(value: EnqueueTransactionCall) -> Self {
+            Self::EnqueueTransaction(value)
+        }
+    }
+    impl ::core::convert::From<FinalizeBlockCall> for ExampleRollupCalls {
+        fn from(value: FinalizeBlockCall) -> Self {
+            Self::FinalizeBlock(value)
+        }
+    }
     impl ::core::convert::From<HotshotCall> for ExampleRollupCalls {
         fn from(value: HotshotCall) -> Self {
             Self::Hotshot(value)
         }
     }
+    impl ::core::convert::From<LeaseExpiryCall> for ExampleRollupCalls {
+        fn from(value: LeaseExpiryCall) -> Self {
+            Self::LeaseExpiry(value)
+        }
+    }
+    impl ::core::convert::From<LeaseStartCall> for ExampleRollupCalls {
+        fn from(value: LeaseStartCall) -> Self {
+            Self::LeaseStart(value)
+        }
+    }
+    impl ::core::convert::From<NumFinalizedBlocksCall> for ExampleRollupCalls {
+        fn from(value: NumFinalizedBlocksCall) -> Self {
+            Self::NumFinalizedBlocks(value)
+        }
+    }
     impl ::core::convert::From<NumVerifiedBlocksCall> for ExampleRollupCalls {
         fn from(value: NumVerifiedBlocksCall) -> Self {
             Self::NumVerifiedBlocks(value)
         }
     }
+    impl ::core::convert::From<OwnerCall> for ExampleRollupCalls {
+        fn from(value: OwnerCall) -> Self {
+            Self::Owner(value)
+        }
+    }
+    impl ::core::convert::From<SetOwnerCall> for ExampleRollupCalls {
+        fn from(value: SetOwnerCall) -> Self {
+            Self::SetOwner(value)
+        }
+    }
+    impl ::core::convert::From<SetSubmitterCall> for ExampleRollupCalls {
+        fn from(value: SetSubmitterCall) -> Self {
+            Self::SetSubmitter(value)
+        }
+    }
     impl ::core::convert::From<StateCommitmentCall> for ExampleRollupCalls {
         fn from(value: StateCommitmentCall) -> Self {
             Self::StateCommitment(value)
         }
     }
+    impl ::core::convert::From<SubmittedAtCall> for ExampleRollupCalls {
+        fn from(value: SubmittedAtCall) -> Self {
+            Self::SubmittedAt(value)
+        }
+    }
     impl ::core::convert::From<VerifyBlocksCall> for ExampleRollupCalls {
         fn from(value: VerifyBlocksCall) -> Self {
             Self::VerifyBlocks(value)
         }
     }
+    ///Container type for all return fields from the `MAX_ENQUEUED_BYTES` function with signature `MAX_ENQUEUED_BYTES()` and selector `0xfd611528`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct MaxEnqueuedBytesReturn(pub ::ethers::core::types::U256);
+    ///Container type for all return fields from the `MAX_LEASE_BLOCKS` function with signature `MAX_LEASE_BLOCKS()` and selector `0x06044c46`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct MaxLeaseBlocksReturn(pub ::ethers::core::types::U256);
+    ///Container type for all return fields from the `MAX_TENURE_BLOCKS` function with signature `MAX_TENURE_BLOCKS()` and selector `0xe901091c`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct MaxTenureBlocksReturn(pub ::ethers::core::types::U256);
+    ///Container type for all return fields from the `TENURE_COOLDOWN_BLOCKS` function with signature `TENURE_COOLDOWN_BLOCKS()` and selector `0x1b2753bc`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct TenureCooldownBlocksReturn(pub ::ethers::core::types::U256);
+    ///Container type for all return fields from the `authorizedSubmitter` function with signature `authorizedSubmitter()` and selector `0x0be75499`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct AuthorizedSubmitterReturn(pub ::ethers::core::types::Address);
+    ///Container type for all return fields from the `challengeWindow` function with signature `challengeWindow()` and selector `0x861a1412`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct ChallengeWindowReturn(pub ::ethers::core::types::U256);
+    ///Container type for all return fields from the `currentSubmitter` function with signature `currentSubmitter()` and selector `0xe1bdcfb3`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct CurrentSubmitterReturn(pub ::ethers::core::types::Address);
     ///Container type for all return fields from the `hotshot` function with signature `hotshot()` and selector `0x2adc8b76`
     #[derive(
         Clone,
@@ -677,6 +2717,42 @@ pub mod example_rollup {
         Hash,
     )]
     pub struct HotshotReturn(pub ::ethers::core::types::Address);
+    ///Container type for all return fields from the `leaseExpiry` function with signature `leaseExpiry()` and selector `0x424a5f2d`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct LeaseExpiryReturn(pub ::ethers::core::types::U256);
+    ///Container type for all return fields from the `leaseStart` function with signature `leaseStart()` and selector `0x87822b62`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct LeaseStartReturn(pub ::ethers::core::types::U256);
+    ///Container type for all return fields from the `numFinalizedBlocks` function with signature `numFinalizedBlocks()` and selector `0xb66cc301`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct NumFinalizedBlocksReturn(pub ::ethers::core::types::U256);
     ///Container type for all return fields from the `numVerifiedBlocks` function with signature `numVerifiedBlocks()` and selector `0x412cc8fe`
     #[derive(
         Clone,
@@ -689,6 +2765,18 @@ pub mod example_rollup {
         Hash,
     )]
     pub struct NumVerifiedBlocksReturn(pub ::ethers::core::types::U256);
+    ///Container type for all return fields from the `owner` function with signature `owner()` and selector `0x8da5cb5b`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct OwnerReturn(pub ::ethers::core::types::Address);
     ///Container type for all return fields from the `stateCommitment` function with signature `stateCommitment()` and selector `0xd800741e`
     #[derive(
         Clone,
@@ -701,6 +2789,18 @@ pub mod example_rollup {
         Hash,
     )]
     pub struct StateCommitmentReturn(pub ::ethers::core::types::U256);
+    ///Container type for all return fields from the `submittedAt` function with signature `submittedAt(uint256)` and selector `0x5adcd749`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct SubmittedAtReturn(pub ::ethers::core::types::U256);
     ///`BatchProof(uint256,uint256,uint256,uint256)`
     #[derive(
         Clone,
@@ -3,11 +3,55 @@ pragma solidity ^0.8.13;
 // TODO: can we get rid of the `src` directory?
 import "hotshot/src/HotShot.sol";
 
+// The part of the ERC-20 interface the rollup uses to take deposits.
+interface IERC20 {
+    function transferFrom(address from, address to, uint256 amount) external returns (bool);
+    function balanceOf(address account) external view returns (uint256);
+}
+
 contract ExampleRollup {
     HotShot public hotshot;
     uint256 public stateCommitment;
     uint256 public numVerifiedBlocks;
 
+    // The account which alone may change the authorized submitter and hand ownership on. Initially
+    // the account which deployed the contract.
+    address public owner;
+    // The only account allowed to submit proofs, so that a compromised operator key can be rotated
+    // out. The zero address allows any account, subject to the submitter lease.
+    address public authorizedSubmitter;
+
+    // The account which currently holds the exclusive right to submit proofs, and the L1 block at
+    // which its lease runs out. Redundant executors use this to elect a single submitter, so they
+    // do not all pay gas to prove the same blocks.
+    address private submitter;
+    uint256 public leaseExpiry;
+    // The L1 block at which the holder of the lease claimed it without holding it before, starting
+    // its tenure. Renewals do not change it.
+    uint256 public leaseStart;
+    // The longest lease which can be claimed at once, about a day of L1 blocks.
+    uint256 public constant MAX_LEASE_BLOCKS = 7200;
+    // The longest tenure, about a week of L1 blocks, and how long after it runs out before its holder
+    // may claim the lease again, about an hour. These only apply while any account may claim the
+    // lease. Without them, one account could keep the lease for good by renewing it, and standby
+    // executors would never submit. With them, every other account gets a turn to claim the lease
+    // when a tenure runs out. An attacker with several keys can still claim the lease with each in
+    // turn; only an authorized submitter rules that out.
+    uint256 public constant MAX_TENURE_BLOCKS = 50400;
+    uint256 public constant TENURE_COOLDOWN_BLOCKS = 300;
+    // The largest transaction which can be enqueued for forced inclusion, in bytes. This bounds the
+    // work an L1 account can force on every executor for the price of L1 calldata.
+    uint256 public constant MAX_ENQUEUED_BYTES = 4096;
+
+    // Seconds for which a state update can be challenged before it is final, in optimistic mode.
+    // Zero makes each update final as soon as it is verified, as in a validity rollup.
+    uint256 public challengeWindow;
+    // Number of blocks whose state updates are final.
+    uint256 public numFinalizedBlocks;
+    // L1 timestamp at which each state update still in its challenge window was accepted, by the
+    // number of blocks verified after it.
+    mapping(uint256 => uint256) public submittedAt;
+
     // Attempted to verify a proof of the blocks from `numVerifiedBlocks` to
     // `numVerifiedBlocks + count`, but the HotShot `blockHeight` is less than
     // `numVerifiedBlocks + count`.
@@ -16,13 +60,59 @@ contract ExampleRollup {
     error InvalidProof(uint256 firstBlock, uint256 lastBlock, uint256 oldState, uint256 newState, BatchProof proof);
     // Attempted to verify an empty chain of blocks;
     error NoBlocks();
+    // Attempted to verify blocks starting at `height`, but the next block to verify is
+    // `numVerifiedBlocks`. A proof of blocks which were already verified is a duplicate.
+    error WrongHeight(uint256 numVerifiedBlocks, uint256 height);
+    // Attempted to claim the submitter lease while another account holds it.
+    error LeaseHeld(address submitter, uint256 leaseExpiry);
+    // Attempted to claim the submitter lease for more than `MAX_LEASE_BLOCKS` L1 blocks.
+    error LeaseTooLong(uint256 leaseBlocks, uint256 maxLeaseBlocks);
+    // Attempted to renew a lease whose tenure has run out, or to claim the lease again before
+    // `claimableAt` after such a tenure.
+    error TenureOver(uint256 claimableAt);
+    // Attempted to verify blocks while another account holds the submitter lease.
+    error NotSubmitter(address submitter);
+    // Attempted to verify blocks or claim the lease from an account other than the authorized
+    // submitter.
+    error NotAuthorized(address authorizedSubmitter);
+    // Attempted to change the authorized submitter or the owner from an account other than the
+    // owner.
+    error NotOwner(address owner);
+    // Attempted to deposit an ERC-20 token which refused or reverted the transfer.
+    error DepositFailed(address token);
+    // Attempted to finalize, or challenge, a height which no state update awaiting finality ends
+    // at.
+    error NotPending(uint256 height);
+    // Attempted to finalize a state update before its challenge window closes at `finalizableAt`.
+    error NotFinalizable(uint256 height, uint256 finalizableAt);
+    // A challenge of a state update was rejected because its fraud proof did not hold, as no fraud
+    // proof against a mock proof does.
+    error InvalidFraudProof(uint256 height);
+    // Attempted to enqueue a transaction of `size` bytes, more than `MAX_ENQUEUED_BYTES`.
+    error TransactionTooLarge(uint256 size, uint256 maxSize);
 
     event StateUpdate(uint256 blockHeight, uint256 stateCommitment);
+    event SubmitterClaimed(address submitter, uint256 leaseExpiry);
+    event SubmitterAuthorized(address submitter);
+    event OwnerChanged(address owner);
+    event Deposit(address token, address sender, address recipient, uint256 amount);
+    event BlocksFinalized(uint256 blockHeight);
+    event TransactionEnqueued(address sender, bytes transaction);
 
-    constructor(address hotshotAddress, uint256 initialState) {
+    constructor(
+        address hotshotAddress,
+        uint256 initialState,
+        uint256 challengeWindowSeconds,
+        address initialSubmitter
+    ) {
         hotshot = HotShot(hotshotAddress);
         stateCommitment = initialState;
         numVerifiedBlocks = 0;
+        challengeWindow = challengeWindowSeconds;
+        owner = msg.sender;
+        authorizedSubmitter = initialSubmitter;
+        emit OwnerChanged(msg.sender);
+        emit SubmitterAuthorized(initialSubmitter);
     }
 
     // A batch proof of the execution of a chain of blocks.
@@ -66,11 +156,196 @@ contract ExampleRollup {
         return oldState == proof.oldState && newState == proof.newState;
     }
 
-    function verifyBlocks(uint64 count, uint256 nextStateCommitment, BatchProof calldata proof) external {
+    // The account holding the submitter lease, or the zero address if no lease is active.
+    function currentSubmitter() public view returns (address) {
+        if (block.number >= leaseExpiry) {
+            return address(0);
+        }
+        return submitter;
+    }
+
+    // Revert unless `msg.sender` may submit proofs.
+    function _checkAuthorized() private view {
+        if (authorizedSubmitter != address(0) && authorizedSubmitter != msg.sender) {
+            revert NotAuthorized(authorizedSubmitter);
+        }
+    }
+
+    // Revert unless `msg.sender` is the owner.
+    function _checkOwner() private view {
+        if (msg.sender != owner) {
+            revert NotOwner(owner);
+        }
+    }
+
+    // Hand ownership of the contract to `newOwner`, so that a compromised owner key can be rotated
+    // out. Only the owner may call this, and the old owner loses every right of the owner at once.
+    //
+    // Handing ownership to the zero address gives it up for good: no one can change the authorized
+    // submitter after that.
+    function setOwner(address newOwner) external {
+        _checkOwner();
+        owner = newOwner;
+        emit OwnerChanged(newOwner);
+    }
+
+    // Make `newSubmitter` the only account allowed to submit proofs, or allow any account with the
+    // zero address. Only the owner may call this.
+    //
+    // Any submitter lease is revoked, so the new submitter can take over at once rather than
+    // waiting for the lease of the old one to run out.
+    function setSubmitter(address newSubmitter) external {
+        _checkOwner();
+        authorizedSubmitter = newSubmitter;
+        leaseExpiry = 0;
+        emit SubmitterAuthorized(newSubmitter);
+    }
+
+    // Claim the exclusive right to submit proofs for the next `leaseBlocks` L1 blocks, at most
+    // `MAX_LEASE_BLOCKS`.
+    //
+    // The holder of an active lease may call this again to renew it. Anyone may claim a lease which
+    // has run out, which is how a standby executor takes over from one which has stopped.
+    //
+    // While any account may claim the lease, renewals cannot extend a tenure past
+    // `MAX_TENURE_BLOCKS`. Once the lease has been renewed up to that limit, renewing it again
+    // reverts, and when it runs out its holder must wait `TENURE_COOLDOWN_BLOCKS` before claiming
+    // it again.
+    function claimSubmitter(uint256 leaseBlocks) external {
+        _checkAuthorized();
+        if (leaseBlocks > MAX_LEASE_BLOCKS) {
+            revert LeaseTooLong(leaseBlocks, MAX_LEASE_BLOCKS);
+        }
+        address holder = currentSubmitter();
+        if (holder != address(0) && holder != msg.sender) {
+            revert LeaseHeld(holder, leaseExpiry);
+        }
+
+        uint256 expiry = block.number + leaseBlocks;
+        if (authorizedSubmitter == address(0)) {
+            uint256 tenureEnd = leaseStart + MAX_TENURE_BLOCKS;
+            // Whether the last tenure ended because it reached the limit, rather than because its
+            // holder stopped renewing the lease.
+            bool tenureOver = submitter == msg.sender && leaseExpiry == tenureEnd;
+            uint256 claimableAt = leaseExpiry + TENURE_COOLDOWN_BLOCKS;
+            if (holder != address(0)) {
+                if (tenureOver) {
+                    revert TenureOver(claimableAt);
+                }
+                if (expiry > tenureEnd) {
+                    expiry = tenureEnd;
+                }
+            } else if (tenureOver && block.number < claimableAt) {
+                revert TenureOver(claimableAt);
+            }
+        }
+        if (holder == address(0)) {
+            leaseStart = block.number;
+        }
+        submitter = msg.sender;
+        leaseExpiry = expiry;
+        emit SubmitterClaimed(msg.sender, leaseExpiry);
+    }
+
+    // Deposit `amount` of the ERC-20 `token` into the rollup, to be credited to `recipient` there.
+    //
+    // The tokens are pulled from the caller, who must have approved this contract to spend them.
+    // Executors credit the deposit once its L1 block is finalized, and only if `token` is on the
+    // rollup's list of assets. Deposits of other tokens are held by this contract but never
+    // credited.
+    //
+    // The deposit is of what this contract actually received, which is less than `amount` for a
+    // token which charges a fee on transfer.
+    function depositERC20(address token, uint256 amount, address recipient) external {
+        uint256 balance = IERC20(token).balanceOf(address(this));
+        // Called at a low level, as SafeERC20 does, so that tokens whose `transferFrom` returns
+        // nothing, such as USDT, are accepted. A token which returns `false` still refuses the
+        // transfer. Reading the balance above has already reverted if `token` has no code.
+        (bool success, bytes memory result) =
+            token.call(abi.encodeCall(IERC20.transferFrom, (msg.sender, address(this), amount)));
+        if (!success || (result.length > 0 && (result.length != 32 || !abi.decode(result, (bool))))) {
+            revert DepositFailed(token);
+        }
+        uint256 received = IERC20(token).balanceOf(address(this)) - balance;
+        emit Deposit(token, msg.sender, recipient, received);
+    }
+
+    // Deposit the ether sent with the call into the rollup, to be credited to `recipient` there.
+    //
+    // The deposit names the zero address as its token. Executors credit it once its L1 block is
+    // finalized, and only if ether is on the rollup's list of assets. Otherwise the ether is held by
+    // this contract but never credited.
+    function depositETH(address recipient) external payable {
+        emit Deposit(address(0), msg.sender, recipient, msg.value);
+    }
+
+    // Enqueue a signed rollup `transaction` for forced inclusion, bypassing the sequencer.
+    //
+    // The transaction is not checked here: it is only logged. Executors of rollups which enable
+    // forced inclusion apply each enqueued transaction once its L1 block is finalized, in L1 order,
+    // and skip any which do not decode. The sender of this call need not be the signer of the
+    // transaction, so a relayer may pay the L1 fee on a user's behalf.
+    function enqueueTransaction(bytes calldata transaction) external {
+        if (transaction.length > MAX_ENQUEUED_BYTES) {
+            revert TransactionTooLarge(transaction.length, MAX_ENQUEUED_BYTES);
+        }
+        emit TransactionEnqueued(msg.sender, transaction);
+    }
+
+    // Make final the state update which verified the first `height` blocks, once its challenge
+    // window has closed, along with every update before it.
+    //
+    // Anyone may call this. Updates are only ever final in order, so an earlier update whose window
+    // is still open cannot be skipped: it was accepted first, so its window closes first.
+    function finalizeBlock(uint256 height) external {
+        uint256 submitted = submittedAt[height];
+        if (height <= numFinalizedBlocks || submitted == 0) {
+            revert NotPending(height);
+        }
+        if (block.timestamp < submitted + challengeWindow) {
+            revert NotFinalizable(height, submitted + challengeWindow);
+        }
+        numFinalizedBlocks = height;
+        emit BlocksFinalized(height);
+    }
+
+    // Challenge the state update which verified the first `height` blocks, while it is still in its
+    // challenge window.
+    //
+    // This is a stub, and every challenge reverts. A mock batch proof carries nothing but the public
+    // parameters which `verifyBlocks` already checked, so there is no execution to dispute and no
+    // fraud proof against one can hold. A real optimistic rollup would re-execute the disputed step
+    // here and roll a disproven update back, and its executors would have to follow the rollback
+    // and prove the blocks again.
+    function challenge(uint256 height, bytes calldata fraudProof) external {
+        if (height <= numFinalizedBlocks || submittedAt[height] == 0) {
+            revert NotPending(height);
+        }
+        revert InvalidFraudProof(height);
+    }
+
+    // Verify a proof of the `count` blocks starting at rollup block `height`.
+    //
+    // `height` must be the number of blocks already verified, so that a delayed or duplicated proof
+    // transaction can never be applied on top of a newer state update.
+    function verifyBlocks(uint256 height, uint64 count, uint256 nextStateCommitment, BatchProof calldata proof)
+        external
+    {
         if (count == 0) {
             revert NoBlocks();
         }
 
+        _checkAuthorized();
+        // While a lease is active, only its holder may submit. Without one, anyone may.
+        address holder = currentSubmitter();
+        if (holder != address(0) && holder != msg.sender) {
+            revert NotSubmitter(holder);
+        }
+
+        if (height != numVerifiedBlocks) {
+            revert WrongHeight(numVerifiedBlocks, height);
+        }
+
         uint256 blockHeight = hotshot.blockHeight();
         if (numVerifiedBlocks + count > blockHeight) {
             revert NotYetSequenced(numVerifiedBlocks, count, blockHeight);
@@ -85,5 +360,12 @@ contract ExampleRollup {
         numVerifiedBlocks += count;
         stateCommitment = nextStateCommitment;
         emit StateUpdate(numVerifiedBlocks, stateCommitment);
+
+        if (challengeWindow == 0) {
+            numFinalizedBlocks = numVerifiedBlocks;
+            emit BlocksFinalized(numVerifiedBlocks);
+        } else {
+            submittedAt[numVerifiedBlocks] = block.timestamp;
+        }
     }
 }
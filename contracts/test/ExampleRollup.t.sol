@@ -4,16 +4,22 @@ pragma solidity ^0.8.13;
 import "forge-std/Test.sol";
 import "hotshot/src/HotShot.sol";
 import "../src/ExampleRollup.sol";
+import "./ExampleToken.sol";
 
 contract ExampleRollupTest is Test {
     HotShot public hotshot;
     ExampleRollup public rollup;
 
     event StateUpdate(uint256 blockHeight, uint256 stateCommitment);
+    event Deposit(address token, address sender, address recipient, uint256 amount);
+    event BlocksFinalized(uint256 blockHeight);
+    event OwnerChanged(address owner);
+    event SubmitterAuthorized(address submitter);
+    event TransactionEnqueued(address sender, bytes transaction);
 
     function setUp() public {
         hotshot = new HotShot();
-        rollup = new ExampleRollup(address(hotshot), 0);
+        rollup = new ExampleRollup(address(hotshot), 0, 0, address(0));
     }
 
     function testStateUpdate() public {
@@ -32,12 +38,39 @@ contract ExampleRollupTest is Test {
         });
         vm.expectEmit(false, false, false, true, address(rollup));
         emit StateUpdate(1, proof.newState);
-        rollup.verifyBlocks(1, proof.newState, proof);
+        rollup.verifyBlocks(0, 1, proof.newState, proof);
 
         assertEq(rollup.stateCommitment(), proof.newState);
         assertEq(rollup.numVerifiedBlocks(), 1);
     }
 
+    function testWrongHeight() public {
+        HotShot.QC[] memory qcs = new HotShot.QC[](2);
+        qcs[0].blockCommitment = 576467464341;
+        qcs[0].height = 0;
+        qcs[1].blockCommitment = 576467464342;
+        qcs[1].height = 1;
+        hotshot.newBlocks(qcs);
+
+        ExampleRollup.BatchProof memory proof = ExampleRollup.BatchProof({
+            firstBlock: qcs[0].blockCommitment,
+            lastBlock: qcs[0].blockCommitment,
+            oldState: 0,
+            newState: 523123
+        });
+
+        // A proof which skips ahead of the verified blocks is rejected.
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.WrongHeight.selector, 0, 1));
+        rollup.verifyBlocks(1, 1, proof.newState, proof);
+
+        // Submitting the same proof twice only applies it once.
+        rollup.verifyBlocks(0, 1, proof.newState, proof);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.WrongHeight.selector, 1, 0));
+        rollup.verifyBlocks(0, 1, proof.newState, proof);
+        assertEq(rollup.numVerifiedBlocks(), 1);
+        assertEq(rollup.stateCommitment(), proof.newState);
+    }
+
     function testInvalidProof() public {
         // Add a commitment to hotshot
         HotShot.QC[] memory qcs = new HotShot.QC[](1);
@@ -59,6 +92,353 @@ contract ExampleRollupTest is Test {
                 proof
             )
         );
-        rollup.verifyBlocks(1, invalidState, proof);
+        rollup.verifyBlocks(0, 1, invalidState, proof);
+    }
+
+    function testClaimSubmitter() public {
+        address leader = address(0x1);
+        address follower = address(0x2);
+
+        // Nobody holds the lease at first.
+        assertEq(rollup.currentSubmitter(), address(0));
+
+        vm.prank(leader);
+        rollup.claimSubmitter(10);
+        assertEq(rollup.currentSubmitter(), leader);
+        assertEq(rollup.leaseExpiry(), block.number + 10);
+
+        // Others cannot claim the lease or submit proofs while it is held.
+        vm.prank(follower);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.LeaseHeld.selector, leader, block.number + 10));
+        rollup.claimSubmitter(10);
+
+        ExampleRollup.BatchProof memory proof =
+            ExampleRollup.BatchProof({firstBlock: 0, lastBlock: 0, oldState: 0, newState: 523123});
+        vm.prank(follower);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotSubmitter.selector, leader));
+        rollup.verifyBlocks(0, 1, proof.newState, proof);
+
+        // The holder can renew the lease.
+        vm.roll(block.number + 5);
+        vm.prank(leader);
+        rollup.claimSubmitter(10);
+        assertEq(rollup.leaseExpiry(), block.number + 10);
+
+        // Once the lease runs out, anyone can take over.
+        vm.roll(block.number + 10);
+        assertEq(rollup.currentSubmitter(), address(0));
+        vm.prank(follower);
+        rollup.claimSubmitter(10);
+        assertEq(rollup.currentSubmitter(), follower);
+    }
+
+    function testLeaseTooLong() public {
+        address leader = address(0x1);
+        address follower = address(0x2);
+        uint256 maxLease = rollup.MAX_LEASE_BLOCKS();
+        assertEq(maxLease, 7200);
+
+        // No one can claim the lease for longer than the limit, even with no lease held.
+        vm.prank(leader);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.LeaseTooLong.selector, maxLease + 1, maxLease));
+        rollup.claimSubmitter(maxLease + 1);
+        assertEq(rollup.currentSubmitter(), address(0));
+
+        // A lease of exactly the limit can be claimed, and runs out after it.
+        vm.prank(leader);
+        rollup.claimSubmitter(maxLease);
+        assertEq(rollup.leaseExpiry(), block.number + maxLease);
+
+        // The holder cannot extend its lease past the limit by renewing it either.
+        vm.prank(leader);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.LeaseTooLong.selector, type(uint256).max, maxLease));
+        rollup.claimSubmitter(type(uint256).max);
+
+        vm.roll(block.number + maxLease - 1);
+        assertEq(rollup.currentSubmitter(), leader);
+        vm.roll(block.number + 1);
+        assertEq(rollup.currentSubmitter(), address(0));
+        vm.prank(follower);
+        rollup.claimSubmitter(10);
+        assertEq(rollup.currentSubmitter(), follower);
+    }
+
+    function testSubmitterTenure() public {
+        address leader = address(0x1);
+        address follower = address(0x2);
+        uint256 maxLease = rollup.MAX_LEASE_BLOCKS();
+        uint256 tenureEnd = block.number + rollup.MAX_TENURE_BLOCKS();
+        uint256 claimableAt = tenureEnd + rollup.TENURE_COOLDOWN_BLOCKS();
+
+        // Renewals extend the lease up to the end of the tenure, and no further.
+        vm.prank(leader);
+        rollup.claimSubmitter(maxLease);
+        while (rollup.leaseExpiry() < tenureEnd) {
+            vm.roll(rollup.leaseExpiry() - 1);
+            vm.prank(leader);
+            rollup.claimSubmitter(maxLease);
+        }
+        assertEq(rollup.leaseExpiry(), tenureEnd);
+        vm.prank(leader);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.TenureOver.selector, claimableAt));
+        rollup.claimSubmitter(maxLease);
+
+        // Once it runs out, the leader has to wait before claiming the lease again, and anyone else
+        // can claim it in the meantime.
+        vm.roll(tenureEnd);
+        vm.prank(leader);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.TenureOver.selector, claimableAt));
+        rollup.claimSubmitter(maxLease);
+        vm.prank(follower);
+        rollup.claimSubmitter(10);
+        assertEq(rollup.currentSubmitter(), follower);
+        assertEq(rollup.leaseStart(), block.number);
+
+        // A new tenure starts when the follower lets the lease run out.
+        vm.roll(claimableAt);
+        vm.prank(leader);
+        rollup.claimSubmitter(10);
+        assertEq(rollup.currentSubmitter(), leader);
+    }
+
+    function testSetSubmitter() public {
+        address operator = address(0x1);
+        address replacement = address(0x2);
+        ExampleRollup restricted = new ExampleRollup(address(hotshot), 0, 0, operator);
+        assertEq(restricted.owner(), address(this));
+        assertEq(restricted.authorizedSubmitter(), operator);
+
+        HotShot.QC[] memory qcs = new HotShot.QC[](1);
+        qcs[0].blockCommitment = 576467464341;
+        qcs[0].height = 0;
+        hotshot.newBlocks(qcs);
+        ExampleRollup.BatchProof memory proof =
+            ExampleRollup.BatchProof({firstBlock: 0, lastBlock: 0, oldState: 0, newState: 523123});
+
+        // Only the authorized submitter may claim the lease or submit proofs.
+        vm.prank(replacement);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotAuthorized.selector, operator));
+        restricted.claimSubmitter(10);
+        vm.prank(replacement);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotAuthorized.selector, operator));
+        restricted.verifyBlocks(0, 1, proof.newState, proof);
+        vm.prank(operator);
+        restricted.claimSubmitter(10);
+
+        // Only the owner may rotate the submitter.
+        vm.prank(operator);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotOwner.selector, address(this)));
+        restricted.setSubmitter(operator);
+
+        // Rotating revokes the lease of the old submitter, which can no longer submit.
+        restricted.setSubmitter(replacement);
+        assertEq(restricted.currentSubmitter(), address(0));
+        vm.prank(operator);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotAuthorized.selector, replacement));
+        restricted.verifyBlocks(0, 1, proof.newState, proof);
+        vm.prank(replacement);
+        restricted.verifyBlocks(0, 1, proof.newState, proof);
+        assertEq(restricted.numVerifiedBlocks(), 1);
+    }
+
+    function testRotateOwner() public {
+        address newOwner = address(0x1);
+        address operator = address(0x2);
+        address replacement = address(0x3);
+        ExampleRollup restricted = new ExampleRollup(address(hotshot), 0, 0, operator);
+
+        // Only the owner may hand ownership on.
+        vm.prank(newOwner);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotOwner.selector, address(this)));
+        restricted.setOwner(newOwner);
+        vm.expectEmit(false, false, false, true, address(restricted));
+        emit OwnerChanged(newOwner);
+        restricted.setOwner(newOwner);
+        assertEq(restricted.owner(), newOwner);
+
+        // The old owner can no longer rotate the submitter or the owner.
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotOwner.selector, newOwner));
+        restricted.setSubmitter(replacement);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotOwner.selector, newOwner));
+        restricted.setOwner(address(this));
+
+        // The new owner can rotate the submitter, which revokes the old submitter's lease.
+        vm.prank(operator);
+        restricted.claimSubmitter(10);
+        vm.expectEmit(false, false, false, true, address(restricted));
+        emit SubmitterAuthorized(replacement);
+        vm.prank(newOwner);
+        restricted.setSubmitter(replacement);
+        assertEq(restricted.authorizedSubmitter(), replacement);
+        assertEq(restricted.currentSubmitter(), address(0));
+        vm.prank(operator);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotAuthorized.selector, replacement));
+        restricted.claimSubmitter(10);
+        vm.prank(replacement);
+        restricted.claimSubmitter(10);
+        assertEq(restricted.currentSubmitter(), replacement);
+
+        // Giving up ownership leaves the submitter fixed for good.
+        vm.prank(newOwner);
+        restricted.setOwner(address(0));
+        vm.prank(newOwner);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotOwner.selector, address(0)));
+        restricted.setSubmitter(operator);
+        assertEq(restricted.authorizedSubmitter(), replacement);
+    }
+
+    function testDepositERC20() public {
+        ExampleToken token = new ExampleToken();
+        address depositor = address(0x1);
+        address recipient = address(0x2);
+        token.mint(depositor, 100);
+
+        // Without an allowance the transfer is refused.
+        vm.prank(depositor);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.DepositFailed.selector, address(token)));
+        rollup.depositERC20(address(token), 60, recipient);
+
+        vm.prank(depositor);
+        token.approve(address(rollup), 60);
+        vm.expectEmit(false, false, false, true, address(rollup));
+        emit Deposit(address(token), depositor, recipient, 60);
+        vm.prank(depositor);
+        rollup.depositERC20(address(token), 60, recipient);
+
+        assertEq(token.balanceOf(depositor), 40);
+        assertEq(token.balanceOf(address(rollup)), 60);
+    }
+
+    function testDepositNonStandardERC20() public {
+        address depositor = address(0x1);
+        address recipient = address(0x2);
+
+        // A token whose `transferFrom` returns nothing is accepted, and one which reverts is refused.
+        NonStandardToken token = new NonStandardToken(0);
+        token.mint(depositor, 100);
+        vm.prank(depositor);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.DepositFailed.selector, address(token)));
+        rollup.depositERC20(address(token), 60, recipient);
+
+        vm.prank(depositor);
+        token.approve(address(rollup), 60);
+        vm.expectEmit(false, false, false, true, address(rollup));
+        emit Deposit(address(token), depositor, recipient, 60);
+        vm.prank(depositor);
+        rollup.depositERC20(address(token), 60, recipient);
+        assertEq(token.balanceOf(address(rollup)), 60);
+
+        // A deposit of a token which charges a fee on transfer is of what the rollup received.
+        NonStandardToken feeToken = new NonStandardToken(5);
+        feeToken.mint(depositor, 100);
+        vm.prank(depositor);
+        feeToken.approve(address(rollup), 60);
+        vm.expectEmit(false, false, false, true, address(rollup));
+        emit Deposit(address(feeToken), depositor, recipient, 55);
+        vm.prank(depositor);
+        rollup.depositERC20(address(feeToken), 60, recipient);
+        assertEq(feeToken.balanceOf(depositor), 40);
+        assertEq(feeToken.balanceOf(address(rollup)), 55);
+    }
+
+    function testDepositETH() public {
+        address depositor = address(0x1);
+        address recipient = address(0x2);
+        vm.deal(depositor, 100);
+
+        vm.expectEmit(false, false, false, true, address(rollup));
+        emit Deposit(address(0), depositor, recipient, 60);
+        vm.prank(depositor);
+        rollup.depositETH{value: 60}(recipient);
+
+        assertEq(depositor.balance, 40);
+        assertEq(address(rollup).balance, 60);
+    }
+
+    function testEnqueueTransaction() public {
+        address relayer = address(0x1);
+        bytes memory transaction = hex"deadbeef";
+
+        // Anyone may enqueue a transaction, and it is only logged.
+        vm.expectEmit(false, false, false, true, address(rollup));
+        emit TransactionEnqueued(relayer, transaction);
+        vm.prank(relayer);
+        rollup.enqueueTransaction(transaction);
+
+        uint256 maxSize = rollup.MAX_ENQUEUED_BYTES();
+        rollup.enqueueTransaction(new bytes(maxSize));
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.TransactionTooLarge.selector, maxSize + 1, maxSize));
+        rollup.enqueueTransaction(new bytes(maxSize + 1));
+    }
+
+    function testOptimisticFinality() public {
+        ExampleRollup optimistic = new ExampleRollup(address(hotshot), 0, 100, address(0));
+        HotShot.QC[] memory qcs = new HotShot.QC[](2);
+        qcs[0].blockCommitment = 576467464341;
+        qcs[0].height = 0;
+        qcs[1].blockCommitment = 576467464342;
+        qcs[1].height = 1;
+        hotshot.newBlocks(qcs);
+
+        // Updates are accepted at once, but are not final.
+        ExampleRollup.BatchProof memory proof = ExampleRollup.BatchProof({
+            firstBlock: qcs[0].blockCommitment,
+            lastBlock: qcs[0].blockCommitment,
+            oldState: 0,
+            newState: 523123
+        });
+        optimistic.verifyBlocks(0, 1, proof.newState, proof);
+        assertEq(optimistic.numVerifiedBlocks(), 1);
+        assertEq(optimistic.numFinalizedBlocks(), 0);
+        uint256 firstWindowEnd = block.timestamp + 100;
+
+        vm.warp(block.timestamp + 50);
+        proof = ExampleRollup.BatchProof({
+            firstBlock: qcs[1].blockCommitment,
+            lastBlock: qcs[1].blockCommitment,
+            oldState: 523123,
+            newState: 523124
+        });
+        optimistic.verifyBlocks(1, 1, proof.newState, proof);
+
+        // Neither can be finalized while its window is open, and a challenge of either is rejected.
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotFinalizable.selector, 1, firstWindowEnd));
+        optimistic.finalizeBlock(1);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.InvalidFraudProof.selector, 1));
+        optimistic.challenge(1, "");
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotPending.selector, 3));
+        optimistic.finalizeBlock(3);
+
+        // Once the first window closes, the first update is final, and only the second is pending.
+        vm.warp(firstWindowEnd);
+        vm.expectEmit(false, false, false, true, address(optimistic));
+        emit BlocksFinalized(1);
+        optimistic.finalizeBlock(1);
+        assertEq(optimistic.numFinalizedBlocks(), 1);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotPending.selector, 1));
+        optimistic.challenge(1, "");
+
+        vm.warp(firstWindowEnd + 50);
+        optimistic.finalizeBlock(2);
+        assertEq(optimistic.numFinalizedBlocks(), 2);
+    }
+
+    function testValidityFinality() public {
+        HotShot.QC[] memory qcs = new HotShot.QC[](1);
+        qcs[0].blockCommitment = 576467464341;
+        qcs[0].height = 0;
+        hotshot.newBlocks(qcs);
+
+        // Without a challenge window, a verified update is final at once.
+        ExampleRollup.BatchProof memory proof = ExampleRollup.BatchProof({
+            firstBlock: qcs[0].blockCommitment,
+            lastBlock: qcs[0].blockCommitment,
+            oldState: 0,
+            newState: 523123
+        });
+        rollup.verifyBlocks(0, 1, proof.newState, proof);
+        assertEq(rollup.numFinalizedBlocks(), 1);
+        vm.expectRevert(abi.encodeWithSelector(ExampleRollup.NotPending.selector, 1));
+        rollup.finalizeBlock(1);
     }
 }
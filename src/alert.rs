@@ -0,0 +1,264 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Alerts raised by the executor when the rollup may be in danger.
+//!
+//! Conditions an operator must act on, such as a block which does not match its commitment on L1
+//! or a state update the executor did not send, are raised as typed [`Alert`]s to every
+//! [`AlertSink`] in [`Alerts`]: the log, the [`AlertBuffer`] served by the API under
+//! `admin/alerts`, and webhooks subscribed to the `alert` event. Sinks must not block, and one
+//! which panics is logged and otherwise ignored, so raising an alert never holds up or fails the
+//! executor.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+use crate::logging::EXECUTOR;
+
+/// Number of alerts kept by an [`AlertBuffer`], by default.
+pub const DEFAULT_ALERT_BUFFER_CAPACITY: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    /// Something needs looking into, but the executor carries on.
+    Warning,
+    /// The executor cannot safely continue, or the rollup state is in question.
+    Critical,
+}
+
+/// The condition an alert reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlertKind {
+    /// A HotShot block does not match the commitment posted for it on L1.
+    CommitmentMismatch,
+    /// The rollup contract holds a different state than the one this executor computed.
+    DivergedFromContract,
+    /// The rollup contract accepted a state update which this executor did not send.
+    UnexpectedStateUpdate,
+    /// The executor stopped on an error it cannot be restarted after.
+    ExecutorHalted,
+    /// The rollup has stayed further behind the HotShot contract, or the rollup contract has
+    /// stayed further behind the rollup, than the executor's threshold for a while.
+    Lagging,
+    /// A block took longer to execute than the executor's hard limit, and was quarantined.
+    BlockQuarantined,
+    /// The state after a block does not have one of the [invariants](crate::invariants) every
+    /// state must have.
+    InvariantViolated,
+    /// A deposit the rollup has credited is no longer on L1 after a reorg.
+    DepositReorged,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alert {
+    pub severity: Severity,
+    pub kind: AlertKind,
+    /// Rollup block height the alert concerns, if any.
+    pub height: Option<u64>,
+    /// What happened, for a human.
+    pub details: String,
+}
+
+impl Display for Alert {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} alert {:?}", self.severity, self.kind)?;
+        if let Some(height) = self.height {
+            write!(f, " at height {height}")?;
+        }
+        write!(f, ": {}", self.details)
+    }
+}
+
+/// Somewhere alerts are sent.
+pub trait AlertSink: Send + Sync {
+    /// Deliver `alert`. This is called from the executor, so it must return promptly; sinks which
+    /// do I/O should hand the alert off to a task of their own.
+    fn emit(&self, alert: &Alert);
+
+    /// Name of the sink in logs.
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+}
+
+/// Logs each alert, as an error if it is critical and a warning otherwise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogSink;
+
+impl AlertSink for LogSink {
+    fn emit(&self, alert: &Alert) {
+        match alert.severity {
+            Severity::Critical => tracing::error!(target: EXECUTOR, "{alert}"),
+            Severity::Warning => tracing::warn!(target: EXECUTOR, "{alert}"),
+        }
+    }
+
+    fn name(&self) -> String {
+        "log".into()
+    }
+}
+
+/// The latest alerts, kept in memory for the API to serve. Once full, each new alert evicts the
+/// oldest one.
+#[derive(Clone, Debug)]
+pub struct AlertBuffer {
+    alerts: Arc<Mutex<VecDeque<Alert>>>,
+    capacity: usize,
+}
+
+impl Default for AlertBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_ALERT_BUFFER_CAPACITY)
+    }
+}
+
+impl AlertBuffer {
+    /// A buffer keeping the last `capacity` alerts.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            alerts: Default::default(),
+            capacity,
+        }
+    }
+
+    /// The alerts kept, the oldest first.
+    pub fn recent(&self) -> Vec<Alert> {
+        self.lock().iter().cloned().collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Alert>> {
+        // A panic while the lock was held cannot leave the queue inconsistent.
+        self.alerts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl AlertSink for AlertBuffer {
+    fn emit(&self, alert: &Alert) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut alerts = self.lock();
+        while alerts.len() >= self.capacity {
+            alerts.pop_front();
+        }
+        alerts.push_back(alert.clone());
+    }
+
+    fn name(&self) -> String {
+        "buffer".into()
+    }
+}
+
+/// The sinks alerts are raised to.
+#[derive(Clone)]
+pub struct Alerts {
+    sinks: Vec<Arc<dyn AlertSink>>,
+}
+
+/// Alerts are logged, and nothing more.
+impl Default for Alerts {
+    fn default() -> Self {
+        Self::new().with(LogSink)
+    }
+}
+
+impl Debug for Alerts {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Alerts")
+            .field(
+                "sinks",
+                &self
+                    .sinks
+                    .iter()
+                    .map(|sink| sink.name())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Alerts {
+    /// No sinks at all, not even the log.
+    pub fn new() -> Self {
+        Self { sinks: vec![] }
+    }
+
+    /// Add a sink, to receive alerts after the ones already added.
+    pub fn with(mut self, sink: impl AlertSink + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Send an alert to every sink.
+    pub fn raise(
+        &self,
+        severity: Severity,
+        kind: AlertKind,
+        height: Option<u64>,
+        details: impl Into<String>,
+    ) {
+        let alert = Alert {
+            severity,
+            kind,
+            height,
+            details: details.into(),
+        };
+        for sink in &self.sinks {
+            if catch_unwind(AssertUnwindSafe(|| sink.emit(&alert))).is_err() {
+                tracing::warn!(
+                    target: EXECUTOR,
+                    "alert sink {} panicked on {alert}",
+                    sink.name()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Panicking;
+
+    impl AlertSink for Panicking {
+        fn emit(&self, _alert: &Alert) {
+            panic!("sink failed");
+        }
+    }
+
+    #[test]
+    fn test_alert_buffer() {
+        let buffer = AlertBuffer::new(2);
+        let alerts = Alerts::default().with(Panicking).with(buffer.clone());
+        for height in 0..3 {
+            alerts.raise(
+                Severity::Critical,
+                AlertKind::CommitmentMismatch,
+                Some(height),
+                "block does not match",
+            );
+        }
+
+        // A failing sink does not keep the alert from the others, and only the latest alerts are
+        // kept.
+        let recent = buffer.recent();
+        assert_eq!(
+            recent.iter().map(|alert| alert.height).collect::<Vec<_>>(),
+            [Some(1), Some(2)]
+        );
+        assert_eq!(recent[0].kind, AlertKind::CommitmentMismatch);
+        assert_eq!(
+            recent[0].to_string(),
+            "Critical alert CommitmentMismatch at height 1: block does not match"
+        );
+    }
+}
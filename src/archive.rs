@@ -0,0 +1,886 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Self-contained archives of a range of the rollup's history.
+//!
+//! A node only keeps the history of recent blocks, as its [retention](crate::retention) allows,
+//! and a node started from scratch has none at all. An archive carries a range of blocks from one
+//! node to another, or into cold storage, without the sequencer or L1: [`export`] writes the
+//! summary, receipts and proof of every block in the range, the snapshots of the states in the
+//! range, and the balance changes between those snapshots, all taken from the snapshots of a node.
+//!
+//! An archive is a directory:
+//!
+//! * `manifest.json`: the [`Manifest`], with the size and SHA-256 digest of every other file;
+//! * `blocks.jsonl`: an [`ArchivedBlock`] per line, by height;
+//! * `diffs.jsonl`: an [`ArchivedDiff`] per line, from each archived snapshot to the next;
+//! * `snapshots/state-{height}.json`: the snapshots, in the format of the
+//!   [snapshot directory](crate::snapshot).
+//!
+//! [`read_archive`] checks every file against the manifest, and that the archive is one chain of
+//! states: the proof of each block starts from the state the block before it ended with, and each
+//! snapshot is the state its height ended with. That shows the archive is consistent, not that it
+//! is the rollup's history, so [`Archive::check_anchor`] also checks the end of the chain against
+//! a root the importer trusts, or against the state updates in the rollup contract. [`import`]
+//! then writes the snapshots up to the anchored height to a node's snapshot directory, filling in
+//! the history of blocks they lack. The rest of the archive is only known to be consistent, so it
+//! is left out. Importing the same or overlapping archives again changes nothing already imported.
+
+use commit::{Commitment, Committable};
+use contract_bindings::example_rollup::ExampleRollup;
+use ethers::types::{Address, U256};
+use sequencer::Vm;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use snafu::Snafu;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use surf_disco::Url;
+
+use crate::build_info::BuildInfo;
+use crate::commitments::commitment_to_u256;
+use crate::logging::STATE;
+use crate::prover::Proof;
+use crate::snapshot::{list_snapshots, load_snapshot, snapshot_name, store_snapshot};
+use crate::state::{BalanceChange, BlockResult, State};
+use crate::utils::create_provider;
+
+/// Version of the archive format written by [`export`]. Archives of any other version are
+/// refused.
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const BLOCKS: &str = "blocks.jsonl";
+const DIFFS: &str = "diffs.jsonl";
+const SNAPSHOTS: &str = "snapshots";
+
+/// The contents of an archive.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub vm_id: u64,
+    /// Height of the first block archived.
+    pub from: u64,
+    /// Height after the last block archived. The archive ends with the state after `to` blocks.
+    pub to: u64,
+    /// Heights of the archived snapshots, ascending. The last is always `to`.
+    pub snapshots: Vec<u64>,
+    /// Every other file in the archive, by path relative to the archive.
+    pub files: BTreeMap<String, FileChecksum>,
+    /// The build of the node which wrote the archive.
+    pub build: BuildInfo,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChecksum {
+    pub bytes: u64,
+    /// SHA-256 digest of the file, in hex.
+    pub sha256: String,
+}
+
+impl FileChecksum {
+    fn of(contents: &[u8]) -> Self {
+        Self {
+            bytes: contents.len() as u64,
+            sha256: hex::encode(Sha256::digest(contents)),
+        }
+    }
+}
+
+/// An archived block: its summary and receipts, and its proof.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchivedBlock {
+    #[serde(flatten)]
+    pub result: BlockResult,
+    pub proof: Proof,
+}
+
+/// The balances which changed between two archived snapshots.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedDiff {
+    pub from: u64,
+    pub to: u64,
+    pub changes: Vec<BalanceChange>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ArchiveError {
+    #[snafu(display("Nothing to archive from height {from} to height {to}."))]
+    EmptyRange { from: u64, to: u64 },
+    #[snafu(display(
+        "No valid snapshot at height {height} in {dir:?}. Snapshots are retained at heights \
+        {available:?}."
+    ))]
+    NoSnapshot {
+        dir: PathBuf,
+        height: u64,
+        available: Vec<u64>,
+    },
+    #[snafu(display(
+        "No snapshot holds the summary, receipts and proof of block {height}. Raise the retention \
+        of the history to archive it."
+    ))]
+    Incomplete { height: u64 },
+    #[snafu(display("{path:?} is not empty."))]
+    OutputNotEmpty { path: PathBuf },
+    #[snafu(display("{path:?}: {source}"))]
+    Io { path: PathBuf, source: io::Error },
+    #[snafu(display("{file} is malformed: {reason}"))]
+    Malformed { file: String, reason: String },
+    #[snafu(display(
+        "Archive format version {version} is not supported. This version reads version \
+        {ARCHIVE_FORMAT_VERSION}."
+    ))]
+    UnsupportedVersion { version: u32 },
+    #[snafu(display("{file} is missing from the archive."))]
+    Missing { file: String },
+    #[snafu(display("{file} does not match its checksum in the manifest."))]
+    Checksum { file: String },
+    #[snafu(display("Block {height} does not continue from the state before it."))]
+    Discontinuous { height: u64 },
+    #[snafu(display("The snapshot at height {height} is not the state the blocks end with."))]
+    SnapshotMismatch { height: u64 },
+    #[snafu(display(
+        "The state after {height} blocks has commitment {archived:#x}, but the anchor has \
+        {anchor:#x}."
+    ))]
+    AnchorMismatch {
+        height: u64,
+        archived: U256,
+        anchor: U256,
+    },
+    #[snafu(display(
+        "The rollup contract has no state update at heights {from}-{to} to check the archive \
+        against."
+    ))]
+    NotAnchored { from: u64, to: u64 },
+    #[snafu(display("Unable to read the state updates of the rollup contract: {reason}"))]
+    Contract { reason: String },
+    #[snafu(display(
+        "{path:?} holds a different state at height {height} than the archive. Nothing was \
+        imported."
+    ))]
+    Conflict { path: PathBuf, height: u64 },
+}
+
+/// Archive blocks `from` up to `to` from the snapshots in `snapshot_dir`, writing the archive to
+/// the directory `out`, which must not exist or be empty.
+///
+/// The archive ends with the snapshot at `to`, which must be retained, and includes every other
+/// retained snapshot in the range. The history of each block is taken from the oldest snapshot
+/// after it which still holds its summary, receipts and proof.
+pub fn export(
+    snapshot_dir: &Path,
+    from: u64,
+    to: u64,
+    out: &Path,
+) -> Result<Manifest, ArchiveError> {
+    if from >= to {
+        return Err(ArchiveError::EmptyRange { from, to });
+    }
+    let listed = list_snapshots(snapshot_dir).map_err(|source| ArchiveError::Io {
+        path: snapshot_dir.into(),
+        source,
+    })?;
+    let mut snapshots = vec![];
+    for (height, path) in &listed {
+        if !(from..=to).contains(height) {
+            continue;
+        }
+        match load_snapshot(path) {
+            Ok(state) if state.block_height() == *height => snapshots.push(state),
+            Ok(_) => tracing::warn!(target: STATE, "Skipping misnamed snapshot {path:?}"),
+            Err(reason) => {
+                tracing::warn!(target: STATE, "Skipping invalid snapshot {path:?}: {reason}")
+            }
+        }
+    }
+    let Some(last) = snapshots.last().filter(|state| state.block_height() == to) else {
+        return Err(ArchiveError::NoSnapshot {
+            dir: snapshot_dir.into(),
+            height: to,
+            available: listed.iter().map(|(height, _)| *height).collect(),
+        });
+    };
+    let vm = last.vm;
+    snapshots.retain(|state| {
+        let same_vm = state.vm.id() == vm.id();
+        if !same_vm {
+            tracing::warn!(
+                target: STATE,
+                "Skipping snapshot at height {} of another VM",
+                state.block_height()
+            );
+        }
+        same_vm
+    });
+
+    let mut blocks = vec![];
+    for height in from..to {
+        let block = snapshots
+            .iter()
+            .filter(|state| state.block_height() > height)
+            .find_map(|state| {
+                Some(ArchivedBlock {
+                    result: BlockResult {
+                        summary: state.block_summary(height)?.clone(),
+                        transactions: state.block_receipts(height)?.to_vec(),
+                    },
+                    proof: state.proof(height)?.clone(),
+                })
+            })
+            .ok_or(ArchiveError::Incomplete { height })?;
+        blocks.push(block);
+    }
+    let diffs = snapshots
+        .windows(2)
+        .map(|pair| ArchivedDiff {
+            from: pair[0].block_height(),
+            to: pair[1].block_height(),
+            changes: pair[0].balance_changes(&pair[1]),
+        })
+        .collect::<Vec<_>>();
+
+    let io_err = |path: &Path| {
+        let path = path.to_owned();
+        move |source| ArchiveError::Io { path, source }
+    };
+    match fs::read_dir(out) {
+        Ok(mut entries) => {
+            if entries.next().is_some() {
+                return Err(ArchiveError::OutputNotEmpty { path: out.into() });
+            }
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(source) => {
+            return Err(ArchiveError::Io {
+                path: out.into(),
+                source,
+            })
+        }
+    }
+    fs::create_dir_all(out.join(SNAPSHOTS)).map_err(io_err(out))?;
+
+    let mut files = BTreeMap::new();
+    let mut write = |file: String, contents: Vec<u8>| -> Result<(), ArchiveError> {
+        let path = out.join(&file);
+        fs::write(&path, &contents).map_err(io_err(&path))?;
+        files.insert(file, FileChecksum::of(&contents));
+        Ok(())
+    };
+    write(BLOCKS.into(), json_lines(&blocks))?;
+    write(DIFFS.into(), json_lines(&diffs))?;
+    for state in &snapshots {
+        let file = format!("{SNAPSHOTS}/{}", snapshot_name(state.block_height()));
+        let path = out.join(&file);
+        store_snapshot(&path, state).map_err(io_err(&path))?;
+        let contents = fs::read(&path).map_err(io_err(&path))?;
+        write(file, contents)?;
+    }
+
+    // The manifest is written last, so an archive whose export failed has none, and is refused.
+    let manifest = Manifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        vm_id: vm.id().into(),
+        from,
+        to,
+        snapshots: snapshots.iter().map(State::block_height).collect(),
+        files,
+        build: BuildInfo::current(),
+    };
+    let path = out.join(MANIFEST);
+    let contents = serde_json::to_vec_pretty(&manifest).expect("manifest serializes");
+    fs::write(&path, contents).map_err(io_err(&path))?;
+    Ok(manifest)
+}
+
+fn json_lines<T: Serialize>(items: &[T]) -> Vec<u8> {
+    let mut contents = vec![];
+    for item in items {
+        serde_json::to_writer(&mut contents, item).expect("archived items serialize");
+        contents.push(b'\n');
+    }
+    contents
+}
+
+/// An archive read by [`read_archive`], and found consistent.
+#[derive(Clone, Debug)]
+pub struct Archive {
+    pub manifest: Manifest,
+    pub blocks: Vec<ArchivedBlock>,
+    pub diffs: Vec<ArchivedDiff>,
+    /// The archived snapshots, in the order of their heights.
+    pub snapshots: Vec<State>,
+}
+
+/// What to check the end of an archive against before importing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    /// The commitment to the state the archive ends with, from a source the importer trusts.
+    TrustedRoot(U256),
+    /// The state commitments in the rollup contract, by the number of blocks they follow, as
+    /// fetched by [`contract_anchor`].
+    Contract(BTreeMap<u64, U256>),
+}
+
+/// Fetch every state update in the rollup contract at `rollup_address`, to check archives against.
+pub async fn contract_anchor(
+    l1_provider: &Url,
+    rollup_address: Address,
+) -> Result<Anchor, ArchiveError> {
+    let rollup_contract =
+        ExampleRollup::new(rollup_address, Arc::new(create_provider(l1_provider)));
+    let updates = rollup_contract
+        .state_update_filter()
+        .from_block(0u64)
+        .query()
+        .await
+        .map_err(|err| ArchiveError::Contract {
+            reason: err.to_string(),
+        })?;
+    Ok(Anchor::Contract(
+        updates
+            .into_iter()
+            .map(|update| (update.block_height.as_u64(), update.state_commitment))
+            .collect(),
+    ))
+}
+
+/// Read the archive in `dir`, checking every file against the manifest and that the blocks and
+/// snapshots form one chain of states.
+pub fn read_archive(dir: &Path) -> Result<Archive, ArchiveError> {
+    let malformed = |file: &str| {
+        let file = file.to_owned();
+        move |err: serde_json::Error| ArchiveError::Malformed {
+            file,
+            reason: err.to_string(),
+        }
+    };
+    let path = dir.join(MANIFEST);
+    let contents = fs::read(&path).map_err(|source| match source.kind() {
+        io::ErrorKind::NotFound => ArchiveError::Missing {
+            file: MANIFEST.into(),
+        },
+        _ => ArchiveError::Io { path, source },
+    })?;
+    let manifest: Manifest = serde_json::from_slice(&contents).map_err(malformed(MANIFEST))?;
+    if manifest.format_version != ARCHIVE_FORMAT_VERSION {
+        return Err(ArchiveError::UnsupportedVersion {
+            version: manifest.format_version,
+        });
+    }
+    if manifest.from >= manifest.to || manifest.snapshots.last() != Some(&manifest.to) {
+        return Err(ArchiveError::Malformed {
+            file: MANIFEST.into(),
+            reason: "the archive does not end with a snapshot after its last block".into(),
+        });
+    }
+
+    let mut contents = BTreeMap::new();
+    for (file, checksum) in &manifest.files {
+        // The manifest names files within the archive, and nothing else.
+        let safe = Path::new(file)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !safe {
+            return Err(ArchiveError::Malformed {
+                file: MANIFEST.into(),
+                reason: format!("{file:?} is not a path within the archive"),
+            });
+        }
+        let path = dir.join(file);
+        let bytes = fs::read(&path).map_err(|source| match source.kind() {
+            io::ErrorKind::NotFound => ArchiveError::Missing { file: file.clone() },
+            _ => ArchiveError::Io { path, source },
+        })?;
+        if FileChecksum::of(&bytes) != *checksum {
+            return Err(ArchiveError::Checksum { file: file.clone() });
+        }
+        contents.insert(file.as_str(), bytes);
+    }
+    let file = |name: &str| {
+        contents
+            .get(name)
+            .ok_or_else(|| ArchiveError::Missing { file: name.into() })
+    };
+
+    let blocks = serde_json::Deserializer::from_slice(file(BLOCKS)?)
+        .into_iter::<ArchivedBlock>()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(malformed(BLOCKS))?;
+    let diffs = serde_json::Deserializer::from_slice(file(DIFFS)?)
+        .into_iter::<ArchivedDiff>()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(malformed(DIFFS))?;
+
+    // Each block starts from the state the block before it ended with.
+    let heights = blocks.iter().map(|block| block.result.summary.height);
+    if !heights.eq(manifest.from..manifest.to) {
+        return Err(ArchiveError::Malformed {
+            file: BLOCKS.into(),
+            reason: format!("expected blocks {}-{}", manifest.from, manifest.to - 1),
+        });
+    }
+    let mut prev = blocks[0].proof.old_state;
+    for block in &blocks {
+        if block.proof.old_state != prev
+            || block.proof.new_state != block.result.summary.state_commitment
+        {
+            return Err(ArchiveError::Discontinuous {
+                height: block.result.summary.height,
+            });
+        }
+        prev = block.proof.new_state;
+    }
+
+    // Each snapshot is the state its height ended with.
+    let mut snapshots = vec![];
+    for height in &manifest.snapshots {
+        let file = format!("{SNAPSHOTS}/{}", snapshot_name(*height));
+        // Snapshots are loaded from disk again, so they must be among the files checked above.
+        if !contents.contains_key(file.as_str()) {
+            return Err(ArchiveError::Missing { file });
+        }
+        let state = load_snapshot(&dir.join(&file))
+            .map_err(|reason| ArchiveError::Malformed { file, reason })?;
+        let vm_id: u64 = state.vm.id().into();
+        if state.block_height() != *height
+            || vm_id != manifest.vm_id
+            || Some(state.commit()) != commitment_at(&manifest, &blocks, *height)
+        {
+            return Err(ArchiveError::SnapshotMismatch { height: *height });
+        }
+        snapshots.push(state);
+    }
+
+    // The balance changes are derived from the snapshots, so they must be exactly those.
+    let derived = snapshots.windows(2).map(|pair| ArchivedDiff {
+        from: pair[0].block_height(),
+        to: pair[1].block_height(),
+        changes: pair[0].balance_changes(&pair[1]),
+    });
+    if !derived.eq(diffs.iter().cloned()) {
+        return Err(ArchiveError::Malformed {
+            file: DIFFS.into(),
+            reason: "the balance changes do not match the snapshots".into(),
+        });
+    }
+
+    Ok(Archive {
+        manifest,
+        blocks,
+        diffs,
+        snapshots,
+    })
+}
+
+/// Commitment to the state after `height` blocks in the chain of `blocks`, if the archive covers
+/// that height.
+fn commitment_at(
+    manifest: &Manifest,
+    blocks: &[ArchivedBlock],
+    height: u64,
+) -> Option<Commitment<State>> {
+    if height == manifest.from {
+        return blocks.first().map(|block| block.proof.old_state);
+    }
+    let index = height.checked_sub(manifest.from + 1)?;
+    blocks
+        .get(index as usize)
+        .map(|block| block.result.summary.state_commitment)
+}
+
+impl Archive {
+    /// Commitment to the state after `height` blocks, if the archive covers that height.
+    pub fn commitment(&self, height: u64) -> Option<Commitment<State>> {
+        commitment_at(&self.manifest, &self.blocks, height)
+    }
+
+    /// Check the archive against `anchor`, returning the highest height at which they agree.
+    ///
+    /// A trusted root must be the commitment to the state the archive ends with. Every state
+    /// update in the rollup contract after the first block archived must match the archive, and
+    /// there must be at least one.
+    pub fn check_anchor(&self, anchor: &Anchor) -> Result<u64, ArchiveError> {
+        let (from, to) = (self.manifest.from, self.manifest.to);
+        let archived = |height| commitment_to_u256(self.commitment(height).unwrap());
+        match anchor {
+            Anchor::TrustedRoot(root) => {
+                if archived(to) != *root {
+                    return Err(ArchiveError::AnchorMismatch {
+                        height: to,
+                        archived: archived(to),
+                        anchor: *root,
+                    });
+                }
+                Ok(to)
+            }
+            Anchor::Contract(updates) => {
+                let mut anchored = None;
+                for (height, commitment) in updates.range(from + 1..=to) {
+                    if archived(*height) != *commitment {
+                        return Err(ArchiveError::AnchorMismatch {
+                            height: *height,
+                            archived: archived(*height),
+                            anchor: *commitment,
+                        });
+                    }
+                    anchored = Some(*height);
+                }
+                anchored.ok_or(ArchiveError::NotAnchored { from: from + 1, to })
+            }
+        }
+    }
+}
+
+/// The snapshots an [`import`] wrote, those it found already complete, and those it left out
+/// because they are above the anchored height.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub written: Vec<u64>,
+    pub unchanged: Vec<u64>,
+    pub skipped: Vec<u64>,
+}
+
+/// Import `archive` into the snapshot directory `snapshot_dir` of a node, up to the height
+/// `anchored` which [`Archive::check_anchor`] returned.
+///
+/// Each archived snapshot up to `anchored` is written to the directory, with the summary, receipts
+/// and proof of every archived block before it which it lacks and has not pruned. A snapshot
+/// already in the directory at the same height must hold the same state, and is filled in the same
+/// way, and only written again if that changed anything. Nothing is written if any snapshot
+/// conflicts. Snapshots above `anchored` are skipped: the archive could hold any history there
+/// which is consistent with itself.
+pub fn import(
+    archive: &Archive,
+    anchored: u64,
+    snapshot_dir: &Path,
+) -> Result<ImportReport, ArchiveError> {
+    fs::create_dir_all(snapshot_dir).map_err(|source| ArchiveError::Io {
+        path: snapshot_dir.into(),
+        source,
+    })?;
+    let mut report = ImportReport::default();
+    let mut imports = vec![];
+    for archived in &archive.snapshots {
+        let height = archived.block_height();
+        if height > anchored {
+            report.skipped.push(height);
+            continue;
+        }
+        let path = snapshot_dir.join(snapshot_name(height));
+        let existing = if path.exists() {
+            match load_snapshot(&path) {
+                Ok(existing) if existing.commit() == archived.commit() => Some(existing),
+                Ok(_) => return Err(ArchiveError::Conflict { path, height }),
+                Err(reason) => {
+                    tracing::warn!(target: STATE, "Replacing invalid snapshot {path:?}: {reason}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        imports.push((path, existing, archived));
+    }
+
+    for (path, existing, archived) in imports {
+        let height = archived.block_height();
+        let mut changed = existing.is_none();
+        let mut state = existing.unwrap_or_else(|| archived.clone());
+        for block in &archive.blocks {
+            let block_height = block.result.summary.height;
+            changed |= state.backfill_block(&block.result);
+            changed |= state.backfill_proof(block_height, &block.proof);
+        }
+        if changed {
+            store_snapshot(&path, &state).map_err(|source| ArchiveError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            report.written.push(height);
+        } else {
+            report.unchanged.push(height);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::write_snapshot;
+    use crate::transaction::{SignedTransaction, Transaction, DEFAULT_VM_ID};
+    use crate::RollupVM;
+    use ethers::signers::{LocalWallet, Signer};
+    use tempfile::TempDir;
+
+    /// A snapshot directory holding the states after 0, 2, 4 and 6 blocks, in each of which alice
+    /// pays bob.
+    async fn snapshots() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let alice = LocalWallet::new(&mut rand::thread_rng());
+        let bob = Address::random();
+        let mut state =
+            State::from_initial_balances([(alice.address(), 100)], RollupVM::new(1.into()));
+        write_snapshot(dir.path(), &state, 10).unwrap();
+        for nonce in 1..=6 {
+            let transfer = SignedTransaction::new_with_wallet(
+                Transaction {
+                    amount: nonce,
+                    destination: bob,
+                    nonce,
+                    priority: 0,
+                    vm_id: DEFAULT_VM_ID,
+                    asset: None,
+                },
+                &alice,
+            )
+            .await;
+            let result = state.execute_transactions(vec![transfer.into()]);
+            assert_eq!(result.summary.payloads.applied, 1);
+            if state.block_height() % 2 == 0 {
+                write_snapshot(dir.path(), &state, 10).unwrap();
+            }
+        }
+        dir
+    }
+
+    fn root(archive: &Archive) -> Anchor {
+        Anchor::TrustedRoot(commitment_to_u256(
+            archive.commitment(archive.manifest.to).unwrap(),
+        ))
+    }
+
+    #[async_std::test]
+    async fn test_round_trip() {
+        let source = snapshots().await;
+        let out = TempDir::new().unwrap();
+        let manifest = export(source.path(), 1, 4, out.path()).unwrap();
+        assert_eq!(manifest.snapshots, [2, 4]);
+        assert_eq!(manifest.files.len(), 4);
+
+        let archive = read_archive(out.path()).unwrap();
+        assert_eq!(archive.blocks.len(), 3);
+        assert_eq!(archive.diffs.len(), 1);
+        assert_eq!(archive.diffs[0].changes.len(), 2);
+        assert_eq!(archive.check_anchor(&root(&archive)).unwrap(), 4);
+
+        let node = TempDir::new().unwrap();
+        let report = import(&archive, 4, node.path()).unwrap();
+        assert_eq!(report.written, [2, 4]);
+        for height in [2, 4] {
+            let imported = load_snapshot(&node.path().join(snapshot_name(height))).unwrap();
+            let original = load_snapshot(&source.path().join(snapshot_name(height))).unwrap();
+            assert_eq!(imported.commit(), original.commit());
+            for block in 0..height {
+                assert_eq!(imported.block_summary(block), original.block_summary(block));
+                assert_eq!(imported.proof(block), original.proof(block));
+            }
+        }
+
+        // Importing the same archive again changes nothing.
+        let report = import(&archive, 4, node.path()).unwrap();
+        assert_eq!(report.unchanged, [2, 4]);
+    }
+
+    #[async_std::test]
+    async fn test_overlapping_imports() {
+        let source = snapshots().await;
+        let (first, second) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        export(source.path(), 0, 4, first.path()).unwrap();
+        export(source.path(), 2, 6, second.path()).unwrap();
+
+        let node = TempDir::new().unwrap();
+        let first = read_archive(first.path()).unwrap();
+        assert_eq!(import(&first, 4, node.path()).unwrap().written, [0, 2, 4]);
+        let second = read_archive(second.path()).unwrap();
+        let report = import(&second, 6, node.path()).unwrap();
+        assert_eq!(report.written, [6]);
+        assert_eq!(report.unchanged, [2, 4]);
+
+        let imported = list_snapshots(node.path()).unwrap();
+        assert_eq!(
+            imported
+                .iter()
+                .map(|(height, _)| *height)
+                .collect::<Vec<_>>(),
+            [0, 2, 4, 6]
+        );
+        let latest = load_snapshot(&imported[3].1).unwrap();
+        assert_eq!(latest.block_height(), 6);
+        assert!((0..6).all(|height| latest.block_receipts(height).is_some()));
+    }
+
+    #[async_std::test]
+    async fn test_export_errors() {
+        let source = snapshots().await;
+        let out = TempDir::new().unwrap();
+        assert!(matches!(
+            export(source.path(), 4, 4, out.path()),
+            Err(ArchiveError::EmptyRange { .. })
+        ));
+        assert!(matches!(
+            export(source.path(), 0, 5, out.path()),
+            Err(ArchiveError::NoSnapshot { height: 5, .. })
+        ));
+        fs::write(out.path().join("other"), "").unwrap();
+        assert!(matches!(
+            export(source.path(), 0, 4, out.path()),
+            Err(ArchiveError::OutputNotEmpty { .. })
+        ));
+    }
+
+    #[async_std::test]
+    async fn test_tampered_archive() {
+        let source = snapshots().await;
+        let out = TempDir::new().unwrap();
+        export(source.path(), 0, 4, out.path()).unwrap();
+        let blocks = out.path().join(BLOCKS);
+        let original = fs::read(&blocks).unwrap();
+
+        // Any change to a file is caught by its checksum.
+        let mut tampered = original.clone();
+        tampered[10] ^= 1;
+        fs::write(&blocks, &tampered).unwrap();
+        assert!(matches!(
+            read_archive(out.path()),
+            Err(ArchiveError::Checksum { file }) if file == BLOCKS
+        ));
+
+        // A change which also updates the manifest is caught by the chain of commitments.
+        let mut archived = serde_json::Deserializer::from_slice(&original)
+            .into_iter::<ArchivedBlock>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        archived[1].proof.old_state = archived[2].proof.old_state;
+        let tampered = json_lines(&archived);
+        let mut manifest: Manifest =
+            serde_json::from_slice(&fs::read(out.path().join(MANIFEST)).unwrap()).unwrap();
+        manifest
+            .files
+            .insert(BLOCKS.into(), FileChecksum::of(&tampered));
+        fs::write(&blocks, &tampered).unwrap();
+        fs::write(
+            out.path().join(MANIFEST),
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            read_archive(out.path()),
+            Err(ArchiveError::Discontinuous { height: 1 })
+        ));
+
+        // Paths outside the archive are refused.
+        manifest
+            .files
+            .insert("../outside".into(), FileChecksum::of(b""));
+        fs::write(
+            out.path().join(MANIFEST),
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            read_archive(out.path()),
+            Err(ArchiveError::Malformed { .. })
+        ));
+    }
+
+    #[async_std::test]
+    async fn test_anchor() {
+        let source = snapshots().await;
+        let out = TempDir::new().unwrap();
+        export(source.path(), 0, 4, out.path()).unwrap();
+        let archive = read_archive(out.path()).unwrap();
+        let commitment = |height| commitment_to_u256(archive.commitment(height).unwrap());
+
+        assert!(matches!(
+            archive.check_anchor(&Anchor::TrustedRoot(commitment(3))),
+            Err(ArchiveError::AnchorMismatch { height: 4, .. })
+        ));
+
+        // Updates the contract recorded within the archive are checked; others are not.
+        let updates = [(2, commitment(2)), (3, commitment(3)), (8, U256::zero())];
+        assert_eq!(
+            archive
+                .check_anchor(&Anchor::Contract(updates.into()))
+                .unwrap(),
+            3
+        );
+        let updates = [(2, commitment(3))];
+        assert!(matches!(
+            archive.check_anchor(&Anchor::Contract(updates.into())),
+            Err(ArchiveError::AnchorMismatch { height: 2, .. })
+        ));
+        let updates = [(0, commitment(0)), (5, U256::zero())];
+        assert!(matches!(
+            archive.check_anchor(&Anchor::Contract(updates.into())),
+            Err(ArchiveError::NotAnchored { from: 1, to: 4 })
+        ));
+    }
+
+    #[async_std::test]
+    async fn test_import_conflict() {
+        let source = snapshots().await;
+        let out = TempDir::new().unwrap();
+        export(source.path(), 0, 4, out.path()).unwrap();
+        let archive = read_archive(out.path()).unwrap();
+
+        // A node with a different state at one of the archived heights is left alone.
+        let node = TempDir::new().unwrap();
+        let other = State::from_initial_balances([(Address::random(), 1)], RollupVM::new(1.into()));
+        store_snapshot(&node.path().join(snapshot_name(0)), &other).unwrap();
+        assert!(matches!(
+            import(&archive, 4, node.path()),
+            Err(ArchiveError::Conflict { height: 0, .. })
+        ));
+        assert_eq!(list_snapshots(node.path()).unwrap().len(), 1);
+    }
+
+    #[async_std::test]
+    async fn test_import_unanchored_tail() {
+        // A forged archive which agrees with the real history up to height 2, and then goes its
+        // own way. It is still one consistent chain of states.
+        let source = snapshots().await;
+        let forged = TempDir::new().unwrap();
+        for height in [0, 2] {
+            fs::copy(
+                source.path().join(snapshot_name(height)),
+                forged.path().join(snapshot_name(height)),
+            )
+            .unwrap();
+        }
+        let mut state = load_snapshot(&source.path().join(snapshot_name(2))).unwrap();
+        state.execute_transactions(vec![]);
+        state.execute_transactions(vec![]);
+        write_snapshot(forged.path(), &state, 10).unwrap();
+        let out = TempDir::new().unwrap();
+        export(forged.path(), 0, 4, out.path()).unwrap();
+        let archive = read_archive(out.path()).unwrap();
+        let real = load_snapshot(&source.path().join(snapshot_name(4))).unwrap();
+        assert_ne!(archive.commitment(4), Some(real.commit()));
+
+        // The contract has only verified the real history up to height 2, so only that much of
+        // the archive is anchored, and the forged tail is left out of the import.
+        let real = load_snapshot(&source.path().join(snapshot_name(2))).unwrap();
+        let updates = [(2, commitment_to_u256(real.commit()))];
+        let anchored = archive
+            .check_anchor(&Anchor::Contract(updates.into()))
+            .unwrap();
+        assert_eq!(anchored, 2);
+        let node = TempDir::new().unwrap();
+        let report = import(&archive, anchored, node.path()).unwrap();
+        assert_eq!(report.written, [0, 2]);
+        assert_eq!(report.skipped, [4]);
+        assert!(!node.path().join(snapshot_name(4)).exists());
+        let imported = load_snapshot(&node.path().join(snapshot_name(2))).unwrap();
+        assert_eq!(imported.commit(), real.commit());
+        assert!(imported.block_summary(2).is_none());
+    }
+}
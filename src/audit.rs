@@ -0,0 +1,221 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Independent replay of a deployed rollup.
+//!
+//! Anyone can check the operator of a rollup by replaying every block HotShot sequenced for it and
+//! comparing the resulting state commitments with the ones the operator's proofs put in the rollup
+//! contract. Blocks are checked against their commitments in the HotShot contract and against each
+//! other, exactly as the executor checks them, and executed with [`State::apply_block`], picking up
+//! the same L1 deposits and forced transactions as the executor.
+
+use async_std::sync::Arc;
+use commit::Committable;
+use contract_bindings::example_rollup::ExampleRollup;
+use ethers::prelude::*;
+use hotshot_contract_bindings::hot_shot::HotShot;
+use sequencer::{api::endpoints::NamespaceProofQueryData, Header, Vm};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use surf_disco::Url;
+
+use crate::backfill::fetch_l1_inputs;
+use crate::chain::{BlockLink, ChainVerifier};
+use crate::commitments::{commitment_to_u256, matches_block_hash};
+use crate::error::ExecutorError;
+use crate::state::{Amount, BlockInputs, State};
+use crate::utils::create_provider;
+
+type HotShotClient = surf_disco::Client<hotshot_query_service::Error>;
+
+#[derive(Clone, Debug)]
+pub struct AuditOptions {
+    pub l1_provider: Url,
+    pub hotshot_address: Address,
+    pub rollup_address: Address,
+    /// URL of a HotShot query service.
+    pub sequencer_url: Url,
+    /// Stop after this many blocks. By default, every block verified by the rollup contract is
+    /// replayed.
+    pub until: Option<u64>,
+}
+
+/// The state commitment computed after some number of blocks, and the one the rollup contract
+/// recorded for the same height, if the operator proved that height.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeightReport {
+    /// Number of blocks executed.
+    pub height: u64,
+    pub computed: U256,
+    pub contract: Option<U256>,
+}
+
+impl HeightReport {
+    pub fn diverges(&self) -> bool {
+        matches!(self.contract, Some(contract) if contract != self.computed)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub vm_id: u64,
+    /// Height the replay started from: 0, or the height of the snapshot it started from.
+    pub start_height: u64,
+    /// Every height replayed, including the starting height. A replay stops at the first
+    /// divergence, since every later state commitment depends on the diverging one.
+    pub heights: Vec<HeightReport>,
+    /// Number of transactions in the rollup's namespace replayed, including invalid ones.
+    pub num_transactions: u64,
+    /// Balances after the last block replayed.
+    pub balances: Vec<(Address, Amount)>,
+}
+
+impl AuditReport {
+    /// Number of blocks executed when the replay ended.
+    pub fn end_height(&self) -> u64 {
+        self.heights
+            .last()
+            .map_or(self.start_height, |report| report.height)
+    }
+
+    /// The first height at which the rollup contract disagrees with the replay, if any.
+    pub fn first_divergence(&self) -> Option<&HeightReport> {
+        self.heights.iter().find(|report| report.diverges())
+    }
+
+    /// Number of heights whose state commitment was checked against the rollup contract.
+    pub fn num_checked(&self) -> usize {
+        self.heights
+            .iter()
+            .filter(|report| report.contract.is_some())
+            .count()
+    }
+
+    pub fn total_balance(&self) -> Amount {
+        self.balances.iter().map(|(_, balance)| balance).sum()
+    }
+}
+
+/// Replay the rollup from `state` and compare the result with the history of the rollup contract.
+///
+/// An error means the replay itself could not be completed, for example because a block does not
+/// match its commitment in the HotShot contract. Disagreements with the rollup contract are not
+/// errors; they are recorded in the report.
+pub async fn audit(opt: &AuditOptions, mut state: State) -> Result<AuditReport, ExecutorError> {
+    let l1_err = |err: ContractError<Provider<Http>>| ExecutorError::L1 {
+        reason: err.to_string(),
+    };
+    let provider = Arc::new(create_provider(&opt.l1_provider));
+    let hotshot_contract = HotShot::new(opt.hotshot_address, provider.clone());
+    let rollup_contract = ExampleRollup::new(opt.rollup_address, provider);
+
+    let verified = rollup_contract
+        .num_verified_blocks()
+        .call()
+        .await
+        .map_err(l1_err)?
+        .as_u64();
+    let end_height = opt.until.map_or(verified, |until| until.min(verified));
+    let updates: BTreeMap<u64, U256> = rollup_contract
+        .state_update_filter()
+        .from_block(0u64)
+        .query()
+        .await
+        .map_err(l1_err)?
+        .into_iter()
+        .map(|update| (update.block_height.as_u64(), update.state_commitment))
+        .collect();
+
+    let hotshot = HotShotClient::new(opt.sequencer_url.join("availability").unwrap());
+    hotshot.connect(None).await;
+
+    let vm_id: u64 = state.vm.id().into();
+    let start_height = state.block_height();
+    let mut chain = ChainVerifier::default();
+    if let Some(height) = start_height.checked_sub(1) {
+        let header = fetch_header(&hotshot, &hotshot_contract, height).await?;
+        chain = ChainVerifier::new(Some(BlockLink::new(height, &header)));
+    }
+
+    let mut report = AuditReport {
+        vm_id,
+        start_height,
+        heights: vec![],
+        num_transactions: 0,
+        balances: vec![],
+    };
+    let mut record = |state: &State| {
+        let height = HeightReport {
+            height: state.block_height(),
+            computed: commitment_to_u256(state.commit()),
+            contract: updates.get(&state.block_height()).copied(),
+        };
+        report.heights.push(height);
+        !height.diverges()
+    };
+
+    let mut agrees = record(&state);
+    let mut num_transactions = 0;
+    for height in start_height..end_height {
+        if !agrees {
+            break;
+        }
+        let header = fetch_header(&hotshot, &hotshot_contract, height).await?;
+        chain
+            .verify(BlockLink::new(height, &header))
+            .map_err(|source| ExecutorError::BrokenChain { source })?;
+        let namespace_proof = hotshot
+            .get::<NamespaceProofQueryData>(&format!("block/{height}/namespace/{vm_id}"))
+            .send()
+            .await
+            .map_err(|err| ExecutorError::QueryService {
+                reason: err.to_string(),
+            })?
+            .proof;
+        let mut inputs = BlockInputs::default();
+        fetch_l1_inputs(&rollup_contract, &state, &header, &mut inputs).await?;
+        let payloads = state.apply_block(&header.transactions_root, &namespace_proof, &inputs);
+        num_transactions += payloads.vm_payloads;
+        agrees = record(&state);
+    }
+
+    report.num_transactions = num_transactions;
+    report.balances = state
+        .balances()
+        .filter(|(_, balance)| *balance > 0)
+        .collect();
+    Ok(report)
+}
+
+/// Fetch the header of the block at `height` and check it against its commitment in the HotShot
+/// contract.
+async fn fetch_header<M: Middleware>(
+    hotshot: &HotShotClient,
+    hotshot_contract: &HotShot<M>,
+    height: u64,
+) -> Result<Header, ExecutorError> {
+    let header: Header = hotshot
+        .get(&format!("header/{height}"))
+        .send()
+        .await
+        .map_err(|err| ExecutorError::QueryService {
+            reason: err.to_string(),
+        })?;
+    let commitment = hotshot_contract
+        .commitments(height.into())
+        .call()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?;
+    if !matches_block_hash(height, &header, commitment)? {
+        return Err(ExecutorError::CommitmentMismatch {
+            height,
+            bundle: None,
+        });
+    }
+    Ok(header)
+}
@@ -0,0 +1,265 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Backfill of block history on a node started from a snapshot.
+//!
+//! A snapshot holds the state at its height, but not necessarily the summaries and receipts of the
+//! blocks before it: snapshots written by older versions have none, for example. The backfill
+//! replays the rollup from genesis on a scratch state, fetching each block from the query service,
+//! and records the summary and receipts of each block in the live state wherever they are missing.
+//! The live accounts are never touched; the scratch state only serves to work out what each block
+//! did.
+//!
+//! The scratch state is saved to a cursor file as the backfill goes, so a restarted node resumes
+//! where the last backfill left off instead of starting again from genesis.
+
+use async_std::sync::{Arc, RwLock};
+use async_std::task::sleep;
+use commit::Committable;
+use contract_bindings::example_rollup::ExampleRollup;
+use ethers::{providers::Middleware, types::Address};
+use hotshot_query_service::availability::BlockQueryData;
+use sequencer::{
+    api::endpoints::NamespaceProofQueryData, Header, NamespaceProofType, SeqTypes, Vm,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+use surf_disco::Url;
+
+use crate::commitments::commitment_to_u256;
+use crate::deposit::fetch_deposits;
+use crate::error::ExecutorError;
+use crate::executor::block_timestamp;
+use crate::forced::fetch_enqueued;
+use crate::inbox::fetch_messages;
+use crate::logging::EXECUTOR;
+use crate::replicas::QueryReplicas;
+use crate::retention::RetentionConfig;
+use crate::snapshot::{load_snapshot, store_snapshot};
+use crate::state::{BlockInputs, State};
+use crate::status::{BackfillStatus, StatusHandle};
+use crate::utils::create_provider;
+
+/// Number of blocks replayed between saves of the cursor.
+const CURSOR_INTERVAL: u64 = 100;
+
+/// Name of the cursor file the node keeps in its snapshot directory.
+pub const BACKFILL_CURSOR_FILE: &str = "backfill.json";
+
+#[derive(Clone, Debug)]
+pub struct BackfillOptions {
+    /// URL of a HotShot query service.
+    pub sequencer_url: Url,
+    pub l1_provider: Url,
+    /// The rollup contract, whose events give the L1 deposits and forced transactions each block
+    /// picks up.
+    pub rollup_address: Address,
+    /// Where to save the scratch state, so that the backfill can resume after a restart.
+    pub cursor_path: Option<PathBuf>,
+    /// Time to wait after each block, to limit the load on the query service.
+    pub delay: Duration,
+}
+
+/// Fill in the summaries and receipts of the blocks below the current height of `state`.
+///
+/// `genesis` is the initial state of the rollup, which the replay starts from unless the cursor
+/// holds a later one. Progress is reported in `status` while the backfill runs. Once the replay
+/// reaches the height `state` had when the backfill started, its commitment is checked against
+/// the live state, to catch a genesis which does not match the one the rollup was deployed with.
+pub async fn run_backfill(
+    opt: &BackfillOptions,
+    genesis: State,
+    state: Arc<RwLock<State>>,
+    status: StatusHandle,
+) -> Result<(), ExecutorError> {
+    let (target_height, expected) = {
+        let state = state.read().await;
+        let height = state.block_height();
+        let expected = match height.checked_sub(1) {
+            Some(last) => state
+                .block_summary(last)
+                .map_or_else(|| state.commit(), |summary| summary.state_commitment),
+            None => return Ok(()),
+        };
+        (height, expected)
+    };
+
+    let mut scratch = resume(opt, genesis, target_height);
+    status.write().await.backfill = Some(BackfillStatus {
+        next_height: scratch.block_height(),
+        target_height,
+    });
+    tracing::info!(
+        target: EXECUTOR,
+        "backfilling blocks {}-{}",
+        scratch.block_height(),
+        target_height - 1
+    );
+
+    let result = replay(opt, &mut scratch, target_height, &state, &status).await;
+    status.write().await.backfill = None;
+    result?;
+
+    if scratch.commit() != expected {
+        return Err(ExecutorError::BackfillDiverged {
+            height: target_height,
+            ours: commitment_to_u256(scratch.commit()),
+            theirs: commitment_to_u256(expected),
+        });
+    }
+    tracing::info!(target: EXECUTOR, "backfilled {target_height} blocks");
+    Ok(())
+}
+
+/// The state to replay from: the one saved in the cursor, if it is usable, or else `genesis`.
+fn resume(opt: &BackfillOptions, genesis: State, target_height: u64) -> State {
+    let Some(path) = opt.cursor_path.as_ref().filter(|path| path.exists()) else {
+        return genesis;
+    };
+    match load_snapshot(path) {
+        Ok(scratch)
+            if scratch.vm.id() == genesis.vm.id() && scratch.block_height() <= target_height =>
+        {
+            scratch
+        }
+        Ok(scratch) => {
+            tracing::warn!(
+                target: EXECUTOR,
+                "Ignoring backfill cursor {path:?} at height {} for VM {:?}",
+                scratch.block_height(),
+                scratch.vm.id()
+            );
+            genesis
+        }
+        Err(reason) => {
+            tracing::warn!(target: EXECUTOR, "Ignoring invalid backfill cursor {path:?}: {reason}");
+            genesis
+        }
+    }
+}
+
+async fn replay(
+    opt: &BackfillOptions,
+    scratch: &mut State,
+    target_height: u64,
+    state: &RwLock<State>,
+    status: &StatusHandle,
+) -> Result<(), ExecutorError> {
+    let hotshot = QueryReplicas::new(&[opt.sequencer_url.clone()]);
+    hotshot.connect(None).await;
+    let rollup_contract = ExampleRollup::new(
+        opt.rollup_address,
+        Arc::new(create_provider(&opt.l1_provider)),
+    );
+
+    for height in scratch.block_height()..target_height {
+        let mut block = fetch_block(&hotshot, scratch, height).await?;
+        fetch_l1_inputs(&rollup_contract, scratch, &block.header, &mut block.inputs).await?;
+        let result = scratch.record_block(
+            &block.header.transactions_root,
+            &block.namespace_proof,
+            block_timestamp(&block.header),
+            block.total_payloads,
+            &block.inputs,
+        );
+        state.write().await.backfill_block(&result);
+        // The history of the scratch state is never served, so it is only kept as long as a live
+        // state keeps its history by default.
+        scratch.prune(&RetentionConfig::default(), scratch.block_height());
+        status.write().await.backfill = Some(BackfillStatus {
+            next_height: height + 1,
+            target_height,
+        });
+
+        if (height + 1) % CURSOR_INTERVAL == 0 || height + 1 == target_height {
+            save_cursor(opt, scratch);
+        }
+        sleep(opt.delay).await;
+    }
+    Ok(())
+}
+
+/// A HotShot block, with everything needed to execute it.
+pub(crate) struct FetchedBlock {
+    pub header: Header,
+    pub namespace_proof: NamespaceProofType,
+    /// Number of transactions in the block, for every rollup.
+    pub total_payloads: u64,
+    /// The messages the block delivers. Deposits and forced transactions are not included, since
+    /// they come from the L1: see [`fetch_l1_inputs`].
+    pub inputs: BlockInputs,
+}
+
+/// Fetch the block at `height` from the query service, to be executed on `state`.
+pub(crate) async fn fetch_block(
+    hotshot: &QueryReplicas,
+    state: &State,
+    height: u64,
+) -> Result<FetchedBlock, ExecutorError> {
+    let query_err = |err: hotshot_query_service::Error| ExecutorError::QueryService {
+        reason: err.to_string(),
+    };
+    let vm_id: u64 = state.vm.id().into();
+    let header: Header = hotshot
+        .get(&format!("header/{height}"))
+        .await
+        .map_err(query_err)?;
+    let namespace_proof = hotshot
+        .get::<NamespaceProofQueryData>(&format!("block/{height}/namespace/{vm_id}"))
+        .await
+        .map_err(query_err)?
+        .proof;
+    let block: BlockQueryData<SeqTypes> = hotshot
+        .get(&format!("block/{height}"))
+        .await
+        .map_err(query_err)?;
+
+    let config = state.chain_config();
+    let messages = fetch_messages(
+        hotshot,
+        height,
+        &header.transactions_root,
+        &config.message_sources,
+        vm_id,
+        config.max_block_weight,
+    )
+    .await?;
+    Ok(FetchedBlock {
+        header,
+        namespace_proof,
+        total_payloads: block.len() as u64,
+        inputs: BlockInputs {
+            messages,
+            ..Default::default()
+        },
+    })
+}
+
+/// Fetch the deposits and forced transactions the block with `header` picks up from the L1 when
+/// executed on `state`, into `inputs`, as the executor does.
+pub async fn fetch_l1_inputs<M: Middleware>(
+    rollup_contract: &ExampleRollup<M>,
+    state: &State,
+    header: &Header,
+    inputs: &mut BlockInputs,
+) -> Result<(), ExecutorError> {
+    let l1_finalized = header.l1_finalized.as_ref().map(|block| block.number);
+    if let Some(blocks) = state.pending_deposits(header.l1_head, l1_finalized) {
+        inputs.deposits = fetch_deposits(rollup_contract, blocks).await?;
+    }
+    if let Some(blocks) = state.pending_forced(l1_finalized) {
+        inputs.forced = fetch_enqueued(rollup_contract, blocks).await?;
+    }
+    Ok(())
+}
+
+fn save_cursor(opt: &BackfillOptions, scratch: &State) {
+    if let Some(path) = &opt.cursor_path {
+        if let Err(err) = store_snapshot(path, scratch) {
+            tracing::warn!(target: EXECUTOR, "Unable to save backfill cursor {path:?}: {err}");
+        }
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Checkpoints of account balances, so the API can chart an account's balance over time without
+//! replaying blocks.
+//!
+//! Every [`interval`](BalanceHistory::interval) blocks, the state checkpoints the balance of each
+//! account which changed since its last checkpoint. Accounts on the watch list, which the operator
+//! manages through the admin API, are also checkpointed after every block which changes their
+//! balance. The balance of an account at any height is that of its last checkpoint at or below the
+//! height, so it is exact at every multiple of the interval, and at every height for a watched
+//! account.
+//!
+//! Like the activity of accounts, this is bookkeeping for the API rather than rollup state, so it is
+//! not part of the state commitment. It is pruned along with the summaries of blocks, keeping the
+//! last checkpoint of each account below the horizon, which still gives its balance there.
+
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::state::Amount;
+
+/// Number of blocks between checkpoints of every account, by default.
+pub const DEFAULT_BALANCE_CHECKPOINT_INTERVAL: u64 = 10;
+
+/// Most points returned by one request for the balance history of an account.
+pub const MAX_BALANCE_HISTORY_POINTS: u64 = 1000;
+
+/// The balance of an account after `height` blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalancePoint {
+    pub height: u64,
+    pub balance: Amount,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceHistory {
+    interval: u64,
+    // Height from which balances are known: the genesis, the height of the snapshot the history
+    // was migrated from, or how far it has been pruned.
+    since: u64,
+    watched: BTreeSet<Address>,
+    // Balance of each account at each of its checkpoints, by address and then by height. Only
+    // changes are checkpointed, so an account has no checkpoint at a height where its balance is
+    // that of its previous checkpoint.
+    checkpoints: BTreeMap<Address, BTreeMap<u64, Amount>>,
+}
+
+impl BalanceHistory {
+    /// A history starting with `balances` at `height`.
+    pub(crate) fn new(height: u64, balances: impl IntoIterator<Item = (Address, Amount)>) -> Self {
+        let mut history = Self {
+            interval: DEFAULT_BALANCE_CHECKPOINT_INTERVAL,
+            since: height,
+            watched: BTreeSet::new(),
+            checkpoints: BTreeMap::new(),
+        };
+        history.record(height, balances);
+        history
+    }
+
+    /// Number of blocks between checkpoints of every account.
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// Checkpoint every account each `interval` blocks from now on. Existing checkpoints are kept.
+    pub(crate) fn set_interval(&mut self, interval: u64) {
+        self.interval = interval.max(1);
+    }
+
+    /// The lowest height whose balances are known.
+    pub fn since(&self) -> u64 {
+        self.since
+    }
+
+    /// The accounts checkpointed after every block which changes their balance.
+    pub fn watched(&self) -> &BTreeSet<Address> {
+        &self.watched
+    }
+
+    /// Whether every account is checkpointed after `height` blocks.
+    pub(crate) fn is_checkpoint(&self, height: u64) -> bool {
+        height % self.interval == 0
+    }
+
+    /// Add `address`, whose balance after `height` blocks is `balance`, to the watch list. Returns
+    /// whether it was not watched already.
+    pub(crate) fn watch(&mut self, address: Address, height: u64, balance: Amount) -> bool {
+        // The balance may have changed since the last checkpoint of the account, which would
+        // otherwise only be recorded once it changes again.
+        if self.balance_at(&address, height) != balance {
+            self.record(height, [(address, balance)]);
+        }
+        self.watched.insert(address)
+    }
+
+    /// Remove `address` from the watch list. Its checkpoints are kept. Returns whether it was
+    /// watched.
+    pub(crate) fn unwatch(&mut self, address: &Address) -> bool {
+        self.watched.remove(address)
+    }
+
+    /// Checkpoint `balances`, as of `height` blocks. Only the ones which changed since the last
+    /// checkpoint of their account should be given.
+    pub(crate) fn record(
+        &mut self,
+        height: u64,
+        balances: impl IntoIterator<Item = (Address, Amount)>,
+    ) {
+        for (address, balance) in balances {
+            self.checkpoints
+                .entry(address)
+                .or_default()
+                .insert(height, balance);
+        }
+    }
+
+    /// Forget the checkpoints below `horizon`, but the last one of each account.
+    pub(crate) fn prune(&mut self, horizon: u64) {
+        if horizon <= self.since {
+            return;
+        }
+        for checkpoints in self.checkpoints.values_mut() {
+            let mut kept = checkpoints.split_off(&horizon);
+            if !kept.contains_key(&horizon) {
+                if let Some((height, balance)) = checkpoints.pop_last() {
+                    kept.insert(height, balance);
+                }
+            }
+            *checkpoints = kept;
+        }
+        self.since = horizon;
+    }
+
+    /// The balance of `address` after `height` blocks, as of its last checkpoint.
+    pub fn balance_at(&self, address: &Address, height: u64) -> Amount {
+        self.checkpoints
+            .get(address)
+            .and_then(|checkpoints| checkpoints.range(..=height).next_back())
+            .map_or(0, |(_, balance)| *balance)
+    }
+
+    /// The balance of `address` at every `step`th height from `from` through `to`.
+    pub fn series(&self, address: &Address, from: u64, to: u64, step: u64) -> Vec<BalancePoint> {
+        (from..=to)
+            .step_by(step.max(1) as usize)
+            .map(|height| BalancePoint {
+                height,
+                balance: self.balance_at(address, height),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_history() {
+        let alice = Address::from_low_u64_be(1);
+        let bob = Address::from_low_u64_be(2);
+        let mut history = BalanceHistory::new(0, [(alice, 100)]);
+        history.set_interval(5);
+        assert!(history.is_checkpoint(10) && !history.is_checkpoint(12));
+
+        history.record(5, [(alice, 90), (bob, 10)]);
+        assert!(history.watch(bob, 7, 15));
+        assert!(!history.watch(bob, 7, 15));
+        history.record(8, [(bob, 20)]);
+
+        // Balances between checkpoints are those of the checkpoint before.
+        assert_eq!(
+            history.series(&alice, 0, 10, 5),
+            [(0, 100), (5, 90), (10, 90)].map(|(height, balance)| BalancePoint { height, balance })
+        );
+        assert_eq!(
+            history
+                .series(&bob, 4, 9, 1)
+                .iter()
+                .map(|point| point.balance)
+                .collect::<Vec<_>>(),
+            [0, 10, 10, 15, 20, 20]
+        );
+
+        // Pruning keeps what is needed for the balances at the horizon.
+        history.prune(6);
+        assert_eq!(history.since(), 6);
+        assert_eq!(history.balance_at(&alice, 6), 90);
+        assert_eq!(history.balance_at(&bob, 6), 10);
+        assert_eq!(history.checkpoints[&alice].len(), 1);
+        assert_eq!(history.checkpoints[&bob].len(), 3);
+
+        assert!(history.unwatch(&bob));
+        assert!(history.watched().is_empty());
+    }
+}
@@ -4,30 +4,47 @@
 // You should have received a copy of the MIT License
 // along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
 
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 use clap::{Args, Parser, Subcommand};
+use contract_bindings::example_rollup::ExampleRollup;
 use ethers::{
     prelude::k256::ecdsa::SigningKey,
     signers::{LocalWallet, Signer, Wallet},
     types::Address,
 };
 use example_l2::{
-    seed::SeedIdentity,
-    state::{Amount, Nonce},
-    transaction::{SignedTransaction, Transaction},
+    backfill::fetch_l1_inputs,
+    builder::TransactionBuilder,
+    client::RollupClient,
+    json::BlockReplayJson,
+    replay::{replay_block, ReplayOptions},
+    seed::{initial_balances, SeedIdentity},
+    state::{Amount, BlockInputs, ChainConfig, State},
+    utils::create_provider,
+    verify_encoded_block_proof, RollupVM,
 };
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
+use sequencer::{api::endpoints::NamespaceProofQueryData, Header};
+use sequencer_utils::connect_rpc;
 use surf_disco::Client;
 use tide_disco::{error::ServerError, Url};
 
-type RollupClient = Client<ServerError>;
+type SequencerClient = Client<ServerError>;
 
 #[derive(Parser, Clone, Debug)]
 pub struct Options {
     /// Url of the Rollup client
-    #[clap(short, long, default_value = "http://localhost:8084")]
+    #[clap(
+        short,
+        long,
+        alias = "api-url",
+        global = true,
+        default_value = "http://localhost:8084"
+    )]
     pub rollup_url: Url,
 
     #[command(subcommand)]
@@ -38,6 +55,16 @@ pub struct Options {
 pub enum ExampleRollupCommand {
     Transfer(Transfer),
     CheckBalance(CheckBalance),
+    VerifyProof(VerifyProof),
+    RotateSubmitter(RotateSubmitter),
+    #[command(subcommand)]
+    Debug(DebugCommand),
+}
+
+/// Tools for investigating the behavior of the rollup.
+#[derive(Subcommand, Clone, Debug)]
+pub enum DebugCommand {
+    ReplayBlock(ReplayBlock),
 }
 
 #[derive(Args, Clone, Debug)]
@@ -45,6 +72,10 @@ pub struct Transfer {
     pub sender: SeedIdentity,
     pub receiver: SeedIdentity,
     pub amount: Amount,
+    /// Tip paid on top of the amount for an earlier position in the block. Higher priorities
+    /// execute first.
+    #[clap(long, default_value = "0")]
+    pub priority: Amount,
 }
 
 #[derive(Args, Clone, Debug)]
@@ -52,21 +83,139 @@ pub struct CheckBalance {
     pub identity: SeedIdentity,
 }
 
+/// Check the proof the rollup node generated for a block, without trusting the node.
+///
+/// The rollup is replayed from its initial state using blocks fetched from the sequencer, and the
+/// proof fetched from the rollup node is checked against the replayed state transition.
+#[derive(Args, Clone, Debug)]
+pub struct VerifyProof {
+    /// Height of the block whose proof to check.
+    #[clap(long)]
+    pub height: u64,
+
+    /// URL of a HotShot sequencer node serving the availability API.
+    #[clap(long, default_value = "http://localhost:50000")]
+    pub sequencer_url: Url,
+
+    /// URL of layer 1 Ethereum JSON-RPC provider.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_L1_HTTP_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    pub l1_provider: Url,
+
+    /// Address of the rollup contract, to fetch the L1 deposits and forced transactions each
+    /// block picks up. Without it, the replay only matches while there are none.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_ADDRESS")]
+    pub rollup_address: Option<Address>,
+
+    /// Comma-separated list of ERC-20 tokens in the genesis chain configuration of the rollup.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DEPOSIT_ASSETS",
+        value_delimiter = ','
+    )]
+    pub deposit_assets: Vec<Address>,
+
+    /// Number of L1 blocks the genesis chain configuration requires to be built on a deposit
+    /// before it is credited.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DEPOSIT_CONFIRMATIONS",
+        default_value = "0"
+    )]
+    pub deposit_confirmations: u64,
+
+    /// Whether the genesis chain configuration applies transactions enqueued on the rollup
+    /// contract.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_FORCED_INCLUSION")]
+    pub forced_inclusion: bool,
+}
+
+/// Authorize a new account to submit proofs to the rollup contract.
+///
+/// Only the owner of the contract, the account which deployed it, can do this. Any executor
+/// sending proofs from another account halts the next time it tries to send one.
+#[derive(Args, Clone, Debug)]
+pub struct RotateSubmitter {
+    /// Address of the account to authorize. The zero address lets any account submit proofs.
+    #[clap(long)]
+    pub new_address: Address,
+
+    /// Address of the rollup contract.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_ADDRESS")]
+    pub rollup_address: Address,
+
+    /// URL of layer 1 Ethereum JSON-RPC provider.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_L1_HTTP_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    pub l1_provider: Url,
+
+    /// Chain ID of the L1, if the provider should be checked against it.
+    #[clap(long, env = "ESPRESSO_DEMO_L1_CHAIN_ID")]
+    pub l1_chain_id: Option<u64>,
+
+    /// Mnemonic phrase for the owner's L1 wallet.
+    #[clap(
+        long,
+        default_value = "test test test test test test test test test test test junk"
+    )]
+    pub mnemonic: String,
+
+    /// Index of the owner's account derived from the mnemonic.
+    #[clap(long, default_value = "0")]
+    pub account_index: u32,
+}
+
+/// Execute a block again against the state before it, and print its receipts, the balances it
+/// changed and the state commitments before and after it.
+///
+/// The state before the block is rebuilt from the newest snapshot which is not above it, so this
+/// works long after the rollup has moved past the block, as long as such a snapshot is still kept.
+/// Nothing is written, and the rollup node need not be running.
+#[derive(Args, Clone, Debug)]
+pub struct ReplayBlock {
+    /// Height of the block to replay.
+    #[clap(long)]
+    pub height: u64,
+
+    /// Directory the executor writes its state snapshots to.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_SNAPSHOT_DIR")]
+    pub snapshot_dir: PathBuf,
+
+    /// URL of a HotShot sequencer node serving the availability API.
+    #[clap(long, default_value = "http://localhost:50000")]
+    pub sequencer_url: Url,
+
+    /// URL of layer 1 Ethereum JSON-RPC provider.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_L1_HTTP_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    pub l1_provider: Url,
+
+    /// Address of the rollup contract, to fetch the L1 deposits the block credits.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_ADDRESS")]
+    pub rollup_address: Option<Address>,
+
+    /// Print the result as JSON, in the format of the `admin/replay/:height` route.
+    #[clap(long)]
+    pub json: bool,
+}
+
 fn get_wallet_from_identity(identity: &SeedIdentity) -> Wallet<SigningKey> {
     LocalWallet::new(&mut ChaChaRng::seed_from_u64(*identity as u64))
 }
 
-async fn transfer(transfer: &Transfer, client: &RollupClient) {
+async fn transfer(transfer: &Transfer, rollup_url: &Url) {
     let sender = get_wallet_from_identity(&transfer.sender);
     let receiver = get_wallet_from_identity(&transfer.receiver);
     let amount = transfer.amount;
-    let nonce = get_nonce(&sender.address(), client).await + 1;
-    let transaction = Transaction {
-        amount,
-        destination: receiver.address(),
-        nonce,
-    };
-    let signed_transaction = SignedTransaction::new(transaction, &sender).await;
 
     println!(
         "Submitting Transaction to Rollup API: Transferring {} tokens from {} to {}",
@@ -75,41 +224,184 @@ async fn transfer(transfer: &Transfer, client: &RollupClient) {
         receiver.address(),
     );
 
-    client
-        .post::<()>("rollup/submit")
-        .body_json(&signed_transaction)
-        .expect("Error setting the response body")
-        .send()
+    let sender_address = sender.address();
+    let hash = TransactionBuilder::new(rollup_url.clone(), sender)
+        .destination(receiver.address())
+        .amount(amount)
+        .priority(transfer.priority)
+        .submit()
         .await
-        .expect("Error sending the transfer transaction")
-}
-
-async fn get_nonce(address: &Address, client: &RollupClient) -> Nonce {
-    client
-        .get::<u64>(&format!("rollup/nonce/{:?}", address))
-        .send()
-        .await
-        .expect("Error sending the get nonce request")
+        .unwrap_or_else(|err| panic!("Error sending the transfer from {sender_address:?}: {err}"));
+    println!("Submitted transaction {hash:?}");
 }
 
 async fn check_balance(check_balance: &CheckBalance, client: &RollupClient) {
     let address = get_wallet_from_identity(&check_balance.identity).address();
     let balance = client
-        .get::<u64>(&format!("rollup/balance/{:?}", address))
-        .send()
+        .balance(address)
         .await
         .expect("Error sending the check balance request");
 
     println!("Balance of {:?}: {}", address, balance)
 }
 
+async fn verify_proof(verify: &VerifyProof, client: &RollupClient) {
+    let info = client.info().await.expect("Error fetching rollup info");
+    let proof = client
+        .encoded_proof(verify.height)
+        .await
+        .expect("Error fetching block proof");
+    match proof.format() {
+        Some(format) => println!(
+            "Proof of block {} is {} bytes in {format:?} format",
+            verify.height,
+            proof.len()
+        ),
+        None => println!("Proof of block {} is in an unknown format", verify.height),
+    }
+
+    let sequencer = SequencerClient::new(verify.sequencer_url.join("availability").unwrap());
+    let rollup_contract = verify
+        .rollup_address
+        .map(|address| ExampleRollup::new(address, Arc::new(create_provider(&verify.l1_provider))));
+    let vm = RollupVM::new(info.vm_id.into());
+    let mut state =
+        State::from_initial_balances(initial_balances(), vm).with_chain_config(ChainConfig {
+            assets: verify.deposit_assets.iter().copied().collect(),
+            deposit_confirmations: verify.deposit_confirmations,
+            forced_inclusion: verify.forced_inclusion,
+            ..Default::default()
+        });
+    for height in 0..=verify.height {
+        let header = sequencer
+            .get::<Header>(&format!("header/{height}"))
+            .send()
+            .await
+            .expect("Error fetching block header");
+        let namespace_proof = sequencer
+            .get::<NamespaceProofQueryData>(&format!("block/{height}/namespace/{}", info.vm_id))
+            .send()
+            .await
+            .expect("Error fetching namespace proof")
+            .proof;
+        let mut inputs = BlockInputs::default();
+        if let Some(rollup_contract) = &rollup_contract {
+            fetch_l1_inputs(rollup_contract, &state, &header, &mut inputs)
+                .await
+                .expect("Error fetching the L1 inputs of the block");
+        }
+        if height < verify.height {
+            state.apply_block(&header.transactions_root, &namespace_proof, &inputs);
+        } else if verify_encoded_block_proof(
+            &state,
+            &header.transactions_root,
+            &namespace_proof,
+            &inputs,
+            &proof,
+        ) {
+            println!("Proof of block {height} matches the replayed state transition");
+        } else {
+            println!("Proof of block {height} does NOT match the replayed state transition");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn rotate_submitter(rotate: &RotateSubmitter) {
+    let l1 = connect_rpc(
+        &rotate.l1_provider,
+        &rotate.mnemonic,
+        rotate.account_index,
+        rotate.l1_chain_id,
+    )
+    .await
+    .expect("Error connecting to the L1 provider");
+    let contract = ExampleRollup::new(rotate.rollup_address, Arc::new(l1));
+    let receipt = contract
+        .set_submitter(rotate.new_address)
+        .send()
+        .await
+        .unwrap_or_else(|err| panic!("Error sending the rotation: {err}"))
+        .await
+        .expect("Error waiting for the rotation to be mined");
+    match receipt {
+        Some(receipt) if receipt.status == Some(1.into()) => println!(
+            "Authorized {:?} to submit proofs in L1 transaction {:?}",
+            rotate.new_address, receipt.transaction_hash
+        ),
+        _ => {
+            println!("The rotation was not mined successfully");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn replay(replay: &ReplayBlock) {
+    let opt = ReplayOptions {
+        snapshot_dir: replay.snapshot_dir.clone(),
+        sequencer_url: replay.sequencer_url.clone(),
+        l1_provider: replay.l1_provider.clone(),
+        rollup_address: replay.rollup_address,
+    };
+    let result = replay_block(&opt, replay.height)
+        .await
+        .unwrap_or_else(|err| panic!("Error replaying block {}: {err}", replay.height));
+    if replay.json {
+        let json = BlockReplayJson::from(&result);
+        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        return;
+    }
+
+    let summary = &result.result.summary;
+    println!(
+        "Replayed block {} from the snapshot at height {}",
+        summary.height, result.snapshot_height
+    );
+    println!("State before: {}", result.pre_state);
+    println!("State after:  {}", summary.state_commitment);
+    println!(
+        "{} of {} transactions in the namespace applied, weight {}",
+        summary.payloads.applied, summary.payloads.vm_payloads, summary.weight
+    );
+    for receipt in &result.result.transactions {
+        let outcome = match &receipt.error {
+            None => "applied".to_string(),
+            Some(err) => format!("not applied: {err}"),
+        };
+        println!("  {:?} from {:?}: {outcome}", receipt.hash, receipt.sender);
+    }
+    println!("Balance changes:");
+    for change in &result.balance_changes {
+        let asset = match change.asset {
+            Some(asset) => format!(" of {asset:?}"),
+            None => String::new(),
+        };
+        println!(
+            "  {:?}{asset}: {} -> {}",
+            change.address, change.before, change.after
+        );
+    }
+}
+
 #[async_std::main]
 async fn main() {
     let Options {
         rollup_url,
         command,
     } = Options::parse();
-    let client: RollupClient = Client::new(rollup_url);
+
+    // Rotating the submitter talks only to the L1, so it works even if the rollup node is down.
+    if let ExampleRollupCommand::RotateSubmitter(rotate_cmd) = &command {
+        rotate_submitter(rotate_cmd).await;
+        return;
+    }
+    // Replaying a block does not need the rollup node either; it reads the snapshots directly.
+    if let ExampleRollupCommand::Debug(DebugCommand::ReplayBlock(replay_cmd)) = &command {
+        replay(replay_cmd).await;
+        return;
+    }
+
+    let client = RollupClient::new(rollup_url.clone());
     let connected = client.connect(Some(Duration::from_secs(2))).await;
     if !connected {
         println!("Could not connect to the Rollup Client. Ensure that the client is running and that the supplied port is correct.");
@@ -117,9 +409,15 @@ async fn main() {
     }
 
     match command {
-        ExampleRollupCommand::Transfer(transfer_cmd) => transfer(&transfer_cmd, &client).await,
+        ExampleRollupCommand::Transfer(transfer_cmd) => transfer(&transfer_cmd, &rollup_url).await,
         ExampleRollupCommand::CheckBalance(check_balance_cmd) => {
             check_balance(&check_balance_cmd, &client).await;
         }
+        ExampleRollupCommand::VerifyProof(verify_proof_cmd) => {
+            verify_proof(&verify_proof_cmd, &client).await;
+        }
+        ExampleRollupCommand::RotateSubmitter(_) | ExampleRollupCommand::Debug(_) => {
+            unreachable!()
+        }
     };
 }
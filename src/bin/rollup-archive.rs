@@ -0,0 +1,139 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Export a range of the rollup's history from a node's snapshots to a self-contained archive, or
+//! import an archive into the snapshots of another node.
+//!
+//! An import checks the archive against its manifest and its own chain of state commitments, and
+//! the state it ends with against a trusted root or the rollup contract, before writing anything.
+
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::{Args, Parser, Subcommand};
+use ethers::types::{Address, U256};
+use example_l2::archive::{contract_anchor, export, import, read_archive, Anchor};
+use std::path::PathBuf;
+use surf_disco::Url;
+
+#[derive(Parser, Clone, Debug)]
+struct Options {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum Command {
+    Export(Export),
+    Import(Import),
+}
+
+/// Archive blocks `--from` up to `--to` from the snapshots of a node.
+///
+/// The snapshot at `--to` must still be retained, and so must the summary, receipts and proof of
+/// every block in the range, in some snapshot after the block.
+#[derive(Args, Clone, Debug)]
+struct Export {
+    /// Directory the node writes its state snapshots to.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_SNAPSHOT_DIR")]
+    snapshot_dir: PathBuf,
+
+    /// Height of the first block to archive.
+    #[clap(long)]
+    from: u64,
+
+    /// Height after the last block to archive. The archive ends with the state after this many
+    /// blocks.
+    #[clap(long)]
+    to: u64,
+
+    /// Directory to write the archive to. It must not exist or be empty.
+    #[clap(long)]
+    out: PathBuf,
+}
+
+/// Import an archive into the snapshots of a node, which need not be running.
+///
+/// The state the archive ends with is checked against `--trusted-root`, or else against the state
+/// updates of the rollup contract at `--rollup-address`.
+#[derive(Args, Clone, Debug)]
+struct Import {
+    /// Directory holding the archive.
+    archive: PathBuf,
+
+    /// Directory the node loads its state snapshots from.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_SNAPSHOT_DIR")]
+    snapshot_dir: PathBuf,
+
+    /// Commitment to the state the archive ends with, in hex as the rollup contract stores it, from
+    /// a source you trust. Takes the place of the rollup contract.
+    #[clap(long)]
+    trusted_root: Option<U256>,
+
+    /// Address of the rollup contract whose state updates to check the archive against.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_ADDRESS",
+        required_unless_present = "trusted_root"
+    )]
+    rollup_address: Option<Address>,
+
+    /// URL of layer 1 Ethereum JSON-RPC provider.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_L1_HTTP_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    l1_http_provider: Url,
+}
+
+fn fail(err: impl std::fmt::Display) -> ! {
+    eprintln!("{err}");
+    std::process::exit(1);
+}
+
+#[async_std::main]
+async fn main() {
+    setup_logging();
+    setup_backtrace();
+
+    match Options::parse().command {
+        Command::Export(opt) => {
+            let manifest = export(&opt.snapshot_dir, opt.from, opt.to, &opt.out)
+                .unwrap_or_else(|err| fail(err));
+            println!(
+                "Archived blocks {}-{} of VM {} to {:?}, with snapshots at heights {:?}",
+                manifest.from,
+                manifest.to - 1,
+                manifest.vm_id,
+                opt.out,
+                manifest.snapshots
+            );
+        }
+        Command::Import(opt) => {
+            let archive = read_archive(&opt.archive).unwrap_or_else(|err| fail(err));
+            let anchor = match (opt.trusted_root, opt.rollup_address) {
+                (Some(root), _) => Anchor::TrustedRoot(root),
+                (None, Some(address)) => contract_anchor(&opt.l1_http_provider, address)
+                    .await
+                    .unwrap_or_else(|err| fail(err)),
+                (None, None) => fail("Either --trusted-root or --rollup-address is required"),
+            };
+            let anchored = archive
+                .check_anchor(&anchor)
+                .unwrap_or_else(|err| fail(err));
+            let report =
+                import(&archive, anchored, &opt.snapshot_dir).unwrap_or_else(|err| fail(err));
+            println!(
+                "Imported blocks {}-{} of VM {}, anchored at height {anchored}",
+                archive.manifest.from,
+                archive.manifest.to - 1,
+                archive.manifest.vm_id
+            );
+            println!("Snapshots written:    {:?}", report.written);
+            println!("Snapshots unchanged:  {:?}", report.unchanged);
+            println!("Snapshots skipped:    {:?}", report.skipped);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Check the operator of a deployed rollup by replaying it independently.
+//!
+//! Every block is replayed from genesis, or from a snapshot, and the resulting state commitments
+//! are compared with the ones recorded in the rollup contract. The process exits with a non-zero
+//! status if they diverge.
+
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::Parser;
+use ethers::types::Address;
+use example_l2::{
+    address::checksummed,
+    audit::{audit, AuditOptions},
+    seed::initial_balances,
+    snapshot::load_snapshot,
+    state::{ChainConfig, State},
+    RollupVM,
+};
+use std::path::PathBuf;
+use surf_disco::Url;
+
+#[derive(Parser, Clone, Debug)]
+struct Options {
+    /// URL of layer 1 Ethereum JSON-RPC provider.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_L1_HTTP_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    l1_http_provider: Url,
+
+    /// URL of a HotShot query service.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_URL",
+        default_value = "http://localhost:50000"
+    )]
+    sequencer_url: Url,
+
+    /// Address of HotShot contract on layer 1.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_HOTSHOT_ADDRESS",
+        default_value = "0x0116686e2291dbd5e317f47fadbfb43b599786ef"
+    )]
+    hotshot_address: Address,
+
+    /// Address of the rollup contract to audit.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_ADDRESS")]
+    rollup_address: Address,
+
+    /// ID of the rollup VM, used when replaying from genesis.
+    #[clap(long, default_value = "1")]
+    vm_id: u64,
+
+    /// Comma-separated list of ERC-20 tokens in the genesis chain configuration of the rollup.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DEPOSIT_ASSETS",
+        value_delimiter = ','
+    )]
+    deposit_assets: Vec<Address>,
+
+    /// Number of L1 blocks the genesis chain configuration requires to be built on a deposit
+    /// before it is credited.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DEPOSIT_CONFIRMATIONS",
+        default_value = "0"
+    )]
+    deposit_confirmations: u64,
+
+    /// Whether the genesis chain configuration applies transactions enqueued on the rollup
+    /// contract.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_FORCED_INCLUSION")]
+    forced_inclusion: bool,
+
+    /// Start from a snapshot written by a rollup node instead of from genesis.
+    ///
+    /// The snapshot is only checked against its own commitment, so a snapshot from an untrusted
+    /// source should be at a height the rollup contract has a state commitment for.
+    #[clap(long)]
+    from_snapshot: Option<PathBuf>,
+
+    /// Stop after this many blocks.
+    #[clap(long)]
+    until: Option<u64>,
+}
+
+#[async_std::main]
+async fn main() {
+    setup_logging();
+    setup_backtrace();
+
+    let opt = Options::parse();
+    let state = match &opt.from_snapshot {
+        Some(path) => load_snapshot(path).unwrap_or_else(|err| {
+            eprintln!("Unable to load snapshot {path:?}: {err}");
+            std::process::exit(2);
+        }),
+        None => State::from_initial_balances(initial_balances(), RollupVM::new(opt.vm_id.into()))
+            .with_chain_config(ChainConfig {
+                assets: opt.deposit_assets.iter().copied().collect(),
+                deposit_confirmations: opt.deposit_confirmations,
+                forced_inclusion: opt.forced_inclusion,
+                ..Default::default()
+            }),
+    };
+    let audit_opt = AuditOptions {
+        l1_provider: opt.l1_http_provider,
+        hotshot_address: opt.hotshot_address,
+        rollup_address: opt.rollup_address,
+        sequencer_url: opt.sequencer_url,
+        until: opt.until,
+    };
+    let report = audit(&audit_opt, state).await.unwrap_or_else(|err| {
+        eprintln!("Audit could not be completed: {err}");
+        std::process::exit(2);
+    });
+
+    println!(
+        "Rollup {} at {}, blocks {}-{}",
+        report.vm_id,
+        checksummed(&opt.rollup_address),
+        report.start_height,
+        report.end_height(),
+    );
+    for height in &report.heights {
+        match height.contract {
+            Some(contract) if height.diverges() => println!(
+                "{:>8}  computed {:#x}  contract {contract:#x}  DIVERGED",
+                height.height, height.computed
+            ),
+            Some(_) => println!(
+                "{:>8}  computed {:#x}  matches contract",
+                height.height, height.computed
+            ),
+            None => println!("{:>8}  computed {:#x}", height.height, height.computed),
+        }
+    }
+    println!();
+    println!(
+        "Blocks replayed:      {}",
+        report.end_height() - report.start_height
+    );
+    println!("Transactions:         {}", report.num_transactions);
+    println!("Commitments checked:  {}", report.num_checked());
+    println!("Accounts:             {}", report.balances.len());
+    println!("Total balance:        {}", report.total_balance());
+    for (address, balance) in &report.balances {
+        println!("  {}  {balance}", checksummed(address));
+    }
+
+    match report.first_divergence() {
+        Some(divergence) => {
+            println!(
+                "First divergence after {} blocks: computed {:#x}, contract holds {:#x}",
+                divergence.height,
+                divergence.computed,
+                divergence.contract.unwrap_or_default()
+            );
+            std::process::exit(1);
+        }
+        None => println!("No divergences"),
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Generate load against a running rollup node and report how it held up.
+//!
+//! Funded wallets submit transfers through the rollup API at a target rate, either for a number of
+//! transfers or, as a soak test, for a duration. Each transfer is followed until it is executed,
+//! and optionally until it is verified on L1. A report of latency percentiles and errors is printed
+//! at the end, and the process exits with a non-zero status if the error rate or the p99 execution
+//! latency exceeds its threshold.
+
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::Parser;
+use ethers::signers::LocalWallet;
+use example_l2::{
+    client::RollupClient,
+    loadgen::{run, LoadOptions, Thresholds, DEFAULT_FUNDING},
+    seed::SeedIdentity,
+};
+use std::time::Duration;
+use surf_disco::Url;
+
+/// Transfers submitted when neither a count nor a duration is given.
+const DEFAULT_TRANSACTIONS: u64 = 100;
+
+#[derive(Parser, Clone, Debug)]
+struct Options {
+    /// URL of the rollup API.
+    #[clap(
+        short,
+        long,
+        alias = "api-url",
+        default_value = "http://localhost:8084"
+    )]
+    rollup_url: Url,
+
+    /// Seed identity which funds the wallets.
+    #[clap(long, default_value = "Alice")]
+    funder: SeedIdentity,
+
+    /// Private key, in hex, of the account which funds the wallets, instead of a seed identity.
+    #[clap(long, env = "ROLLUP_LOADGEN_FUNDER_KEY")]
+    funder_key: Option<LocalWallet>,
+
+    /// Number of wallets submitting transfers concurrently.
+    #[clap(long, default_value = "10")]
+    wallets: usize,
+
+    /// Transfers submitted per second, across all wallets. Zero submits as fast as the wallets
+    /// can.
+    #[clap(long, default_value = "10")]
+    rate: f64,
+
+    /// Stop after submitting this many transfers. Without this or a duration, 100 are submitted.
+    #[clap(long)]
+    transactions: Option<u64>,
+
+    /// Keep submitting for this many seconds, as a soak test.
+    #[clap(long)]
+    duration: Option<u64>,
+
+    /// Seconds to wait for each transfer to be executed before counting it as timed out.
+    #[clap(long, default_value = "60")]
+    execution_timeout_secs: u64,
+
+    /// Also follow each transfer until its block is verified on L1, for at most this many seconds.
+    #[clap(long)]
+    verification_timeout_secs: Option<u64>,
+
+    /// What the funder sends each wallet before the load starts.
+    #[clap(long, default_value_t = DEFAULT_FUNDING)]
+    funding: u64,
+
+    /// Seed the keys of the wallets are derived from. The same seed gives the same wallets.
+    #[clap(long, default_value = "0")]
+    seed: u64,
+
+    /// Fail if more than this fraction of the transfers fail.
+    #[clap(long, default_value = "0.01")]
+    max_error_rate: f64,
+
+    /// Fail if the 99th percentile of the time from submission to execution exceeds this many
+    /// milliseconds.
+    #[clap(long, default_value = "30000")]
+    max_p99_latency_ms: u64,
+}
+
+#[async_std::main]
+async fn main() {
+    setup_logging();
+    setup_backtrace();
+
+    let opt = Options::parse();
+    let client = RollupClient::new(opt.rollup_url.clone());
+    if !client.connect(Some(Duration::from_secs(5))).await {
+        eprintln!("Rollup API at {} is unreachable.", opt.rollup_url);
+        std::process::exit(2);
+    }
+    let funder = opt
+        .funder_key
+        .clone()
+        .unwrap_or_else(|| opt.funder.wallet());
+    let transactions = match (opt.transactions, opt.duration) {
+        (None, None) => Some(DEFAULT_TRANSACTIONS),
+        (transactions, _) => transactions,
+    };
+    let options = LoadOptions {
+        wallets: opt.wallets,
+        rate: opt.rate,
+        transactions,
+        duration: opt.duration.map(Duration::from_secs),
+        execution_timeout: Duration::from_secs(opt.execution_timeout_secs),
+        verification_timeout: opt.verification_timeout_secs.map(Duration::from_secs),
+        funding: opt.funding,
+        seed: opt.seed,
+        ..Default::default()
+    };
+    let thresholds = Thresholds {
+        max_error_rate: opt.max_error_rate,
+        max_p99_latency: Duration::from_millis(opt.max_p99_latency_ms),
+    };
+
+    let report = run(&client, &funder, &options).await.unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
+    });
+    print!("{report}");
+    if let Err(exceeded) = report.check(&thresholds) {
+        for reason in exceeded {
+            println!("FAILED: {reason}");
+        }
+        std::process::exit(1);
+    }
+    println!("PASSED");
+}
@@ -0,0 +1,375 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Serve the rollup API from a replica which trusts only L1 and the sequencer, not the operator.
+//!
+//! The replica executes every block itself, checking each against the commitment posted to the
+//! HotShot contract, and keeps its own state, snapshots and cursor. It never sends anything to L1.
+//! Instead, it compares each state update the operator proves to the rollup contract with the state
+//! it computed, and reports the result as `contract_agreement` under `/executor`. Submitted
+//! transactions are proxied to the operator's API if one is given, and refused otherwise.
+
+use async_compatibility_layer::logging::setup_backtrace;
+use async_std::{channel, sync::RwLock, task::spawn};
+use clap::Parser;
+use ethers::types::Address;
+use example_l2::{
+    api::{serve, APIOptions, SubmitRoute, DEFAULT_READ_TIMEOUT},
+    cache::ReadCache,
+    executor::{run_executor_supervised, ExecutorOptions},
+    hooks::BlockHooks,
+    invariants::DEFAULT_INVARIANT_INTERVAL,
+    l1_updates::L1UpdateHistory,
+    logging::{init_logging, LogFormat, NODE},
+    migrations::{data_files, migrate_files},
+    retention::{RetentionConfig, DEFAULT_HISTORY_BLOCKS, DEFAULT_RECEIPTS_BLOCKS},
+    seed::initial_balances,
+    shutdown::close_on_signal,
+    snapshot::{load_latest_snapshot, SnapshotOptions},
+    state::{ChainConfig, State},
+    status::StatusHandle,
+    RollupVM,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use surf_disco::Url;
+
+/// The replica never signs anything, so the L1 account its client is built with is irrelevant.
+const UNUSED_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+#[derive(Parser, Clone, Debug)]
+struct Options {
+    /// Port where the rollup API will be served.
+    #[clap(short, long, env = "ESPRESSO_DEMO_ROLLUP_PORT", default_value = "8084")]
+    api_port: u16,
+
+    /// Which events to log, such as `info,rollup::executor=debug`. Defaults to `RUST_LOG`, or
+    /// `info`.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_LOG_FILTER")]
+    log_filter: Option<String>,
+
+    /// How to write log lines: `pretty` or `json`.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_LOG_FORMAT",
+        value_enum,
+        default_value_t = LogFormat::Pretty
+    )]
+    log_format: LogFormat,
+
+    /// URL of a HotShot query service.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_URL",
+        default_value = "http://localhost:50000"
+    )]
+    sequencer_url: Url,
+
+    /// Comma-separated list of URLs of other query services to fail over to.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_QUERY_REPLICA_URLS",
+        value_delimiter = ','
+    )]
+    query_replica_urls: Vec<Url>,
+
+    /// URL of layer 1 Ethereum JSON-RPC provider.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_L1_HTTP_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    l1_http_provider: Url,
+
+    /// URL of layer 1 Ethereum websocket provider.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_L1_WS_PROVIDER",
+        default_value = "ws://localhost:8545"
+    )]
+    l1_ws_provider: Url,
+
+    /// Chain ID the L1 providers must report.
+    #[clap(long, env = "ESPRESSO_DEMO_L1_CHAIN_ID")]
+    l1_chain_id: Option<u64>,
+
+    /// Address of HotShot contract on layer 1.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_HOTSHOT_ADDRESS",
+        default_value = "0x0116686e2291dbd5e317f47fadbfb43b599786ef"
+    )]
+    hotshot_address: Address,
+
+    /// Address of the rollup contract to follow.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_ADDRESS")]
+    rollup_address: Address,
+
+    /// VM ID of the rollup.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_VM_ID", default_value = "1")]
+    vm_id: u64,
+
+    /// Comma-separated list of ERC-20 tokens in the genesis chain configuration of the rollup.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DEPOSIT_ASSETS",
+        value_delimiter = ','
+    )]
+    deposit_assets: Vec<Address>,
+
+    /// Comma-separated list of VM IDs in the genesis chain configuration of the rollup.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_MESSAGE_SOURCES",
+        value_delimiter = ','
+    )]
+    message_sources: Vec<u64>,
+
+    /// Whether the genesis chain configuration rejects transfers to the zero address.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_REJECT_ZERO_DESTINATION")]
+    reject_zero_destination: bool,
+
+    /// Comma-separated list of addresses transfers to which the genesis chain configuration
+    /// rejects.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DENIED_DESTINATIONS",
+        value_delimiter = ','
+    )]
+    denied_destinations: Vec<Address>,
+
+    /// Account which the genesis chain configuration lets change the minimum fee.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_FEE_ADMIN")]
+    fee_admin: Option<Address>,
+
+    /// Number of L1 blocks the genesis chain configuration requires to be built on a deposit
+    /// before it is credited.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DEPOSIT_CONFIRMATIONS",
+        default_value = "0"
+    )]
+    deposit_confirmations: u64,
+
+    /// Whether the genesis chain configuration applies transactions enqueued on the rollup
+    /// contract.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_FORCED_INCLUSION")]
+    forced_inclusion: bool,
+
+    /// Base URL of the operator's rollup API, to proxy submitted transactions to.
+    ///
+    /// If not set, the replica refuses submissions.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_UPSTREAM_API_URL")]
+    upstream_api_url: Option<Url>,
+
+    /// Comma-separated list of origins allowed to make cross-origin requests to the API.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_CORS_ALLOWED_ORIGINS",
+        value_delimiter = ','
+    )]
+    cors_allowed_origins: Vec<String>,
+
+    /// Number of blocks the local state may lag behind the rollup contract before read endpoints
+    /// report that the replica is still syncing.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_MAX_SYNC_LAG", default_value = "0")]
+    max_sync_lag: u64,
+
+    /// Milliseconds allowed for API routes which read the local state.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_READ_TIMEOUT_MS",
+        default_value_t = DEFAULT_READ_TIMEOUT.as_millis() as u64
+    )]
+    read_timeout_ms: u64,
+
+    /// Read everything from the state on every request, instead of caching hot reads.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_DISABLE_READ_CACHE")]
+    disable_read_cache: bool,
+
+    /// Directory where evidence is written if a block does not match its commitment on layer 1.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DIAGNOSTICS_DIR",
+        default_value = "diagnostics"
+    )]
+    diagnostics_dir: PathBuf,
+
+    /// File where the replica records its progress, so it can resume quickly after a restart.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_CURSOR_PATH")]
+    cursor_path: Option<PathBuf>,
+
+    /// Directory where snapshots of the state are written, and resumed from on startup.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_SNAPSHOT_DIR")]
+    snapshot_dir: Option<PathBuf>,
+
+    /// Number of blocks executed between state snapshots.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_SNAPSHOT_INTERVAL",
+        default_value = "100"
+    )]
+    snapshot_interval: u64,
+
+    /// Number of state snapshots kept on disk.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_SNAPSHOTS_RETAINED",
+        default_value = "3"
+    )]
+    snapshots_retained: usize,
+
+    /// Number of recent blocks whose transaction receipts are kept.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_RECEIPTS_RETAINED_BLOCKS",
+        default_value_t = DEFAULT_RECEIPTS_BLOCKS
+    )]
+    receipts_retained_blocks: u64,
+
+    /// Number of recent blocks whose summaries and proofs are kept.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_HISTORY_RETAINED_BLOCKS",
+        default_value_t = DEFAULT_HISTORY_BLOCKS
+    )]
+    history_retained_blocks: u64,
+}
+
+#[async_std::main]
+async fn main() {
+    let opt = Options::parse();
+    if let Err(err) = init_logging(opt.log_filter.as_deref(), opt.log_format) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+    setup_backtrace();
+
+    if let Err(err) = data_files(opt.snapshot_dir.as_deref(), opt.cursor_path.as_deref())
+        .and_then(|files| migrate_files(&files))
+    {
+        tracing::error!(target: NODE, "Unable to migrate the replica's data: {err}");
+        std::process::exit(1);
+    }
+
+    // `stop` is kept until the end, so the channel only closes on a signal.
+    let (stop, shutdown) = channel::bounded(1);
+    let signals = stop.clone();
+    spawn(async move {
+        if let Err(err) = close_on_signal(signals).await {
+            tracing::error!(target: NODE, "Unable to handle shutdown signals: {err}");
+        }
+    });
+
+    let vm = RollupVM::new(opt.vm_id.into());
+    let snapshot = opt
+        .snapshot_dir
+        .as_ref()
+        .and_then(|dir| load_latest_snapshot(dir, &vm));
+    let state = snapshot.unwrap_or_else(|| {
+        State::from_initial_balances(initial_balances(), vm).with_chain_config(ChainConfig {
+            assets: opt.deposit_assets.iter().copied().collect(),
+            message_sources: opt.message_sources.iter().copied().collect(),
+            reject_zero_destination: opt.reject_zero_destination,
+            denied_destinations: opt.denied_destinations.iter().copied().collect(),
+            fee_admin: opt.fee_admin,
+            deposit_confirmations: opt.deposit_confirmations,
+            forced_inclusion: opt.forced_inclusion,
+            ..Default::default()
+        })
+    });
+    let state = Arc::new(RwLock::new(state));
+    let status = StatusHandle::default();
+    // Until a block is executed, the API serves the state it starts from.
+    let height = state.read().await.block_height();
+    status
+        .write()
+        .await
+        .set_local_height(height, Instant::now());
+
+    let read_cache = (!opt.disable_read_cache).then(ReadCache::default);
+    // The replica sends no proofs, so the operator's updates are recorded without being checked.
+    let l1_updates = L1UpdateHistory::default();
+    let api_options = APIOptions {
+        cors_allowed_origins: opt.cors_allowed_origins.clone(),
+        max_sync_lag: opt.max_sync_lag,
+        read_timeout: Duration::from_millis(opt.read_timeout_ms),
+        shutdown: Some(shutdown.clone()),
+        read_cache: read_cache.clone(),
+        submit_route: match &opt.upstream_api_url {
+            Some(url) => SubmitRoute::Upstream(url.clone()),
+            None => SubmitRoute::Disabled,
+        },
+        l1_updates: l1_updates.clone(),
+        ..APIOptions::new(opt.api_port, opt.sequencer_url.clone())
+    };
+
+    let executor_options = ExecutorOptions {
+        sequencer_urls: std::iter::once(opt.sequencer_url.clone())
+            .chain(opt.query_replica_urls.iter().cloned())
+            .collect(),
+        l1_http_provider: opt.l1_http_provider.clone(),
+        l1_ws_provider: opt.l1_ws_provider.clone(),
+        l1_chain_id: opt.l1_chain_id,
+        rollup_account_index: 0,
+        rollup_mnemonic: UNUSED_MNEMONIC.into(),
+        hotshot_address: opt.hotshot_address,
+        rollup_address: opt.rollup_address,
+        output_stream: None,
+        block_hooks: match read_cache {
+            Some(cache) => BlockHooks::default().with(cache),
+            None => BlockHooks::default(),
+        },
+        webhooks: vec![],
+        alerts: Default::default(),
+        fee_bump: Default::default(),
+        balance_monitor: Default::default(),
+        lag_alerts: Default::default(),
+        watchdog: Default::default(),
+        invariant_interval: DEFAULT_INVARIANT_INTERVAL,
+        event_backfill: Default::default(),
+        retention: RetentionConfig {
+            receipts_blocks: opt.receipts_retained_blocks,
+            history_blocks: opt.history_retained_blocks,
+            proofs_blocks: opt.history_retained_blocks,
+            snapshots: opt.snapshots_retained,
+        },
+        submission_url: None,
+        diagnostics_dir: opt.diagnostics_dir.clone(),
+        cursor_path: opt.cursor_path.clone(),
+        snapshots: opt.snapshot_dir.clone().map(|dir| SnapshotOptions {
+            dir,
+            interval: opt.snapshot_interval,
+        }),
+        start: None,
+        // Only checked after sending proofs. The replica compares every state update instead, and
+        // keeps serving if one differs.
+        check_commitments: false,
+        verify_chain: true,
+        submitter_lease_blocks: None,
+        read_only: true,
+        l1_updates,
+        proof_interval: 1,
+        proof_queue_depth: 16,
+        drain_timeout: Duration::ZERO,
+        shutdown: Some(shutdown),
+    };
+
+    tracing::info!(target: NODE, "Launching read-only rollup replica");
+    {
+        let state = state.clone();
+        let status = status.clone();
+        spawn(async move { serve(&api_options, state, status).await.unwrap() });
+    }
+    run_executor_supervised(&executor_options, state.clone(), status)
+        .await
+        .unwrap();
+    drop(stop);
+
+    let height = state.read().await.block_height();
+    tracing::info!(target: NODE, height, "shutdown complete: executed {height} blocks");
+}
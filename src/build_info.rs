@@ -0,0 +1,50 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Which build of the node is running, as embedded at compile time by the build script.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// The version, git commit and build time of a build of this crate.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// The crate version.
+    pub version: String,
+    /// The git commit the build was made from, or `unknown` if it was built outside a checkout.
+    pub git_commit: String,
+    /// When the build was made, in seconds since the Unix epoch.
+    pub build_timestamp: u64,
+}
+
+impl BuildInfo {
+    /// The build of the running binary.
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").into(),
+            git_commit: env!("EXAMPLE_L2_GIT_COMMIT").into(),
+            build_timestamp: env!("EXAMPLE_L2_BUILD_TIMESTAMP")
+                .parse()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for BuildInfo {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "example-l2 {} (commit {}, built at {})",
+            self.version, self.git_commit, self.build_timestamp
+        )
+    }
+}
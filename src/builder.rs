@@ -0,0 +1,342 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Building, signing and submitting transactions through the rollup API.
+//!
+//! [`TransactionBuilder`] fills in whatever a transaction needs from the API of a rollup node, so
+//! clients only have to say who gets how much. The nonce is the sender's next one according to the
+//! node, and the VM is the one the node serves, so the signature is only valid on that rollup. The
+//! priority defaults to zero, since the rollup charges no minimum fee. Every field can be set
+//! explicitly instead; a fully specified transaction is built without contacting the node.
+
+use ethers::{signers::Signer, types::Address, types::H256};
+use snafu::Snafu;
+use std::time::Duration;
+use surf_disco::Url;
+
+use crate::address::checksummed;
+use crate::client::{ClientError, RollupClient};
+use crate::state::{Amount, Nonce};
+use crate::transaction::{SignedTransaction, Transaction};
+
+/// How long to wait for the rollup API to respond before giving up on it, by default.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Snafu)]
+pub enum BuilderError {
+    #[snafu(display("Rollup API at {url} is unreachable."))]
+    Unreachable { url: Url },
+    #[snafu(display("Transaction has no {field}."))]
+    MissingField { field: &'static str },
+    #[snafu(display("Unable to fetch the nonce of {}: {source}", checksummed(address)))]
+    FetchNonce {
+        address: Address,
+        source: ClientError,
+    },
+    #[snafu(display("Unable to fetch the VM of the rollup: {source}"))]
+    FetchVm { source: ClientError },
+    #[snafu(display("Unable to sign the transaction: {reason}"))]
+    Signing { reason: String },
+    #[snafu(display("Rollup API rejected the transaction: {source}"))]
+    Submit { source: ClientError },
+}
+
+/// A transaction under construction, sent by `signer` through the rollup API at a base URL.
+///
+/// The base URL is the one the API is served at, such as `http://localhost:8082`, under which the
+/// rollup routes live at `rollup/`.
+#[derive(Clone)]
+pub struct TransactionBuilder<S> {
+    url: Url,
+    client: RollupClient,
+    signer: S,
+    connect_timeout: Duration,
+    destination: Option<Address>,
+    amount: Option<Amount>,
+    nonce: Option<Nonce>,
+    priority: Amount,
+    vm_id: Option<u64>,
+    asset: Option<Address>,
+}
+
+impl<S: Signer> TransactionBuilder<S> {
+    pub fn new(api_url: Url, signer: S) -> Self {
+        Self {
+            client: RollupClient::new(api_url.clone()),
+            url: api_url,
+            signer,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            destination: None,
+            amount: None,
+            nonce: None,
+            priority: 0,
+            vm_id: None,
+            asset: None,
+        }
+    }
+
+    pub fn destination(mut self, destination: Address) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    pub fn amount(mut self, amount: Amount) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Use `nonce` instead of the sender's next nonce according to the node.
+    pub fn nonce(mut self, nonce: Nonce) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn priority(mut self, priority: Amount) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sign the transaction for the rollup with `vm_id` instead of the one the node serves.
+    pub fn vm_id(mut self, vm_id: u64) -> Self {
+        self.vm_id = Some(vm_id);
+        self
+    }
+
+    /// Transfer the deposited asset `asset` instead of the native one. The tip is still paid in the
+    /// native asset.
+    pub fn asset(mut self, asset: Address) -> Self {
+        self.asset = Some(asset);
+        self
+    }
+
+    /// How long to wait for the rollup API to respond before failing with
+    /// [`BuilderError::Unreachable`].
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// The transaction, with any fields not set filled in, signed by the signer.
+    pub async fn build(&self) -> Result<SignedTransaction, BuilderError> {
+        let destination = self.destination.ok_or(BuilderError::MissingField {
+            field: "destination",
+        })?;
+        let amount = self
+            .amount
+            .ok_or(BuilderError::MissingField { field: "amount" })?;
+        let nonce = match self.nonce {
+            Some(nonce) => nonce,
+            None => self.next_nonce().await?,
+        };
+        let vm_id = match self.vm_id {
+            Some(vm_id) => vm_id,
+            None => self.fetch_vm_id().await?,
+        };
+        let transaction = Transaction {
+            amount,
+            destination,
+            nonce,
+            priority: self.priority,
+            vm_id,
+            asset: self.asset,
+        };
+        SignedTransaction::new(transaction, &self.signer)
+            .await
+            .map_err(|err| BuilderError::Signing {
+                reason: err.to_string(),
+            })
+    }
+
+    /// Build the transaction and submit it to the rollup API, returning its hash.
+    ///
+    /// This returns once the node has forwarded the transaction to the sequencer. Its progress
+    /// from there can be followed at `rollup/status/:hash`.
+    pub async fn submit(&self) -> Result<H256, BuilderError> {
+        let transaction = self.build().await?;
+        self.connect().await?;
+        self.client
+            .submit(&transaction)
+            .await
+            .map_err(|source| BuilderError::Submit { source })
+    }
+
+    async fn next_nonce(&self) -> Result<Nonce, BuilderError> {
+        self.connect().await?;
+        let address = self.signer.address();
+        let nonce = self
+            .client
+            .nonce(address)
+            .await
+            .map_err(|source| BuilderError::FetchNonce { address, source })?;
+        Ok(nonce + 1)
+    }
+
+    async fn fetch_vm_id(&self) -> Result<u64, BuilderError> {
+        self.connect().await?;
+        let info = self
+            .client
+            .info()
+            .await
+            .map_err(|source| BuilderError::FetchVm { source })?;
+        Ok(info.vm_id)
+    }
+
+    async fn connect(&self) -> Result<(), BuilderError> {
+        if self.client.connect(Some(self.connect_timeout)).await {
+            Ok(())
+        } else {
+            Err(BuilderError::Unreachable {
+                url: self.url.clone(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{serve, APIOptions};
+    use crate::state::State;
+    use crate::transaction::DEFAULT_VM_ID;
+    use crate::RollupVM;
+    use async_std::{sync::RwLock, task::spawn};
+    use ethers::signers::LocalWallet;
+    use portpicker::pick_unused_port;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::sync::Arc;
+
+    const GENESIS_BALANCE: Amount = 1000;
+
+    /// Serve the rollup API for a state in which `wallet` holds the genesis balance, forwarding
+    /// submissions to a stand-in sequencer which accepts everything.
+    async fn serve_api(wallet: &LocalWallet) -> Url {
+        let sequencer_port = pick_unused_port().expect("No ports free");
+        let mut sequencer = tide::new();
+        sequencer
+            .at("/submit/submit")
+            .post(|_: tide::Request<()>| async {
+                tide::Body::from_json(&()).map(tide::Response::from)
+            });
+        spawn(sequencer.listen(format!("0.0.0.0:{sequencer_port}")));
+        let sequencer_url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let mut state = State::from_initial_balances(
+            [(wallet.address(), GENESIS_BALANCE)],
+            RollupVM::new(1.into()),
+        );
+        // Move the sender's nonce past its initial value, so that a nonce filled in from the API
+        // is distinguishable from a default.
+        let signed = SignedTransaction::new_with_wallet(
+            Transaction {
+                amount: 1,
+                destination: Address::random(),
+                nonce: 1,
+                priority: 0,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            },
+            wallet,
+        )
+        .await;
+        state.apply_transactions(vec![signed]);
+
+        let port = pick_unused_port().expect("No ports free");
+        let api_url: Url = format!("http://localhost:{port}").parse().unwrap();
+        let options = APIOptions::new(port, sequencer_url);
+        let state = Arc::new(RwLock::new(state));
+        spawn(async move { serve(&options, state, Default::default()).await });
+        api_url
+    }
+
+    #[async_std::test]
+    async fn test_fill_from_api() {
+        let wallet = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let api_url = serve_api(&wallet).await;
+        let destination = Address::random();
+        let builder = TransactionBuilder::new(api_url, wallet.clone())
+            .destination(destination)
+            .amount(10);
+
+        let signed = builder.build().await.unwrap();
+        assert_eq!(
+            signed.transaction,
+            Transaction {
+                amount: 10,
+                destination,
+                nonce: 2,
+                priority: 0,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            }
+        );
+        assert_eq!(signed.sender().unwrap(), wallet.address());
+
+        // Submitting returns the hash of the transaction the builder would build.
+        assert_eq!(builder.submit().await.unwrap(), signed.hash());
+    }
+
+    #[async_std::test]
+    async fn test_overrides() {
+        let wallet = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let destination = Address::random();
+        let expected = Transaction {
+            amount: 5,
+            destination,
+            nonce: 42,
+            priority: 3,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+
+        // With every field given, nothing is fetched, so no API is needed.
+        let unreachable: Url = "http://localhost:1".parse().unwrap();
+        let builder = TransactionBuilder::new(unreachable, wallet)
+            .destination(destination)
+            .amount(5)
+            .nonce(42)
+            .priority(3)
+            .vm_id(DEFAULT_VM_ID)
+            .connect_timeout(Duration::from_millis(100));
+        assert_eq!(builder.build().await.unwrap().transaction, expected);
+
+        // Submitting does need the API.
+        assert!(matches!(
+            builder.submit().await,
+            Err(BuilderError::Unreachable { .. })
+        ));
+    }
+
+    #[async_std::test]
+    async fn test_errors() {
+        let wallet = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let unreachable: Url = "http://localhost:1".parse().unwrap();
+        let builder = TransactionBuilder::new(unreachable, wallet)
+            .connect_timeout(Duration::from_millis(100));
+        assert!(matches!(
+            builder.build().await,
+            Err(BuilderError::MissingField {
+                field: "destination"
+            })
+        ));
+
+        let builder = builder.destination(Address::random());
+        assert!(matches!(
+            builder.build().await,
+            Err(BuilderError::MissingField { field: "amount" })
+        ));
+
+        // The nonce is needed from the API, which is not there.
+        let err = builder.amount(1).build().await.unwrap_err();
+        assert!(matches!(err, BuilderError::Unreachable { .. }));
+        assert_eq!(
+            err.to_string(),
+            "Rollup API at http://localhost:1/ is unreachable."
+        );
+    }
+}
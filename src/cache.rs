@@ -0,0 +1,285 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! A cache of the API's hottest reads.
+//!
+//! Explorers poll balances and the latest block far more often than blocks are executed, and every
+//! read of the state takes the lock the executor needs to apply each block. The [`ReadCache`]
+//! answers repeated reads without touching the state. Misses are read from the state and cached,
+//! and the executor brings the cache up to date after each block by running it as a
+//! [`BlockHook`].
+//!
+//! Every balance in the cache was read from the state at the same height, the height the cache
+//! records, and the cache only ever moves to a later height. A response served from the cache
+//! therefore never mixes blocks, never reflects a block which is still being applied, and is never
+//! older than a response served before it.
+
+use ethers::types::Address;
+use futures::future::{BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::api::RollupInfo;
+use crate::hooks::BlockHook;
+use crate::state::{Amount, BlockResult, BlockSummary, State};
+
+/// Number of balances cached, by default.
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// How often the cache has answered reads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Reads answered from the cache.
+    pub hits: u64,
+    /// Reads which had to go to the state.
+    pub misses: u64,
+    /// Number of balances currently cached.
+    pub balances: usize,
+}
+
+/// Read-through cache of balances, the latest block summary and the node's info, shared between
+/// the API and the executor.
+///
+/// The cache is only kept up to date while it is registered as a block hook of the executor which
+/// updates the state it caches.
+#[derive(Clone)]
+pub struct ReadCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    entries: RwLock<Entries>,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Default)]
+struct Entries {
+    /// Height of the state every balance below was read from.
+    height: u64,
+    balances: HashMap<Address, Amount>,
+    /// Summary of the last block executed at `height`.
+    latest: Option<BlockSummary>,
+    info: Option<RollupInfo>,
+}
+
+impl Entries {
+    /// Move to the state at `height`, forgetting everything read from an earlier one. Returns
+    /// whether values read at `height` may be cached.
+    fn advance(&mut self, height: u64) -> bool {
+        if height > self.height {
+            self.height = height;
+            self.balances.clear();
+            self.latest = None;
+        }
+        height == self.height
+    }
+}
+
+impl Debug for ReadCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadCache")
+            .field("capacity", &self.inner.capacity)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl Default for ReadCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+impl ReadCache {
+    /// An empty cache holding at most `capacity` balances. Balances of further addresses are read
+    /// from the state each time until the cache next moves to a new block.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                entries: Default::default(),
+                capacity,
+                hits: Default::default(),
+                misses: Default::default(),
+            }),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.inner.hits.load(Ordering::Relaxed),
+            misses: self.inner.misses.load(Ordering::Relaxed),
+            balances: self.inner.entries.read().unwrap().balances.len(),
+        }
+    }
+
+    /// Bring the cache up to date with `state`, reading every cached balance again from it.
+    pub fn refresh(&self, state: &State) {
+        let height = state.block_height();
+        let mut entries = self.inner.entries.write().unwrap();
+        if height < entries.height {
+            return;
+        }
+        entries.height = height;
+        for (address, balance) in entries.balances.iter_mut() {
+            *balance = state.get_balance(address);
+        }
+        entries.latest = height
+            .checked_sub(1)
+            .and_then(|latest| state.block_summary(latest))
+            .cloned();
+        if let Some(info) = &mut entries.info {
+            info.min_fee = *state.min_fee();
+        }
+    }
+
+    /// The balance of `address`, with the height of the state it was read from.
+    pub(crate) fn balance(&self, address: &Address) -> Option<(u64, Amount)> {
+        let entries = self.inner.entries.read().unwrap();
+        let balance = entries
+            .balances
+            .get(address)
+            .map(|balance| (entries.height, *balance));
+        drop(entries);
+        self.count(balance)
+    }
+
+    /// Cache the balance of `address` read from the state at `height`.
+    pub(crate) fn insert_balance(&self, height: u64, address: Address, balance: Amount) {
+        let mut entries = self.inner.entries.write().unwrap();
+        if entries.advance(height) && entries.balances.len() < self.inner.capacity {
+            entries.balances.insert(address, balance);
+        }
+    }
+
+    /// The summary of the block at `height`, if it is the latest block, with the height of the
+    /// state it was read from.
+    pub(crate) fn block(&self, height: u64) -> Option<(u64, BlockSummary)> {
+        let entries = self.inner.entries.read().unwrap();
+        let summary = entries
+            .latest
+            .clone()
+            .filter(|summary| summary.height == height)
+            .map(|summary| (entries.height, summary));
+        drop(entries);
+        self.count(summary)
+    }
+
+    /// Cache `summary`, read from the state at `height`, if it is the summary of the latest block.
+    pub(crate) fn insert_block(&self, height: u64, summary: &BlockSummary) {
+        let mut entries = self.inner.entries.write().unwrap();
+        if entries.advance(height) && summary.height + 1 == height {
+            entries.latest = Some(summary.clone());
+        }
+    }
+
+    /// The node's info, with the height of the state it was read from.
+    pub(crate) fn info(&self) -> Option<(u64, RollupInfo)> {
+        let entries = self.inner.entries.read().unwrap();
+        let info = entries.info.clone().map(|info| (entries.height, info));
+        drop(entries);
+        self.count(info)
+    }
+
+    /// Cache the node's info, read from the state at `height`. It only changes with the minimum
+    /// fee, which [`refresh`](Self::refresh) keeps current.
+    pub(crate) fn insert_info(&self, height: u64, info: RollupInfo) {
+        let mut entries = self.inner.entries.write().unwrap();
+        if entries.advance(height) {
+            entries.info = Some(info);
+        }
+    }
+
+    fn count<T>(&self, value: Option<T>) -> Option<T> {
+        let counter = if value.is_some() {
+            &self.inner.hits
+        } else {
+            &self.inner.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+}
+
+impl BlockHook for ReadCache {
+    fn on_block<'a>(
+        &'a self,
+        _height: u64,
+        _block: &'a BlockResult,
+        state: &'a State,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        self.refresh(state);
+        async { Ok(()) }.boxed()
+    }
+
+    fn name(&self) -> String {
+        "read cache".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{SignedTransaction, Transaction, DEFAULT_VM_ID};
+    use crate::RollupVM;
+    use ethers::signers::{LocalWallet, Signer};
+    use sequencer_utils::u256_to_commitment;
+
+    #[async_std::test]
+    async fn test_refresh() {
+        let alice = LocalWallet::new(&mut rand::thread_rng());
+        let bob = Address::random();
+        let mut state =
+            State::from_initial_balances([(alice.address(), 100)], RollupVM::new(1.into()));
+        let cache = ReadCache::new(1);
+
+        assert_eq!(cache.balance(&alice.address()), None);
+        cache.insert_balance(0, alice.address(), 100);
+        // Beyond the capacity, balances are not cached.
+        cache.insert_balance(0, bob, 0);
+        assert_eq!(cache.balance(&alice.address()), Some((0, 100)));
+        assert_eq!(cache.balance(&bob), None);
+
+        let transaction = Transaction {
+            amount: 10,
+            destination: bob,
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let signed = SignedTransaction::new_with_wallet(transaction, &alice).await;
+        let block = u256_to_commitment(1.into()).unwrap();
+        state.apply_block_with_transactions(block, vec![signed.into()]);
+
+        // Refreshing reads the cached balances again from the new state.
+        cache.refresh(&state);
+        assert_eq!(cache.balance(&alice.address()), Some((1, 90)));
+
+        // A balance read from an older state is never cached, and one read from a newer state
+        // replaces everything read from older ones.
+        cache.insert_balance(0, alice.address(), 100);
+        assert_eq!(cache.balance(&alice.address()), Some((1, 90)));
+        cache.insert_balance(2, bob, 20);
+        assert_eq!(cache.balance(&alice.address()), None);
+        assert_eq!(cache.balance(&bob), Some((2, 20)));
+        cache.refresh(&state);
+        assert_eq!(cache.balance(&bob), Some((2, 20)));
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 5,
+                misses: 3,
+                balances: 1,
+            }
+        );
+    }
+}
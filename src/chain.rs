@@ -0,0 +1,226 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Checks that consecutive HotShot blocks form a chain.
+//!
+//! Each block is checked against its commitment in the HotShot contract, but that only shows the
+//! block matches one contract entry. A bogus entry could splice in a block from somewhere else
+//! entirely. The [`ChainVerifier`] additionally checks each block against the one before it.
+//!
+//! HotShot headers do not yet carry a commitment to their parent, so the linkage checked here is
+//! what the headers do carry: heights are consecutive, and timestamps and the L1 blocks each header
+//! references never go backwards. A spliced block has to agree with both of its neighbours on all
+//! of these to go unnoticed.
+
+use sequencer::Header;
+use snafu::Snafu;
+
+/// The parts of a header which link it to the previous one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockLink {
+    pub height: u64,
+    pub timestamp: u64,
+    pub l1_head: u64,
+    pub l1_finalized: Option<u64>,
+}
+
+impl BlockLink {
+    pub fn new(height: u64, header: &Header) -> Self {
+        Self {
+            height,
+            timestamp: header.timestamp,
+            l1_head: header.l1_head,
+            l1_finalized: header.l1_finalized.as_ref().map(|block| block.number),
+        }
+    }
+}
+
+/// Where the chain of HotShot blocks breaks.
+#[derive(Clone, Debug, PartialEq, Eq, Snafu)]
+pub enum ChainError {
+    #[snafu(display("Expected block {expected}, got block {actual}."))]
+    HeightGap { expected: u64, actual: u64 },
+    #[snafu(display(
+        "Block {height} has timestamp {timestamp}, earlier than the previous block's {parent}."
+    ))]
+    TimestampRegressed {
+        height: u64,
+        parent: u64,
+        timestamp: u64,
+    },
+    #[snafu(display(
+        "Block {height} references L1 block {l1_head}, earlier than the previous block's {parent}."
+    ))]
+    L1HeadRegressed {
+        height: u64,
+        parent: u64,
+        l1_head: u64,
+    },
+    #[snafu(display(
+        "Block {height} references finalized L1 block {l1_finalized:?}, earlier than the previous \
+        block's {parent}."
+    ))]
+    L1FinalizedRegressed {
+        height: u64,
+        parent: u64,
+        l1_finalized: Option<u64>,
+    },
+}
+
+impl ChainError {
+    /// Height of the first block which does not follow from the one before it.
+    pub fn height(&self) -> u64 {
+        match self {
+            Self::HeightGap { actual, .. } => *actual,
+            Self::TimestampRegressed { height, .. }
+            | Self::L1HeadRegressed { height, .. }
+            | Self::L1FinalizedRegressed { height, .. } => *height,
+        }
+    }
+}
+
+/// Tracks the last verified block and checks that each new block follows from it.
+#[derive(Clone, Debug, Default)]
+pub struct ChainVerifier {
+    last: Option<BlockLink>,
+}
+
+impl ChainVerifier {
+    /// A verifier which trusts `anchor` and checks every block after it.
+    ///
+    /// Without an anchor, the first block verified is trusted as the start of the chain.
+    pub fn new(anchor: Option<BlockLink>) -> Self {
+        Self { last: anchor }
+    }
+
+    /// Check that `block` follows the last verified block, and make it the last verified block.
+    ///
+    /// On error the last verified block is unchanged.
+    pub fn verify(&mut self, block: BlockLink) -> Result<(), ChainError> {
+        if let Some(parent) = &self.last {
+            check_link(parent, &block)?;
+        }
+        self.last = Some(block);
+        Ok(())
+    }
+}
+
+fn check_link(parent: &BlockLink, block: &BlockLink) -> Result<(), ChainError> {
+    if block.height != parent.height + 1 {
+        return Err(ChainError::HeightGap {
+            expected: parent.height + 1,
+            actual: block.height,
+        });
+    }
+    if block.timestamp < parent.timestamp {
+        return Err(ChainError::TimestampRegressed {
+            height: block.height,
+            parent: parent.timestamp,
+            timestamp: block.timestamp,
+        });
+    }
+    if block.l1_head < parent.l1_head {
+        return Err(ChainError::L1HeadRegressed {
+            height: block.height,
+            parent: parent.l1_head,
+            l1_head: block.l1_head,
+        });
+    }
+    if let Some(parent_finalized) = parent.l1_finalized {
+        if block.l1_finalized.unwrap_or(0) < parent_finalized {
+            return Err(ChainError::L1FinalizedRegressed {
+                height: block.height,
+                parent: parent_finalized,
+                l1_finalized: block.l1_finalized,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(len: u64) -> Vec<BlockLink> {
+        (0..len)
+            .map(|height| BlockLink {
+                height,
+                timestamp: 1_700_000_000 + height,
+                l1_head: 100 + height / 2,
+                l1_finalized: (height >= 2).then_some(90 + height / 2),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_chain() {
+        let mut verifier = ChainVerifier::default();
+        for block in chain(10) {
+            verifier.verify(block).unwrap();
+        }
+
+        // Starting from a trusted anchor in the middle of the chain.
+        let blocks = chain(10);
+        let mut verifier = ChainVerifier::new(Some(blocks[4]));
+        for block in &blocks[5..] {
+            verifier.verify(*block).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_spliced_chain() {
+        let blocks = chain(10);
+
+        // A block from much later in the chain, relabeled with the expected height, references L1
+        // blocks the next block does not.
+        let mut spliced = blocks.clone();
+        spliced[5] = BlockLink {
+            height: 5,
+            ..chain(100)[80]
+        };
+        let mut verifier = ChainVerifier::default();
+        let err = spliced
+            .into_iter()
+            .find_map(|block| verifier.verify(block).err())
+            .unwrap();
+        assert_eq!(err.height(), 6);
+        assert!(matches!(err, ChainError::TimestampRegressed { .. }));
+
+        // A block from an older chain regresses straight away.
+        let mut spliced = blocks.clone();
+        spliced[5] = BlockLink {
+            height: 5,
+            timestamp: blocks[5].timestamp,
+            ..blocks[0]
+        };
+        let mut verifier = ChainVerifier::default();
+        let err = spliced
+            .into_iter()
+            .find_map(|block| verifier.verify(block).err())
+            .unwrap();
+        assert_eq!(
+            err,
+            ChainError::L1HeadRegressed {
+                height: 5,
+                parent: blocks[4].l1_head,
+                l1_head: blocks[0].l1_head,
+            }
+        );
+
+        // Skipping a block.
+        let mut verifier = ChainVerifier::new(Some(blocks[3]));
+        assert_eq!(
+            verifier.verify(blocks[5]),
+            Err(ChainError::HeightGap {
+                expected: 4,
+                actual: 5
+            })
+        );
+        // The rejected block is not trusted.
+        verifier.verify(blocks[4]).unwrap();
+    }
+}
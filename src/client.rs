@@ -0,0 +1,416 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! A typed client for the rollup API.
+//!
+//! [`RollupClient`] has a method for each route of the API, taking and returning the types the
+//! server uses, so services built on the rollup do not have to spell out paths and JSON shapes.
+//! Errors returned by the server arrive as the [`ApiError`] it sent.
+
+use async_std::{future::timeout, task::sleep};
+use ethers::types::{Address, H256};
+use futures::stream::{self, BoxStream, StreamExt};
+use snafu::Snafu;
+use std::collections::VecDeque;
+use std::time::Duration;
+use surf_disco::{Client, Url};
+
+use crate::api::{AccountInfo, Balances, RollupInfo};
+use crate::balance_history::BalancePoint;
+use crate::error::ApiError;
+use crate::json::{
+    CrossVmMessageJson, MultisigJson, PreparedTransactionJson, ReceiptJson, RollupTransactionJson,
+    UnsignedTransactionJson,
+};
+use crate::prover::{EncodedProof, Proof};
+use crate::state::{Amount, BlockSummary, Nonce, ProofTransaction};
+use crate::status::{BlockFinality, BlockVerification, ConfirmedBalance, ExecutorStatus};
+use crate::submission::{SubmissionTicket, SubmitResponse, TransactionStatus};
+use crate::transaction::RollupTransaction;
+
+/// How often to poll the API while waiting for something to happen, by default.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Debug, Snafu)]
+pub enum ClientError {
+    #[snafu(display("{source}"))]
+    Api { source: ApiError },
+    #[snafu(display("Transaction {hash:?} could not be forwarded to the sequencer: {reason}"))]
+    NotForwarded { hash: H256, reason: String },
+    #[snafu(display("Transaction {hash:?} was not sequenced within {timeout:?}."))]
+    NotSequenced { hash: H256, timeout: Duration },
+}
+
+impl From<ApiError> for ClientError {
+    fn from(source: ApiError) -> Self {
+        Self::Api { source }
+    }
+}
+
+/// A client of the rollup API served at a base URL.
+///
+/// The base URL is the one the API is served at, such as `http://localhost:8082`, under which the
+/// rollup routes live at `rollup/`.
+#[derive(Clone)]
+pub struct RollupClient {
+    inner: Client<ApiError>,
+    poll_interval: Duration,
+}
+
+impl RollupClient {
+    pub fn new(api_url: Url) -> Self {
+        Self {
+            inner: Client::new(api_url),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// How often [`submit_and_wait`](Self::submit_and_wait) and
+    /// [`subscribe_blocks`](Self::subscribe_blocks) poll the API.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Wait for the API to come up, for at most `timeout` if given. Returns whether it did.
+    pub async fn connect(&self, timeout: Option<Duration>) -> bool {
+        self.inner.connect(timeout).await
+    }
+
+    pub async fn info(&self) -> Result<RollupInfo, ClientError> {
+        Ok(self.inner.get("rollup/info").send().await?)
+    }
+
+    pub async fn balance(&self, address: Address) -> Result<Amount, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/balance/{address:?}"))
+            .send()
+            .await?)
+    }
+
+    /// The balance of `address`, even if the node is still catching up and the balance is stale.
+    pub async fn stale_balance(&self, address: Address) -> Result<Amount, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/balance/{address:?}/true"))
+            .send()
+            .await?)
+    }
+
+    /// The balance of `address` in `asset`, the L1 token of an asset deposited into the rollup.
+    pub async fn asset_balance(
+        &self,
+        address: Address,
+        asset: Address,
+    ) -> Result<Amount, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/balance/{address:?}/asset/{asset:?}"))
+            .send()
+            .await?)
+    }
+
+    /// The balances of `address` in the native asset and in every asset of the rollup.
+    pub async fn balances(&self, address: Address) -> Result<Balances, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/balances/{address:?}"))
+            .send()
+            .await?)
+    }
+
+    /// The balance of `address` as of the last block verified on L1.
+    pub async fn confirmed_balance(
+        &self,
+        address: Address,
+    ) -> Result<ConfirmedBalance, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/confirmed_balance/{address:?}"))
+            .send()
+            .await?)
+    }
+
+    /// The nonce of the last transaction applied from `address`. Its next transaction uses this
+    /// plus one.
+    pub async fn nonce(&self, address: Address) -> Result<Nonce, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/nonce/{address:?}"))
+            .send()
+            .await?)
+    }
+
+    /// The configuration of the multisig account at `address`.
+    pub async fn multisig(&self, address: Address) -> Result<MultisigJson, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/multisig/{address:?}"))
+            .send()
+            .await?)
+    }
+
+    /// Everything known about the account at `address`, which is empty if the rollup has never
+    /// seen it.
+    pub async fn account(&self, address: Address) -> Result<AccountInfo, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/account/{address:?}"))
+            .send()
+            .await?)
+    }
+
+    /// The balance of `address` at every `step`th height from `from` through `to`, or at every
+    /// height the node checkpoints every account if `step` is `None`.
+    pub async fn balance_history(
+        &self,
+        address: Address,
+        from: u64,
+        to: u64,
+        step: Option<u64>,
+    ) -> Result<Vec<BalancePoint>, ClientError> {
+        let mut path = format!("rollup/account/{address:?}/balance-history/{from}/{to}");
+        if let Some(step) = step {
+            path = format!("{path}/{step}");
+        }
+        Ok(self.inner.get(&path).send().await?)
+    }
+
+    /// The messages delivered to `address` from other rollups, in the order they were delivered.
+    pub async fn messages(&self, address: Address) -> Result<Vec<CrossVmMessageJson>, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/messages/{address:?}"))
+            .send()
+            .await?)
+    }
+
+    /// The transfer described by `request`, with the fields it leaves out filled in by the node,
+    /// and the encoding and digest to sign for it.
+    pub async fn prepare(
+        &self,
+        request: &UnsignedTransactionJson,
+    ) -> Result<PreparedTransactionJson, ClientError> {
+        Ok(self
+            .inner
+            .post("rollup/prepare")
+            .body_json(request)?
+            .send()
+            .await?)
+    }
+
+    /// Submit a transaction of any kind, such as a
+    /// [`SignedTransaction`](crate::transaction::SignedTransaction), returning its hash once the
+    /// node has forwarded it to the sequencer.
+    pub async fn submit<T>(&self, transaction: &T) -> Result<H256, ClientError>
+    where
+        T: Clone + Into<RollupTransaction>,
+    {
+        let transaction: RollupTransaction = transaction.clone().into();
+        self.inner
+            .post::<Option<SubmissionTicket>>("rollup/submit")
+            .body_json(&RollupTransactionJson::from(&transaction))?
+            .send()
+            .await?;
+        Ok(transaction.hash())
+    }
+
+    /// Submit a transaction, asking for how to enqueue it on L1 instead if the node cannot reach
+    /// the sequencer, as described under [`forced`](crate::forced).
+    ///
+    /// Fails with [`ApiError::FallbackUnavailable`](crate::error::ApiError::FallbackUnavailable) if
+    /// the rollup does not include transactions enqueued on L1.
+    pub async fn submit_with_fallback<T>(
+        &self,
+        transaction: &T,
+    ) -> Result<SubmitResponse, ClientError>
+    where
+        T: Clone + Into<RollupTransaction>,
+    {
+        let transaction: RollupTransaction = transaction.clone().into();
+        Ok(self
+            .inner
+            .post::<SubmitResponse>("rollup/submit/fallback/l1")
+            .body_json(&RollupTransactionJson::from(&transaction))?
+            .send()
+            .await?)
+    }
+
+    /// Submit a transaction to be forwarded to the sequencer in the background.
+    pub async fn submit_async<T>(&self, transaction: &T) -> Result<SubmissionTicket, ClientError>
+    where
+        T: Clone + Into<RollupTransaction>,
+    {
+        let transaction: RollupTransaction = transaction.clone().into();
+        let ticket = self
+            .inner
+            .post::<Option<SubmissionTicket>>("rollup/submit/true")
+            .body_json(&RollupTransactionJson::from(&transaction))?
+            .send()
+            .await?;
+        Ok(ticket.expect("asynchronous submission responds with a ticket"))
+    }
+
+    /// Submit a transaction and wait, for at most `wait`, until it is executed, returning its
+    /// receipt.
+    ///
+    /// The receipt says whether the transaction was applied; a transaction which was executed but
+    /// not applied is not an error here.
+    pub async fn submit_and_wait<T>(
+        &self,
+        transaction: &T,
+        wait: Duration,
+    ) -> Result<ReceiptJson, ClientError>
+    where
+        T: Clone + Into<RollupTransaction>,
+    {
+        let hash = self.submit(transaction).await?;
+        timeout(wait, async {
+            loop {
+                if let Some(receipt) = self.receipt(hash).await? {
+                    return Ok(receipt);
+                }
+                sleep(self.poll_interval).await;
+            }
+        })
+        .await
+        .unwrap_or(Err(ClientError::NotSequenced {
+            hash,
+            timeout: wait,
+        }))
+    }
+
+    pub async fn status(&self, hash: H256) -> Result<TransactionStatus, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/status/{hash:?}"))
+            .send()
+            .await?)
+    }
+
+    /// The receipt of a transaction, or `None` if it has not been executed yet.
+    ///
+    /// Fails if the transaction could not be forwarded, or if its block is too old for the node to
+    /// still have its receipts.
+    pub async fn receipt(&self, hash: H256) -> Result<Option<ReceiptJson>, ClientError> {
+        let block = match self.status(hash).await? {
+            TransactionStatus::Sequenced { block, .. } => block,
+            TransactionStatus::Failed { reason } => {
+                return Err(ClientError::NotForwarded { hash, reason })
+            }
+            _ => return Ok(None),
+        };
+        Ok(self
+            .receipts(block)
+            .await?
+            .into_iter()
+            .find(|receipt| receipt.hash == hash))
+    }
+
+    /// The receipts of every rollup transaction in the block at `height`, in execution order.
+    pub async fn receipts(&self, height: u64) -> Result<Vec<ReceiptJson>, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/block/{height}/receipts"))
+            .send()
+            .await?)
+    }
+
+    pub async fn block(&self, height: u64) -> Result<BlockSummary, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/block/{height}"))
+            .send()
+            .await?)
+    }
+
+    /// Whether the proof covering the block at `height` has been accepted on L1.
+    pub async fn verification(&self, height: u64) -> Result<BlockVerification, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/block/{height}/verification"))
+            .send()
+            .await?)
+    }
+
+    /// The blocks proven by the L1 transaction `l1_tx`.
+    pub async fn proof_transaction(&self, l1_tx: H256) -> Result<ProofTransaction, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/l1-tx/{l1_tx:?}"))
+            .send()
+            .await?)
+    }
+
+    /// Whether the block at `height` can still be reverted by a fraud proof.
+    pub async fn finality(&self, height: u64) -> Result<BlockFinality, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/block/{height}/finality"))
+            .send()
+            .await?)
+    }
+
+    pub async fn proof(&self, height: u64) -> Result<Proof, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/block/{height}/proof"))
+            .send()
+            .await?)
+    }
+
+    /// The proof of the block at `height` as published, possibly compressed. Check it with
+    /// [`verify_encoded_block_proof`](crate::verify_encoded_block_proof).
+    pub async fn encoded_proof(&self, height: u64) -> Result<EncodedProof, ClientError> {
+        Ok(self
+            .inner
+            .get(&format!("rollup/block/{height}/proof/encoded"))
+            .send()
+            .await?)
+    }
+
+    /// The progress of the node's executor.
+    pub async fn sync_status(&self) -> Result<ExecutorStatus, ClientError> {
+        Ok(self.inner.get("rollup/executor").send().await?)
+    }
+
+    /// Summaries of the executed blocks starting at height `from`, as they are executed.
+    ///
+    /// The stream waits for blocks which have not been executed yet, so it only ends after an
+    /// error, which it yields.
+    pub fn subscribe_blocks(
+        &self,
+        from: u64,
+    ) -> BoxStream<'static, Result<BlockSummary, ClientError>> {
+        let client = self.clone();
+        stream::unfold(Some((from, VecDeque::new())), move |cursor| {
+            let client = client.clone();
+            async move {
+                let (mut next, mut buffer) = cursor?;
+                loop {
+                    if let Some(summary) = buffer.pop_front() {
+                        return Some((Ok(summary), Some((next, buffer))));
+                    }
+                    match client
+                        .inner
+                        .get::<Vec<BlockSummary>>(&format!("rollup/stream/blocks/{next}"))
+                        .send()
+                        .await
+                    {
+                        Ok(page) if page.is_empty() => sleep(client.poll_interval).await,
+                        Ok(page) => {
+                            next += page.len() as u64;
+                            buffer.extend(page);
+                        }
+                        Err(source) => return Some((Err(ClientError::Api { source }), None)),
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Running the HotShot commitment task alongside the executor.
+//!
+//! The executor only learns of HotShot blocks once their commitments are posted to the HotShot
+//! contract, which is the job of the sequencer's commitment task. A node started with
+//! `--with-commitment-task` runs the task itself, configured from the node's own options: the same
+//! L1 provider, HotShot contract and query service as the executor, and the same mnemonic, but
+//! another account, so that the task and the executor never compete for nonces.
+//!
+//! The task is restarted with backoff whenever it stops or panics, and stops with the executor on
+//! shutdown. Its health is reported as [`commitment_task`](ExecutorStatus::commitment_task) in
+//! the executor status.
+
+use async_std::channel::Receiver;
+use async_std::sync::Arc;
+use async_std::task::sleep;
+use futures::future::{self, FutureExt};
+use hotshot_contract_bindings::hot_shot::HotShot;
+use sequencer::hotshot_commitment::{run_hotshot_commitment_task, CommitmentTaskOptions};
+use sequencer::Header;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::executor::panic_message;
+use crate::logging::EXECUTOR;
+use crate::replicas::QueryReplicas;
+use crate::status::{CommitmentTaskStatus, StatusHandle};
+use crate::utils::create_provider;
+use crate::Options;
+
+/// How often the HotShot contract and the query service are checked for the health of the task.
+pub const COMMITMENT_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+const MIN_RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The commitment task of a node started with `opt`.
+pub fn commitment_task_options(opt: &Options) -> CommitmentTaskOptions {
+    CommitmentTaskOptions {
+        l1_provider: opt.l1_http_provider.clone(),
+        sequencer_mnemonic: opt.rollup_mnemonic.clone(),
+        sequencer_account_index: opt.commitment_account_index,
+        hotshot_address: opt.hotshot_address,
+        l1_chain_id: opt.l1_chain_id,
+        query_service_url: Some(opt.sequencer_url.clone()),
+        delay: None,
+    }
+}
+
+/// Run the commitment task with `opt` until `shutdown` fires, restarting it whenever it stops, and
+/// report its health in `status`.
+pub async fn run_commitment_task_supervised(
+    opt: &CommitmentTaskOptions,
+    status: StatusHandle,
+    shutdown: Option<Receiver<()>>,
+) {
+    status.write().await.commitment_task = Some(Default::default());
+    let supervise = async {
+        let mut backoff = MIN_RESTART_BACKOFF;
+        loop {
+            let started = Instant::now();
+            let reason = match AssertUnwindSafe(run_hotshot_commitment_task(opt))
+                .catch_unwind()
+                .await
+            {
+                Ok(()) => "commitment task stopped".to_string(),
+                Err(panic) => format!("commitment task panicked: {}", panic_message(&*panic)),
+            };
+            // A task which ran for a while before failing made progress, so it is restarted
+            // promptly.
+            if started.elapsed() > MAX_RESTART_BACKOFF {
+                backoff = MIN_RESTART_BACKOFF;
+            }
+            tracing::warn!(target: EXECUTOR, "restarting commitment task in {backoff:?}: {reason}");
+            if let Some(task) = status.write().await.commitment_task.as_mut() {
+                task.restarts += 1;
+                task.last_failure = Some(reason);
+            }
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+        }
+    };
+    let stopped = async {
+        match &shutdown {
+            Some(shutdown) => {
+                shutdown.recv().await.ok();
+            }
+            None => future::pending().await,
+        }
+    };
+    let run = future::join(supervise, check_commitments(opt, &status));
+    future::select(Box::pin(run), Box::pin(stopped)).await;
+    tracing::info!(target: EXECUTOR, "commitment task shutting down");
+}
+
+/// Track the blocks committed to the HotShot contract, and how long the query service has had a
+/// block which is not committed yet.
+async fn check_commitments(opt: &CommitmentTaskOptions, status: &StatusHandle) {
+    let hotshot = HotShot::new(
+        opt.hotshot_address,
+        Arc::new(create_provider(&opt.l1_provider)),
+    );
+    let query_service = opt
+        .query_service_url
+        .as_ref()
+        .map(|url| QueryReplicas::new(&[url.clone()]));
+    let mut waiting_since = None;
+    loop {
+        sleep(COMMITMENT_CHECK_INTERVAL).await;
+        let height = match hotshot.block_height().call().await {
+            Ok(height) => height.as_u64(),
+            Err(err) => {
+                tracing::warn!(target: EXECUTOR, "unable to read HotShot block height: {err}");
+                continue;
+            }
+        };
+        // The next block to commit is waiting if the query service already has it.
+        let waiting = match &query_service {
+            Some(query_service) => query_service
+                .get::<Header>(&format!("header/{height}"))
+                .await
+                .is_ok(),
+            None => false,
+        };
+        let now = Instant::now();
+        waiting_since = waiting.then(|| waiting_since.unwrap_or(now));
+
+        let mut status = status.write().await;
+        let task = status
+            .commitment_task
+            .get_or_insert_with(CommitmentTaskStatus::default);
+        if height > task.committed_height {
+            task.last_commitment_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|time| time.as_secs());
+        }
+        task.committed_height = height;
+        task.lag_secs = waiting_since.map_or(0, |since| now.duration_since(since).as_secs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_commitment_task_options() {
+        let opt = Options::try_parse_from([
+            "example-l2",
+            "--with-commitment-task",
+            "--commitment-account-index",
+            "5",
+            "--l1-chain-id",
+            "31337",
+        ])
+        .unwrap();
+        let task = commitment_task_options(&opt);
+        assert_eq!(task.l1_provider, opt.l1_http_provider);
+        assert_eq!(task.sequencer_mnemonic, opt.rollup_mnemonic);
+        assert_eq!(task.sequencer_account_index, 5);
+        assert_eq!(task.hotshot_address, opt.hotshot_address);
+        assert_eq!(task.l1_chain_id, Some(31337));
+        assert_eq!(task.query_service_url, Some(opt.sequencer_url.clone()));
+    }
+}
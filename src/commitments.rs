@@ -0,0 +1,140 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Conversions between commitments and the `uint256` words contracts store them as.
+//!
+//! A commitment is 32 bytes, in the order of its canonical serialization. Contracts hold it as a
+//! `uint256` whose little-endian bytes are those 32 bytes, so converting reads and writes the
+//! word in little-endian order. Reading it big-endian instead still yields a well-formed
+//! commitment, just a different one, so a mistake here does not fail to decode: it makes every
+//! HotShot block look like it does not match its commitment on L1. Every conversion between the
+//! executor and a contract therefore goes through this module, whose functions are inverses of
+//! each other and agree with the ones the sequencer posts HotShot commitments with.
+//!
+//! A contract slot which was never written reads as zero, which would also decode as a commitment.
+//! [`u256_to_commitment`] rejects it as [`CommitmentError::Unset`], so that a height the contract
+//! does not have yet is told apart from one it disagrees on.
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use commit::{Commitment, Committable};
+use ethers::types::U256;
+use sequencer::Header;
+use snafu::Snafu;
+
+use crate::error::ExecutorError;
+
+/// The hash of a HotShot block, as the HotShot contract stores it: the commitment to its header.
+pub type BlockHash = Commitment<Header>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Snafu)]
+pub enum CommitmentError {
+    #[snafu(display("Commitment is zero: the contract has not recorded one yet."))]
+    Unset,
+    #[snafu(display("Malformed commitment {value}: {reason}"))]
+    Malformed { value: U256, reason: String },
+}
+
+/// The `uint256` a contract stores `commitment` as.
+pub fn commitment_to_u256<T: Committable>(commitment: Commitment<T>) -> U256 {
+    let mut bytes = Vec::with_capacity(32);
+    commitment
+        .serialize_uncompressed(&mut bytes)
+        .expect("serializing to memory does not fail");
+    U256::from_little_endian(&bytes)
+}
+
+/// The commitment stored in a contract as `value`.
+///
+/// Fails with [`CommitmentError::Unset`] if `value` is zero, as read from a slot which was never
+/// written.
+pub fn u256_to_commitment<T: Committable>(value: U256) -> Result<Commitment<T>, CommitmentError> {
+    if value.is_zero() {
+        return Err(CommitmentError::Unset);
+    }
+    let mut bytes = [0; 32];
+    value.to_little_endian(&mut bytes);
+    Commitment::deserialize_uncompressed(bytes.as_slice()).map_err(|err| {
+        CommitmentError::Malformed {
+            value,
+            reason: err.to_string(),
+        }
+    })
+}
+
+/// The `uint256` the HotShot contract stores `hash` as.
+pub fn block_hash_to_u256(hash: BlockHash) -> U256 {
+    commitment_to_u256(hash)
+}
+
+/// The block hash the HotShot contract stores as `value`.
+pub fn u256_to_block_hash(value: U256) -> Result<BlockHash, CommitmentError> {
+    u256_to_commitment(value)
+}
+
+/// Whether `header` is the HotShot block the HotShot contract stores as `value` at `height`.
+///
+/// Fails with [`ExecutorError::NotYetCommitted`] if the contract has no commitment at `height` yet.
+/// A value which does not decode is a mismatch, like any other commitment not to `header`.
+pub(crate) fn matches_block_hash(
+    height: u64,
+    header: &Header,
+    value: U256,
+) -> Result<bool, ExecutorError> {
+    match u256_to_block_hash(value) {
+        Ok(hash) => Ok(hash == header.commit()),
+        Err(CommitmentError::Unset) => Err(ExecutorError::NotYetCommitted { height }),
+        Err(CommitmentError::Malformed { .. }) => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_commitment_round_trip(bytes in any::<[u8; 32]>()) {
+            let value = U256::from_little_endian(&bytes);
+            prop_assume!(!value.is_zero());
+            let hash = u256_to_block_hash(value).unwrap();
+            prop_assert_eq!(block_hash_to_u256(hash), value);
+            prop_assert_eq!(u256_to_block_hash(block_hash_to_u256(hash)).unwrap(), hash);
+
+            // The sequencer posts HotShot commitments with its own conversion, which must agree.
+            prop_assert_eq!(sequencer_utils::commitment_to_u256(hash), value);
+            let state = u256_to_commitment::<State>(value).unwrap();
+            prop_assert_eq!(sequencer_utils::u256_to_commitment::<State>(value).unwrap(), state);
+        }
+    }
+
+    #[test]
+    fn test_unset_commitment() {
+        assert_eq!(
+            u256_to_block_hash(U256::zero()),
+            Err(CommitmentError::Unset)
+        );
+        // Only the least significant byte of one is set, and it comes first in little-endian order.
+        let hash = u256_to_block_hash(U256::one()).unwrap();
+        let mut bytes = vec![];
+        hash.serialize_uncompressed(&mut bytes).unwrap();
+        assert_eq!(bytes[0], 1);
+        assert!(bytes[1..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_matches_block_hash() {
+        let block = &crate::testing::mock_chain()[1];
+        let header: Header = serde_json::from_value(block.header.clone()).unwrap();
+        assert!(matches_block_hash(1, &header, block.commitment).unwrap());
+        assert!(!matches_block_hash(1, &header, block.commitment ^ U256::one()).unwrap());
+        assert!(matches!(
+            matches_block_hash(1, &header, U256::zero()),
+            Err(ExecutorError::NotYetCommitted { height: 1 })
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! The configuration a node runs with, and where each option of it came from.
+//!
+//! Every option of the node can be set with a command line flag or an environment variable, or
+//! left to its default. [`parse_options`] records which it was for each option, so that the admin
+//! `config` route can tell an operator what a node is actually running with, without shell access
+//! to it. Secrets, such as mnemonics and tokens, are never reported.
+
+use clap::{parser::ValueSource, ArgAction, ArgMatches, CommandFactory, FromArgMatches};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+use crate::retention::RetentionConfig;
+use crate::state::ChainConfig;
+use crate::Options;
+
+/// What a secret option is reported as, when it is set.
+pub const REDACTED: &str = "<redacted>";
+
+/// Options whose values are secret.
+const SECRET_OPTIONS: &[&str] = &[
+    "rollup_mnemonic",
+    "deployer_mnemonic",
+    "admin_token",
+    "webhook_secret",
+];
+
+/// Where the value of an option came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigSource {
+    CommandLine,
+    Environment,
+    Default,
+    /// The option has no default and was not set.
+    Unset,
+}
+
+/// The value of one option.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionValue {
+    /// The value as given, [`REDACTED`] for a secret, and null if the option is not set. Options
+    /// which take a list are lists, and switches are booleans.
+    pub value: Value,
+    pub source: ConfigSource,
+    /// The environment variable the option can be set with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+}
+
+/// The configuration of a running node, as served to holders of the admin token.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EffectiveConfig {
+    /// Every option of the node, by the name of its flag.
+    pub options: BTreeMap<String, OptionValue>,
+    /// How much history the node keeps.
+    pub retention: Option<RetentionConfig>,
+    /// The chain configuration of the state. It may differ from the one the options give, since a
+    /// node resuming from a snapshot keeps the configuration of the snapshot.
+    pub chain: Option<ChainConfig>,
+}
+
+/// Parse the options of the node from the command line and the environment, recording where each
+/// came from.
+///
+/// Exits with a usage message if the options are invalid, like [`clap::Parser::parse`].
+pub fn parse_options() -> (Options, EffectiveConfig) {
+    try_parse_options_from(std::env::args_os()).unwrap_or_else(|err| err.exit())
+}
+
+/// Parse the options of the node from `args` and the environment, recording where each came from.
+pub fn try_parse_options_from<I, T>(args: I) -> Result<(Options, EffectiveConfig), clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let command = Options::command();
+    let matches = command.clone().try_get_matches_from(args)?;
+    let opt = Options::from_arg_matches(&matches)?;
+    let options = command
+        .get_arguments()
+        .filter(|arg| !matches!(arg.get_action(), ArgAction::Help | ArgAction::Version))
+        .map(|arg| {
+            let id = arg.get_id().as_str();
+            let name = arg.get_long().unwrap_or(id).to_string();
+            let value = OptionValue {
+                value: option_value(&matches, arg),
+                source: match matches.value_source(id) {
+                    Some(ValueSource::DefaultValue) => ConfigSource::Default,
+                    Some(ValueSource::EnvVariable) => ConfigSource::Environment,
+                    Some(_) => ConfigSource::CommandLine,
+                    None => ConfigSource::Unset,
+                },
+                env: arg.get_env().map(|env| env.to_string_lossy().into_owned()),
+            };
+            (name, value)
+        })
+        .collect();
+    Ok((
+        opt,
+        EffectiveConfig {
+            options,
+            ..Default::default()
+        },
+    ))
+}
+
+fn option_value(matches: &ArgMatches, arg: &clap::Arg) -> Value {
+    let id = arg.get_id().as_str();
+    if matches!(arg.get_action(), ArgAction::SetTrue) {
+        return Value::Bool(matches.get_flag(id));
+    }
+    let Some(raw) = matches.get_raw(id) else {
+        return Value::Null;
+    };
+    if SECRET_OPTIONS.contains(&id) {
+        return REDACTED.into();
+    }
+    let mut values = raw.map(|value| Value::String(value.to_string_lossy().into_owned()));
+    if arg.get_value_delimiter().is_some() {
+        Value::Array(values.collect())
+    } else {
+        values.next().unwrap_or(Value::Null)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_config() {
+        // Not read by any other test, which may parse options concurrently.
+        std::env::set_var("ESPRESSO_DEMO_ROLLUP_BACKFILL_DELAY_MS", "75");
+        let res = try_parse_options_from([
+            "example-l2",
+            "--rollup-mnemonic",
+            "secret secret secret",
+            "--snapshot-interval",
+            "10",
+            "--deposit-assets",
+            "0x0000000000000000000000000000000000000001,0x0000000000000000000000000000000000000002",
+        ]);
+        std::env::remove_var("ESPRESSO_DEMO_ROLLUP_BACKFILL_DELAY_MS");
+        let (opt, config) = res.unwrap();
+        assert_eq!(opt.backfill_delay_ms, 75);
+
+        let option = |name: &str| config.options[name].clone();
+        assert_eq!(
+            option("backfill-delay-ms"),
+            OptionValue {
+                value: "75".into(),
+                source: ConfigSource::Environment,
+                env: Some("ESPRESSO_DEMO_ROLLUP_BACKFILL_DELAY_MS".into()),
+            }
+        );
+        assert_eq!(option("snapshot-interval").value, "10");
+        assert_eq!(
+            option("snapshot-interval").source,
+            ConfigSource::CommandLine
+        );
+        assert_eq!(option("snapshots-retained").value, "3");
+        assert_eq!(option("snapshots-retained").source, ConfigSource::Default);
+        assert_eq!(option("deposit-assets").value.as_array().unwrap().len(), 2);
+        assert_eq!(option("skip-backfill").value, false);
+        assert_eq!(option("rollup-address").value, Value::Null);
+        assert_eq!(option("rollup-address").source, ConfigSource::Unset);
+
+        // Secrets are redacted whether they are set or left to their defaults.
+        assert_eq!(option("rollup-mnemonic").value, REDACTED);
+        assert_eq!(option("rollup-mnemonic").source, ConfigSource::CommandLine);
+        assert_eq!(option("deployer-mnemonic").value, REDACTED);
+        assert_eq!(option("deployer-mnemonic").source, ConfigSource::Default);
+        assert_eq!(option("admin-token").value, Value::Null);
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(!json.contains("secret secret"), "{json}");
+        assert!(!json.contains("test test"), "{json}");
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! A small durable record of executor progress.
+//!
+//! The cursor lets a restarted executor pick up where it left off without scanning the L1 for
+//! every `NewBlocks` event since genesis. It is rewritten after every block, so writes go to a
+//! temporary file which is then renamed over the old cursor, and the contents carry a checksum so
+//! a torn or hand-edited file is detected rather than trusted. Cursors in the format of an older
+//! version are upgraded by the [migrations](crate::migrations) as they are loaded.
+//!
+//! The cursor also keeps the [deposits](crate::deposit::DepositWatch) the executor is watching for
+//! L1 reorgs, so that a deposit which disappears while the executor is down is still noticed.
+
+use ethers::{types::H256, utils::keccak256};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::deposit::DepositWatch;
+use crate::migrations::{upgrade, Format, MigrationError, CURSOR_FORMAT_VERSION};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    /// Number of blocks applied to the state.
+    pub executed_height: u64,
+    /// Number of blocks whose batch proof has been mined on L1.
+    pub submitted_height: u64,
+    /// L1 block containing the `NewBlocks` event of the batch being executed. The event stream
+    /// resumes from here.
+    pub l1_log_block: u64,
+    /// Deposits seen on L1 which are pending or were credited recently.
+    #[serde(default)]
+    pub deposits: DepositWatch,
+}
+
+#[derive(Debug, Snafu)]
+pub enum CursorError {
+    #[snafu(display("Unable to read executor cursor: {source}"))]
+    Io { source: io::Error },
+    #[snafu(display("Executor cursor is corrupted: {reason}"))]
+    Corrupted { reason: String },
+    #[snafu(display("Unable to read executor cursor: {source}"))]
+    TooNew { source: MigrationError },
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorFile {
+    format_version: u32,
+    cursor: Cursor,
+    checksum: H256,
+}
+
+impl Cursor {
+    /// Load the cursor stored at `path`, or `None` if there is no cursor there.
+    pub fn load(path: &Path) -> Result<Option<Self>, CursorError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(CursorError::Io { source }),
+        };
+        let corrupted = |reason: String| CursorError::Corrupted { reason };
+        let file = serde_json::from_slice(&bytes).map_err(|err| corrupted(err.to_string()))?;
+        let file = upgrade(Format::Cursor, file).map_err(|err| match err {
+            MigrationError::TooNew { .. } => CursorError::TooNew { source: err },
+            err => corrupted(err.to_string()),
+        })?;
+        let file: CursorFile =
+            serde_json::from_value(file).map_err(|err| corrupted(err.to_string()))?;
+        if file.checksum != file.cursor.checksum() {
+            return Err(CursorError::Corrupted {
+                reason: "checksum does not match contents".into(),
+            });
+        }
+        Ok(Some(file.cursor))
+    }
+
+    /// Atomically replace the cursor stored at `path`.
+    pub fn store(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = CursorFile {
+            format_version: CURSOR_FORMAT_VERSION,
+            cursor: self.clone(),
+            checksum: self.checksum(),
+        };
+        let tmp = path.with_extension("tmp");
+        let mut tmp_file = fs::File::create(&tmp)?;
+        tmp_file.write_all(&serde_json::to_vec(&file)?)?;
+        // The contents must reach the disk before the rename does, or a crash could leave the
+        // cursor renamed but empty.
+        tmp_file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        // Make the rename itself durable. Not every platform can open a directory to sync it.
+        if let Some(dir) = path.parent().and_then(|dir| fs::File::open(dir).ok()) {
+            dir.sync_all().ok();
+        }
+        Ok(())
+    }
+
+    fn checksum(&self) -> H256 {
+        let mut bytes = [0; 24];
+        bytes[..8].copy_from_slice(&self.executed_height.to_be_bytes());
+        bytes[8..16].copy_from_slice(&self.submitted_height.to_be_bytes());
+        bytes[16..].copy_from_slice(&self.l1_log_block.to_be_bytes());
+        if self.deposits.is_empty() {
+            return keccak256(bytes).into();
+        }
+        let mut bytes = bytes.to_vec();
+        bytes.extend(serde_json::to_vec(&self.deposits).expect("Serialization should not fail"));
+        keccak256(bytes).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deposit::{CreditedDeposit, Deposit};
+    use ethers::types::Address;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cursor.json");
+        let cursor = Cursor {
+            executed_height: 10,
+            submitted_height: 8,
+            l1_log_block: 42,
+            ..Default::default()
+        };
+        cursor.store(&path).unwrap();
+        assert_eq!(Cursor::load(&path).unwrap(), Some(cursor.clone()));
+
+        // Storing again replaces the cursor and leaves no temporary file behind.
+        let cursor = Cursor {
+            executed_height: 11,
+            ..cursor
+        };
+        cursor.store(&path).unwrap();
+        assert_eq!(Cursor::load(&path).unwrap(), Some(cursor.clone()));
+        assert!(!path.with_extension("tmp").exists());
+
+        // The deposits being watched are kept, and covered by the checksum.
+        let deposit = Deposit {
+            token: Address::random(),
+            sender: Address::random(),
+            recipient: Address::random(),
+            amount: 5.into(),
+            l1_block: 40,
+        };
+        let cursor = Cursor {
+            deposits: DepositWatch {
+                pending: vec![deposit.clone()],
+                credited: vec![CreditedDeposit {
+                    height: 10,
+                    deposit: Deposit {
+                        l1_block: 30,
+                        ..deposit
+                    },
+                }],
+            },
+            ..cursor
+        };
+        cursor.store(&path).unwrap();
+        assert_eq!(Cursor::load(&path).unwrap(), Some(cursor));
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::write(
+            &path,
+            contents.replace("\"l1_block\":40", "\"l1_block\":41"),
+        )
+        .unwrap();
+        assert!(matches!(
+            Cursor::load(&path),
+            Err(CursorError::Corrupted { .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(Cursor::load(&dir.path().join("cursor.json")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cursor.json");
+        Cursor {
+            executed_height: 10,
+            submitted_height: 8,
+            l1_log_block: 42,
+            ..Default::default()
+        }
+        .store(&path)
+        .unwrap();
+
+        // Change the contents without updating the checksum.
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::write(&path, contents.replace("10", "12")).unwrap();
+        assert!(matches!(
+            Cursor::load(&path),
+            Err(CursorError::Corrupted { .. })
+        ));
+
+        // Truncate the file.
+        fs::write(&path, &contents[..contents.len() / 2]).unwrap();
+        assert!(matches!(
+            Cursor::load(&path),
+            Err(CursorError::Corrupted { .. })
+        ));
+
+        // A cursor written by a newer version is not corrupted, but cannot be read either.
+        let mut file: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        file["format_version"] = (CURSOR_FORMAT_VERSION + 1).into();
+        fs::write(&path, file.to_string()).unwrap();
+        assert!(matches!(
+            Cursor::load(&path),
+            Err(CursorError::TooNew { .. })
+        ));
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Deposits from L1.
+//!
+//! Depositing an ERC-20 token calls `depositERC20` on the rollup contract, which pulls the tokens
+//! from the sender with `transferFrom` and emits a `Deposit` event naming the token, the amount it
+//! received and the rollup account to credit. Depositing ether calls `depositETH` with the ether to deposit, and
+//! its event names the zero address as the token. Each rollup block credits the deposits made in
+//! the finalized L1 blocks it references which earlier blocks have not, as described under
+//! [`State::pending_deposits`](crate::state::State::pending_deposits), so every executor credits
+//! the same deposits in the same rollup block.
+//!
+//! Only tokens listed as assets in the [`ChainConfig`](crate::state::ChainConfig) are credited.
+//!
+//! A deposit is only credited once its L1 block is
+//! [`deposit_confirmations`](crate::state::ChainConfig::deposit_confirmations) blocks below the L1
+//! head the rollup block references, so that a shallow L1 reorg cannot take back a deposit which has
+//! already been spent on the rollup. The executor keeps a [`DepositWatch`] of the deposits it has
+//! seen but not yet credited, and of those it credited recently, with its cursor. A pending deposit
+//! which a reorg removes from L1 is dropped. One which disappears after it was credited cannot be
+//! taken back, and is reported.
+
+use contract_bindings::example_rollup::ExampleRollup;
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
+
+use crate::error::ExecutorError;
+use crate::logging::BRIDGE;
+
+/// Number of L1 blocks for which a credited deposit is watched for a reorg.
+pub const DEPOSIT_WATCH_BLOCKS: u64 = 128;
+
+/// A `Deposit` event emitted by the rollup contract.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deposit {
+    pub token: Address,
+    pub sender: Address,
+    pub recipient: Address,
+    pub amount: U256,
+    /// The L1 block the deposit was made in.
+    pub l1_block: u64,
+}
+
+impl Deposit {
+    /// Whether `other` is the same transfer, wherever a reorg may have moved it.
+    fn same_transfer(&self, other: &Deposit) -> bool {
+        self.token == other.token
+            && self.sender == other.sender
+            && self.recipient == other.recipient
+            && self.amount == other.amount
+    }
+
+    /// Remove the deposit of `deposits` which is this one, if any, preferring one still in the
+    /// same L1 block.
+    ///
+    /// Each deposit on L1 accounts for at most one watched deposit, so that of two identical
+    /// deposits, one cannot hide the loss of the other.
+    fn take_from(&self, deposits: &mut Vec<Deposit>) -> Option<Deposit> {
+        let index = deposits
+            .iter()
+            .position(|other| other == self)
+            .or_else(|| deposits.iter().position(|other| other.same_transfer(self)))?;
+        Some(deposits.remove(index))
+    }
+}
+
+/// The deposits made in a range of L1 blocks, in the order they were made.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1Deposits {
+    /// The last L1 block of the range.
+    pub through: u64,
+    pub deposits: Vec<Deposit>,
+}
+
+/// Fetch the deposits made to `rollup_contract` in the L1 blocks `blocks`.
+pub async fn fetch_deposits<M: Middleware>(
+    rollup_contract: &ExampleRollup<M>,
+    blocks: RangeInclusive<u64>,
+) -> Result<L1Deposits, ExecutorError> {
+    let mut events = rollup_contract
+        .deposit_filter()
+        .from_block(*blocks.start())
+        .to_block(*blocks.end())
+        .address(rollup_contract.address().into())
+        .query_with_meta()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: format!("unable to fetch deposits in L1 blocks {blocks:?}: {err}"),
+        })?;
+    events.sort_by_key(|(_, meta)| (meta.block_number, meta.log_index));
+    Ok(L1Deposits {
+        through: *blocks.end(),
+        deposits: events
+            .into_iter()
+            .map(|(event, meta)| Deposit {
+                token: event.token,
+                sender: event.sender,
+                recipient: event.recipient,
+                amount: event.amount,
+                l1_block: meta.block_number.as_u64(),
+            })
+            .collect(),
+    })
+}
+
+/// A deposit which was credited to the rollup.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreditedDeposit {
+    /// The rollup block which credited the deposit.
+    pub height: u64,
+    pub deposit: Deposit,
+}
+
+/// Deposits the executor has seen on L1 and is watching for reorgs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepositWatch {
+    /// Deposits made after the last L1 block the state has credited deposits through.
+    pub pending: Vec<Deposit>,
+    /// Deposits credited in the last [`DEPOSIT_WATCH_BLOCKS`] L1 blocks.
+    pub credited: Vec<CreditedDeposit>,
+}
+
+impl DepositWatch {
+    /// Whether there is nothing to watch.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.credited.is_empty()
+    }
+
+    /// Look for the watched deposits again, and for new ones, up to L1 block `l1_head`, given that
+    /// the state has credited the deposits made up to `credited_through`.
+    ///
+    /// Returns the credited deposits which are no longer on L1.
+    pub async fn update<M: Middleware>(
+        &mut self,
+        rollup_contract: &ExampleRollup<M>,
+        credited_through: u64,
+        l1_head: u64,
+    ) -> Result<Vec<CreditedDeposit>, ExecutorError> {
+        let from = self
+            .pending
+            .iter()
+            .chain(self.credited.iter().map(|credited| &credited.deposit))
+            .map(|deposit| deposit.l1_block)
+            .fold(credited_through + 1, u64::min);
+        let found = if from <= l1_head {
+            fetch_deposits(rollup_contract, from..=l1_head).await?
+        } else {
+            L1Deposits::default()
+        };
+        Ok(self.observe(found.deposits, credited_through, l1_head))
+    }
+
+    /// Compare the watched deposits with those `found` on L1 from the oldest of them up to
+    /// `l1_head`.
+    fn observe(
+        &mut self,
+        mut found: Vec<Deposit>,
+        credited_through: u64,
+        l1_head: u64,
+    ) -> Vec<CreditedDeposit> {
+        let mut reorged = vec![];
+        for credited in std::mem::take(&mut self.credited) {
+            match credited.deposit.take_from(&mut found) {
+                Some(_) => {
+                    if credited.deposit.l1_block + DEPOSIT_WATCH_BLOCKS > l1_head {
+                        self.credited.push(credited);
+                    }
+                }
+                None => reorged.push(credited),
+            }
+        }
+        // Deposits at or below the credited block were credited before they were watched, or are
+        // of tokens which are not assets.
+        found.retain(|deposit| deposit.l1_block > credited_through);
+        let mut unmatched = found.clone();
+        for deposit in &self.pending {
+            if deposit.take_from(&mut unmatched).is_none() {
+                log_dropped(deposit);
+            }
+        }
+        self.pending = found;
+        reorged
+    }
+
+    /// Record that rollup block `height` credited `deposits`, of which those of `assets` were
+    /// credited and the rest ignored.
+    pub fn credit(&mut self, height: u64, deposits: &L1Deposits, assets: &BTreeSet<Address>) {
+        let mut unmatched = deposits.deposits.clone();
+        self.pending.retain(|deposit| {
+            if deposit.l1_block > deposits.through {
+                return true;
+            }
+            if deposit.take_from(&mut unmatched).is_none() {
+                log_dropped(deposit);
+            }
+            false
+        });
+        let mut credited = deposits.deposits.clone();
+        credited.retain(|deposit| assets.contains(&deposit.token));
+        self.credited.extend(
+            credited
+                .into_iter()
+                .map(|deposit| CreditedDeposit { height, deposit }),
+        );
+    }
+}
+
+fn log_dropped(deposit: &Deposit) {
+    tracing::warn!(
+        target: BRIDGE,
+        "Dropping deposit of {} of token {:?} to {:?} in L1 block {}, which an L1 reorg removed \
+         before it was credited",
+        deposit.amount,
+        deposit.token,
+        deposit.recipient,
+        deposit.l1_block
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe() {
+        let deposit = |amount: u64, l1_block| Deposit {
+            token: Address::zero(),
+            sender: Address::zero(),
+            recipient: Address::zero(),
+            amount: amount.into(),
+            l1_block,
+        };
+        let assets = [Address::zero()].into();
+        let mut watch = DepositWatch::default();
+        assert_eq!(watch.observe(vec![deposit(1, 5), deposit(2, 6)], 0, 6), []);
+        watch.credit(
+            1,
+            &L1Deposits {
+                through: 5,
+                deposits: vec![deposit(1, 5)],
+            },
+            &assets,
+        );
+        assert_eq!(watch.pending, [deposit(2, 6)]);
+        assert_eq!(
+            watch.credited,
+            [CreditedDeposit {
+                height: 1,
+                deposit: deposit(1, 5)
+            }]
+        );
+
+        // A deposit a reorg moved to a later block is still the same deposit, and one credited long
+        // enough ago is no longer watched.
+        assert_eq!(watch.observe(vec![deposit(1, 7), deposit(2, 8)], 5, 8), []);
+        assert_eq!(watch.pending, [deposit(2, 8)]);
+        assert_eq!(watch.credited.len(), 1);
+        watch.observe(
+            vec![deposit(1, 5), deposit(2, 8)],
+            5,
+            5 + DEPOSIT_WATCH_BLOCKS,
+        );
+        assert_eq!(watch.credited, []);
+
+        // A pending deposit which disappears is dropped.
+        watch.observe(vec![], 5, 5 + DEPOSIT_WATCH_BLOCKS);
+        assert!(watch.is_empty());
+    }
+
+    #[test]
+    fn test_identical_deposits() {
+        let deposit = |amount: u64, l1_block| Deposit {
+            token: Address::zero(),
+            sender: Address::zero(),
+            recipient: Address::zero(),
+            amount: amount.into(),
+            l1_block,
+        };
+        let credited = |height, deposit| CreditedDeposit { height, deposit };
+        let assets = [Address::zero()].into();
+        let mut watch = DepositWatch::default();
+        watch.observe(
+            vec![deposit(1, 5), deposit(1, 5), deposit(2, 6), deposit(2, 6)],
+            0,
+            6,
+        );
+        watch.credit(
+            1,
+            &L1Deposits {
+                through: 5,
+                deposits: vec![deposit(1, 5), deposit(1, 5)],
+            },
+            &assets,
+        );
+        assert_eq!(watch.pending, [deposit(2, 6), deposit(2, 6)]);
+        assert_eq!(watch.credited.len(), 2);
+
+        // When a reorg removes one of two identical deposits, the one left on L1 accounts for only
+        // one of them, whether they were credited or are still pending.
+        let reorged = watch.observe(vec![deposit(1, 5), deposit(2, 6)], 5, 7);
+        assert_eq!(reorged, [credited(1, deposit(1, 5))]);
+        assert_eq!(watch.credited, [credited(1, deposit(1, 5))]);
+        assert_eq!(watch.pending, [deposit(2, 6)]);
+
+        // Each deposit credited likewise accounts for only one pending deposit.
+        watch.observe(vec![deposit(1, 5), deposit(2, 6), deposit(2, 6)], 5, 7);
+        assert_eq!(watch.pending, [deposit(2, 6), deposit(2, 6)]);
+        watch.credit(
+            2,
+            &L1Deposits {
+                through: 6,
+                deposits: vec![deposit(2, 6)],
+            },
+            &assets,
+        );
+        assert_eq!(watch.pending, []);
+        assert_eq!(
+            watch.credited,
+            [credited(1, deposit(1, 5)), credited(2, deposit(2, 6))]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Checks of the node's configuration against the services it connects to, run by `--check`.
+//!
+//! Each check is one the executor also makes when it starts, so a configuration which passes will
+//! not fail the same way later. Unlike the executor, the checks carry on past a failure, so one
+//! run reports everything that is wrong.
+
+use ethers::providers::{Provider, Ws};
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use crate::error::ExecutorError;
+use crate::executor::{check_chain_id, check_contract_code, check_funded};
+use crate::utils::{create_provider, wallet_address};
+use crate::Options;
+
+type HotShotClient = surf_disco::Client<hotshot_query_service::Error>;
+
+/// How long to wait for the sequencer to respond.
+const SEQUENCER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The result of one check.
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub name: &'static str,
+    /// What was found if the check passed, or why it failed.
+    pub outcome: Result<String, String>,
+    /// What to do about a failure.
+    pub hint: &'static str,
+}
+
+impl Check {
+    fn new(name: &'static str, hint: &'static str, outcome: Result<String, String>) -> Self {
+        Self {
+            name,
+            outcome,
+            hint,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// The results of all the checks, in the order they were made.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(Check::passed)
+    }
+
+    /// The check called `name`, if it was made.
+    pub fn check(&self, name: &str) -> Option<&Check> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.outcome {
+                Ok(found) => writeln!(f, "[PASS] {}: {found}", check.name)?,
+                Err(reason) => {
+                    writeln!(f, "[FAIL] {}: {reason}", check.name)?;
+                    writeln!(f, "       {}", check.hint)?;
+                }
+            }
+        }
+        if self.passed() {
+            writeln!(f, "All checks passed.")
+        } else {
+            writeln!(f, "Some checks failed.")
+        }
+    }
+}
+
+/// Check that the services configured in `opt` are reachable and set up as the node expects,
+/// without starting the node.
+pub async fn run_checks(opt: &Options) -> Report {
+    let mut report = Report::default();
+
+    let sequencer = HotShotClient::new(opt.sequencer_url.join("availability").unwrap());
+    report.checks.push(Check::new(
+        "sequencer",
+        "Check that --sequencer-url points at a running sequencer node serving the availability \
+        API.",
+        if sequencer.connect(Some(SEQUENCER_TIMEOUT)).await {
+            Ok(format!("{} responds", opt.sequencer_url))
+        } else {
+            Err(format!(
+                "{} did not respond within {SEQUENCER_TIMEOUT:?}",
+                opt.sequencer_url
+            ))
+        },
+    ));
+
+    let l1 = create_provider(&opt.l1_http_provider);
+    let chain_id = check_chain_id(&l1, &opt.l1_http_provider, opt.l1_chain_id).await;
+    report.checks.push(Check::new(
+        "L1 HTTP provider",
+        "Check that --l1-http-provider is a running JSON-RPC endpoint, and that --l1-chain-id is \
+        the chain it serves.",
+        match &chain_id {
+            Ok(chain_id) => Ok(format!("{} serves chain {chain_id}", opt.l1_http_provider)),
+            Err(err) => Err(err.to_string()),
+        },
+    ));
+
+    let ws = match Provider::<Ws>::connect(&opt.l1_ws_provider).await {
+        Ok(ws) => check_chain_id(&ws, &opt.l1_ws_provider, chain_id.as_ref().ok().copied())
+            .await
+            .map(|chain_id| format!("{} serves chain {chain_id}", opt.l1_ws_provider))
+            .map_err(|err| err.to_string()),
+        Err(err) => Err(format!(
+            "unable to connect to {}: {err}",
+            opt.l1_ws_provider
+        )),
+    };
+    report.checks.push(Check::new(
+        "L1 websocket provider",
+        "Check that --l1-ws-provider supports websockets and serves the same chain as \
+        --l1-http-provider.",
+        ws,
+    ));
+
+    // Without an L1 connection there is nothing to check the contracts and the account against.
+    if chain_id.is_err() {
+        return report;
+    }
+
+    let mut contracts = vec![("HotShot", opt.hotshot_address)];
+    contracts.extend(opt.rollup_address.map(|address| ("rollup", address)));
+    let found = async {
+        for &(name, address) in &contracts {
+            check_contract_code(&l1, name, address).await?;
+        }
+        Ok::<_, ExecutorError>(())
+    }
+    .await;
+    report.checks.push(Check::new(
+        "contracts",
+        "Check --hotshot-address and --rollup-address, and that the L1 provider serves the chain \
+        they were deployed on. Leave --rollup-address unset to deploy a new rollup contract.",
+        found
+            .map(|()| {
+                if opt.rollup_address.is_some() {
+                    "HotShot and rollup contracts are deployed".into()
+                } else {
+                    "HotShot contract is deployed, and a rollup contract will be".into()
+                }
+            })
+            .map_err(|err| err.to_string()),
+    ));
+
+    let operator = wallet_address(&opt.rollup_mnemonic, opt.rollup_account_index);
+    report.checks.push(Check::new(
+        "operator account",
+        "Send ETH to the operator account, or point --rollup-mnemonic and \
+        --rollup-account-index at a funded one.",
+        check_funded(&l1, operator)
+            .await
+            .map(|balance| format!("{operator:?} holds {balance} wei"))
+            .map_err(|err| err.to_string()),
+    ));
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{spawn_anvil, TestAnvil};
+    use clap::Parser;
+    use ethers::providers::Middleware;
+    use ethers::types::Address;
+    use portpicker::pick_unused_port;
+    use sequencer_utils::test_utils::TestL1System;
+    use surf_disco::Url;
+
+    /// Options for a node on the L1 at `l1_url`, with a sequencer URL nothing is listening on.
+    fn options(l1_url: &Url, anvil: &TestAnvil, test_l1: &TestL1System, extra: &[&str]) -> Options {
+        let sequencer_url = format!("http://localhost:{}", pick_unused_port().unwrap());
+        let hotshot_address = format!("{:?}", test_l1.hotshot.address());
+        let ws_url = anvil.ws_url();
+        let mut args = vec![
+            "example-l2",
+            "--sequencer-url",
+            &sequencer_url,
+            "--l1-http-provider",
+            l1_url.as_str(),
+            "--l1-ws-provider",
+            ws_url.as_str(),
+            "--hotshot-address",
+            &hotshot_address,
+        ];
+        args.extend(extra);
+        Options::try_parse_from(args).unwrap()
+    }
+
+    #[async_std::test]
+    async fn test_checks() {
+        let anvil = spawn_anvil(Default::default()).await;
+        let l1_url = anvil.url();
+        let test_l1 = TestL1System::deploy(create_provider(&l1_url))
+            .await
+            .unwrap();
+
+        // Everything but the sequencer is set up.
+        let report = run_checks(&options(&l1_url, &anvil, &test_l1, &[])).await;
+        assert!(!report.passed());
+        assert!(!report.check("sequencer").unwrap().passed());
+        for name in [
+            "L1 HTTP provider",
+            "L1 websocket provider",
+            "contracts",
+            "operator account",
+        ] {
+            assert!(report.check(name).unwrap().passed(), "{report}");
+        }
+
+        // An L1 on a chain other than the configured one.
+        let chain_id = create_provider(&anvil.url())
+            .get_chainid()
+            .await
+            .unwrap()
+            .as_u64();
+        let wrong_chain = (chain_id + 1).to_string();
+        let report = run_checks(&options(
+            &l1_url,
+            &anvil,
+            &test_l1,
+            &["--l1-chain-id", &wrong_chain],
+        ))
+        .await;
+        let check = report.check("L1 HTTP provider").unwrap();
+        assert!(
+            check.outcome.as_ref().unwrap_err().contains(&wrong_chain),
+            "{report}"
+        );
+
+        // A rollup contract address with nothing deployed at it.
+        let nowhere = format!("{:?}", Address::random());
+        let report = run_checks(&options(
+            &l1_url,
+            &anvil,
+            &test_l1,
+            &["--rollup-address", &nowhere],
+        ))
+        .await;
+        assert!(!report.check("contracts").unwrap().passed(), "{report}");
+        assert!(report.check("operator account").unwrap().passed());
+
+        // An operator account with no ETH.
+        let report = run_checks(&options(
+            &l1_url,
+            &anvil,
+            &test_l1,
+            &["--rollup-account-index", "1000"],
+        ))
+        .await;
+        assert!(
+            !report.check("operator account").unwrap().passed(),
+            "{report}"
+        );
+        assert!(report.check("contracts").unwrap().passed());
+
+        // An L1 provider which is not running. Nothing can be checked against it.
+        let dead: Url = format!("http://localhost:{}", pick_unused_port().unwrap())
+            .parse()
+            .unwrap();
+        let report = run_checks(&options(&dead, &anvil, &test_l1, &[])).await;
+        assert!(!report.check("L1 HTTP provider").unwrap().passed());
+        assert!(report.check("operator account").is_none());
+    }
+}
@@ -4,21 +4,342 @@
 // You should have received a copy of the MIT License
 // along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
 
-use crate::state::Nonce;
-use ethers::abi::Address;
+use crate::address::{checksummed, AddressError};
+use crate::chain::ChainError;
+use crate::invariants::Violation;
+use crate::status::SyncProgress;
+use ethers::types::{Address, H256, U256};
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
+use std::path::PathBuf;
+use surf_disco::Url;
+use tide_disco::{RequestError, StatusCode};
 
-#[derive(Snafu, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub enum RollupError {
-    #[snafu(display("Error validating the transaction signature."))]
-    SignatureError,
-    #[snafu(display("Insufficient balance for sender: {address}."))]
-    InsufficientBalance { address: Address },
-    #[snafu(display("Invalid nonce for sender {address}. Found {actual}, expected {expected}"))]
-    InvalidNonce {
+pub use example_l2_transaction::RollupError;
+
+/// An error returned by the Rollup API.
+///
+/// The variant is serialized into the response body, so clients can match on the kind of error
+/// instead of parsing the message.
+#[derive(Clone, Debug, Snafu, Serialize, Deserialize)]
+pub enum ApiError {
+    #[snafu(display("{source}"))]
+    Request { source: RequestError },
+    #[snafu(display("Malformed transaction: {reason}"))]
+    MalformedTransaction { reason: String },
+    #[snafu(display("Malformed request body: {field}: expected {expected}, got {got}"))]
+    InvalidField {
+        field: String,
+        expected: String,
+        got: String,
+    },
+    #[snafu(display("Invalid transaction signature: {source}"))]
+    InvalidSignature { source: RollupError },
+    #[snafu(display("Malformed address: {source}"))]
+    MalformedAddress { source: AddressError },
+    #[snafu(display("Malformed transaction hash. Ensure that the hash is 32 hex encoded bytes."))]
+    MalformedHash,
+    #[snafu(display("Missing credentials. Send an `Authorization: Bearer <token>` header."))]
+    Unauthorized,
+    #[snafu(display("Invalid credentials for this route."))]
+    Forbidden,
+    #[snafu(display("Transaction submission is paused by the operator."))]
+    Paused,
+    #[snafu(display("Node is shutting down and no longer accepts transactions."))]
+    ShuttingDown,
+    #[snafu(display("Node is a read-only replica and does not accept transactions."))]
+    ReadOnly,
+    #[snafu(display("Request body exceeds the limit of {limit} bytes."))]
+    PayloadTooLarge { limit: usize },
+    #[snafu(display("Submission queue is full. Retry in {retry_after_secs} seconds."))]
+    QueueFull { retry_after_secs: u64 },
+    #[snafu(display(
+        "{} already has {limit} transactions pending. Retry once some of them have executed.",
+        checksummed(sender)
+    ))]
+    TooManyPending { sender: Address, limit: usize },
+    #[snafu(display(
+        "Node is syncing: executed {} of {} blocks.",
+        progress.current_height,
+        progress.target_height
+    ))]
+    Syncing { progress: SyncProgress },
+    #[snafu(display("Block {height} has not been executed yet."))]
+    UnknownBlock { height: u64 },
+    #[snafu(display("No multisig account is registered at {}.", checksummed(address)))]
+    UnknownMultisig { address: Address },
+    #[snafu(display("L1 transaction {l1_tx:?} did not prove any block this node keeps."))]
+    UnknownProofTransaction { l1_tx: H256 },
+    #[snafu(display("Block {height} is still being backfilled from the query service."))]
+    Backfilling { height: u64 },
+    #[snafu(display(
+        "Block {height} has been pruned. This node only keeps the requested data for blocks \
+        from {horizon} on."
+    ))]
+    Pruned { height: u64, horizon: u64 },
+    #[snafu(display("This node keeps no snapshots to replay blocks from."))]
+    ReplayUnavailable,
+    #[snafu(display("Unable to replay block {height}: {reason}"))]
+    ReplayFailed { height: u64, reason: String },
+    #[snafu(display("Request timed out after {timeout_ms} ms."))]
+    Timeout { timeout_ms: u64 },
+    #[snafu(display("Sequencer at {url} is unavailable: {reason}"))]
+    SequencerUnavailable { url: String, reason: String },
+    #[snafu(display("Sequencer at {url} rejected the transaction with {status}: {message}"))]
+    SequencerRejected {
+        url: String,
+        status: u16,
+        message: String,
+    },
+    #[snafu(display("Sequencer at {url} timed out: {reason}"))]
+    SequencerTimeout { url: String, reason: String },
+    #[snafu(display("Unknown fallback {fallback:?}. The only fallback is `l1`."))]
+    UnknownFallback { fallback: String },
+    #[snafu(display("This rollup does not include transactions enqueued on L1."))]
+    FallbackUnavailable,
+    #[snafu(display("{message}"))]
+    Custom { message: String, status: StatusCode },
+}
+
+impl tide_disco::error::Error for ApiError {
+    fn catch_all(status: StatusCode, message: String) -> Self {
+        Self::Custom { status, message }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Request { .. }
+            | Self::MalformedTransaction { .. }
+            | Self::InvalidField { .. }
+            | Self::MalformedAddress { .. }
+            | Self::MalformedHash
+            | Self::UnknownFallback { .. }
+            | Self::InvalidSignature { .. } => StatusCode::BadRequest,
+            Self::Unauthorized => StatusCode::Unauthorized,
+            Self::Forbidden => StatusCode::Forbidden,
+            Self::Paused
+            | Self::ShuttingDown
+            | Self::QueueFull { .. }
+            | Self::Syncing { .. }
+            | Self::Backfilling { .. }
+            | Self::SequencerUnavailable { .. } => StatusCode::ServiceUnavailable,
+            Self::SequencerRejected { .. } | Self::ReplayFailed { .. } => StatusCode::BadGateway,
+            Self::PayloadTooLarge { .. } => StatusCode::PayloadTooLarge,
+            Self::TooManyPending { .. } => StatusCode::TooManyRequests,
+            Self::ReadOnly | Self::ReplayUnavailable | Self::FallbackUnavailable => {
+                StatusCode::NotImplemented
+            }
+            Self::UnknownBlock { .. }
+            | Self::UnknownMultisig { .. }
+            | Self::UnknownProofTransaction { .. } => StatusCode::NotFound,
+            Self::Pruned { .. } => StatusCode::Gone,
+            Self::Timeout { .. } | Self::SequencerTimeout { .. } => StatusCode::GatewayTimeout,
+            Self::Custom { status, .. } => *status,
+        }
+    }
+}
+
+impl From<RequestError> for ApiError {
+    fn from(source: RequestError) -> Self {
+        Self::Request { source }
+    }
+}
+
+/// An error which stops the executor.
+#[derive(Clone, Debug, Snafu)]
+pub enum ExecutorError {
+    #[snafu(display("Error communicating with the L1: {reason}"))]
+    L1 { reason: String },
+    #[snafu(display("Error communicating with the sequencer query service: {reason}"))]
+    QueryService { reason: String },
+    #[snafu(display(
+        "Block {height} does not match its commitment on L1. Diagnostic bundle: {bundle:?}"
+    ))]
+    CommitmentMismatch {
+        height: u64,
+        bundle: Option<PathBuf>,
+    },
+    #[snafu(display("HotShot block {height} is not yet available on the HotShot contract."))]
+    NotYetCommitted { height: u64 },
+    #[snafu(display(
+        "Rollup contract rejected a proof of the blocks from {height}, since it has verified \
+        {verified} blocks."
+    ))]
+    ProofAtWrongHeight { height: u64, verified: u64 },
+    #[snafu(display("Proof transaction {l1_tx:?} was not mined after {bumps} fee bumps."))]
+    ProofTransactionStuck { l1_tx: H256, bumps: u32 },
+    #[snafu(display(
+        "Gave up on the proof of blocks {first_block}-{} after {attempts} failed attempts: \
+        {reason}",
+        end_block - 1
+    ))]
+    ProofRetriesExhausted {
+        first_block: u64,
+        end_block: u64,
+        attempts: u32,
+        reason: String,
+    },
+    #[snafu(display(
+        "Executor cursor {path:?} records {cursor_height} executed blocks, but the state has only \
+        {state_height}. Restore the state the cursor was written with, or delete the cursor to \
+        recover from the rollup contract."
+    ))]
+    CursorAheadOfState {
+        path: PathBuf,
+        cursor_height: u64,
+        state_height: u64,
+    },
+    #[snafu(display("Executor cursor {path:?} cannot be used: {reason}"))]
+    UnsupportedCursor { path: PathBuf, reason: String },
+    #[snafu(display(
+        "The state has executed {state_height} blocks, but rollup contract {} has verified only \
+        {verified_height} and the proof of block {missing} has been pruned, so the rest cannot be \
+        proven again. Restore a snapshot of at most {verified_height} blocks, or start from a \
+        trusted state with --start-snapshot.",
+        checksummed(contract)
+    ))]
+    ProofsUnavailable {
+        state_height: u64,
+        contract: Address,
+        verified_height: u64,
+        missing: u64,
+    },
+    #[snafu(display("Trusted state {path:?} cannot be started from: {reason}"))]
+    InvalidStartPoint { path: PathBuf, reason: String },
+    #[snafu(display(
+        "Trusted state after {height} blocks has commitment {snapshot}, but {expected} was \
+        expected, and the rollup contract recorded {}.",
+        contract.map_or("no commitment at that height".into(), |theirs| theirs.to_string())
+    ))]
+    StartPointMismatch {
+        height: u64,
+        snapshot: U256,
+        expected: U256,
+        contract: Option<U256>,
+    },
+    #[snafu(display(
+        "Rollup contract holds state commitment {theirs} after block {height}, but this executor \
+        computed {ours}. Check whether another prover is submitting to the same contract."
+    ))]
+    DivergedFromContract {
+        height: u64,
+        ours: U256,
+        theirs: U256,
+    },
+    #[snafu(display(
+        "L1 provider {url} is connected to chain {actual}, but chain {expected} was configured."
+    ))]
+    WrongL1Chain {
+        url: Url,
+        expected: u64,
+        actual: u64,
+    },
+    #[snafu(display("HotShot blocks do not form a chain: {source}"))]
+    BrokenChain { source: ChainError },
+    #[snafu(display(
+        "Replaying {height} blocks from genesis to backfill them gave state commitment {ours}, but \
+        this node's state is {theirs}. Check that the genesis matches the rollup contract."
+    ))]
+    BackfillDiverged {
+        height: u64,
+        ours: U256,
+        theirs: U256,
+    },
+    #[snafu(display(
+        "Replaying block {height} executed by the followed executor gave state commitment {ours}, \
+        but the executor reports {theirs}. Check that this node has the executor's genesis."
+    ))]
+    FollowerDiverged {
+        height: u64,
+        ours: U256,
+        theirs: U256,
+    },
+    #[snafu(display("Error communicating with the followed executor's API: {reason}"))]
+    ExecutorApi { reason: String },
+    #[snafu(display(
+        "Block {height} took longer than {limit_ms} ms to execute and was quarantined. Executing \
+        stops, since skipping the block would fork the state; see admin/quarantine."
+    ))]
+    BlockQuarantined { height: u64, limit_ms: u64 },
+    #[snafu(display(
+        "The state after block {height} violates {} invariants, so the block was not proven: {}",
+        violations.len(),
+        violations.iter().map(ToString::to_string).collect::<Vec<_>>().join(" ")
+    ))]
+    InvariantViolated {
+        height: u64,
+        violations: Vec<Violation>,
+    },
+    #[snafu(display(
+        "This executor submits proofs from {}, but the rollup contract only accepts them from {}. \
+        Restart with the key of the authorized submitter.",
+        checksummed(ours),
+        checksummed(authorized)
+    ))]
+    NotAuthorizedSubmitter { ours: Address, authorized: Address },
+    #[snafu(display(
+        "No {name} contract is deployed at {} on the L1. Check the configured address and L1 \
+        provider.",
+        checksummed(address)
+    ))]
+    MissingContract {
+        name: &'static str,
         address: Address,
-        expected: Nonce,
-        actual: Nonce,
     },
+    #[snafu(display(
+        "L1 account {} has no ETH to pay for proofs. Fund it, or configure a funded account.",
+        checksummed(address)
+    ))]
+    UnfundedAccount { address: Address },
+    #[snafu(display(
+        "The L1 account sending proofs cannot pay for them: {reason}. Fund it to resume proving."
+    ))]
+    InsufficientFunds { reason: String },
+}
+
+impl ExecutorError {
+    /// Whether the executor may be restarted after this error.
+    ///
+    /// Communication errors are usually transient, a proof at the wrong height, or one which kept
+    /// failing, is resent from the contract's height after a restart, and proving resumes once an
+    /// account which ran out of funds is topped up. A block the HotShot contract has not recorded
+    /// yet will be recorded once the sequencer posts it. A commitment mismatch means the executor
+    /// cannot trust its inputs, and a stuck proof transaction needs an operator to look at the L1
+    /// account, so neither is retried. Neither is a cursor which does not match the state, since
+    /// the operator has to decide which of the two to keep, nor a cursor written by a newer
+    /// version, nor a state whose unverified blocks can no longer be proven, nor a trusted state to
+    /// start from which does not check out, nor a contract which holds a state other than ours, nor
+    /// HotShot blocks which do not link up, nor an L1 provider on the wrong chain, nor a backfill
+    /// or an API node which does not reproduce the state, nor a key the contract no longer accepts
+    /// proofs from, nor a missing contract or an unfunded account, which only the operator can fix,
+    /// nor a block which took too long to execute, which would most likely stall the executor
+    /// again, nor a state which violates an invariant, which could only be proven wrong.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::L1 { .. }
+            | Self::QueryService { .. }
+            | Self::ExecutorApi { .. }
+            | Self::ProofAtWrongHeight { .. }
+            | Self::ProofRetriesExhausted { .. }
+            | Self::InsufficientFunds { .. }
+            | Self::NotYetCommitted { .. } => true,
+            Self::CommitmentMismatch { .. }
+            | Self::ProofTransactionStuck { .. }
+            | Self::CursorAheadOfState { .. }
+            | Self::UnsupportedCursor { .. }
+            | Self::ProofsUnavailable { .. }
+            | Self::InvalidStartPoint { .. }
+            | Self::StartPointMismatch { .. }
+            | Self::DivergedFromContract { .. }
+            | Self::WrongL1Chain { .. }
+            | Self::BrokenChain { .. }
+            | Self::BackfillDiverged { .. }
+            | Self::FollowerDiverged { .. }
+            | Self::NotAuthorizedSubmitter { .. }
+            | Self::MissingContract { .. }
+            | Self::UnfundedAccount { .. }
+            | Self::BlockQuarantined { .. }
+            | Self::InvariantViolated { .. } => false,
+        }
+    }
 }
@@ -4,60 +4,434 @@
 // You should have received a copy of the MIT License
 // along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
 
-use crate::prover::BatchProof;
+use crate::alert::{AlertKind, Alerts, Severity};
+use crate::build_info::BuildInfo;
+use crate::chain::{BlockLink, ChainVerifier};
+use crate::commitments::{commitment_to_u256, matches_block_hash};
+use crate::cursor::{Cursor, CursorError};
+use crate::deposit::{fetch_deposits, L1Deposits};
+use crate::error::ExecutorError;
+use crate::forced::{fetch_enqueued, L1Queue};
+use crate::hooks::BlockHooks;
+use crate::inbox::fetch_messages;
+use crate::invariants;
+use crate::l1_events::{fetch_in_batches, EventBackfillOptions, EventId, SeenEvents};
+use crate::l1_updates::{L1Update, L1UpdateHistory};
+use crate::logging::EXECUTOR;
+use crate::prover::{BatchProof, Proof};
+use crate::reconcile::{reconcile, Decision, Reconciliation, SavedCursor, StartupRecords};
+use crate::replicas::QueryReplicas;
+use crate::retention::RetentionConfig;
+use crate::snapshot::{read_snapshot, spawn_snapshot, write_snapshot, SnapshotOptions};
+use crate::watchdog::{PhaseTimer, QuarantinedBlock, WatchdogOptions};
+use crate::webhook::{WebhookConfig, WebhookEvent, WebhookSender};
 use async_compatibility_layer::async_primitives::broadcast::BroadcastSender;
-use async_std::sync::{Arc, RwLock};
-use async_std::task::sleep;
+use async_std::channel::{self, Receiver};
+use async_std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
+use async_std::task::{sleep, spawn_blocking};
 use commit::Committable;
 use contract_bindings::example_rollup::{self, ExampleRollup};
-use ethers::prelude::*;
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+use futures::future::{self, BoxFuture, Either, FutureExt};
+use futures::pin_mut;
 use hotshot_contract_bindings::hot_shot::{HotShot, NewBlocksFilter};
-use sequencer::{api::endpoints::NamespaceProofQueryData, Header, Vm};
+use hotshot_query_service::availability::BlockQueryData;
+use sequencer::{api::endpoints::NamespaceProofQueryData, Header, SeqTypes, Vm};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::fs::{self, File};
+use std::io;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use surf_disco::Url;
+use tide_disco::{error::Error as _, StatusCode};
 
-use sequencer_utils::{commitment_to_u256, connect_rpc, contract_send, u256_to_commitment};
+use sequencer_utils::{connect_rpc, Signer};
 
-use crate::state::State;
+use crate::state::{
+    Amount, BlockInputs, BlockResult, BlockTimestamp, ProofTransaction, State, TimestampSource,
+};
+use crate::status::{
+    BlockVerification, CommitmentMismatch, ProofCalldata, StatusHandle, SyncProgress,
+};
+use crate::utils::create_provider;
 
-type HotShotClient = surf_disco::Client<hotshot_query_service::Error>;
+const MIN_RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How long to wait for more blocks before proving a partly filled stride.
+const PROOF_IDLE_DELAY: Duration = Duration::from_secs(10);
+
+/// How often to check for verified blocks whose challenge window has passed, in optimistic mode.
+const FINALIZE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// The longest submitter lease the rollup contract lets an executor claim, in L1 blocks. Mirrors
+/// `MAX_LEASE_BLOCKS` in `ExampleRollup.sol`.
+pub const MAX_LEASE_BLOCKS: u64 = 7200;
+
+/// How often to check how many blocks the HotShot contract has committed, to report how far the
+/// executor has to go.
+const SYNC_TARGET_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long to wait before asking the query service again for block data it does not have yet.
+const UNAVAILABLE_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Number of times other failed queries for block data are retried before the executor gives up.
+const QUERY_RETRIES: u32 = 5;
+const MIN_QUERY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// How long to wait at startup for a replica of the query service to answer.
+const QUERY_CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[derive(Clone, Debug)]
 pub struct ExecutorOptions {
-    pub sequencer_url: Url,
+    /// Replicas of the query service to read HotShot blocks from, the primary first. Queries fail
+    /// over to the next replica when one does not answer; see [`replicas`](crate::replicas).
+    pub sequencer_urls: Vec<Url>,
     pub l1_http_provider: Url,
     pub l1_ws_provider: Url,
+    /// Chain ID the L1 providers must report.
+    ///
+    /// Proof transactions are signed for this chain, and the executor refuses to start if either
+    /// L1 provider reports a different one. If not set, whatever chain the HTTP provider reports is
+    /// trusted.
+    pub l1_chain_id: Option<u64>,
     pub rollup_account_index: u32,
     pub rollup_mnemonic: String,
     pub hotshot_address: Address,
     pub rollup_address: Address,
     pub output_stream: Option<BroadcastSender<(u64, State)>>,
+    /// Custom processing run after each executed block.
+    pub block_hooks: BlockHooks,
+    /// Endpoints notified of executed blocks, verified proofs and large transfers. Notifications
+    /// which cannot be delivered are logged to `webhook-dead-letters.jsonl` in `diagnostics_dir`.
+    pub webhooks: Vec<WebhookConfig>,
+    /// Where alerts are raised when the rollup may be in danger, such as when a block does not
+    /// match its commitment on L1. Webhooks subscribed to [`WebhookEvent::Alert`] receive them as
+    /// well.
+    pub alerts: Alerts,
+    pub fee_bump: FeeBumpOptions,
+    pub balance_monitor: BalanceMonitorOptions,
+    /// When to alert that the rollup is falling behind HotShot or L1 verification.
+    pub lag_alerts: LagAlertOptions,
+    /// When to warn that a block is slow to execute, and when to give up on it. See
+    /// [`watchdog`](crate::watchdog).
+    pub watchdog: WatchdogOptions,
+    /// Number of blocks between checks of the [invariants](crate::invariants) of the state. The
+    /// state is checked after each block whose height plus one is a multiple of this, and never if
+    /// this is 0. A block after which the state violates one is not proven.
+    pub invariant_interval: u64,
+    /// How to recover the events of the HotShot contract which the log subscription misses. See
+    /// [`l1_events`](crate::l1_events).
+    pub event_backfill: EventBackfillOptions,
+    /// How much block history to keep in the state, and how many snapshots to keep on disk. See
+    /// [`retention`](crate::retention).
+    pub retention: RetentionConfig,
+    /// JSON-RPC endpoint which proof transactions are sent to, such as a private relay.
+    ///
+    /// Transactions are signed locally and sent raw. If not set, they are sent through
+    /// `l1_http_provider`, which is used for all reads either way.
+    pub submission_url: Option<Url>,
+    /// Directory where evidence is written if a block does not match its commitment on L1.
+    pub diagnostics_dir: PathBuf,
+    /// File where progress is recorded after every block. See [`Cursor`].
+    pub cursor_path: Option<PathBuf>,
+    /// Where to write periodic snapshots of the state, if anywhere.
+    pub snapshots: Option<SnapshotOptions>,
+    /// Start from a trusted state rather than the one the executor is given. See [`StartPoint`].
+    pub start: Option<StartPoint>,
+    /// Read back the state commitment from the rollup contract after each accepted proof, and
+    /// stop if it is not the one we computed. This costs one `eth_call` per batch.
+    pub check_commitments: bool,
+    /// Check that each HotShot block follows from the one before it. See [`ChainVerifier`].
+    ///
+    /// The chain is anchored at the block before the first one executed, which is trusted once it
+    /// matches its commitment on L1.
+    pub verify_chain: bool,
+    /// Run in high-availability mode, holding a submitter lease of this many L1 blocks.
+    ///
+    /// Several executors can run against the same rollup contract in this mode. Only the one
+    /// holding the lease sends proofs; the others execute blocks and stand by, and one of them
+    /// takes over once the lease runs out. Unless the contract has an authorized submitter, an
+    /// executor can only renew the lease for a tenure of about a week, and then stands by for a
+    /// while, so a standby gets a turn.
+    pub submitter_lease_blocks: Option<u64>,
+    /// Follow the rollup without ever sending a transaction to L1.
+    ///
+    /// Blocks are executed and checked against the HotShot contract as usual, but instead of
+    /// proving them, the executor compares each state update the rollup contract accepts with the
+    /// state it computed, and reports the result as
+    /// [`contract_agreement`](crate::status::ExecutorStatus::contract_agreement). The L1 account is
+    /// never used, and need not be funded nor authorized to submit proofs.
+    pub read_only: bool,
+    /// Where the state updates the rollup contract accepts are recorded, for the API to serve.
+    ///
+    /// Unless another executor may be sending proofs, in read-only or high-availability mode, each
+    /// update the executor did not send itself is reported in
+    /// [`l1_updates`](crate::status::ExecutorStatus::l1_updates).
+    pub l1_updates: L1UpdateHistory,
+    /// Number of blocks to prove in each proof sent to L1. The state is only submitted after blocks
+    /// whose height is a multiple of this, and after the latest block once no new blocks have
+    /// arrived for a while. When HotShot commits several blocks at once, they are proven up to the
+    /// last such height among them. 1 proves each batch of blocks as soon as it is executed.
+    pub proof_interval: u64,
+    /// Number of executed batches whose proofs may wait to be sent to L1. When this many are
+    /// waiting, execution pauses until the oldest has been sent.
+    pub proof_queue_depth: usize,
+    /// How long to keep sending queued proofs after execution stops.
+    pub drain_timeout: Duration,
+    /// Stop executing new blocks once a message is received on this channel or it is closed.
+    /// Proofs of the blocks already executed are still sent, for up to `drain_timeout`.
+    pub shutdown: Option<Receiver<()>>,
+    #[cfg(test)]
+    pub(crate) hooks: TestHooks,
+}
+
+/// A trusted state to start the executor from, to recover from a lost or damaged state, or to bring
+/// up another node without executing the whole rollup again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StartPoint {
+    /// Number of blocks the trusted state has executed. Execution resumes with the block at this
+    /// height.
+    pub height: u64,
+    /// Snapshot of the trusted state, as written by an executor.
+    pub state_snapshot_path: PathBuf,
+    /// Commitment the trusted state must have.
+    pub expected_commitment: U256,
+}
+
+/// Faults which tests can inject into the executor.
+#[cfg(test)]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TestHooks {
+    /// Panic once, just before executing the block at this height.
+    pub(crate) panic_at_block: Arc<std::sync::Mutex<Option<u64>>>,
+    /// Number of upcoming attempts to send a proof which fail without reaching L1.
+    pub(crate) failed_submissions: Arc<std::sync::atomic::AtomicU32>,
+    /// Held by each attempt to send a proof, so tests can hold up the submitter.
+    pub(crate) submission_gate: Arc<RwLock<()>>,
+    /// Slow down the block at this height by this long, in this phase of its execution.
+    pub(crate) slow_block: Option<(u64, crate::watchdog::Phase, Duration)>,
+    /// Number of upcoming events which the L1 log subscription loses, as it would while its
+    /// websocket reconnects.
+    pub(crate) dropped_events: Arc<std::sync::atomic::AtomicU32>,
+    /// Corrupt the state just after executing the block at this height, as a bug in a transition
+    /// could, by crediting the zero address with a token from nowhere.
+    pub(crate) corrupt_block: Option<u64>,
+}
+
+#[cfg(test)]
+impl TestHooks {
+    fn before_block(&self, height: u64) {
+        let mut panic_at_block = self.panic_at_block.lock().unwrap();
+        if *panic_at_block == Some(height) {
+            *panic_at_block = None;
+            drop(panic_at_block);
+            panic!("injected panic before block {height}");
+        }
+    }
+
+    fn after_block(&self, height: u64, state: &mut State) {
+        if self.corrupt_block == Some(height) {
+            let balance = state.get_balance(&Address::zero());
+            state.corrupt_balance(Address::zero(), balance + 1);
+        }
+    }
+
+    fn drops_event(&self) -> bool {
+        use std::sync::atomic::Ordering;
+
+        self.dropped_events
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |dropped| {
+                dropped.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    fn block_timer(&self, height: u64) -> PhaseTimer {
+        match self.slow_block {
+            Some((slow, phase, delay)) if slow == height => PhaseTimer::delayed(phase, delay),
+            _ => PhaseTimer::default(),
+        }
+    }
+
+    async fn before_submission(&self) -> Result<(), ExecutorError> {
+        use std::sync::atomic::Ordering;
+
+        drop(self.submission_gate.read().await);
+        let failures =
+            self.failed_submissions
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| {
+                    failures.checked_sub(1)
+                });
+        match failures {
+            Ok(_) => Err(ExecutorError::L1 {
+                reason: "injected submission failure".into(),
+            }),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// Evidence collected when a block header does not match the commitment HotShot posted to L1.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MismatchReport {
+    pub height: u64,
+    /// The commitment read from the HotShot contract, exactly as stored.
+    pub contract_commitment: U256,
+    /// The commitment computed from the header served by the query service.
+    pub header_commitment: String,
+    pub header: Header,
+    /// The query service replica which served the header.
+    pub sequencer_url: Url,
+    pub l1_url: Url,
+}
+
+impl MismatchReport {
+    /// Write the report to a new file in `dir`, returning its path.
+    pub fn write_bundle(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!(
+            "commitment-mismatch-{}-{timestamp}.json",
+            self.height
+        ));
+        serde_json::to_writer_pretty(File::create(&path)?, self)?;
+        Ok(path)
+    }
+}
+
+/// A channel for broadcasting signed L1 transactions.
+pub trait SubmitTx: Send + Sync {
+    /// Broadcast a signed, RLP-encoded transaction, returning its hash.
+    fn submit_raw(&self, tx: Bytes) -> BoxFuture<'_, Result<H256, ExecutorError>>;
+}
+
+/// Broadcast transactions with `eth_sendRawTransaction` on a JSON-RPC provider.
+impl<P: JsonRpcClient> SubmitTx for Provider<P> {
+    fn submit_raw(&self, tx: Bytes) -> BoxFuture<'_, Result<H256, ExecutorError>> {
+        async move {
+            self.send_raw_transaction(tx)
+                .await
+                .map(|pending| pending.tx_hash())
+                .map_err(l1_tx_error)
+        }
+        .boxed()
+    }
+}
+
+/// When and how to replace proof transactions which are not being mined, and how often to retry
+/// proofs which fail.
+#[derive(Clone, Debug)]
+pub struct FeeBumpOptions {
+    /// Number of L1 blocks to wait for a proof transaction before replacing it.
+    pub confirmation_blocks: u64,
+    /// Percentage by which each replacement raises the fees. Most L1 nodes require at least 10.
+    pub bump_percent: u64,
+    /// Number of replacements after which the executor gives up.
+    pub max_bumps: u32,
+    /// Number of times a proof may fail before the executor gives up on it. Proofs which the L1
+    /// account cannot pay for are not counted, since they wait for the account to be funded.
+    pub max_attempts: u32,
+}
+
+impl Default for FeeBumpOptions {
+    fn default() -> Self {
+        Self {
+            confirmation_blocks: 5,
+            bump_percent: 20,
+            max_bumps: 5,
+            max_attempts: 10,
+        }
+    }
+}
+
+/// When to check the balance of the L1 account proofs are sent from, and when to warn that it is
+/// running low.
+#[derive(Clone, Debug)]
+pub struct BalanceMonitorOptions {
+    /// How often to check the balance.
+    pub interval: Duration,
+    /// Warn while the balance, in wei, is below this.
+    pub low_balance_wei: U256,
+}
+
+impl Default for BalanceMonitorOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            // 0.1 ETH.
+            low_balance_wei: U256::exp10(17),
+        }
+    }
+}
+
+/// When to alert that the rollup is falling behind.
+///
+/// The lags are those reported in [`ExecutorStatus`](crate::status::ExecutorStatus): the
+/// `sequencing_lag` of the executed blocks behind the HotShot contract, and the
+/// `verification_lag` of the rollup contract behind the executed blocks.
+#[derive(Clone, Debug)]
+pub struct LagAlertOptions {
+    /// Alert when the sequencing lag stays above this many blocks. Never, if not set.
+    pub max_sequencing_lag: Option<u64>,
+    /// Alert when the verification lag stays above this many blocks. Never, if not set.
+    pub max_verification_lag: Option<u64>,
+    /// How long a lag must stay above its threshold before an alert is raised.
+    pub sustained: Duration,
+}
+
+impl Default for LagAlertOptions {
+    fn default() -> Self {
+        Self {
+            max_sequencing_lag: None,
+            max_verification_lag: None,
+            sustained: Duration::from_secs(60),
+        }
+    }
 }
 
 /// Runs the executor service, which is responsible for:
 /// 1) Fetching blocks of ordered transactions from HotShot and applying them to the Rollup State.
 /// 2) Submitting mock proofs to the Rollup Contract.
-pub async fn run_executor(opt: &ExecutorOptions, state: Arc<RwLock<State>>) {
+///
+/// Progress is reported through `status`, so the API can tell whether the local state has caught
+/// up with the blocks the rollup contract had already verified when the executor started.
+///
+/// Proofs are sent from a bounded queue, so execution runs ahead of L1 submission by at most
+/// `proof_queue_depth` batches. Returns once the L1 event stream ends or `opt.shutdown` fires,
+/// after sending the proofs still queued for up to `drain_timeout`. On shutdown, a last snapshot of
+/// the state is written and the number of blocks left unproven is reported in `status`.
+///
+/// Returns an error if a proof cannot be gotten onto the L1 even after raising its fees.
+pub async fn run_executor(
+    opt: &ExecutorOptions,
+    state: Arc<RwLock<State>>,
+    status: StatusHandle,
+) -> Result<(), ExecutorError> {
     let ExecutorOptions {
         rollup_account_index,
-        sequencer_url,
+        sequencer_urls,
         l1_http_provider,
         l1_ws_provider,
         hotshot_address,
         rollup_address,
         rollup_mnemonic,
-        output_stream,
+        submission_url,
+        ..
     } = opt;
-
-    let query_service_url = sequencer_url.join("availability").unwrap();
-    let hotshot = HotShotClient::new(query_service_url.clone());
-    hotshot.connect(None).await;
+    tracing::info!(target: EXECUTOR, "starting executor, {}", BuildInfo::current());
 
     // Connect to the layer one HotShot contract.
     let l1 = connect_rpc(
         l1_http_provider,
         rollup_mnemonic,
         *rollup_account_index,
-        None,
+        opt.l1_chain_id,
     )
     .await
     .expect("unable to connect to L1, hotshot commitment task exiting");
@@ -68,367 +442,6821 @@ pub async fn run_executor(opt: &ExecutorOptions, state: Arc<RwLock<State>>) {
         .await
         .expect("Unable to make websocket connection to L1");
 
+    // Make sure we are talking to the intended chain before anything is signed.
+    let chain_id = check_chain_id(&l1, l1_http_provider, opt.l1_chain_id).await?;
+    check_chain_id(&socket_provider, l1_ws_provider, Some(chain_id)).await?;
+    status.write().await.l1_chain_id = Some(chain_id);
+    check_contract_code(&l1, "HotShot", *hotshot_address).await?;
+    check_contract_code(&l1, "rollup", *rollup_address).await?;
+    if !opt.read_only {
+        check_funded(&l1, l1.address()).await?;
+    }
+
+    let hotshot = QueryReplicas::new(sequencer_urls).with_status(status.clone());
+    if !hotshot.connect(Some(QUERY_CONNECT_TIMEOUT)).await {
+        return Err(ExecutorError::QueryService {
+            reason: format!(
+                "none of {} replicas answered within {QUERY_CONNECT_TIMEOUT:?}",
+                sequencer_urls.len()
+            ),
+        });
+    }
+
     let rollup_contract = ExampleRollup::new(*rollup_address, Arc::new(l1));
+    let l1_start = rollup_contract
+        .client()
+        .get_block_number()
+        .await
+        .expect("Unable to read L1 block number");
+    let target_height = rollup_contract
+        .num_verified_blocks()
+        .block(l1_start)
+        .call()
+        .await
+        .expect("Unable to read verified block height from rollup contract");
+    let challenge_window = rollup_contract
+        .challenge_window()
+        .block(l1_start)
+        .call()
+        .await
+        .expect("Unable to read challenge window from rollup contract")
+        .as_u64();
+    let finalized_height = rollup_contract
+        .num_finalized_blocks()
+        .block(l1_start)
+        .call()
+        .await
+        .expect("Unable to read finalized block height from rollup contract");
+    {
+        let mut status = status.write().await;
+        status.target_height = target_height.as_u64();
+        status.challenge_window = challenge_window;
+        status.set_finalized(finalized_height.as_u64());
+        status.set_verified(target_height.as_u64(), None);
+    }
+    if !opt.read_only {
+        check_authorized_submitter(&rollup_contract).await?;
+    }
+    if let Some(start) = &opt.start {
+        let verified = target_height.as_u64();
+        start_from(opt, start, &state, &rollup_contract, l1_start, verified).await?;
+    }
+    backfill_verifications(&rollup_contract, &state, &status).await;
+
+    // If this executor is being restarted, the state may already include some blocks. Resume
+    // from the first block not yet executed, scanning L1 from where the cursor left off if it
+    // agrees with the state, and proving again whatever the contract has not verified.
+    let start_height = state.read().await.block_height();
+    let verified_height = target_height.as_u64();
+    let missing_proof = {
+        let state = state.read().await;
+        (verified_height..start_height).find(|height| state.proof(*height).is_none())
+    };
+    let Reconciliation { cursor, decisions } = reconcile(&StartupRecords {
+        state_height: start_height,
+        cursor: read_cursor(opt.cursor_path.as_deref())?,
+        contract: *rollup_address,
+        verified_height,
+        missing_proof,
+    })?;
+    for decision in &decisions {
+        match decision {
+            Decision::NoCursor | Decision::ReplayForward { .. } | Decision::InSync { .. } => {
+                tracing::info!(target: EXECUTOR, "{decision}")
+            }
+            _ => tracing::warn!(target: EXECUTOR, "{decision}"),
+        }
+    }
+    let from_block = cursor.l1_log_block;
+
     let hotshot_contract = HotShot::new(*hotshot_address, Arc::new(socket_provider));
     let filter = hotshot_contract
         .new_blocks_filter()
-        .from_block(0)
+        .from_block(from_block)
         // Ethers does not set the contract address on filters created via contract bindings. This
         // seems like a bug and I have reported it: https://github.com/gakonst/ethers-rs/issues/2528.
         // In the mean time we can work around by setting the address manually.
         .address(hotshot_contract.address().into());
+    let hotshot_height = hotshot_contract
+        .block_height()
+        .call()
+        .await
+        .expect("Unable to read block height from HotShot contract");
+    {
+        let mut status = status.write().await;
+        status.sync = SyncProgress::new(start_height, hotshot_height.as_u64(), Instant::now());
+        status.set_hotshot_height(hotshot_height.as_u64());
+    }
     let mut commits_stream = filter
-        .subscribe()
+        .subscribe_with_meta()
         .await
         .expect("Unable to subscribe to L1 log stream");
+    // Events the subscription misses are queried for over HTTP, which works even while the
+    // websocket is reconnecting.
+    let hotshot_logs = HotShot::new(*hotshot_address, rollup_contract.client());
+
+    let webhooks = webhook_sender(opt);
+    let block_hooks = match &webhooks {
+        Some(webhooks) => opt.block_hooks.clone().with(webhooks.clone()),
+        None => opt.block_hooks.clone(),
+    };
+    let alerts = executor_alerts(opt, webhooks.as_ref());
+    let watchdog = BlockWatchdog {
+        options: opt.watchdog,
+        alerts: &alerts,
+        status: &status,
+    };
 
-    let mut header_stream = hotshot
-        .socket("stream/headers/0")
-        .subscribe::<Header>()
-        .await
-        .expect("Unable to subscribe to HotShot block header stream");
     let vm_id: u64 = state.read().await.vm.id().into();
+    let mut chain = if opt.verify_chain {
+        Some(chain_anchor(opt, &alerts, &hotshot, &hotshot_contract, start_height).await?)
+    } else {
+        None
+    };
 
-    while let Some(event) = commits_stream.next().await {
-        let (first_block, num_blocks) = match event {
-            Ok(NewBlocksFilter {
-                first_block_number,
-                num_blocks,
-            }) => (first_block_number, num_blocks.as_u64()),
-            Err(err) => {
-                tracing::error!("Error in HotShot block stream, retrying: {err}");
-                continue;
+    // Proofs are sent to L1 by a separate submitter, so L1 latency does not hold up execution. The
+    // queue between them is bounded; when it is full, execution waits.
+    let cursor = Mutex::new(cursor);
+    let (queue, pending) = channel::bounded(opt.proof_queue_depth.max(1));
+    let interval = opt.proof_interval.max(1);
+    status.write().await.proof_interval = interval;
+    let submitter = ProofSubmitter {
+        opt,
+        rollup_contract: &rollup_contract,
+        broadcast: match submission_url {
+            Some(url) => Box::new(create_provider(url)),
+            None => Box::new(rollup_contract.client().provider().clone()),
+        },
+        state: &state,
+        status: &status,
+        cursor: &cursor,
+        l1_start,
+        target_height: target_height.as_u64(),
+        verified_height,
+        webhooks,
+        alerts: &alerts,
+    };
+    let blocks = BlockExecutor {
+        opt,
+        state: &state,
+        status: &status,
+        cursor: &cursor,
+        hotshot: &hotshot,
+        hotshot_contract: &hotshot_contract,
+        rollup_contract: &rollup_contract,
+        alerts: &alerts,
+        watchdog,
+        block_hooks,
+        vm_id,
+    };
+    // History is pruned in the background whenever blocks are executed or proven, so execution only
+    // waits for it while what is pruned is dropped. A request made while the last one is still
+    // waiting is redundant, and dropped.
+    let prune_floor = AtomicU64::new(start_height);
+    let (prune, prunes) = channel::bounded(1);
+    let submit = async {
+        let proofs = async {
+            while let Ok(batch) = pending.recv().await {
+                submitter.submit(&batch).await?;
+                {
+                    let mut cursor = cursor.lock().await;
+                    cursor.l1_log_block = batch.l1_log_block;
+                    store_cursor(opt.cursor_path.as_deref(), &cursor);
+                }
+                prune.try_send(()).ok();
             }
+            Ok::<_, ExecutorError>(())
         };
-
-        // Full block content may not be available immediately so wait for all blocks to be ready
-        // before building the batch proof
-        let headers: Vec<Header> = header_stream
-            .by_ref()
-            .take(num_blocks as usize)
-            .map(|result| result.expect("Error fetching block header"))
-            .collect()
-            .await;
-
-        // Execute new blocks, generating proofs.
-        let mut proofs = vec![];
-        tracing::info!(
-            "executing blocks {}-{}, state is {}",
-            first_block,
-            first_block + num_blocks - 1,
-            state.read().await.commit()
+        // Finalizing, pruning history, tracking the HotShot contract and watching the balance of
+        // our L1 account run alongside proving, and stop once the last proof has been sent. A
+        // read-only executor has no account to watch, and follows the proofs of the operator
+        // instead.
+        let background = future::join5(
+            finalize_blocks(&rollup_contract, &status, challenge_window, opt.read_only),
+            track_sync_target(&hotshot_contract, &status, &opt.lag_alerts, &alerts),
+            prune_history(
+                &state,
+                &status,
+                &cursor,
+                &prune_floor,
+                &opt.retention,
+                prunes,
+            ),
+            future::join(
+                submitter.follow(),
+                record_state_updates(opt, &alerts, &rollup_contract, &status, l1_start),
+            ),
+            async {
+                if !opt.read_only {
+                    monitor_balance(rollup_contract.client(), &opt.balance_monitor, &status).await;
+                }
+                future::pending().await
+            },
         );
-        for (i, header) in headers.into_iter().enumerate() {
-            let commitment = hotshot_contract
-                .commitments(first_block + i)
-                .call()
-                .await
-                .expect("Unable to read commitment");
-            let block_commitment =
-                u256_to_commitment(commitment).expect("Unable to deserialize block commitment");
-
-            if header.commit() != block_commitment {
-                panic!("Block commitment does not match hash of received block, the executor cannot continue");
+        pin_mut!(proofs);
+        pin_mut!(background);
+        match future::select(proofs, background).await {
+            Either::Left((res, _)) => res,
+            Either::Right((((), (), (), ((), ()), ()), _)) => {
+                unreachable!("background tasks of the submitter never stop")
+            }
+        }
+    };
+    let shutdown = async {
+        match &opt.shutdown {
+            Some(shutdown) => {
+                shutdown.recv().await.ok();
+                // Close the channel so that neither a restart nor the supervisor misses the
+                // shutdown once this message has been consumed.
+                shutdown.close();
+            }
+            None => future::pending().await,
+        }
+    };
+    let execute = async {
+        // Dropping the queue when execution stops lets the submitter drain it and finish.
+        let queue = queue;
+        let send = |batch: PendingBatch| queue.send(batch);
+        let mut stride = Stride::default();
+        // Events the subscription missed, found by querying the L1, which are processed before
+        // anything newer.
+        let mut missed = VecDeque::new();
+        let mut seen = SeenEvents::new(from_block);
+        let mut last_event = Instant::now();
+        let mut last_backfill = Instant::now();
+        pin_mut!(shutdown);
+        loop {
+            if missed.is_empty() && last_backfill.elapsed() >= opt.event_backfill.interval {
+                last_backfill = Instant::now();
+                let executed = state.read().await.block_height();
+                match backfill_new_blocks(opt, &hotshot_logs, &seen, executed, None).await {
+                    Ok(events) => {
+                        status.write().await.l1_events.backfilled += events.len() as u64;
+                        missed = events;
+                    }
+                    Err(err) => {
+                        tracing::warn!(target: EXECUTOR, "unable to check for missed blocks: {err}")
+                    }
+                }
+            }
+            let (event, meta) = match missed.pop_front() {
+                Some(event) => event,
+                None => {
+                    // While part of a stride is waiting to be proven, stop waiting for it to fill
+                    // up once HotShot goes quiet, so the latest block still gets proven. Either
+                    // way, stop waiting in time to check for missed events.
+                    let idle = !stride.is_empty();
+                    let mut wait = opt
+                        .event_backfill
+                        .interval
+                        .saturating_sub(last_backfill.elapsed());
+                    if idle {
+                        wait = wait.min(PROOF_IDLE_DELAY.saturating_sub(last_event.elapsed()));
+                    }
+                    let next = async_std::future::timeout(wait, commits_stream.next());
+                    pin_mut!(next);
+                    match future::select(shutdown.as_mut(), next).await {
+                        Either::Left(_) => {
+                            tracing::info!(target: EXECUTOR, "executor shutting down");
+                            break;
+                        }
+                        Either::Right((Ok(Some(Ok(event))), _)) => {
+                            #[cfg(test)]
+                            if opt.hooks.drops_event() {
+                                continue;
+                            }
+                            event
+                        }
+                        Either::Right((Ok(Some(Err(err))), _)) => {
+                            tracing::error!(
+                                target: EXECUTOR,
+                                "Error in HotShot block stream, retrying: {err}"
+                            );
+                            continue;
+                        }
+                        Either::Right((Ok(None), _)) => break,
+                        Either::Right((Err(_), _)) => {
+                            if idle && last_event.elapsed() >= PROOF_IDLE_DELAY {
+                                let batch = stride.take(&*state.read().await);
+                                if let Some(batch) = batch {
+                                    tracing::info!(
+                                        target: EXECUTOR,
+                                        "idle, proving blocks up to {}",
+                                        batch.end_block()
+                                    );
+                                    send(batch)
+                                        .await
+                                        .expect("proof queue is open while the executor runs");
+                                }
+                            }
+                            continue;
+                        }
+                    }
+                }
+            };
+            let id = event_id(&meta);
+            if seen.contains(&id) {
+                continue;
+            }
+            let first_block = event.first_block_number.as_u64();
+            let executed = state.read().await.block_height();
+            if first_block > executed {
+                // The subscription missed the events announcing the blocks in between. Process
+                // them first, and this one after them.
+                let l1_block = meta.block_number.as_u64();
+                tracing::warn!(
+                    target: EXECUTOR,
+                    "L1 event stream skipped HotShot blocks {executed}-{}, fetching them from L1",
+                    first_block - 1
+                );
+                let mut events =
+                    backfill_new_blocks(opt, &hotshot_logs, &seen, executed, Some(l1_block))
+                        .await?;
+                let recovered = events
+                    .iter()
+                    .filter(|(_, meta)| event_id(meta) != id)
+                    .count();
+                if !events
+                    .front()
+                    .is_some_and(|(event, _)| event.first_block_number.as_u64() <= executed)
+                {
+                    return Err(ExecutorError::L1 {
+                        reason: format!(
+                            "HotShot blocks {executed}-{} are missing from the L1 event stream",
+                            first_block - 1
+                        ),
+                    });
+                }
+                if events.len() == recovered {
+                    events.push_back((event, meta));
+                }
+                {
+                    let mut status = status.write().await;
+                    status.l1_events.gaps += 1;
+                    status.l1_events.backfilled += recovered as u64;
+                }
+                missed = events;
+                continue;
+            }
+            seen.insert(id, &opt.event_backfill);
+            last_event = Instant::now();
+            let num_blocks = event.num_blocks.as_u64();
+            let l1_block = meta.block_number.as_u64();
+            let end_block = first_block + num_blocks;
+            if end_block <= start_height && end_block <= verified_height {
+                // Executed and proven before a restart.
+                continue;
             }
 
-            let namespace_proof_query: NamespaceProofQueryData = hotshot
-                .get(&format!(
-                    "block/{}/namespace/{}",
-                    first_block.as_u64() + (i as u64),
-                    vm_id
-                ))
-                .send()
-                .await
-                .unwrap();
-            let namespace_proof = namespace_proof_query.proof;
+            let resume_block = first_block.max(start_height).min(end_block);
 
-            let mut state = state.write().await;
-            proofs.push(
-                state
-                    .execute_block(header.transactions_root, namespace_proof)
-                    .await,
+            // Blocks executed before a restart were already proven; reuse those proofs. Blocks the
+            // contract has verified need no proof, and theirs may have been pruned.
+            for height in first_block.max(verified_height).min(resume_block)..resume_block {
+                let proof = state.read().await.proof(height).cloned();
+                stride.push(
+                    height,
+                    l1_block,
+                    proof.expect("Proof of executed block is no longer available"),
+                );
+            }
+
+            // Execute new blocks, generating proofs.
+            tracing::info!(
+                target: EXECUTOR,
+                "executing blocks {}-{}, state is {}",
+                resume_block,
+                end_block - 1,
+                state.read().await.commit()
             );
-            if let Some(stream) = &output_stream {
-                stream
-                    .send_async((first_block.as_u64() + (i as u64), state.clone()))
+            for height in resume_block..end_block {
+                let proof = blocks.execute(height, &mut chain).await?;
+                stride.push(height, l1_block, proof);
+            }
+
+            // Prove the blocks up to the last stride boundary in this batch, if it reached one.
+            let batch = stride.cut(interval, &*state.read().await);
+            if let Some(batch) = batch {
+                send(batch)
                     .await
-                    .ok();
+                    .expect("proof queue is open while the executor runs");
             }
+
+            // The summary of any block in the stride may be needed to prove it.
+            let first_unproven = if stride.is_empty() {
+                state.read().await.block_height()
+            } else {
+                stride.first_block
+            };
+            prune_floor.store(first_unproven, Ordering::Relaxed);
+            prune.try_send(()).ok();
         }
 
-        // Compute an aggregate proof.
-        let proof = BatchProof::generate(&proofs).expect("Error generating batch proof");
-        let state_comm = commitment_to_u256(state.read().await.commit());
+        // Prove whatever is left of the last stride before stopping.
+        let batch = stride.take(&*state.read().await);
+        if let Some(batch) = batch {
+            send(batch)
+                .await
+                .expect("proof queue is open while the executor runs");
+        }
+        Ok::<_, ExecutorError>(())
+    };
 
-        // Send the batch proof to L1.
-        tracing::info!(
-            "rollup {vm_id} sending batch proof of state {} after blocks {}-{} to L1: {:?}",
-            state_comm,
-            first_block,
-            first_block + num_blocks - 1,
-            proof,
-        );
-        let proof = example_rollup::BatchProof::from(proof);
-        let call = rollup_contract.verify_blocks(num_blocks, state_comm, proof);
-        while let Err(err) = contract_send(&call).await {
-            tracing::warn!("Failed to submit proof to contract, retrying: {err}");
-            sleep(std::time::Duration::from_secs(1)).await;
+    // Boxed rather than pinned in place, so that neither is left holding a lock once this is done.
+    let res = match future::select(Box::pin(execute), Box::pin(submit)).await {
+        Either::Left((Ok(()), submit)) => {
+            // Send the proofs of everything we executed before stopping, if we can.
+            match async_std::future::timeout(opt.drain_timeout, submit).await {
+                Ok(res) => res,
+                Err(_) => {
+                    tracing::warn!(
+                        target: EXECUTOR,
+                        "gave up sending queued proofs after {:?}",
+                        opt.drain_timeout
+                    );
+                    Ok(())
+                }
+            }
         }
+        Either::Left((Err(err), _)) => Err(err),
+        Either::Right((res, _)) => res,
+    };
+
+    if opt
+        .shutdown
+        .as_ref()
+        .is_some_and(|shutdown| shutdown.is_closed())
+    {
+        record_shutdown(opt, &state, &status, &cursor).await;
     }
+    res
 }
 
-#[cfg(test)]
-mod test {
-    use crate::state::{Amount, Nonce};
-    use crate::transaction::{SignedTransaction, Transaction};
-    use crate::utils::{create_provider, deploy_example_contract, ExampleRollupContract};
-    use crate::RollupVM;
+/// Report the blocks left unproven by a shutdown in `status`, and snapshot the final state, so a
+/// restart does not replay the blocks since the last snapshot.
+async fn record_shutdown(
+    opt: &ExecutorOptions,
+    state: &RwLock<State>,
+    status: &StatusHandle,
+    cursor: &Mutex<Cursor>,
+) {
+    let state = state.read().await.clone();
+    let submitted = cursor.lock().await.submitted_height;
+    status.write().await.dropped_proofs = state.block_height().saturating_sub(submitted);
+    if let Some(snapshots) = &opt.snapshots {
+        let (dir, retain) = (snapshots.dir.clone(), opt.retention.snapshots);
+        let height = state.block_height();
+        match spawn_blocking(move || write_snapshot(&dir, &state, retain)).await {
+            Ok(path) => tracing::info!(target: EXECUTOR, "wrote final state snapshot {path:?}"),
+            Err(err) => {
+                tracing::warn!(
+                    target: EXECUTOR,
+                    "Unable to write snapshot of state at height {height}: {err}"
+                )
+            }
+        }
+    }
+}
 
-    use super::*;
-    use async_compatibility_layer::{
-        async_primitives::broadcast,
-        logging::{setup_backtrace, setup_logging},
+/// Replace `state` with the trusted state of `start`, unless the executor has already executed past
+/// it, as it has when it is restarted after executing more blocks.
+///
+/// The trusted state must have executed `start.height` blocks and have the expected commitment,
+/// which must also be the one the rollup contract recorded after that many blocks, if it is known:
+/// the commitment the contract holds if it has verified exactly `verified` blocks, or else that of
+/// the [recorded](crate::l1_updates) state update to that height. Unless the executor is read-only,
+/// the state must also have the proofs of the blocks the contract has yet to verify. The executor
+/// cursor is reset to the start point.
+async fn start_from<M: Middleware>(
+    opt: &ExecutorOptions,
+    start: &StartPoint,
+    state: &RwLock<State>,
+    rollup_contract: &ExampleRollup<M>,
+    l1_block: U64,
+    verified: u64,
+) -> Result<(), ExecutorError> {
+    let (height, vm) = {
+        let state = state.read().await;
+        (state.block_height(), state.vm)
     };
-    use async_std::task::spawn;
-    use contract_bindings::example_rollup::StateUpdateFilter;
-    use derivative::Derivative;
-    use ethers::prelude::k256::ecdsa::SigningKey;
-    use ethers::providers::{Middleware, Provider};
-    use ethers::signers::{LocalWallet, Signer};
-    use futures::{
-        future::{join_all, ready},
-        stream, FutureExt, Stream,
+    if height > start.height {
+        tracing::info!(
+            target: EXECUTOR,
+            "state has executed {height} blocks, past the start point at {}; resuming from it",
+            start.height
+        );
+        return Ok(());
+    }
+    let invalid = |reason: String| ExecutorError::InvalidStartPoint {
+        path: start.state_snapshot_path.clone(),
+        reason,
     };
-    use hotshot::types::SystemContextHandle;
-    use portpicker::pick_unused_port;
-    use rand::SeedableRng;
-    use rand_chacha::ChaChaRng;
-    use sequencer::{
-        api::options::{Http, Options},
-        context::SequencerContext,
-        hotshot_commitment::{run_hotshot_commitment_task, CommitmentTaskOptions},
-        network,
-        persistence::fs,
-        testing::{init_hotshot_handles, wait_for_decide_on_handle},
-        Node, SeqTypes, Vm, VmId,
+    let trusted = read_snapshot(&start.state_snapshot_path, &vm).map_err(invalid)?;
+    if trusted.block_height() != start.height {
+        return Err(invalid(format!(
+            "it has executed {} blocks rather than {}",
+            trusted.block_height(),
+            start.height
+        )));
+    }
+
+    let contract = if verified == start.height {
+        let commitment = rollup_contract
+            .state_commitment()
+            .block(l1_block)
+            .call()
+            .await
+            .map_err(|err| ExecutorError::L1 {
+                reason: err.to_string(),
+            })?;
+        Some(commitment)
+    } else {
+        opt.l1_updates
+            .page(start.height.saturating_sub(1), 1)
+            .await
+            .into_iter()
+            .find(|update| update.block_height == start.height)
+            .map(|update| update.state_commitment)
     };
-    use sequencer_utils::{commitment_to_u256, test_utils::TestL1System, Anvil, AnvilOptions};
-    use std::path::PathBuf;
-    use std::time::Duration;
-    use surf_disco::{Client, Url};
-    use tempfile::TempDir;
-    use tide_disco::error::ServerError;
+    let snapshot = commitment_to_u256(trusted.commit());
+    if snapshot != start.expected_commitment || contract.is_some_and(|theirs| theirs != snapshot) {
+        return Err(ExecutorError::StartPointMismatch {
+            height: start.height,
+            snapshot,
+            expected: start.expected_commitment,
+            contract,
+        });
+    }
+    if !opt.read_only {
+        if let Some(height) =
+            (verified..start.height).find(|height| trusted.proof(*height).is_none())
+        {
+            return Err(invalid(format!(
+                "it has no proof of block {height}, which the rollup contract has yet to verify"
+            )));
+        }
+    }
 
-    #[derive(Clone, Derivative)]
-    #[derivative(Debug)]
-    struct TestRollupInstance {
-        contract: ExampleRollupContract,
-        vm: RollupVM,
-        socket_provider: Provider<Ws>,
-        l1_url: Url,
-        alice: Wallet<SigningKey>,
-        state: Arc<RwLock<State>>,
-        bob: Wallet<SigningKey>,
-        #[derivative(Debug = "ignore")]
-        executor_send: BroadcastSender<(u64, State)>,
+    tracing::info!(
+        target: EXECUTOR,
+        "starting from trusted state {:?} after {} blocks, with commitment {snapshot}",
+        start.state_snapshot_path,
+        start.height
+    );
+    *state.write().await = trusted;
+    store_cursor(
+        opt.cursor_path.as_deref(),
+        &Cursor {
+            executed_height: start.height,
+            ..Default::default()
+        },
+    );
+    Ok(())
+}
+
+/// Prune the history kept in `state` under `retention` on each request received on `requests`.
+///
+/// Nothing is pruned at or above the height finalized on L1, nor at or above `floor`, the first
+/// block execution may still need the summary of, nor the last block whose proof has been accepted,
+/// which the submitter checks against the rollup contract when it catches up with it.
+async fn prune_history(
+    state: &RwLock<State>,
+    status: &StatusHandle,
+    cursor: &Mutex<Cursor>,
+    floor: &AtomicU64,
+    retention: &RetentionConfig,
+    requests: Receiver<()>,
+) {
+    while requests.recv().await.is_ok() {
+        let finalized = status.read().await.finalized_height;
+        let submitted = cursor.lock().await.submitted_height;
+        let protected = floor
+            .load(Ordering::Relaxed)
+            .min(finalized)
+            .min(submitted.saturating_sub(1));
+        let pruned = state.write().await.prune(retention, protected);
+        tracing::debug!(target: EXECUTOR, "pruned block history: {pruned:?}");
     }
+    future::pending().await
+}
 
-    impl TestRollupInstance {
-        pub async fn launch(
-            l1_url: Url,
-            vm_id: VmId,
-            alice: Wallet<SigningKey>,
-            bob: Wallet<SigningKey>,
-            test_l1: &TestL1System,
-        ) -> Self {
-            // Create mock rollup state
-            let vm = RollupVM::new(vm_id);
-            let state = State::from_initial_balances([(alice.address(), 9999)], vm);
-            let initial_state = state.commit();
-            let state = Arc::new(RwLock::new(state));
-            tracing::info!(
-                "rollup {vm_id:?} initial state: {initial_state} ({})",
-                commitment_to_u256(initial_state)
-            );
-            let mut ws_url = l1_url.clone();
-            ws_url.set_scheme("ws").unwrap();
-            let socket_provider = Provider::<Ws>::connect(ws_url).await.unwrap();
-            let rollup_contract = deploy_example_contract(test_l1, initial_state).await;
-            let (executor_send, _) = broadcast::channel();
+/// A batch of executed blocks whose proof is waiting to be sent to L1.
+struct PendingBatch {
+    first_block: u64,
+    num_blocks: u64,
+    /// L1 block containing the `NewBlocks` event for the last block in this batch.
+    l1_log_block: u64,
+    /// Commitment to the state after the last block in the batch.
+    state_comm: U256,
+    /// Balances after the last block in the batch, if it was the latest block executed when the
+    /// batch was cut.
+    balances: Option<Vec<(Address, Amount)>>,
+    proofs: Vec<Proof>,
+}
 
-            Self {
-                contract: rollup_contract,
-                vm,
-                socket_provider,
-                alice,
-                l1_url,
-                bob,
-                state,
-                executor_send,
-            }
-        }
+impl PendingBatch {
+    fn end_block(&self) -> u64 {
+        self.first_block + self.num_blocks
+    }
+}
 
-        pub async fn reset_socket_connnection(&mut self) {
-            let mut ws_url = self.l1_url.clone();
-            ws_url.set_scheme("ws").unwrap();
-            // Occasionally the connection fails, so we retry a few times.
-            for _ in 0..10 {
-                match Provider::<Ws>::connect(ws_url.clone()).await {
-                    Ok(provider) => {
-                        self.socket_provider = provider;
-                        return;
-                    }
-                    Err(_) => {
-                        tracing::warn!("Failed to connect to websocket, retrying");
-                        sleep(Duration::from_secs(1)).await;
-                    }
-                }
-            }
-            panic!("Failed to connect to websocket server: {:?}", ws_url);
-        }
+/// Executed blocks accumulating towards the next proof sent to L1.
+#[derive(Debug, Default)]
+struct Stride {
+    first_block: u64,
+    l1_log_block: u64,
+    proofs: Vec<Proof>,
+}
 
-        pub async fn subscribe_contract(
-            &self,
-        ) -> impl '_ + Stream<Item = (StateUpdateFilter, LogMeta)> {
-            let filter = self
-                .contract
-                .state_update_filter()
-                .filter
-                // Ethers does not set the contract address on filters created via contract
-                // bindings. This seems like a bug and I have reported it:
-                // https://github.com/gakonst/ethers-rs/issues/2528. In the mean time we can work
-                // around by setting the address manually.
-                .address(self.contract.address());
-            self.socket_provider
-                .subscribe_logs(&filter)
+impl Stride {
+    fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    fn push(&mut self, height: u64, l1_log_block: u64, proof: Proof) {
+        if self.is_empty() {
+            self.first_block = height;
+        }
+        self.l1_log_block = l1_log_block;
+        self.proofs.push(proof);
+    }
+
+    /// Split off the blocks up to the last height which is a multiple of `interval`, if the
+    /// stride has reached one. Later blocks stay in the stride.
+    fn cut(&mut self, interval: u64, state: &State) -> Option<PendingBatch> {
+        let end_block = self.first_block + self.proofs.len() as u64;
+        let boundary = end_block - end_block % interval;
+        if boundary <= self.first_block {
+            return None;
+        }
+        let rest = self
+            .proofs
+            .split_off((boundary - self.first_block) as usize);
+        let proofs = std::mem::replace(&mut self.proofs, rest);
+        let batch = self.batch(proofs, state);
+        self.first_block = boundary;
+        Some(batch)
+    }
+
+    /// End the stride, returning the batch of its blocks if there are any.
+    fn take(&mut self, state: &State) -> Option<PendingBatch> {
+        if self.is_empty() {
+            return None;
+        }
+        let proofs = std::mem::take(&mut self.proofs);
+        Some(self.batch(proofs, state))
+    }
+
+    fn batch(&self, proofs: Vec<Proof>, state: &State) -> PendingBatch {
+        let num_blocks = proofs.len() as u64;
+        let end_block = self.first_block + num_blocks;
+        let (state_comm, balances) = if end_block == state.block_height() {
+            (state.commit(), Some(state.balances().collect()))
+        } else {
+            let summary = state
+                .block_summary(end_block - 1)
+                .expect("Summary of executed block is no longer available");
+            (summary.state_commitment, None)
+        };
+        PendingBatch {
+            first_block: self.first_block,
+            num_blocks,
+            l1_log_block: self.l1_log_block,
+            state_comm: commitment_to_u256(state_comm),
+            balances,
+            proofs,
+        }
+    }
+}
+
+/// Executes the blocks announced by the HotShot contract one at a time, for [`run_executor`].
+struct BlockExecutor<'a> {
+    opt: &'a ExecutorOptions,
+    state: &'a RwLock<State>,
+    status: &'a StatusHandle,
+    cursor: &'a Mutex<Cursor>,
+    hotshot: &'a QueryReplicas,
+    hotshot_contract: &'a HotShot<Provider<Ws>>,
+    rollup_contract: &'a ExampleRollup<Signer>,
+    alerts: &'a Alerts,
+    watchdog: BlockWatchdog<'a>,
+    block_hooks: BlockHooks,
+    /// Namespace of the rollup in HotShot blocks.
+    vm_id: u64,
+}
+
+impl BlockExecutor<'_> {
+    /// Execute the block at `height` on the state, returning its proof.
+    ///
+    /// The header is checked against L1 and, if `chain` is given, against the headers before it.
+    /// Once executed, the block is recorded in the status and the cursor, and passed on to
+    /// snapshots, the output stream and the block hooks.
+    async fn execute(
+        &self,
+        height: u64,
+        chain: &mut Option<ChainVerifier>,
+    ) -> Result<Proof, ExecutorError> {
+        #[cfg(test)]
+        self.opt.hooks.before_block(height);
+
+        let header = self.fetch_header(height, chain).await?;
+        let namespace_path = format!("block/{height}/namespace/{}", self.vm_id);
+        let namespace_proof_query: NamespaceProofQueryData = self.fetch(&namespace_path).await?;
+        let namespace_proof = namespace_proof_query.proof;
+        let block: BlockQueryData<SeqTypes> = self.fetch(&format!("block/{height}")).await?;
+        let (inputs, assets) = self.fetch_inputs(height, &header).await?;
+        let credited = inputs.deposits.clone();
+
+        #[cfg(test)]
+        let timer = self.opt.hooks.block_timer(height);
+        #[cfg(not(test))]
+        let timer = PhaseTimer::default();
+        let timestamp = block_timestamp(&header);
+        let nmt_root = header.transactions_root;
+        let total_payloads = block.len() as u64;
+        let mut state = self.state.write().await;
+        let (proof, result) = self
+            .watchdog
+            .execute(
+                &mut state,
+                &timer,
+                height,
+                commitment_to_u256(nmt_root.commit()),
+                namespace_path,
+                move |state, timer| {
+                    state.execute_block(
+                        nmt_root,
+                        namespace_proof,
+                        timestamp,
+                        total_payloads,
+                        &inputs,
+                        timer,
+                    )
+                },
+            )
+            .await?;
+        #[cfg(test)]
+        self.opt.hooks.after_block(height, &mut state);
+        self.check_invariants(height, &state, &result)?;
+        self.record(height, state, &result, &credited, &assets)
+            .await;
+        Ok(proof)
+    }
+
+    /// Fetch the header of block `height`, and check it against L1 and the chain verified so far.
+    async fn fetch_header(
+        &self,
+        height: u64,
+        chain: &mut Option<ChainVerifier>,
+    ) -> Result<Header, ExecutorError> {
+        // Headers are fetched one at a time rather than streamed from a single replica, so a
+        // failed replica only costs a failover.
+        let header: Header = self.fetch(&format!("header/{height}")).await?;
+        let header = check_header_commitment(
+            self.opt,
+            self.alerts,
+            self.hotshot,
+            self.hotshot_contract,
+            height,
+            header,
+        )
+        .await?;
+        if let Some(chain) = chain {
+            chain
+                .verify(BlockLink::new(height, &header))
+                .map_err(|source| ExecutorError::BrokenChain { source })?;
+        }
+        Ok(header)
+    }
+
+    /// Fetch `path` from the query service.
+    async fn fetch<T: DeserializeOwned>(&self, path: &str) -> Result<T, ExecutorError> {
+        fetch_block_data(self.opt, self.hotshot, path, self.status).await
+    }
+
+    /// Fetch what block `height` takes from outside its namespace: L1 deposits, messages from other
+    /// rollups and transactions forced in through L1.
+    ///
+    /// Also returns the assets deposits are credited in. Before deposits are fetched, those already
+    /// credited are checked for L1 reorgs.
+    async fn fetch_inputs(
+        &self,
+        height: u64,
+        header: &Header,
+    ) -> Result<(BlockInputs, BTreeSet<Address>), ExecutorError> {
+        let l1_finalized = header.l1_finalized.as_ref().map(|block| block.number);
+        let (pending, sources, max_block_weight, assets, credited_through) = {
+            let state = self.state.read().await;
+            (
+                state.pending_deposits(header.l1_head, l1_finalized),
+                state.chain_config().message_sources.clone(),
+                state.chain_config().max_block_weight,
+                state.chain_config().assets.clone(),
+                state.deposits_through(),
+            )
+        };
+        if !assets.is_empty() {
+            self.watch_deposits(credited_through, header.l1_head)
+                .await?;
+        }
+        let deposits = match pending {
+            Some(blocks) => fetch_deposits(self.rollup_contract, blocks).await?,
+            None => L1Deposits::default(),
+        };
+        let messages = fetch_messages(
+            self.hotshot,
+            height,
+            &header.transactions_root,
+            &sources,
+            self.vm_id,
+            max_block_weight,
+        )
+        .await?;
+        let pending_forced = self.state.read().await.pending_forced(l1_finalized);
+        let forced = match pending_forced {
+            Some(blocks) => fetch_enqueued(self.rollup_contract, blocks).await?,
+            None => L1Queue::default(),
+        };
+        let inputs = BlockInputs {
+            deposits,
+            messages,
+            forced,
+        };
+        Ok((inputs, assets))
+    }
+
+    /// Raise an alert for each deposit credited through L1 block `credited_through` which an L1
+    /// reorg has since removed, as of L1 block `l1_head`.
+    async fn watch_deposits(
+        &self,
+        credited_through: u64,
+        l1_head: u64,
+    ) -> Result<(), ExecutorError> {
+        let mut watch = self.cursor.lock().await.deposits.clone();
+        let reorged = watch
+            .update(self.rollup_contract, credited_through, l1_head)
+            .await?;
+        for credited in &reorged {
+            let deposit = &credited.deposit;
+            self.alerts.raise(
+                Severity::Critical,
+                AlertKind::DepositReorged,
+                Some(credited.height),
+                format!(
+                    "deposit of {} of token {:?} to {:?} in L1 block {} was credited, but an L1 \
+                    reorg has removed it",
+                    deposit.amount, deposit.token, deposit.recipient, deposit.l1_block
+                ),
+            );
+        }
+        if !reorged.is_empty() {
+            self.status.write().await.reorged_deposits.extend(reorged);
+        }
+        self.cursor.lock().await.deposits = watch;
+        Ok(())
+    }
+
+    /// Check the state invariants after block `height`, if they are due, and stop if any fail.
+    fn check_invariants(
+        &self,
+        height: u64,
+        state: &State,
+        result: &BlockResult,
+    ) -> Result<(), ExecutorError> {
+        let interval = self.opt.invariant_interval;
+        if interval == 0 || (height + 1) % interval != 0 {
+            return Ok(());
+        }
+        let violations = invariants::check(state, result);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let err = ExecutorError::InvariantViolated { height, violations };
+        self.alerts.raise(
+            Severity::Critical,
+            AlertKind::InvariantViolated,
+            Some(height),
+            err.to_string(),
+        );
+        Err(err)
+    }
+
+    /// Record that block `height` has been executed, crediting the deposits it took in `assets`.
+    ///
+    /// The lock on the state is released before the block hooks run.
+    async fn record(
+        &self,
+        height: u64,
+        state: RwLockWriteGuard<'_, State>,
+        result: &BlockResult,
+        credited: &L1Deposits,
+        assets: &BTreeSet<Address>,
+    ) {
+        {
+            let mut status = self.status.write().await;
+            status.set_local_height(height + 1, Instant::now());
+            status.payloads += result.summary.payloads;
+        }
+        {
+            let mut cursor = self.cursor.lock().await;
+            cursor.executed_height = height + 1;
+            cursor.deposits.credit(height, credited, assets);
+            store_cursor(self.opt.cursor_path.as_deref(), &cursor);
+        }
+        if let Some(snapshots) = &self.opt.snapshots {
+            if (height + 1) % snapshots.interval == 0 {
+                spawn_snapshot(snapshots, self.opt.retention.snapshots, state.clone());
+            }
+        }
+        if let Some(stream) = &self.opt.output_stream {
+            stream.send_async((height, state.clone())).await.ok();
+        }
+        if !self.block_hooks.is_empty() {
+            let snapshot = state.clone();
+            drop(state);
+            self.block_hooks.run(result, &snapshot).await;
+        }
+    }
+}
+
+/// Sends the proofs of executed batches to the rollup contract, strictly in order.
+struct ProofSubmitter<'a> {
+    opt: &'a ExecutorOptions,
+    rollup_contract: &'a ExampleRollup<Signer>,
+    broadcast: Box<dyn SubmitTx>,
+    state: &'a RwLock<State>,
+    status: &'a StatusHandle,
+    cursor: &'a Mutex<Cursor>,
+    /// L1 block at which the executor started.
+    l1_start: U64,
+    /// Number of blocks the rollup contract had verified when the executor started.
+    target_height: u64,
+    /// Number of blocks the rollup contract had verified when the executor started, below which
+    /// nothing is proven again.
+    verified_height: u64,
+    webhooks: Option<WebhookSender>,
+    alerts: &'a Alerts,
+}
+
+impl<'a> ProofSubmitter<'a> {
+    /// Get a batch proven on L1, unless it already is or, in high-availability mode, another
+    /// executor is responsible for it. Does nothing in read-only mode.
+    async fn submit(&self, batch: &PendingBatch) -> Result<(), ExecutorError> {
+        let opt = self.opt;
+        if opt.read_only {
+            return Ok(());
+        }
+        let rollup_contract = self.rollup_contract;
+        let status = self.status;
+        let state_comm = batch.state_comm;
+        let end_block = batch.end_block();
+        if opt.check_commitments
+            && batch.first_block < self.target_height
+            && self.target_height < end_block
+        {
+            // A stride may end after the height the contract had reached when we started; check
+            // that we agree with the contract at that height before proving the rest.
+            let summary = self
+                .state
+                .read()
                 .await
-                .unwrap()
-                .map(|log| {
-                    let meta = LogMeta::from(&log);
-                    (parse_log(log).unwrap(), meta)
-                })
+                .block_summary(self.target_height - 1)
+                .cloned();
+            let summary = summary.expect("Summary of executed block is no longer available");
+            check_contract_commitment(
+                self.alerts,
+                rollup_contract,
+                self.l1_start.into(),
+                self.target_height,
+                commitment_to_u256(summary.state_commitment),
+            )
+            .await?;
+        }
+        if end_block <= self.verified_height {
+            // The rollup contract verified these blocks before this executor started. Once we
+            // catch up with it, make sure we agree on the result.
+            if opt.check_commitments && end_block == self.target_height {
+                check_contract_commitment(
+                    self.alerts,
+                    rollup_contract,
+                    self.l1_start.into(),
+                    end_block,
+                    state_comm,
+                )
+                .await?;
+            }
+            self.confirm(batch).await;
+            return Ok(());
         }
 
-        pub async fn subscribe_executor(&self) -> impl Stream<Item = (u64, State)> {
-            let recv = self.executor_send.handle_async().await;
-            stream::unfold(recv, |mut recv| async move {
-                Some((recv.recv_async().await.unwrap(), recv))
+        // Only prove the part of the batch which was not verified before this executor started.
+        let mut first_block = batch.first_block.max(self.verified_height);
+        let mut num_blocks = end_block - first_block;
+        let mut proofs = batch.proofs[(first_block - batch.first_block) as usize..].to_vec();
+
+        // In high-availability mode, only the holder of the submitter lease sends proofs. Since the
+        // previous holder may have stopped before proving everything, a new holder resyncs with
+        // the contract and proves all blocks it has not yet verified.
+        if let Some(lease_blocks) = opt.submitter_lease_blocks {
+            if !hold_submitter_lease(rollup_contract, lease_blocks).await? {
+                tracing::debug!(
+                    target: EXECUTOR,
+                    "following: another executor holds the submitter lease"
+                );
+                return Ok(());
+            }
+            let verified = rollup_contract
+                .num_verified_blocks()
+                .call()
+                .await
+                .map_err(|err| ExecutorError::L1 {
+                    reason: err.to_string(),
+                })?
+                .as_u64();
+            self.set_submitted_height(verified.min(end_block)).await;
+            if verified >= end_block {
+                self.confirm(batch).await;
+                return Ok(());
+            }
+            if verified != first_block {
+                proofs = self.executed_proofs(verified, end_block).await;
+                first_block = verified;
+                num_blocks = end_block - verified;
+            }
+        }
+
+        // Compute an aggregate proof.
+        let proof = BatchProof::generate(&proofs).expect("Error generating batch proof");
+
+        // Send the batch proof to L1. The call carries the height it proves from, so the contract
+        // rejects it once any other proof has been accepted in the mean time.
+        tracing::info!(
+            target: EXECUTOR,
+            "sending batch proof of state {} after blocks {}-{} to L1: {:?}",
+            state_comm,
+            first_block,
+            end_block - 1,
+            proof,
+        );
+        let proof = example_rollup::BatchProof::from(proof);
+        let proof_bytes = ethers::abi::AbiEncode::encode(proof.clone()).len();
+        let mut call =
+            rollup_contract.verify_blocks(first_block.into(), num_blocks, state_comm, proof);
+        let calldata = ProofCalldata::measure(
+            first_block,
+            num_blocks,
+            proof_bytes,
+            &call.calldata().unwrap_or_default(),
+        );
+        tracing::debug!(
+            target: EXECUTOR,
+            "proof of blocks {first_block}-{} is {proof_bytes} bytes, in {} bytes of calldata \
+            costing {} gas",
+            end_block - 1,
+            calldata.calldata_bytes,
+            calldata.calldata_gas,
+        );
+        let client = rollup_contract.client();
+        let mut failures = 0;
+        loop {
+            #[cfg(test)]
+            let res = opt.hooks.before_submission().await;
+            #[cfg(not(test))]
+            let res = Ok(());
+            let res = match res {
+                Ok(()) => {
+                    send_with_fee_bumps(
+                        &*client,
+                        &*self.broadcast,
+                        call.tx.clone(),
+                        &opt.fee_bump,
+                        status,
+                        first_block,
+                        num_blocks,
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            };
+            match res {
+                Ok(TransactionReceipt {
+                    status: Some(success),
+                    block_number: Some(l1_block),
+                    transaction_hash: l1_tx,
+                    gas_used,
+                    effective_gas_price,
+                    ..
+                }) if success == U64::one() => {
+                    let mut status = status.write().await;
+                    status.set_verification(
+                        first_block,
+                        num_blocks,
+                        BlockVerification::Verified {
+                            l1_tx,
+                            l1_block: l1_block.as_u64(),
+                        },
+                    );
+                    if let (Some(gas), Some(price)) = (gas_used, effective_gas_price) {
+                        status.record_proof_cost(num_blocks, gas * price);
+                    }
+                    status.record_proof_calldata(calldata);
+                    drop(status);
+                    self.state
+                        .write()
+                        .await
+                        .record_proof_transaction(ProofTransaction {
+                            l1_tx,
+                            l1_block: l1_block.as_u64(),
+                            first_block,
+                            num_blocks,
+                        });
+                    if let Some(webhooks) = &self.webhooks {
+                        webhooks
+                            .proof_verified(first_block, num_blocks, l1_tx, l1_block.as_u64())
+                            .await;
+                    }
+                    if opt.check_commitments {
+                        check_contract_commitment(
+                            self.alerts,
+                            rollup_contract,
+                            l1_block.into(),
+                            end_block,
+                            state_comm,
+                        )
+                        .await?;
+                    }
+                    self.confirm(batch).await;
+                    return Ok(());
+                }
+                Err(err @ ExecutorError::ProofTransactionStuck { .. }) => return Err(err),
+                res => {
+                    // A proof of these blocks may have been accepted in the mean time, either one
+                    // we sent before a restart or one from another prover, in which case ours is
+                    // rejected, usually before it is even sent, as a proof at the wrong height.
+                    // Then ours is a duplicate, and we skip it rather than retrying.
+                    let verified = match client.get_block_number().await {
+                        Ok(l1_head) => rollup_contract
+                            .num_verified_blocks()
+                            .block(l1_head)
+                            .call()
+                            .await
+                            .ok()
+                            .map(|verified| (l1_head, verified.as_u64())),
+                        Err(_) => None,
+                    };
+                    if let Some((l1_head, verified)) = verified {
+                        if verified >= end_block {
+                            tracing::info!(
+                                target: EXECUTOR,
+                                "blocks up to {end_block} were already verified, skipping our proof"
+                            );
+                            if opt.check_commitments && verified == end_block {
+                                check_contract_commitment(
+                                    self.alerts,
+                                    rollup_contract,
+                                    l1_head.into(),
+                                    end_block,
+                                    state_comm,
+                                )
+                                .await?;
+                            }
+                            self.confirm(batch).await;
+                            return Ok(());
+                        }
+                        if verified > first_block {
+                            // Only part of the batch was proven, so ours can never be accepted.
+                            // Prove the rest instead, which is not a failure of ours.
+                            tracing::info!(
+                                target: EXECUTOR,
+                                "blocks up to {verified} were already verified, proving only \
+                                blocks {verified}-{}",
+                                end_block - 1
+                            );
+                            self.set_submitted_height(verified).await;
+                            first_block = verified;
+                            num_blocks = end_block - verified;
+                            let proofs = self.executed_proofs(verified, end_block).await;
+                            let proof = BatchProof::generate(&proofs)
+                                .expect("Error generating batch proof");
+                            call = rollup_contract.verify_blocks(
+                                first_block.into(),
+                                num_blocks,
+                                state_comm,
+                                proof.into(),
+                            );
+                            continue;
+                        }
+                    }
+                    // The proof may have been rejected because our key was rotated out.
+                    check_authorized_submitter(rollup_contract).await?;
+                    if let Some(lease_blocks) = opt.submitter_lease_blocks {
+                        if !hold_submitter_lease(rollup_contract, lease_blocks).await? {
+                            tracing::warn!(
+                                target: EXECUTOR,
+                                "lost the submitter lease, no longer sending proofs"
+                            );
+                            status.write().await.set_verification(
+                                first_block,
+                                num_blocks,
+                                BlockVerification::Unproven,
+                            );
+                            return Ok(());
+                        }
+                    }
+                    match &res {
+                        Err(ExecutorError::InsufficientFunds { reason }) => {
+                            tracing::error!(
+                                target: EXECUTOR,
+                                "L1 account {:?} cannot pay for the proof of blocks \
+                                {first_block}-{}, fund it to resume proving: {reason}",
+                                client.address(),
+                                end_block - 1,
+                            )
+                        }
+                        _ => {
+                            tracing::warn!(
+                                target: EXECUTOR,
+                                "Failed to submit proof to contract, retrying: {res:?}"
+                            )
+                        }
+                    }
+                    status.write().await.set_verification(
+                        first_block,
+                        num_blocks,
+                        BlockVerification::Unproven,
+                    );
+                    if !matches!(res, Err(ExecutorError::InsufficientFunds { .. })) {
+                        failures += 1;
+                        if failures >= opt.fee_bump.max_attempts {
+                            return Err(ExecutorError::ProofRetriesExhausted {
+                                first_block,
+                                end_block,
+                                attempts: failures,
+                                reason: match res {
+                                    Ok(receipt) => format!(
+                                        "transaction {:?} reverted",
+                                        receipt.transaction_hash
+                                    ),
+                                    Err(err) => err.to_string(),
+                                },
+                            });
+                        }
+                    }
+                    sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    }
+
+    /// The proofs of the executed blocks from `from` up to `end_block`.
+    async fn executed_proofs(&self, from: u64, end_block: u64) -> Vec<Proof> {
+        let state = self.state.read().await;
+        (from..end_block)
+            .map(|height| {
+                state
+                    .proof(height)
+                    .cloned()
+                    .expect("Proof of executed block is no longer available")
             })
-            .boxed()
+            .collect()
+    }
+
+    async fn set_submitted_height(&self, height: u64) {
+        let mut cursor = self.cursor.lock().await;
+        cursor.submitted_height = cursor.submitted_height.max(height);
+        store_cursor(self.opt.cursor_path.as_deref(), &cursor);
+        drop(cursor);
+        self.status.write().await.set_verified(height, None);
+    }
+
+    /// In read-only mode, check each state update the rollup contract accepts against the state
+    /// we computed, once we have executed the blocks it covers. Never returns.
+    async fn follow(&self) {
+        if !self.opt.read_only {
+            return future::pending().await;
+        }
+        // Updates already checked before a restart are not counted again.
+        let mut checked = self
+            .status
+            .write()
+            .await
+            .contract_agreement
+            .get_or_insert_with(Default::default)
+            .checked_height;
+        let mut from_block = U64::zero();
+        loop {
+            match self.check_state_updates(from_block, checked).await {
+                Ok((l1_block, height)) => {
+                    from_block = l1_block;
+                    checked = height;
+                }
+                Err(err) => {
+                    tracing::warn!(target: EXECUTOR, "unable to check state updates: {err}")
+                }
+            }
+            sleep(FINALIZE_POLL_INTERVAL).await;
         }
+    }
 
-        /// Wait until some effect has happened, causing the rollup state to satisfy `predicate`.
-        ///
-        /// At each intermediate state, including the terminal state, this function checks that the
-        /// state reported by the executor matches the state in the contract. This implies that, if
-        /// this function returns, `predicate` holds on both the executor state and some state that
-        /// has been verified by the smart contract.
-        pub async fn wait_for_effect(&self, predicate: impl Fn(State) -> bool) {
-            let vm_id: VmId = self.vm.into();
-            let mut exec_stream = self.subscribe_executor().await;
-            let mut l1_stream = self.subscribe_contract().await;
-            loop {
-                // Get the next event from the contract.
-                let (event, log) = l1_stream.next().await.unwrap();
-                tracing::info!("rollup {vm_id:?} got contract event {event:?} {log:?}");
+    /// Compare the state updates the rollup contract accepted from L1 block `from_block` on, after
+    /// more than `checked` blocks, with the state we computed, as far as we have executed.
+    ///
+    /// Returns the L1 block and the height to resume from.
+    async fn check_state_updates(
+        &self,
+        from_block: U64,
+        mut checked: u64,
+    ) -> Result<(U64, u64), ExecutorError> {
+        let updates = self
+            .rollup_contract
+            .state_update_filter()
+            .from_block(from_block)
+            .address(self.rollup_contract.address().into())
+            .query_with_meta()
+            .await
+            .map_err(|err| ExecutorError::L1 {
+                reason: err.to_string(),
+            })?;
+        let mut from_block = from_block;
+        for (update, meta) in updates {
+            let height = update.block_height.as_u64();
+            if height <= checked {
+                continue;
+            }
+            let ours = {
+                let state = self.state.read().await;
+                if height > state.block_height() {
+                    // Check the rest once we have caught up with them.
+                    break;
+                }
+                state
+                    .block_summary(height - 1)
+                    .map(|summary| commitment_to_u256(summary.state_commitment))
+            };
+            let theirs = update.state_commitment;
+            self.state
+                .write()
+                .await
+                .record_proof_transaction(ProofTransaction {
+                    l1_tx: meta.transaction_hash,
+                    l1_block: meta.block_number.as_u64(),
+                    first_block: checked,
+                    num_blocks: height - checked,
+                });
+            {
+                let mut status = self.status.write().await;
+                status.set_verification(
+                    checked,
+                    height - checked,
+                    BlockVerification::Verified {
+                        l1_tx: meta.transaction_hash,
+                        l1_block: meta.block_number.as_u64(),
+                    },
+                );
+                let agreement = status
+                    .contract_agreement
+                    .get_or_insert_with(Default::default);
+                agreement.checked_height = height;
+                match ours {
+                    Some(ours) if ours == theirs => agreement.matched_updates += 1,
+                    Some(ours) => {
+                        if agreement.mismatch.is_none() {
+                            self.alerts.raise(
+                                Severity::Critical,
+                                AlertKind::DivergedFromContract,
+                                Some(height),
+                                format!(
+                                    "rollup contract holds state {theirs} after block {height}, \
+                                    but we computed {ours}"
+                                ),
+                            );
+                            agreement.mismatch = Some(CommitmentMismatch {
+                                height,
+                                ours,
+                                theirs,
+                            });
+                        }
+                    }
+                    // The summary was pruned, or is still to be backfilled.
+                    None => {}
+                }
+            }
+            self.set_submitted_height(height).await;
+            checked = height;
+            from_block = meta.block_number;
+        }
+        Ok((from_block, checked))
+    }
 
-                // Advance the executor stream to the corresponding state.
-                let state = loop {
-                    let (block_index, state) = exec_stream.next().await.unwrap();
+    /// Record that the rollup contract has verified every block in `batch`.
+    async fn confirm(&self, batch: &PendingBatch) {
+        self.set_submitted_height(batch.end_block()).await;
+        self.status
+            .write()
+            .await
+            .set_verified(batch.end_block(), batch.balances.clone());
+    }
+}
+
+/// Record each state update the rollup contract accepts in the executor's
+/// [history](ExecutorOptions::l1_updates). Never returns.
+async fn record_state_updates(
+    opt: &ExecutorOptions,
+    alerts: &Alerts,
+    rollup_contract: &ExampleRollup<Signer>,
+    status: &StatusHandle,
+    l1_start: U64,
+) {
+    let history = &opt.l1_updates;
+    // Only updates accepted since we started are checked against our account, and only if we send
+    // proofs at all.
+    let checks = UpdateChecks {
+        account: (!opt.read_only).then(|| rollup_contract.client().address()),
+        since: l1_start,
+        alert: opt.submitter_lease_blocks.is_none(),
+    };
+    let mut from_block = history
+        .last()
+        .await
+        .map_or(U64::zero(), |update| update.l1_block.into());
+    status.write().await.l1_updates.recorded = history.len().await as u64;
+    loop {
+        match record_new_state_updates(
+            rollup_contract,
+            history,
+            status,
+            alerts,
+            from_block,
+            &checks,
+        )
+        .await
+        {
+            Ok(l1_block) => from_block = l1_block,
+            Err(err) => {
+                tracing::warn!(target: EXECUTOR, "unable to record state updates: {err}")
+            }
+        }
+        sleep(FINALIZE_POLL_INTERVAL).await;
+    }
+}
+
+/// How the executor reconciles the state updates it records with its own proofs.
+struct UpdateChecks {
+    /// The L1 account our proofs are sent from, if we send any.
+    account: Option<Address>,
+    /// First L1 block whose updates are checked against `account`.
+    since: U64,
+    /// Whether an update we did not send is unexpected, which it is unless another executor may
+    /// hold the submitter lease.
+    alert: bool,
+}
+
+/// Record the state updates accepted from L1 block `from_block` on which are not yet in `history`.
+///
+/// Returns the L1 block to resume from.
+async fn record_new_state_updates(
+    rollup_contract: &ExampleRollup<Signer>,
+    history: &L1UpdateHistory,
+    status: &StatusHandle,
+    alerts: &Alerts,
+    from_block: U64,
+    checks: &UpdateChecks,
+) -> Result<U64, ExecutorError> {
+    let l1_err = |reason: String| ExecutorError::L1 { reason };
+    let updates = rollup_contract
+        .state_update_filter()
+        .from_block(from_block)
+        .address(rollup_contract.address().into())
+        .query_with_meta()
+        .await
+        .map_err(|err| l1_err(err.to_string()))?;
+    let mut from_block = from_block;
+    for (update, meta) in updates {
+        let block_height = update.block_height.as_u64();
+        if history
+            .last()
+            .await
+            .is_some_and(|last| last.block_height >= block_height)
+        {
+            from_block = meta.block_number;
+            continue;
+        }
+        let sent_by_us = match checks.account {
+            Some(account) if meta.block_number >= checks.since => {
+                let tx = rollup_contract
+                    .client()
+                    .get_transaction(meta.transaction_hash)
+                    .await
+                    .map_err(|err| l1_err(err.to_string()))?;
+                Some(tx.is_some_and(|tx| tx.from == account))
+            }
+            _ => None,
+        };
+        let update = L1Update {
+            block_height,
+            state_commitment: update.state_commitment,
+            l1_block: meta.block_number.as_u64(),
+            l1_tx: meta.transaction_hash,
+            sent_by_us,
+        };
+        if let Err(err) = history.record(update).await {
+            // Try again on the next poll, so that the history has no gaps.
+            tracing::warn!(target: EXECUTOR, "unable to record state update: {err}");
+            return Ok(from_block);
+        }
+        let mut status = status.write().await;
+        status.l1_updates.recorded += 1;
+        if checks.alert && sent_by_us == Some(false) {
+            alerts.raise(
+                Severity::Critical,
+                AlertKind::UnexpectedStateUpdate,
+                Some(block_height),
+                format!(
+                    "rollup contract accepted a state update to height {block_height} in L1 \
+                    transaction {:?}, which this executor did not send",
+                    meta.transaction_hash
+                ),
+            );
+            status.l1_updates.unexpected += 1;
+            status.l1_updates.last_unexpected = Some(update);
+        }
+        from_block = meta.block_number;
+    }
+    Ok(from_block)
+}
+
+/// Check the balance of the L1 account of `client` every `opt.interval`, recording it in `status`
+/// and warning while it is low.
+async fn monitor_balance(client: Arc<Signer>, opt: &BalanceMonitorOptions, status: &StatusHandle) {
+    let address = client.address();
+    loop {
+        match client.get_balance(address, None).await {
+            Ok(balance) => {
+                let mut status = status.write().await;
+                status.set_operator_balance(balance);
+                let runway = status.runway_blocks;
+                drop(status);
+                if balance < opt.low_balance_wei {
+                    let runway = match runway {
+                        Some(blocks) => format!("enough to prove about {blocks} more blocks"),
+                        None => "no proofs sent yet to estimate the runway from".into(),
+                    };
+                    tracing::warn!(
+                        target: EXECUTOR,
+                        "L1 account {address:?} holds {balance} wei, below the threshold of {} \
+                        wei ({runway}). Fund it before proofs start failing.",
+                        opt.low_balance_wei,
+                    );
+                }
+            }
+            Err(err) => {
+                tracing::warn!(
+                    target: EXECUTOR,
+                    "unable to read the balance of L1 account {address:?}: {err}"
+                )
+            }
+        }
+        sleep(opt.interval).await;
+    }
+}
+
+/// Finalize verified blocks once their challenge window has passed, if the rollup contract runs in
+/// optimistic mode.
+///
+/// Anyone may finalize a block, so the executor does it itself rather than waiting for someone else
+/// to, unless it is `read_only`, in which case it only records what others have finalized. Failures
+/// are logged and retried on the next poll. Never returns.
+async fn finalize_blocks(
+    rollup_contract: &ExampleRollup<Signer>,
+    status: &StatusHandle,
+    challenge_window: u64,
+    read_only: bool,
+) {
+    if challenge_window == 0 {
+        // Every verified block is already final.
+        return future::pending().await;
+    }
+    loop {
+        sleep(FINALIZE_POLL_INTERVAL).await;
+        if let Err(err) =
+            finalize_ready_blocks(rollup_contract, status, challenge_window, read_only).await
+        {
+            tracing::warn!(target: EXECUTOR, "unable to finalize blocks: {err}");
+        }
+    }
+}
+
+/// Keep the target height of the executor's progress, and the lags derived from it, up to date
+/// with the HotShot contract, raising an alert whenever a lag has stayed above its threshold in
+/// `opt` for long enough.
+async fn track_sync_target<M: Middleware>(
+    hotshot_contract: &HotShot<M>,
+    status: &StatusHandle,
+    opt: &LagAlertOptions,
+    alerts: &Alerts,
+) {
+    let mut sequencing = LagWatch::default();
+    let mut verification = LagWatch::default();
+    loop {
+        sleep(SYNC_TARGET_INTERVAL).await;
+        match hotshot_contract.block_height().call().await {
+            Ok(height) => status.write().await.set_hotshot_height(height.as_u64()),
+            Err(err) => {
+                tracing::warn!(target: EXECUTOR, "unable to read HotShot block height: {err}")
+            }
+        }
+        let (height, sequencing_lag, verification_lag) = {
+            let status = status.read().await;
+            (
+                status.local_height,
+                status.sequencing_lag,
+                status.verification_lag,
+            )
+        };
+        let now = Instant::now();
+        for (watch, lag, threshold, behind) in [
+            (
+                &mut sequencing,
+                sequencing_lag,
+                opt.max_sequencing_lag,
+                "the rollup is behind the HotShot contract",
+            ),
+            (
+                &mut verification,
+                verification_lag,
+                opt.max_verification_lag,
+                "the rollup contract is behind the executed blocks",
+            ),
+        ] {
+            let Some(threshold) = threshold else {
+                continue;
+            };
+            let raised = watch.raised;
+            if watch.observe(lag > threshold, opt.sustained, now) {
+                alerts.raise(
+                    Severity::Warning,
+                    AlertKind::Lagging,
+                    Some(height),
+                    format!(
+                        "{behind} by {lag} blocks, and has been by more than {threshold} for at \
+                        least {:?}",
+                        opt.sustained
+                    ),
+                );
+            } else if raised && !watch.raised {
+                tracing::info!(
+                    target: EXECUTOR,
+                    "{behind} by {lag} blocks, back within the threshold of {threshold}"
+                );
+            }
+        }
+    }
+}
+
+/// Whether a lag has stayed above its threshold for long enough to alert on.
+#[derive(Clone, Copy, Debug, Default)]
+struct LagWatch {
+    /// When the lag rose above the threshold, while it stays there.
+    above_since: Option<Instant>,
+    /// Whether an alert has been raised since then.
+    raised: bool,
+}
+
+impl LagWatch {
+    /// Record whether the lag is above its threshold at `now`. Returns whether to alert, which is
+    /// once for each stretch of time above the threshold, after it has lasted for `sustained`.
+    fn observe(&mut self, above: bool, sustained: Duration, now: Instant) -> bool {
+        if !above {
+            *self = Self::default();
+            return false;
+        }
+        let since = *self.above_since.get_or_insert(now);
+        if self.raised || now.duration_since(since) < sustained {
+            return false;
+        }
+        self.raised = true;
+        true
+    }
+}
+
+/// Finalize the last verified block whose challenge window has passed, if there is one and the
+/// executor is not `read_only`.
+async fn finalize_ready_blocks(
+    rollup_contract: &ExampleRollup<Signer>,
+    status: &StatusHandle,
+    challenge_window: u64,
+    read_only: bool,
+) -> Result<(), ExecutorError> {
+    // Another node may have finalized blocks since we last looked.
+    let finalized = rollup_contract
+        .num_finalized_blocks()
+        .call()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?;
+    status.write().await.set_finalized(finalized.as_u64());
+    if read_only {
+        return Ok(());
+    }
+
+    let now = rollup_contract
+        .client()
+        .get_block(BlockNumber::Latest)
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?
+        .ok_or_else(|| ExecutorError::L1 {
+            reason: "latest L1 block is not available".into(),
+        })?
+        .timestamp;
+    // Updates are submitted in order, so their windows end in order too.
+    let mut ready = None;
+    for height in status.read().await.unfinalized_heights() {
+        let submitted = rollup_contract
+            .submitted_at(height.into())
+            .call()
+            .await
+            .map_err(|err| ExecutorError::L1 {
+                reason: err.to_string(),
+            })?;
+        if submitted.is_zero() || submitted + challenge_window > now {
+            break;
+        }
+        ready = Some(height);
+    }
+    let Some(height) = ready else {
+        return Ok(());
+    };
+
+    let receipt = rollup_contract
+        .finalize_block(height.into())
+        .send()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?;
+    match receipt {
+        Some(TransactionReceipt {
+            status: Some(success),
+            ..
+        }) if success == U64::one() => {
+            tracing::info!(target: EXECUTOR, "finalized blocks up to {height}");
+            status.write().await.set_finalized(height);
+            Ok(())
+        }
+        _ => Err(ExecutorError::L1 {
+            reason: format!("finalizing blocks up to {height} failed"),
+        }),
+    }
+}
+
+/// Fetch the block data at `path` from the query service.
+///
+/// The HotShot contract can certify a block slightly before the query service has its contents,
+/// which the query service reports as not found. That is retried, without limit, until the data
+/// arrives. Other errors are retried [`QUERY_RETRIES`] times with exponential backoff before
+/// giving up.
+async fn fetch_block_data<T: DeserializeOwned>(
+    opt: &ExecutorOptions,
+    hotshot: &QueryReplicas,
+    path: &str,
+    status: &StatusHandle,
+) -> Result<T, ExecutorError> {
+    let mut unavailable = false;
+    let mut failures = 0;
+    let mut backoff = MIN_QUERY_BACKOFF;
+    loop {
+        match hotshot.get::<T>(path).await {
+            Ok(data) => return Ok(data),
+            Err(err) if err.status() == StatusCode::NotFound => {
+                if !unavailable {
+                    unavailable = true;
+                    status.write().await.unavailable_blocks += 1;
                     tracing::info!(
-                        "rollup {vm_id:?} executor commitment after block {block_index} is {}",
-                        commitment_to_u256(state.commit())
+                        target: EXECUTOR,
+                        "{path} is not available from the query service yet, waiting"
                     );
-                    if block_index + 1 == event.block_height.as_u64() {
-                        break state;
-                    }
-                };
+                }
+                sleep(UNAVAILABLE_RETRY_DELAY).await;
+            }
+            Err(err) if failures < QUERY_RETRIES => {
+                failures += 1;
+                tracing::warn!(
+                    target: EXECUTOR,
+                    "failed to fetch {path}, retrying in {backoff:?}: {err}"
+                );
+                sleep(backoff).await;
+                backoff *= 2;
+            }
+            Err(err) => {
+                return Err(ExecutorError::QueryService {
+                    reason: format!(
+                        "{path} from any of {} replicas: {err}",
+                        opt.sequencer_urls.len()
+                    ),
+                })
+            }
+        }
+    }
+}
+
+/// Start verifying the HotShot chain at the block before `start_height`, after checking it against
+/// its commitment on L1.
+async fn chain_anchor<M: Middleware>(
+    opt: &ExecutorOptions,
+    alerts: &Alerts,
+    hotshot: &QueryReplicas,
+    hotshot_contract: &HotShot<M>,
+    start_height: u64,
+) -> Result<ChainVerifier, ExecutorError> {
+    let Some(height) = start_height.checked_sub(1) else {
+        return Ok(ChainVerifier::default());
+    };
+    let header: Header = hotshot
+        .get(&format!("header/{height}"))
+        .await
+        .map_err(|err| ExecutorError::QueryService {
+            reason: err.to_string(),
+        })?;
+    let header =
+        check_header_commitment(opt, alerts, hotshot, hotshot_contract, height, header).await?;
+    Ok(ChainVerifier::new(Some(BlockLink::new(height, &header))))
+}
+
+/// The timestamp of a HotShot block, or the current time if HotShot did not assign one.
+pub(crate) fn block_timestamp(header: &Header) -> BlockTimestamp {
+    if header.timestamp != 0 {
+        return BlockTimestamp {
+            seconds: header.timestamp,
+            source: TimestampSource::HotShot,
+        };
+    }
+    BlockTimestamp {
+        seconds: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        source: TimestampSource::Observed,
+    }
+}
+
+/// Check that the L1 provider at `url` is connected to the chain with ID `expected`, if given.
+///
+/// Returns the chain ID reported by the provider.
+pub(crate) async fn check_chain_id<M: Middleware>(
+    provider: &M,
+    url: &Url,
+    expected: Option<u64>,
+) -> Result<u64, ExecutorError> {
+    let actual = provider
+        .get_chainid()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?
+        .as_u64();
+    match expected {
+        Some(expected) if expected != actual => Err(ExecutorError::WrongL1Chain {
+            url: url.clone(),
+            expected,
+            actual,
+        }),
+        _ => Ok(actual),
+    }
+}
+
+/// Check that the `name` contract is deployed at `address`.
+pub(crate) async fn check_contract_code<M: Middleware>(
+    provider: &M,
+    name: &'static str,
+    address: Address,
+) -> Result<(), ExecutorError> {
+    let code = provider
+        .get_code(address, None)
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?;
+    if code.is_empty() {
+        return Err(ExecutorError::MissingContract { name, address });
+    }
+    Ok(())
+}
+
+/// Check that the L1 account `address` can pay for transactions, returning its balance.
+pub(crate) async fn check_funded<M: Middleware>(
+    provider: &M,
+    address: Address,
+) -> Result<U256, ExecutorError> {
+    let balance = provider
+        .get_balance(address, None)
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?;
+    if balance.is_zero() {
+        return Err(ExecutorError::UnfundedAccount { address });
+    }
+    Ok(balance)
+}
+
+/// Check that the rollup contract, as of `l1_block`, holds the state commitment we computed after
+/// executing `height` blocks.
+///
+/// A mismatch means something other than this executor, such as a second prover instance, has
+/// updated the contract, and the executor must stop.
+async fn check_contract_commitment<M: Middleware>(
+    alerts: &Alerts,
+    rollup_contract: &ExampleRollup<M>,
+    l1_block: BlockId,
+    height: u64,
+    ours: U256,
+) -> Result<(), ExecutorError> {
+    let theirs = rollup_contract
+        .state_commitment()
+        .block(l1_block)
+        .call()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?;
+    if theirs != ours {
+        alerts.raise(
+            Severity::Critical,
+            AlertKind::DivergedFromContract,
+            Some(height),
+            format!(
+                "rollup contract holds state {theirs} after block {height} as of L1 block \
+                {l1_block:?}, but we computed {ours}"
+            ),
+        );
+        return Err(ExecutorError::DivergedFromContract {
+            height,
+            ours,
+            theirs,
+        });
+    }
+    Ok(())
+}
+
+/// Make sure the rollup contract accepts proofs from this executor's L1 account.
+///
+/// Fails with [`ExecutorError::NotAuthorizedSubmitter`] if the owner of the contract has made
+/// another account the authorized submitter, for instance after rotating a compromised key.
+async fn check_authorized_submitter(
+    rollup_contract: &ExampleRollup<Signer>,
+) -> Result<(), ExecutorError> {
+    let ours = rollup_contract.client().address();
+    let authorized = rollup_contract
+        .authorized_submitter()
+        .call()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?;
+    if authorized != Address::zero() && authorized != ours {
+        return Err(ExecutorError::NotAuthorizedSubmitter { ours, authorized });
+    }
+    Ok(())
+}
+
+/// Make sure this executor holds the submitter lease on the rollup contract, claiming or renewing it
+/// if possible.
+///
+/// Returns `false` if another executor holds the lease, in which case this one must not send
+/// proofs.
+async fn hold_submitter_lease(
+    rollup_contract: &ExampleRollup<Signer>,
+    lease_blocks: u64,
+) -> Result<bool, ExecutorError> {
+    let me = rollup_contract.client().address();
+    let l1_head = rollup_contract
+        .client()
+        .get_block_number()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?;
+    let holder = rollup_contract
+        .current_submitter()
+        .block(l1_head)
+        .call()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: err.to_string(),
+        })?;
+    if holder != Address::zero() && holder != me {
+        return Ok(false);
+    }
+    if holder == me {
+        let expiry = rollup_contract
+            .lease_expiry()
+            .block(l1_head)
+            .call()
+            .await
+            .map_err(|err| ExecutorError::L1 {
+                reason: err.to_string(),
+            })?;
+        // Renew once half the lease has passed, so it does not lapse between batches.
+        if expiry.as_u64() > l1_head.as_u64() + lease_blocks / 2 {
+            return Ok(true);
+        }
+    }
+
+    // Another executor may claim the lease at the same time, in which case one of the claims
+    // reverts. A renewal reverts once our tenure has run out, but the lease is still ours until it
+    // expires.
+    let call = rollup_contract.claim_submitter(lease_blocks.into());
+    let claimed = match call.send().await {
+        Ok(pending) => matches!(
+            pending.await,
+            Ok(Some(TransactionReceipt { status: Some(success), .. })) if success == U64::one()
+        ),
+        Err(err) => {
+            tracing::info!(target: EXECUTOR, "Unable to claim submitter lease: {err}");
+            false
+        }
+    };
+    if claimed && holder != me {
+        tracing::info!(
+            target: EXECUTOR,
+            "claimed the submitter lease, taking over proof submission"
+        );
+    }
+    Ok(claimed || holder == me)
+}
+
+/// Read the cursor left by a previous run, if a cursor file is configured.
+///
+/// A cursor in a newer format than this version supports means the node was downgraded, which the
+/// operator has to resolve. Any other cursor which cannot be read is left to [`reconcile`] to set
+/// aside.
+fn read_cursor(path: Option<&Path>) -> Result<SavedCursor, ExecutorError> {
+    let Some(path) = path else {
+        return Ok(SavedCursor::None);
+    };
+    match Cursor::load(path) {
+        Ok(Some(cursor)) => Ok(SavedCursor::Found {
+            path: path.to_path_buf(),
+            cursor,
+        }),
+        Ok(None) => Ok(SavedCursor::None),
+        Err(CursorError::TooNew { source }) => Err(ExecutorError::UnsupportedCursor {
+            path: path.to_path_buf(),
+            reason: source.to_string(),
+        }),
+        Err(err) => Ok(SavedCursor::Unreadable {
+            path: path.to_path_buf(),
+            reason: err.to_string(),
+        }),
+    }
+}
+
+/// Record executor progress, if a cursor file is configured.
+///
+/// A failed write is only logged. The next restart notices the stale cursor and falls back to
+/// the rollup contract.
+fn store_cursor(path: Option<&Path>, cursor: &Cursor) {
+    if let Some(path) = path {
+        if let Err(err) = cursor.store(path) {
+            tracing::warn!(target: EXECUTOR, "Unable to store executor cursor at {path:?}: {err}");
+        }
+    }
+}
+
+/// The webhooks in `opt.webhooks`, if there are any.
+fn webhook_sender(opt: &ExecutorOptions) -> Option<WebhookSender> {
+    (!opt.webhooks.is_empty()).then(|| {
+        WebhookSender::new(
+            &opt.webhooks,
+            opt.diagnostics_dir.join("webhook-dead-letters.jsonl"),
+        )
+    })
+}
+
+/// The sinks in `opt.alerts`, and `webhooks` if any of them subscribe to alerts.
+fn executor_alerts(opt: &ExecutorOptions, webhooks: Option<&WebhookSender>) -> Alerts {
+    let subscribed = opt
+        .webhooks
+        .iter()
+        .any(|webhook| webhook.events.contains(&WebhookEvent::Alert));
+    match webhooks {
+        Some(webhooks) if subscribed => opt.alerts.clone().with(webhooks.clone()),
+        _ => opt.alerts.clone(),
+    }
+}
+
+/// Times each block the executor executes, and gives up on one which takes too long. See
+/// [`watchdog`](crate::watchdog).
+struct BlockWatchdog<'a> {
+    options: WatchdogOptions,
+    alerts: &'a Alerts,
+    status: &'a StatusHandle,
+}
+
+impl BlockWatchdog<'_> {
+    /// Run `execute` on `state` to execute the block at `height`, timing it with `timer`.
+    ///
+    /// The block is executed on a copy of the state, so that a panic part way through leaves
+    /// `state` as it was, and a restart executes the block again from the start. With a hard
+    /// limit, the copy is executed in a thread of its own, and if it does not finish in time the
+    /// block is quarantined, as the rollup block `height` whose transactions have commitment
+    /// `block` and are served by the query service at `namespace_path`, and `state` is left as it
+    /// was.
+    async fn execute(
+        &self,
+        state: &mut State,
+        timer: &PhaseTimer,
+        height: u64,
+        block: U256,
+        namespace_path: String,
+        execute: impl FnOnce(&mut State, &PhaseTimer) -> (Proof, BlockResult) + Send + 'static,
+    ) -> Result<(Proof, BlockResult), ExecutorError> {
+        let mut next = state.clone();
+        let (proof, result) = match self.options.block_limit {
+            None => {
+                let executed = execute(&mut next, timer);
+                *state = next;
+                executed
+            }
+            Some(limit) => {
+                let worker_timer = timer.clone();
+                let worker = spawn_blocking(move || {
+                    let executed = execute(&mut next, &worker_timer);
+                    (next, executed)
+                });
+                match async_std::future::timeout(limit, worker).await {
+                    Ok((next, executed)) => {
+                        *state = next;
+                        executed
+                    }
+                    Err(_) => {
+                        // The thread cannot be stopped, but it only has its own copy of the state,
+                        // which is dropped if it ever finishes.
+                        let quarantined = QuarantinedBlock {
+                            height,
+                            block,
+                            namespace_path,
+                            phase: timer.phase(),
+                            timings: timer.timings(),
+                            limit_ms: limit.as_millis() as u64,
+                            quarantined_at: SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .map(|time| time.as_secs())
+                                .unwrap_or_default(),
+                        };
+                        self.alerts.raise(
+                            Severity::Critical,
+                            AlertKind::BlockQuarantined,
+                            Some(height),
+                            format!(
+                                "block {height} did not execute within {limit:?} and was \
+                                quarantined in phase {:?} ({}); executing stops at it",
+                                quarantined.phase, quarantined.timings
+                            ),
+                        );
+                        self.status.write().await.quarantine(quarantined);
+                        return Err(ExecutorError::BlockQuarantined {
+                            height,
+                            limit_ms: limit.as_millis() as u64,
+                        });
+                    }
+                }
+            }
+        };
+
+        let timings = timer.timings();
+        if self
+            .options
+            .slow_block
+            .is_some_and(|slow| timings.total_ms() > slow.as_millis() as u64)
+        {
+            tracing::warn!(
+                target: EXECUTOR,
+                height,
+                total_ms = timings.total_ms(),
+                decode_ms = timings.decode_ms,
+                apply_ms = timings.apply_ms,
+                commit_ms = timings.commit_ms,
+                prove_ms = timings.prove_ms,
+                "block {height} was slow to execute: {timings}"
+            );
+        }
+        Ok((proof, result))
+    }
+}
+
+/// Runs the executor, restarting it after panics and retryable errors.
+///
+/// Each restart resumes from the first block not yet executed in `state`. Restarts back off
+/// exponentially. The backoff resets once a run makes progress. Restarts and the reason for the
+/// last one are reported in `status`.
+///
+/// Some errors mean the executor cannot safely continue, such as a block which does not match its
+/// commitment on L1. These raise an alert and are returned.
+///
+/// A quarantined block is not retried. The executor waits for the operator to look at it, and the
+/// API keeps serving the state before it.
+///
+/// Returns `Ok` once the executor has shut down through `opt.shutdown`.
+pub async fn run_executor_supervised(
+    opt: &ExecutorOptions,
+    state: Arc<RwLock<State>>,
+    status: StatusHandle,
+) -> Result<(), ExecutorError> {
+    let mut backoff = MIN_RESTART_BACKOFF;
+    loop {
+        let height_before = state.read().await.block_height();
+        let res = AssertUnwindSafe(run_executor(opt, state.clone(), status.clone()))
+            .catch_unwind()
+            .await;
+        let reason = match res {
+            Ok(Ok(()))
+                if opt
+                    .shutdown
+                    .as_ref()
+                    .is_some_and(|shutdown| shutdown.is_closed()) =>
+            {
+                return Ok(());
+            }
+            Ok(Ok(())) => "L1 event stream ended".to_string(),
+            Ok(Err(err)) if err.is_retryable() => err.to_string(),
+            Ok(Err(err @ ExecutorError::BlockQuarantined { .. })) => {
+                tracing::error!(target: EXECUTOR, "{err}");
+                status.write().await.last_failure = Some(err.to_string());
+                match &opt.shutdown {
+                    Some(shutdown) => {
+                        shutdown.recv().await.ok();
+                    }
+                    None => future::pending().await,
+                }
+                return Ok(());
+            }
+            Ok(Err(err)) => {
+                executor_alerts(opt, webhook_sender(opt).as_ref()).raise(
+                    Severity::Critical,
+                    AlertKind::ExecutorHalted,
+                    None,
+                    format!("executor failed and cannot be restarted: {err}"),
+                );
+                return Err(err);
+            }
+            Err(panic) => format!("executor panicked: {}", panic_message(&*panic)),
+        };
+
+        if state.read().await.block_height() > height_before {
+            backoff = MIN_RESTART_BACKOFF;
+        }
+        tracing::warn!(target: EXECUTOR, "restarting executor in {backoff:?}: {reason}");
+        {
+            let mut status = status.write().await;
+            status.restarts += 1;
+            status.last_failure = Some(reason);
+        }
+        sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+    }
+}
+
+/// The message a task panicked with.
+pub(crate) fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "unknown panic".into())
+}
+
+/// Send a proof transaction, replacing it with higher fees if it is not mined in time.
+///
+/// Transactions are filled in and signed by `client`, which is also used for all reads, and
+/// broadcast through `submitter`. Each replacement reuses the nonce of the original, so at most one
+/// of them can be mined. Every transaction sent is recorded in `status` as the pending proof of the
+/// `num_blocks` blocks starting at `first_block`. Returns the receipt of whichever transaction was
+/// mined.
+async fn send_with_fee_bumps<M: Middleware>(
+    client: &M,
+    submitter: &dyn SubmitTx,
+    mut tx: TypedTransaction,
+    opt: &FeeBumpOptions,
+    status: &StatusHandle,
+    first_block: u64,
+    num_blocks: u64,
+) -> Result<TransactionReceipt, ExecutorError> {
+    // Filling in the gas estimates the transaction, so a proof the contract would reject for being
+    // at the wrong height fails here, before anything is sent.
+    client
+        .fill_transaction(&mut tx, None)
+        .await
+        .map_err(|err| match wrong_height(&err) {
+            Some(rejected) => ExecutorError::ProofAtWrongHeight {
+                height: rejected.height.as_u64(),
+                verified: rejected.num_verified_blocks.as_u64(),
+            },
+            None => l1_tx_error(err),
+        })?;
+    if tx.chain_id().is_none() {
+        tx.set_chain_id(client.get_chainid().await.map_err(l1_tx_error)?.as_u64());
+    }
+    let from = *tx.from().ok_or_else(|| ExecutorError::L1 {
+        reason: "L1 client did not set the sender of the proof transaction".into(),
+    })?;
+
+    let mut sent: Vec<H256> = vec![];
+    let mut bumps = 0;
+    loop {
+        let signature = client
+            .sign_transaction(&tx, from)
+            .await
+            .map_err(l1_tx_error)?;
+        match submitter.submit_raw(tx.rlp_signed(&signature)).await {
+            Ok(l1_tx) => {
+                sent.push(l1_tx);
+                status.write().await.set_verification(
+                    first_block,
+                    num_blocks,
+                    BlockVerification::Submitted { l1_tx },
+                );
+            }
+            // If an earlier transaction was mined in the mean time, the replacement is rejected
+            // for reusing its nonce. Keep waiting on the transactions we already sent.
+            Err(err) if !sent.is_empty() => {
+                tracing::warn!(
+                    target: EXECUTOR,
+                    "Failed to send replacement proof transaction: {err}"
+                );
+            }
+            Err(err) => return Err(err),
+        }
+
+        let deadline =
+            client.get_block_number().await.map_err(l1_tx_error)? + opt.confirmation_blocks;
+        loop {
+            for l1_tx in &sent {
+                if let Some(receipt) = client
+                    .get_transaction_receipt(*l1_tx)
+                    .await
+                    .map_err(l1_tx_error)?
+                {
+                    return Ok(receipt);
+                }
+            }
+            if client.get_block_number().await.map_err(l1_tx_error)? >= deadline {
+                break;
+            }
+            sleep(client.provider().get_interval()).await;
+        }
+
+        let l1_tx = *sent.last().unwrap();
+        if bumps == opt.max_bumps {
+            return Err(ExecutorError::ProofTransactionStuck { l1_tx, bumps });
+        }
+        bumps += 1;
+        bump_fees(&mut tx, opt.bump_percent);
+        tracing::warn!(
+            target: EXECUTOR,
+            "proof transaction {l1_tx:?} not mined after {} L1 blocks, replacing it with fees raised by {}% ({bumps}/{})",
+            opt.confirmation_blocks,
+            opt.bump_percent,
+            opt.max_bumps,
+        );
+    }
+}
+
+/// Classify an error from the L1 about a transaction we sent, picking out a sender which cannot
+/// pay for it, since retrying will not help until it is funded.
+fn l1_tx_error(err: impl MiddlewareError) -> ExecutorError {
+    match err.as_error_response() {
+        Some(response) if response.message.contains("insufficient funds") => {
+            ExecutorError::InsufficientFunds {
+                reason: response.message.clone(),
+            }
+        }
+        _ => ExecutorError::L1 {
+            reason: err.to_string(),
+        },
+    }
+}
+
+/// The `WrongHeight` revert of the rollup contract, if that is what `err` is.
+fn wrong_height(err: &impl MiddlewareError) -> Option<example_rollup::WrongHeight> {
+    let data = err.as_error_response()?.as_revert_data()?;
+    example_rollup::WrongHeight::decode_with_selector(&data)
+}
+
+/// Raise the fees of `tx` by `percent`, rounding up so that small fees still increase.
+fn bump_fees(tx: &mut TypedTransaction, percent: u64) {
+    let bump = |fee: U256| fee + (fee * percent + 99) / 100;
+    match tx {
+        TypedTransaction::Legacy(tx) => tx.gas_price = tx.gas_price.map(bump),
+        TypedTransaction::Eip2930(tx) => tx.tx.gas_price = tx.tx.gas_price.map(bump),
+        TypedTransaction::Eip1559(tx) => {
+            tx.max_fee_per_gas = tx.max_fee_per_gas.map(bump);
+            tx.max_priority_fee_per_gas = tx.max_priority_fee_per_gas.map(bump);
+        }
+    }
+}
+
+/// Query the HotShot contract for the `NewBlocks` events not yet in `seen`, from the backfill
+/// window before the last event processed through L1 block `through`, or the latest L1 block.
+///
+/// Only events announcing blocks at or above `executed` are returned, in the order they were
+/// emitted.
+async fn backfill_new_blocks<M: Middleware>(
+    opt: &ExecutorOptions,
+    hotshot_contract: &HotShot<M>,
+    seen: &SeenEvents,
+    executed: u64,
+    through: Option<u64>,
+) -> Result<VecDeque<(NewBlocksFilter, LogMeta)>, ExecutorError> {
+    let through = match through {
+        Some(block) => block,
+        None => hotshot_contract
+            .client()
+            .get_block_number()
+            .await
+            .map_err(|err| ExecutorError::L1 {
+                reason: err.to_string(),
+            })?
+            .as_u64(),
+    };
+    let from = seen.window_start(&opt.event_backfill);
+    if from > through {
+        return Ok(Default::default());
+    }
+    let mut events = fetch_in_batches(
+        from..=through,
+        opt.event_backfill.batch_blocks,
+        |blocks| async move {
+            hotshot_contract
+                .new_blocks_filter()
+                .from_block(*blocks.start())
+                .to_block(*blocks.end())
+                .address(hotshot_contract.address().into())
+                .query_with_meta()
+                .await
+                .map_err(|err| ExecutorError::L1 {
+                    reason: format!(
+                        "unable to fetch HotShot blocks from L1 blocks {blocks:?}: {err}"
+                    ),
+                })
+        },
+    )
+    .await?;
+    events.retain(|(event, meta)| {
+        let end_block = event.first_block_number.as_u64() + event.num_blocks.as_u64();
+        end_block > executed && !seen.contains(&event_id(meta))
+    });
+    events.sort_by_key(|(_, meta)| (meta.block_number, meta.log_index));
+    Ok(events.into())
+}
+
+/// The L1 block, transaction hash and log index of an event.
+fn event_id(meta: &LogMeta) -> EventId {
+    (
+        meta.block_number.as_u64(),
+        meta.transaction_hash,
+        meta.log_index,
+    )
+}
+
+/// Check that `header` matches the commitment HotShot posted to L1 for block `height`.
+///
+/// A mismatch may come from reading a value that was not final yet, so both sides are fetched
+/// again before concluding. If they still disagree, the evidence is written to
+/// `opt.diagnostics_dir`, an alert is raised, and the executor must stop.
+async fn check_header_commitment<M: Middleware>(
+    opt: &ExecutorOptions,
+    alerts: &Alerts,
+    hotshot: &QueryReplicas,
+    hotshot_contract: &HotShot<M>,
+    height: u64,
+    header: Header,
+) -> Result<Header, ExecutorError> {
+    let read_commitment = || async {
+        hotshot_contract
+            .commitments(height.into())
+            .call()
+            .await
+            .map_err(|err| ExecutorError::L1 {
+                reason: err.to_string(),
+            })
+    };
+
+    let contract_commitment = read_commitment().await?;
+    if matches_block_hash(height, &header, contract_commitment)? {
+        return Ok(header);
+    }
+    tracing::warn!(
+        target: EXECUTOR,
+        "block {height} does not match its commitment on L1, fetching both again"
+    );
+    let header: Header = hotshot
+        .get(&format!("header/{height}"))
+        .await
+        .map_err(|err| ExecutorError::QueryService {
+            reason: err.to_string(),
+        })?;
+    let contract_commitment = read_commitment().await?;
+    if matches_block_hash(height, &header, contract_commitment)? {
+        return Ok(header);
+    }
+
+    let report = MismatchReport {
+        height,
+        contract_commitment,
+        header_commitment: header.commit().to_string(),
+        header,
+        sequencer_url: hotshot.active_url(),
+        l1_url: opt.l1_http_provider.clone(),
+    };
+    let bundle = match report.write_bundle(&opt.diagnostics_dir) {
+        Ok(path) => Some(path),
+        Err(err) => {
+            tracing::error!(
+                target: EXECUTOR,
+                "Unable to write diagnostic bundle, evidence follows: {report:?}: {err}"
+            );
+            None
+        }
+    };
+    let evidence = match &bundle {
+        Some(path) => format!(", evidence is in {}", path.display()),
+        None => String::new(),
+    };
+    alerts.raise(
+        Severity::Critical,
+        AlertKind::CommitmentMismatch,
+        Some(height),
+        format!(
+            "block {height} has commitment {}, but HotShot committed to {contract_commitment} on \
+            L1{evidence}",
+            report.header_commitment
+        ),
+    );
+    Err(ExecutorError::CommitmentMismatch { height, bundle })
+}
+
+/// Record the L1 transactions which verified blocks before this executor started, in the status
+/// and in the summaries of the blocks the state still keeps, such as after restoring a snapshot
+/// which predates them.
+///
+/// Each `StateUpdate` event covers the blocks between the previous verified height and the new
+/// one, and the log metadata tells us which L1 transaction and block it was emitted in. An update
+/// which does not raise the verified height covers no blocks, and is skipped with a warning.
+async fn backfill_verifications<M: Middleware>(
+    rollup_contract: &ExampleRollup<M>,
+    state: &RwLock<State>,
+    status: &StatusHandle,
+) {
+    let updates = match rollup_contract
+        .state_update_filter()
+        .from_block(0)
+        .address(rollup_contract.address().into())
+        .query_with_meta()
+        .await
+    {
+        Ok(updates) => updates,
+        Err(err) => {
+            tracing::warn!(
+                target: EXECUTOR,
+                "Unable to fetch past state updates, verification status of earlier blocks will be \
+                unavailable: {err}"
+            );
+            return;
+        }
+    };
+    let mut state = state.write().await;
+    let mut status = status.write().await;
+    let mut first_block = 0;
+    for (update, meta) in updates {
+        let block_height = update.block_height.as_u64();
+        // Verification only moves forward, so an update which does not is not one we can attribute
+        // blocks to.
+        let Some(num_blocks) = block_height.checked_sub(first_block).filter(|n| *n > 0) else {
+            tracing::warn!(
+                target: EXECUTOR,
+                "skipping state update to height {block_height} in L1 transaction {:?}, which does \
+                not follow the update to height {first_block}",
+                meta.transaction_hash
+            );
+            continue;
+        };
+        let proof = ProofTransaction {
+            l1_tx: meta.transaction_hash,
+            l1_block: meta.block_number.as_u64(),
+            first_block,
+            num_blocks,
+        };
+        status.set_verification(
+            first_block,
+            proof.num_blocks,
+            BlockVerification::Verified {
+                l1_tx: proof.l1_tx,
+                l1_block: proof.l1_block,
+            },
+        );
+        state.record_proof_transaction(proof);
+        first_block = block_height;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::alert::{Alert, AlertBuffer};
+    use crate::api::{serve, APIOptions};
+    use crate::audit::{audit, AuditOptions};
+    use crate::backfill::{run_backfill, BackfillOptions};
+    use crate::deposit::{CreditedDeposit, DepositWatch};
+    use crate::error::{ApiError, RollupError};
+    use crate::invariants::Violation;
+    use crate::message::{outbox_commitment, Message, OutboundMessage};
+    use crate::prover::EncodedProof;
+    use crate::snapshot::{list_snapshots, load_latest_snapshot, load_snapshot, write_snapshot};
+    use crate::state::{Amount, BlockCounts, ChainConfig, Nonce};
+    use crate::status::BlockFinality;
+    use crate::testing::fixtures::FixtureWallet;
+    use crate::testing::{
+        commit_blocks, mock_chain, start_query_service, MockQueryService, MOCK_CHAIN_LEN,
+        MOCK_VM_ID,
+    };
+    use crate::transaction::{SignedTransaction, Transaction};
+    use crate::utils::{
+        create_provider, deploy_example_contract, spawn_anvil, wallet_address,
+        ExampleRollupContract, TestAnvil,
+    };
+    use crate::webhook::Notification;
+    use crate::{verify_block_proof, verify_encoded_block_proof, RollupVM};
+
+    use super::*;
+    use async_compatibility_layer::{
+        async_primitives::broadcast,
+        logging::{setup_backtrace, setup_logging},
+    };
+    use async_std::task::spawn;
+    use contract_bindings::example_rollup::StateUpdateFilter;
+    use derivative::Derivative;
+    use ethers::prelude::k256::ecdsa::SigningKey;
+    use ethers::providers::{Middleware, Provider};
+    use ethers::signers::{LocalWallet, Signer};
+    use futures::{future::join_all, stream, FutureExt, Stream};
+    use portpicker::pick_unused_port;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use sequencer::{
+        hotshot_commitment::{run_hotshot_commitment_task, CommitmentTaskOptions},
+        testing::{init_hotshot_handles, wait_for_decide_on_handle},
+        Vm, VmId,
+    };
+    use sequencer_utils::test_utils::TestL1System;
+    use std::time::Duration;
+    use surf_disco::{Client, Url};
+    use tempfile::TempDir;
+    use tide_disco::error::ServerError;
+
+    #[derive(Clone, Derivative)]
+    #[derivative(Debug)]
+    struct TestRollupInstance {
+        contract: ExampleRollupContract,
+        vm: RollupVM,
+        socket_provider: Provider<Ws>,
+        l1_url: Url,
+        alice: Wallet<SigningKey>,
+        state: Arc<RwLock<State>>,
+        bob: Wallet<SigningKey>,
+        #[derivative(Debug = "ignore")]
+        executor_send: BroadcastSender<(u64, State)>,
+    }
+
+    impl TestRollupInstance {
+        pub async fn launch(
+            l1_url: Url,
+            vm_id: VmId,
+            alice: Wallet<SigningKey>,
+            bob: Wallet<SigningKey>,
+            test_l1: &TestL1System,
+        ) -> Self {
+            Self::launch_with_chain_config(l1_url, vm_id, alice, bob, test_l1, Default::default())
+                .await
+        }
+
+        pub async fn launch_with_chain_config(
+            l1_url: Url,
+            vm_id: VmId,
+            alice: Wallet<SigningKey>,
+            bob: Wallet<SigningKey>,
+            test_l1: &TestL1System,
+            chain_config: ChainConfig,
+        ) -> Self {
+            Self::launch_with(l1_url, vm_id, alice, bob, test_l1, chain_config, 0).await
+        }
+
+        /// Launch a rollup whose contract runs in optimistic mode, with a challenge window of
+        /// `challenge_window` seconds.
+        pub async fn launch_optimistic(
+            l1_url: Url,
+            vm_id: VmId,
+            alice: Wallet<SigningKey>,
+            bob: Wallet<SigningKey>,
+            test_l1: &TestL1System,
+            challenge_window: u64,
+        ) -> Self {
+            Self::launch_with(
+                l1_url,
+                vm_id,
+                alice,
+                bob,
+                test_l1,
+                Default::default(),
+                challenge_window,
+            )
+            .await
+        }
+
+        async fn launch_with(
+            l1_url: Url,
+            vm_id: VmId,
+            alice: Wallet<SigningKey>,
+            bob: Wallet<SigningKey>,
+            test_l1: &TestL1System,
+            chain_config: ChainConfig,
+            challenge_window: u64,
+        ) -> Self {
+            // Create mock rollup state
+            let vm = RollupVM::new(vm_id);
+            let state = State::from_initial_balances([(alice.address(), 9999)], vm)
+                .with_chain_config(chain_config);
+            let initial_state = state.commit();
+            let state = Arc::new(RwLock::new(state));
+            tracing::info!(
+                "rollup {vm_id:?} initial state: {initial_state} ({})",
+                commitment_to_u256(initial_state)
+            );
+            let mut ws_url = l1_url.clone();
+            ws_url.set_scheme("ws").unwrap();
+            let socket_provider = Provider::<Ws>::connect(ws_url).await.unwrap();
+            // Any account may submit proofs until a test rotates in an authorized submitter.
+            let rollup_contract =
+                deploy_example_contract(test_l1, initial_state, challenge_window, Address::zero())
+                    .await;
+            let (executor_send, _) = broadcast::channel();
+
+            Self {
+                contract: rollup_contract,
+                vm,
+                socket_provider,
+                alice,
+                l1_url,
+                bob,
+                state,
+                executor_send,
+            }
+        }
+
+        pub async fn subscribe_contract(
+            &self,
+        ) -> impl '_ + Stream<Item = (StateUpdateFilter, LogMeta)> {
+            let filter = self
+                .contract
+                .state_update_filter()
+                .filter
+                // Ethers does not set the contract address on filters created via contract
+                // bindings. This seems like a bug and I have reported it:
+                // https://github.com/gakonst/ethers-rs/issues/2528. In the mean time we can work
+                // around by setting the address manually.
+                .address(self.contract.address());
+            self.socket_provider
+                .subscribe_logs(&filter)
+                .await
+                .unwrap()
+                .map(|log| {
+                    let meta = LogMeta::from(&log);
+                    (parse_log(log).unwrap(), meta)
+                })
+        }
+
+        pub async fn subscribe_executor(&self) -> impl Stream<Item = (u64, State)> {
+            let recv = self.executor_send.handle_async().await;
+            stream::unfold(recv, |mut recv| async move {
+                Some((recv.recv_async().await.unwrap(), recv))
+            })
+            .boxed()
+        }
+
+        /// Wait until some effect has happened, causing the rollup state to satisfy `predicate`.
+        ///
+        /// At each intermediate state, including the terminal state, this function checks that the
+        /// state reported by the executor matches the state in the contract. This implies that, if
+        /// this function returns, `predicate` holds on both the executor state and some state that
+        /// has been verified by the smart contract.
+        pub async fn wait_for_effect(&self, predicate: impl Fn(State) -> bool) {
+            let vm_id: VmId = self.vm.into();
+            let mut exec_stream = self.subscribe_executor().await;
+            let mut l1_stream = self.subscribe_contract().await;
+            loop {
+                // Get the next event from the contract.
+                let (event, log) = l1_stream.next().await.unwrap();
+                tracing::info!("rollup {vm_id:?} got contract event {event:?} {log:?}");
+
+                // Advance the executor stream to the corresponding state.
+                let state = loop {
+                    let (block_index, state) = exec_stream.next().await.unwrap();
+                    tracing::info!(
+                        "rollup {vm_id:?} executor commitment after block {block_index} is {}",
+                        commitment_to_u256(state.commit())
+                    );
+                    if block_index + 1 == event.block_height.as_u64() {
+                        break state;
+                    }
+                };
+
+                // Ensure the executor's state commitment matches the contract.
+                let contract_comm = self
+                    .contract
+                    .state_commitment()
+                    .block(log.block_number)
+                    .call()
+                    .await
+                    .unwrap();
+                tracing::info!(
+                    "rollup {vm_id:?} contract commitment at block {} is {contract_comm}",
+                    log.block_number
+                );
+                assert_eq!(commitment_to_u256(state.commit()), contract_comm);
+
+                // If the predicate is satisfied, finish up.
+                if predicate(state) {
+                    break;
+                }
+            }
+        }
+
+        pub async fn test_transaction(
+            &self,
+            amount: Amount,
+            nonce: Nonce,
+        ) -> sequencer::Transaction {
+            let txn = Transaction {
+                amount,
+                destination: self.bob.address(),
+                nonce,
+                priority: 0,
+                vm_id: self.vm.id().into(),
+                asset: None,
+            };
+            let txn = SignedTransaction::new_with_wallet(txn, &self.alice).await;
+            self.vm.wrap(&txn.into())
+        }
+    }
+
+    /// Deploy the mock ERC-20 token of the contract tests, built by `forge build`.
+    async fn deploy_test_token(test_l1: &TestL1System) -> Contract<sequencer_utils::Signer> {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/contracts/out/ExampleToken.sol/ExampleToken.json"
+        );
+        let artifact: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(path).expect("Run `forge build` to build the test token"),
+        )
+        .unwrap();
+        let abi: Abi = serde_json::from_value(artifact["abi"].clone()).unwrap();
+        let bytecode: Bytes =
+            serde_json::from_value(artifact["bytecode"]["object"].clone()).unwrap();
+        ContractFactory::new(abi, bytecode, test_l1.clients.deployer.provider.clone())
+            .deploy(())
+            .unwrap()
+            .send()
+            .await
+            .unwrap()
+    }
+
+    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+    #[async_std::test]
+    async fn test_execute() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = FixtureWallet::Alice.wallet();
+        let bob = FixtureWallet::Bob.wallet();
+
+        // Deploy hotshot contract
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Start a test Rollup instance
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 10.into(), alice, bob, &test_l1).await;
+
+        // Start a test HotShot configuration
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        // Submit transaction to sequencer
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        let txn = test_rollup.test_transaction(100, 1).await;
+        client.connect(None).await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        // Spawn hotshot commitment and executor tasks
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+
+        let history = L1UpdateHistory::default();
+        let status = StatusHandle::default();
+        let rollup_opt = ExecutorOptions {
+            l1_updates: history.clone(),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        {
+            let status = status.clone();
+            spawn(async move { run_executor(&rollup_opt, state_lock, status).await });
+        }
+
+        // Wait for the rollup contract to process all state updates
+        test_rollup
+            .wait_for_effect(|state| {
+                let bob_balance = state.get_balance(&test_rollup.bob.address());
+                tracing::info!("Bob's balance is {bob_balance}/100");
+                bob_balance == 100
+            })
+            .await;
+
+        // Every state update the contract accepted is recorded, as sent by us, and together the
+        // updates cover every block proven.
+        let contract = &test_rollup.contract;
+        let verified = loop {
+            let verified = contract
+                .num_verified_blocks()
+                .call()
+                .await
+                .unwrap()
+                .as_u64();
+            let recorded = history.last().await.map(|update| update.block_height);
+            if verified > 0 && recorded == Some(verified) {
+                break verified;
+            }
+            sleep(Duration::from_millis(500)).await;
+        };
+        let updates = history.page(0, usize::MAX).await;
+        let events = contract
+            .state_update_filter()
+            .from_block(0u64)
+            .query()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|event| event.block_height.as_u64() <= verified)
+            .collect::<Vec<_>>();
+        let updates = updates
+            .into_iter()
+            .filter(|update| update.block_height <= verified)
+            .collect::<Vec<_>>();
+        assert_eq!(updates.len(), events.len());
+        assert_eq!(updates.last().unwrap().block_height, verified);
+        for (update, event) in updates.iter().zip(&events) {
+            assert_eq!(update.block_height, event.block_height.as_u64());
+            assert_eq!(update.state_commitment, event.state_commitment);
+            assert_eq!(update.sent_by_us, Some(true));
+        }
+        {
+            let state = test_rollup.state.read().await;
+            for update in &updates {
+                let summary = state.block_summary(update.block_height - 1).unwrap();
+                assert_eq!(
+                    commitment_to_u256(summary.state_commitment),
+                    update.state_commitment
+                );
+            }
+        }
+        let stats = status.read().await.l1_updates.clone();
+        assert!(stats.recorded >= updates.len() as u64);
+        assert_eq!(stats.unexpected, 0);
+
+        // Every executed block has a summary, and block times never go backwards.
+        let state = test_rollup.state.read().await;
+        let summaries = (0..state.block_height())
+            .map(|height| state.block_summary(height).unwrap().clone())
+            .collect::<Vec<_>>();
+        for (height, summary) in summaries.iter().enumerate() {
+            assert_eq!(summary.height, height as u64);
+        }
+        for pair in summaries.windows(2) {
+            assert!(
+                pair[0].timestamp.seconds <= pair[1].timestamp.seconds,
+                "{pair:?}"
+            );
+        }
+        assert_eq!(summaries.last().unwrap().state_commitment, state.commit());
+    }
+
+    #[async_std::test]
+    async fn test_execute_with_commitment_task() {
+        use crate::commitment_task::{commitment_task_options, run_commitment_task_supervised};
+        use crate::Options;
+        use clap::Parser;
+
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = FixtureWallet::Alice.wallet();
+        let bob = FixtureWallet::Bob.wallet();
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 11.into(), alice, bob, &test_l1).await;
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, nodes[0].clone()).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        let txn = test_rollup.test_transaction(100, 1).await;
+        client.connect(None).await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        // The node runs the commitment task itself, configured from its own options, and nothing
+        // else commits HotShot blocks.
+        let opt = Options::try_parse_from([
+            "example-l2".to_string(),
+            "--with-commitment-task".into(),
+            "--commitment-account-index".into(),
+            test_l1.clients.funded[0].index.to_string(),
+            "--rollup-account-index".into(),
+            test_l1.clients.funded[1].index.to_string(),
+            "--rollup-mnemonic".into(),
+            TEST_MNEMONIC.into(),
+            "--l1-http-provider".into(),
+            anvil.url().to_string(),
+            "--hotshot-address".into(),
+            format!("{:?}", test_l1.hotshot.address()),
+            "--sequencer-url".into(),
+            sequencer_url.to_string(),
+        ])
+        .unwrap();
+        crate::mode::check_mode(&opt).unwrap();
+        let task_opt = commitment_task_options(&opt);
+
+        let (stop, shutdown) = channel::bounded(1);
+        let status = StatusHandle::default();
+        let rollup_opt = ExecutorOptions {
+            sequencer_urls: vec![sequencer_url],
+            rollup_account_index: opt.rollup_account_index,
+            l1_http_provider: anvil.url(),
+            l1_ws_provider: anvil.ws_url(),
+            l1_chain_id: None,
+            rollup_mnemonic: opt.rollup_mnemonic.clone(),
+            hotshot_address: opt.hotshot_address,
+            rollup_address: test_rollup.contract.address(),
+            output_stream: Some(test_rollup.executor_send.clone()),
+            block_hooks: Default::default(),
+            webhooks: vec![],
+            alerts: Default::default(),
+            fee_bump: Default::default(),
+            balance_monitor: Default::default(),
+            lag_alerts: Default::default(),
+            watchdog: Default::default(),
+            invariant_interval: 1,
+            event_backfill: Default::default(),
+            retention: Default::default(),
+            submission_url: None,
+            diagnostics_dir: std::env::temp_dir(),
+            cursor_path: None,
+            snapshots: None,
+            start: None,
+            check_commitments: true,
+            verify_chain: true,
+            submitter_lease_blocks: None,
+            read_only: false,
+            l1_updates: Default::default(),
+            proof_interval: 1,
+            proof_queue_depth: 16,
+            drain_timeout: Duration::from_secs(60),
+            shutdown: Some(shutdown.clone()),
+            hooks: Default::default(),
+        };
+        let task = {
+            let status = status.clone();
+            let shutdown = Some(shutdown);
+            spawn(async move { run_commitment_task_supervised(&task_opt, status, shutdown).await })
+        };
+        let executor = {
+            let state = test_rollup.state.clone();
+            let status = status.clone();
+            spawn(async move { run_executor_supervised(&rollup_opt, state, status).await })
+        };
+
+        // The transfer is executed and proven, and the commitments it was sequenced in are
+        // reported.
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&test_rollup.bob.address()) == 100)
+            .await;
+        while test_rollup
+            .contract
+            .num_verified_blocks()
+            .call()
+            .await
+            .unwrap()
+            .is_zero()
+        {
+            sleep(Duration::from_millis(500)).await;
+        }
+        loop {
+            let task = status.read().await.commitment_task.clone().unwrap();
+            if task.committed_height > 0 {
+                assert!(task.last_commitment_at.is_some(), "{task:?}");
+                assert_eq!(task.restarts, 0, "{task:?}");
+                break;
+            }
+            sleep(Duration::from_millis(500)).await;
+        }
+
+        // Both stop on shutdown.
+        stop.close();
+        task.await;
+        executor.await.unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_read_only_replica() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider.clone()).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 10.into(), alice, bob, &test_l1).await;
+        let replica_state = Arc::new(RwLock::new(test_rollup.state.read().await.clone()));
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let tmp_dir = TempDir::new().unwrap();
+        start_query_service(
+            sequencer_port,
+            tmp_dir.path().join("tmp_storage"),
+            nodes[0].clone(),
+        )
+        .await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+
+        // The operator proves blocks as usual. The replica runs alongside it with an L1 account of
+        // its own, which the rollup contract does not accept proofs from.
+        let operator_opt = ExecutorOptions {
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url.clone())
+        };
+        let replica_index = test_l1.clients.funded[1].index + 1;
+        let replica_opt = ExecutorOptions {
+            rollup_account_index: replica_index,
+            output_stream: None,
+            read_only: true,
+            l1_updates: Default::default(),
+            ..operator_opt.clone()
+        };
+        let replica_status = StatusHandle::default();
+        {
+            let state = test_rollup.state.clone();
+            spawn(async move { run_executor(&operator_opt, state, Default::default()).await });
+            let state = replica_state.clone();
+            let status = replica_status.clone();
+            spawn(async move { run_executor(&replica_opt, state, status).await });
+        }
+
+        // Submit some transfers and wait for the operator to prove them.
+        let client: Client<ServerError> = Client::new(sequencer_url);
+        client.connect(None).await;
+        for nonce in 1..=3 {
+            let txn = test_rollup.test_transaction(10, nonce).await;
+            client
+                .post::<()>("submit/submit")
+                .body_json(&txn)
+                .unwrap()
+                .send()
+                .await
+                .unwrap();
+        }
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&test_rollup.bob.address()) == 30)
+            .await;
+
+        // The replica checks every state update the operator proved against its own state, and
+        // finds they agree.
+        let verified = test_rollup
+            .contract
+            .num_verified_blocks()
+            .call()
+            .await
+            .unwrap()
+            .as_u64();
+        let agreement = loop {
+            let agreement = replica_status
+                .read()
+                .await
+                .contract_agreement
+                .clone()
+                .unwrap();
+            if agreement.checked_height >= verified {
+                break agreement;
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+        assert_eq!(agreement.mismatch, None);
+        assert!(agreement.matched_updates > 0);
+        assert!(matches!(
+            replica_status.read().await.verification(verified - 1),
+            Some(BlockVerification::Verified { .. })
+        ));
+
+        // Both nodes computed the same commitments and balances.
+        let (operator, replica) = loop {
+            let operator = test_rollup.state.read().await.clone();
+            let replica = replica_state.read().await.clone();
+            if operator.block_height() == replica.block_height() {
+                break (operator, replica);
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+        assert_eq!(operator.commit(), replica.commit());
+        assert_eq!(
+            operator
+                .block_summary(verified - 1)
+                .unwrap()
+                .state_commitment,
+            replica
+                .block_summary(verified - 1)
+                .unwrap()
+                .state_commitment
+        );
+        for address in [test_rollup.alice.address(), test_rollup.bob.address()] {
+            assert_eq!(
+                operator.get_balance(&address),
+                replica.get_balance(&address)
+            );
+        }
+
+        // The replica never sent anything to L1.
+        let replica_account = wallet_address(TEST_MNEMONIC, replica_index);
+        assert_eq!(
+            provider
+                .get_transaction_count(replica_account, None)
+                .await
+                .unwrap(),
+            U256::zero()
+        );
+    }
+
+    #[async_std::test]
+    async fn test_start_point() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider.clone()).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 10.into(), alice, bob, &test_l1).await;
+        let genesis = test_rollup.state.read().await.clone();
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let tmp_dir = TempDir::new().unwrap();
+        start_query_service(
+            sequencer_port,
+            tmp_dir.path().join("tmp_storage"),
+            nodes[0].clone(),
+        )
+        .await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+
+        // The primary executor writes snapshots, and records the state updates it sends.
+        let snapshots = SnapshotOptions {
+            dir: tmp_dir.path().join("snapshots"),
+            interval: 5,
+        };
+        let l1_updates = L1UpdateHistory::default();
+        let primary_opt = ExecutorOptions {
+            snapshots: Some(snapshots.clone()),
+            l1_updates: l1_updates.clone(),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url.clone())
+        };
+        {
+            let opt = primary_opt.clone();
+            let state = test_rollup.state.clone();
+            spawn(async move { run_executor(&opt, state, Default::default()).await });
+        }
+
+        let client: Client<ServerError> = Client::new(sequencer_url);
+        client.connect(None).await;
+        for nonce in 1..=3 {
+            let txn = test_rollup.test_transaction(10, nonce).await;
+            client
+                .post::<()>("submit/submit")
+                .body_json(&txn)
+                .unwrap()
+                .send()
+                .await
+                .unwrap();
+        }
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&test_rollup.bob.address()) == 30)
+            .await;
+
+        // Take the newest snapshot, mid-chain, once the contract has verified it.
+        let (height, path) = list_snapshots(&snapshots.dir)
+            .unwrap()
+            .pop()
+            .expect("the primary wrote a snapshot");
+        let commitment = commitment_to_u256(load_snapshot(&path).unwrap().commit());
+        while l1_updates.last().await.map(|update| update.block_height) < Some(height) {
+            sleep(Duration::from_millis(100)).await;
+        }
+
+        // A second executor refuses a trusted state which does not have the expected commitment,
+        // naming each commitment.
+        let secondary_opt = ExecutorOptions {
+            rollup_account_index: test_l1.clients.funded[1].index + 1,
+            output_stream: None,
+            snapshots: None,
+            read_only: true,
+            start: Some(StartPoint {
+                height,
+                state_snapshot_path: path.clone(),
+                expected_commitment: U256::one(),
+            }),
+            ..primary_opt.clone()
+        };
+        let secondary_state = Arc::new(RwLock::new(genesis.clone()));
+        let err = run_executor(&secondary_opt, secondary_state.clone(), Default::default())
+            .await
+            .unwrap_err();
+        match err {
+            ExecutorError::StartPointMismatch {
+                height: mismatch_height,
+                snapshot,
+                expected,
+                contract,
+            } => {
+                assert_eq!(mismatch_height, height);
+                assert_eq!(snapshot, commitment);
+                assert_eq!(expected, U256::one());
+                assert_eq!(contract, Some(commitment));
+            }
+            err => panic!("expected StartPointMismatch, got {err}"),
+        }
+        assert_eq!(secondary_state.read().await.block_height(), 0);
+
+        // With the right commitment, it starts from the snapshot and catches up with the primary.
+        let secondary_opt = ExecutorOptions {
+            start: Some(StartPoint {
+                height,
+                state_snapshot_path: path,
+                expected_commitment: commitment,
+            }),
+            ..secondary_opt
+        };
+        {
+            let state = secondary_state.clone();
+            spawn(async move { run_executor(&secondary_opt, state, Default::default()).await });
+        }
+        for nonce in 4..=5 {
+            let txn = test_rollup.test_transaction(10, nonce).await;
+            client
+                .post::<()>("submit/submit")
+                .body_json(&txn)
+                .unwrap()
+                .send()
+                .await
+                .unwrap();
+        }
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&test_rollup.bob.address()) == 50)
+            .await;
+        let (primary, secondary) = loop {
+            let primary = test_rollup.state.read().await.clone();
+            let secondary = secondary_state.read().await.clone();
+            if secondary.block_height() >= primary.block_height() {
+                break (primary, secondary);
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+        let converged = secondary
+            .block_summary(primary.block_height() - 1)
+            .unwrap()
+            .state_commitment;
+        assert_eq!(converged, primary.commit());
+        assert_eq!(secondary.get_balance(&test_rollup.bob.address()), 50);
+    }
+
+    #[async_std::test]
+    async fn test_deposit_erc20() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider.clone()).await.unwrap();
+
+        // The token has to exist before the genesis state which lists it as an asset. A second
+        // token, which is not listed, is deposited as well and ignored.
+        let token = deploy_test_token(&test_l1).await;
+        let unlisted = deploy_test_token(&test_l1).await;
+        let test_rollup = TestRollupInstance::launch_with_chain_config(
+            anvil.url().clone(),
+            10.into(),
+            alice,
+            bob.clone(),
+            &test_l1,
+            ChainConfig {
+                assets: [token.address()].into(),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        // Deposit from the deployer, to Bob on the rollup.
+        let depositor = test_l1.clients.deployer.provider.address();
+        for token in [&token, &unlisted] {
+            token
+                .method::<_, ()>("mint", (depositor, U256::from(100)))
+                .unwrap()
+                .send()
+                .await
+                .unwrap()
+                .await
+                .unwrap();
+            token
+                .method::<_, bool>("approve", (test_rollup.contract.address(), U256::from(60)))
+                .unwrap()
+                .send()
+                .await
+                .unwrap()
+                .await
+                .unwrap();
+            test_rollup
+                .contract
+                .deposit_erc20(token.address(), 60.into(), bob.address())
+                .send()
+                .await
+                .unwrap()
+                .await
+                .unwrap();
+        }
+        let held: U256 = token
+            .method("balanceOf", test_rollup.contract.address())
+            .unwrap()
+            .call()
+            .await
+            .unwrap();
+        assert_eq!(held, 60.into());
+        // Deposits are only credited from finalized L1 blocks, which Anvil reports 64 blocks
+        // behind its head.
+        provider
+            .request::<_, ()>("anvil_mine", [U256::from(100)])
+            .await
+            .unwrap();
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let tmp_dir = TempDir::new().unwrap();
+        start_query_service(
+            sequencer_port,
+            tmp_dir.path().join("tmp_storage"),
+            nodes[0].clone(),
+        )
+        .await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+
+        // The deposit is credited, and the state crediting it is verified by the contract.
+        test_rollup
+            .wait_for_effect(|state| state.asset_balance(&token.address(), &bob.address()) == 60)
+            .await;
+        let state = test_rollup.state.read().await;
+        assert_eq!(state.asset_balance(&unlisted.address(), &bob.address()), 0);
+        assert_eq!(state.get_balance(&bob.address()), 0);
+    }
+
+    #[async_std::test]
+    async fn test_forced_inclusion_sequencer_down() {
+        use crate::api::{serve, APIOptions};
+        use crate::client::{ClientError, RollupClient};
+        use crate::submission::SubmitResponse;
+
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil().await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider.clone()).await.unwrap();
+        let test_rollup = TestRollupInstance::launch_with_chain_config(
+            anvil.url().clone(),
+            10.into(),
+            alice,
+            bob.clone(),
+            &test_l1,
+            ChainConfig {
+                forced_inclusion: true,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        // The sequencer which the API forwards to is down: nothing listens on its port.
+        let down_url: Url = format!("http://localhost:{}", pick_unused_port().unwrap())
+            .parse()
+            .unwrap();
+        let api_port = pick_unused_port().unwrap();
+        let api_options = APIOptions {
+            rollup_address: Some(test_rollup.contract.address()),
+            ..APIOptions::new(api_port, down_url)
+        };
+        {
+            let state = test_rollup.state.clone();
+            spawn(async move { serve(&api_options, state, Default::default()).await });
+        }
+        let client = RollupClient::new(format!("http://localhost:{api_port}").parse().unwrap());
+        client.connect(None).await;
+
+        let signed = SignedTransaction::new_with_wallet(
+            Transaction {
+                amount: 100,
+                destination: bob.address(),
+                nonce: 1,
+                priority: 0,
+                vm_id: test_rollup.vm.id().into(),
+                asset: None,
+            },
+            &test_rollup.alice,
+        )
+        .await;
+        // Without the fallback, the submission just fails.
+        let err = client.submit(&signed).await.unwrap_err();
+        assert!(matches!(err, ClientError::Api { .. }), "{err:?}");
+        let SubmitResponse::Fallback { l1_fallback } =
+            client.submit_with_fallback(&signed).await.unwrap()
+        else {
+            panic!("no fallback offered");
+        };
+        assert_eq!(l1_fallback.hash, signed.hash());
+        assert_eq!(l1_fallback.contract, test_rollup.contract.address());
+
+        // Any L1 account may enqueue the transaction, here the deployer.
+        let receipt = test_l1
+            .clients
+            .deployer
+            .provider
+            .send_transaction(
+                TransactionRequest::new()
+                    .to(l1_fallback.contract)
+                    .data(l1_fallback.calldata.clone()),
+                None,
+            )
+            .await
+            .unwrap()
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(receipt.gas_used.unwrap() <= l1_fallback.estimated_gas.into());
+        // Enqueued transactions are only applied from finalized L1 blocks, which Anvil reports 64
+        // blocks behind its head.
+        provider
+            .request::<_, ()>("anvil_mine", [U256::from(100)])
+            .await
+            .unwrap();
+
+        // HotShot keeps running, so the executor keeps executing blocks, with nothing sequenced.
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let tmp_dir = TempDir::new().unwrap();
+        start_query_service(
+            sequencer_port,
+            tmp_dir.path().join("tmp_storage"),
+            nodes[0].clone(),
+        )
+        .await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+
+        // The transaction is applied, by a block which the contract verifies.
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&bob.address()) == 100)
+            .await;
+        let state = test_rollup.state.read().await;
+        let height = state.transaction_block(&signed.hash()).unwrap();
+        let receipts = state.block_receipts(height).unwrap();
+        let receipt = receipts
+            .iter()
+            .find(|receipt| receipt.hash == signed.hash())
+            .unwrap();
+        assert!(receipt.l1_block.is_some());
+        assert!(receipt.applied());
+        assert_eq!(state.block_summary(height).unwrap().payloads.forced, 1);
+    }
+
+    #[async_std::test]
+    async fn test_deposit_reorg() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil().await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider.clone()).await.unwrap();
+        // Ether is the only asset.
+        let assets = [Address::zero()].into();
+        let test_rollup = TestRollupInstance::launch_with_chain_config(
+            anvil.url().clone(),
+            10.into(),
+            alice,
+            bob.clone(),
+            &test_l1,
+            ChainConfig {
+                assets: assets.clone(),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        // Deposit after taking a snapshot of the L1, then revert to the snapshot and build a longer
+        // chain without the deposit, as a reorg would. A deposit removed before it is credited is
+        // dropped; one removed after it was credited is reported.
+        for credit in [false, true] {
+            let mut watch = DepositWatch::default();
+            let snapshot: U256 = provider.request("evm_snapshot", ()).await.unwrap();
+            test_rollup
+                .contract
+                .deposit_eth(bob.address())
+                .value(30)
+                .send()
+                .await
+                .unwrap()
+                .await
+                .unwrap();
+            let deposited = provider.get_block_number().await.unwrap().as_u64();
+            let reorged = watch
+                .update(&test_rollup.contract, 0, deposited)
+                .await
+                .unwrap();
+            assert_eq!(reorged, []);
+            assert_eq!(watch.pending.len(), 1);
+            let deposit = watch.pending[0].clone();
+            let credited_through = if credit {
+                let deposits = L1Deposits {
+                    through: deposited,
+                    deposits: watch.pending.clone(),
+                };
+                watch.credit(3, &deposits, &assets);
+                assert_eq!(watch.pending, []);
+                assert_eq!(watch.credited.len(), 1);
+                deposited
+            } else {
+                0
+            };
+
+            assert!(provider
+                .request::<_, bool>("evm_revert", [snapshot])
+                .await
+                .unwrap());
+            provider
+                .request::<_, ()>("anvil_mine", [U256::from(5)])
+                .await
+                .unwrap();
+            let head = provider.get_block_number().await.unwrap().as_u64();
+            assert!(head > deposited);
+            let reorged = watch
+                .update(&test_rollup.contract, credited_through, head)
+                .await
+                .unwrap();
+            if credit {
+                assert_eq!(reorged, [CreditedDeposit { height: 3, deposit }]);
+            } else {
+                assert_eq!(reorged, []);
+            }
+            assert_eq!(watch, DepositWatch::default());
+        }
+    }
+
+    #[async_std::test]
+    async fn test_optimistic_finality() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider.clone()).await.unwrap();
+        let challenge_window = 3600;
+        let test_rollup = TestRollupInstance::launch_optimistic(
+            anvil.url().clone(),
+            95.into(),
+            alice,
+            bob,
+            &test_l1,
+            challenge_window,
+        )
+        .await;
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let tmp_dir = TempDir::new().unwrap();
+        start_query_service(
+            sequencer_port,
+            tmp_dir.path().join("tmp_storage"),
+            nodes[0].clone(),
+        )
+        .await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        client.connect(None).await;
+        let signed = SignedTransaction::new_with_wallet(
+            Transaction {
+                amount: 100,
+                destination: test_rollup.bob.address(),
+                nonce: 1,
+                priority: 0,
+                vm_id: test_rollup.vm.id().into(),
+                asset: None,
+            },
+            &test_rollup.alice,
+        )
+        .await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&test_rollup.vm.wrap(&signed.clone().into()))
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+        let status = StatusHandle::default();
+        let state_lock = test_rollup.state.clone();
+        let executor_status = status.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        spawn(async move { run_executor(&rollup_opt, state_lock, executor_status).await });
+
+        // The block with the transfer is verified, but stays challengeable, so the transfer is not
+        // confirmed yet.
+        let bob = test_rollup.bob.address();
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&bob) == 100)
+            .await;
+        let block = test_rollup
+            .state
+            .read()
+            .await
+            .transaction_block(&signed.hash())
+            .unwrap();
+        while status.read().await.verified_height <= block {
+            sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(status.read().await.challenge_window, challenge_window);
+        assert_eq!(
+            status.read().await.finality(block),
+            BlockFinality::Challengeable
+        );
+        assert_eq!(status.read().await.confirmed_balance(&bob).balance, 0);
+        assert_eq!(
+            test_rollup
+                .contract
+                .num_finalized_blocks()
+                .call()
+                .await
+                .unwrap(),
+            0.into()
+        );
+
+        // Once the window has passed on L1, the executor finalizes the block.
+        provider
+            .request::<_, serde_json::Value>("evm_increaseTime", [U256::from(challenge_window)])
+            .await
+            .unwrap();
+        provider
+            .request::<_, serde_json::Value>("evm_mine", ())
+            .await
+            .unwrap();
+        let confirmed = loop {
+            let confirmed = status.read().await.confirmed_balance(&bob);
+            if confirmed.balance == 100 {
+                break confirmed;
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+        assert!(block < confirmed.height);
+        assert_eq!(status.read().await.finality(block), BlockFinality::Final);
+        let finalized = test_rollup
+            .contract
+            .num_finalized_blocks()
+            .call()
+            .await
+            .unwrap();
+        assert!(finalized.as_u64() >= confirmed.height);
+    }
+
+    #[async_std::test]
+    async fn test_audit() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 70.into(), alice, bob, &test_l1).await;
+        let genesis = test_rollup.state.read().await.clone();
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        let txn = test_rollup.test_transaction(100, 1).await;
+        client.connect(None).await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url.clone())
+        };
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&test_rollup.bob.address()) == 100)
+            .await;
+
+        // An honest operator passes the audit.
+        let audit_opt = AuditOptions {
+            l1_provider: anvil.url(),
+            hotshot_address: test_l1.hotshot.address(),
+            rollup_address: test_rollup.contract.address(),
+            sequencer_url,
+            until: None,
+        };
+        let report = audit(&audit_opt, genesis.clone()).await.unwrap();
+        assert_eq!(report.first_divergence(), None);
+        assert!(report.num_checked() > 0);
+        assert_eq!(report.start_height, 0);
+        assert!(report.end_height() > 0);
+        assert!(report.num_transactions >= 1);
+        assert!(report.balances.contains(&(test_rollup.bob.address(), 100)));
+
+        // A snapshot whose state was altered before it was written is internally consistent, but
+        // the replay from it disagrees with the contract.
+        let mut tampered = genesis;
+        let theft = Transaction {
+            amount: 1,
+            destination: test_rollup.bob.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: test_rollup.vm.id().into(),
+            asset: None,
+        };
+        tampered
+            .apply_transaction(&SignedTransaction::new_with_wallet(theft, &test_rollup.alice).await)
+            .unwrap();
+        let path = write_snapshot(tmp_dir.path(), &tampered, 1).unwrap();
+        let report = audit(&audit_opt, load_snapshot(&path).unwrap())
+            .await
+            .unwrap();
+        let divergence = report.first_divergence().unwrap();
+        assert_eq!(report.heights.last(), Some(divergence));
+        assert_eq!(
+            report
+                .heights
+                .iter()
+                .filter(|height| height.diverges())
+                .count(),
+            1
+        );
+    }
+
+    #[async_std::test]
+    async fn test_supervised_restart() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+
+        // Deploy hotshot contract
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Start a test Rollup instance
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 60.into(), alice, bob, &test_l1).await;
+
+        // Start a test HotShot configuration
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        // Spawn hotshot commitment and executor tasks, with the executor set to panic once.
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+
+        let panic_at = 3;
+        let rollup_opt = ExecutorOptions {
+            hooks: TestHooks {
+                panic_at_block: Arc::new(std::sync::Mutex::new(Some(panic_at))),
+                ..Default::default()
+            },
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+
+        let mut exec_stream = test_rollup.subscribe_executor().await;
+        let state_lock = test_rollup.state.clone();
+        let status = StatusHandle::default();
+        let executor_status = status.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        spawn(
+            async move { run_executor_supervised(&rollup_opt, state_lock, executor_status).await },
+        );
+
+        // Every block is executed exactly once, in order: after the panic, execution resumes at
+        // the block which was interrupted.
+        for expected in 0..panic_at + 3 {
+            let (height, state) = exec_stream.next().await.unwrap();
+            assert_eq!(height, expected);
+            assert_eq!(state.block_height(), expected + 1);
+        }
+        let status = status.read().await.clone();
+        assert_eq!(status.restarts, 1);
+        assert!(status
+            .last_failure
+            .unwrap()
+            .contains(&format!("injected panic before block {panic_at}")));
+
+        // Proofs sent after the restart are accepted by the rollup contract.
+        while test_rollup
+            .contract
+            .num_verified_blocks()
+            .call()
+            .await
+            .unwrap()
+            .as_u64()
+            <= panic_at
+        {
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    #[async_std::test]
+    async fn test_submission_retries_preserve_order() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 80.into(), alice, bob, &test_l1).await;
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        client.connect(None).await;
+        for nonce in 1..=3 {
+            let txn = test_rollup.test_transaction(100, nonce).await;
+            client
+                .post::<()>("submit/submit")
+                .body_json(&txn)
+                .unwrap()
+                .send()
+                .await
+                .unwrap();
+        }
+
+        // The first few attempts to send a proof fail, so the batches behind them queue up.
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            hooks: TestHooks {
+                failed_submissions: Arc::new(3.into()),
+                ..Default::default()
+            },
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+
+        // Every state update matches the executor's state at that height, in order.
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&test_rollup.bob.address()) == 300)
+            .await;
+        let updates = test_rollup
+            .contract
+            .state_update_filter()
+            .from_block(0u64)
+            .query()
+            .await
+            .unwrap();
+        assert!(!updates.is_empty());
+        for pair in updates.windows(2) {
+            assert!(pair[0].block_height < pair[1].block_height, "{pair:?}");
+        }
+    }
+
+    /// Build executor options for the rollup `test_rollup`, reading from the sequencer at
+    /// `sequencer_url`. Tests override the fields they exercise.
+    fn test_executor_options(
+        anvil: &TestAnvil,
+        test_l1: &TestL1System,
+        test_rollup: &TestRollupInstance,
+        sequencer_url: Url,
+    ) -> ExecutorOptions {
+        ExecutorOptions {
+            sequencer_urls: vec![sequencer_url],
+            rollup_account_index: test_l1.clients.funded[1].index,
+            l1_http_provider: anvil.url(),
+            l1_ws_provider: anvil.ws_url(),
+            l1_chain_id: None,
+            rollup_mnemonic: TEST_MNEMONIC.to_string(),
+            hotshot_address: test_l1.hotshot.address(),
+            rollup_address: test_rollup.contract.address(),
+            output_stream: Some(test_rollup.executor_send.clone()),
+            block_hooks: Default::default(),
+            webhooks: vec![],
+            alerts: Default::default(),
+            fee_bump: Default::default(),
+            balance_monitor: Default::default(),
+            lag_alerts: Default::default(),
+            watchdog: Default::default(),
+            invariant_interval: 1,
+            event_backfill: Default::default(),
+            retention: Default::default(),
+            submission_url: None,
+            diagnostics_dir: std::env::temp_dir(),
+            cursor_path: None,
+            snapshots: None,
+            start: None,
+            check_commitments: true,
+            verify_chain: true,
+            submitter_lease_blocks: None,
+            read_only: false,
+            l1_updates: Default::default(),
+            proof_interval: 1,
+            proof_queue_depth: 16,
+            drain_timeout: Duration::from_secs(60),
+            shutdown: None,
+            hooks: Default::default(),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_block_not_yet_available() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            MOCK_VM_ID.into(),
+            alice,
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        // The query service lags behind the HotShot contract, so the blocks the executor is told
+        // about are not available yet.
+        let chain = mock_chain();
+        let mock = MockQueryService::start(chain);
+        mock.set_available(0);
+        commit_blocks(&test_l1.hotshot, chain).await;
+
+        let rollup_opt = test_executor_options(&anvil, &test_l1, &test_rollup, mock.url());
+        let state_lock = test_rollup.state.clone();
+        let status = StatusHandle::default();
+        let executor_status = status.clone();
+        // Unsupervised, so the test fails if the executor exits instead of waiting.
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, executor_status).await });
+
+        while status.read().await.unavailable_blocks == 0 {
+            sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(test_rollup.state.read().await.block_height(), 0);
+
+        // Once the query service catches up, so does the executor.
+        mock.set_available(MOCK_CHAIN_LEN);
+        test_rollup
+            .wait_for_effect(|state| state.block_height() == MOCK_CHAIN_LEN as u64)
+            .await;
+        assert!(executor.cancel().await.is_none());
+    }
+
+    #[async_std::test]
+    async fn test_query_errors_retried() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            MOCK_VM_ID.into(),
+            alice,
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        let chain = mock_chain();
+        let mock = MockQueryService::start(chain);
+        commit_blocks(&test_l1.hotshot, chain).await;
+
+        // As many failures in a row as the executor retries are ridden out.
+        mock.fail_next(
+            QUERY_RETRIES as usize,
+            tide::StatusCode::InternalServerError,
+        );
+        let rollup_opt = test_executor_options(&anvil, &test_l1, &test_rollup, mock.url());
+        let state_lock = test_rollup.state.clone();
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+        test_rollup
+            .wait_for_effect(|state| state.block_height() == MOCK_CHAIN_LEN as u64)
+            .await;
+
+        // A query service which stays down is not, and the executor gives up on the next block.
+        mock.set_down(true);
+        commit_blocks(&test_l1.hotshot, &chain[..1]).await;
+        let err = executor.await.unwrap_err();
+        assert!(
+            matches!(err, ExecutorError::QueryService { .. }),
+            "unexpected error {err:?}"
+        );
+    }
+
+    #[async_std::test]
+    async fn test_missed_events_recovered() {
+        use std::sync::atomic::Ordering;
+
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            MOCK_VM_ID.into(),
+            alice,
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        let chain = mock_chain();
+        let mock = MockQueryService::start(chain);
+        let hooks = TestHooks::default();
+        let rollup_opt = ExecutorOptions {
+            event_backfill: EventBackfillOptions {
+                interval: Duration::from_secs(2),
+                window_blocks: 4,
+                batch_blocks: 2,
+            },
+            hooks: hooks.clone(),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, mock.url())
+        };
+        let state_lock = test_rollup.state.clone();
+        let status = StatusHandle::default();
+        let executor_status = status.clone();
+        // Unsupervised, so the test fails if the executor restarts instead of recovering.
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, executor_status).await });
+
+        // The subscription loses the events announcing the first two blocks, as it would if its
+        // websocket dropped while they were emitted. The event announcing the third gives the gap
+        // away, or else the next query for missed events finds them.
+        hooks.dropped_events.store(2, Ordering::SeqCst);
+        for block in chain[..3].chunks(1) {
+            commit_blocks(&test_l1.hotshot, block).await;
+        }
+        test_rollup
+            .wait_for_effect(|state| state.block_height() == 3)
+            .await;
+        // A query may also find an event before the subscription delivers it.
+        assert!(status.read().await.l1_events.backfilled >= 2);
+
+        // The last event is lost with nothing after it to give it away, so only the periodic query
+        // finds it.
+        hooks.dropped_events.store(1, Ordering::SeqCst);
+        commit_blocks(&test_l1.hotshot, &chain[3..]).await;
+        test_rollup
+            .wait_for_effect(|state| state.block_height() == MOCK_CHAIN_LEN as u64)
+            .await;
+        assert!(status.read().await.l1_events.backfilled >= 3);
+        assert_eq!(hooks.dropped_events.load(Ordering::SeqCst), 0);
+        assert!(executor.cancel().await.is_none());
+    }
+
+    #[async_std::test]
+    async fn test_block_quarantined() {
+        use crate::watchdog::Phase;
+
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            MOCK_VM_ID.into(),
+            alice,
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        let chain = mock_chain();
+        let mock = MockQueryService::start(chain);
+        commit_blocks(&test_l1.hotshot, chain).await;
+
+        let alert_buffer = AlertBuffer::default();
+        let status = StatusHandle::default();
+        let api_port = pick_unused_port().unwrap();
+        let api_options = APIOptions {
+            admin_token: Some("secret".parse().unwrap()),
+            alerts: alert_buffer.clone(),
+            ..APIOptions::new(api_port, mock.url())
+        };
+        {
+            let state = test_rollup.state.clone();
+            let status = status.clone();
+            spawn(async move { serve(&api_options, state, status).await });
+        }
+
+        // Applying the transactions of the second block takes far longer than the limit.
+        let rollup_opt = ExecutorOptions {
+            alerts: Alerts::default().with(alert_buffer),
+            watchdog: WatchdogOptions {
+                slow_block: Some(Duration::ZERO),
+                block_limit: Some(Duration::from_millis(500)),
+            },
+            hooks: TestHooks {
+                slow_block: Some((1, Phase::Apply, Duration::from_secs(3))),
+                ..Default::default()
+            },
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, mock.url())
+        };
+        let err = run_executor(&rollup_opt, test_rollup.state.clone(), status.clone())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ExecutorError::BlockQuarantined {
+                    height: 1,
+                    limit_ms: 500
+                }
+            ),
+            "unexpected error {err:?}"
+        );
+        assert!(!err.is_retryable());
+
+        // The state is left as it was before the block, which admins can find in the API.
+        assert_eq!(test_rollup.state.read().await.block_height(), 1);
+        let client =
+            Client::<ApiError>::new(format!("http://localhost:{api_port}").parse().unwrap());
+        client.connect(None).await;
+        let quarantined: Option<QuarantinedBlock> = client
+            .get("rollup/admin/quarantine")
+            .header("Authorization", "Bearer secret")
+            .send()
+            .await
+            .unwrap();
+        let quarantined = quarantined.unwrap();
+        assert_eq!(quarantined.height, 1);
+        assert_eq!(
+            quarantined.namespace_path,
+            format!("block/1/namespace/{MOCK_VM_ID}")
+        );
+        assert_eq!(quarantined.phase, Some(Phase::Apply));
+        assert_eq!(quarantined.limit_ms, 500);
+        assert!(quarantined.timings.apply_ms > 0, "{quarantined:?}");
+        assert_eq!(quarantined.timings.prove_ms, 0);
+
+        let alerts: Vec<Alert> = client
+            .get("rollup/admin/alerts")
+            .header("Authorization", "Bearer secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(alerts.len(), 1, "{alerts:?}");
+        assert_eq!(alerts[0].severity, Severity::Critical);
+        assert_eq!(alerts[0].kind, AlertKind::BlockQuarantined);
+        assert_eq!(alerts[0].height, Some(1));
+    }
+
+    #[async_std::test]
+    async fn test_invariant_violated() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            MOCK_VM_ID.into(),
+            alice,
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        let chain = mock_chain();
+        let mock = MockQueryService::start(chain);
+        commit_blocks(&test_l1.hotshot, chain).await;
+
+        let alert_buffer = AlertBuffer::default();
+        let rollup_opt = ExecutorOptions {
+            alerts: Alerts::default().with(alert_buffer.clone()),
+            hooks: TestHooks {
+                corrupt_block: Some(2),
+                ..Default::default()
+            },
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, mock.url())
+        };
+        let err = run_executor(&rollup_opt, test_rollup.state.clone(), Default::default())
+            .await
+            .unwrap_err();
+        let ExecutorError::InvariantViolated { height, violations } = &err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(*height, 2);
+        assert!(
+            violations
+                .iter()
+                .any(|violation| matches!(violation, Violation::NativeSupply { .. })),
+            "{violations:?}"
+        );
+        assert!(!err.is_retryable());
+
+        // The corrupt state is never proven.
+        let verified = test_rollup
+            .contract
+            .num_verified_blocks()
+            .call()
+            .await
+            .unwrap();
+        assert!(verified <= 2.into(), "{verified}");
+
+        let alerts = alert_buffer.recent();
+        assert_eq!(alerts.len(), 1, "{alerts:?}");
+        assert_eq!(alerts[0].severity, Severity::Critical);
+        assert_eq!(alerts[0].kind, AlertKind::InvariantViolated);
+        assert_eq!(alerts[0].height, Some(2));
+    }
+
+    #[async_std::test]
+    async fn test_retention() {
+        use crate::retention::PrunedHeights;
+
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            MOCK_VM_ID.into(),
+            alice,
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        let chain = mock_chain();
+        let mock = MockQueryService::start(chain);
+        commit_blocks(&test_l1.hotshot, chain).await;
+
+        // Hold up the submitter, so that no block is confirmed on L1 while all of them are
+        // executed.
+        let hooks = TestHooks::default();
+        let gate = hooks.submission_gate.write().await;
+        let rollup_opt = ExecutorOptions {
+            retention: RetentionConfig {
+                receipts_blocks: 1,
+                history_blocks: 2,
+                proofs_blocks: 1,
+                snapshots: 1,
+            },
+            hooks: hooks.clone(),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, mock.url())
+        };
+        let state_lock = test_rollup.state.clone();
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+        test_rollup
+            .wait_for_effect(|state| state.block_height() == MOCK_CHAIN_LEN as u64)
+            .await;
+
+        // Nothing unconfirmed is pruned, however little history is to be kept.
+        {
+            let state = test_rollup.state.read().await;
+            assert_eq!(state.pruned(), PrunedHeights::default());
+            assert!(state.block_receipts(0).is_some());
+            assert!(state.block_summary(0).is_some());
+            assert!(state.proof(0).is_some());
+        }
+
+        // Once the blocks are verified, everything older than the retention is pruned, except the
+        // summary and proof of the last verified block, which the submitter may check again.
+        drop(gate);
+        let expected = PrunedHeights {
+            receipts: 4,
+            history: 3,
+            proofs: 4,
+        };
+        while test_rollup.state.read().await.pruned() != expected {
+            sleep(Duration::from_millis(100)).await;
+        }
+        let state = test_rollup.state.read().await;
+        assert!(state.block_receipts(3).is_none());
+        assert!(state.block_receipts(4).is_some());
+        assert!(state.block_summary(2).is_none());
+        assert!(state.block_summary(3).is_some());
+        assert!(state.proof(3).is_none());
+        assert!(state.proof(4).is_some());
+        drop(state);
+        assert!(executor.cancel().await.is_none());
+    }
+
+    /// Forward requests to the query service at `upstream` until `alive` is cleared, then fail
+    /// every request, as a replica which has gone down would.
+    fn flaky_replica(upstream: Url, alive: Arc<std::sync::atomic::AtomicBool>) -> Url {
+        use std::sync::atomic::Ordering;
+
+        let port = pick_unused_port().unwrap();
+        let mut server = tide::with_state((upstream, alive));
+        server.at("*").get(
+            |req: tide::Request<(Url, Arc<std::sync::atomic::AtomicBool>)>| async move {
+                let (upstream, alive) = req.state();
+                if !alive.load(Ordering::SeqCst) {
+                    return tide::Result::Ok(tide::Response::new(
+                        tide::StatusCode::ServiceUnavailable,
+                    ));
+                }
+                let mut forward = surf::get(upstream.join(req.url().path())?);
+                if let Some(accept) = req.header("Accept") {
+                    forward = forward.header("Accept", accept.as_str());
+                }
+                let mut upstream_res = forward.await?;
+                let mut res = tide::Response::new(upstream_res.status());
+                res.set_body(upstream_res.body_bytes().await?);
+                if let Some(content_type) = upstream_res.content_type() {
+                    res.set_content_type(content_type);
+                }
+                Ok(res)
+            },
+        );
+        spawn(server.listen(format!("0.0.0.0:{port}")));
+        format!("http://localhost:{port}").parse().unwrap()
+    }
+
+    #[async_std::test]
+    async fn test_query_replica_failover() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 160.into(), alice, bob, &test_l1).await;
+
+        // Two sequencer nodes serve the query service. The executor reaches the first, its
+        // primary, through a proxy the test can take down.
+        let nodes = init_hotshot_handles().await;
+        let tmp_dir = TempDir::new().unwrap();
+        let mut urls = vec![];
+        for (i, node) in nodes[..2].iter().enumerate() {
+            let port = pick_unused_port().unwrap();
+            let storage_path = tmp_dir.path().join(format!("tmp_storage_{i}"));
+            start_query_service(port, storage_path, node.clone()).await;
+            urls.push(format!("http://localhost:{port}").parse::<Url>().unwrap());
+        }
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url = urls[0].clone();
+        let alive = Arc::new(AtomicBool::new(true));
+        let primary = flaky_replica(sequencer_url.clone(), alive.clone());
+        let secondary = urls[1].clone();
+
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        client.connect(None).await;
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            sequencer_urls: vec![primary.clone(), secondary.clone()],
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, primary.clone())
+        };
+        let state_lock = test_rollup.state.clone();
+        let status = StatusHandle::default();
+        let executor_status = status.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        // Unsupervised, so the test fails if the executor exits instead of failing over.
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, executor_status).await });
+
+        let submit = |nonce| {
+            let client = &client;
+            let test_rollup = &test_rollup;
+            async move {
+                let txn = test_rollup.test_transaction(100, nonce).await;
+                client
+                    .post::<()>("submit/submit")
+                    .body_json(&txn)
+                    .unwrap()
+                    .send()
+                    .await
+                    .unwrap();
+            }
+        };
+        submit(1).await;
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&test_rollup.bob.address()) == 100)
+            .await;
+        {
+            let status = status.read().await;
+            assert!(status.query_replicas[0].active);
+            assert!(status.query_replicas[0].healthy);
+        }
+
+        // Take the primary down mid-sync. The executor carries on from the secondary.
+        alive.store(false, Ordering::SeqCst);
+        submit(2).await;
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&test_rollup.bob.address()) == 200)
+            .await;
+        {
+            let status = status.read().await;
+            assert_eq!(status.query_replicas[0].url, primary);
+            assert!(!status.query_replicas[0].healthy);
+            assert!(status.query_replicas[0].failures > 0);
+            assert_eq!(status.query_replicas[1].url, secondary);
+            assert!(status.query_replicas[1].active);
+            assert!(status.query_replicas[1].healthy);
+        }
+        assert!(executor.cancel().await.is_none());
+    }
+
+    #[async_std::test]
+    async fn test_proof_interval() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 85.into(), alice, bob, &test_l1).await;
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        client.connect(None).await;
+        let signed = SignedTransaction::new_with_wallet(
+            Transaction {
+                amount: 100,
+                destination: test_rollup.bob.address(),
+                nonce: 1,
+                priority: 0,
+                vm_id: test_rollup.vm.id().into(),
+                asset: None,
+            },
+            &test_rollup.alice,
+        )
+        .await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&test_rollup.vm.wrap(&signed.clone().into()))
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            proof_interval: 5,
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+        let status = StatusHandle::default();
+        let state_lock = test_rollup.state.clone();
+        let executor_status = status.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        spawn(async move { run_executor(&rollup_opt, state_lock, executor_status).await });
+
+        // The transfer shows up in the confirmed balance once a stride containing it is verified.
+        let bob = test_rollup.bob.address();
+        let confirmed = loop {
+            let confirmed = status.read().await.confirmed_balance(&bob);
+            if confirmed.balance == 100 {
+                break confirmed;
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+        assert_eq!(confirmed.height % 5, 0);
+        let block = test_rollup
+            .state
+            .read()
+            .await
+            .transaction_block(&signed.hash())
+            .unwrap();
+        assert!(block < confirmed.height);
+        assert!(matches!(
+            status.read().await.verification(block),
+            Some(BlockVerification::Verified { .. })
+        ));
+
+        // Every block was proven, in strides of 5. HotShot may commit several blocks at once, in
+        // which case they are proven up to the last multiple of 5 among them, so there is at most
+        // one proof per stride.
+        let updates = test_rollup
+            .contract
+            .state_update_filter()
+            .from_block(0u64)
+            .query()
+            .await
+            .unwrap();
+        let verified = updates.last().unwrap().block_height.as_u64();
+        assert!(updates.len() as u64 <= (verified + 4) / 5);
+        for update in &updates {
+            assert_eq!(update.block_height.as_u64() % 5, 0, "{update:?}");
+        }
+        for pair in updates.windows(2) {
+            assert!(pair[0].block_height < pair[1].block_height, "{pair:?}");
+        }
+    }
+
+    #[async_std::test]
+    async fn test_shutdown_drains_proofs() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 90.into(), alice, bob, &test_l1).await;
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        // Hold up the submitter, so proofs pile up in its queue while blocks are executed.
+        let hooks = TestHooks::default();
+        let gate = hooks.submission_gate.write().await;
+        let (stop, shutdown) = channel::bounded(1);
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            shutdown: Some(shutdown),
+            hooks: hooks.clone(),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+        let mut exec_stream = test_rollup.subscribe_executor().await;
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        let status = StatusHandle::default();
+        let executor_status = status.clone();
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, executor_status).await });
+
+        // Execution carries on while no proof can be sent.
+        while exec_stream.next().await.unwrap().0 < 5 {}
+        assert_eq!(
+            test_rollup
+                .contract
+                .num_verified_blocks()
+                .call()
+                .await
+                .unwrap(),
+            0.into()
+        );
+
+        // Shut down, then let the queued proofs through. Every executed block gets proven before
+        // the executor exits.
+        stop.send(()).await.unwrap();
+        drop(gate);
+        executor.await.unwrap();
+        let executed = test_rollup.state.read().await.block_height();
+        let verified = test_rollup
+            .contract
+            .num_verified_blocks()
+            .call()
+            .await
+            .unwrap();
+        assert_eq!(verified, executed.into());
+        assert_eq!(status.read().await.dropped_proofs, 0);
+    }
+
+    #[async_std::test]
+    async fn test_snapshot_restart() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+
+        // Deploy hotshot contract
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Start a test Rollup instance
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 70.into(), alice, bob, &test_l1).await;
+
+        // Start a test HotShot configuration
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        // Spawn hotshot commitment and executor tasks, with the executor writing snapshots.
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+
+        let snapshots = SnapshotOptions {
+            dir: tmp_dir.path().join("snapshots"),
+            interval: 10,
+        };
+        let rollup_opt = ExecutorOptions {
+            retention: RetentionConfig {
+                snapshots: 2,
+                ..Default::default()
+            },
+            snapshots: Some(snapshots.clone()),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url.clone())
+        };
+
+        let mut exec_stream = test_rollup.subscribe_executor().await;
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+
+        // Execute 50 blocks from genesis, then stop the executor.
+        let num_blocks = 50;
+        let mut genesis_replay = None;
+        while genesis_replay.is_none() {
+            let (height, state) = exec_stream.next().await.unwrap();
+            if height + 1 == num_blocks {
+                genesis_replay = Some(state);
+            }
+        }
+        let genesis_replay = genesis_replay.unwrap();
+        executor.cancel().await;
+
+        // Snapshots are written in the background; wait for the last one.
+        while load_latest_snapshot(&snapshots.dir, &test_rollup.vm)
+            .map(|state| state.block_height())
+            != Some(num_blocks)
+        {
+            sleep(Duration::from_millis(100)).await;
+        }
+        // Only the newest snapshots are kept.
+        let mut files = std::fs::read_dir(&snapshots.dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["state-40.json", "state-50.json"]);
+
+        // Restart from the older snapshot, replaying the gap from the query service.
+        std::fs::remove_file(snapshots.dir.join("state-50.json")).unwrap();
+        let mut state = load_latest_snapshot(&snapshots.dir, &test_rollup.vm).unwrap();
+        assert_eq!(state.block_height(), 40);
+        let availability: Client<ServerError> =
+            Client::new(sequencer_url.join("availability").unwrap());
+        for height in state.block_height()..num_blocks {
+            let header: Header = availability
+                .get(&format!("header/{height}"))
+                .send()
+                .await
+                .unwrap();
+            let namespace_proof = availability
+                .get::<NamespaceProofQueryData>(&format!(
+                    "block/{height}/namespace/{}",
+                    u64::from(test_rollup.vm.id())
+                ))
+                .send()
+                .await
+                .unwrap()
+                .proof;
+            state.apply_block(
+                &header.transactions_root,
+                &namespace_proof,
+                &BlockInputs::default(),
+            );
+        }
+        assert_eq!(state.block_height(), num_blocks);
+        assert_eq!(state.commit(), genesis_replay.commit());
+    }
+
+    #[async_std::test]
+    async fn test_replay_block() {
+        use crate::replay::{replay_block, ReplayError, ReplayOptions};
+
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = FixtureWallet::Alice.wallet();
+        let bob = FixtureWallet::Bob.wallet();
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 71.into(), alice, bob, &test_l1).await;
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, nodes[0].clone()).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        let txn = test_rollup.test_transaction(100, 1).await;
+        client.connect(None).await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        // The executor writes a snapshot every few blocks, on top of the genesis state, so that
+        // every block has a snapshot at or below it.
+        let snapshots = SnapshotOptions {
+            dir: tmp_dir.path().join("snapshots"),
+            interval: 3,
+        };
+        write_snapshot(&snapshots.dir, &*test_rollup.state.read().await, 100).unwrap();
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            sequencer_urls: vec![sequencer_url.clone()],
+            rollup_account_index: test_l1.clients.funded[1].index,
+            l1_http_provider: anvil.url(),
+            l1_ws_provider: anvil.ws_url(),
+            l1_chain_id: None,
+            rollup_mnemonic: TEST_MNEMONIC.to_string(),
+            hotshot_address: test_l1.hotshot.address(),
+            rollup_address: test_rollup.contract.address(),
+            output_stream: Some(test_rollup.executor_send.clone()),
+            block_hooks: Default::default(),
+            webhooks: vec![],
+            alerts: Default::default(),
+            fee_bump: Default::default(),
+            balance_monitor: Default::default(),
+            lag_alerts: Default::default(),
+            watchdog: Default::default(),
+            retention: RetentionConfig {
+                snapshots: 100,
+                ..Default::default()
+            },
+            submission_url: None,
+            diagnostics_dir: std::env::temp_dir(),
+            cursor_path: None,
+            snapshots: Some(snapshots.clone()),
+            start: None,
+            check_commitments: true,
+            verify_chain: true,
+            submitter_lease_blocks: None,
+            read_only: false,
+            l1_updates: Default::default(),
+            proof_interval: 1,
+            proof_queue_depth: 16,
+            drain_timeout: Duration::from_secs(60),
+            shutdown: None,
+            hooks: Default::default(),
+        };
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+
+        // Let the executor move well past the block with the transfer.
+        test_rollup
+            .wait_for_effect(|state| state.get_balance(&test_rollup.bob.address()) == 100)
+            .await;
+        let height = {
+            let state = test_rollup.state.read().await;
+            (0..state.block_height())
+                .find(|height| !state.block_receipts(*height).unwrap().is_empty())
+                .unwrap()
+        };
+        while test_rollup.state.read().await.block_height() < height + 5 {
+            sleep(Duration::from_millis(100)).await;
+        }
+        executor.cancel().await;
+
+        // Replaying the block and the one after it reproduces what the executor recorded, from
+        // whichever snapshot is below each, without touching the state.
+        let opt = ReplayOptions {
+            snapshot_dir: snapshots.dir.clone(),
+            sequencer_url,
+            l1_provider: anvil.url(),
+            rollup_address: Some(test_rollup.contract.address()),
+        };
+        let state = test_rollup.state.read().await.clone();
+        for height in [height, height + 1] {
+            let replay = replay_block(&opt, height).await.unwrap();
+            assert!(replay.snapshot_height <= height);
+            let summary = state.block_summary(height).unwrap();
+            assert_eq!(replay.result.summary.height, height);
+            assert_eq!(
+                replay.result.summary.state_commitment,
+                summary.state_commitment
+            );
+            assert_eq!(replay.result.summary.payloads, summary.payloads);
+            assert_eq!(replay.result.summary.weight, summary.weight);
+            assert_eq!(
+                serde_json::to_value(&replay.result.transactions).unwrap(),
+                serde_json::to_value(state.block_receipts(height).unwrap()).unwrap()
+            );
+            let pre_state = match height.checked_sub(1) {
+                Some(prev) => state.block_summary(prev).unwrap().state_commitment,
+                None => replay.pre_state,
+            };
+            assert_eq!(replay.pre_state, pre_state);
+        }
+        let replay = replay_block(&opt, height).await.unwrap();
+        let bob = replay
+            .balance_changes
+            .iter()
+            .find(|change| change.address == test_rollup.bob.address())
+            .unwrap();
+        assert_eq!(bob.after - bob.before, 100);
+        assert_eq!(
+            test_rollup.state.read().await.commit(),
+            state.commit(),
+            "replaying changed the state"
+        );
+
+        // Without a snapshot below the block, it cannot be replayed.
+        let opt = ReplayOptions {
+            snapshot_dir: tmp_dir.path().join("missing"),
+            ..opt
+        };
+        let err = replay_block(&opt, height).await.unwrap_err();
+        assert!(
+            matches!(err, ReplayError::NoSnapshot { oldest: None, .. }),
+            "{err}"
+        );
+    }
+
+    #[async_std::test]
+    async fn test_backfill() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 70.into(), alice, bob, &test_l1).await;
+        let genesis = test_rollup.state.read().await.clone();
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let txn = SignedTransaction::new_with_wallet(
+            Transaction {
+                amount: 100,
+                destination: test_rollup.bob.address(),
+                nonce: 1,
+                priority: 0,
+                vm_id: test_rollup.vm.id().into(),
+                asset: None,
+            },
+            &test_rollup.alice,
+        )
+        .await;
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        client.connect(None).await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&test_rollup.vm.wrap(&txn.clone().into()))
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        // Execute until the transaction is in a snapshot, then stop the executor.
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let snapshots = SnapshotOptions {
+            dir: tmp_dir.path().join("snapshots"),
+            interval: 10,
+        };
+        let rollup_opt = ExecutorOptions {
+            retention: RetentionConfig {
+                snapshots: 100,
+                ..Default::default()
+            },
+            snapshots: Some(snapshots.clone()),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url.clone())
+        };
+        let mut exec_stream = test_rollup.subscribe_executor().await;
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+        let executed = loop {
+            let (height, state) = exec_stream.next().await.unwrap();
+            if (height + 1) % snapshots.interval == 0
+                && state.transaction_block(&txn.hash()).is_some()
+            {
+                break state;
+            }
+        };
+        executor.cancel().await;
+        let snapshot_height = executed.block_height();
+        let txn_block = executed.transaction_block(&txn.hash()).unwrap();
+        let snapshot_path = snapshots.dir.join(format!("state-{snapshot_height}.json"));
+        while !snapshot_path.exists() {
+            sleep(Duration::from_millis(100)).await;
+        }
+
+        // Restart from the snapshot as a node which has none of the history below it.
+        let mut restarted = load_snapshot(&snapshot_path).unwrap();
+        restarted.forget_history();
+        let balances = restarted.balances().collect::<Vec<_>>();
+        let state = Arc::new(RwLock::new(restarted));
+        let status = StatusHandle::default();
+        let backfill_opt = BackfillOptions {
+            sequencer_url,
+            l1_provider: anvil.url(),
+            rollup_address: test_rollup.contract.address(),
+            cursor_path: Some(tmp_dir.path().join("backfill.json")),
+            delay: Duration::ZERO,
+        };
+        run_backfill(&backfill_opt, genesis, state.clone(), status.clone())
+            .await
+            .unwrap();
+        assert_eq!(status.read().await.backfill, None);
+
+        // The history below the snapshot is back, and the balances are untouched.
+        let state = state.read().await;
+        assert_eq!(state.balances().collect::<Vec<_>>(), balances);
+        for height in 0..snapshot_height {
+            let summary = state.block_summary(height).unwrap();
+            let expected = executed.block_summary(height).unwrap();
+            assert_eq!(summary.state_commitment, expected.state_commitment);
+            assert_eq!(summary.payloads, expected.payloads);
+        }
+        assert!(state.block_receipts(3).is_some());
+        let receipts = state.block_receipts(txn_block).unwrap();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].hash, txn.hash());
+        assert_eq!(receipts[0].sender, Some(test_rollup.alice.address()));
+        assert!(receipts[0].applied());
+
+        // A finished backfill leaves its cursor at the snapshot height, to resume from.
+        assert_eq!(
+            load_snapshot(backfill_opt.cursor_path.as_ref().unwrap())
+                .unwrap()
+                .block_height(),
+            snapshot_height
+        );
+    }
+
+    #[async_std::test]
+    async fn test_execute_multi_rollup() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        // Deploy hotshot contract
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Start test Rollup instances
+        let num_rollups = 3;
+        let mut test_rollups = Vec::new();
+        for i in 1..num_rollups + 1 {
+            // To keep nonces consistent for the underlying provider, we must await these iteratively
+            let test_rollup = TestRollupInstance::launch(
+                anvil.url().clone(),
+                i.into(),
+                alice.clone(),
+                bob.clone(),
+                &test_l1,
+            )
+            .await;
+            test_rollups.push(test_rollup);
+        }
+
+        // Start a test HotShot configuration
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        // Submit transactions to sequencer
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        for i in 0..num_rollups {
+            let txn = test_rollups[i as usize].test_transaction(100, 1).await;
+            client.connect(None).await;
+            client
+                .post::<()>("submit/submit")
+                .body_json(&txn)
+                .unwrap()
+                .send()
+                .await
+                .unwrap();
+        }
+
+        // Spawn hotshot commitment task
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+
+        // Spawn all rollup executors
+        for test_rollup in &test_rollups {
+            let state_lock = test_rollup.state.clone();
+            let rollup_opt = ExecutorOptions {
+                ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url.clone())
+            };
+            spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+        }
+
+        // Wait for all rollup contracts to process state updates
+        join_all(test_rollups.iter().map(|test_rollup| {
+            test_rollup.wait_for_effect(|state| {
+                let bob_balance = state.get_balance(&test_rollup.bob.address());
+                tracing::info!("Bob's balance is {bob_balance}/100");
+                bob_balance == 100
+            })
+        }))
+        .await;
+
+        // Every rollup saw only its own transaction in its namespace, and the others' transactions
+        // in the blocks. Wait until every executor has passed all of the blocks with transactions.
+        let mut end = 0;
+        for test_rollup in &test_rollups {
+            let state = test_rollup.state.read().await;
+            let txn_block = (0..state.block_height())
+                .find(|height| state.block_summary(*height).unwrap().payloads.vm_payloads > 0)
+                .unwrap();
+            end = end.max(txn_block + 1);
+        }
+        for test_rollup in &test_rollups {
+            while test_rollup.state.read().await.block_height() < end {
+                sleep(Duration::from_millis(100)).await;
+            }
+            let state = test_rollup.state.read().await;
+            let mut payloads = BlockCounts::default();
+            for height in 0..end {
+                payloads += state.block_summary(height).unwrap().payloads;
+            }
+            assert_eq!(
+                payloads,
+                BlockCounts {
+                    total_payloads: num_rollups,
+                    vm_payloads: 1,
+                    decoded: 1,
+                    applied: 1,
+                    duplicates: 0,
+                    forced: 0,
+                }
+            );
+        }
+
+        // A transfer signed for one rollup is rejected by another, even when it is sequenced in
+        // the other's namespace and its nonce is the next one there.
+        let (own, other) = (&test_rollups[0], &test_rollups[1]);
+        let replay = SignedTransaction::new_with_wallet(
+            Transaction {
+                amount: 50,
+                destination: own.bob.address(),
+                nonce: 2,
+                priority: 0,
+                vm_id: own.vm.id().into(),
+                asset: None,
+            },
+            &own.alice,
+        )
+        .await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&other.vm.wrap(&replay.clone().into()))
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        let receipt = loop {
+            let state = other.state.read().await;
+            let receipt = (end..state.block_height())
+                .filter_map(|height| state.block_receipts(height))
+                .flatten()
+                .find(|receipt| receipt.hash == replay.hash())
+                .cloned();
+            if let Some(receipt) = receipt {
+                break receipt;
+            }
+            drop(state);
+            sleep(Duration::from_millis(100)).await;
+        };
+        assert_eq!(
+            receipt.error,
+            Some(
+                RollupError::WrongVm {
+                    expected: other.vm.id().into(),
+                    actual: own.vm.id().into(),
+                }
+                .to_string()
+            )
+        );
+        let state = other.state.read().await;
+        assert_eq!(state.get_balance(&other.bob.address()), 100);
+        assert_eq!(state.get_nonce(&other.alice.address()), 1);
+    }
+
+    #[async_std::test]
+    async fn test_cross_vm_message() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Rollup 120 accepts messages from rollup 110.
+        let source = TestRollupInstance::launch(
+            anvil.url().clone(),
+            110.into(),
+            alice.clone(),
+            bob.clone(),
+            &test_l1,
+        )
+        .await;
+        let dest = TestRollupInstance::launch_with_chain_config(
+            anvil.url().clone(),
+            120.into(),
+            alice.clone(),
+            bob.clone(),
+            &test_l1,
+            ChainConfig {
+                message_sources: [110].into(),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        // Alice on rollup 110 sends a message to Bob on rollup 120.
+        let outbound = OutboundMessage::new(
+            Message {
+                dest_vm: 120,
+                recipient: bob.address(),
+                nonce: 1,
+                payload: b"hello".to_vec().into(),
+            },
+            &alice,
+        )
+        .await
+        .unwrap();
+        let txn = source.vm.wrap(&outbound.into());
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        client.connect(None).await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+
+        for test_rollup in [&source, &dest] {
+            let state_lock = test_rollup.state.clone();
+            let rollup_opt = ExecutorOptions {
+                ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url.clone())
+            };
+            spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+        }
+
+        // The message is delivered on rollup 120, in a state verified by its contract.
+        dest.wait_for_effect(|state| !state.messages(&bob.address()).is_empty())
+            .await;
+        let state = dest.state.read().await;
+        let delivered = state.messages(&bob.address());
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].source_vm, 110);
+        assert_eq!(delivered[0].sender, alice.address());
+        assert_eq!(delivered[0].payload.as_ref(), b"hello");
+
+        // The block which sent it recorded it in its outbox on rollup 110, once that rollup has
+        // executed it too.
+        while source.state.read().await.block_height() < state.block_height() {
+            sleep(Duration::from_millis(100)).await;
+        }
+        let source_state = source.state.read().await;
+        assert!((0..source_state.block_height()).any(|height| {
+            source_state
+                .block_summary(height)
+                .and_then(|summary| summary.outbox)
+                == Some(outbox_commitment(delivered))
+        }));
+    }
+
+    #[async_std::test]
+    async fn test_fee_bump() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let provider = create_provider(&anvil.url());
+        let client = Arc::new(
+            connect_rpc(&anvil.url(), TEST_MNEMONIC, 1, None)
+                .await
+                .unwrap(),
+        );
+
+        // Take over block production, so we control the base fee of every block.
+        provider
+            .request::<_, serde_json::Value>("evm_setIntervalMining", [0])
+            .await
+            .unwrap();
+        let base_fee = U256::from(2_000_000_000u64);
+
+        // Send a transaction whose fee cap is below the base fee, so it cannot be mined until its
+        // fees are raised.
+        let low_fee = U256::from(1_000_000_000u64);
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(Address::random())
+            .value(1)
+            .max_fee_per_gas(low_fee)
+            .max_priority_fee_per_gas(low_fee)
+            .into();
+        let opt = FeeBumpOptions {
+            confirmation_blocks: 1,
+            bump_percent: 100,
+            max_bumps: 3,
+            ..Default::default()
+        };
+        let status = StatusHandle::default();
+        let send_status = status.clone();
+        let send_client = client.clone();
+        let send = spawn(async move {
+            send_with_fee_bumps(
+                &*send_client,
+                send_client.provider(),
+                tx,
+                &opt,
+                &send_status,
+                0,
+                1,
+            )
+            .await
+        });
+
+        // Once the original transaction is sent, mine blocks with a base fee above its fee cap.
+        let original = loop {
+            if let Some(BlockVerification::Submitted { l1_tx }) =
+                status.read().await.verification(0)
+            {
+                break l1_tx;
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+        spawn(async move {
+            loop {
+                provider
+                    .request::<_, serde_json::Value>("anvil_setNextBlockBaseFeePerGas", [base_fee])
+                    .await
+                    .unwrap();
+                provider
+                    .request::<_, serde_json::Value>("evm_mine", ())
+                    .await
+                    .unwrap();
+                sleep(Duration::from_millis(500)).await;
+            }
+        });
+
+        let receipt = send.await.unwrap();
+        assert_eq!(receipt.status, Some(U64::one()));
+        assert_ne!(receipt.transaction_hash, original);
+        assert!(receipt.effective_gas_price.unwrap() >= base_fee);
+        assert!(client
+            .get_transaction_receipt(original)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[async_std::test]
+    async fn test_insufficient_funds() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let provider = create_provider(&anvil.url());
+        let client = Arc::new(
+            connect_rpc(&anvil.url(), TEST_MNEMONIC, 1, None)
+                .await
+                .unwrap(),
+        );
+
+        // Drain the account, leaving it too little to pay for any transaction.
+        provider
+            .request::<_, serde_json::Value>("anvil_setBalance", (client.address(), U256::one()))
+            .await
+            .unwrap();
+
+        // The monitor reports the balance.
+        let status = StatusHandle::default();
+        let opt = BalanceMonitorOptions {
+            interval: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let monitor_client = client.clone();
+        let monitor_status = status.clone();
+        let monitor =
+            spawn(async move { monitor_balance(monitor_client, &opt, &monitor_status).await });
+        while status.read().await.operator_balance.is_none() {
+            sleep(Duration::from_millis(100)).await;
+        }
+        monitor.cancel().await;
+        assert_eq!(status.read().await.operator_balance, Some(U256::one()));
+        assert_eq!(status.read().await.runway_blocks, None);
+
+        // A proof it cannot pay for fails for that reason, not as a generic L1 error.
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(Address::random())
+            .value(1)
+            .into();
+        let err = send_with_fee_bumps(
+            &*client,
+            client.provider(),
+            tx,
+            &FeeBumpOptions::default(),
+            &status,
+            0,
+            1,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, ExecutorError::InsufficientFunds { .. }),
+            "{err}"
+        );
+        assert!(err.is_retryable());
+    }
+
+    #[async_std::test]
+    async fn test_separate_submission_provider() {
+        setup_logging();
+        setup_backtrace();
+
+        // Reads go to one L1 node, and transactions are sent to another.
+        let read_anvil = spawn_anvil(Default::default()).await;
+        let submit_anvil = spawn_anvil(Default::default()).await;
+        let read_provider = create_provider(&read_anvil.url());
+        let submit_provider = create_provider(&submit_anvil.url());
+        let client = connect_rpc(&read_anvil.url(), TEST_MNEMONIC, 1, None)
+            .await
+            .unwrap();
+
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(Address::random())
+            .value(1)
+            .into();
+        let opt = FeeBumpOptions {
+            confirmation_blocks: 2,
+            bump_percent: 20,
+            max_bumps: 0,
+            ..Default::default()
+        };
+        let status = StatusHandle::default();
+        let err = send_with_fee_bumps(&client, &submit_provider, tx, &opt, &status, 0, 1)
+            .await
+            .unwrap_err();
+
+        // The transaction was mined by the submission node only, so the reading node never saw
+        // it confirmed.
+        let ExecutorError::ProofTransactionStuck { l1_tx, bumps: 0 } = err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(
+            status.read().await.verification(0),
+            Some(BlockVerification::Submitted { l1_tx })
+        );
+        assert!(submit_provider
+            .get_transaction_receipt(l1_tx)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(read_provider
+            .get_transaction(l1_tx)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_bump_fees() {
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .max_fee_per_gas(100)
+            .max_priority_fee_per_gas(1)
+            .into();
+        bump_fees(&mut tx, 10);
+        match tx {
+            TypedTransaction::Eip1559(tx) => {
+                assert_eq!(tx.max_fee_per_gas, Some(110.into()));
+                // Small fees are rounded up, so they still satisfy the replacement rules.
+                assert_eq!(tx.max_priority_fee_per_gas, Some(2.into()));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_lag_watch() {
+        let sustained = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut watch = LagWatch::default();
+
+        // A lag above the threshold is only alerted on once it has lasted, and then only once.
+        assert!(!watch.observe(true, sustained, start));
+        assert!(!watch.observe(true, sustained, start + sustained / 2));
+        assert!(watch.observe(true, sustained, start + sustained));
+        assert!(!watch.observe(true, sustained, start + sustained * 2));
+
+        // Dropping back within the threshold, even briefly, starts the wait over.
+        assert!(!watch.observe(false, sustained, start + sustained * 3));
+        assert!(!watch.observe(true, sustained, start + sustained * 4));
+        assert!(watch.observe(true, sustained, start + sustained * 5));
+    }
+
+    #[test]
+    fn test_read_cursor() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path().join("cursor.json");
+        let cursor = Cursor {
+            executed_height: 5,
+            submitted_height: 4,
+            l1_log_block: 20,
+            ..Default::default()
+        };
+
+        // No cursor configured, or none written yet.
+        assert_eq!(read_cursor(None).unwrap(), SavedCursor::None);
+        assert_eq!(read_cursor(Some(&path)).unwrap(), SavedCursor::None);
+
+        cursor.store(&path).unwrap();
+        assert_eq!(
+            read_cursor(Some(&path)).unwrap(),
+            SavedCursor::Found {
+                path: path.clone(),
+                cursor
+            }
+        );
+
+        // A corrupted cursor is left to reconciliation to set aside.
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(
+            read_cursor(Some(&path)).unwrap(),
+            SavedCursor::Unreadable { .. }
+        ));
+    }
+
+    #[async_std::test]
+    async fn test_commitment_mismatch() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+
+        // Deploy hotshot contract
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Start a test Rollup instance
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            MOCK_VM_ID.into(),
+            alice,
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        // Serve a recorded chain instead of running HotShot.
+        let chain = mock_chain();
+        let mock = MockQueryService::start(chain);
+        let sequencer_url = mock.url();
+        let tmp_dir = TempDir::new().unwrap();
+
+        // Instead of running the HotShot commitment task, post a bogus commitment for block 0.
+        let bogus_commitment = U256::from(12345);
+        test_l1
+            .hotshot
+            .new_blocks(vec![bogus_commitment], vec![Bytes::default()])
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+
+        // Alerts go to an endpoint which records every notification, and to a buffer served by the
+        // API.
+        let received = Arc::new(Mutex::new(Vec::<Notification>::new()));
+        let hook_port = pick_unused_port().unwrap();
+        let mut hook = tide::new();
+        {
+            let received = received.clone();
+            hook.at("/hook").post(move |mut req: tide::Request<()>| {
+                let received = received.clone();
+                async move {
+                    received.lock().await.push(req.body_json().await?);
+                    Ok(tide::Response::new(200))
+                }
+            });
+        }
+        spawn(hook.listen(format!("0.0.0.0:{hook_port}")));
+        let alert_buffer = AlertBuffer::default();
+        let api_port = pick_unused_port().unwrap();
+        let api_options = APIOptions {
+            admin_token: Some("secret".parse().unwrap()),
+            alerts: alert_buffer.clone(),
+            ..APIOptions::new(api_port, sequencer_url.clone())
+        };
+        {
+            let state = test_rollup.state.clone();
+            spawn(async move { serve(&api_options, state, Default::default()).await });
+        }
+
+        let diagnostics_dir = tmp_dir.path().join("diagnostics");
+        let rollup_opt = ExecutorOptions {
+            output_stream: None,
+            webhooks: vec![WebhookConfig {
+                url: format!("http://localhost:{hook_port}/hook")
+                    .parse()
+                    .unwrap(),
+                events: vec![WebhookEvent::Alert],
+                secret: "secret".into(),
+            }],
+            alerts: Alerts::default().with(alert_buffer),
+            diagnostics_dir: diagnostics_dir.clone(),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url.clone())
+        };
+        let err = run_executor(&rollup_opt, test_rollup.state.clone(), Default::default())
+            .await
+            .unwrap_err();
+        let ExecutorError::CommitmentMismatch {
+            height: 0,
+            bundle: Some(bundle),
+        } = err
+        else {
+            panic!("unexpected error {err:?}");
+        };
+        assert!(bundle.starts_with(&diagnostics_dir));
+
+        let report: MismatchReport =
+            serde_json::from_reader(std::fs::File::open(bundle).unwrap()).unwrap();
+        assert_eq!(report.height, 0);
+        assert_eq!(report.contract_commitment, bogus_commitment);
+        assert_eq!(report.header_commitment, report.header.commit().to_string());
+        assert_eq!(
+            commitment_to_u256(report.header.commit()),
+            chain[0].commitment
+        );
+        assert_ne!(chain[0].commitment, bogus_commitment);
+        assert_eq!(report.sequencer_url, sequencer_url);
+        assert_eq!(report.l1_url, anvil.url());
+
+        // Nothing was executed.
+        assert_eq!(test_rollup.state.read().await.block_height(), 0);
+
+        // The mismatch was raised as an alert, which admins can read from the API.
+        let client =
+            Client::<ApiError>::new(format!("http://localhost:{api_port}").parse().unwrap());
+        client.connect(None).await;
+        let alerts: Vec<Alert> = client
+            .get("rollup/admin/alerts")
+            .header("Authorization", "Bearer secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(alerts.len(), 1, "{alerts:?}");
+        let alert = &alerts[0];
+        assert_eq!(alert.severity, Severity::Critical);
+        assert_eq!(alert.kind, AlertKind::CommitmentMismatch);
+        assert_eq!(alert.height, Some(0));
+        assert!(
+            alert.details.contains(&bogus_commitment.to_string()),
+            "{alert:?}"
+        );
+        let err = client
+            .get::<Vec<Alert>>("rollup/admin/alerts")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::Unauthorized);
+
+        // It was also posted to the webhook.
+        for _ in 0..300 {
+            if !received.lock().await.is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(
+            *received.lock().await,
+            [Notification::Alert {
+                alert: alert.clone()
+            }]
+        );
+    }
+
+    #[async_std::test]
+    async fn test_wrong_l1_chain() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let chain_id = provider.get_chainid().await.unwrap().as_u64();
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 60.into(), alice, bob, &test_l1).await;
+
+        // Configure the executor for a different chain than the one Anvil is running. The executor
+        // must fail before it signs anything, or even contacts the sequencer.
+        let rollup_opt = ExecutorOptions {
+            l1_chain_id: Some(chain_id + 1),
+            output_stream: None,
+            ..test_executor_options(
+                &anvil,
+                &test_l1,
+                &test_rollup,
+                "http://localhost:1".parse().unwrap(),
+            )
+        };
+        let status = StatusHandle::default();
+        let err = run_executor(&rollup_opt, test_rollup.state.clone(), status.clone())
+            .await
+            .unwrap_err();
+        assert!(!err.is_retryable());
+        let ExecutorError::WrongL1Chain {
+            url,
+            expected,
+            actual,
+        } = err
+        else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(url, anvil.url());
+        assert_eq!(expected, chain_id + 1);
+        assert_eq!(actual, chain_id);
+        assert_eq!(status.read().await.l1_chain_id, None);
+        assert_eq!(test_rollup.state.read().await.block_height(), 0);
+    }
+
+    #[async_std::test]
+    async fn test_diverged_from_contract() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+
+        // Deploy hotshot contract
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Start a test Rollup instance
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 80.into(), alice, bob, &test_l1).await;
+
+        // Start a test HotShot configuration
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        let rollup_opt = ExecutorOptions {
+            output_stream: None,
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+
+        // Hold the state lock so the executor cannot execute anything until a second writer has
+        // won the race to verify the first batch.
+        let state_lock = test_rollup.state.clone();
+        let paused = test_rollup.state.write().await;
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+
+        let num_blocks = loop {
+            let events = test_l1
+                .hotshot
+                .new_blocks_filter()
+                .address(test_l1.hotshot.address().into())
+                .from_block(0)
+                .query()
+                .await
+                .unwrap();
+            if let Some(event) = events.first() {
+                break event.num_blocks.as_u64();
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+        let racer = ExampleRollup::new(
+            test_rollup.contract.address(),
+            test_l1.clients.deployer.provider.clone(),
+        );
+        let bogus_commitment = U256::from(12345);
+        let proof = example_rollup::BatchProof {
+            first_block: U256::zero(),
+            last_block: U256::zero(),
+            old_state: racer.state_commitment().call().await.unwrap(),
+            new_state: bogus_commitment,
+        };
+        racer
+            .verify_blocks(U256::zero(), num_blocks, bogus_commitment, proof)
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        drop(paused);
+
+        let err = executor.await.unwrap_err();
+        let ExecutorError::DivergedFromContract { height, theirs, .. } = err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(height, num_blocks);
+        assert_eq!(theirs, bogus_commitment);
+        assert!(!err.is_retryable());
+    }
+
+    #[async_std::test]
+    async fn test_duplicate_submission() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            140.into(),
+            alice.clone(),
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+
+        // Two executors prove the same blocks from different L1 accounts. The second is held up
+        // until the first has proved its blocks, so every proof it sends is a duplicate.
+        let first_opt = ExecutorOptions {
+            output_stream: None,
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+        let second_opt = ExecutorOptions {
+            rollup_account_index: test_l1.clients.funded[1].index + 1,
+            ..first_opt.clone()
+        };
+        let gate = second_opt.hooks.submission_gate.clone();
+        let held = gate.write().await;
+        let first_state = test_rollup.state.clone();
+        let second_state = Arc::new(RwLock::new(State::from_initial_balances(
+            [(alice.address(), 9999)],
+            test_rollup.vm,
+        )));
+        let first =
+            spawn(async move { run_executor(&first_opt, first_state, Default::default()).await });
+        spawn(async move { run_executor(&second_opt, second_state, Default::default()).await });
+
+        let contract = &test_rollup.contract;
+        while contract.num_verified_blocks().call().await.unwrap() < 3.into() {
+            sleep(Duration::from_secs(1)).await;
+        }
+
+        // The contract rejects a second proof at a height it has already verified.
+        let proof = example_rollup::BatchProof {
+            first_block: U256::zero(),
+            last_block: U256::zero(),
+            old_state: U256::zero(),
+            new_state: U256::zero(),
+        };
+        let err = contract
+            .verify_blocks(U256::zero(), 1, U256::zero(), proof)
+            .call()
+            .await
+            .unwrap_err();
+        let rejected = err.decode_revert::<example_rollup::WrongHeight>().unwrap();
+        assert_eq!(rejected.height, U256::zero());
+        assert!(rejected.num_verified_blocks >= 3.into());
+
+        // Let the second executor send its duplicates, and stop the first. The second skips the
+        // blocks which were already verified and goes on to prove new ones.
+        drop(held);
+        first.cancel().await;
+        let verified = contract.num_verified_blocks().call().await.unwrap();
+        while contract.num_verified_blocks().call().await.unwrap() <= verified {
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    #[async_std::test]
+    async fn test_stale_proof_skipped() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            MOCK_VM_ID.into(),
+            alice,
+            bob,
+            &test_l1,
+        )
+        .await;
+        let chain = mock_chain();
+        let mock = MockQueryService::start(chain);
+        commit_blocks(&test_l1.hotshot, chain).await;
+
+        // Hold up the submitter until every block is executed, and allow it a single failure, so
+        // that it gives up rather than retrying a proof which can never be accepted.
+        let mut rollup_opt = mock_executor_options(&anvil, &test_l1, &test_rollup, &mock);
+        rollup_opt.fee_bump.max_attempts = 1;
+        let gate = rollup_opt.hooks.submission_gate.clone();
+        let held = gate.write().await;
+        let state_lock = test_rollup.state.clone();
+        let status = StatusHandle::default();
+        let executor_status = status.clone();
+        // Unsupervised, so the test fails if the executor exits instead of skipping.
+        let executor =
+            spawn(async move { run_executor(&rollup_opt, state_lock, executor_status).await });
+        test_rollup
+            .wait_for_effect(|state| state.block_height() == MOCK_CHAIN_LEN as u64)
+            .await;
+
+        // Prove the first two of the blocks out of band, as a previous run might have.
+        let contract = &test_rollup.contract;
+        let (proof, state_comm) = {
+            let state = test_rollup.state.read().await;
+            let proofs = (0..2)
+                .map(|height| state.proof(height).cloned().unwrap())
+                .collect::<Vec<_>>();
+            let summary = state.block_summary(1).unwrap();
+            (
+                BatchProof::generate(&proofs).unwrap(),
+                commitment_to_u256(summary.state_commitment),
+            )
+        };
+        contract
+            .verify_blocks(U256::zero(), 2, state_comm, proof.into())
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+
+        // The executor's proof of all the blocks is now stale. It proves only the rest instead.
+        drop(held);
+        while contract.num_verified_blocks().call().await.unwrap() < MOCK_CHAIN_LEN.into() {
+            sleep(Duration::from_millis(100)).await;
+        }
+        let updates = contract
+            .state_update_filter()
+            .from_block(0u64)
+            .query()
+            .await
+            .unwrap();
+        assert_eq!(
+            updates
+                .iter()
+                .map(|update| update.block_height)
+                .collect::<Vec<_>>(),
+            [2.into(), MOCK_CHAIN_LEN.into()]
+        );
+        assert_eq!(status.read().await.verified_height, MOCK_CHAIN_LEN as u64);
+        assert!(matches!(
+            status.read().await.verification(2),
+            Some(BlockVerification::Verified { .. })
+        ));
+        assert!(executor.cancel().await.is_none());
+    }
+
+    #[async_std::test]
+    async fn test_verify_block_proof() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+
+        // Deploy hotshot contract
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Start a test Rollup instance
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 40.into(), alice, bob, &test_l1).await;
+
+        // Start a test HotShot configuration
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        // Submit transaction to sequencer
+        let client: Client<ServerError> = Client::new(sequencer_url.clone());
+        let txn = test_rollup.test_transaction(100, 1).await;
+        client.connect(None).await;
+        client
+            .post::<()>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        // Spawn hotshot commitment and executor tasks
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+
+        let rollup_opt = ExecutorOptions {
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url.clone())
+        };
+
+        let mut prev_state = test_rollup.state.read().await.clone();
+        let mut exec_stream = test_rollup.subscribe_executor().await;
+        let state_lock = test_rollup.state.clone();
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
+
+        // Find the block which includes the transfer, and the state before it.
+        let (height, state) = loop {
+            let (height, state) = exec_stream.next().await.unwrap();
+            if state.get_balance(&test_rollup.bob.address()) == 100 {
+                break (height, state);
+            }
+            prev_state = state;
+        };
+        let proof = state.proof(height).unwrap().clone();
+
+        // Fetch the block from the query service, as an auditor would.
+        let availability: Client<ServerError> =
+            Client::new(sequencer_url.join("availability").unwrap());
+        let header: Header = availability
+            .get(&format!("header/{height}"))
+            .send()
+            .await
+            .unwrap();
+        let namespace_proof = availability
+            .get::<NamespaceProofQueryData>(&format!(
+                "block/{height}/namespace/{}",
+                u64::from(test_rollup.vm.id())
+            ))
+            .send()
+            .await
+            .unwrap()
+            .proof;
+        let root = header.transactions_root;
+        // The block picks up no deposits, messages or forced transactions.
+        let inputs = BlockInputs::default();
+
+        assert!(verify_block_proof(
+            &prev_state,
+            &root,
+            &namespace_proof,
+            &inputs,
+            &proof
+        ));
+
+        // A proof claiming a different result is rejected.
+        let mut tampered = proof.clone();
+        tampered.new_state = prev_state.commit();
+        assert!(!verify_block_proof(
+            &prev_state,
+            &root,
+            &namespace_proof,
+            &inputs,
+            &tampered
+        ));
+
+        // So is a proof checked against the wrong starting state.
+        assert!(!verify_block_proof(
+            &state,
+            &root,
+            &namespace_proof,
+            &inputs,
+            &proof
+        ));
+
+        // The published encodings check the same way, compressed or not.
+        for compress in [false, true] {
+            let encoded = EncodedProof::encode(&proof, compress);
+            assert!(verify_encoded_block_proof(
+                &prev_state,
+                &root,
+                &namespace_proof,
+                &inputs,
+                &encoded
+            ));
+            assert!(!verify_encoded_block_proof(
+                &state,
+                &root,
+                &namespace_proof,
+                &inputs,
+                &encoded
+            ));
+            let tampered = EncodedProof::encode(&tampered, compress);
+            assert!(!verify_encoded_block_proof(
+                &prev_state,
+                &root,
+                &namespace_proof,
+                &inputs,
+                &tampered
+            ));
+        }
+    }
+
+    #[async_std::test]
+    async fn test_submitter_failover() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil(Default::default()).await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
+
+        // Deploy hotshot contract
+        let provider = create_provider(&anvil.url());
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Start a test Rollup instance
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            90.into(),
+            alice.clone(),
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        // Start a test HotShot configuration
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
 
-                // Ensure the executor's state commitment matches the contract.
-                let contract_comm = self
-                    .contract
-                    .state_commitment()
-                    .block(log.block_number)
-                    .call()
-                    .await
-                    .unwrap();
-                tracing::info!(
-                    "rollup {vm_id:?} contract commitment at block {} is {contract_comm}",
-                    log.block_number
-                );
-                assert_eq!(commitment_to_u256(state.commit()), contract_comm);
+        // Two executors with different L1 accounts, each with its own copy of the state.
+        let lease_blocks = 10;
+        let leader_opt = ExecutorOptions {
+            output_stream: None,
+            submitter_lease_blocks: Some(lease_blocks),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+        let follower_opt = ExecutorOptions {
+            rollup_account_index: test_l1.clients.funded[1].index + 1,
+            ..leader_opt.clone()
+        };
+        let follower_state = Arc::new(RwLock::new(State::from_initial_balances(
+            [(alice.address(), 9999)],
+            test_rollup.vm,
+        )));
 
-                // If the predicate is satisfied, finish up.
-                if predicate(state) {
-                    break;
-                }
+        // Start the leader and wait for it to take the lease and prove some blocks.
+        let leader_state = test_rollup.state.clone();
+        let leader_task =
+            spawn(async move { run_executor(&leader_opt, leader_state, Default::default()).await });
+        let contract = &test_rollup.contract;
+        let leader = loop {
+            let holder = contract.current_submitter().call().await.unwrap();
+            let verified = contract.num_verified_blocks().call().await.unwrap();
+            if holder != Address::zero() && verified > U256::zero() {
+                break holder;
             }
+            sleep(Duration::from_secs(1)).await;
+        };
+
+        // While the leader is running, the follower does not take over.
+        spawn(async move { run_executor(&follower_opt, follower_state, Default::default()).await });
+        let verified = contract.num_verified_blocks().call().await.unwrap();
+        while contract.num_verified_blocks().call().await.unwrap() <= verified {
+            sleep(Duration::from_secs(1)).await;
         }
+        assert_eq!(contract.current_submitter().call().await.unwrap(), leader);
 
-        pub async fn test_transaction(
-            &self,
-            amount: Amount,
-            nonce: Nonce,
-        ) -> sequencer::Transaction {
-            let txn = Transaction {
-                amount,
-                destination: self.bob.address(),
-                nonce,
-            };
-            let txn = SignedTransaction::new(txn, &self.alice).await;
-            self.vm.wrap(&txn)
+        // Kill the leader. The follower takes over once the lease runs out, and resumes proving
+        // from wherever the leader left off.
+        leader_task.cancel().await;
+        let expiry = contract.lease_expiry().call().await.unwrap().as_u64();
+        let verified = contract.num_verified_blocks().call().await.unwrap();
+        let provider = create_provider(&anvil.url());
+        loop {
+            let holder = contract.current_submitter().call().await.unwrap();
+            if holder != Address::zero() && holder != leader {
+                break;
+            }
+            assert!(
+                provider.get_block_number().await.unwrap().as_u64() <= expiry + lease_blocks,
+                "follower did not take over within a lease of the old one running out"
+            );
+            sleep(Duration::from_secs(1)).await;
+        }
+        while contract.num_verified_blocks().call().await.unwrap() <= verified {
+            assert!(
+                provider.get_block_number().await.unwrap().as_u64() <= expiry + 2 * lease_blocks,
+                "follower did not submit a proof after taking over"
+            );
+            sleep(Duration::from_secs(1)).await;
         }
     }
 
-    async fn start_query_service<N: network::Type>(
-        port: u16,
-        storage_path: PathBuf,
-        node: SystemContextHandle<SeqTypes, Node<N>>,
-    ) {
-        let init_handle = Box::new(move |_| {
-            ready(SequencerContext::new(
-                node,
-                0,
-                Default::default(),
-                Default::default(),
-                None,
-            ))
-            .boxed()
-        });
-        Options::from(Http { port })
-            .submit(Default::default())
-            .status(Default::default())
-            .query_fs(Default::default(), fs::Options { path: storage_path })
-            .serve(init_handle)
-            .await
-            .unwrap();
-    }
+    #[async_std::test]
+    async fn test_submitter_race() {
+        use ethers::abi::AbiDecode;
 
-    async fn spawn_anvil() -> Anvil {
-        let anvil = AnvilOptions::default()
-            .block_time(Duration::from_secs(1))
-            .spawn()
-            .await;
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = spawn_anvil().await;
+        let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
+        let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
 
-        // When we are running a local Anvil node, as in tests, some endpoints (e.g. eth_feeHistory)
-        // do not work until at least one block has been mined. Wait until the fee history endpoint
-        // works.
+        // Deploy hotshot contract
         let provider = create_provider(&anvil.url());
-        while let Err(err) = provider.fee_history(1, BlockNumber::Latest, &[]).await {
-            tracing::warn!("RPC is not ready: {err}");
+        let test_l1 = TestL1System::deploy(provider).await.unwrap();
+
+        // Start a test Rollup instance
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            90.into(),
+            alice.clone(),
+            bob,
+            &test_l1,
+        )
+        .await;
+
+        // Start a test HotShot configuration
+        let sequencer_port = pick_unused_port().unwrap();
+        let nodes = init_hotshot_handles().await;
+        let api_node = nodes[0].clone();
+        let tmp_dir = TempDir::new().unwrap();
+        let storage_path = tmp_dir.path().join("tmp_storage");
+        start_query_service(sequencer_port, storage_path, api_node).await;
+        for node in &nodes {
+            node.hotshot.start_consensus().await;
+        }
+        let sequencer_url: Url = format!("http://localhost:{sequencer_port}")
+            .parse()
+            .unwrap();
+
+        let hotshot_opt = CommitmentTaskOptions {
+            l1_provider: anvil.url(),
+            sequencer_mnemonic: TEST_MNEMONIC.to_string(),
+            sequencer_account_index: test_l1.clients.funded[0].index,
+            hotshot_address: test_l1.hotshot.address(),
+            l1_chain_id: None,
+            query_service_url: Some(sequencer_url.clone()),
+            delay: None,
+        };
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+
+        // Two executors with different L1 accounts, each with its own copy of the state, start at
+        // the same time and race for the lease on the deployed contract.
+        let first_opt = ExecutorOptions {
+            output_stream: None,
+            submitter_lease_blocks: Some(10),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+        let second_opt = ExecutorOptions {
+            rollup_account_index: test_l1.clients.funded[1].index + 1,
+            ..first_opt.clone()
+        };
+        let first_state = test_rollup.state.clone();
+        let second_state = Arc::new(RwLock::new(State::from_initial_balances(
+            [(alice.address(), 9999)],
+            test_rollup.vm,
+        )));
+        let first_task =
+            spawn(async move { run_executor(&first_opt, first_state, Default::default()).await });
+        let second_task =
+            spawn(async move { run_executor(&second_opt, second_state, Default::default()).await });
+
+        // Let both run for long enough to prove several blocks and renew the lease.
+        let contract = &test_rollup.contract;
+        while contract.num_verified_blocks().call().await.unwrap() < U256::from(5) {
             sleep(Duration::from_secs(1)).await;
         }
+        let holder = contract.current_submitter().call().await.unwrap();
+        assert_ne!(holder, Address::zero());
+
+        // Only the winner of the race submitted proofs: the loser neither paid for a proof which
+        // was rejected nor got one accepted.
+        let provider = create_provider(&anvil.url());
+        let updates = contract
+            .state_update_filter()
+            .from_block(0)
+            .address(contract.address().into())
+            .query_with_meta()
+            .await
+            .unwrap();
+        assert!(!updates.is_empty());
+        for (update, meta) in updates {
+            let tx = provider
+                .get_transaction(meta.transaction_hash)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                tx.from, holder,
+                "proof of block {} submitted by the executor without the lease",
+                update.block_height
+            );
+        }
+        let latest = provider.get_block_number().await.unwrap().as_u64();
+        for number in 0..=latest {
+            let block = provider.get_block_with_txs(number).await.unwrap().unwrap();
+            for tx in block.transactions {
+                if tx.to != Some(contract.address()) || tx.from == holder {
+                    continue;
+                }
+                assert!(
+                    !matches!(
+                        example_rollup::ExampleRollupCalls::decode(&tx.input),
+                        Ok(example_rollup::ExampleRollupCalls::VerifyBlocks(_))
+                    ),
+                    "the executor without the lease sent a proof in L1 block {number}"
+                );
+            }
+        }
 
-        anvil
+        first_task.cancel().await;
+        second_task.cancel().await;
     }
 
-    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
     #[async_std::test]
-    async fn test_execute() {
+    async fn test_rotate_submitter() {
         setup_logging();
         setup_backtrace();
 
-        let anvil = spawn_anvil().await;
+        let anvil = spawn_anvil(Default::default()).await;
         let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
         let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
 
@@ -437,8 +7265,14 @@ mod test {
         let test_l1 = TestL1System::deploy(provider).await.unwrap();
 
         // Start a test Rollup instance
-        let test_rollup =
-            TestRollupInstance::launch(anvil.url().clone(), 10.into(), alice, bob, &test_l1).await;
+        let test_rollup = TestRollupInstance::launch(
+            anvil.url().clone(),
+            130.into(),
+            alice.clone(),
+            bob,
+            &test_l1,
+        )
+        .await;
 
         // Start a test HotShot configuration
         let sequencer_port = pick_unused_port().unwrap();
@@ -454,19 +7288,6 @@ mod test {
             .parse()
             .unwrap();
 
-        // Submit transaction to sequencer
-        let client: Client<ServerError> = Client::new(sequencer_url.clone());
-        let txn = test_rollup.test_transaction(100, 1).await;
-        client.connect(None).await;
-        client
-            .post::<()>("submit/submit")
-            .body_json(&txn)
-            .unwrap()
-            .send()
-            .await
-            .unwrap();
-
-        // Spawn hotshot commitment and executor tasks
         let hotshot_opt = CommitmentTaskOptions {
             l1_provider: anvil.url(),
             sequencer_mnemonic: TEST_MNEMONIC.to_string(),
@@ -476,59 +7297,118 @@ mod test {
             query_service_url: Some(sequencer_url.clone()),
             delay: None,
         };
+        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
 
-        let rollup_opt = ExecutorOptions {
-            sequencer_url,
-            rollup_account_index: test_l1.clients.funded[1].index,
-            l1_http_provider: anvil.url(),
-            l1_ws_provider: anvil.ws_url(),
-            rollup_mnemonic: TEST_MNEMONIC.to_string(),
-            hotshot_address: test_l1.hotshot.address(),
-            rollup_address: test_rollup.contract.address(),
-            output_stream: Some(test_rollup.executor_send.clone()),
+        // The contract owner authorizes the first executor's key.
+        let contract = &test_rollup.contract;
+        let old_opt = ExecutorOptions {
+            output_stream: None,
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
         };
+        let old_submitter = wallet_address(TEST_MNEMONIC, old_opt.rollup_account_index);
+        contract
+            .set_submitter(old_submitter)
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(
+            contract.authorized_submitter().call().await.unwrap(),
+            old_submitter
+        );
 
-        let state_lock = test_rollup.state.clone();
-        spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
-        spawn(async move { run_executor(&rollup_opt, state_lock).await });
+        // Run it until it has proved some blocks.
+        let old_state = test_rollup.state.clone();
+        let task_opt = old_opt.clone();
+        let old_task =
+            spawn(async move { run_executor(&task_opt, old_state, Default::default()).await });
+        while contract.num_verified_blocks().call().await.unwrap() == U256::zero() {
+            sleep(Duration::from_secs(1)).await;
+        }
 
-        // Wait for the rollup contract to process all state updates
-        test_rollup
-            .wait_for_effect(|state| {
-                let bob_balance = state.get_balance(&test_rollup.bob.address());
-                tracing::info!("Bob's balance is {bob_balance}/100");
-                bob_balance == 100
-            })
-            .await;
+        // Rotate in a key from a different mnemonic, funded by the owner.
+        let new_mnemonic =
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+            abandon abandon about";
+        let new_submitter = wallet_address(new_mnemonic, 0);
+        let owner = &test_l1.clients.deployer.provider;
+        owner
+            .send_transaction(
+                TransactionRequest::new()
+                    .to(new_submitter)
+                    .value(U256::exp10(18)),
+                None,
+            )
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        contract
+            .set_submitter(new_submitter)
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        let rotated_at = contract.num_verified_blocks().call().await.unwrap();
+
+        // The old executor halts the next time it tries to prove a block, and cannot be restarted
+        // with the old key.
+        let err = old_task.await.unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ExecutorError::NotAuthorizedSubmitter { ours, authorized }
+                    if ours == old_submitter && authorized == new_submitter
+            ),
+            "{err}"
+        );
+        let restart_state = Arc::new(RwLock::new(State::from_initial_balances(
+            [(alice.address(), 9999)],
+            test_rollup.vm,
+        )));
+        let err = run_executor(&old_opt, restart_state, Default::default())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ExecutorError::NotAuthorizedSubmitter { .. }),
+            "{err}"
+        );
+
+        // An executor with the new key picks up where the old one left off.
+        let new_opt = ExecutorOptions {
+            rollup_mnemonic: new_mnemonic.to_string(),
+            rollup_account_index: 0,
+            ..old_opt
+        };
+        let new_state = Arc::new(RwLock::new(State::from_initial_balances(
+            [(alice.address(), 9999)],
+            test_rollup.vm,
+        )));
+        spawn(async move { run_executor(&new_opt, new_state, Default::default()).await });
+        while contract.num_verified_blocks().call().await.unwrap() <= rotated_at {
+            sleep(Duration::from_secs(1)).await;
+        }
     }
 
     #[async_std::test]
-    async fn test_execute_multi_rollup() {
+    async fn test_verification_status() {
         setup_logging();
         setup_backtrace();
 
-        let anvil = spawn_anvil().await;
         let alice = LocalWallet::new(&mut ChaChaRng::seed_from_u64(0));
         let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
-        // Deploy hotshot contract
+
+        // Start a test HotShot and Rollup contract.
+        let anvil = spawn_anvil(Default::default()).await;
         let provider = create_provider(&anvil.url());
         let test_l1 = TestL1System::deploy(provider).await.unwrap();
+        let test_rollup =
+            TestRollupInstance::launch(anvil.url().clone(), 30.into(), alice, bob, &test_l1).await;
 
-        // Start test Rollup instances
-        let num_rollups = 3;
-        let mut test_rollups = Vec::new();
-        for i in 1..num_rollups + 1 {
-            // To keep nonces consistent for the underlying provider, we must await these iteratively
-            let test_rollup = TestRollupInstance::launch(
-                anvil.url().clone(),
-                i.into(),
-                alice.clone(),
-                bob.clone(),
-                &test_l1,
-            )
-            .await;
-            test_rollups.push(test_rollup);
-        }
+        // Slow down the L1, so that proof transactions stay pending long enough to observe.
+        anvil.set_block_time(Duration::from_secs(5)).await;
 
         // Start a test HotShot configuration
         let sequencer_port = pick_unused_port().unwrap();
@@ -544,21 +7424,7 @@ mod test {
             .parse()
             .unwrap();
 
-        // Submit transactions to sequencer
-        let client: Client<ServerError> = Client::new(sequencer_url.clone());
-        for i in 0..num_rollups {
-            let txn = test_rollups[i as usize].test_transaction(100, 1).await;
-            client.connect(None).await;
-            client
-                .post::<()>("submit/submit")
-                .body_json(&txn)
-                .unwrap()
-                .send()
-                .await
-                .unwrap();
-        }
-
-        // Spawn hotshot commitment task
+        // Spawn hotshot commitment and executor tasks
         let hotshot_opt = CommitmentTaskOptions {
             l1_provider: anvil.url(),
             sequencer_mnemonic: TEST_MNEMONIC.to_string(),
@@ -568,33 +7434,49 @@ mod test {
             query_service_url: Some(sequencer_url.clone()),
             delay: None,
         };
+
+        let rollup_opt = ExecutorOptions {
+            output_stream: None,
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
+        };
+
+        let state_lock = test_rollup.state.clone();
+        let status = StatusHandle::default();
+        let executor_status = status.clone();
         spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
+        spawn(async move { run_executor(&rollup_opt, state_lock, executor_status).await });
 
-        // Spawn all rollup executors
-        for test_rollup in &test_rollups {
-            let state_lock = test_rollup.state.clone();
-            let rollup_opt = ExecutorOptions {
-                sequencer_url: sequencer_url.clone(),
-                rollup_account_index: test_l1.clients.funded[1].index,
-                l1_http_provider: anvil.url(),
-                l1_ws_provider: anvil.ws_url(),
-                rollup_mnemonic: TEST_MNEMONIC.to_string(),
-                hotshot_address: test_l1.hotshot.address(),
-                rollup_address: test_rollup.contract.address(),
-                output_stream: Some(test_rollup.executor_send.clone()),
-            };
-            spawn(async move { run_executor(&rollup_opt, state_lock).await });
-        }
+        // Wait for the proof of the first block to be sent.
+        let l1_tx = loop {
+            match status.read().await.verification(0) {
+                Some(BlockVerification::Submitted { l1_tx }) => break l1_tx,
+                Some(BlockVerification::Verified { .. }) => {
+                    panic!("block was verified before its proof was seen pending")
+                }
+                _ => sleep(Duration::from_millis(100)).await,
+            }
+        };
+        tracing::info!("proof of block 0 submitted in {l1_tx:?}");
 
-        // Wait for all rollup contracts to process state updates
-        join_all(test_rollups.iter().map(|test_rollup| {
-            test_rollup.wait_for_effect(|state| {
-                let bob_balance = state.get_balance(&test_rollup.bob.address());
-                tracing::info!("Bob's balance is {bob_balance}/100");
-                bob_balance == 100
-            })
-        }))
-        .await;
+        // Wait for Anvil to mine the proof transaction.
+        let l1_block = loop {
+            match status.read().await.verification(0) {
+                Some(BlockVerification::Verified {
+                    l1_tx: verified_tx,
+                    l1_block,
+                }) => {
+                    assert_eq!(verified_tx, l1_tx);
+                    break l1_block;
+                }
+                _ => sleep(Duration::from_millis(100)).await,
+            }
+        };
+        let receipt = create_provider(&anvil.url())
+            .get_transaction_receipt(l1_tx)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.block_number.unwrap().as_u64(), l1_block);
     }
 
     #[async_std::test]
@@ -609,17 +7491,14 @@ mod test {
         let bob = LocalWallet::new(&mut ChaChaRng::seed_from_u64(1));
 
         // Start a test HotShot and Rollup contract.
-        let mut anvil = spawn_anvil().await;
+        let anvil = spawn_anvil(Default::default()).await;
         let provider = create_provider(&anvil.url());
         let test_l1 = TestL1System::deploy(provider).await.unwrap();
-        let mut test_rollup =
+        let test_rollup =
             TestRollupInstance::launch(anvil.url().clone(), 20.into(), alice, bob, &test_l1).await;
 
-        // Once the contracts have been deployed, restart the L1 with a slow block time.
-        anvil
-            .restart(AnvilOptions::default().block_time(Duration::from_secs(5)))
-            .await;
-        test_rollup.reset_socket_connnection().await;
+        // Once the contracts have been deployed, slow the L1 down.
+        anvil.set_block_time(Duration::from_secs(5)).await;
 
         // Start a test HotShot configuration
         let sequencer_port = pick_unused_port().unwrap();
@@ -648,19 +7527,12 @@ mod test {
         };
 
         let rollup_opt = ExecutorOptions {
-            sequencer_url,
-            l1_http_provider: anvil.url(),
-            l1_ws_provider: anvil.ws_url(),
-            rollup_account_index: test_l1.clients.funded[1].index,
-            rollup_mnemonic: TEST_MNEMONIC.to_string(),
-            hotshot_address: test_l1.hotshot.address(),
-            rollup_address: test_rollup.contract.address(),
-            output_stream: Some(test_rollup.executor_send.clone()),
+            ..test_executor_options(&anvil, &test_l1, &test_rollup, sequencer_url)
         };
 
         let state_lock = test_rollup.state.clone();
         spawn(async move { run_hotshot_commitment_task(&hotshot_opt).await });
-        spawn(async move { run_executor(&rollup_opt, state_lock).await });
+        spawn(async move { run_executor(&rollup_opt, state_lock, Default::default()).await });
 
         // Submit transactions to sequencer
         for nonce in 1..=num_txns {
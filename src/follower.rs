@@ -0,0 +1,214 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Keeping the state of an API node up to date with an executor running elsewhere.
+//!
+//! An API node does not execute the rollup on its own, since it has no L1 to read deposits and
+//! verified heights from. Instead it follows an executor, either by loading each newer snapshot the
+//! executor writes to a shared directory, or by replaying each block the executor reports on its
+//! block stream, fetching the block from the query service. A replayed block must lead to the state
+//! commitment the executor reports, or the follower stops: an API node never serves a state the
+//! executor did not reach.
+
+use async_std::sync::{Arc, RwLock};
+use async_std::task::sleep;
+use commit::Committable;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use surf_disco::{Client, Url};
+
+use crate::backfill::fetch_block;
+use crate::cache::ReadCache;
+use crate::commitments::commitment_to_u256;
+use crate::error::{ApiError, ExecutorError};
+use crate::logging::NODE;
+use crate::replicas::QueryReplicas;
+use crate::retention::RetentionConfig;
+use crate::snapshot::{list_snapshots, load_latest_snapshot};
+use crate::state::{BlockSummary, State};
+use crate::status::StatusHandle;
+
+/// Where an API node learns of the blocks the executor has executed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateSource {
+    /// The snapshots the executor writes to this directory.
+    Snapshots(PathBuf),
+    /// The block stream of the executor's API at this URL.
+    Executor(Url),
+}
+
+#[derive(Clone, Debug)]
+pub struct FollowerOptions {
+    pub source: StateSource,
+    /// URL of a HotShot query service, from which blocks on the executor's block stream are
+    /// fetched.
+    pub sequencer_url: Url,
+    /// Time between checks for a newer snapshot or block.
+    pub interval: Duration,
+    /// How much history the node keeps of the blocks it replays.
+    pub retention: RetentionConfig,
+}
+
+/// Follow the executor, updating `state`, `status` and `cache` as it executes blocks.
+///
+/// This only returns on an error which following again would not fix, such as a block which does
+/// not reproduce the executor's state.
+pub async fn run_follower(
+    opt: &FollowerOptions,
+    state: Arc<RwLock<State>>,
+    status: StatusHandle,
+    cache: Option<ReadCache>,
+) -> Result<(), ExecutorError> {
+    match &opt.source {
+        StateSource::Snapshots(dir) => {
+            follow_snapshots(dir, opt.interval, &state, &status, cache.as_ref()).await
+        }
+        StateSource::Executor(url) => {
+            follow_executor(url, opt, &state, &status, cache.as_ref()).await
+        }
+    }
+}
+
+async fn follow_snapshots(
+    dir: &Path,
+    interval: Duration,
+    state: &RwLock<State>,
+    status: &StatusHandle,
+    cache: Option<&ReadCache>,
+) -> Result<(), ExecutorError> {
+    let vm = state.read().await.vm;
+    loop {
+        let height = state.read().await.block_height();
+        // Only read a snapshot once there is a newer one, since each is the whole state.
+        let newest = list_snapshots(dir)
+            .ok()
+            .and_then(|snapshots| snapshots.last().map(|(height, _)| *height));
+        if newest > Some(height) {
+            if let Some(snapshot) =
+                load_latest_snapshot(dir, &vm).filter(|snapshot| snapshot.block_height() > height)
+            {
+                let mut state = state.write().await;
+                *state = snapshot;
+                advance(&state, status, cache).await;
+            }
+        }
+        sleep(interval).await;
+    }
+}
+
+async fn follow_executor(
+    url: &Url,
+    opt: &FollowerOptions,
+    state: &RwLock<State>,
+    status: &StatusHandle,
+    cache: Option<&ReadCache>,
+) -> Result<(), ExecutorError> {
+    let executor = Client::<ApiError>::new(url.clone());
+    let hotshot = QueryReplicas::new(&[opt.sequencer_url.clone()]);
+    executor.connect(None).await;
+    hotshot.connect(None).await;
+
+    loop {
+        let from = state.read().await.block_height();
+        let page: Vec<BlockSummary> = executor
+            .get(&format!("rollup/stream/blocks/{from}"))
+            .send()
+            .await
+            .map_err(|err| ExecutorError::ExecutorApi {
+                reason: err.to_string(),
+            })?;
+        if page.is_empty() {
+            sleep(opt.interval).await;
+            continue;
+        }
+        for summary in page {
+            // The follower is the only writer, so the state does not change while the block is
+            // fetched.
+            let block = fetch_block(&hotshot, &*state.read().await, summary.height).await?;
+            let mut state = state.write().await;
+            let result = state.record_block(
+                &block.header.transactions_root,
+                &block.namespace_proof,
+                summary.timestamp,
+                block.total_payloads,
+                &block.inputs,
+            );
+            if result.summary.state_commitment != summary.state_commitment {
+                return Err(ExecutorError::FollowerDiverged {
+                    height: summary.height,
+                    ours: commitment_to_u256(state.commit()),
+                    theirs: commitment_to_u256(summary.state_commitment),
+                });
+            }
+            let height = state.block_height();
+            state.prune(&opt.retention, height);
+            advance(&state, status, cache).await;
+        }
+    }
+}
+
+/// Report that `state` has moved to a new height.
+async fn advance(state: &State, status: &StatusHandle, cache: Option<&ReadCache>) {
+    let height = state.block_height();
+    status.write().await.local_height = height;
+    if let Some(cache) = cache {
+        cache.refresh(state);
+    }
+    tracing::debug!(target: NODE, height, "following the executor at height {height}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::SeedIdentity;
+    use crate::snapshot::write_snapshot;
+    use crate::RollupVM;
+    use async_std::task::spawn;
+    use ethers::signers::Signer;
+    use portpicker::pick_unused_port;
+    use tempfile::TempDir;
+
+    #[async_std::test]
+    async fn test_follow_snapshots() {
+        let dir = TempDir::new().unwrap();
+        let address = SeedIdentity::Alice.wallet().address();
+        let genesis = State::from_initial_balances([(address, 100)], RollupVM::new(1.into()));
+        let state = Arc::new(RwLock::new(genesis.clone()));
+        let status = StatusHandle::default();
+        let cache = ReadCache::default();
+        let opt = FollowerOptions {
+            source: StateSource::Snapshots(dir.path().into()),
+            sequencer_url: format!("http://localhost:{}", pick_unused_port().unwrap())
+                .parse()
+                .unwrap(),
+            interval: Duration::from_millis(10),
+            retention: Default::default(),
+        };
+        {
+            let state = state.clone();
+            let status = status.clone();
+            spawn(async move { run_follower(&opt, state, status, Some(cache)).await });
+        }
+
+        // The executor writes a snapshot a few blocks on.
+        let mut executed = genesis;
+        for height in 0..3 {
+            let block = sequencer_utils::u256_to_commitment(height.into()).unwrap();
+            executed.apply_block_payloads(block, Vec::<sequencer::Transaction>::new());
+        }
+        write_snapshot(dir.path(), &executed, 2).unwrap();
+
+        for _ in 0..100 {
+            if state.read().await.block_height() == 3 {
+                break;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(state.read().await.commit(), executed.commit());
+        assert_eq!(status.read().await.local_height, 3);
+        assert_eq!(state.read().await.get_balance(&address), 100);
+    }
+}
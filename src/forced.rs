@@ -0,0 +1,211 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Forced inclusion of rollup transactions through L1.
+//!
+//! Anyone may call `enqueueTransaction` on the rollup contract with the encoding of a signed rollup
+//! transaction, and the contract logs it in a `TransactionEnqueued` event. On a rollup whose
+//! [`ChainConfig`](crate::state::ChainConfig::forced_inclusion) enables forced inclusion, each
+//! rollup block applies the transactions enqueued in the finalized L1 blocks it references which
+//! earlier blocks have not, as described under
+//! [`State::pending_forced`](crate::state::State::pending_forced), so every executor applies the
+//! same ones in the same rollup block. They are applied before the block's sequenced transactions,
+//! in the order they were enqueued, and count against the same weight budget. A transaction the
+//! sequencer will not take, because it is down or censoring, still executes, at the price of an L1
+//! transaction and of waiting for L1 finality.
+//!
+//! The contract does not look at what is enqueued. An enqueued transaction which does not decode
+//! is skipped.
+//!
+//! When the API cannot reach the sequencer, the submit route can answer with an [`L1Fallback`]: the
+//! call to make instead, with what it costs and how long it takes.
+
+use contract_bindings::example_rollup::{EnqueueTransactionCall, ExampleRollup};
+use ethers::{
+    abi::AbiEncode,
+    providers::Middleware,
+    types::{Address, Bytes, H256},
+};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+use crate::error::ExecutorError;
+use crate::transaction::RollupTransaction;
+
+/// Gas of an L1 transaction before its calldata and execution are paid for.
+const INTRINSIC_GAS: u64 = 21_000;
+
+/// Gas allowed for `enqueueTransaction` beyond its calldata and its event, for decoding the
+/// calldata and copying it to memory. This is more than a transaction of the largest size takes.
+const ENQUEUE_EXECUTION_GAS: u64 = 10_000;
+
+/// A transaction enqueued on the rollup contract for forced inclusion.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnqueuedTransaction {
+    /// The L1 account which enqueued the transaction, which need not be its signer.
+    pub sender: Address,
+    /// The encoded rollup transaction, as enqueued.
+    pub transaction: Bytes,
+    /// The L1 block the transaction was enqueued in.
+    pub l1_block: u64,
+}
+
+impl EnqueuedTransaction {
+    /// The rollup transaction enqueued, if it decodes.
+    pub fn decode(&self) -> Option<RollupTransaction> {
+        RollupTransaction::decode(&self.transaction)
+    }
+}
+
+/// The transactions enqueued in a range of L1 blocks, in the order they were enqueued.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1Queue {
+    /// The last L1 block of the range.
+    pub through: u64,
+    pub transactions: Vec<EnqueuedTransaction>,
+}
+
+/// Fetch the transactions enqueued on `rollup_contract` in the L1 blocks `blocks`.
+pub async fn fetch_enqueued<M: Middleware>(
+    rollup_contract: &ExampleRollup<M>,
+    blocks: RangeInclusive<u64>,
+) -> Result<L1Queue, ExecutorError> {
+    let mut events = rollup_contract
+        .transaction_enqueued_filter()
+        .from_block(*blocks.start())
+        .to_block(*blocks.end())
+        .address(rollup_contract.address().into())
+        .query_with_meta()
+        .await
+        .map_err(|err| ExecutorError::L1 {
+            reason: format!("unable to fetch enqueued transactions in L1 blocks {blocks:?}: {err}"),
+        })?;
+    events.sort_by_key(|(_, meta)| (meta.block_number, meta.log_index));
+    Ok(L1Queue {
+        through: *blocks.end(),
+        transactions: events
+            .into_iter()
+            .map(|(event, meta)| EnqueuedTransaction {
+                sender: event.sender,
+                transaction: event.transaction,
+                l1_block: meta.block_number.as_u64(),
+            })
+            .collect(),
+    })
+}
+
+/// Calldata of a call to `enqueueTransaction` which enqueues `transaction`.
+pub fn enqueue_calldata(transaction: &RollupTransaction) -> Bytes {
+    EnqueueTransactionCall {
+        transaction: transaction.encode().into(),
+    }
+    .encode()
+    .into()
+}
+
+/// Gas an L1 transaction with `calldata` to `enqueueTransaction` takes, about.
+///
+/// This counts the calldata at its EIP-2028 price and the event at the price of a log with one
+/// topic and the same data, plus [`ENQUEUE_EXECUTION_GAS`], so it errs on the high side.
+pub fn enqueue_gas(calldata: &[u8]) -> u64 {
+    let calldata_gas: u64 = calldata
+        .iter()
+        .map(|byte| if *byte == 0 { 4 } else { 16 })
+        .sum();
+    // The event logs the sender and the transaction, which are encoded like the call's arguments
+    // with one more word, and the selector left out.
+    let log_data = calldata.len().saturating_sub(4) as u64 + 32;
+    let log_gas = 375 + 375 + 8 * log_data;
+    INTRINSIC_GAS + calldata_gas + log_gas + ENQUEUE_EXECUTION_GAS
+}
+
+/// How to have a transaction included through L1, returned by the submit route in place of a
+/// submission when the sequencer cannot be reached.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1Fallback {
+    /// Why the transaction was not forwarded to the sequencer.
+    pub reason: String,
+    /// Hash of the rollup transaction.
+    pub hash: H256,
+    /// The rollup contract, which the L1 transaction calls.
+    pub contract: Address,
+    /// Calldata of the L1 transaction, which calls `enqueueTransaction`.
+    pub calldata: Bytes,
+    /// Gas the L1 transaction takes, about, from [`enqueue_gas`].
+    pub estimated_gas: u64,
+    /// How long the transaction takes to execute once enqueued.
+    pub latency: String,
+    /// What the transaction costs once enqueued.
+    pub fee: String,
+}
+
+impl L1Fallback {
+    /// The fallback for `transaction`, which could not be forwarded for `reason`, on the rollup
+    /// whose contract is `contract`.
+    pub fn new(reason: String, transaction: &RollupTransaction, contract: Address) -> Self {
+        let calldata = enqueue_calldata(transaction);
+        let estimated_gas = enqueue_gas(&calldata);
+        Self {
+            reason,
+            hash: transaction.hash(),
+            contract,
+            calldata,
+            estimated_gas,
+            latency: "The transaction executes in the first rollup block which references a \
+                finalized L1 block at or after the one which includes the L1 transaction. On \
+                Ethereum, L1 blocks are finalized after about two epochs, or 13 minutes."
+                .into(),
+            fee: format!(
+                "The sender of the L1 transaction pays for about {estimated_gas} gas at the L1 \
+                gas price. The rollup transaction still pays its own priority, and is executed \
+                like any other, so it may still fail, for example on its nonce."
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{SignedTransaction, Transaction, DEFAULT_VM_ID};
+    use ethers::{abi::AbiDecode, signers::LocalWallet};
+
+    #[async_std::test]
+    async fn test_enqueue_calldata() {
+        let wallet = LocalWallet::new(&mut rand::thread_rng());
+        let transaction: RollupTransaction = SignedTransaction::new_with_wallet(
+            Transaction {
+                amount: 1,
+                destination: Address::random(),
+                nonce: 1,
+                priority: 0,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            },
+            &wallet,
+        )
+        .await
+        .into();
+        let calldata = enqueue_calldata(&transaction);
+        let call = EnqueueTransactionCall::decode(&calldata).unwrap();
+        let enqueued = EnqueuedTransaction {
+            sender: Address::random(),
+            transaction: call.transaction,
+            l1_block: 1,
+        };
+        assert_eq!(enqueued.decode().unwrap().hash(), transaction.hash());
+
+        // Larger transactions cost more to enqueue, and each costs more than the fixed gas.
+        let gas = enqueue_gas(&calldata);
+        assert!(gas > INTRINSIC_GAS + ENQUEUE_EXECUTION_GAS);
+        assert!(enqueue_gas(&[calldata.to_vec(), vec![1; 32]].concat()) > gas);
+
+        let fallback = L1Fallback::new("down".into(), &transaction, Address::random());
+        assert_eq!(fallback.hash, transaction.hash());
+        assert_eq!(fallback.calldata, calldata);
+        assert_eq!(fallback.estimated_gas, gas);
+    }
+}
@@ -0,0 +1,348 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! HTTP front door for the rollup API.
+//!
+//! tide-disco's `App` owns the tide server it runs on and does not let us install middleware. Its
+//! server also installs a CORS policy of its own, which allows every origin. So the rollup API is
+//! served on a loopback listener and this gateway, a plain tide server, sits in front of it on the
+//! public port. HTTP-level policy that has to run before a request reaches a route handler, such as
+//! CORS and request body limits, lives here, as does proxying submissions to another node.
+//!
+//! The gateway applies the only CORS policy clients see: the CORS headers of the rollup API are
+//! dropped from its responses. The address of the client is passed on in [`FORWARDED_FOR_HEADER`].
+//!
+//! The gateway also tells clients how far the node has got. Every response served by the node
+//! itself carries [`STATE_HEIGHT_HEADER`], the number of blocks in the state the response was
+//! read from. The gateway tags each request it forwards with [`REQUEST_ID_HEADER`], and the route
+//! handler records the height of the state it answers from under that ID in [`AnsweredHeights`],
+//! so the header is exactly the height of the response, not of whatever state the node has moved
+//! on to since. Routes which do not read the state get the height the node had reached when the
+//! response was sent. A client comparing the header across nodes can tell which are behind.
+
+use futures::AsyncReadExt;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use surf::Url;
+use tide::{
+    http::{headers::HeaderValue, mime},
+    security::{CorsMiddleware, Origin},
+    Body, Request, Response, StatusCode,
+};
+use tide_disco::RequestParams;
+
+use crate::api::{APIOptions, SubmitRoute};
+use crate::error::ApiError;
+use crate::status::StatusHandle;
+
+/// Header giving the height of the state a response was read from.
+pub(crate) const STATE_HEIGHT_HEADER: &str = "X-Rollup-Height";
+
+/// Header giving the address of the client a request was forwarded for.
+pub(crate) const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+
+/// Header identifying a request the gateway forwards to the rollup API, for
+/// [`AnsweredHeights`]. Never taken from clients.
+pub(crate) const REQUEST_ID_HEADER: &str = "X-Rollup-Request";
+
+/// The heights of the states the rollup API answered forwarded requests from, by request ID.
+///
+/// Only requests the gateway is waiting on have an entry, so a request which did not come through
+/// the gateway records nothing.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AnsweredHeights {
+    next_id: Arc<AtomicU64>,
+    heights: Arc<Mutex<HashMap<u64, Option<u64>>>>,
+}
+
+impl AnsweredHeights {
+    /// Start waiting on a request, returning its ID.
+    fn begin(&self) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.heights.lock().unwrap().insert(id, None);
+        id
+    }
+
+    /// Record that `req` is answered from the state at `height`.
+    pub(crate) fn record(&self, req: &RequestParams, height: u64) {
+        let Some(id) = req
+            .header(REQUEST_ID_HEADER)
+            .and_then(|values| values.last().as_str().parse::<u64>().ok())
+        else {
+            return;
+        };
+        if let Some(answered) = self.heights.lock().unwrap().get_mut(&id) {
+            *answered = Some(height);
+        }
+    }
+
+    /// Stop waiting on the request `id`, returning the height it was answered from, if its handler
+    /// read the state.
+    fn finish(&self, id: u64) -> Option<u64> {
+        self.heights.lock().unwrap().remove(&id).flatten()
+    }
+}
+
+/// Headers which describe a single connection or message framing. These are not forwarded across
+/// the proxy hop; the client on each side of the hop sets its own.
+const HOP_BY_HOP_HEADERS: [&str; 6] = [
+    "connection",
+    "content-length",
+    "host",
+    "keep-alive",
+    "transfer-encoding",
+    "upgrade",
+];
+
+#[derive(Clone, Debug)]
+struct Upstream {
+    url: Url,
+    /// Where submissions go instead of `url`, if anywhere.
+    submit_url: Option<Url>,
+    client: surf::Client,
+    max_submit_body_bytes: usize,
+    status: StatusHandle,
+    heights: AnsweredHeights,
+}
+
+impl Upstream {
+    /// The largest request body accepted for `path`.
+    fn body_limit(&self, path: &str) -> Option<usize> {
+        is_submission(path).then_some(self.max_submit_body_bytes)
+    }
+
+    /// The server which handles requests for `path`.
+    fn url(&self, path: &str) -> &Url {
+        match &self.submit_url {
+            Some(url) if is_submission(path) => url,
+            _ => &self.url,
+        }
+    }
+}
+
+/// Whether `path` is the `submit` route of the rollup API, at `rollup` or at a versioned path such
+/// as `rollup/v0`.
+fn is_submission(path: &str) -> bool {
+    let mut segments = path.trim_matches('/').split('/').peekable();
+    if segments.next() != Some("rollup") {
+        return false;
+    }
+    if segments
+        .peek()
+        .is_some_and(|segment| is_api_version(segment))
+    {
+        segments.next();
+    }
+    if segments.next() != Some("submit") {
+        return false;
+    }
+    // `submit`, `submit/:async` or `submit/fallback/:fallback`.
+    match (segments.next(), segments.next(), segments.next()) {
+        (None, _, _) => true,
+        (Some(segment), None, _) => !segment.is_empty(),
+        (Some("fallback"), Some(fallback), None) => !fallback.is_empty(),
+        _ => false,
+    }
+}
+
+fn is_api_version(segment: &str) -> bool {
+    segment
+        .strip_prefix('v')
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Serve the gateway on the public API port, forwarding every request to `upstream`, except
+/// submissions which the options send to another node. Handlers record the height of the state
+/// they answer from in `heights`, and the height of the node is read from `status` for those that
+/// do not read the state.
+pub(crate) async fn serve(
+    options: &APIOptions,
+    upstream: SocketAddr,
+    status: StatusHandle,
+    heights: AnsweredHeights,
+) -> io::Result<()> {
+    let url = format!("http://{upstream}")
+        .parse()
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    let submit_url = match &options.submit_route {
+        SubmitRoute::Upstream(url) => Some(url.clone()),
+        SubmitRoute::Sequencer | SubmitRoute::Disabled => None,
+    };
+    let mut server = tide::with_state(Upstream {
+        url,
+        submit_url,
+        client: surf::Client::new(),
+        max_submit_body_bytes: options.max_submit_body_bytes,
+        status,
+        heights,
+    });
+    if let Some(cors) = cors_middleware(&options.cors_allowed_origins) {
+        server.with(cors);
+    }
+    server.at("/").all(forward);
+    server.at("/*").all(forward);
+    server.listen(format!("0.0.0.0:{}", options.api_port)).await
+}
+
+/// Build the CORS policy for the configured origins.
+///
+/// No origins means no CORS headers at all, so browsers on other origins are blocked. The
+/// wildcard `*` must be listed explicitly to allow every origin.
+fn cors_middleware(allowed_origins: &[String]) -> Option<CorsMiddleware> {
+    if allowed_origins.is_empty() {
+        return None;
+    }
+    let origin = if allowed_origins.iter().any(|origin| origin == "*") {
+        Origin::Any
+    } else {
+        Origin::List(allowed_origins.to_vec())
+    };
+    Some(
+        CorsMiddleware::new()
+            .allow_methods("GET, POST, OPTIONS".parse::<HeaderValue>().unwrap())
+            .allow_headers(
+                "Content-Type, Authorization"
+                    .parse::<HeaderValue>()
+                    .unwrap(),
+            )
+            .allow_origin(origin),
+    )
+}
+
+async fn forward(mut req: Request<Upstream>) -> tide::Result {
+    let mut url = req.state().url(req.url().path()).clone();
+    url.set_path(req.url().path());
+    url.set_query(req.url().query());
+
+    let mut upstream = surf::Request::new(req.method(), url);
+    for (name, values) in req.iter() {
+        if is_hop_by_hop(name.as_str()) || name.as_str().eq_ignore_ascii_case(REQUEST_ID_HEADER) {
+            continue;
+        }
+        for value in values.iter() {
+            upstream.append_header(name.clone(), value.clone());
+        }
+    }
+    if let Some(peer) = req.peer_addr() {
+        upstream.append_header(FORWARDED_FOR_HEADER, client_ip(peer));
+    }
+    match req.state().body_limit(req.url().path()) {
+        Some(limit) => match read_bounded(&mut req, limit).await? {
+            Some(body) => upstream.set_body(body),
+            None => return Ok(payload_too_large(limit)),
+        },
+        None => upstream.set_body(req.take_body()),
+    }
+
+    let proxied = req.state().submit_url.is_some() && is_submission(req.url().path());
+    let heights = &req.state().heights;
+    let id = (!proxied).then(|| heights.begin());
+    if let Some(id) = id {
+        upstream.insert_header(REQUEST_ID_HEADER, id.to_string());
+    }
+    let res = req.state().client.send(upstream).await;
+    let answered = id.and_then(|id| heights.finish(id));
+    let mut res = res?;
+    let mut response = Response::new(res.status());
+    for (name, values) in res.iter() {
+        // The gateway applies its own CORS policy, not the one tide-disco installs.
+        if is_hop_by_hop(name.as_str()) || is_cors(name.as_str()) {
+            continue;
+        }
+        for value in values.iter() {
+            response.append_header(name.clone(), value.clone());
+        }
+    }
+    response.set_body(res.take_body());
+    // A response from another node says nothing about this one's state.
+    if !proxied {
+        let height = match answered {
+            Some(height) => height,
+            None => req.state().status.read().await.local_height,
+        };
+        response.insert_header(STATE_HEIGHT_HEADER, height.to_string());
+    }
+    Ok(response)
+}
+
+/// Read the request body, giving up as soon as it exceeds `limit` bytes.
+///
+/// Returns `None` if the body is too large. A declared `Content-Length` over the limit is rejected
+/// without reading anything, and chunked bodies are never buffered past `limit + 1` bytes.
+async fn read_bounded(req: &mut Request<Upstream>, limit: usize) -> tide::Result<Option<Vec<u8>>> {
+    if matches!(req.len(), Some(len) if len > limit) {
+        return Ok(None);
+    }
+    let mut body = Vec::new();
+    req.take_body()
+        .take(limit as u64 + 1)
+        .read_to_end(&mut body)
+        .await?;
+    Ok((body.len() <= limit).then_some(body))
+}
+
+fn payload_too_large(limit: usize) -> Response {
+    let mut res = Response::new(StatusCode::PayloadTooLarge);
+    res.set_content_type(mime::JSON);
+    res.set_body(Body::from_json(&ApiError::PayloadTooLarge { limit }).unwrap());
+    res
+}
+
+fn is_cors(name: &str) -> bool {
+    name.get(..15)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("access-control-"))
+}
+
+/// The IP address in the `peer` address of a connection, without its port.
+fn client_ip(peer: &str) -> String {
+    match peer.parse::<SocketAddr>() {
+        Ok(addr) => addr.ip().to_string(),
+        Err(_) => peer.to_string(),
+    }
+}
+
+fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP_HEADERS
+        .iter()
+        .any(|header| header.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_submission() {
+        for path in [
+            "/rollup/submit",
+            "/rollup/v0/submit",
+            "/rollup/submit/async",
+            "/rollup/v0/submit/fallback/l1",
+        ] {
+            assert!(is_submission(path), "{path}");
+        }
+        for path in [
+            "/rollup/submissions/0x01",
+            "/rollup/balance/submit",
+            "/rollup/submit/fallback/l1/extra",
+            "/rollup/admin/submit",
+            "/submit",
+        ] {
+            assert!(!is_submission(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_is_cors() {
+        assert!(is_cors("Access-Control-Allow-Origin"));
+        assert!(is_cors("access-control-allow-credentials"));
+        assert!(!is_cors("content-type"));
+        assert!(!is_cors("access"));
+    }
+}
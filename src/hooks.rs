@@ -0,0 +1,279 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Custom processing of executed blocks.
+//!
+//! Applications built on this rollup can react to each block the executor executes, for example to
+//! index it into a database, by registering a [`BlockHook`] in [`BlockHooks`]. Hooks run after the
+//! block has been applied to the state, without holding any lock on it. A hook which fails or takes
+//! too long is logged and otherwise ignored, so hooks cannot stop or stall the executor.
+
+use async_std::{
+    fs::OpenOptions,
+    future::timeout,
+    io::WriteExt,
+    sync::{Arc, Mutex},
+};
+use futures::future::{join_all, BoxFuture, FutureExt};
+use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::logging::EXECUTOR;
+use crate::state::{BlockResult, State};
+
+/// How long each hook may take to process a block, by default.
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Processing run after each executed block.
+pub trait BlockHook: Send + Sync {
+    /// Called once the block at `height` has been executed, with its effects and the resulting
+    /// state.
+    fn on_block<'a>(
+        &'a self,
+        height: u64,
+        block: &'a BlockResult,
+        state: &'a State,
+    ) -> BoxFuture<'a, Result<(), String>>;
+
+    /// Name of the hook in logs.
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+}
+
+/// The hooks run after each executed block.
+#[derive(Clone)]
+pub struct BlockHooks {
+    hooks: Vec<Arc<dyn BlockHook>>,
+    timeout: Duration,
+}
+
+impl Default for BlockHooks {
+    fn default() -> Self {
+        Self::new(DEFAULT_HOOK_TIMEOUT)
+    }
+}
+
+impl Debug for BlockHooks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockHooks")
+            .field(
+                "hooks",
+                &self
+                    .hooks
+                    .iter()
+                    .map(|hook| hook.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl BlockHooks {
+    /// No hooks, each of which will be given `timeout` to process a block once it is added.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            hooks: vec![],
+            timeout,
+        }
+    }
+
+    /// Add a hook, to run after the ones already added.
+    pub fn with(mut self, hook: impl BlockHook + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run every hook on an executed block.
+    ///
+    /// The hooks run concurrently, and this returns once each has finished or run out of time.
+    pub(crate) async fn run(&self, block: &BlockResult, state: &State) {
+        let height = block.summary.height;
+        join_all(self.hooks.iter().map(|hook| async move {
+            match timeout(self.timeout, hook.on_block(height, block, state)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    tracing::warn!(
+                        target: EXECUTOR,
+                        "block hook {} failed on block {height}: {err}",
+                        hook.name()
+                    )
+                }
+                Err(_) => tracing::warn!(
+                    target: EXECUTOR,
+                    "block hook {} timed out on block {height} after {:?}",
+                    hook.name(),
+                    self.timeout
+                ),
+            }
+        }))
+        .await;
+    }
+}
+
+/// A hook which appends the summary of each block to a file, one JSON object per line.
+#[derive(Debug)]
+pub struct SummaryLog {
+    path: PathBuf,
+    // Serializes appends, in case blocks are ever processed concurrently.
+    lock: Mutex<()>,
+}
+
+impl SummaryLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+impl BlockHook for SummaryLog {
+    fn on_block<'a>(
+        &'a self,
+        _height: u64,
+        block: &'a BlockResult,
+        _state: &'a State,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            let mut line = serde_json::to_string(&block.summary).map_err(|err| err.to_string())?;
+            line.push('\n');
+            let _guard = self.lock.lock().await;
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await
+                .map_err(|err| format!("unable to open {}: {err}", self.path.display()))?;
+            file.write_all(line.as_bytes())
+                .await
+                .map_err(|err| format!("unable to write {}: {err}", self.path.display()))?;
+            file.flush().await.map_err(|err| err.to_string())
+        }
+        .boxed()
+    }
+
+    fn name(&self) -> String {
+        format!("summary log {}", self.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{BlockSummary, BlockTimestamp, TimestampSource};
+    use crate::RollupVM;
+    use commit::Committable;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Instant;
+    use tempfile::TempDir;
+
+    struct CountingHook(Arc<AtomicU64>);
+
+    impl BlockHook for CountingHook {
+        fn on_block<'a>(
+            &'a self,
+            _height: u64,
+            _block: &'a BlockResult,
+            _state: &'a State,
+        ) -> BoxFuture<'a, Result<(), String>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            async { Ok(()) }.boxed()
+        }
+    }
+
+    struct HangingHook;
+
+    impl BlockHook for HangingHook {
+        fn on_block<'a>(
+            &'a self,
+            _height: u64,
+            _block: &'a BlockResult,
+            _state: &'a State,
+        ) -> BoxFuture<'a, Result<(), String>> {
+            futures::future::pending().boxed()
+        }
+    }
+
+    struct FailingHook;
+
+    impl BlockHook for FailingHook {
+        fn on_block<'a>(
+            &'a self,
+            height: u64,
+            _block: &'a BlockResult,
+            _state: &'a State,
+        ) -> BoxFuture<'a, Result<(), String>> {
+            async move { Err(format!("no block {height} for you")) }.boxed()
+        }
+    }
+
+    fn block(height: u64, state: &State) -> BlockResult {
+        BlockResult {
+            summary: BlockSummary {
+                height,
+                timestamp: BlockTimestamp {
+                    seconds: 1_700_000_000 + height,
+                    source: TimestampSource::HotShot,
+                },
+                state_commitment: state.commit(),
+                payloads: Default::default(),
+                weight: 0,
+                outbox: None,
+                l1_tx: None,
+            },
+            transactions: vec![],
+        }
+    }
+
+    #[async_std::test]
+    async fn test_hook_isolation() {
+        let state = State::from_initial_balances([], RollupVM::new(1.into()));
+        let count = Arc::new(AtomicU64::new(0));
+        let hooks = BlockHooks::new(Duration::from_millis(100))
+            .with(HangingHook)
+            .with(FailingHook)
+            .with(CountingHook(count.clone()));
+
+        // Every block reaches the counting hook, and a hook which never finishes only holds up
+        // each block for the timeout.
+        let start = Instant::now();
+        for height in 0..5 {
+            hooks.run(&block(height, &state), &state).await;
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 5);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[async_std::test]
+    async fn test_summary_log() {
+        let state = State::from_initial_balances([], RollupVM::new(1.into()));
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("blocks.jsonl");
+        let hooks = BlockHooks::default().with(SummaryLog::new(&path));
+        for height in 0..3 {
+            hooks.run(&block(height, &state), &state).await;
+        }
+
+        let summaries = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<BlockSummary>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summaries,
+            (0..3)
+                .map(|height| block(height, &state).summary)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Messages to this rollup from the other rollups sharing the sequencer.
+//!
+//! The messages a block sends are found from the sending rollup's namespace of the block alone, as
+//! described under [`message`](crate::message), so the executor reads them from the same HotShot
+//! block it executes: for each of the [message sources](crate::state::ChainConfig::message_sources)
+//! it fetches the source's namespace with its proof, checks the proof against the block's
+//! transaction root, and keeps the messages addressed to this rollup. They are delivered before the
+//! block's own transactions are applied. Which messages fit in the source's weight budget is found
+//! with this rollup's [`max_block_weight`](crate::state::ChainConfig::max_block_weight), so every
+//! message source must have the same one.
+
+use sequencer::{api::endpoints::NamespaceProofQueryData, NMTRoot, NamespaceProofType, Vm};
+use std::collections::BTreeSet;
+
+use crate::error::ExecutorError;
+use crate::message::{budgeted_messages, outbox, CrossVmMessage};
+use crate::replicas::QueryReplicas;
+use crate::transaction::Weight;
+use crate::RollupVM;
+
+/// The messages sent to the rollup with VM ID `dest` by the rollup with VM ID `source`, in the
+/// block with transaction root `nmt_root`, whose weight budget is `max_block_weight`.
+///
+/// `proof` is the namespace proof of `source` in that block. Returns `None` if it does not prove
+/// the namespace of `source` against `nmt_root`.
+pub fn inbound_messages(
+    nmt_root: &NMTRoot,
+    source: u64,
+    proof: &NamespaceProofType,
+    dest: u64,
+    max_block_weight: Weight,
+) -> Option<Vec<CrossVmMessage>> {
+    let vm = RollupVM::new(source.into());
+    if !proof
+        .verify(&nmt_root.root(), vm.id())
+        .map_or(false, |res| res.is_ok())
+    {
+        return None;
+    }
+    let transactions = proof
+        .get_namespace_leaves()
+        .into_iter()
+        .filter(|txn| txn.vm() == vm.id())
+        .filter_map(|txn| txn.as_vm(&vm))
+        .collect::<Vec<_>>();
+    Some(
+        outbox(source, budgeted_messages(&transactions, max_block_weight))
+            .into_iter()
+            .flatten()
+            .filter(|message| message.dest_vm == dest)
+            .collect(),
+    )
+}
+
+/// Fetch the messages sent to the rollup with VM ID `dest` by each of `sources` in the block at
+/// `height`, in the order of the sources, with a weight budget of `max_block_weight`.
+pub(crate) async fn fetch_messages(
+    hotshot: &QueryReplicas,
+    height: u64,
+    nmt_root: &NMTRoot,
+    sources: &BTreeSet<u64>,
+    dest: u64,
+    max_block_weight: Weight,
+) -> Result<Vec<CrossVmMessage>, ExecutorError> {
+    let mut messages = vec![];
+    for &source in sources {
+        let proof = hotshot
+            .get::<NamespaceProofQueryData>(&format!("block/{height}/namespace/{source}"))
+            .await
+            .map_err(|err| ExecutorError::QueryService {
+                reason: err.to_string(),
+            })?
+            .proof;
+        let inbound = inbound_messages(nmt_root, source, &proof, dest, max_block_weight)
+            .ok_or_else(|| ExecutorError::QueryService {
+                reason: format!("invalid proof of namespace {source} in block {height}"),
+            })?;
+        messages.extend(inbound);
+    }
+    Ok(messages)
+}
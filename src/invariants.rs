@@ -0,0 +1,465 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Checks of properties every state must have, run by the executor after executing blocks.
+//!
+//! A bug in a state transition need not make a block fail: it can just as well leave the state
+//! quietly wrong, and an executor which proves a wrong state commits the rollup to it. [`check`]
+//! looks for the signs of such a bug after a block:
+//!
+//! * the balances of each token add up to its [supply](crate::state::Supply), which is what there
+//!   was at genesis, plus what was deposited, minus what was burned as tips;
+//! * no balance exceeds the supply of its token, as a balance which went below zero and wrapped
+//!   around would;
+//! * the nonce of each sender is the last one the block applied for it, and the block applied
+//!   each sender's nonces one after the other;
+//! * the counts and weight in the block's summary match its receipts;
+//! * the commitment cached in the state is the one the state computes from scratch, and the one
+//!   recorded in the block's summary.
+//!
+//! Adding up every balance costs time in proportion to the number of accounts, so the executor
+//! only checks every [`DEFAULT_INVARIANT_INTERVAL`] blocks in release builds, and every block in
+//! debug builds. A violation is raised as a critical alert and stops the executor before the
+//! block is proven.
+
+use commit::{Commitment, Committable};
+use ethers::types::{Address, U256};
+use snafu::Snafu;
+use std::collections::BTreeMap;
+
+use crate::state::{Amount, BlockResult, Nonce, State};
+
+/// Number of blocks between checks of the invariants, by default: every block in debug builds,
+/// and fewer in release builds, where the cost of adding up every balance matters more.
+pub const DEFAULT_INVARIANT_INTERVAL: u64 = if cfg!(debug_assertions) { 1 } else { 16 };
+
+/// An invariant which a state does not have after a block.
+#[derive(Clone, Debug, PartialEq, Eq, Snafu)]
+pub enum Violation {
+    #[snafu(display("Block summary is for block {summary}, but the state has executed {state}."))]
+    Height { summary: u64, state: u64 },
+    #[snafu(display("Block summary counts {summary} {count}, but its receipts {receipts}."))]
+    Count {
+        count: String,
+        summary: u64,
+        receipts: u64,
+    },
+    #[snafu(display("Block applied nonce {nonce} of {address:?} after nonce {previous}."))]
+    NonceSkipped {
+        address: Address,
+        previous: Nonce,
+        nonce: Nonce,
+    },
+    #[snafu(display(
+        "Block last applied nonce {applied} of {address:?}, but the state has nonce {state}."
+    ))]
+    NonceMismatch {
+        address: Address,
+        applied: Nonce,
+        state: Nonce,
+    },
+    #[snafu(display("Native balances add up to {balances}, but the supply is {supply}."))]
+    NativeSupply { supply: U256, balances: U256 },
+    #[snafu(display(
+        "Balances of asset {asset:?} add up to {balances}, but its supply is {supply}."
+    ))]
+    AssetSupply {
+        asset: Address,
+        supply: U256,
+        balances: U256,
+    },
+    #[snafu(display(
+        "Balance {balance} of {address:?} in {} exceeds its supply {supply}.",
+        asset.map_or("the native token".into(), |asset| format!("asset {asset:?}"))
+    ))]
+    BalanceExceedsSupply {
+        address: Address,
+        asset: Option<Address>,
+        balance: Amount,
+        supply: U256,
+    },
+    #[snafu(display("State caches commitment {cached}, but computes {computed} from scratch."))]
+    StaleCommitment {
+        cached: Commitment<State>,
+        computed: Commitment<State>,
+    },
+    #[snafu(display(
+        "Block summary records state commitment {summary}, but the state's is {state}."
+    ))]
+    SummaryCommitment {
+        summary: Commitment<State>,
+        state: Commitment<State>,
+    },
+}
+
+/// Check that `state`, just after executing the block `result`, has every invariant. Returns the
+/// violations found, if any.
+pub fn check(state: &State, result: &BlockResult) -> Vec<Violation> {
+    let mut violations = vec![];
+    check_counts(state, result, &mut violations);
+    check_nonces(state, result, &mut violations);
+    check_supply(state, &mut violations);
+    check_commitments(state, result, &mut violations);
+    violations
+}
+
+fn check_counts(state: &State, result: &BlockResult, violations: &mut Vec<Violation>) {
+    let summary = &result.summary;
+    let receipts = &result.transactions;
+    if summary.height + 1 != state.block_height() {
+        violations.push(Violation::Height {
+            summary: summary.height,
+            state: state.block_height(),
+        });
+    }
+    let applied = receipts.iter().filter(|txn| txn.applied()).count() as u64;
+    let duplicates = receipts.iter().filter(|txn| txn.duplicate).count() as u64;
+    let mut counts = vec![
+        ("decoded", summary.payloads.decoded, receipts.len() as u64),
+        ("applied", summary.payloads.applied, applied),
+        ("duplicates", summary.payloads.duplicates, duplicates),
+        (
+            "weight",
+            summary.weight,
+            receipts.iter().map(|txn| txn.weight).sum(),
+        ),
+    ];
+    if let Some(stored) = state.block_receipts(summary.height) {
+        counts.push((
+            "stored receipts",
+            stored.len() as u64,
+            receipts.len() as u64,
+        ));
+    }
+    for (name, summary, receipts) in counts {
+        if summary != receipts {
+            violations.push(Violation::Count {
+                count: name.into(),
+                summary,
+                receipts,
+            });
+        }
+    }
+}
+
+fn check_nonces(state: &State, result: &BlockResult, violations: &mut Vec<Violation>) {
+    let mut applied = BTreeMap::new();
+    for outcome in result.transactions.iter().filter(|txn| txn.applied()) {
+        let (Some(address), Some(transaction)) = (outcome.sender, &outcome.transaction) else {
+            continue;
+        };
+        let nonce = transaction.nonce;
+        if let Some(previous) = applied.insert(address, nonce) {
+            if nonce != previous + 1 {
+                violations.push(Violation::NonceSkipped {
+                    address,
+                    previous,
+                    nonce,
+                });
+            }
+        }
+    }
+    for (address, applied) in applied {
+        let nonce = state.get_nonce(&address);
+        if nonce != applied {
+            violations.push(Violation::NonceMismatch {
+                address,
+                applied,
+                state: nonce,
+            });
+        }
+    }
+}
+
+fn check_supply(state: &State, violations: &mut Vec<Violation>) {
+    let supply = state.supply();
+    let native = supply.native();
+    let mut balances = U256::zero();
+    for (address, balance) in state.balances() {
+        if U256::from(balance) > native {
+            violations.push(Violation::BalanceExceedsSupply {
+                address,
+                asset: None,
+                balance,
+                supply: native,
+            });
+        }
+        balances += U256::from(balance);
+    }
+    if balances != native {
+        violations.push(Violation::NativeSupply {
+            supply: native,
+            balances,
+        });
+    }
+
+    let mut assets = supply
+        .deposited
+        .keys()
+        .map(|asset| (*asset, U256::zero()))
+        .collect::<BTreeMap<_, _>>();
+    for (asset, address, balance) in state.asset_holdings() {
+        let asset_supply = supply.asset(&asset);
+        if U256::from(balance) > asset_supply {
+            violations.push(Violation::BalanceExceedsSupply {
+                address,
+                asset: Some(asset),
+                balance,
+                supply: asset_supply,
+            });
+        }
+        *assets.entry(asset).or_default() += U256::from(balance);
+    }
+    for (asset, balances) in assets {
+        if balances != supply.asset(&asset) {
+            violations.push(Violation::AssetSupply {
+                asset,
+                supply: supply.asset(&asset),
+                balances,
+            });
+        }
+    }
+}
+
+fn check_commitments(state: &State, result: &BlockResult, violations: &mut Vec<Violation>) {
+    let cached = state.commit();
+    let computed = state.compute_commitment();
+    if cached != computed {
+        violations.push(Violation::StaleCommitment { cached, computed });
+    }
+    if result.summary.state_commitment != cached {
+        violations.push(Violation::SummaryCommitment {
+            summary: result.summary.state_commitment,
+            state: cached,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deposit::{Deposit, L1Deposits};
+    use crate::state::{BlockInputs, ChainConfig};
+    use crate::transaction::{RollupTransaction, SignedTransaction, Transaction, DEFAULT_VM_ID};
+    use crate::RollupVM;
+    use ethers::signers::{LocalWallet, Signer};
+
+    /// A state in which alice has paid bob in the native token and in a deposited asset, tipping
+    /// both times, and the block which did it.
+    async fn executed() -> (State, BlockResult, LocalWallet, Address, Address) {
+        let alice = LocalWallet::new(&mut rand::thread_rng());
+        let bob = Address::random();
+        let token = Address::random();
+        let mut state =
+            State::from_initial_balances([(alice.address(), 100)], RollupVM::new(1.into()))
+                .with_chain_config(ChainConfig {
+                    assets: [token].into(),
+                    ..Default::default()
+                });
+        let deposits = L1Deposits {
+            through: 1,
+            deposits: vec![Deposit {
+                token,
+                sender: Address::random(),
+                recipient: alice.address(),
+                amount: 50.into(),
+                l1_block: 1,
+            }],
+        };
+        let transfer = |nonce, asset| {
+            SignedTransaction::new_with_wallet(
+                Transaction {
+                    amount: 10,
+                    destination: bob,
+                    nonce,
+                    priority: 2,
+                    vm_id: DEFAULT_VM_ID,
+                    asset,
+                },
+                &alice,
+            )
+        };
+        let transactions = vec![
+            transfer(1, None).await.into(),
+            transfer(2, Some(token)).await.into(),
+        ];
+        let inputs = BlockInputs {
+            deposits,
+            ..Default::default()
+        };
+        let result = state.execute_transactions_with_inputs(&inputs, transactions);
+        assert_eq!(result.summary.payloads.applied, 2);
+        (state, result, alice, bob, token)
+    }
+
+    #[async_std::test]
+    async fn test_consistent_blocks() {
+        let (mut state, result, _, _, token) = executed().await;
+        assert_eq!(check(&state, &result), []);
+        // Both tips were burned.
+        assert_eq!(state.supply().native(), 96.into());
+        assert_eq!(state.supply().asset(&token), 50.into());
+
+        let result = state.execute_transactions(vec![]);
+        assert_eq!(check(&state, &result), []);
+    }
+
+    #[async_std::test]
+    async fn test_supply_violations() {
+        let (state, result, alice, bob, token) = executed().await;
+
+        // A transition which credits more than it debits.
+        let mut corrupt = state.clone();
+        corrupt.corrupt_balance(bob, 11);
+        let violations = check(&corrupt, &result);
+        assert!(
+            violations.contains(&Violation::NativeSupply {
+                supply: 96.into(),
+                balances: 97.into(),
+            }),
+            "{violations:?}"
+        );
+
+        // A balance which went below zero and wrapped around.
+        let mut corrupt = state.clone();
+        corrupt.corrupt_balance(alice.address(), Amount::MAX);
+        let violations = check(&corrupt, &result);
+        assert!(
+            violations.contains(&Violation::BalanceExceedsSupply {
+                address: alice.address(),
+                asset: None,
+                balance: Amount::MAX,
+                supply: 96.into(),
+            }),
+            "{violations:?}"
+        );
+
+        let mut corrupt = state;
+        corrupt.corrupt_asset_balance(token, bob, 9);
+        let violations = check(&corrupt, &result);
+        assert!(
+            violations.contains(&Violation::AssetSupply {
+                asset: token,
+                supply: 50.into(),
+                balances: 49.into(),
+            }),
+            "{violations:?}"
+        );
+    }
+
+    #[async_std::test]
+    async fn test_nonce_violations() {
+        let (state, mut result, alice, ..) = executed().await;
+
+        let mut corrupt = state.clone();
+        corrupt.corrupt_nonce(alice.address(), 1);
+        let violations = check(&corrupt, &result);
+        assert!(
+            violations.contains(&Violation::NonceMismatch {
+                address: alice.address(),
+                applied: 2,
+                state: 1,
+            }),
+            "{violations:?}"
+        );
+
+        // A block which applied a nonce out of turn.
+        result.transactions[1].transaction.as_mut().unwrap().nonce = 3;
+        let violations = check(&state, &result);
+        assert!(
+            violations.contains(&Violation::NonceSkipped {
+                address: alice.address(),
+                previous: 1,
+                nonce: 3,
+            }),
+            "{violations:?}"
+        );
+    }
+
+    #[async_std::test]
+    async fn test_count_violations() {
+        let (state, result, ..) = executed().await;
+
+        let mut wrong = result.clone();
+        wrong.summary.height += 1;
+        assert_eq!(
+            check(&state, &wrong),
+            [Violation::Height {
+                summary: 1,
+                state: 1,
+            }]
+        );
+
+        let mut wrong = result.clone();
+        wrong.summary.payloads.applied = 1;
+        wrong.summary.weight += 1;
+        let weight = result.summary.weight;
+        assert_eq!(
+            check(&state, &wrong),
+            [
+                Violation::Count {
+                    count: "applied".into(),
+                    summary: 1,
+                    receipts: 2,
+                },
+                Violation::Count {
+                    count: "weight".into(),
+                    summary: weight + 1,
+                    receipts: weight,
+                }
+            ]
+        );
+
+        let mut wrong = result;
+        let mut duplicate = wrong.transactions[0].clone();
+        duplicate.error = Some("duplicate".into());
+        duplicate.duplicate = true;
+        duplicate.weight = 0;
+        wrong.transactions.push(duplicate);
+        assert_eq!(
+            check(&state, &wrong),
+            [
+                Violation::Count {
+                    count: "decoded".into(),
+                    summary: 2,
+                    receipts: 3,
+                },
+                Violation::Count {
+                    count: "duplicates".into(),
+                    summary: 0,
+                    receipts: 1,
+                }
+            ]
+        );
+    }
+
+    #[async_std::test]
+    async fn test_commitment_violations() {
+        let (state, result, alice, bob, _) = executed().await;
+
+        // A transition which moves funds without clearing the cached commitment.
+        let mut corrupt = state.clone();
+        corrupt.corrupt_balance(alice.address(), state.get_balance(&alice.address()) - 1);
+        corrupt.corrupt_balance(bob, state.get_balance(&bob) + 1);
+        assert_eq!(
+            check(&corrupt, &result),
+            [Violation::StaleCommitment {
+                cached: state.commit(),
+                computed: corrupt.compute_commitment(),
+            }]
+        );
+
+        let mut wrong = result;
+        wrong.summary.state_commitment = corrupt.compute_commitment();
+        assert_eq!(
+            check(&state, &wrong),
+            [Violation::SummaryCommitment {
+                summary: corrupt.compute_commitment(),
+                state: state.commit(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,860 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! The JSON format of transactions in the rollup API.
+//!
+//! The derived serialization of [`Transaction`] and [`SignedTransaction`] is their canonical
+//! encoding: it is what is signed, hashed and sequenced, so it cannot change without invalidating
+//! every existing signature. The API instead speaks the format defined here, which is meant for
+//! clients written in any language:
+//! * amounts, nonces, priorities, VM IDs and weights are decimal strings, since JSON numbers
+//!   above 2^53 lose precision in many parsers;
+//! * addresses are EIP-55 checksummed;
+//! * signatures are a single 0x-prefixed hex string of the 65 bytes `r || s || v`;
+//! * hashes are 0x-prefixed hex strings.
+//!
+//! Requests in the older format, which was the canonical encoding, are rejected with an error
+//! saying what changed rather than being misread.
+
+use commit::Commitment;
+use ethers::{
+    types::{Address, Bytes, Signature, H256},
+    utils::hex,
+};
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt::{self, Formatter};
+
+use crate::address::{checksummed, parse_address};
+use crate::error::RollupError;
+use crate::fee::{SetMinFee, SignedSetMinFee};
+use crate::message::{CrossVmMessage, Message, OutboundMessage};
+use crate::multisig::{MultisigConfig, MultisigTransfer, RegisterMultisig};
+use crate::replay::BlockReplay;
+use crate::state::{BalanceChange, BlockSummary, State, TransactionOutcome};
+use crate::transaction::{RollupTransaction, SignedTransaction, Transaction, DEFAULT_VM_ID};
+
+/// An amount, nonce or priority, as a decimal string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quantity(pub u64);
+
+impl Serialize for Quantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct QuantityVisitor;
+
+        impl<'de> Visitor<'de> for QuantityVisitor {
+            type Value = Quantity;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                write!(f, "a decimal string, such as \"100\"")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Quantity, E> {
+                if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(E::custom(format!(
+                        "quantity must be a string of decimal digits, found \"{s}\""
+                    )));
+                }
+                s.parse()
+                    .map(Quantity)
+                    .map_err(|_| E::custom(format!("quantity {s} does not fit in 64 bits")))
+            }
+
+            fn visit_u64<E: de::Error>(self, n: u64) -> Result<Quantity, E> {
+                Err(E::custom(format!(
+                    "quantities are decimal strings, found the number {n}; send \"{n}\" instead"
+                )))
+            }
+
+            fn visit_i64<E: de::Error>(self, n: i64) -> Result<Quantity, E> {
+                Err(E::custom(format!(
+                    "quantities are non-negative decimal strings, found the number {n}"
+                )))
+            }
+
+            fn visit_f64<E: de::Error>(self, n: f64) -> Result<Quantity, E> {
+                Err(E::custom(format!(
+                    "quantities are decimal strings of whole numbers, found the number {n}"
+                )))
+            }
+        }
+
+        deserializer.deserialize_any(QuantityVisitor)
+    }
+}
+
+/// An address, written in its EIP-55 checksummed form and parsed with [`parse_address`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChecksumAddress(pub Address);
+
+impl Serialize for ChecksumAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&checksummed(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for ChecksumAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse_address(&s)
+            .map(ChecksumAddress)
+            .map_err(de::Error::custom)
+    }
+}
+
+/// A signature, as the 0x-prefixed hex string of `r || s || v`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HexSignature(pub Signature);
+
+impl Serialize for HexSignature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(self.0.to_vec())))
+    }
+}
+
+impl<'de> Deserialize<'de> for HexSignature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SignatureVisitor;
+
+        impl<'de> Visitor<'de> for SignatureVisitor {
+            type Value = HexSignature;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                write!(f, "a 0x-prefixed hex string of 65 bytes")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<HexSignature, E> {
+                let digits = s
+                    .strip_prefix("0x")
+                    .ok_or_else(|| E::custom("signature must start with 0x"))?;
+                let bytes = hex::decode(digits)
+                    .map_err(|_| E::custom("signature contains characters which are not hex"))?;
+                if bytes.len() != 65 {
+                    return Err(E::custom(format!(
+                        "signature must be 65 bytes (r, s and v), found {}",
+                        bytes.len()
+                    )));
+                }
+                Signature::try_from(bytes.as_slice())
+                    .map(HexSignature)
+                    .map_err(E::custom)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, _map: A) -> Result<HexSignature, A::Error> {
+                Err(de::Error::custom(
+                    "signatures are a 0x-prefixed hex string of r, s and v (65 bytes); the \
+                    { r, s, v } object format is no longer accepted",
+                ))
+            }
+        }
+
+        deserializer.deserialize_any(SignatureVisitor)
+    }
+}
+
+/// A [`Transaction`] in the API format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransactionJson {
+    pub amount: Quantity,
+    pub destination: ChecksumAddress,
+    pub nonce: Quantity,
+    #[serde(default)]
+    pub priority: Quantity,
+    /// Left out for, and defaults to, [`DEFAULT_VM_ID`], like in the canonical encoding.
+    #[serde(default = "default_vm_id", skip_serializing_if = "is_default_vm_id")]
+    pub vm_id: Quantity,
+    /// Left out for, and defaults to, the native asset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<ChecksumAddress>,
+}
+
+fn default_vm_id() -> Quantity {
+    Quantity(DEFAULT_VM_ID)
+}
+
+fn is_default_vm_id(vm_id: &Quantity) -> bool {
+    vm_id.0 == DEFAULT_VM_ID
+}
+
+impl From<&Transaction> for TransactionJson {
+    fn from(txn: &Transaction) -> Self {
+        Self {
+            amount: Quantity(txn.amount),
+            destination: ChecksumAddress(txn.destination),
+            nonce: Quantity(txn.nonce),
+            priority: Quantity(txn.priority),
+            vm_id: Quantity(txn.vm_id),
+            asset: txn.asset.map(ChecksumAddress),
+        }
+    }
+}
+
+impl From<TransactionJson> for Transaction {
+    fn from(txn: TransactionJson) -> Self {
+        Self {
+            amount: txn.amount.0,
+            destination: txn.destination.0,
+            nonce: txn.nonce.0,
+            priority: txn.priority.0,
+            vm_id: txn.vm_id.0,
+            asset: txn.asset.map(|asset| asset.0),
+        }
+    }
+}
+
+/// A [`SignedTransaction`] in the API format, as accepted by the `submit` route.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignedTransactionJson {
+    pub transaction: TransactionJson,
+    pub signature: HexSignature,
+}
+
+impl From<&SignedTransaction> for SignedTransactionJson {
+    fn from(signed: &SignedTransaction) -> Self {
+        Self {
+            transaction: (&signed.transaction).into(),
+            signature: HexSignature(*signed.signature()),
+        }
+    }
+}
+
+impl From<SignedTransactionJson> for SignedTransaction {
+    fn from(signed: SignedTransactionJson) -> Self {
+        // The signature is kept exactly as sent, so that one which is not in canonical form is
+        // rejected rather than silently fixed.
+        SignedTransaction::from_parts(signed.transaction.into(), signed.signature.0)
+    }
+}
+
+/// The fields of a transfer to be prepared for signing, as accepted by the `prepare` route. The
+/// node fills in the fields left out.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnsignedTransactionJson {
+    /// The account which will sign the transfer.
+    pub sender: ChecksumAddress,
+    pub amount: Quantity,
+    pub destination: ChecksumAddress,
+    /// Defaults to the next nonce of the sender.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<Quantity>,
+    /// Defaults to the minimum fee of the next block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Quantity>,
+    /// Defaults to the VM of this rollup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vm_id: Option<Quantity>,
+    /// Left out for, and defaults to, the native asset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<ChecksumAddress>,
+}
+
+/// A transfer ready to be signed, as returned by the `prepare` route.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreparedTransactionJson {
+    /// The transfer with every field filled in, as it goes in the `transaction` field of a
+    /// submission.
+    pub transaction: TransactionJson,
+    /// The fields the node filled in, because the request left them out.
+    pub defaults: Vec<String>,
+    /// The canonical encoding of the transfer, which is what is signed.
+    pub encoding: Bytes,
+    /// The digest of the encoding to sign, for signers which sign a hash rather than a message.
+    pub signing_hash: H256,
+    /// Height of the state the defaults and warnings come from.
+    pub height: u64,
+    /// Why the transfer would not be applied, were it executed against the state at `height`. This
+    /// is only advice: transactions executed before it may change the nonce and balances of the
+    /// sender.
+    pub warnings: Vec<RollupError>,
+}
+
+/// A [`RegisterMultisig`] in the API format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisterMultisigJson {
+    pub signers: Vec<ChecksumAddress>,
+    pub threshold: u32,
+}
+
+impl From<&RegisterMultisig> for RegisterMultisigJson {
+    fn from(txn: &RegisterMultisig) -> Self {
+        Self {
+            signers: txn.signers.iter().copied().map(ChecksumAddress).collect(),
+            threshold: txn.threshold,
+        }
+    }
+}
+
+impl From<RegisterMultisigJson> for RegisterMultisig {
+    fn from(txn: RegisterMultisigJson) -> Self {
+        Self {
+            signers: txn.signers.into_iter().map(|signer| signer.0).collect(),
+            threshold: txn.threshold,
+        }
+    }
+}
+
+/// A [`MultisigTransfer`] in the API format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultisigTransferJson {
+    pub account: ChecksumAddress,
+    pub transaction: TransactionJson,
+    pub signatures: Vec<HexSignature>,
+}
+
+impl From<&MultisigTransfer> for MultisigTransferJson {
+    fn from(txn: &MultisigTransfer) -> Self {
+        Self {
+            account: ChecksumAddress(txn.account),
+            transaction: (&txn.transaction).into(),
+            signatures: txn.signatures.iter().copied().map(HexSignature).collect(),
+        }
+    }
+}
+
+impl From<MultisigTransferJson> for MultisigTransfer {
+    fn from(txn: MultisigTransferJson) -> Self {
+        // As for a single signature, the signatures are kept exactly as sent.
+        Self {
+            account: txn.account.0,
+            transaction: txn.transaction.into(),
+            signatures: txn.signatures.into_iter().map(|sig| sig.0).collect(),
+        }
+    }
+}
+
+/// A [`Message`] in the API format. The payload is a 0x-prefixed hex string.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessageJson {
+    pub dest_vm: Quantity,
+    pub recipient: ChecksumAddress,
+    pub nonce: Quantity,
+    pub payload: Bytes,
+}
+
+impl From<&Message> for MessageJson {
+    fn from(message: &Message) -> Self {
+        Self {
+            dest_vm: Quantity(message.dest_vm),
+            recipient: ChecksumAddress(message.recipient),
+            nonce: Quantity(message.nonce),
+            payload: message.payload.clone(),
+        }
+    }
+}
+
+impl From<MessageJson> for Message {
+    fn from(message: MessageJson) -> Self {
+        Self {
+            dest_vm: message.dest_vm.0,
+            recipient: message.recipient.0,
+            nonce: message.nonce.0,
+            payload: message.payload,
+        }
+    }
+}
+
+/// An [`OutboundMessage`] in the API format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutboundMessageJson {
+    pub message: MessageJson,
+    pub signature: HexSignature,
+}
+
+impl From<&OutboundMessage> for OutboundMessageJson {
+    fn from(txn: &OutboundMessage) -> Self {
+        Self {
+            message: (&txn.message).into(),
+            signature: HexSignature(txn.signature),
+        }
+    }
+}
+
+impl From<OutboundMessageJson> for OutboundMessage {
+    fn from(txn: OutboundMessageJson) -> Self {
+        // As for a transfer, the signature is kept exactly as sent.
+        Self {
+            message: txn.message.into(),
+            signature: txn.signature.0,
+        }
+    }
+}
+
+/// A [`SetMinFee`] in the API format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetMinFeeJson {
+    pub value: Quantity,
+    pub effective_height: Quantity,
+    pub nonce: Quantity,
+    pub vm_id: Quantity,
+}
+
+impl From<&SetMinFee> for SetMinFeeJson {
+    fn from(change: &SetMinFee) -> Self {
+        Self {
+            value: Quantity(change.value),
+            effective_height: Quantity(change.effective_height),
+            nonce: Quantity(change.nonce),
+            vm_id: Quantity(change.vm_id),
+        }
+    }
+}
+
+impl From<SetMinFeeJson> for SetMinFee {
+    fn from(change: SetMinFeeJson) -> Self {
+        Self {
+            value: change.value.0,
+            effective_height: change.effective_height.0,
+            nonce: change.nonce.0,
+            vm_id: change.vm_id.0,
+        }
+    }
+}
+
+/// A [`SignedSetMinFee`] in the API format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignedSetMinFeeJson {
+    pub set_min_fee: SetMinFeeJson,
+    pub signature: HexSignature,
+}
+
+impl From<&SignedSetMinFee> for SignedSetMinFeeJson {
+    fn from(txn: &SignedSetMinFee) -> Self {
+        Self {
+            set_min_fee: (&txn.set_min_fee).into(),
+            signature: HexSignature(txn.signature),
+        }
+    }
+}
+
+impl From<SignedSetMinFeeJson> for SignedSetMinFee {
+    fn from(txn: SignedSetMinFeeJson) -> Self {
+        // As for a transfer, the signature is kept exactly as sent.
+        Self {
+            set_min_fee: txn.set_min_fee.into(),
+            signature: txn.signature.0,
+        }
+    }
+}
+
+/// A [`CrossVmMessage`] delivered to this rollup, in the API format, as returned by the
+/// `messages/:address` route.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrossVmMessageJson {
+    pub source_vm: Quantity,
+    pub sender: ChecksumAddress,
+    pub recipient: ChecksumAddress,
+    pub nonce: Quantity,
+    pub payload: Bytes,
+    pub hash: H256,
+}
+
+impl From<&CrossVmMessage> for CrossVmMessageJson {
+    fn from(message: &CrossVmMessage) -> Self {
+        Self {
+            source_vm: Quantity(message.source_vm),
+            sender: ChecksumAddress(message.sender),
+            recipient: ChecksumAddress(message.recipient),
+            nonce: Quantity(message.nonce),
+            payload: message.payload.clone(),
+            hash: message.hash(),
+        }
+    }
+}
+
+/// A [`RollupTransaction`] of any kind in the API format, as accepted by the `submit` route.
+///
+/// The kind is told apart by its fields: a body with `signatures` is a multisig transfer, one with
+/// `signers` registers a multisig account, one with `message` sends a message to another rollup,
+/// one with `set_min_fee` changes the minimum fee, and anything else is read as a transfer, so
+/// that errors in a transfer are reported as such.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum RollupTransactionJson {
+    Transfer(SignedTransactionJson),
+    RegisterMultisig(RegisterMultisigJson),
+    MultisigTransfer(MultisigTransferJson),
+    OutboundMessage(OutboundMessageJson),
+    SetMinFee(SignedSetMinFeeJson),
+}
+
+impl<'de> Deserialize<'de> for RollupTransactionJson {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = serde_json::Map::deserialize(deserializer)?;
+        let kind = if fields.contains_key("signatures") {
+            serde_json::from_value(fields.into()).map(Self::MultisigTransfer)
+        } else if fields.contains_key("signers") {
+            serde_json::from_value(fields.into()).map(Self::RegisterMultisig)
+        } else if fields.contains_key("message") {
+            serde_json::from_value(fields.into()).map(Self::OutboundMessage)
+        } else if fields.contains_key("set_min_fee") {
+            serde_json::from_value(fields.into()).map(Self::SetMinFee)
+        } else {
+            serde_json::from_value(fields.into()).map(Self::Transfer)
+        };
+        kind.map_err(de::Error::custom)
+    }
+}
+
+impl From<&RollupTransaction> for RollupTransactionJson {
+    fn from(txn: &RollupTransaction) -> Self {
+        match txn {
+            RollupTransaction::Transfer(txn) => Self::Transfer(txn.into()),
+            RollupTransaction::RegisterMultisig(txn) => Self::RegisterMultisig(txn.into()),
+            RollupTransaction::MultisigTransfer(txn) => Self::MultisigTransfer(txn.into()),
+            RollupTransaction::OutboundMessage(txn) => Self::OutboundMessage(txn.into()),
+            RollupTransaction::SetMinFee(txn) => Self::SetMinFee(txn.into()),
+        }
+    }
+}
+
+impl From<RollupTransactionJson> for RollupTransaction {
+    fn from(txn: RollupTransactionJson) -> Self {
+        match txn {
+            RollupTransactionJson::Transfer(txn) => SignedTransaction::from(txn).into(),
+            RollupTransactionJson::RegisterMultisig(txn) => RegisterMultisig::from(txn).into(),
+            RollupTransactionJson::MultisigTransfer(txn) => MultisigTransfer::from(txn).into(),
+            RollupTransactionJson::OutboundMessage(txn) => OutboundMessage::from(txn).into(),
+            RollupTransactionJson::SetMinFee(txn) => SignedSetMinFee::from(txn).into(),
+        }
+    }
+}
+
+/// A registered [`MultisigConfig`] in the API format, as returned by the `multisig/:address`
+/// route.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigJson {
+    pub address: ChecksumAddress,
+    pub signers: Vec<ChecksumAddress>,
+    pub threshold: u32,
+}
+
+impl From<&MultisigConfig> for MultisigJson {
+    fn from(config: &MultisigConfig) -> Self {
+        Self {
+            address: ChecksumAddress(config.address()),
+            signers: config
+                .signers()
+                .iter()
+                .copied()
+                .map(ChecksumAddress)
+                .collect(),
+            threshold: config.threshold(),
+        }
+    }
+}
+
+/// A [`TransactionOutcome`] in the API format, as returned by the `block/:height/receipts` route.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptJson {
+    pub hash: H256,
+    /// Position of the transaction among the payloads of its block's namespace, as sequenced. Left
+    /// out if the node executed the block before it recorded positions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u64>,
+    /// The L1 block a transaction forced in from L1 was enqueued in. Left out for other
+    /// transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_block: Option<u64>,
+    pub sender: Option<ChecksumAddress>,
+    /// The transfer, or `null` for the registration of a multisig account, a message or a fee
+    /// change.
+    pub transaction: Option<TransactionJson>,
+    /// The multisig account registered, for a registration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultisigJson>,
+    /// The message sent to another rollup, for an outbound message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<MessageJson>,
+    /// The change to the minimum fee, for a fee change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_fee: Option<SetMinFeeJson>,
+    /// Weight the transaction consumed from its block's budget.
+    pub weight: Quantity,
+    pub error: Option<String>,
+    /// Whether the transaction was a copy of one already applied. Left out when it was not.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub duplicate: bool,
+}
+
+impl From<&TransactionOutcome> for ReceiptJson {
+    fn from(outcome: &TransactionOutcome) -> Self {
+        Self {
+            hash: outcome.hash,
+            index: outcome.index,
+            l1_block: outcome.l1_block,
+            sender: outcome.sender.map(ChecksumAddress),
+            transaction: outcome.transaction.as_ref().map(TransactionJson::from),
+            multisig: outcome.multisig.as_ref().map(MultisigJson::from),
+            message: outcome.message.as_ref().map(MessageJson::from),
+            min_fee: outcome.min_fee.as_ref().map(SetMinFeeJson::from),
+            weight: Quantity(outcome.weight),
+            error: outcome.error.clone(),
+            duplicate: outcome.duplicate,
+        }
+    }
+}
+
+/// A [`BalanceChange`] in the API format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceChangeJson {
+    pub address: ChecksumAddress,
+    /// The deposited asset, left out for the native token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<ChecksumAddress>,
+    pub before: Quantity,
+    pub after: Quantity,
+}
+
+impl From<&BalanceChange> for BalanceChangeJson {
+    fn from(change: &BalanceChange) -> Self {
+        Self {
+            address: ChecksumAddress(change.address),
+            asset: change.asset.map(ChecksumAddress),
+            before: Quantity(change.before),
+            after: Quantity(change.after),
+        }
+    }
+}
+
+/// A [`BlockReplay`] in the API format, as returned by the `admin/replay/:height` route.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockReplayJson {
+    pub snapshot_height: u64,
+    pub pre_state: Commitment<State>,
+    /// The summary of the block, in the format of the `block/:height` route.
+    pub summary: BlockSummary,
+    pub receipts: Vec<ReceiptJson>,
+    pub balance_changes: Vec<BalanceChangeJson>,
+}
+
+impl From<&BlockReplay> for BlockReplayJson {
+    fn from(replay: &BlockReplay) -> Self {
+        Self {
+            snapshot_height: replay.snapshot_height,
+            pre_state: replay.pre_state,
+            summary: replay.result.summary.clone(),
+            receipts: replay.result.transactions.iter().map(Into::into).collect(),
+            balance_changes: replay.balance_changes.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U256;
+    use serde_json::Value;
+
+    // The transfer from the README, signed by the first genesis account.
+    const SIGNED_TRANSACTION: &str = include_str!("../fixtures/json/signed_transaction.json");
+    // The same transfer in the format the API accepted before this one.
+    const LEGACY_SIGNED_TRANSACTION: &str =
+        include_str!("../fixtures/json/legacy_signed_transaction.json");
+    const RECEIPT: &str = include_str!("../fixtures/json/receipt.json");
+
+    fn signer() -> Address {
+        "0xf23694f9c6d4837fc596c4eb7c3c3d8a8bae69ca"
+            .parse()
+            .unwrap()
+    }
+
+    fn transaction() -> Transaction {
+        Transaction {
+            amount: 100,
+            destination: "0x885ee92eebda03540066a25a57cc625bbee15d5a"
+                .parse()
+                .unwrap(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        }
+    }
+
+    #[test]
+    fn test_signed_transaction_golden() {
+        let json: SignedTransactionJson = serde_json::from_str(SIGNED_TRANSACTION).unwrap();
+        let signed = SignedTransaction::from(json.clone());
+        assert_eq!(signed.transaction, transaction());
+        assert_eq!(signed.verify().unwrap(), signer());
+
+        // Serializing gives back the fixture, field for field.
+        assert_eq!(
+            serde_json::to_value(SignedTransactionJson::from(&signed)).unwrap(),
+            serde_json::from_str::<Value>(SIGNED_TRANSACTION).unwrap()
+        );
+        assert_eq!(SignedTransactionJson::from(&signed), json);
+    }
+
+    #[test]
+    fn test_receipt_golden() {
+        let receipt = ReceiptJson::from(&TransactionOutcome {
+            hash: H256::repeat_byte(0xab),
+            index: Some(3),
+            l1_block: None,
+            sender: Some(signer()),
+            transaction: Some(Transaction {
+                priority: 7,
+                ..transaction()
+            }),
+            multisig: None,
+            message: None,
+            min_fee: None,
+            weight: 23_450,
+            error: Some("Insufficient balance".into()),
+            duplicate: false,
+        });
+        assert_eq!(
+            serde_json::to_value(&receipt).unwrap(),
+            serde_json::from_str::<Value>(RECEIPT).unwrap()
+        );
+        assert_eq!(
+            serde_json::from_str::<ReceiptJson>(RECEIPT).unwrap(),
+            receipt
+        );
+    }
+
+    #[test]
+    fn test_legacy_format_rejected() {
+        let err = serde_json::from_str::<SignedTransactionJson>(LEGACY_SIGNED_TRANSACTION)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("send \"100\" instead"), "{err}");
+
+        // With the quantities fixed, the old signature object is still rejected.
+        let mut legacy: Value = serde_json::from_str(LEGACY_SIGNED_TRANSACTION).unwrap();
+        legacy["transaction"]["amount"] = "100".into();
+        legacy["transaction"]["nonce"] = "1".into();
+        let err = serde_json::from_value::<SignedTransactionJson>(legacy)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("object format is no longer accepted"), "{err}");
+    }
+
+    #[test]
+    fn test_malformed_fields() {
+        for quantity in ["", "-1", "1.5", "0x10", "18446744073709551616"] {
+            assert!(serde_json::from_value::<Quantity>(quantity.into()).is_err());
+        }
+        assert_eq!(
+            serde_json::from_value::<Quantity>("18446744073709551615".into()).unwrap(),
+            Quantity(u64::MAX)
+        );
+
+        let mut json: Value = serde_json::from_str(SIGNED_TRANSACTION).unwrap();
+        let signature = json["signature"].as_str().unwrap().to_string();
+        for bad in [&signature[2..], &signature[..signature.len() - 2], "0xzz"] {
+            json["signature"] = bad.into();
+            assert!(serde_json::from_value::<SignedTransactionJson>(json.clone()).is_err());
+        }
+
+        // A typo in a checksummed address is caught.
+        let mut json: Value = serde_json::from_str(SIGNED_TRANSACTION).unwrap();
+        json["transaction"]["destination"] = "0x885ee92EebdA03540066a25A57cC625BbEE15d5A".into();
+        assert!(serde_json::from_value::<SignedTransactionJson>(json).is_err());
+    }
+
+    #[test]
+    fn test_transaction_kinds() {
+        let transfer: RollupTransactionJson = serde_json::from_str(SIGNED_TRANSACTION).unwrap();
+        assert_eq!(
+            transfer,
+            RollupTransactionJson::Transfer(serde_json::from_str(SIGNED_TRANSACTION).unwrap())
+        );
+        // Errors in a transfer are still reported field by field.
+        let err = serde_json::from_str::<RollupTransactionJson>(LEGACY_SIGNED_TRANSACTION)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("quantities are decimal strings"), "{err}");
+
+        let register = serde_json::json!({
+            "signers": [checksummed(&signer()), checksummed(&transaction().destination)],
+            "threshold": 2,
+        });
+        let RollupTransactionJson::RegisterMultisig(json) =
+            serde_json::from_value(register.clone()).unwrap()
+        else {
+            panic!("not read as a registration");
+        };
+        assert_eq!(
+            RegisterMultisig::from(json).config().unwrap().signers(),
+            [transaction().destination, signer()]
+        );
+
+        let mut spend: Value = serde_json::from_str(SIGNED_TRANSACTION).unwrap();
+        spend["signatures"] = Value::Array(vec![spend["signature"].take()]);
+        spend.as_object_mut().unwrap().remove("signature");
+        spend["account"] = checksummed(&signer()).into();
+        let txn: RollupTransactionJson = serde_json::from_value(spend.clone()).unwrap();
+        assert!(matches!(txn, RollupTransactionJson::MultisigTransfer(_)));
+        assert_eq!(serde_json::to_value(&txn).unwrap(), spend);
+
+        spend["threshold"] = 1.into();
+        assert!(serde_json::from_value::<RollupTransactionJson>(spend).is_err());
+        let mut send: Value = serde_json::from_str(SIGNED_TRANSACTION).unwrap();
+        send.as_object_mut().unwrap().remove("transaction");
+        send["message"] = serde_json::json!({
+            "dest_vm": "2",
+            "recipient": checksummed(&signer()),
+            "nonce": "1",
+            "payload": "0x6869",
+        });
+        let txn: RollupTransactionJson = serde_json::from_value(send.clone()).unwrap();
+        let RollupTransactionJson::OutboundMessage(json) = &txn else {
+            panic!("not read as a message");
+        };
+        assert_eq!(json.message.payload.as_ref(), b"hi");
+        assert_eq!(serde_json::to_value(&txn).unwrap(), send);
+
+        let mut change = send;
+        change.as_object_mut().unwrap().remove("message");
+        change["set_min_fee"] = serde_json::json!({
+            "value": "10",
+            "effective_height": "5",
+            "nonce": "1",
+            "vm_id": "1",
+        });
+        let txn: RollupTransactionJson = serde_json::from_value(change.clone()).unwrap();
+        let RollupTransactionJson::SetMinFee(json) = &txn else {
+            panic!("not read as a fee change");
+        };
+        assert_eq!(
+            SetMinFee::from(json.set_min_fee.clone()).effective_height,
+            5
+        );
+        assert_eq!(serde_json::to_value(&txn).unwrap(), change);
+    }
+
+    #[test]
+    fn test_signature_not_normalized() {
+        // A high-s signature sent through the API reaches verification as is, and is rejected.
+        let mut json: SignedTransactionJson = serde_json::from_str(SIGNED_TRANSACTION).unwrap();
+        let order = U256::from_dec_str(
+            "115792089237316195423570985008687907852837564279074904382605163141518161494337",
+        )
+        .unwrap();
+        json.signature.0.s = order - json.signature.0.s;
+        json.signature.0.v = 28;
+        assert!(SignedTransaction::from(json).verify().is_err());
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Recovery of contract events missed by a websocket log subscription.
+//!
+//! The executor learns of new HotShot blocks from a websocket subscription to the `NewBlocks`
+//! events of the HotShot contract. A subscription only delivers the events emitted while it is
+//! connected, so whatever is emitted while the websocket reconnects is silently lost, and a missed
+//! `NewBlocks` event would leave the blocks it announces unexecuted. The executor therefore keeps
+//! track of the events it has processed, and queries the L1 for the range of blocks since the last
+//! of them with `eth_getLogs`, both periodically and as soon as an event shows that some are
+//! missing. Events found this way are processed in order before anything newer, and events seen
+//! twice, once from the subscription and once from a query, are recognized by their transaction
+//! hash and log index and only processed once.
+//!
+//! The other contract events the node consumes, deposits and state updates, are only ever read
+//! with `eth_getLogs` over ranges of L1 blocks which follow on from the last range read, so they
+//! cannot have gaps.
+
+use ethers::types::{H256, U256};
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use crate::error::ExecutorError;
+
+/// How the executor recovers events its log subscription missed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventBackfillOptions {
+    /// Time between queries for events the subscription may have missed, even if nothing shows
+    /// that it has.
+    pub interval: Duration,
+    /// Number of L1 blocks before the last processed event which each query covers again, so that
+    /// events from blocks the L1 node had not yet served to the subscription are still found.
+    pub window_blocks: u64,
+    /// Most L1 blocks covered by one `eth_getLogs` request. Providers limit the range a single
+    /// request may cover.
+    pub batch_blocks: u64,
+}
+
+impl Default for EventBackfillOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            window_blocks: 64,
+            batch_blocks: 1000,
+        }
+    }
+}
+
+/// Where an event was emitted, which identifies it.
+pub type EventId = (u64, H256, U256);
+
+/// The events processed from recent L1 blocks, so that an event delivered again by a query which
+/// overlaps them is not processed twice.
+#[derive(Clone, Debug, Default)]
+pub struct SeenEvents {
+    /// The L1 block of the last event processed, or the block processing started from.
+    last_block: u64,
+    /// The L1 block, transaction hash and log index of each event processed from the
+    /// backfill window.
+    seen: BTreeSet<EventId>,
+}
+
+impl SeenEvents {
+    /// Start processing events from L1 block `from_block`.
+    pub fn new(from_block: u64) -> Self {
+        Self {
+            last_block: from_block,
+            seen: Default::default(),
+        }
+    }
+
+    /// The L1 block of the last event processed.
+    pub fn last_block(&self) -> u64 {
+        self.last_block
+    }
+
+    /// Whether the event at `id` has been processed.
+    pub fn contains(&self, id: &EventId) -> bool {
+        self.seen.contains(id)
+    }
+
+    /// Record that the event at `id` has been processed, forgetting events from blocks before the
+    /// backfill window of `opt`, which no query covers again.
+    ///
+    /// Returns `false` if it had already been processed.
+    pub fn insert(&mut self, id: EventId, opt: &EventBackfillOptions) -> bool {
+        if !self.seen.insert(id) {
+            return false;
+        }
+        self.last_block = self.last_block.max(id.0);
+        let horizon = self.window_start(opt);
+        self.seen = self.seen.split_off(&(horizon, H256::zero(), U256::zero()));
+        true
+    }
+
+    /// The first L1 block to query for missed events: the start of the backfill window of `opt`
+    /// behind the last event processed.
+    pub fn window_start(&self, opt: &EventBackfillOptions) -> u64 {
+        self.last_block.saturating_sub(opt.window_blocks)
+    }
+}
+
+/// Query the L1 blocks `blocks` for events in ranges of at most `batch_blocks` blocks, returning
+/// everything found, in the order the ranges were queried.
+pub async fn fetch_in_batches<T, F, Fut>(
+    blocks: RangeInclusive<u64>,
+    batch_blocks: u64,
+    mut query: F,
+) -> Result<Vec<T>, ExecutorError>
+where
+    F: FnMut(RangeInclusive<u64>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, ExecutorError>>,
+{
+    let batch_blocks = batch_blocks.max(1);
+    let mut events = vec![];
+    let mut from = *blocks.start();
+    while from <= *blocks.end() {
+        let to = from.saturating_add(batch_blocks - 1).min(*blocks.end());
+        events.extend(query(from..=to).await?);
+        if to == u64::MAX {
+            break;
+        }
+        from = to + 1;
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::ready;
+
+    #[test]
+    fn test_seen_events() {
+        let opt = EventBackfillOptions {
+            window_blocks: 10,
+            ..Default::default()
+        };
+        let id = |block: u64, index: u64| (block, H256::repeat_byte(block as u8), index.into());
+        let mut seen = SeenEvents::new(5);
+        assert_eq!(seen.window_start(&opt), 0);
+
+        assert!(seen.insert(id(8, 0), &opt));
+        assert!(seen.insert(id(8, 1), &opt));
+        assert!(!seen.insert(id(8, 0), &opt));
+        assert!(seen.contains(&id(8, 1)));
+        assert_eq!(seen.last_block(), 8);
+
+        // Events from before the window are forgotten once it moves past them.
+        assert!(seen.insert(id(20, 0), &opt));
+        assert_eq!(seen.window_start(&opt), 10);
+        assert!(!seen.contains(&id(8, 0)));
+        assert!(seen.contains(&id(20, 0)));
+
+        // An event delivered late does not move the window back.
+        assert!(seen.insert(id(15, 3), &opt));
+        assert_eq!(seen.last_block(), 20);
+    }
+
+    #[async_std::test]
+    async fn test_fetch_in_batches() {
+        let mut queried = vec![];
+        let events = fetch_in_batches(3..=12, 4, |blocks| {
+            queried.push(blocks.clone());
+            ready(Ok(blocks.collect::<Vec<_>>()))
+        })
+        .await
+        .unwrap();
+        assert_eq!(queried, [3..=6, 7..=10, 11..=12]);
+        assert_eq!(events, (3..=12).collect::<Vec<_>>());
+
+        let err = fetch_in_batches(0..=10, 5, |blocks| {
+            ready(if *blocks.start() == 0 {
+                Ok(vec![()])
+            } else {
+                Err(ExecutorError::L1 {
+                    reason: "unavailable".into(),
+                })
+            })
+        })
+        .await
+        .unwrap_err();
+        assert!(err.is_retryable());
+    }
+}
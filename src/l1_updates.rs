@@ -0,0 +1,201 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! The history of the state updates accepted by the rollup contract.
+//!
+//! Each `StateUpdate` event of the rollup contract is the authoritative record of a batch of blocks
+//! being verified: how far, to which state, and in which L1 transaction. The executor records every
+//! one it sees in an [`L1UpdateHistory`], optionally kept in a file so that it survives restarts,
+//! and the API serves the history under `l1-updates`.
+
+use async_std::fs::OpenOptions;
+use async_std::io::WriteExt;
+use async_std::sync::{Arc, RwLock};
+use ethers::types::{H256, U256};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::logging::EXECUTOR;
+
+/// Most updates returned by one request to the API.
+pub const MAX_L1_UPDATES_PAGE: usize = 100;
+
+/// A state update accepted by the rollup contract.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1Update {
+    /// Number of rollup blocks verified once the update was accepted.
+    pub block_height: u64,
+    /// Commitment to the rollup state after `block_height` blocks.
+    pub state_commitment: U256,
+    /// L1 block in which the update was accepted.
+    pub l1_block: u64,
+    /// L1 transaction which sent the update.
+    pub l1_tx: H256,
+    /// Whether this executor sent the update. Unknown for updates accepted before the executor
+    /// started, and for every update seen by a read-only executor.
+    pub sent_by_us: Option<bool>,
+}
+
+/// The state updates seen so far, in the order the contract accepted them, shared between the
+/// executor which records them and the API which serves them.
+#[derive(Clone, Debug, Default)]
+pub struct L1UpdateHistory {
+    inner: Arc<RwLock<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    updates: Vec<L1Update>,
+    /// File each update is appended to, one JSON object per line.
+    path: Option<PathBuf>,
+}
+
+impl L1UpdateHistory {
+    /// A history kept in the file at `path`, starting with the updates already recorded there.
+    ///
+    /// A line which cannot be read, such as the last line of a file whose writer was interrupted,
+    /// is skipped with a warning; the update is recorded again once it is seen on L1.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            // Start the next update on a line of its own.
+            fs::OpenOptions::new()
+                .append(true)
+                .open(&path)?
+                .write_all(b"\n")?;
+        }
+        let mut updates: Vec<L1Update> = vec![];
+        for (i, line) in contents.lines().enumerate() {
+            match serde_json::from_str::<L1Update>(line) {
+                Ok(update)
+                    if updates
+                        .last()
+                        .map_or(true, |last| last.block_height < update.block_height) =>
+                {
+                    updates.push(update)
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    tracing::warn!(
+                        target: EXECUTOR,
+                        "skipping line {} of L1 update history {path:?}: {err}",
+                        i + 1
+                    )
+                }
+            }
+        }
+        Ok(Self {
+            inner: Arc::new(RwLock::new(Inner {
+                updates,
+                path: Some(path),
+            })),
+        })
+    }
+
+    /// Record `update`, unless an update to the same or a later height already is. Returns whether
+    /// it was recorded.
+    pub async fn record(&self, update: L1Update) -> io::Result<bool> {
+        let mut inner = self.inner.write().await;
+        if inner
+            .updates
+            .last()
+            .is_some_and(|last| last.block_height >= update.block_height)
+        {
+            return Ok(false);
+        }
+        if let Some(path) = &inner.path {
+            let mut line = serde_json::to_string(&update)?;
+            line.push('\n');
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+            file.flush().await?;
+        }
+        inner.updates.push(update);
+        Ok(true)
+    }
+
+    /// At most `limit` of the updates which verified blocks at or after height `from`, the oldest
+    /// first.
+    pub async fn page(&self, from: u64, limit: usize) -> Vec<L1Update> {
+        let inner = self.inner.read().await;
+        let start = inner
+            .updates
+            .partition_point(|update| update.block_height <= from);
+        inner.updates[start..].iter().take(limit).copied().collect()
+    }
+
+    /// The latest update recorded.
+    pub async fn last(&self) -> Option<L1Update> {
+        self.inner.read().await.updates.last().copied()
+    }
+
+    /// Number of updates recorded.
+    pub async fn len(&self) -> usize {
+        self.inner.read().await.updates.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn update(block_height: u64) -> L1Update {
+        L1Update {
+            block_height,
+            state_commitment: block_height.into(),
+            l1_block: 10 + block_height,
+            l1_tx: H256::from_low_u64_be(block_height),
+            sent_by_us: Some(true),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_l1_update_history() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("l1-updates.jsonl");
+        let history = L1UpdateHistory::open(&path).unwrap();
+        assert!(history.is_empty().await);
+
+        for height in [2, 3, 5] {
+            assert!(history.record(update(height)).await.unwrap());
+        }
+        // An update seen again, after a restart for example, is not recorded twice.
+        assert!(!history.record(update(3)).await.unwrap());
+        assert_eq!(history.len().await, 3);
+
+        // Pages start with the first update verifying a block at or after the height asked for.
+        assert_eq!(history.page(0, 10).await, [update(2), update(3), update(5)]);
+        assert_eq!(history.page(2, 10).await, [update(3), update(5)]);
+        assert_eq!(history.page(3, 1).await, [update(5)]);
+        assert_eq!(history.page(5, 10).await, []);
+
+        // The history survives a restart, even if the last line was cut short.
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"block_height\":").unwrap();
+        let reopened = L1UpdateHistory::open(&path).unwrap();
+        assert_eq!(reopened.page(0, 10).await, history.page(0, 10).await);
+        assert!(reopened.record(update(8)).await.unwrap());
+        let reopened = L1UpdateHistory::open(&path).unwrap();
+        assert_eq!(reopened.last().await, Some(update(8)));
+        assert_eq!(reopened.len().await, 4);
+    }
+}
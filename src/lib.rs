@@ -4,21 +4,82 @@
 // You should have received a copy of the MIT License
 // along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
 
+use api::{
+    AdminToken, DEFAULT_MAX_SUBMIT_BODY_BYTES, DEFAULT_READ_TIMEOUT, DEFAULT_SUBMIT_QUEUE_CAPACITY,
+    DEFAULT_SUBMIT_TIMEOUT,
+};
+use balance_history::DEFAULT_BALANCE_CHECKPOINT_INTERVAL;
 use clap::Parser;
 use derive_more::{From, Into};
-use ethers::types::Address;
+use ethers::types::{Address, U256};
+use invariants::DEFAULT_INVARIANT_INTERVAL;
+use logging::LogFormat;
+use mode::RunMode;
 use sequencer::{Vm, VmId};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use submission::{DEFAULT_FORWARD_WORKERS, DEFAULT_MAX_PENDING_PER_SENDER};
 use surf_disco::Url;
-use transaction::SignedTransaction;
+use transaction::RollupTransaction;
+use webhook::WebhookEvent;
 
+pub use example_l2_transaction::{address, fee, message, multisig, transaction};
+pub use prover::{verify_block_proof, verify_encoded_block_proof};
+
+pub mod alert;
 pub mod api;
+pub mod archive;
+pub mod audit;
+pub mod backfill;
+pub mod balance_history;
+pub mod build_info;
+#[cfg(feature = "client")]
+pub mod builder;
+pub mod cache;
+pub mod chain;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod commitment_task;
+pub mod commitments;
+pub mod config;
+pub mod cursor;
+pub mod deposit;
+pub mod doctor;
 pub mod error;
 pub mod executor;
-mod prover;
+pub mod follower;
+pub mod forced;
+mod gateway;
+pub mod hooks;
+pub mod inbox;
+pub mod invariants;
+pub mod json;
+pub mod l1_events;
+pub mod l1_updates;
+#[cfg(feature = "client")]
+pub mod loadgen;
+pub mod logging;
+pub mod metrics;
+pub mod migrations;
+pub mod mode;
+pub mod prover;
+pub mod reconcile;
+pub mod replay;
+pub mod replicas;
+pub mod retention;
+pub mod schema;
 pub mod seed;
+pub mod shutdown;
+pub mod snapshot;
 pub mod state;
-pub mod transaction;
+pub mod status;
+pub mod submission;
+#[cfg(test)]
+pub(crate) mod testing;
 pub mod utils;
+pub mod vectors;
+pub mod watchdog;
+pub mod webhook;
 
 #[derive(Parser, Clone, Debug)]
 pub struct Options {
@@ -26,6 +87,59 @@ pub struct Options {
     #[clap(short, long, env = "ESPRESSO_DEMO_ROLLUP_PORT", default_value = "8084")]
     pub api_port: u16,
 
+    /// Check the configuration against the sequencer, the L1 and the contracts, print a report,
+    /// and exit instead of starting the node.
+    ///
+    /// Exits with a nonzero status if any check fails.
+    #[clap(long)]
+    pub check: bool,
+
+    /// Which parts of the node to run: `full` runs the executor and the API, `executor` only the
+    /// executor, and `api` only the API, following an executor running elsewhere.
+    ///
+    /// An API node follows the snapshots the executor writes to `--snapshot-dir`, or the block
+    /// stream of the executor's API at `--executor-api-url`. It sends nothing to L1, so the
+    /// executor's L1 options do not apply to it.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_MODE",
+        value_enum,
+        default_value_t = RunMode::Full
+    )]
+    pub mode: RunMode,
+
+    /// URL of the API of the executor an API node follows, replaying each block it executes from
+    /// the query service at `--sequencer-url`. Only used in api mode.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_EXECUTOR_API_URL")]
+    pub executor_api_url: Option<Url>,
+
+    /// Milliseconds between checks by an API node for a newer snapshot or block of the executor it
+    /// follows.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_FOLLOW_INTERVAL_MS",
+        default_value = "1000"
+    )]
+    pub follow_interval_ms: u64,
+
+    /// Which events to log, such as `info,rollup::executor=debug,ethers=warn`.
+    ///
+    /// Every event of the node itself has one of the targets `rollup::executor`, `rollup::api`,
+    /// `rollup::state`, `rollup::bridge` or `rollup::node`. If this is not set, the filter is read
+    /// from `RUST_LOG`, and defaults to `info`.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_LOG_FILTER")]
+    pub log_filter: Option<String>,
+
+    /// How to write log lines: `pretty` for human-readable text, or `json` for one JSON object per
+    /// event.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_LOG_FORMAT",
+        value_enum,
+        default_value_t = LogFormat::Pretty
+    )]
+    pub log_format: LogFormat,
+
     /// URL of a HotShot sequencer node.
     #[clap(
         long,
@@ -34,6 +148,18 @@ pub struct Options {
     )]
     pub sequencer_url: Url,
 
+    /// Comma-separated list of URLs of other sequencer nodes whose query services replicate that
+    /// of `--sequencer-url`.
+    ///
+    /// The executor reads blocks from `--sequencer-url` while it answers, and fails over to these,
+    /// in order, when it does not.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_QUERY_REPLICA_URLS",
+        value_delimiter = ','
+    )]
+    pub query_replica_urls: Vec<Url>,
+
     /// URL of layer 1 Ethereum JSON-RPC provider.
     #[clap(
         long,
@@ -50,6 +176,49 @@ pub struct Options {
     )]
     pub l1_ws_provider: Url,
 
+    /// URL of a layer 1 JSON-RPC endpoint to send proof transactions to, instead of the HTTP
+    /// provider.
+    ///
+    /// Use this to send proofs through a private relay or a paid transaction service. All reads
+    /// still go to the HTTP provider.
+    #[clap(long, env = "ESPRESSO_DEMO_L1_SUBMISSION_URL")]
+    pub l1_submission_url: Option<Url>,
+
+    /// Chain ID of the layer 1.
+    ///
+    /// If set, the executor refuses to start unless both L1 providers report this chain, so a
+    /// misconfigured or malicious provider cannot get it to sign transactions for another chain.
+    #[clap(long, env = "ESPRESSO_DEMO_L1_CHAIN_ID")]
+    pub l1_chain_id: Option<u64>,
+
+    /// Seconds between queries of the L1 for HotShot blocks whose events the websocket
+    /// subscription to the HotShot contract missed, which it may while it reconnects.
+    ///
+    /// Blocks are also queried for as soon as an event shows that the ones before it were missed.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_EVENT_BACKFILL_INTERVAL_SECS",
+        default_value = "30"
+    )]
+    pub event_backfill_interval_secs: u64,
+
+    /// Number of L1 blocks before the last event received which each query for missed events
+    /// covers again.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_EVENT_BACKFILL_WINDOW_BLOCKS",
+        default_value = "64"
+    )]
+    pub event_backfill_window_blocks: u64,
+
+    /// Most L1 blocks covered by each `eth_getLogs` request made to query for missed events.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_EVENT_BACKFILL_BATCH_BLOCKS",
+        default_value = "1000"
+    )]
+    pub event_backfill_batch_blocks: u64,
+
     /// Address of HotShot contract on layer 1.
     #[clap(
         long,
@@ -73,9 +242,555 @@ pub struct Options {
     /// that will send proofs to the rollup contract
     #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_ACCOUNT_INDEX", default_value = "1")]
     pub rollup_account_index: u32,
+
+    /// Run the HotShot commitment task alongside the executor, instead of relying on one run
+    /// elsewhere. It posts the commitments of HotShot blocks to the HotShot contract, which the
+    /// executor waits for before executing them.
+    #[clap(long, env = "ESPRESSO_DEMO_WITH_COMMITMENT_TASK")]
+    pub with_commitment_task: bool,
+
+    /// Index of the funded account, derived from the rollup mnemonic, which the commitment task
+    /// sends HotShot commitments from. It must not be the account proofs are sent from.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_COMMITMENT_ACCOUNT_INDEX",
+        default_value = "2"
+    )]
+    pub commitment_account_index: u32,
+
+    /// VM ID of the rollup, which is the namespace of its transactions in the sequencer.
+    ///
+    /// Rollups sharing a sequencer must have different VM IDs.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_VM_ID", default_value = "1")]
+    pub vm_id: u64,
+
+    /// Comma-separated list of assets which may be deposited into the rollup from L1.
+    ///
+    /// Each asset is the address of an ERC-20 token, or the zero address for ether. Part of the
+    /// genesis chain configuration, so every executor of the rollup must list the same assets.
+    /// Deposits of any other asset are ignored. Not used when resuming from a snapshot, which
+    /// carries its own configuration.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DEPOSIT_ASSETS",
+        value_delimiter = ','
+    )]
+    pub deposit_assets: Vec<Address>,
+
+    /// Comma-separated list of VM IDs of rollups whose messages to this one are delivered.
+    ///
+    /// Part of the genesis chain configuration, as for `--deposit-assets`: every executor of the
+    /// rollup must list the same rollups, and messages from any other rollup are ignored.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_MESSAGE_SOURCES",
+        value_delimiter = ','
+    )]
+    pub message_sources: Vec<u64>,
+
+    /// Reject transfers to the zero address instead of burning the amount sent.
+    ///
+    /// Part of the genesis chain configuration, as for `--deposit-assets`.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_REJECT_ZERO_DESTINATION")]
+    pub reject_zero_destination: bool,
+
+    /// Comma-separated list of addresses transfers to which are rejected, such as the rollup
+    /// contract's own.
+    ///
+    /// Part of the genesis chain configuration, as for `--deposit-assets`.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DENIED_DESTINATIONS",
+        value_delimiter = ','
+    )]
+    pub denied_destinations: Vec<Address>,
+
+    /// Account which may change the rollup's minimum fee, with signed fee change transactions.
+    ///
+    /// Without one the minimum fee stays 0. Part of the genesis chain configuration, as for
+    /// `--deposit-assets`.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_FEE_ADMIN")]
+    pub fee_admin: Option<Address>,
+
+    /// Number of L1 blocks which must be built on a deposit before the rollup credits it.
+    ///
+    /// Deposits are credited from finalized L1 blocks which are also this far below the L1 head a
+    /// rollup block references, so that an L1 reorg cannot take back a deposit already spent on
+    /// the rollup. Part of the genesis chain configuration, as for `--deposit-assets`.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DEPOSIT_CONFIRMATIONS",
+        default_value = "0"
+    )]
+    pub deposit_confirmations: u64,
+
+    /// Apply transactions enqueued on the rollup contract, so that the sequencer cannot keep a
+    /// transaction out of the rollup for good.
+    ///
+    /// Each block applies the transactions enqueued in the finalized L1 blocks it references, ahead
+    /// of its own, and the submit route offers to enqueue a transaction when it cannot reach the
+    /// sequencer. Part of the genesis chain configuration, as for `--deposit-assets`.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_FORCED_INCLUSION")]
+    pub forced_inclusion: bool,
+
+    /// Seconds a verified state update can be challenged before it becomes final.
+    ///
+    /// Zero, the default, deploys the rollup contract in validity mode, where each verified update
+    /// is final at once. Otherwise the contract runs in optimistic mode and the executor finalizes
+    /// each update once its window has passed. Only used when deploying a new rollup contract;
+    /// an existing contract keeps the window it was deployed with.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_CHALLENGE_WINDOW_SECS",
+        default_value = "0"
+    )]
+    pub challenge_window_secs: u64,
+
+    /// Comma-separated list of origins allowed to make cross-origin requests to the Rollup API.
+    ///
+    /// Browser front-ends served from any other origin are blocked. Use `*` to explicitly allow
+    /// every origin.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_CORS_ALLOWED_ORIGINS",
+        value_delimiter = ','
+    )]
+    pub cors_allowed_origins: Vec<String>,
+
+    /// Bearer token required by the admin routes of the Rollup API.
+    ///
+    /// If not set, admin routes reject every request.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_ADMIN_TOKEN")]
+    pub admin_token: Option<AdminToken>,
+
+    /// Largest request body, in bytes, accepted by the transaction submission route.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_MAX_SUBMIT_BODY_BYTES",
+        default_value_t = DEFAULT_MAX_SUBMIT_BODY_BYTES
+    )]
+    pub max_submit_body_bytes: usize,
+
+    /// Number of blocks the local state may lag behind the rollup contract before read endpoints
+    /// report that the node is still syncing.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_MAX_SYNC_LAG", default_value = "0")]
+    pub max_sync_lag: u64,
+
+    /// Number of submitted transactions which may wait to be forwarded to the sequencer before
+    /// further submissions are refused.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_SUBMIT_QUEUE_CAPACITY",
+        default_value_t = DEFAULT_SUBMIT_QUEUE_CAPACITY
+    )]
+    pub submit_queue_capacity: usize,
+
+    /// Number of workers forwarding submitted transactions to the sequencer. They share one
+    /// client, so connections to the sequencer are reused.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_FORWARD_WORKERS",
+        default_value_t = DEFAULT_FORWARD_WORKERS
+    )]
+    pub forward_workers: usize,
+
+    /// Number of submitted transactions one sender may have waiting to be executed before its
+    /// further submissions are refused.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_MAX_PENDING_PER_SENDER",
+        default_value_t = DEFAULT_MAX_PENDING_PER_SENDER
+    )]
+    pub max_pending_per_sender: usize,
+
+    /// Compress the proofs served by the Rollup API with zstd, whenever that makes them smaller.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_PROOF_COMPRESSION")]
+    pub proof_compression: bool,
+
+    /// Tolerate submitted transactions which do not strictly follow the JSON format: drop fields it
+    /// does not have, and read JSON numbers as quantities. Meant for giving clients of the older
+    /// format time to move.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_RELAXED_REQUESTS")]
+    pub relaxed_requests: bool,
+
+    /// Milliseconds allowed for Rollup API routes which read the local state.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_READ_TIMEOUT_MS",
+        default_value_t = DEFAULT_READ_TIMEOUT.as_millis() as u64
+    )]
+    pub read_timeout_ms: u64,
+
+    /// Read balances, the latest block summary and the node's info from the state on every Rollup
+    /// API request, instead of caching them between blocks.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_DISABLE_READ_CACHE")]
+    pub disable_read_cache: bool,
+
+    /// Milliseconds allowed for the sequencer to accept a transaction submitted to the Rollup API.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_SUBMIT_TIMEOUT_MS",
+        default_value_t = DEFAULT_SUBMIT_TIMEOUT.as_millis() as u64
+    )]
+    pub submit_timeout_ms: u64,
+
+    /// Number of L1 blocks to wait for a proof transaction to be mined before replacing it with
+    /// one paying higher fees.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_PROOF_CONFIRMATION_BLOCKS",
+        default_value = "5"
+    )]
+    pub proof_confirmation_blocks: u64,
+
+    /// Percentage by which each replacement proof transaction raises the fees.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_PROOF_FEE_BUMP_PERCENT",
+        default_value = "20"
+    )]
+    pub proof_fee_bump_percent: u64,
+
+    /// Number of times a proof transaction is replaced before the executor gives up.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_PROOF_MAX_FEE_BUMPS",
+        default_value = "5"
+    )]
+    pub proof_max_fee_bumps: u32,
+
+    /// Number of times the proof of a batch of blocks may fail before the executor gives up on it
+    /// and restarts, resyncing with the rollup contract.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_PROOF_MAX_ATTEMPTS",
+        default_value = "10"
+    )]
+    pub proof_max_attempts: u32,
+
+    /// Seconds between checks of the balance of the L1 account proofs are sent from.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_BALANCE_CHECK_INTERVAL_SECS",
+        default_value = "60"
+    )]
+    pub balance_check_interval_secs: u64,
+
+    /// Balance in wei of the L1 account proofs are sent from below which the executor warns that
+    /// it needs funding.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_LOW_BALANCE_WEI",
+        default_value = "100000000000000000"
+    )]
+    pub low_balance_wei: u128,
+
+    /// Number of blocks the executor may stay behind the HotShot contract before it raises an
+    /// alert. Never alert, if not set.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_MAX_SEQUENCING_LAG")]
+    pub max_sequencing_lag: Option<u64>,
+
+    /// Number of executed blocks the rollup contract may stay behind in verifying before the
+    /// executor raises an alert. Never alert, if not set.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_MAX_VERIFICATION_LAG")]
+    pub max_verification_lag: Option<u64>,
+
+    /// Seconds a lag must stay above its threshold before the executor raises an alert.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_LAG_ALERT_SECS",
+        default_value = "60"
+    )]
+    pub lag_alert_secs: u64,
+
+    /// Milliseconds a block may take to execute before the executor logs it as slow, with the time
+    /// spent in each phase of executing it. Never, if set to 0.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_SLOW_BLOCK_MS",
+        default_value = "5000"
+    )]
+    pub slow_block_ms: u64,
+
+    /// Milliseconds a block may take to execute before the executor gives up on it, quarantines it
+    /// and stops executing, raising a critical alert. The quarantined block is served under
+    /// `admin/quarantine`. Blocks are not limited, if not set.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_BLOCK_LIMIT_MS")]
+    pub block_limit_ms: Option<u64>,
+
+    /// Number of blocks between checks of the invariants of the state, such as the balances adding
+    /// up to the supply. A block after which the state violates one is not proven, and the
+    /// executor stops there, raising a critical alert. Every block in debug builds by default, and
+    /// every 16 in release builds. Never, if set to 0.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_INVARIANT_CHECK_INTERVAL",
+        default_value_t = DEFAULT_INVARIANT_INTERVAL
+    )]
+    pub invariant_check_interval: u64,
+
+    /// Directory where the executor writes evidence if a block does not match its commitment on
+    /// layer 1.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_DIAGNOSTICS_DIR",
+        default_value = "diagnostics"
+    )]
+    pub diagnostics_dir: PathBuf,
+
+    /// File where the executor records its progress, so it can resume quickly after a restart.
+    ///
+    /// If not set, a restarted executor recovers its position from the rollup contract.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_CURSOR_PATH")]
+    pub cursor_path: Option<PathBuf>,
+
+    /// Snapshot of a trusted state for the executor to start from, instead of the newest snapshot
+    /// in `--snapshot-dir` or the initial state.
+    ///
+    /// The state must have executed `--start-height` blocks and have the commitment
+    /// `--start-commitment`, as must the state the rollup contract recorded at that height, if it
+    /// is known. The executor cursor is reset to the start point.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_START_SNAPSHOT",
+        requires_all = ["start_height", "start_commitment"]
+    )]
+    pub start_snapshot: Option<PathBuf>,
+
+    /// Number of blocks the state in `--start-snapshot` has executed.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_START_HEIGHT",
+        requires = "start_snapshot"
+    )]
+    pub start_height: Option<u64>,
+
+    /// Commitment the state in `--start-snapshot` must have, in hex, as the rollup contract
+    /// stores it.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_START_COMMITMENT",
+        requires = "start_snapshot"
+    )]
+    pub start_commitment: Option<U256>,
+
+    /// Address of a rollup contract deployed by an earlier run of this node.
+    ///
+    /// If not set, a new rollup contract is deployed, and the node starts from the initial state
+    /// since snapshots left by earlier runs do not match the new contract.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_ADDRESS")]
+    pub rollup_address: Option<Address>,
+
+    /// Mnemonic phrase for the wallet which deploys the rollup contract, and so owns it.
+    ///
+    /// Only used when deploying a new rollup contract. The account must be funded with ETH on the
+    /// layer 1.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_DEPLOYER_MNEMONIC",
+        default_value = "test test test test test test test test test test test junk"
+    )]
+    pub deployer_mnemonic: String,
+
+    /// Index of the deployer's account derived from the deployer mnemonic.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_DEPLOYER_ACCOUNT_INDEX",
+        default_value = "0"
+    )]
+    pub deployer_account_index: u32,
+
+    /// Number of L1 blocks a new rollup contract must be buried under before the node uses it.
+    #[clap(long, env = "ESPRESSO_DEMO_DEPLOY_CONFIRMATIONS", default_value = "1")]
+    pub deploy_confirmations: usize,
+
+    /// Directory where snapshots of the rollup state are written.
+    ///
+    /// On startup, the node resumes from the newest snapshot and only replays later blocks. If not
+    /// set, no snapshots are written and the node always replays from the initial state.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_SNAPSHOT_DIR")]
+    pub snapshot_dir: Option<PathBuf>,
+
+    /// Number of blocks executed between state snapshots.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_SNAPSHOT_INTERVAL",
+        default_value = "100"
+    )]
+    pub snapshot_interval: u64,
+
+    /// Number of state snapshots kept on disk.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_SNAPSHOTS_RETAINED",
+        default_value = "3"
+    )]
+    pub snapshots_retained: usize,
+
+    /// Number of recent blocks whose transaction receipts are kept.
+    ///
+    /// The receipts, summaries and proofs of older blocks are pruned in the background, except
+    /// those of blocks not yet finalized on layer 1, and the API reports them as pruned.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_RECEIPTS_RETAINED_BLOCKS",
+        default_value = "256"
+    )]
+    pub receipts_retained_blocks: u64,
+
+    /// Number of recent blocks whose summaries are kept.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_HISTORY_RETAINED_BLOCKS",
+        default_value = "65536"
+    )]
+    pub history_retained_blocks: u64,
+
+    /// Number of blocks between checkpoints of the balance of every account, which the Rollup API
+    /// serves as the balance history of each account. Watched accounts are also checkpointed after
+    /// every block which changes their balance. Checkpoints are kept as long as block summaries.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_BALANCE_CHECKPOINT_INTERVAL",
+        default_value_t = DEFAULT_BALANCE_CHECKPOINT_INTERVAL
+    )]
+    pub balance_checkpoint_interval: u64,
+
+    /// Number of recent blocks whose proofs are kept.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_PROOFS_RETAINED_BLOCKS",
+        default_value = "65536"
+    )]
+    pub proofs_retained_blocks: u64,
+
+    /// Do not backfill the summaries and receipts of blocks below the snapshot the node resumed
+    /// from.
+    ///
+    /// By default, a node which resumes from a snapshot replays the blocks below it from the query
+    /// service in the background, so that their summaries and receipts can be served. Progress is
+    /// saved in `backfill.json` in the snapshot directory.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_SKIP_BACKFILL")]
+    pub skip_backfill: bool,
+
+    /// Milliseconds to wait after each backfilled block, to limit the load on the query service.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_BACKFILL_DELAY_MS",
+        default_value = "50"
+    )]
+    pub backfill_delay_ms: u64,
+
+    /// Do not read back the state commitment from the rollup contract after each accepted proof.
+    ///
+    /// The check costs one `eth_call` per batch and stops the executor if another prover has
+    /// updated the contract with a different state.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_SKIP_COMMITMENT_CHECK")]
+    pub skip_commitment_check: bool,
+
+    /// Do not check that each HotShot block follows from the one before it.
+    ///
+    /// By default, blocks which are out of sequence, or whose timestamps or L1 references go
+    /// backwards, stop the executor even if they match their commitments on L1.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_SKIP_CHAIN_VERIFICATION")]
+    pub skip_chain_verification: bool,
+
+    /// Recover the senders of each block's transactions in parallel before executing the block.
+    ///
+    /// By default each sender is recovered as its transaction is executed. The outcome is the same
+    /// either way; this only changes how the work is scheduled.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_EAGER_SENDER_RECOVERY")]
+    pub eager_sender_recovery: bool,
+
+    /// Number of threads to recover senders on with `--eager-sender-recovery`.
+    ///
+    /// Zero, the default, means one per core. Only takes effect when built with the `parallel`
+    /// feature.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_RECOVERY_THREADS",
+        default_value = "0"
+    )]
+    pub recovery_threads: usize,
+
+    /// Run the executor in high-availability mode, with a submitter lease of this many L1 blocks.
+    ///
+    /// Several nodes can then run against the same rollup contract with `--rollup-address`. Only
+    /// the node holding the lease sends proofs, and another takes over if it stops renewing it. The
+    /// rollup contract allows leases of at most 7200 blocks. Unless it has an authorized submitter,
+    /// it also hands the lease to another node after about a week of renewals.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_SUBMITTER_LEASE_BLOCKS",
+        value_parser = clap::value_parser!(u64).range(1..=executor::MAX_LEASE_BLOCKS)
+    )]
+    pub submitter_lease_blocks: Option<u64>,
+
+    /// Number of executed batches whose proofs may wait to be sent to L1.
+    ///
+    /// When this many are waiting, execution pauses until the oldest has been sent.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_PROOF_QUEUE_DEPTH",
+        default_value = "16"
+    )]
+    pub proof_queue_depth: usize,
+
+    /// Milliseconds to keep sending queued proofs after the executor stops.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_PROOF_DRAIN_TIMEOUT_MS",
+        default_value = "30000"
+    )]
+    pub proof_drain_timeout_ms: u64,
+
+    /// Number of rollup blocks proven by each proof sent to L1.
+    ///
+    /// The state is submitted after every block whose height is a multiple of this, and after the
+    /// latest block once HotShot stops producing blocks. Larger values save L1 fees at the cost of
+    /// slower finality.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_PROOF_INTERVAL", default_value = "1")]
+    pub proof_interval: u64,
+
+    /// File where the state updates accepted by the rollup contract are recorded, one JSON object
+    /// per line, so the history served under `l1-updates` survives restarts. If not set, the
+    /// history is read from L1 again on each start, and which of the updates accepted before then
+    /// this node sent is no longer known.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_L1_UPDATES_PATH")]
+    pub l1_updates_path: Option<PathBuf>,
+
+    /// File to which a JSON summary of each executed block is appended, one per line.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_BLOCK_SUMMARY_LOG")]
+    pub block_summary_log: Option<PathBuf>,
+
+    /// Comma-separated list of URLs notified of executor events with a signed JSON `POST`.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_WEBHOOK_URLS",
+        value_delimiter = ',',
+        requires = "webhook_secret"
+    )]
+    pub webhook_urls: Vec<Url>,
+
+    /// Comma-separated list of events sent to webhooks.
+    ///
+    /// Each is one of `block-executed`, `proof-verified`, `large-transfer:<threshold>`, which
+    /// reports applied transfers of at least `threshold`, or `alert`, which reports the alerts the
+    /// executor raises.
+    #[clap(
+        long,
+        env = "ESPRESSO_DEMO_ROLLUP_WEBHOOK_EVENTS",
+        value_delimiter = ',',
+        default_value = "block-executed,proof-verified"
+    )]
+    pub webhook_events: Vec<WebhookEvent>,
+
+    /// Secret shared with webhook receivers, used to sign notifications.
+    #[clap(long, env = "ESPRESSO_DEMO_ROLLUP_WEBHOOK_SECRET")]
+    pub webhook_secret: Option<String>,
 }
 
-#[derive(Clone, Copy, Debug, Default, Into, From)]
+#[derive(Clone, Copy, Debug, Default, Into, From, Serialize, Deserialize)]
 pub struct RollupVM(VmId);
 
 impl RollupVM {
@@ -85,7 +800,7 @@ impl RollupVM {
 }
 
 impl Vm for RollupVM {
-    type Transaction = SignedTransaction;
+    type Transaction = RollupTransaction;
 
     fn id(&self) -> VmId {
         self.0
@@ -0,0 +1,714 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Load generation against a running rollup node.
+//!
+//! [`run`] funds a number of wallets, which then submit transfers through the rollup API at a
+//! target rate, and follows each transfer through the status endpoint: from submission until it is
+//! executed and, optionally, until its block is verified on L1. The [`LoadReport`] gives
+//! percentiles of each latency and a breakdown of what went wrong, and [`LoadReport::check`]
+//! compares them with [`Thresholds`], so a soak test can fail a CI run.
+//!
+//! Each wallet has at most one transfer waiting to be executed, so its nonces never race; it
+//! submits the next once the previous has been executed. To sustain a rate, use at least as many
+//! wallets as the rate times the execution latency.
+
+use async_std::{
+    future::timeout,
+    task::{sleep, spawn},
+};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Address, H256},
+};
+use futures::future::join_all;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use snafu::Snafu;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tide_disco::error::Error as _;
+
+use crate::address::checksummed;
+use crate::client::{ClientError, RollupClient};
+use crate::json::ReceiptJson;
+use crate::state::{Amount, Nonce};
+use crate::status::BlockVerification;
+use crate::submission::TransactionStatus;
+use crate::transaction::{SignedTransaction, Transaction};
+
+/// How long to wait for a transfer to be executed, by default.
+pub const DEFAULT_EXECUTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What each wallet is funded with, by default. Every transfer moves one unit.
+pub const DEFAULT_FUNDING: Amount = 1_000;
+
+/// Stream of the key generator the wallets are drawn from.
+const WALLET_STREAM: u64 = 1;
+
+#[derive(Debug, Snafu)]
+pub enum LoadError {
+    #[snafu(display("The load has no end: give a number of transactions or a duration."))]
+    Unbounded,
+    #[snafu(display("Unable to fund {}: {reason}", checksummed(address)))]
+    Funding { address: Address, reason: String },
+    #[snafu(display("Unable to fetch the VM of the rollup: {reason}"))]
+    FetchVm { reason: String },
+}
+
+/// How much load to generate.
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    /// Number of wallets submitting transfers concurrently.
+    pub wallets: usize,
+    /// Transfers submitted per second, across all wallets. Zero submits as fast as the wallets
+    /// can.
+    pub rate: f64,
+    /// Stop after submitting this many transfers.
+    pub transactions: Option<u64>,
+    /// Stop submitting after this long, for a soak test.
+    pub duration: Option<Duration>,
+    /// How long to wait for a transfer to be executed before counting it as timed out.
+    pub execution_timeout: Duration,
+    /// How long to follow each executed transfer until its block is verified on L1, if at all.
+    pub verification_timeout: Option<Duration>,
+    /// What the funder sends each wallet before the load starts.
+    pub funding: Amount,
+    /// Seed the keys of the wallets are derived from.
+    pub seed: u64,
+    /// How often to poll the status of a transfer.
+    pub poll_interval: Duration,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            wallets: 10,
+            rate: 10.,
+            transactions: None,
+            duration: None,
+            execution_timeout: DEFAULT_EXECUTION_TIMEOUT,
+            verification_timeout: None,
+            funding: DEFAULT_FUNDING,
+            seed: 0,
+            poll_interval: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Limits a load test must stay within to pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thresholds {
+    /// Largest fraction of submitted transfers which may fail.
+    pub max_error_rate: f64,
+    /// Longest the 99th percentile of the time from submission to execution may be.
+    pub max_p99_latency: Duration,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            max_error_rate: 0.01,
+            max_p99_latency: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Percentiles of a set of latencies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Latencies {
+    pub count: usize,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Latencies {
+    pub fn from_samples(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        // The nearest-rank percentile: the smallest sample at least `p` percent of the samples do
+        // not exceed.
+        let percentile = |p: usize| {
+            let rank = (samples.len() * p + 99) / 100;
+            samples
+                .get(rank.saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        Self {
+            count: samples.len(),
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: samples.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+impl Display for Latencies {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>7} {:>10.3?} {:>10.3?} {:>10.3?} {:>10.3?}",
+            self.count, self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+/// How often one kind of failure happened, with the details of the first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorCount {
+    pub count: u64,
+    pub example: String,
+}
+
+/// The outcome of a load test.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadReport {
+    /// From the first submission until the last transfer was followed to the end.
+    pub elapsed: Duration,
+    /// Transfers submitted, including those the API rejected.
+    pub submitted: u64,
+    /// From sending a transfer to the API accepting it.
+    pub submit: Latencies,
+    /// From sending a transfer to it being executed.
+    pub execution: Latencies,
+    /// From sending a transfer to its block being verified on L1.
+    pub verification: Latencies,
+    /// Failures, by kind.
+    pub errors: BTreeMap<String, ErrorCount>,
+}
+
+impl LoadReport {
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().map(|error| error.count).sum()
+    }
+
+    /// Fraction of the submitted transfers which failed.
+    pub fn error_rate(&self) -> f64 {
+        if self.submitted == 0 {
+            return 0.;
+        }
+        self.error_count() as f64 / self.submitted as f64
+    }
+
+    /// Transfers executed per second.
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.;
+        }
+        self.execution.count as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Check the report against `thresholds`, describing each one exceeded.
+    pub fn check(&self, thresholds: &Thresholds) -> Result<(), Vec<String>> {
+        let mut exceeded = vec![];
+        if self.error_rate() > thresholds.max_error_rate {
+            exceeded.push(format!(
+                "error rate {:.2}% exceeds {:.2}%",
+                self.error_rate() * 100.,
+                thresholds.max_error_rate * 100.
+            ));
+        }
+        if self.execution.p99 > thresholds.max_p99_latency {
+            exceeded.push(format!(
+                "p99 execution latency {:?} exceeds {:?}",
+                self.execution.p99, thresholds.max_p99_latency
+            ));
+        }
+        if exceeded.is_empty() {
+            Ok(())
+        } else {
+            Err(exceeded)
+        }
+    }
+}
+
+impl Display for LoadReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} transfers submitted in {:.1?}, {:.2} executed per second",
+            self.submitted,
+            self.elapsed,
+            self.throughput()
+        )?;
+        writeln!(
+            f,
+            "{:<13} {:>7} {:>10} {:>10} {:>10} {:>10}",
+            "latency", "count", "p50", "p90", "p99", "max"
+        )?;
+        writeln!(f, "{:<13} {}", "submit", self.submit)?;
+        writeln!(f, "{:<13} {}", "execution", self.execution)?;
+        writeln!(f, "{:<13} {}", "verification", self.verification)?;
+        writeln!(
+            f,
+            "{} errors ({:.2}%)",
+            self.error_count(),
+            self.error_rate() * 100.
+        )?;
+        for (kind, error) in &self.errors {
+            writeln!(f, "  {kind}: {} (first: {})", error.count, error.example)?;
+        }
+        Ok(())
+    }
+}
+
+/// The wallets a load test with `options` submits from. Deterministic, so that a rerun uses the
+/// same accounts, but drawn from a different stream of the generator than the
+/// [seed identities](crate::seed::SeedIdentity), so the funder is never one of them.
+pub fn load_wallets(options: &LoadOptions) -> Vec<LocalWallet> {
+    let mut rng = ChaChaRng::seed_from_u64(options.seed);
+    rng.set_stream(WALLET_STREAM);
+    (0..options.wallets)
+        .map(|_| LocalWallet::new(&mut rng))
+        .collect()
+}
+
+/// Fund the wallets from `funder`, then generate load as `options` say, through the API `client`
+/// talks to.
+pub async fn run(
+    client: &RollupClient,
+    funder: &LocalWallet,
+    options: &LoadOptions,
+) -> Result<LoadReport, LoadError> {
+    if options.transactions.is_none() && options.duration.is_none() {
+        return Err(LoadError::Unbounded);
+    }
+    // Transfers are signed for the rollup the node serves.
+    let vm_id = client
+        .info()
+        .await
+        .map_err(|err| LoadError::FetchVm {
+            reason: err.to_string(),
+        })?
+        .vm_id;
+    let wallets = load_wallets(options);
+    fund(client, funder, &wallets, vm_id, options).await?;
+
+    let start = Instant::now();
+    let pacer = Arc::new(Pacer::new(options, start));
+    let recorder = Recorder::default();
+    let destinations = wallets
+        .iter()
+        .cycle()
+        .skip(1)
+        .map(|wallet| wallet.address())
+        .collect::<Vec<_>>();
+    let workers = wallets
+        .into_iter()
+        .zip(destinations)
+        .map(|(wallet, destination)| {
+            spawn(submit_transfers(
+                client.clone(),
+                wallet,
+                destination,
+                vm_id,
+                pacer.clone(),
+                recorder.clone(),
+                options.clone(),
+            ))
+        })
+        .collect::<Vec<_>>();
+    join_all(workers).await;
+    let verifications = std::mem::take(&mut *recorder.verifications.lock().unwrap());
+    join_all(verifications).await;
+    Ok(recorder.report(start.elapsed()))
+}
+
+/// Send each wallet `options.funding` from `funder`, and wait for every transfer to be applied.
+async fn fund(
+    client: &RollupClient,
+    funder: &LocalWallet,
+    wallets: &[LocalWallet],
+    vm_id: u64,
+    options: &LoadOptions,
+) -> Result<(), LoadError> {
+    let failed = |reason: String| LoadError::Funding {
+        address: funder.address(),
+        reason,
+    };
+    let mut nonce = client
+        .nonce(funder.address())
+        .await
+        .map_err(|err| failed(err.to_string()))?;
+    let mut hashes = vec![];
+    for wallet in wallets {
+        nonce += 1;
+        let transfer = sign(funder, wallet.address(), options.funding, nonce, vm_id);
+        hashes.push(
+            client
+                .submit(&transfer)
+                .await
+                .map_err(|err| failed(err.to_string()))?,
+        );
+    }
+    for hash in hashes {
+        let receipt = wait_executed(client, hash, options)
+            .await
+            .map_err(|failure| failed(failure.example))?;
+        if let Some(error) = receipt.error {
+            return Err(failed(error));
+        }
+    }
+    Ok(())
+}
+
+/// Submit transfers of one unit from `wallet` to `destination` whenever `pacer` allows, one at a
+/// time.
+async fn submit_transfers(
+    client: RollupClient,
+    wallet: LocalWallet,
+    destination: Address,
+    vm_id: u64,
+    pacer: Arc<Pacer>,
+    recorder: Recorder,
+    options: LoadOptions,
+) {
+    let mut nonce = None;
+    while pacer.next().await {
+        let submitted = Instant::now();
+        recorder.submitted();
+        let next = match nonce {
+            Some(nonce) => nonce,
+            None => match client.nonce(wallet.address()).await {
+                Ok(nonce) => nonce + 1,
+                Err(err) => {
+                    recorder.error(Failure::client("nonce", err));
+                    continue;
+                }
+            },
+        };
+        let transfer = sign(&wallet, destination, 1, next, vm_id);
+        let hash = match client.submit(&transfer).await {
+            Ok(hash) => hash,
+            Err(err) => {
+                recorder.error(Failure::client("submit", err));
+                continue;
+            }
+        };
+        recorder.record_submit(submitted.elapsed());
+        match wait_executed(&client, hash, &options).await {
+            Ok(ReceiptJson {
+                error: Some(error), ..
+            }) => {
+                recorder.error(Failure::new("not applied", error));
+                // The node's nonce is the one to follow after a rejection.
+                nonce = None;
+            }
+            Ok(_) => {
+                recorder.record_execution(submitted.elapsed());
+                nonce = Some(next + 1);
+                if let Some(wait) = options.verification_timeout {
+                    let verification = spawn(wait_verified(
+                        client.clone(),
+                        hash,
+                        submitted,
+                        wait,
+                        recorder.clone(),
+                        options.poll_interval,
+                    ));
+                    recorder.verifications.lock().unwrap().push(verification);
+                }
+            }
+            Err(failure) => {
+                recorder.error(failure);
+                nonce = None;
+            }
+        }
+    }
+}
+
+fn sign(
+    wallet: &LocalWallet,
+    destination: Address,
+    amount: Amount,
+    nonce: Nonce,
+    vm_id: u64,
+) -> SignedTransaction {
+    let transaction = Transaction {
+        amount,
+        destination,
+        nonce,
+        priority: 0,
+        vm_id,
+        asset: None,
+    };
+    let signature = wallet
+        .sign_hash(transaction.signing_hash())
+        .expect("signing with a local key does not fail");
+    SignedTransaction::from_signature(transaction, signature)
+}
+
+async fn wait_executed(
+    client: &RollupClient,
+    hash: H256,
+    options: &LoadOptions,
+) -> Result<ReceiptJson, Failure> {
+    let wait = async {
+        loop {
+            match client.receipt(hash).await {
+                Ok(Some(receipt)) => return Ok(receipt),
+                Ok(None) => sleep(options.poll_interval).await,
+                Err(err) => return Err(Failure::client("status", err)),
+            }
+        }
+    };
+    timeout(options.execution_timeout, wait)
+        .await
+        .unwrap_or_else(|_| {
+            Err(Failure::new(
+                "execution timeout",
+                format!(
+                    "{hash:?} not executed within {:?}",
+                    options.execution_timeout
+                ),
+            ))
+        })
+}
+
+async fn wait_verified(
+    client: RollupClient,
+    hash: H256,
+    submitted: Instant,
+    wait: Duration,
+    recorder: Recorder,
+    poll_interval: Duration,
+) {
+    let verified = async {
+        loop {
+            match client.status(hash).await {
+                Ok(TransactionStatus::Sequenced {
+                    verification: Some(BlockVerification::Verified { .. }),
+                    ..
+                }) => return Ok(()),
+                Ok(_) => sleep(poll_interval).await,
+                Err(err) => return Err(Failure::client("status", err)),
+            }
+        }
+    };
+    match timeout(wait, verified).await {
+        Ok(Ok(())) => recorder.record_verification(submitted.elapsed()),
+        Ok(Err(failure)) => recorder.error(failure),
+        Err(_) => recorder.error(Failure::new(
+            "verification timeout",
+            format!("{hash:?} not verified within {wait:?}"),
+        )),
+    }
+}
+
+/// Hands out submission slots at the target rate until the load is over.
+struct Pacer {
+    start: Instant,
+    interval: Duration,
+    limit: Option<u64>,
+    deadline: Option<Instant>,
+    next_slot: Mutex<u64>,
+}
+
+impl Pacer {
+    fn new(options: &LoadOptions, start: Instant) -> Self {
+        let interval = if options.rate > 0. {
+            Duration::from_secs_f64(1. / options.rate)
+        } else {
+            Duration::ZERO
+        };
+        Self {
+            start,
+            interval,
+            limit: options.transactions,
+            deadline: options.duration.map(|duration| start + duration),
+            next_slot: Mutex::new(0),
+        }
+    }
+
+    /// Wait for the next slot. Returns whether there is one.
+    async fn next(&self) -> bool {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            if matches!(self.limit, Some(limit) if *next_slot >= limit) {
+                return false;
+            }
+            *next_slot += 1;
+            *next_slot - 1
+        };
+        let at = self.start + self.interval.mul_f64(slot as f64);
+        if matches!(self.deadline, Some(deadline) if at >= deadline || Instant::now() >= deadline) {
+            return false;
+        }
+        let now = Instant::now();
+        if at > now {
+            sleep(at - now).await;
+        }
+        true
+    }
+}
+
+/// A failed transfer: what kind of failure it was, and its details.
+#[derive(Debug)]
+struct Failure {
+    kind: String,
+    example: String,
+}
+
+impl Failure {
+    fn new(kind: &str, details: impl ToString) -> Self {
+        Self {
+            kind: kind.into(),
+            example: details.to_string(),
+        }
+    }
+
+    /// A failure to talk to the API, told apart by the status the API responded with.
+    fn client(step: &str, err: ClientError) -> Self {
+        let kind = match &err {
+            ClientError::Api { source } => format!("{step}: HTTP {}", source.status()),
+            ClientError::NotForwarded { .. } => format!("{step}: not forwarded"),
+            ClientError::NotSequenced { .. } => format!("{step}: not sequenced"),
+        };
+        Self {
+            kind,
+            example: err.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Recorder {
+    samples: Arc<Mutex<Samples>>,
+    verifications: Arc<Mutex<Vec<async_std::task::JoinHandle<()>>>>,
+}
+
+#[derive(Default)]
+struct Samples {
+    submitted: u64,
+    submit: Vec<Duration>,
+    execution: Vec<Duration>,
+    verification: Vec<Duration>,
+    errors: BTreeMap<String, ErrorCount>,
+}
+
+impl Recorder {
+    fn submitted(&self) {
+        self.samples.lock().unwrap().submitted += 1;
+    }
+
+    fn record_submit(&self, latency: Duration) {
+        self.samples.lock().unwrap().submit.push(latency);
+    }
+
+    fn record_execution(&self, latency: Duration) {
+        self.samples.lock().unwrap().execution.push(latency);
+    }
+
+    fn record_verification(&self, latency: Duration) {
+        self.samples.lock().unwrap().verification.push(latency);
+    }
+
+    fn error(&self, failure: Failure) {
+        let mut samples = self.samples.lock().unwrap();
+        let error = samples.errors.entry(failure.kind).or_default();
+        if error.count == 0 {
+            error.example = failure.example;
+        }
+        error.count += 1;
+    }
+
+    fn report(&self, elapsed: Duration) -> LoadReport {
+        let samples = std::mem::take(&mut *self.samples.lock().unwrap());
+        LoadReport {
+            elapsed,
+            submitted: samples.submitted,
+            submit: Latencies::from_samples(samples.submit),
+            execution: Latencies::from_samples(samples.execution),
+            verification: Latencies::from_samples(samples.verification),
+            errors: samples.errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::SeedIdentity;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_latencies() {
+        let samples = (1..=200).rev().map(Duration::from_millis).collect();
+        assert_eq!(
+            Latencies::from_samples(samples),
+            Latencies {
+                count: 200,
+                p50: Duration::from_millis(100),
+                p90: Duration::from_millis(180),
+                p99: Duration::from_millis(198),
+                max: Duration::from_millis(200),
+            }
+        );
+        assert_eq!(Latencies::from_samples(vec![]), Latencies::default());
+        let one = Latencies::from_samples(vec![Duration::from_secs(1)]);
+        assert_eq!(one.p50, Duration::from_secs(1));
+        assert_eq!(one.p99, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_check() {
+        let mut report = LoadReport {
+            elapsed: Duration::from_secs(10),
+            submitted: 100,
+            execution: Latencies::from_samples(vec![Duration::from_secs(2); 99]),
+            ..Default::default()
+        };
+        let thresholds = Thresholds {
+            max_error_rate: 0.01,
+            max_p99_latency: Duration::from_secs(2),
+        };
+        report.errors.insert(
+            "execution timeout".into(),
+            ErrorCount {
+                count: 1,
+                example: "timed out".into(),
+            },
+        );
+        assert_eq!(report.check(&thresholds), Ok(()));
+        assert_eq!(report.throughput(), 9.9);
+
+        report.errors.get_mut("execution timeout").unwrap().count += 1;
+        report.execution.p99 = Duration::from_secs(3);
+        let exceeded = report.check(&thresholds).unwrap_err();
+        assert_eq!(exceeded.len(), 2, "{exceeded:?}");
+    }
+
+    #[test]
+    fn test_wallets_deterministic() {
+        let options = LoadOptions {
+            wallets: 3,
+            ..Default::default()
+        };
+        let addresses = |options: &LoadOptions| {
+            load_wallets(options)
+                .iter()
+                .map(|wallet| wallet.address())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(addresses(&options), addresses(&options));
+        assert_eq!(addresses(&options).len(), 3);
+        let other = LoadOptions {
+            seed: 1,
+            ..options.clone()
+        };
+        assert_ne!(addresses(&other), addresses(&options));
+
+        // None of them is a seed identity, which might be funding them.
+        for identity in SeedIdentity::iter() {
+            assert!(!addresses(&options).contains(&identity.wallet().address()));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Logging for the whole node.
+//!
+//! Every event the node logs has one of the targets below, so each part of the node can be turned
+//! up or down on its own with a filter such as `info,rollup::executor=debug,ethers=warn`, in the
+//! syntax of [`EnvFilter`]. Events from the crates the node is built on keep their own targets,
+//! which are their module paths.
+//!
+//! Warnings and errors identical to one logged less than [`RATE_LIMIT_WINDOW`] ago are dropped,
+//! so that a condition which is retried every second, such as a failing proof submission, is
+//! reported once a minute rather than every time.
+
+use clap::ValueEnum;
+use snafu::Snafu;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::{Debug, Write};
+use std::hash::{Hash, Hasher};
+use std::io::IsTerminal;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Target of events from the executor: fetching, executing and proving blocks, and everything it
+/// runs alongside, such as block hooks, webhooks and the backfill.
+pub const EXECUTOR: &str = "rollup::executor";
+
+/// Target of events from the rollup API and the forwarding of submitted transactions.
+pub const API: &str = "rollup::api";
+
+/// Target of events about the rollup state: applying transactions, and the snapshots and
+/// migrations of persisted data.
+pub const STATE: &str = "rollup::state";
+
+/// Target of events about L1 deposits and messages between rollups.
+pub const BRIDGE: &str = "rollup::bridge";
+
+/// Target of events about the node as a whole: starting, deploying contracts and shutting down.
+pub const NODE: &str = "rollup::node";
+
+/// The filter used when neither the node's option nor `RUST_LOG` sets one.
+pub const DEFAULT_LOG_FILTER: &str = "info";
+
+/// How long a warning or error is not logged again after it has been.
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Largest number of distinct warnings remembered for rate limiting. Beyond it, those which are no
+/// longer rate limited are forgotten.
+const RATE_LIMIT_ENTRIES: usize = 1024;
+
+/// How log lines are written.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable text, one event per line.
+    #[default]
+    Pretty,
+    /// One JSON object per event, for log collectors.
+    Json,
+}
+
+#[derive(Debug, Snafu)]
+pub enum LoggingError {
+    #[snafu(display("Invalid log filter {filter:?}: {reason}"))]
+    InvalidFilter { filter: String, reason: String },
+    #[snafu(display("Unable to install the logger: {reason}"))]
+    Install { reason: String },
+}
+
+/// Log to standard output for the rest of the process, keeping the events `filter` allows.
+///
+/// If `filter` is not given, the filter is read from `RUST_LOG`, or is [`DEFAULT_LOG_FILTER`] if
+/// that is not set either.
+pub fn init_logging(filter: Option<&str>, format: LogFormat) -> Result<(), LoggingError> {
+    let filter = match filter {
+        Some(filter) => EnvFilter::try_new(filter).map_err(|err| LoggingError::InvalidFilter {
+            filter: filter.into(),
+            reason: err.to_string(),
+        })?,
+        None => {
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER))
+        }
+    };
+    let fmt = tracing_subscriber::fmt::layer().with_ansi(std::io::stdout().is_terminal());
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(RateLimit::new(RATE_LIMIT_WINDOW));
+    match format {
+        LogFormat::Pretty => registry.with(fmt).try_init(),
+        LogFormat::Json => registry.with(fmt.json()).try_init(),
+    }
+    .map_err(|err| LoggingError::Install {
+        reason: err.to_string(),
+    })
+}
+
+/// A layer which drops warnings and errors identical to one let through less than `window` ago.
+///
+/// Events are identical if they come from the same place in the code with the same fields,
+/// including the message.
+#[derive(Debug)]
+pub struct RateLimit {
+    window: Duration,
+    last_logged: Mutex<HashMap<u64, Instant>>,
+}
+
+impl RateLimit {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_logged: Default::default(),
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for RateLimit {
+    fn event_enabled(&self, event: &Event<'_>, _ctx: Context<'_, S>) -> bool {
+        // Levels compare by verbosity, so this lets through everything less severe than a warning.
+        if *event.metadata().level() > Level::WARN {
+            return true;
+        }
+        let mut fields = FieldsHasher(DefaultHasher::new());
+        event.metadata().callsite().hash(&mut fields.0);
+        event.record(&mut fields);
+        let key = fields.0.finish();
+
+        let now = Instant::now();
+        let mut last_logged = self.last_logged.lock().unwrap();
+        if matches!(last_logged.get(&key), Some(last) if now.duration_since(*last) < self.window) {
+            return false;
+        }
+        if last_logged.len() >= RATE_LIMIT_ENTRIES {
+            last_logged.retain(|_, last| now.duration_since(*last) < self.window);
+        }
+        last_logged.insert(key, now);
+        true
+    }
+}
+
+/// Hashes the name and value of each field of an event.
+struct FieldsHasher(DefaultHasher);
+
+impl Visit for FieldsHasher {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let mut text = String::new();
+        write!(text, "{value:?}").ok();
+        (field.name(), text).hash(&mut self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tracing_subscriber::Registry;
+
+    /// An event as captured by [`Capture`].
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Captured {
+        target: String,
+        level: Level,
+        message: String,
+    }
+
+    /// A layer which records every event it sees.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<Captured>>>);
+
+    impl Capture {
+        fn events(&self) -> Vec<Captured> {
+            self.0.lock().unwrap().clone()
+        }
+
+        /// A subscriber which records into this capture every event `rate_limit` lets through.
+        fn subscriber(&self, rate_limit: RateLimit) -> impl Subscriber + Send + Sync {
+            Registry::default().with(rate_limit).with(self.clone())
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for Capture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            struct Message(String);
+            impl Visit for Message {
+                fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                    if field.name() == "message" {
+                        self.0 = format!("{value:?}");
+                    }
+                }
+            }
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            self.0.lock().unwrap().push(Captured {
+                target: event.metadata().target().into(),
+                level: *event.metadata().level(),
+                message: message.0,
+            });
+        }
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let capture = Capture::default();
+        let window = Duration::from_millis(200);
+        tracing::subscriber::with_default(capture.subscriber(RateLimit::new(window)), || {
+            let warn = |attempt: u64| {
+                tracing::warn!(target: EXECUTOR, "retrying");
+                tracing::warn!(target: EXECUTOR, "retry {attempt}");
+                tracing::info!(target: EXECUTOR, "progress");
+            };
+            warn(1);
+            warn(1);
+            warn(2);
+            std::thread::sleep(window);
+            warn(2);
+        });
+
+        let messages = capture
+            .events()
+            .into_iter()
+            .map(|event| {
+                assert_eq!(event.target, EXECUTOR);
+                (event.level, event.message)
+            })
+            .collect::<Vec<_>>();
+        let warn = |message: &str| (Level::WARN, message.to_string());
+        let info = || (Level::INFO, "progress".to_string());
+        assert_eq!(
+            messages,
+            [
+                // Repeats of the same warning are dropped, but not other warnings, nor anything
+                // less severe.
+                warn("retrying"),
+                warn("retry 1"),
+                info(),
+                info(),
+                warn("retry 2"),
+                info(),
+                // Once the window has passed, the warnings are logged again.
+                warn("retrying"),
+                warn("retry 2"),
+                info(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_targets() {
+        let capture = Capture::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("state-1.json"), "not a snapshot").unwrap();
+        tracing::subscriber::with_default(
+            capture.subscriber(RateLimit::new(RATE_LIMIT_WINDOW)),
+            || crate::snapshot::load_latest_snapshot(dir.path(), &crate::RollupVM::new(1.into())),
+        );
+        let events = capture.events();
+        assert!(!events.is_empty());
+        for event in events {
+            assert_eq!(event.target, STATE, "{event:?}");
+        }
+    }
+}
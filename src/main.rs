@@ -4,73 +4,417 @@
 // You should have received a copy of the MIT License
 // along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
 
-use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
-use async_std::sync::RwLock;
-use clap::Parser;
+use async_compatibility_layer::logging::setup_backtrace;
+use async_std::{
+    channel,
+    sync::RwLock,
+    task::{sleep, spawn},
+};
 use commit::Committable;
-use ethers::signers::{LocalWallet, Signer};
 use example_l2::{
-    api::{serve, APIOptions},
-    executor::{run_executor, ExecutorOptions},
-    seed::{SeedIdentity, INITIAL_BALANCE},
-    state::State,
-    utils::{create_provider, deploy_example_contract},
-    Options, RollupVM,
+    alert::{AlertBuffer, Alerts},
+    api::{serve, APIOptions, SubmitRoute},
+    backfill::{run_backfill, BackfillOptions, BACKFILL_CURSOR_FILE},
+    cache::ReadCache,
+    commitment_task::{commitment_task_options, run_commitment_task_supervised},
+    config::{parse_options, EffectiveConfig},
+    doctor::run_checks,
+    executor::{
+        run_executor_supervised, BalanceMonitorOptions, ExecutorOptions, FeeBumpOptions,
+        LagAlertOptions, StartPoint,
+    },
+    follower::{run_follower, FollowerOptions, StateSource},
+    hooks::{BlockHooks, SummaryLog},
+    l1_events::EventBackfillOptions,
+    l1_updates::L1UpdateHistory,
+    logging::{init_logging, NODE},
+    migrations::{data_files, migrate_files},
+    mode::{check_mode, RunMode},
+    replay::ReplayOptions,
+    retention::RetentionConfig,
+    schema::Strictness,
+    seed::initial_balances,
+    shutdown::close_on_signal,
+    snapshot::{load_latest_snapshot, SnapshotOptions},
+    state::{ChainConfig, State},
+    status::StatusHandle,
+    utils::{deploy_rollup_only, wallet_address, DeployOptions},
+    watchdog::WatchdogOptions,
+    webhook::WebhookConfig,
+    RollupVM,
 };
-use futures::join;
-use rand::SeedableRng;
-use rand_chacha::ChaChaRng;
-use sequencer_utils::test_utils::TestL1System;
+use futures::future::{self, Either};
 use std::sync::Arc;
-use strum::IntoEnumIterator;
+use std::time::{Duration, Instant};
+
+/// How long the executor may take to stop after a shutdown signal, on top of the time it is allowed
+/// for sending the proofs still queued.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
 
 #[async_std::main]
 async fn main() {
-    setup_logging();
+    let (opt, config) = parse_options();
+    if let Err(err) = init_logging(opt.log_filter.as_deref(), opt.log_format) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
     setup_backtrace();
 
-    let opt = Options::parse();
-    let vm = RollupVM::new(1.into());
+    if opt.check {
+        let report = run_checks(&opt).await;
+        print!("{report}");
+        std::process::exit(if report.passed() { 0 } else { 1 });
+    }
 
-    let mut initial_balances = vec![];
-    for identity in SeedIdentity::iter() {
-        let address = LocalWallet::new(&mut ChaChaRng::seed_from_u64(identity as u64)).address();
-        initial_balances.push((address, INITIAL_BALANCE))
+    if let Err(err) = check_mode(&opt) {
+        eprintln!("{err}");
+        std::process::exit(1);
     }
-    let state = Arc::new(RwLock::new(State::from_initial_balances(
-        initial_balances,
-        vm,
-    )));
 
-    let api_options = APIOptions {
-        api_port: opt.api_port,
-        sequencer_url: opt.sequencer_url.clone(),
+    let vm = RollupVM::new(opt.vm_id.into());
+
+    // Bring data written by an older version up to date before reading any of it. Data written by
+    // a newer version is left untouched, and the node does not start. An API node only reads the
+    // executor's data, so leaves it to the executor to migrate.
+    if opt.mode.runs_executor() {
+        if let Err(err) = data_files(opt.snapshot_dir.as_deref(), opt.cursor_path.as_deref())
+            .and_then(|files| migrate_files(&files))
+        {
+            tracing::error!(target: NODE, "Unable to migrate the node's data: {err}");
+            std::process::exit(1);
+        }
+    }
+
+    // Closed on SIGINT or SIGTERM, which stops the API accepting transactions and the executor
+    // executing blocks.
+    // `stop` is kept until the end, so the channel only closes on a signal, even if signals cannot
+    // be handled.
+    let (stop, shutdown) = channel::bounded(1);
+    let signals = stop.clone();
+    spawn(async move {
+        if let Err(err) = close_on_signal(signals).await {
+            tracing::error!(target: NODE, "Unable to handle shutdown signals: {err}");
+        }
+    });
+
+    // Snapshots only match the rollup contract they were proven against, so they are only used
+    // when resuming with an existing contract.
+    let snapshot = match (&opt.rollup_address, &opt.snapshot_dir) {
+        (Some(_), Some(dir)) => load_latest_snapshot(dir, &vm),
+        _ => None,
     };
+    let genesis =
+        State::from_initial_balances(initial_balances(), vm).with_chain_config(ChainConfig {
+            assets: opt.deposit_assets.iter().copied().collect(),
+            message_sources: opt.message_sources.iter().copied().collect(),
+            reject_zero_destination: opt.reject_zero_destination,
+            denied_destinations: opt.denied_destinations.iter().copied().collect(),
+            fee_admin: opt.fee_admin,
+            deposit_confirmations: opt.deposit_confirmations,
+            forced_inclusion: opt.forced_inclusion,
+            ..Default::default()
+        });
+    let resumed = snapshot.is_some();
+    let mut state = snapshot.unwrap_or_else(|| genesis.clone());
+    state.set_eager_recovery(opt.eager_sender_recovery);
+    state.set_recovery_threads(opt.recovery_threads);
+    state.set_balance_checkpoint_interval(opt.balance_checkpoint_interval);
+    let state = Arc::new(RwLock::new(state));
 
-    let serve_api = async {
-        serve(&api_options, state.clone()).await.unwrap();
+    let l1_updates = match &opt.l1_updates_path {
+        Some(path) => L1UpdateHistory::open(path).unwrap_or_else(|err| {
+            tracing::error!(target: NODE, "Unable to read the L1 update history {path:?}: {err}");
+            std::process::exit(1);
+        }),
+        None => Default::default(),
     };
 
-    let initial_state = { state.read().await.commit() };
+    // Hot reads are answered from the cache between blocks; the executor refreshes it after each.
+    let read_cache = (!opt.disable_read_cache).then(ReadCache::default);
+    let alert_buffer = AlertBuffer::default();
+    let retention = RetentionConfig {
+        receipts_blocks: opt.receipts_retained_blocks,
+        history_blocks: opt.history_retained_blocks,
+        proofs_blocks: opt.proofs_retained_blocks,
+        snapshots: opt.snapshots_retained,
+    };
+    let api_options = APIOptions {
+        cors_allowed_origins: opt.cors_allowed_origins.clone(),
+        admin_token: opt.admin_token.clone(),
+        max_submit_body_bytes: opt.max_submit_body_bytes,
+        max_sync_lag: opt.max_sync_lag,
+        submit_queue_capacity: opt.submit_queue_capacity,
+        forward_workers: opt.forward_workers,
+        max_pending_per_sender: opt.max_pending_per_sender,
+        proof_compression: opt.proof_compression,
+        request_strictness: if opt.relaxed_requests {
+            Strictness::Relaxed
+        } else {
+            Strictness::Strict
+        },
+        read_timeout: Duration::from_millis(opt.read_timeout_ms),
+        submit_timeout: Duration::from_millis(opt.submit_timeout_ms),
+        shutdown: Some(shutdown.clone()),
+        read_cache: read_cache.clone(),
+        // Submissions are left to the API nodes of a separate executor.
+        submit_route: if opt.mode == RunMode::Executor {
+            SubmitRoute::Disabled
+        } else {
+            SubmitRoute::Sequencer
+        },
+        l1_updates: l1_updates.clone(),
+        alerts: alert_buffer.clone(),
+        replay: opt.snapshot_dir.clone().map(|snapshot_dir| ReplayOptions {
+            snapshot_dir,
+            sequencer_url: opt.sequencer_url.clone(),
+            l1_provider: opt.l1_http_provider.clone(),
+            rollup_address: opt.rollup_address,
+        }),
+        config: EffectiveConfig {
+            retention: Some(retention),
+            ..config
+        },
+        rollup_address: opt.rollup_address,
+        ..APIOptions::new(opt.api_port, opt.sequencer_url.clone())
+    };
 
-    tracing::info!("Deploying Rollup contracts");
-    let provider = create_provider(&opt.l1_http_provider);
-    let test_system = TestL1System::new(provider, opt.hotshot_address)
+    let status = StatusHandle::default();
+    // Until a block is executed, the API serves the state it starts from.
+    let height = state.read().await.block_height();
+    status
+        .write()
         .await
-        .unwrap();
-    let rollup_contract = deploy_example_contract(&test_system, initial_state).await;
+        .set_local_height(height, Instant::now());
+
+    if opt.mode == RunMode::Api {
+        let source = match (&opt.executor_api_url, &opt.snapshot_dir) {
+            (Some(url), _) => StateSource::Executor(url.clone()),
+            (None, Some(dir)) => StateSource::Snapshots(dir.clone()),
+            (None, None) => unreachable!("checked by check_mode"),
+        };
+        let follower_options = FollowerOptions {
+            source,
+            sequencer_url: opt.sequencer_url.clone(),
+            interval: Duration::from_millis(opt.follow_interval_ms),
+            retention,
+        };
+        tracing::info!(
+            target: NODE,
+            "Launching Example Rollup API, following {:?}",
+            follower_options.source
+        );
+        {
+            let state = state.clone();
+            let status = status.clone();
+            spawn(async move { serve(&api_options, state, status).await.unwrap() });
+        }
+        let follow = run_follower(&follower_options, state.clone(), status, read_cache);
+        let stopped = async {
+            shutdown.recv().await.ok();
+        };
+        if let Either::Left((Err(err), _)) =
+            future::select(Box::pin(follow), Box::pin(stopped)).await
+        {
+            tracing::error!(target: NODE, "Stopped following the executor: {err}");
+            std::process::exit(1);
+        }
+        drop(stop);
+        let height = state.read().await.block_height();
+        tracing::info!(target: NODE, height, "shutdown complete at height {height}");
+        return;
+    }
+
+    let initial_state = { state.read().await.commit() };
+
+    let rollup_address = match opt.rollup_address {
+        Some(address) => address,
+        None => {
+            tracing::info!(target: NODE, "Deploying Rollup contracts");
+            let deploy_options = DeployOptions {
+                hotshot: Some(opt.hotshot_address),
+                deployer_mnemonic: opt.deployer_mnemonic.clone(),
+                deployer_account_index: opt.deployer_account_index,
+                confirmations: opt.deploy_confirmations,
+                initial_state,
+                challenge_window: opt.challenge_window_secs,
+                submitter: wallet_address(&opt.rollup_mnemonic, opt.rollup_account_index),
+            };
+            deploy_rollup_only(&opt.l1_http_provider, &deploy_options)
+                .await
+                .unwrap_or_else(|err| panic!("Error deploying the rollup contract: {err}"))
+                .rollup
+                .address()
+        }
+    };
+
+    // Replayed blocks credit deposits from the rollup contract, which may have just been deployed,
+    // and the submit route's fallback enqueues transactions on it.
+    let api_options = APIOptions {
+        replay: api_options.replay.map(|replay| ReplayOptions {
+            rollup_address: Some(rollup_address),
+            ..replay
+        }),
+        rollup_address: Some(rollup_address),
+        ..api_options
+    };
+
+    let serve_api = async {
+        serve(&api_options, state.clone(), status.clone())
+            .await
+            .unwrap();
+    };
 
     let executor_options = ExecutorOptions {
         hotshot_address: opt.hotshot_address,
         l1_http_provider: opt.l1_http_provider.clone(),
         l1_ws_provider: opt.l1_ws_provider.clone(),
-        rollup_address: rollup_contract.address(),
+        l1_chain_id: opt.l1_chain_id,
+        rollup_address,
         rollup_account_index: opt.rollup_account_index,
         rollup_mnemonic: opt.rollup_mnemonic.clone(),
-        sequencer_url: opt.sequencer_url.clone(),
+        sequencer_urls: std::iter::once(opt.sequencer_url.clone())
+            .chain(opt.query_replica_urls.iter().cloned())
+            .collect(),
         output_stream: None,
+        block_hooks: {
+            let mut hooks = BlockHooks::default();
+            if let Some(path) = &opt.block_summary_log {
+                hooks = hooks.with(SummaryLog::new(path));
+            }
+            if let Some(cache) = read_cache {
+                hooks = hooks.with(cache);
+            }
+            if api_options.submit_route == SubmitRoute::Sequencer {
+                hooks = hooks.with(api_options.submissions.clone());
+            }
+            hooks
+        },
+        webhooks: opt
+            .webhook_urls
+            .iter()
+            .map(|url| WebhookConfig {
+                url: url.clone(),
+                events: opt.webhook_events.clone(),
+                secret: opt.webhook_secret.clone().unwrap_or_default(),
+            })
+            .collect(),
+        alerts: Alerts::default().with(alert_buffer),
+        fee_bump: FeeBumpOptions {
+            confirmation_blocks: opt.proof_confirmation_blocks,
+            bump_percent: opt.proof_fee_bump_percent,
+            max_bumps: opt.proof_max_fee_bumps,
+            max_attempts: opt.proof_max_attempts,
+        },
+        balance_monitor: BalanceMonitorOptions {
+            interval: Duration::from_secs(opt.balance_check_interval_secs),
+            low_balance_wei: opt.low_balance_wei.into(),
+        },
+        lag_alerts: LagAlertOptions {
+            max_sequencing_lag: opt.max_sequencing_lag,
+            max_verification_lag: opt.max_verification_lag,
+            sustained: Duration::from_secs(opt.lag_alert_secs),
+        },
+        watchdog: WatchdogOptions {
+            slow_block: (opt.slow_block_ms > 0).then(|| Duration::from_millis(opt.slow_block_ms)),
+            block_limit: opt.block_limit_ms.map(Duration::from_millis),
+        },
+        invariant_interval: opt.invariant_check_interval,
+        event_backfill: EventBackfillOptions {
+            interval: Duration::from_secs(opt.event_backfill_interval_secs),
+            window_blocks: opt.event_backfill_window_blocks,
+            batch_blocks: opt.event_backfill_batch_blocks,
+        },
+        retention,
+        submission_url: opt.l1_submission_url.clone(),
+        diagnostics_dir: opt.diagnostics_dir.clone(),
+        cursor_path: opt.cursor_path.clone(),
+        snapshots: opt.snapshot_dir.clone().map(|dir| SnapshotOptions {
+            dir,
+            interval: opt.snapshot_interval,
+        }),
+        start: opt
+            .start_snapshot
+            .clone()
+            .zip(opt.start_height)
+            .zip(opt.start_commitment)
+            .map(
+                |((state_snapshot_path, height), expected_commitment)| StartPoint {
+                    height,
+                    state_snapshot_path,
+                    expected_commitment,
+                },
+            ),
+        check_commitments: !opt.skip_commitment_check,
+        verify_chain: !opt.skip_chain_verification,
+        submitter_lease_blocks: opt.submitter_lease_blocks,
+        read_only: false,
+        l1_updates,
+        proof_interval: opt.proof_interval,
+        proof_queue_depth: opt.proof_queue_depth,
+        drain_timeout: Duration::from_millis(opt.proof_drain_timeout_ms),
+        shutdown: Some(shutdown.clone()),
+    };
+
+    // A node resuming from a snapshot may lack the history below it; fill it in alongside the
+    // executor.
+    if let Some(dir) = opt
+        .snapshot_dir
+        .as_ref()
+        .filter(|_| resumed && !opt.skip_backfill)
+    {
+        let backfill_options = BackfillOptions {
+            sequencer_url: opt.sequencer_url.clone(),
+            l1_provider: opt.l1_http_provider.clone(),
+            rollup_address,
+            cursor_path: Some(dir.join(BACKFILL_CURSOR_FILE)),
+            delay: Duration::from_millis(opt.backfill_delay_ms),
+        };
+        let state = state.clone();
+        let status = status.clone();
+        spawn(async move {
+            if let Err(err) = run_backfill(&backfill_options, genesis, state, status).await {
+                tracing::error!(target: NODE, "Backfill stopped: {err}");
+            }
+        });
+    }
+
+    tracing::info!(target: NODE, "Launching Example Rollup API and Executor");
+    {
+        let state = state.clone();
+        let status = status.clone();
+        spawn(async move { serve(&api_options, state, status).await.unwrap() });
+    }
+    if opt.with_commitment_task {
+        tracing::info!(target: NODE, "Launching HotShot commitment task");
+        let task_options = commitment_task_options(&opt);
+        let status = status.clone();
+        let shutdown = shutdown.clone();
+        spawn(async move {
+            run_commitment_task_supervised(&task_options, status, Some(shutdown)).await
+        });
+    }
+    let execute = run_executor_supervised(&executor_options, state.clone(), status.clone());
+    // The executor may be stuck connecting to the sequencer or L1 when it is told to stop, in which
+    // case it is abandoned once it has had time to stop.
+    let deadline = async {
+        shutdown.recv().await.ok();
+        shutdown.close();
+        sleep(executor_options.drain_timeout + SHUTDOWN_GRACE).await;
     };
+    match future::select(Box::pin(execute), Box::pin(deadline)).await {
+        Either::Left((res, _)) => res.unwrap(),
+        Either::Right(((), _)) => {
+            tracing::warn!(target: NODE, "executor did not stop in time, exiting anyway")
+        }
+    }
+    drop(stop);
 
-    tracing::info!("Launching Example Rollup API and Executor");
-    join!(run_executor(&executor_options, state.clone()), serve_api,);
+    let height = state.read().await.block_height();
+    let dropped_proofs = status.read().await.dropped_proofs;
+    tracing::info!(
+        target: NODE,
+        height,
+        dropped_proofs,
+        "shutdown complete: executed {height} blocks, {dropped_proofs} of them not proven on L1"
+    );
 }
@@ -0,0 +1,425 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Prometheus metrics of the node, served by the `metrics` route of the API.
+//!
+//! The executor and the API already keep every number exported here in the [`ExecutorStatus`]
+//! they report, so the metrics are refreshed from that status each time they are scraped, rather
+//! than updated alongside it wherever it changes. Counters follow the totals in the status, which
+//! only grow while the node runs.
+//!
+//! Every metric carries the `version` and `git_commit` of the node's [build](BuildInfo) as
+//! constant labels.
+
+use prometheus::{
+    Gauge, GaugeVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::build_info::BuildInfo;
+use crate::status::ExecutorStatus;
+
+/// The metrics of a node, registered in a registry of their own.
+#[derive(Clone)]
+pub struct NodeMetrics {
+    registry: Registry,
+    executed_height: IntGauge,
+    verified_height: IntGauge,
+    finalized_height: IntGauge,
+    sequencing_lag: IntGauge,
+    verification_lag: IntGauge,
+    restarts: IntCounter,
+    payloads: IntCounterVec,
+    unavailable_blocks: IntCounter,
+    operator_balance: Gauge,
+    runway_blocks: IntGauge,
+    proof_calldata_gas: IntCounter,
+    read_cache_hits: IntCounter,
+    read_cache_misses: IntCounter,
+    read_cache_balances: IntGauge,
+    forwarding_queue_depth: IntGauge,
+    forwarding_attempts: IntCounterVec,
+    forwarding_latency: GaugeVec,
+    query_requests: IntCounterVec,
+    query_failures: IntCounterVec,
+    query_latency: GaugeVec,
+    query_breaker_open: IntGaugeVec,
+}
+
+impl NodeMetrics {
+    pub fn new(build: &BuildInfo) -> Self {
+        let labels = HashMap::from([
+            ("version".to_string(), build.version.clone()),
+            ("git_commit".to_string(), build.git_commit.clone()),
+        ]);
+        let registry =
+            Registry::new_custom(None, Some(labels)).expect("constant labels should be valid");
+        let metrics = Self {
+            executed_height: IntGauge::new(
+                "executed_height",
+                "Number of rollup blocks executed by this node",
+            )
+            .unwrap(),
+            verified_height: IntGauge::new(
+                "verified_height",
+                "Number of rollup blocks this node has seen the rollup contract verify",
+            )
+            .unwrap(),
+            finalized_height: IntGauge::new(
+                "finalized_height",
+                "Number of rollup blocks this node has seen the rollup contract finalize",
+            )
+            .unwrap(),
+            sequencing_lag: IntGauge::new(
+                "sequencing_lag",
+                "Blocks committed to the HotShot contract which this node has not executed",
+            )
+            .unwrap(),
+            verification_lag: IntGauge::new(
+                "verification_lag",
+                "Blocks executed by this node which the rollup contract has not verified",
+            )
+            .unwrap(),
+            restarts: IntCounter::new(
+                "executor_restarts_total",
+                "Times the executor has been restarted after a failure",
+            )
+            .unwrap(),
+            payloads: IntCounterVec::new(
+                Opts::new(
+                    "block_payloads_total",
+                    "Transactions in the blocks executed, by whether they were in this rollup's \
+                     namespace, decoded and were applied",
+                ),
+                &["kind"],
+            )
+            .unwrap(),
+            unavailable_blocks: IntCounter::new(
+                "block_unavailable_total",
+                "Blocks certified by the HotShot contract before the query service had them",
+            )
+            .unwrap(),
+            operator_balance: Gauge::new(
+                "operator_balance_wei",
+                "Balance of the L1 account proofs are sent from, when it was last checked",
+            )
+            .unwrap(),
+            runway_blocks: IntGauge::new(
+                "operator_runway_blocks",
+                "Blocks the operator account can pay to prove at the recent cost of proofs",
+            )
+            .unwrap(),
+            proof_calldata_gas: IntCounter::new(
+                "proof_calldata_gas_total",
+                "Calldata gas of the proofs the rollup contract accepted",
+            )
+            .unwrap(),
+            read_cache_hits: IntCounter::new(
+                "read_cache_hits_total",
+                "Reads answered from the API's read cache",
+            )
+            .unwrap(),
+            read_cache_misses: IntCounter::new(
+                "read_cache_misses_total",
+                "Reads the API's read cache had to pass on to the state",
+            )
+            .unwrap(),
+            read_cache_balances: IntGauge::new(
+                "read_cache_balances",
+                "Balances held in the API's read cache",
+            )
+            .unwrap(),
+            forwarding_queue_depth: IntGauge::new(
+                "forwarding_queue_depth",
+                "Submitted transactions waiting to be forwarded to the sequencer",
+            )
+            .unwrap(),
+            forwarding_attempts: IntCounterVec::new(
+                Opts::new(
+                    "forwarding_attempts_total",
+                    "Attempts to forward a transaction to the sequencer, by result",
+                ),
+                &["result"],
+            )
+            .unwrap(),
+            forwarding_latency: GaugeVec::new(
+                Opts::new(
+                    "forwarding_latency_seconds",
+                    "Time the sequencer took to accept forwarded transactions: the last, and \
+                     the mean",
+                ),
+                &["stat"],
+            )
+            .unwrap(),
+            query_requests: IntCounterVec::new(
+                Opts::new(
+                    "query_requests_total",
+                    "Requests each replica of the query service answered",
+                ),
+                &["replica"],
+            )
+            .unwrap(),
+            query_failures: IntCounterVec::new(
+                Opts::new(
+                    "query_failures_total",
+                    "Requests each replica of the query service failed to answer",
+                ),
+                &["replica"],
+            )
+            .unwrap(),
+            query_latency: GaugeVec::new(
+                Opts::new(
+                    "query_latency_seconds",
+                    "Time each replica of the query service took to answer: the last request, \
+                     and the mean",
+                ),
+                &["replica", "stat"],
+            )
+            .unwrap(),
+            query_breaker_open: IntGaugeVec::new(
+                Opts::new(
+                    "query_breaker_open",
+                    "Whether the circuit breaker of each replica of the query service is open",
+                ),
+                &["replica"],
+            )
+            .unwrap(),
+            registry,
+        };
+        metrics.register();
+        metrics
+    }
+
+    fn register(&self) {
+        let collectors: [Box<dyn prometheus::core::Collector>; 21] = [
+            Box::new(self.executed_height.clone()),
+            Box::new(self.verified_height.clone()),
+            Box::new(self.finalized_height.clone()),
+            Box::new(self.sequencing_lag.clone()),
+            Box::new(self.verification_lag.clone()),
+            Box::new(self.restarts.clone()),
+            Box::new(self.payloads.clone()),
+            Box::new(self.unavailable_blocks.clone()),
+            Box::new(self.operator_balance.clone()),
+            Box::new(self.runway_blocks.clone()),
+            Box::new(self.proof_calldata_gas.clone()),
+            Box::new(self.read_cache_hits.clone()),
+            Box::new(self.read_cache_misses.clone()),
+            Box::new(self.read_cache_balances.clone()),
+            Box::new(self.forwarding_queue_depth.clone()),
+            Box::new(self.forwarding_attempts.clone()),
+            Box::new(self.forwarding_latency.clone()),
+            Box::new(self.query_requests.clone()),
+            Box::new(self.query_failures.clone()),
+            Box::new(self.query_latency.clone()),
+            Box::new(self.query_breaker_open.clone()),
+        ];
+        for collector in collectors {
+            self.registry
+                .register(collector)
+                .expect("metric names should be unique");
+        }
+    }
+
+    /// The registry the metrics are registered in, for exporting them.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Bring the metrics up to date with `status`.
+    pub fn update(&self, status: &ExecutorStatus) {
+        self.executed_height.set(gauge(status.local_height));
+        self.verified_height.set(gauge(status.verified_height));
+        self.finalized_height.set(gauge(status.finalized_height));
+        self.sequencing_lag.set(gauge(status.sequencing_lag));
+        self.verification_lag.set(gauge(status.verification_lag));
+        advance(&self.restarts, status.restarts);
+
+        let payloads = &status.payloads;
+        for (kind, total) in [
+            ("total", payloads.total_payloads),
+            ("vm", payloads.vm_payloads),
+            ("decoded", payloads.decoded),
+            ("applied", payloads.applied),
+            ("duplicate", payloads.duplicates),
+            ("forced", payloads.forced),
+        ] {
+            advance(&self.payloads.with_label_values(&[kind]), total);
+        }
+        advance(&self.unavailable_blocks, status.unavailable_blocks);
+
+        if let Some(balance) = status.operator_balance {
+            // Wei do not fit in a float exactly, but a gauge only needs to be close.
+            self.operator_balance
+                .set(balance.to_string().parse().unwrap_or(f64::MAX));
+        }
+        if let Some(runway) = status.runway_blocks {
+            self.runway_blocks.set(gauge(runway));
+        }
+        advance(&self.proof_calldata_gas, status.proof_calldata_gas);
+
+        if let Some(cache) = &status.read_cache {
+            advance(&self.read_cache_hits, cache.hits);
+            advance(&self.read_cache_misses, cache.misses);
+            self.read_cache_balances.set(gauge(cache.balances as u64));
+        }
+
+        if let Some(forwarding) = &status.forwarding {
+            self.forwarding_queue_depth
+                .set(gauge(forwarding.queue_depth as u64));
+            for (result, total) in [
+                ("forwarded", forwarding.forwarded),
+                ("unavailable", forwarding.unavailable),
+                ("rejected", forwarding.rejected),
+                ("timed_out", forwarding.timed_out),
+            ] {
+                advance(
+                    &self.forwarding_attempts.with_label_values(&[result]),
+                    total,
+                );
+            }
+            for (stat, ms) in [
+                ("last", forwarding.last_latency_ms),
+                ("mean", forwarding.mean_latency_ms),
+            ] {
+                if let Some(ms) = ms {
+                    self.forwarding_latency
+                        .with_label_values(&[stat])
+                        .set(seconds(ms));
+                }
+            }
+        }
+
+        for replica in &status.query_replicas {
+            let url = replica.url.as_str();
+            advance(
+                &self.query_requests.with_label_values(&[url]),
+                replica.answered,
+            );
+            advance(
+                &self.query_failures.with_label_values(&[url]),
+                replica.failures,
+            );
+            for (stat, ms) in [
+                ("last", replica.last_latency_ms),
+                ("mean", replica.mean_latency_ms),
+            ] {
+                if let Some(ms) = ms {
+                    self.query_latency
+                        .with_label_values(&[url, stat])
+                        .set(seconds(ms));
+                }
+            }
+            self.query_breaker_open
+                .with_label_values(&[url])
+                .set(replica.breaker_open.into());
+        }
+    }
+}
+
+impl Debug for NodeMetrics {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("NodeMetrics").finish_non_exhaustive()
+    }
+}
+
+/// Raise `counter` to `total`. Totals never go down while the node runs, so neither does the
+/// counter.
+fn advance(counter: &IntCounter, total: u64) {
+    let current = counter.get();
+    if total > current {
+        counter.inc_by(total - current);
+    }
+}
+
+fn gauge(value: u64) -> i64 {
+    value.try_into().unwrap_or(i64::MAX)
+}
+
+fn seconds(ms: u64) -> f64 {
+    ms as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheStats;
+    use prometheus::{Encoder, TextEncoder};
+
+    fn export(metrics: &NodeMetrics) -> String {
+        let mut buf = vec![];
+        TextEncoder::new()
+            .encode(&metrics.registry().gather(), &mut buf)
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// The value of the metric `name` with the label `label`, if it was exported with the labels of
+    /// `build`.
+    fn value(exported: &str, build: &BuildInfo, name: &str, label: &str) -> Option<String> {
+        exported
+            .lines()
+            .filter(|line| line.starts_with(&format!("{name}{{")))
+            .filter(|line| line.contains(&format!("version=\"{}\"", build.version)))
+            .filter(|line| line.contains(&format!("git_commit=\"{}\"", build.git_commit)))
+            .find(|line| line.contains(label))
+            .and_then(|line| line.rsplit(' ').next())
+            .map(String::from)
+    }
+
+    #[test]
+    fn test_metrics_follow_status() {
+        let build = BuildInfo::current();
+        let metrics = NodeMetrics::new(&build);
+        let mut status = ExecutorStatus {
+            local_height: 7,
+            sequencing_lag: 3,
+            verification_lag: 2,
+            unavailable_blocks: 1,
+            read_cache: Some(CacheStats {
+                hits: 5,
+                misses: 4,
+                balances: 2,
+            }),
+            ..Default::default()
+        };
+        status.payloads.total_payloads = 10;
+        status.payloads.vm_payloads = 6;
+        metrics.update(&status);
+        let exported = export(&metrics);
+        for (name, label, expected) in [
+            ("executed_height", "", "7"),
+            ("sequencing_lag", "", "3"),
+            ("verification_lag", "", "2"),
+            ("block_unavailable_total", "", "1"),
+            ("block_payloads_total", "kind=\"total\"", "10"),
+            ("block_payloads_total", "kind=\"vm\"", "6"),
+            ("read_cache_hits_total", "", "5"),
+            ("read_cache_misses_total", "", "4"),
+        ] {
+            assert_eq!(
+                value(&exported, &build, name, label).as_deref(),
+                Some(expected),
+                "{name} {label} in {exported}"
+            );
+        }
+
+        // Gauges follow the status down; counters only go up.
+        status.sequencing_lag = 0;
+        status.unavailable_blocks = 3;
+        metrics.update(&status);
+        let exported = export(&metrics);
+        assert_eq!(
+            value(&exported, &build, "sequencing_lag", "").as_deref(),
+            Some("0")
+        );
+        assert_eq!(
+            value(&exported, &build, "block_unavailable_total", "").as_deref(),
+            Some("3")
+        );
+    }
+}
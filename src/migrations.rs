@@ -0,0 +1,643 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Upgrades of the files the node persists from the formats written by older versions.
+//!
+//! Each persisted file records the version of its format in a `format_version` field. Files
+//! written before formats were versioned have none, and are version 1. A change to a format bumps
+//! its version and adds a function to [`MIGRATIONS`] which upgrades files from the previous
+//! version. Migrations work on the JSON of a file, so old formats need no Rust types of their own.
+//!
+//! On startup, the node upgrades its data directories with [`migrate_files`] before reading
+//! anything from them, keeping a copy of each file as it was. The loaders also upgrade what they
+//! read in memory, so a file restored from a backup is still understood. Files in a format newer
+//! than this build supports are never touched: reading them could silently drop whatever the
+//! newer format added, so the node refuses to start instead.
+
+use ethers::types::{Address, U256};
+use serde_json::{json, Value};
+use snafu::{ResultExt, Snafu};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::backfill::BACKFILL_CURSOR_FILE;
+use crate::balance_history::BalanceHistory;
+use crate::logging::STATE;
+use crate::retention::PrunedHeights;
+use crate::snapshot::list_snapshots;
+use crate::state::{ChainConfig, MinFee, Supply};
+
+/// Version of the snapshot format written by this build. Also used for the backfill cursor, which
+/// is a snapshot.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 9;
+
+/// Version of the executor cursor format written by this build.
+pub const CURSOR_FORMAT_VERSION: u32 = 2;
+
+/// A kind of persisted file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// A state snapshot, or the backfill cursor.
+    Snapshot,
+    /// The executor cursor.
+    Cursor,
+}
+
+impl Format {
+    /// The version of this format written by this build.
+    pub fn current_version(self) -> u32 {
+        match self {
+            Self::Snapshot => SNAPSHOT_FORMAT_VERSION,
+            Self::Cursor => CURSOR_FORMAT_VERSION,
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Snapshot => write!(f, "snapshot"),
+            Self::Cursor => write!(f, "executor cursor"),
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum MigrationError {
+    #[snafu(display(
+        "{format} format version {found} is newer than version {supported}, the newest this build \
+        of the node supports. Upgrade the node to use this data."
+    ))]
+    TooNew {
+        format: Format,
+        found: u32,
+        supported: u32,
+    },
+    #[snafu(display("Malformed {format}: {reason}"))]
+    Malformed { format: Format, reason: String },
+    #[snafu(display("{path:?}: {source}"))]
+    File {
+        path: PathBuf,
+        #[snafu(source(from(MigrationError, Box::new)))]
+        source: Box<MigrationError>,
+    },
+    #[snafu(display("Unable to migrate {path:?}: {source}"))]
+    Io { path: PathBuf, source: io::Error },
+}
+
+/// An upgrade of files in `format` from version `from` to the next.
+struct Migration {
+    format: Format,
+    from: u32,
+    migrate: fn(&mut Value) -> Result<(), String>,
+}
+
+/// Every migration, in no particular order. Each format must have one from every version below
+/// its current one.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        format: Format::Snapshot,
+        from: 1,
+        migrate: migrate_snapshot_v1_to_v2,
+    },
+    Migration {
+        format: Format::Snapshot,
+        from: 2,
+        migrate: migrate_snapshot_v2_to_v3,
+    },
+    Migration {
+        format: Format::Snapshot,
+        from: 3,
+        migrate: migrate_snapshot_v3_to_v4,
+    },
+    Migration {
+        format: Format::Snapshot,
+        from: 4,
+        migrate: migrate_snapshot_v4_to_v5,
+    },
+    Migration {
+        format: Format::Snapshot,
+        from: 5,
+        migrate: migrate_snapshot_v5_to_v6,
+    },
+    Migration {
+        format: Format::Snapshot,
+        from: 6,
+        migrate: migrate_snapshot_v6_to_v7,
+    },
+    Migration {
+        format: Format::Snapshot,
+        from: 7,
+        migrate: migrate_snapshot_v7_to_v8,
+    },
+    Migration {
+        format: Format::Snapshot,
+        from: 8,
+        migrate: migrate_snapshot_v8_to_v9,
+    },
+    Migration {
+        format: Format::Cursor,
+        from: 1,
+        migrate: migrate_cursor_v1_to_v2,
+    },
+];
+
+/// Version 2 requires the parts of the state added since the first snapshots were written, which
+/// used to be filled in when missing: the multisig accounts, the chain configuration, the deposited
+/// assets, the last L1 block whose forced transactions have been applied, the message inbox with
+/// the nonce of the last message from each sender, the outbox, and the summaries and receipts of
+/// recent blocks. Version 1 snapshots get what a state had before each of them existed.
+fn migrate_snapshot_v1_to_v2(file: &mut Value) -> Result<(), String> {
+    let state = file
+        .get_mut("state")
+        .and_then(Value::as_object_mut)
+        .ok_or("no state")?;
+    let chain_config = serde_json::to_value(ChainConfig::default()).map_err(|e| e.to_string())?;
+    for (field, value) in [
+        ("multisigs", json!({})),
+        ("chain_config", chain_config),
+        ("asset_balances", json!({})),
+        ("deposits_through", json!(0)),
+        ("forced_through", json!(0)),
+        ("inbox", json!({})),
+        ("inbox_nonces", json!({})),
+        ("outbox", Value::Null),
+        ("recent_summaries", json!({})),
+        ("recent_receipts", json!({})),
+    ] {
+        state.entry(field).or_insert(value);
+    }
+    Ok(())
+}
+
+/// Version 3 records how far the history of blocks in the state has been pruned. Nothing had been,
+/// beyond the fixed limits of earlier versions, which were not recorded.
+fn migrate_snapshot_v2_to_v3(file: &mut Value) -> Result<(), String> {
+    let state = file
+        .get_mut("state")
+        .and_then(Value::as_object_mut)
+        .ok_or("no state")?;
+    let pruned = serde_json::to_value(PrunedHeights::default()).map_err(|e| e.to_string())?;
+    state.entry("pruned").or_insert(pruned);
+    Ok(())
+}
+
+/// Version 4 records which transactions of the recent blocks were applied, so that copies of them
+/// sequenced later are recognized as duplicates. Earlier versions did not, so none are known to
+/// have been; a copy of one of them still fails on its nonce, and is only reported differently.
+fn migrate_snapshot_v3_to_v4(file: &mut Value) -> Result<(), String> {
+    let state = file
+        .get_mut("state")
+        .and_then(Value::as_object_mut)
+        .ok_or("no state")?;
+    state.entry("recent_applied").or_insert(json!({}));
+    Ok(())
+}
+
+/// Version 5 records the history of each account for the API. Earlier versions did not, so each
+/// account of the snapshot is recorded as first seen at the snapshot's height, and its
+/// transactions are counted from there.
+fn migrate_snapshot_v4_to_v5(file: &mut Value) -> Result<(), String> {
+    let state = file
+        .get_mut("state")
+        .and_then(Value::as_object_mut)
+        .ok_or("no state")?;
+    let height = state
+        .get("block_height")
+        .cloned()
+        .ok_or("no block height")?;
+    let activity = state
+        .get("accounts")
+        .and_then(Value::as_object)
+        .ok_or("no accounts")?
+        .keys()
+        .map(|address| {
+            let activity = json!({ "first_seen": height, "transactions": 0 });
+            (address.clone(), activity)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    state.entry("activity").or_insert(activity.into());
+    Ok(())
+}
+
+/// Version 6 keeps checkpoints of the balance of each account for the API. Earlier versions did
+/// not, so the history starts with the balance of each account at the snapshot's height.
+fn migrate_snapshot_v5_to_v6(file: &mut Value) -> Result<(), String> {
+    let state = file
+        .get_mut("state")
+        .and_then(Value::as_object_mut)
+        .ok_or("no state")?;
+    let height = state
+        .get("block_height")
+        .and_then(Value::as_u64)
+        .ok_or("no block height")?;
+    let balances = state
+        .get("accounts")
+        .and_then(Value::as_object)
+        .ok_or("no accounts")?
+        .iter()
+        .map(|(address, account)| {
+            let address = address.parse::<Address>().map_err(|e| e.to_string())?;
+            let balance = account
+                .get("balance")
+                .and_then(Value::as_u64)
+                .ok_or("account without a balance")?;
+            Ok((address, balance))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let history =
+        serde_json::to_value(BalanceHistory::new(height, balances)).map_err(|e| e.to_string())?;
+    state.entry("balance_history").or_insert(history);
+    Ok(())
+}
+
+/// Version 7 records which L1 transaction proved each recent block. Earlier versions did not, so
+/// none is known until the executor reads the rollup contract's state updates again.
+fn migrate_snapshot_v6_to_v7(file: &mut Value) -> Result<(), String> {
+    let state = file
+        .get_mut("state")
+        .and_then(Value::as_object_mut)
+        .ok_or("no state")?;
+    state.entry("proof_transactions").or_insert(json!({}));
+    Ok(())
+}
+
+/// Version 8 keeps the supply of each token, which the invariants check the balances against.
+/// Earlier versions did not, so the supply starts from the balances at the snapshot's height, as if
+/// they were the genesis balances.
+fn migrate_snapshot_v7_to_v8(file: &mut Value) -> Result<(), String> {
+    let state = file
+        .get_mut("state")
+        .and_then(Value::as_object_mut)
+        .ok_or("no state")?;
+    let total = |balances: Vec<&Value>| {
+        balances
+            .into_iter()
+            .try_fold(U256::zero(), |total, balance| {
+                let balance = balance.as_u64().ok_or("malformed balance")?;
+                Ok::<_, String>(total + U256::from(balance))
+            })
+    };
+    let genesis = total(
+        state
+            .get("accounts")
+            .and_then(Value::as_object)
+            .ok_or("no accounts")?
+            .values()
+            .map(|account| account.get("balance").unwrap_or(&Value::Null))
+            .collect(),
+    )?;
+    let deposited = state
+        .get("asset_balances")
+        .and_then(Value::as_object)
+        .ok_or("no asset balances")?
+        .iter()
+        .map(|(asset, balances)| {
+            let asset = asset.parse::<Address>().map_err(|e| e.to_string())?;
+            let balances = balances.as_object().ok_or("malformed asset balances")?;
+            Ok((asset, total(balances.values().collect())?))
+        })
+        .collect::<Result<_, String>>()?;
+    let supply = Supply {
+        genesis,
+        burned: U256::zero(),
+        deposited,
+    };
+    let supply = serde_json::to_value(supply).map_err(|e| e.to_string())?;
+    state.entry("supply").or_insert(supply);
+    Ok(())
+}
+
+/// Version 9 keeps the minimum fee, which the fee admin can change. Earlier versions had none, so
+/// the minimum is zero, with no change scheduled, as it is at genesis.
+fn migrate_snapshot_v8_to_v9(file: &mut Value) -> Result<(), String> {
+    let state = file
+        .get_mut("state")
+        .and_then(Value::as_object_mut)
+        .ok_or("no state")?;
+    let min_fee = serde_json::to_value(MinFee::default()).map_err(|e| e.to_string())?;
+    state.entry("min_fee").or_insert(min_fee);
+    Ok(())
+}
+
+/// Version 2 only added the format version.
+fn migrate_cursor_v1_to_v2(_file: &mut Value) -> Result<(), String> {
+    Ok(())
+}
+
+/// The version of the format `file` is in.
+pub fn format_version(format: Format, file: &Value) -> Result<u32, MigrationError> {
+    let malformed = |reason: &str| MigrationError::Malformed {
+        format,
+        reason: reason.into(),
+    };
+    let file = file.as_object().ok_or_else(|| malformed("not an object"))?;
+    match file.get("format_version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| version.try_into().ok())
+            .ok_or_else(|| malformed("invalid format version")),
+    }
+}
+
+/// Bring `file` up to the current version of `format`.
+///
+/// Fails without changing anything if `file` is in a newer version than this build supports.
+pub fn upgrade(format: Format, mut file: Value) -> Result<Value, MigrationError> {
+    let supported = format.current_version();
+    let mut version = format_version(format, &file)?;
+    if version > supported {
+        return TooNewSnafu {
+            format,
+            found: version,
+            supported,
+        }
+        .fail();
+    }
+    while version < supported {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.format == format && migration.from == version)
+            .expect("every format has a migration from each older version");
+        (migration.migrate)(&mut file)
+            .map_err(|reason| MigrationError::Malformed { format, reason })?;
+        version += 1;
+        file["format_version"] = version.into();
+    }
+    Ok(file)
+}
+
+/// The persisted files which exist, given where they are kept: the snapshots and the backfill
+/// cursor in `snapshot_dir`, and the executor cursor at `cursor_path`.
+pub fn data_files(
+    snapshot_dir: Option<&Path>,
+    cursor_path: Option<&Path>,
+) -> Result<Vec<(Format, PathBuf)>, MigrationError> {
+    let mut files = vec![];
+    if let Some(dir) = snapshot_dir {
+        match list_snapshots(dir) {
+            Ok(snapshots) => files.extend(
+                snapshots
+                    .into_iter()
+                    .map(|(_, path)| (Format::Snapshot, path)),
+            ),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(source) => {
+                return Err(MigrationError::Io {
+                    path: dir.into(),
+                    source,
+                })
+            }
+        }
+        files.push((Format::Snapshot, dir.join(BACKFILL_CURSOR_FILE)));
+    }
+    if let Some(path) = cursor_path {
+        files.push((Format::Cursor, path.into()));
+    }
+    files.retain(|(_, path)| path.exists());
+    Ok(files)
+}
+
+/// The path the contents of `path` are kept at when it is upgraded from `version`.
+pub fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{version}.bak"));
+    path.with_file_name(name)
+}
+
+/// Upgrade each of `files` which is in an older version of its format, keeping the original at its
+/// [`backup_path`]. Returns the paths of the files upgraded.
+///
+/// Every file is upgraded in memory and written out beside the original before any original is
+/// replaced, and each is replaced by an atomic rename. If any file is in a newer format than this
+/// build supports, nothing is changed. Files which cannot be parsed are left alone for the loaders
+/// to reject, as they would have been without migrations.
+pub fn migrate_files(files: &[(Format, PathBuf)]) -> Result<Vec<PathBuf>, MigrationError> {
+    let mut pending = vec![];
+    for (format, path) in files {
+        let bytes = fs::read(path).context(IoSnafu { path })?;
+        let Ok(file) = serde_json::from_slice::<Value>(&bytes) else {
+            tracing::warn!(target: STATE, "{path:?} is not a valid {format}, leaving it as it is");
+            continue;
+        };
+        let version = match format_version(*format, &file) {
+            Ok(version) if version == format.current_version() => continue,
+            Ok(version) => version,
+            Err(err) => {
+                tracing::warn!(target: STATE, "{path:?}: {err}, leaving it as it is");
+                continue;
+            }
+        };
+        match upgrade(*format, file) {
+            Ok(file) => pending.push((path, version, file)),
+            Err(err @ MigrationError::TooNew { .. }) => {
+                return Err(err).context(FileSnafu { path })
+            }
+            Err(err) => tracing::warn!(target: STATE, "{path:?}: {err}, leaving it as it is"),
+        }
+    }
+
+    for (path, version, file) in &pending {
+        fs::copy(path, backup_path(path, *version)).context(IoSnafu { path: *path })?;
+        let tmp = path.with_extension("migrating");
+        fs::write(&tmp, serde_json::to_vec(file).unwrap()).context(IoSnafu { path: &tmp })?;
+    }
+    let mut migrated = vec![];
+    for (path, version, _) in pending {
+        fs::rename(path.with_extension("migrating"), path).context(IoSnafu { path })?;
+        tracing::info!(
+            target: STATE,
+            "migrated {path:?} from format version {version}, keeping the original at {:?}",
+            backup_path(path, version)
+        );
+        migrated.push(path.clone());
+    }
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor::Cursor;
+    use crate::snapshot::{load_latest_snapshot, load_snapshot, store_snapshot};
+    use crate::state::State;
+    use crate::RollupVM;
+    use commit::Committable;
+    use ethers::types::Address;
+    use tempfile::TempDir;
+
+    /// Copy the checked-in data directory written by version `version` of each format to a
+    /// temporary directory.
+    fn fixture_dir(version: u32) -> TempDir {
+        let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/migrations")
+            .join(format!("v{version}"));
+        let dir = TempDir::new().unwrap();
+        for entry in fs::read_dir(fixture).unwrap() {
+            let entry = entry.unwrap();
+            fs::copy(entry.path(), dir.path().join(entry.file_name())).unwrap();
+        }
+        dir
+    }
+
+    /// The state in the snapshot in the version 1 fixture.
+    fn v1_state() -> State {
+        let alice: Address = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8"
+            .parse()
+            .unwrap();
+        State::from_initial_balances([(alice, 1000)], RollupVM::new(1.into()))
+    }
+
+    #[test]
+    fn test_migrations_registered() {
+        for format in [Format::Snapshot, Format::Cursor] {
+            for from in 1..format.current_version() {
+                assert_eq!(
+                    MIGRATIONS
+                        .iter()
+                        .filter(|m| m.format == format && m.from == from)
+                        .count(),
+                    1,
+                    "{format} migration from version {from}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_migrate_v1_fixture() {
+        let dir = fixture_dir(1);
+        let snapshot = dir.path().join("state-0.json");
+        let cursor = dir.path().join("cursor.json");
+
+        // The commitment in a snapshot depends on how the commit crate encodes it, so the fixture
+        // leaves it out and it is filled in here from the state the fixture was written from.
+        let mut file: Value = serde_json::from_slice(&fs::read(&snapshot).unwrap()).unwrap();
+        file["commitment"] = serde_json::to_value(v1_state().commit()).unwrap();
+        fs::write(&snapshot, file.to_string()).unwrap();
+        let original_snapshot = fs::read(&snapshot).unwrap();
+        let original_cursor = fs::read(&cursor).unwrap();
+
+        let files = data_files(Some(dir.path()), Some(&cursor)).unwrap();
+        assert_eq!(
+            files,
+            [
+                (Format::Snapshot, snapshot.clone()),
+                (Format::Cursor, cursor.clone())
+            ]
+        );
+        assert_eq!(
+            migrate_files(&files).unwrap(),
+            [snapshot.clone(), cursor.clone()]
+        );
+
+        // The originals are kept.
+        assert_eq!(
+            fs::read(backup_path(&snapshot, 1)).unwrap(),
+            original_snapshot
+        );
+        assert_eq!(fs::read(backup_path(&cursor, 1)).unwrap(), original_cursor);
+        assert!(!snapshot.with_extension("migrating").exists());
+
+        // The upgraded files are current, and hold what the originals did.
+        for (format, path) in &files {
+            let file: Value = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+            assert_eq!(
+                format_version(*format, &file).unwrap(),
+                format.current_version()
+            );
+        }
+        let state = load_latest_snapshot(dir.path(), &RollupVM::new(1.into())).unwrap();
+        assert_eq!(state.commit(), v1_state().commit());
+        assert_eq!(state.supply(), v1_state().supply());
+        assert_eq!(
+            Cursor::load(&cursor).unwrap(),
+            Some(Cursor {
+                executed_height: 2,
+                submitted_height: 2,
+                l1_log_block: 17,
+                ..Default::default()
+            })
+        );
+
+        // Migrating again finds nothing to do.
+        assert_eq!(migrate_files(&files).unwrap(), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn test_migrate_stripped_snapshot() {
+        // A snapshot written by this build, cut down to what version 1 had, loads as the original
+        // state once migrated.
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state-0.json");
+        let state = v1_state();
+        store_snapshot(&path, &state).unwrap();
+        let mut file: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        let object = file.as_object_mut().unwrap();
+        object.remove("format_version");
+        object.remove("build");
+        let old_state = object["state"].as_object_mut().unwrap();
+        for field in [
+            "min_fee",
+            "supply",
+            "proof_transactions",
+            "balance_history",
+            "activity",
+            "recent_applied",
+            "pruned",
+            "multisigs",
+            "chain_config",
+            "asset_balances",
+            "deposits_through",
+            "forced_through",
+            "inbox",
+            "inbox_nonces",
+            "outbox",
+            "recent_summaries",
+            "recent_receipts",
+        ] {
+            assert!(old_state.remove(field).is_some(), "{field}");
+        }
+        fs::write(&path, file.to_string()).unwrap();
+
+        // The loader upgrades it in memory, leaving the file as it is.
+        assert_eq!(load_snapshot(&path).unwrap().commit(), state.commit());
+        let files = [(Format::Snapshot, path.clone())];
+        assert_eq!(migrate_files(&files).unwrap(), [path.clone()]);
+        assert_eq!(load_snapshot(&path).unwrap().commit(), state.commit());
+    }
+
+    #[test]
+    fn test_too_new() {
+        let dir = fixture_dir(1);
+        let cursor = dir.path().join("cursor.json");
+        let snapshot = dir.path().join("state-0.json");
+        let mut file: Value = serde_json::from_slice(&fs::read(&snapshot).unwrap()).unwrap();
+        file["format_version"] = (SNAPSHOT_FORMAT_VERSION + 1).into();
+        fs::write(&snapshot, file.to_string()).unwrap();
+        let contents = fs::read(&snapshot).unwrap();
+        let cursor_contents = fs::read(&cursor).unwrap();
+
+        // Nothing is migrated if any file is too new, not even the older files listed before it.
+        let files = [
+            (Format::Cursor, cursor.clone()),
+            (Format::Snapshot, snapshot.clone()),
+        ];
+        let err = migrate_files(&files).unwrap_err();
+        assert!(
+            matches!(&err, MigrationError::File { path, source }
+                if *path == snapshot && matches!(**source, MigrationError::TooNew { .. })),
+            "{err}"
+        );
+        assert_eq!(fs::read(&snapshot).unwrap(), contents);
+        assert_eq!(fs::read(&cursor).unwrap(), cursor_contents);
+        assert!(!backup_path(&cursor, 1).exists());
+        assert!(load_snapshot(&snapshot).unwrap_err().contains("newer"));
+    }
+}
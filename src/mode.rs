@@ -0,0 +1,231 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Which parts of the node a process runs.
+//!
+//! A rollup has a single executor, which holds the L1 key, but the API serving its state can be
+//! scaled out separately. The executor runs in [`RunMode::Executor`], and any number of API nodes
+//! in [`RunMode::Api`] keep their own copy of the state by following it, either through the
+//! snapshots it writes to a shared directory or through its block stream. [`RunMode::Full`] runs
+//! both in one process.
+//!
+//! [`check_mode`] rejects options which the mode needs but lacks, or which it would ignore, so a
+//! misconfigured process fails at startup rather than silently doing something else.
+
+use clap::ValueEnum;
+use snafu::Snafu;
+use std::fmt::{self, Display, Formatter};
+
+use crate::Options;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RunMode {
+    /// Run the executor and serve the API from its state.
+    #[default]
+    Full,
+    /// Serve the API from a state which follows an executor running elsewhere. Nothing is sent to
+    /// L1, and submitted transactions are forwarded to the sequencer.
+    Api,
+    /// Run the executor, serving the API only for its status, its admin routes and the block
+    /// stream API nodes follow. Submitted transactions are refused.
+    Executor,
+}
+
+impl Display for RunMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Full => "full",
+            Self::Api => "api",
+            Self::Executor => "executor",
+        };
+        f.write_str(name)
+    }
+}
+
+impl RunMode {
+    /// Whether this process runs the executor.
+    pub fn runs_executor(&self) -> bool {
+        !matches!(self, Self::Api)
+    }
+}
+
+#[derive(Clone, Debug, Snafu, PartialEq, Eq)]
+pub enum ModeError {
+    #[snafu(display(
+        "api mode needs a state to follow: set --snapshot-dir to the executor's snapshot \
+        directory, or --executor-api-url to the executor's API."
+    ))]
+    MissingStateSource,
+    #[snafu(display(
+        "api mode follows either the executor's snapshots or its API, not both: unset \
+        --snapshot-dir or --executor-api-url."
+    ))]
+    ConflictingStateSources,
+    #[snafu(display(
+        "--executor-api-url cannot be used with --deposit-assets, since an API node has no L1 to \
+        read deposits from. Follow the executor's snapshots with --snapshot-dir instead."
+    ))]
+    FollowWithDeposits,
+    #[snafu(display("--{option} only applies to the executor, which {mode} mode does not run."))]
+    ExecutorOnly { option: &'static str, mode: RunMode },
+    #[snafu(display("--{option} only applies to api mode, not {mode} mode."))]
+    ApiOnly { option: &'static str, mode: RunMode },
+    #[snafu(display(
+        "The commitment task and the executor would both send transactions from account {index}, \
+        competing for its nonces. Set --commitment-account-index to another account."
+    ))]
+    SharedCommitmentAccount { index: u32 },
+}
+
+/// Check that `opt` has what its mode needs, and nothing the mode would ignore.
+pub fn check_mode(opt: &Options) -> Result<(), ModeError> {
+    let mode = opt.mode;
+    if mode.runs_executor() {
+        if opt.executor_api_url.is_some() {
+            return Err(ModeError::ApiOnly {
+                option: "executor-api-url",
+                mode,
+            });
+        }
+        if opt.with_commitment_task && opt.commitment_account_index == opt.rollup_account_index {
+            return Err(ModeError::SharedCommitmentAccount {
+                index: opt.rollup_account_index,
+            });
+        }
+        return Ok(());
+    }
+
+    match (&opt.snapshot_dir, &opt.executor_api_url) {
+        (None, None) => return Err(ModeError::MissingStateSource),
+        (Some(_), Some(_)) => return Err(ModeError::ConflictingStateSources),
+        (None, Some(_)) if !opt.deposit_assets.is_empty() => {
+            return Err(ModeError::FollowWithDeposits)
+        }
+        _ => {}
+    }
+    let executor_only = [
+        ("rollup-address", opt.rollup_address.is_some()),
+        ("cursor-path", opt.cursor_path.is_some()),
+        ("start-snapshot", opt.start_snapshot.is_some()),
+        ("l1-submission-url", opt.l1_submission_url.is_some()),
+        (
+            "submitter-lease-blocks",
+            opt.submitter_lease_blocks.is_some(),
+        ),
+        ("block-summary-log", opt.block_summary_log.is_some()),
+        ("l1-updates-path", opt.l1_updates_path.is_some()),
+        ("webhook-urls", !opt.webhook_urls.is_empty()),
+        ("max-sequencing-lag", opt.max_sequencing_lag.is_some()),
+        ("max-verification-lag", opt.max_verification_lag.is_some()),
+        ("with-commitment-task", opt.with_commitment_task),
+    ];
+    match executor_only.into_iter().find(|(_, set)| *set) {
+        Some((option, _)) => Err(ModeError::ExecutorOnly { option, mode }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn check(args: &[&str]) -> Result<(), ModeError> {
+        let opt = Options::try_parse_from(["example-l2"].iter().chain(args)).unwrap();
+        check_mode(&opt)
+    }
+
+    #[test]
+    fn test_check_mode() {
+        // Full mode is the default, and needs nothing more than before.
+        assert_eq!(check(&[]), Ok(()));
+        assert_eq!(
+            check(&["--mode", "executor", "--cursor-path", "cursor"]),
+            Ok(())
+        );
+        assert_eq!(
+            check(&["--executor-api-url", "http://executor:8084"]),
+            Err(ModeError::ApiOnly {
+                option: "executor-api-url",
+                mode: RunMode::Full
+            })
+        );
+
+        // The commitment task runs with the executor, from an account of its own.
+        assert_eq!(check(&["--with-commitment-task"]), Ok(()));
+        assert_eq!(
+            check(&["--with-commitment-task", "--commitment-account-index", "1"]),
+            Err(ModeError::SharedCommitmentAccount { index: 1 })
+        );
+        assert_eq!(
+            check(&[
+                "--mode",
+                "api",
+                "--snapshot-dir",
+                "data",
+                "--with-commitment-task"
+            ]),
+            Err(ModeError::ExecutorOnly {
+                option: "with-commitment-task",
+                mode: RunMode::Api
+            })
+        );
+
+        // An API node follows exactly one source.
+        assert_eq!(
+            check(&["--mode", "api"]),
+            Err(ModeError::MissingStateSource)
+        );
+        assert_eq!(check(&["--mode", "api", "--snapshot-dir", "data"]), Ok(()));
+        assert_eq!(
+            check(&[
+                "--mode",
+                "api",
+                "--executor-api-url",
+                "http://executor:8084"
+            ]),
+            Ok(())
+        );
+        assert_eq!(
+            check(&[
+                "--mode",
+                "api",
+                "--snapshot-dir",
+                "data",
+                "--executor-api-url",
+                "http://executor:8084"
+            ]),
+            Err(ModeError::ConflictingStateSources)
+        );
+        assert_eq!(
+            check(&[
+                "--mode",
+                "api",
+                "--executor-api-url",
+                "http://executor:8084",
+                "--deposit-assets",
+                "0x0000000000000000000000000000000000000001"
+            ]),
+            Err(ModeError::FollowWithDeposits)
+        );
+
+        // Options of the executor are refused rather than ignored.
+        assert_eq!(
+            check(&[
+                "--mode",
+                "api",
+                "--snapshot-dir",
+                "data",
+                "--cursor-path",
+                "c"
+            ]),
+            Err(ModeError::ExecutorOnly {
+                option: "cursor-path",
+                mode: RunMode::Api
+            })
+        );
+    }
+}
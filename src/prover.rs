@@ -5,16 +5,20 @@
 // along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
 
 extern crate derive_more;
-use ark_serialize::SerializationError;
 use commit::{Commitment, Committable};
 use contract_bindings::example_rollup as bindings;
 use derive_more::Into;
+use ethers::types::Bytes;
 use jf_primitives::merkle_tree::namespaced_merkle_tree::NamespaceProof;
 use sequencer::{NMTRoot, NamespaceProofType, Vm};
-use sequencer_utils::{commitment_to_u256, u256_to_commitment};
+use serde::{Deserialize, Serialize};
 use snafu::Snafu;
 
-use crate::{state::State, RollupVM};
+use crate::{
+    commitments::{commitment_to_u256, u256_to_commitment, CommitmentError},
+    state::{BlockInputs, State},
+    RollupVM,
+};
 
 /// An error that occurs while generating proofs.
 #[derive(Clone, Debug, Snafu)]
@@ -25,15 +29,27 @@ pub enum ProofError {
         new_state: Commitment<State>,
         old_state: Commitment<State>,
     },
+    #[snafu(display("Encoded proof is empty."))]
+    EmptyProof,
+    #[snafu(display("Unknown proof format {format}."))]
+    UnknownFormat { format: u8 },
+    #[snafu(display("Malformed {format:?} proof: {reason}"))]
+    MalformedProof { format: ProofFormat, reason: String },
 }
 
 /// A mock proof that state_commitment represents a valid state transition from
 /// previous_state_commitment when the transactions in a given block are applied.
-#[derive(Debug, Clone)]
-pub(crate) struct Proof {
-    block: Commitment<NMTRoot>,
-    old_state: Commitment<State>,
-    new_state: Commitment<State>,
+///
+/// The proof only refers to the block and states by commitment, so it can be checked with
+/// [`verify_block_proof`] by anyone who has the block and the previous state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proof {
+    /// Commitment to the transaction root of the block.
+    pub block: Commitment<NMTRoot>,
+    /// Commitment to the state before the block.
+    pub old_state: Commitment<State>,
+    /// Commitment to the state after the block.
+    pub new_state: Commitment<State>,
 }
 
 impl Proof {
@@ -42,7 +58,7 @@ impl Proof {
     ///
     /// Transaction data comes from the 'get_namespaced_leaves' method of the NamespaceProof interface.
     /// A real prover would incorporate this data during proof construction.
-    pub fn generate(
+    pub(crate) fn generate(
         nmt_comm: NMTRoot,
         state_commitment: Commitment<State>,
         previous_state_commitment: Commitment<State>,
@@ -61,6 +77,124 @@ impl Proof {
     }
 }
 
+/// Check a block proof by re-executing the block on top of `prev_state`.
+///
+/// Returns `true` if the proof starts from `prev_state`, refers to the block with transaction root
+/// `nmt_root`, `namespace_proof` shows the rollup's transactions in that block, and applying them
+/// after the deposits, messages and forced transactions in `inputs` yields the state the proof
+/// claims.
+pub fn verify_block_proof(
+    prev_state: &State,
+    nmt_root: &NMTRoot,
+    namespace_proof: &NamespaceProofType,
+    inputs: &BlockInputs,
+    proof: &Proof,
+) -> bool {
+    if proof.old_state != prev_state.commit() || proof.block != nmt_root.commit() {
+        return false;
+    }
+    let namespace_proof_valid = namespace_proof
+        .verify(&nmt_root.root(), prev_state.vm.id())
+        .map(|res| res.is_ok())
+        .unwrap_or(false);
+    if !namespace_proof_valid {
+        return false;
+    }
+    let mut state = prev_state.clone();
+    state.apply_block(nmt_root, namespace_proof, inputs);
+    state.commit() == proof.new_state
+}
+
+/// How the bytes of an [`EncodedProof`] after its format byte are packed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProofFormat {
+    /// The JSON serialization of the [`Proof`].
+    Raw = 0,
+    /// The raw format, compressed with zstd.
+    Zstd = 1,
+}
+
+/// Level proofs are compressed at. It is fixed so that every node compresses a proof to the same
+/// bytes, and so makes the same choice of format for it.
+const PROOF_COMPRESSION_LEVEL: i32 = 19;
+
+/// A [`Proof`] as published, for anyone to check with [`verify_encoded_block_proof`]: a byte
+/// giving its [`ProofFormat`], followed by the proof in that format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodedProof(Bytes);
+
+impl EncodedProof {
+    /// Encode `proof`, compressed if `compress` is set and compressing it makes it smaller.
+    ///
+    /// The choice only depends on the proof, so the same proof is always encoded the same way.
+    pub fn encode(proof: &Proof, compress: bool) -> Self {
+        let raw = serde_json::to_vec(proof).expect("Serialization should not fail");
+        let compressed = compress
+            .then(|| zstd::encode_all(raw.as_slice(), PROOF_COMPRESSION_LEVEL).ok())
+            .flatten()
+            .filter(|compressed| compressed.len() < raw.len());
+        let (format, payload) = match compressed {
+            Some(compressed) => (ProofFormat::Zstd, compressed),
+            None => (ProofFormat::Raw, raw),
+        };
+        let mut bytes = Vec::with_capacity(payload.len() + 1);
+        bytes.push(format as u8);
+        bytes.extend(payload);
+        Self(bytes.into())
+    }
+
+    /// The format of the proof, or `None` if its format byte is not one this node knows.
+    pub fn format(&self) -> Option<ProofFormat> {
+        match self.0.first() {
+            Some(0) => Some(ProofFormat::Raw),
+            Some(1) => Some(ProofFormat::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Size of the encoded proof in bytes, including its format byte.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The proof this encodes.
+    pub fn decode(&self) -> Result<Proof, ProofError> {
+        let Some((&byte, payload)) = self.0.split_first() else {
+            return Err(ProofError::EmptyProof);
+        };
+        let format = self
+            .format()
+            .ok_or(ProofError::UnknownFormat { format: byte })?;
+        let malformed = |reason: String| ProofError::MalformedProof { format, reason };
+        let raw = match format {
+            ProofFormat::Raw => payload.to_vec(),
+            ProofFormat::Zstd => {
+                zstd::decode_all(payload).map_err(|err| malformed(err.to_string()))?
+            }
+        };
+        serde_json::from_slice(&raw).map_err(|err| malformed(err.to_string()))
+    }
+}
+
+/// Check an encoded block proof with [`verify_block_proof`], whatever its format.
+///
+/// Returns `false` if the proof cannot be decoded.
+pub fn verify_encoded_block_proof(
+    prev_state: &State,
+    nmt_root: &NMTRoot,
+    namespace_proof: &NamespaceProofType,
+    inputs: &BlockInputs,
+    proof: &EncodedProof,
+) -> bool {
+    proof.decode().is_ok_and(|proof| {
+        verify_block_proof(prev_state, nmt_root, namespace_proof, inputs, &proof)
+    })
+}
+
 /// A mock proof aggregating a batch of proofs for a range of blocks.
 #[derive(Debug, Clone, Into)]
 pub(crate) struct BatchProof {
@@ -98,7 +232,7 @@ impl BatchProof {
 }
 
 impl TryFrom<bindings::BatchProof> for BatchProof {
-    type Error = SerializationError;
+    type Error = CommitmentError;
 
     fn try_from(p: bindings::BatchProof) -> Result<Self, Self::Error> {
         Ok(Self {
@@ -120,3 +254,67 @@ impl From<BatchProof> for bindings::BatchProof {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U256;
+
+    fn proof(block: u64, old_state: u64, new_state: u64) -> Proof {
+        Proof {
+            block: u256_to_commitment(U256::from(block)).unwrap(),
+            old_state: u256_to_commitment(U256::from(old_state)).unwrap(),
+            new_state: u256_to_commitment(U256::from(new_state)).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_encoded_proof_round_trip() {
+        let proof = proof(1, 2, 3);
+
+        let raw = EncodedProof::encode(&proof, false);
+        assert_eq!(raw.format(), Some(ProofFormat::Raw));
+        assert_eq!(raw.decode().unwrap(), proof);
+
+        // Commitments to small numbers are mostly zeros, so compressing the proof pays off.
+        let compressed = EncodedProof::encode(&proof, true);
+        assert_eq!(compressed.format(), Some(ProofFormat::Zstd));
+        assert!(compressed.len() < raw.len());
+        assert_eq!(compressed.decode().unwrap(), proof);
+
+        // Every node publishes the same bytes for the same proof.
+        assert_eq!(EncodedProof::encode(&proof, true), compressed);
+        assert_eq!(EncodedProof::encode(&proof, false), raw);
+
+        // The encoding survives the API's JSON.
+        let json = serde_json::to_string(&compressed).unwrap();
+        assert_eq!(
+            serde_json::from_str::<EncodedProof>(&json).unwrap(),
+            compressed
+        );
+    }
+
+    #[test]
+    fn test_encoded_proof_errors() {
+        let unknown = EncodedProof(vec![7, 0, 0].into());
+        assert_eq!(unknown.format(), None);
+        assert!(matches!(
+            unknown.decode(),
+            Err(ProofError::UnknownFormat { format: 7 })
+        ));
+        assert!(matches!(
+            EncodedProof(Bytes::new()).decode(),
+            Err(ProofError::EmptyProof)
+        ));
+
+        // A proof cut short does not decode in either format.
+        for compress in [false, true] {
+            let encoded = EncodedProof::encode(&proof(1, 2, 3), compress);
+            let truncated = EncodedProof(encoded.0[..encoded.len() - 4].to_vec().into());
+            assert!(
+                matches!(truncated.decode(), Err(ProofError::MalformedProof { .. })),
+                "{compress}"
+            );
+        }
+    }
+}
@@ -0,0 +1,412 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Deciding where a restarted executor picks up.
+//!
+//! After a crash, three records of the executor's progress may disagree: the height of the state
+//! it was restored with, the [`Cursor`] it left, and the number of blocks the rollup contract has
+//! verified. [`reconcile`] compares them before the executor starts, and either decides how to
+//! bring them back in line, each [`Decision`] saying what it does and why, or refuses to start
+//! when only the operator can tell which record to keep.
+//!
+//! The contract is the authority on what has been proven, and the state on what has been executed.
+//! Blocks the contract verified beyond the state are executed again; blocks the state executed
+//! beyond the contract are proven again, from the proofs the state kept. The cursor only saves
+//! scanning the L1 from its first block, so one which cannot be trusted is set aside, unless it is
+//! ahead of the state, which means the state it was written with has been lost.
+
+use ethers::types::Address;
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+
+use crate::cursor::Cursor;
+use crate::error::ExecutorError;
+
+/// The cursor left by a previous run, as far as it could be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SavedCursor {
+    /// No cursor is configured, or none has been written yet.
+    None,
+    /// The cursor at `path` could not be read, such as after a torn write.
+    Unreadable {
+        path: PathBuf,
+        reason: String,
+    },
+    Found {
+        path: PathBuf,
+        cursor: Cursor,
+    },
+}
+
+/// What the executor knows of its progress when it starts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StartupRecords {
+    /// Number of blocks the state has executed.
+    pub state_height: u64,
+    pub cursor: SavedCursor,
+    /// Address of the rollup contract.
+    pub contract: Address,
+    /// Number of blocks the rollup contract has verified.
+    pub verified_height: u64,
+    /// The lowest height between the verified height and the state's height whose proof the
+    /// state no longer has, if any.
+    pub missing_proof: Option<u64>,
+}
+
+/// Something [`reconcile`] decided.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// There is no cursor, so `NewBlocks` events are read from the first L1 block.
+    NoCursor,
+    /// The cursor could not be read, so `NewBlocks` events are read from the first L1 block.
+    UnreadableCursor { path: PathBuf, reason: String },
+    /// The cursor is behind the state, because writing it failed, so it is set aside and
+    /// `NewBlocks` events are read from the first L1 block.
+    CursorBehindState {
+        path: PathBuf,
+        cursor_height: u64,
+        state_height: u64,
+    },
+    /// The cursor records proofs the contract has not verified, as after an L1 reorg. The
+    /// contract is believed, and the blocks are proven again.
+    CursorAheadOfContract {
+        path: PathBuf,
+        cursor_submitted: u64,
+        verified_height: u64,
+    },
+    /// The contract has verified blocks `from..to`, which the state has not executed. They are
+    /// executed again, but not proven.
+    ReplayForward { from: u64, to: u64 },
+    /// The state has executed blocks `from..to`, which the contract has not verified. The proofs
+    /// the state kept for them are sent again.
+    ResubmitProofs { from: u64, to: u64 },
+    /// The state, the cursor, if any, and the contract agree on `height`.
+    InSync { height: u64 },
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCursor => write!(
+                f,
+                "no executor cursor, scanning L1 for HotShot blocks from its first block"
+            ),
+            Self::UnreadableCursor { path, reason } => write!(
+                f,
+                "executor cursor {path:?} cannot be read ({reason}), scanning L1 for HotShot \
+                blocks from its first block"
+            ),
+            Self::CursorBehindState {
+                path,
+                cursor_height,
+                state_height,
+            } => write!(
+                f,
+                "executor cursor {path:?} records {cursor_height} executed blocks but the state \
+                has {state_height}, so a write of the cursor failed; ignoring it and scanning L1 \
+                for HotShot blocks from its first block"
+            ),
+            Self::CursorAheadOfContract {
+                path,
+                cursor_submitted,
+                verified_height,
+            } => write!(
+                f,
+                "executor cursor {path:?} records proofs of {cursor_submitted} blocks as mined, \
+                but the rollup contract has verified {verified_height}, as after an L1 reorg; \
+                proving the blocks from {verified_height} again"
+            ),
+            Self::ReplayForward { from, to } => write!(
+                f,
+                "the rollup contract has verified blocks {from}-{} which the state has not \
+                executed; executing them again without proving them",
+                to - 1
+            ),
+            Self::ResubmitProofs { from, to } => write!(
+                f,
+                "the state has executed blocks {from}-{} which the rollup contract has not \
+                verified; sending their stored proofs again",
+                to - 1
+            ),
+            Self::InSync { height } => write!(
+                f,
+                "the state, the executor cursor and the rollup contract agree on height {height}"
+            ),
+        }
+    }
+}
+
+/// Where the executor picks up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Progress to resume from: the state's height as executed, the contract's as submitted, and
+    /// the L1 block of the cursor, or 0 if it was set aside.
+    pub cursor: Cursor,
+    /// What was decided, in the order it was decided.
+    pub decisions: Vec<Decision>,
+}
+
+/// Decide where the executor picks up, given the records of its progress, or refuse to start if
+/// they cannot all be right.
+pub fn reconcile(records: &StartupRecords) -> Result<Reconciliation, ExecutorError> {
+    let StartupRecords {
+        state_height,
+        verified_height,
+        ..
+    } = *records;
+    let mut decisions = vec![];
+    let mut cursor = Cursor {
+        executed_height: state_height,
+        submitted_height: verified_height,
+        l1_log_block: 0,
+    };
+
+    match &records.cursor {
+        SavedCursor::None => decisions.push(Decision::NoCursor),
+        SavedCursor::Unreadable { path, reason } => decisions.push(Decision::UnreadableCursor {
+            path: path.clone(),
+            reason: reason.clone(),
+        }),
+        SavedCursor::Found {
+            path,
+            cursor: saved,
+        } if saved.executed_height > state_height => {
+            return Err(ExecutorError::CursorAheadOfState {
+                path: path.clone(),
+                cursor_height: saved.executed_height,
+                state_height,
+            });
+        }
+        SavedCursor::Found {
+            path,
+            cursor: saved,
+        } if saved.executed_height < state_height => {
+            decisions.push(Decision::CursorBehindState {
+                path: path.clone(),
+                cursor_height: saved.executed_height,
+                state_height,
+            });
+        }
+        SavedCursor::Found {
+            path,
+            cursor: saved,
+        } => {
+            cursor.l1_log_block = saved.l1_log_block;
+            if saved.submitted_height > verified_height {
+                decisions.push(Decision::CursorAheadOfContract {
+                    path: path.clone(),
+                    cursor_submitted: saved.submitted_height,
+                    verified_height,
+                });
+            }
+        }
+    }
+
+    if verified_height > state_height {
+        decisions.push(Decision::ReplayForward {
+            from: state_height,
+            to: verified_height,
+        });
+    } else if state_height > verified_height {
+        if let Some(missing) = records.missing_proof {
+            return Err(ExecutorError::ProofsUnavailable {
+                state_height,
+                contract: records.contract,
+                verified_height,
+                missing,
+            });
+        }
+        decisions.push(Decision::ResubmitProofs {
+            from: verified_height,
+            to: state_height,
+        });
+    } else if matches!(records.cursor, SavedCursor::Found { .. }) && decisions.is_empty() {
+        decisions.push(Decision::InSync {
+            height: state_height,
+        });
+    }
+    Ok(Reconciliation { cursor, decisions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::checksummed;
+
+    fn found(executed_height: u64, submitted_height: u64) -> SavedCursor {
+        SavedCursor::Found {
+            path: "cursor.json".into(),
+            cursor: Cursor {
+                executed_height,
+                submitted_height,
+                l1_log_block: 42,
+            },
+        }
+    }
+
+    fn records(state_height: u64, cursor: SavedCursor, verified_height: u64) -> StartupRecords {
+        StartupRecords {
+            state_height,
+            cursor,
+            contract: Address::from_low_u64_be(7),
+            verified_height,
+            missing_proof: None,
+        }
+    }
+
+    fn decide(state_height: u64, cursor: SavedCursor, verified_height: u64) -> Reconciliation {
+        reconcile(&records(state_height, cursor, verified_height)).unwrap()
+    }
+
+    #[test]
+    fn test_in_sync() {
+        // A clean restart resumes from the cursor.
+        let plan = decide(10, found(10, 10), 10);
+        assert_eq!(plan.decisions, [Decision::InSync { height: 10 }]);
+        assert_eq!(
+            plan.cursor,
+            Cursor {
+                executed_height: 10,
+                submitted_height: 10,
+                l1_log_block: 42,
+            }
+        );
+
+        // A first start, or one without a cursor, scans the L1 from the start.
+        let plan = decide(0, SavedCursor::None, 0);
+        assert_eq!(plan.decisions, [Decision::NoCursor]);
+        assert_eq!(plan.cursor, Cursor::default());
+    }
+
+    #[test]
+    fn test_state_ahead_of_contract() {
+        // Blocks executed but not yet verified are proven again, whatever the cursor says about
+        // them.
+        for cursor in [found(10, 6), found(10, 10), SavedCursor::None] {
+            let plan = decide(10, cursor.clone(), 6);
+            assert_eq!(
+                plan.decisions.last(),
+                Some(&Decision::ResubmitProofs { from: 6, to: 10 }),
+                "{cursor:?}"
+            );
+            assert_eq!(plan.cursor.submitted_height, 6, "{cursor:?}");
+        }
+        assert_eq!(
+            decide(10, found(10, 8), 6).decisions,
+            [
+                Decision::CursorAheadOfContract {
+                    path: "cursor.json".into(),
+                    cursor_submitted: 8,
+                    verified_height: 6,
+                },
+                Decision::ResubmitProofs { from: 6, to: 10 },
+            ]
+        );
+
+        // Unless the proofs are gone.
+        let err = reconcile(&StartupRecords {
+            missing_proof: Some(7),
+            ..records(10, found(10, 6), 6)
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ExecutorError::ProofsUnavailable {
+                state_height: 10,
+                verified_height: 6,
+                missing: 7,
+                ..
+            }
+        ));
+        assert!(!err.is_retryable());
+        assert!(err
+            .to_string()
+            .contains(&checksummed(&Address::from_low_u64_be(7))));
+    }
+
+    #[test]
+    fn test_contract_ahead_of_state() {
+        // Blocks verified by another submitter, or before the state was snapshotted, are
+        // executed again, resuming the scan from the cursor if it matches the state.
+        let plan = decide(4, found(4, 4), 10);
+        assert_eq!(
+            plan.decisions,
+            [Decision::ReplayForward { from: 4, to: 10 }]
+        );
+        assert_eq!(
+            plan.cursor,
+            Cursor {
+                executed_height: 4,
+                submitted_height: 10,
+                l1_log_block: 42,
+            }
+        );
+
+        // The same with the cursor behind both, which is set aside.
+        let plan = decide(4, found(2, 2), 10);
+        assert_eq!(
+            plan.decisions,
+            [
+                Decision::CursorBehindState {
+                    path: "cursor.json".into(),
+                    cursor_height: 2,
+                    state_height: 4,
+                },
+                Decision::ReplayForward { from: 4, to: 10 },
+            ]
+        );
+        assert_eq!(plan.cursor.l1_log_block, 0);
+    }
+
+    #[test]
+    fn test_cursor_ahead_of_state() {
+        // The state the cursor was written with has been lost, whatever the contract says.
+        for verified_height in [2, 4, 8] {
+            let err = reconcile(&records(4, found(6, 2), verified_height)).unwrap_err();
+            assert!(matches!(
+                err,
+                ExecutorError::CursorAheadOfState {
+                    cursor_height: 6,
+                    state_height: 4,
+                    ..
+                }
+            ));
+            assert!(err.to_string().contains("cursor.json"));
+        }
+    }
+
+    #[test]
+    fn test_cursor_not_trusted() {
+        // A cursor which cannot be read, or which is behind the state, is set aside, and the rest
+        // is reconciled without it.
+        let unreadable = SavedCursor::Unreadable {
+            path: "cursor.json".into(),
+            reason: "checksum does not match contents".into(),
+        };
+        let plan = decide(5, unreadable, 5);
+        assert!(matches!(
+            plan.decisions.as_slice(),
+            [Decision::UnreadableCursor { .. }]
+        ));
+        assert_eq!(plan.cursor.l1_log_block, 0);
+
+        let plan = decide(5, found(3, 3), 5);
+        assert!(matches!(
+            plan.decisions.as_slice(),
+            [Decision::CursorBehindState {
+                cursor_height: 3,
+                state_height: 5,
+                ..
+            }]
+        ));
+
+        // A cursor which agrees with the state but is behind the contract only means another
+        // submitter proved blocks; the contract's height is taken.
+        let plan = decide(8, found(8, 5), 8);
+        assert_eq!(plan.decisions, [Decision::InSync { height: 8 }]);
+        assert_eq!(plan.cursor.submitted_height, 8);
+    }
+}
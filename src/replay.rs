@@ -0,0 +1,197 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Deterministic replay of a single block, for debugging.
+//!
+//! A block whose effects are surprising can be executed again, exactly as the executor executed
+//! it, against the state before it, with [`State::replay_block`]. The state before a block is
+//! rebuilt from the newest snapshot at or below its height by replaying the blocks in between, so
+//! any block can be replayed long after the node has moved past it, as long as a snapshot from
+//! before it is still retained. Blocks are fetched from the query service without checking them
+//! against the HotShot contract, since the executor has already done so.
+//!
+//! Nothing is written: neither the snapshots nor the state of a running node change.
+
+use commit::{Commitment, Committable};
+use contract_bindings::example_rollup::ExampleRollup;
+use ethers::{providers::Middleware, types::Address};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::path::PathBuf;
+use std::sync::Arc;
+use surf_disco::Url;
+
+use crate::backfill::{fetch_block, fetch_l1_inputs, FetchedBlock};
+use crate::error::ExecutorError;
+use crate::executor::block_timestamp;
+use crate::logging::STATE;
+use crate::replicas::QueryReplicas;
+use crate::snapshot::{list_snapshots, load_snapshot};
+use crate::state::{BalanceChange, BlockData, BlockResult, State};
+use crate::utils::create_provider;
+
+/// Where the blocks to replay, and the states before them, come from.
+#[derive(Clone, Debug)]
+pub struct ReplayOptions {
+    /// Directory the executor writes snapshots to.
+    pub snapshot_dir: PathBuf,
+    /// URL of a HotShot query service.
+    pub sequencer_url: Url,
+    pub l1_provider: Url,
+    /// The rollup contract, whose events give the L1 deposits and forced transactions a block
+    /// picks up. Blocks which pick any up cannot be replayed without it.
+    pub rollup_address: Option<Address>,
+}
+
+/// The outcome of replaying a block.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockReplay {
+    /// Height of the snapshot the state before the block was rebuilt from.
+    pub snapshot_height: u64,
+    /// Commitment to the state before the block.
+    pub pre_state: Commitment<State>,
+    /// The summary and receipts of the block. The summary gives the state commitment after it.
+    pub result: BlockResult,
+    /// The balances the block changed.
+    pub balance_changes: Vec<BalanceChange>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ReplayError {
+    #[snafu(display(
+        "No snapshot in {dir:?} holds a state from before block {height}. {}",
+        match oldest {
+            Some(oldest) => format!("The oldest retained snapshot is at height {oldest}."),
+            None => "There are no snapshots.".into(),
+        }
+    ))]
+    NoSnapshot {
+        dir: PathBuf,
+        height: u64,
+        oldest: Option<u64>,
+    },
+    #[snafu(display(
+        "Block {height} credits the deposits of L1 blocks {from}-{through}, which cannot be \
+        fetched without the address of the rollup contract."
+    ))]
+    DepositsUnavailable {
+        height: u64,
+        from: u64,
+        through: u64,
+    },
+    #[snafu(display(
+        "Block {height} applies the transactions enqueued in L1 blocks {from}-{through}, which \
+        cannot be fetched without the address of the rollup contract."
+    ))]
+    ForcedUnavailable {
+        height: u64,
+        from: u64,
+        through: u64,
+    },
+    #[snafu(display("Unable to fetch block {height}: {source}"))]
+    Fetch { height: u64, source: ExecutorError },
+}
+
+/// Replay the block at `height` against the state before it, rebuilt from the newest snapshot
+/// which is not above it.
+pub async fn replay_block(opt: &ReplayOptions, height: u64) -> Result<BlockReplay, ReplayError> {
+    let (snapshot_height, mut state) = load_pre_state(opt, height)?;
+    let hotshot = QueryReplicas::new(&[opt.sequencer_url.clone()]);
+    let rollup_contract = opt
+        .rollup_address
+        .map(|address| ExampleRollup::new(address, Arc::new(create_provider(&opt.l1_provider))));
+
+    for height in snapshot_height..height {
+        let block = fetch_block_data(&hotshot, rollup_contract.as_ref(), &state, height).await?;
+        state = State::replay(&state, &block).0;
+    }
+    let block = fetch_block_data(&hotshot, rollup_contract.as_ref(), &state, height).await?;
+    let (post_state, result) = State::replay(&state, &block);
+    Ok(BlockReplay {
+        snapshot_height,
+        pre_state: state.commit(),
+        balance_changes: state.balance_changes(&post_state),
+        result,
+    })
+}
+
+/// The newest valid snapshot in the snapshot directory at or below `height`, with its height.
+fn load_pre_state(opt: &ReplayOptions, height: u64) -> Result<(u64, State), ReplayError> {
+    let no_snapshot = |oldest| ReplayError::NoSnapshot {
+        dir: opt.snapshot_dir.clone(),
+        height,
+        oldest,
+    };
+    let snapshots = list_snapshots(&opt.snapshot_dir).map_err(|_| no_snapshot(None))?;
+    let oldest = snapshots.first().map(|(height, _)| *height);
+    for (snapshot_height, path) in snapshots.into_iter().rev() {
+        if snapshot_height > height {
+            continue;
+        }
+        match load_snapshot(&path) {
+            Ok(state) if state.block_height() == snapshot_height => {
+                return Ok((snapshot_height, state))
+            }
+            Ok(state) => tracing::warn!(
+                target: STATE,
+                "Skipping snapshot {path:?}, which holds the state at height {}",
+                state.block_height()
+            ),
+            Err(reason) => {
+                tracing::warn!(target: STATE, "Skipping invalid snapshot {path:?}: {reason}")
+            }
+        }
+    }
+    Err(no_snapshot(oldest))
+}
+
+/// Fetch the block at `height`, with the deposits, messages and forced transactions it picks up
+/// when executed on `state`.
+async fn fetch_block_data<M: Middleware>(
+    hotshot: &QueryReplicas,
+    rollup_contract: Option<&ExampleRollup<M>>,
+    state: &State,
+    height: u64,
+) -> Result<BlockData, ReplayError> {
+    let fetch_err = |source| ReplayError::Fetch { height, source };
+    let FetchedBlock {
+        header,
+        namespace_proof,
+        total_payloads,
+        mut inputs,
+    } = fetch_block(hotshot, state, height)
+        .await
+        .map_err(fetch_err)?;
+    match rollup_contract {
+        Some(rollup_contract) => fetch_l1_inputs(rollup_contract, state, &header, &mut inputs)
+            .await
+            .map_err(fetch_err)?,
+        None => {
+            let l1_finalized = header.l1_finalized.as_ref().map(|block| block.number);
+            if let Some(blocks) = state.pending_deposits(header.l1_head, l1_finalized) {
+                return Err(ReplayError::DepositsUnavailable {
+                    height,
+                    from: *blocks.start(),
+                    through: *blocks.end(),
+                });
+            }
+            if let Some(blocks) = state.pending_forced(l1_finalized) {
+                return Err(ReplayError::ForcedUnavailable {
+                    height,
+                    from: *blocks.start(),
+                    through: *blocks.end(),
+                });
+            }
+        }
+    }
+    Ok(BlockData {
+        nmt_root: header.transactions_root,
+        namespace_proof,
+        timestamp: block_timestamp(&header),
+        total_payloads,
+        inputs,
+    })
+}
@@ -0,0 +1,449 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Failover between replicas of the HotShot query service.
+//!
+//! Queries go to the first replica, the primary, while it answers. A replica which fails or does
+//! not answer within [`QUERY_TIMEOUT`] is marked unhealthy and the query goes to the next one,
+//! which is used from then on. While a secondary is in use the primary is probed again every
+//! [`PROBE_INTERVAL`], and used again as soon as it answers.
+//!
+//! A replica which rejects a query with a client error, such as for data it does not have yet, is
+//! up, and the others would reject it too, so the error is returned as it is. Any other error,
+//! including one reaching the replica at all, counts against the replica, and once it has failed
+//! [`BREAKER_THRESHOLD`] times in a row its circuit breaker opens: it is skipped for
+//! [`BREAKER_COOLDOWN`], after which the next query tries it once more. While every breaker is
+//! open, queries fail at once rather than waiting on replicas which are known to be down.
+//!
+//! Failing over does not change what the executor trusts: every header is checked against its
+//! commitment in the HotShot contract, and every namespace against the transaction root of its
+//! header, whichever replica served them.
+
+use async_std::future::timeout;
+use serde::de::DeserializeOwned;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use surf_disco::Url;
+use tide_disco::{error::Error as _, StatusCode};
+
+use crate::logging::EXECUTOR;
+use crate::status::{ReplicaStatus, StatusHandle};
+
+type HotShotClient = surf_disco::Client<hotshot_query_service::Error>;
+
+/// How long to wait for a replica to answer before trying the next one.
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to try the primary again while a secondary is in use.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Number of failures in a row after which a replica is skipped, by default.
+pub const BREAKER_THRESHOLD: u64 = 10;
+
+/// How long a replica is skipped once it has failed too many times in a row, by default.
+pub const BREAKER_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Clients of the availability API of each replica of the query service, in order of preference.
+pub struct QueryReplicas {
+    replicas: Vec<(Url, HotShotClient)>,
+    failover: Mutex<Failover>,
+    status: Option<StatusHandle>,
+    timeout: Duration,
+    breaker_threshold: u64,
+    breaker_cooldown: Duration,
+}
+
+struct Failover {
+    /// The replica queries go to first.
+    active: usize,
+    /// When the primary was last tried while a secondary was active.
+    last_probe: Instant,
+    /// The circuit breaker of each replica.
+    breakers: Vec<Breaker>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Breaker {
+    /// Number of queries the replica has failed since it last answered one.
+    failures: u64,
+    /// Until when the replica is skipped, once it has failed too many times in a row.
+    open_until: Option<Instant>,
+}
+
+impl QueryReplicas {
+    /// Query the sequencer nodes at `urls`, preferring them in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `urls` is empty.
+    pub fn new(urls: &[Url]) -> Self {
+        assert!(!urls.is_empty(), "no query service URLs configured");
+        Self {
+            replicas: urls
+                .iter()
+                .map(|url| {
+                    (
+                        url.clone(),
+                        HotShotClient::new(url.join("availability").unwrap()),
+                    )
+                })
+                .collect(),
+            failover: Mutex::new(Failover {
+                active: 0,
+                last_probe: Instant::now(),
+                breakers: vec![Breaker::default(); urls.len()],
+            }),
+            status: None,
+            timeout: QUERY_TIMEOUT,
+            breaker_threshold: BREAKER_THRESHOLD,
+            breaker_cooldown: BREAKER_COOLDOWN,
+        }
+    }
+
+    /// Report the health of each replica in `status`.
+    pub fn with_status(mut self, status: StatusHandle) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Wait `timeout` for a replica to answer before trying the next one, instead of
+    /// [`QUERY_TIMEOUT`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Skip a replica for `cooldown` once it has failed `threshold` times in a row, instead of
+    /// [`BREAKER_COOLDOWN`] after [`BREAKER_THRESHOLD`] failures.
+    pub fn with_breaker(mut self, threshold: u64, cooldown: Duration) -> Self {
+        self.breaker_threshold = threshold.max(1);
+        self.breaker_cooldown = cooldown;
+        self
+    }
+
+    /// Wait until one of the replicas is up, preferring them in order, for at most `limit` if one
+    /// is given. Returns whether a replica answered.
+    pub async fn connect(&self, limit: Option<Duration>) -> bool {
+        if let Some(status) = &self.status {
+            status.write().await.query_replicas = self
+                .replicas
+                .iter()
+                .map(|(url, _)| ReplicaStatus::new(url.clone()))
+                .collect();
+        }
+        let deadline = limit.map(|limit| Instant::now() + limit);
+        loop {
+            for (i, (url, client)) in self.replicas.iter().enumerate() {
+                let wait = match deadline {
+                    Some(deadline) => {
+                        let left = deadline.saturating_duration_since(Instant::now());
+                        if left.is_zero() {
+                            return false;
+                        }
+                        left.min(self.timeout)
+                    }
+                    None => self.timeout,
+                };
+                let start = Instant::now();
+                if client.connect(Some(wait)).await {
+                    self.succeeded(i, start.elapsed()).await;
+                    return true;
+                }
+                tracing::warn!(target: EXECUTOR, "query service replica {url} is not responding");
+                self.failed(i, "not responding").await;
+            }
+        }
+    }
+
+    /// The URL of the replica queries currently go to first.
+    pub fn active_url(&self) -> Url {
+        let active = self.failover.lock().unwrap().active;
+        self.replicas[active].0.clone()
+    }
+
+    /// Get `path` from the availability API of the first replica which answers.
+    ///
+    /// A replica which rejects the query, such as because it does not have the requested data, is
+    /// up, so the query is not sent to the others. If no replica answers, the error from the last
+    /// one is returned, and if every replica's circuit breaker is open, a `ServiceUnavailable`
+    /// error is returned without sending the query at all.
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<T, hotshot_query_service::Error> {
+        let mut last_err = None;
+        for i in self.order() {
+            let (url, client) = &self.replicas[i];
+            let start = Instant::now();
+            let err = match timeout(self.timeout, client.get::<T>(path).send()).await {
+                Ok(Ok(data)) => {
+                    self.succeeded(i, start.elapsed()).await;
+                    return Ok(data);
+                }
+                Ok(Err(err)) if err.status().is_client_error() => {
+                    self.succeeded(i, start.elapsed()).await;
+                    return Err(err);
+                }
+                Ok(Err(err)) => err,
+                Err(_) => hotshot_query_service::Error::catch_all(
+                    StatusCode::GatewayTimeout,
+                    format!("no response within {:?}", self.timeout),
+                ),
+            };
+            tracing::warn!(
+                target: EXECUTOR,
+                "query service replica {url} failed to serve {path}: {err}"
+            );
+            self.failed(i, &err.to_string()).await;
+            last_err = Some(err);
+        }
+        Err(last_err.unwrap_or_else(|| {
+            hotshot_query_service::Error::catch_all(
+                StatusCode::ServiceUnavailable,
+                format!(
+                    "every replica of the query service has failed {} times in a row",
+                    self.breaker_threshold
+                ),
+            )
+        }))
+    }
+
+    /// The replicas to try, in order: the active one and those after it, then those before it.
+    /// The primary goes first instead when it is due to be probed. Replicas whose breaker is open
+    /// are left out.
+    fn order(&self) -> Vec<usize> {
+        let mut failover = self.failover.lock().unwrap();
+        let n = self.replicas.len();
+        let mut order = (0..n)
+            .map(|i| (failover.active + i) % n)
+            .collect::<Vec<_>>();
+        if failover.active != 0 && failover.last_probe.elapsed() >= PROBE_INTERVAL {
+            failover.last_probe = Instant::now();
+            order.retain(|&i| i != 0);
+            order.insert(0, 0);
+        }
+        let now = Instant::now();
+        order.retain(|&i| {
+            failover.breakers[i]
+                .open_until
+                .map_or(true, |until| now >= until)
+        });
+        order
+    }
+
+    async fn succeeded(&self, i: usize, latency: Duration) {
+        {
+            let mut failover = self.failover.lock().unwrap();
+            if failover.breakers[i].open_until.is_some() {
+                tracing::info!(
+                    target: EXECUTOR,
+                    "query service replica {} is answering again",
+                    self.replicas[i].0
+                );
+            }
+            failover.breakers[i] = Breaker::default();
+            if failover.active != i {
+                tracing::info!(
+                    target: EXECUTOR,
+                    "switching to query service replica {}",
+                    self.replicas[i].0
+                );
+                failover.active = i;
+                failover.last_probe = Instant::now();
+            }
+        }
+        if let Some(status) = &self.status {
+            for (j, replica) in status.write().await.query_replicas.iter_mut().enumerate() {
+                replica.active = i == j;
+                if i == j {
+                    replica.healthy = true;
+                    replica.last_error = None;
+                    replica.breaker_open = false;
+                    replica.record_latency(latency);
+                }
+            }
+        }
+    }
+
+    async fn failed(&self, i: usize, reason: &str) {
+        let tripped = {
+            let mut failover = self.failover.lock().unwrap();
+            let breaker = &mut failover.breakers[i];
+            breaker.failures += 1;
+            if breaker.failures >= self.breaker_threshold {
+                breaker.open_until = Some(Instant::now() + self.breaker_cooldown);
+                true
+            } else {
+                false
+            }
+        };
+        if tripped {
+            tracing::warn!(
+                target: EXECUTOR,
+                "query service replica {} has failed {} times in a row, skipping it for {:?}",
+                self.replicas[i].0,
+                self.breaker_threshold,
+                self.breaker_cooldown
+            );
+        }
+        if let Some(status) = &self.status {
+            if let Some(replica) = status.write().await.query_replicas.get_mut(i) {
+                replica.healthy = false;
+                replica.failures += 1;
+                replica.last_error = Some(reason.into());
+                replica.breaker_open = tripped;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::task::sleep;
+    use portpicker::pick_unused_port;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    /// A stand-in for a query service which serves every block as the number 1.
+    fn stub_replica() -> Url {
+        let port = pick_unused_port().unwrap();
+        let mut server = tide::new();
+        server
+            .at("/availability/healthcheck")
+            .get(|_: tide::Request<()>| async { tide::Body::from_json(&()) });
+        server
+            .at("/availability/block/:height")
+            .get(|_: tide::Request<()>| async { tide::Body::from_json(&1) });
+        async_std::task::spawn(server.listen(format!("0.0.0.0:{port}")));
+        format!("http://localhost:{port}").parse().unwrap()
+    }
+
+    #[async_std::test]
+    async fn test_failover() {
+        let dead: Url = format!("http://localhost:{}", pick_unused_port().unwrap())
+            .parse()
+            .unwrap();
+        let live = stub_replica();
+        let status = StatusHandle::default();
+        let replicas =
+            QueryReplicas::new(&[dead.clone(), live.clone()]).with_status(status.clone());
+        assert!(replicas.connect(None).await);
+        assert_eq!(replicas.active_url(), live);
+
+        assert_eq!(replicas.get::<u64>("block/0").await.unwrap(), 1);
+        let status = status.read().await;
+        assert_eq!(status.query_replicas.len(), 2);
+        assert!(!status.query_replicas[0].healthy);
+        assert!(!status.query_replicas[0].active);
+        assert!(status.query_replicas[1].healthy);
+        assert!(status.query_replicas[1].active);
+    }
+
+    #[async_std::test]
+    async fn test_connect_timeout() {
+        let dead: Url = format!("http://localhost:{}", pick_unused_port().unwrap())
+            .parse()
+            .unwrap();
+        let replicas = QueryReplicas::new(&[dead]).with_timeout(Duration::from_millis(100));
+        let start = Instant::now();
+        assert!(!replicas.connect(Some(Duration::from_millis(500))).await);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    /// A stand-in for a query service which serves every block as the number 1 while `up` is set,
+    /// and fails every query otherwise.
+    fn flapping_replica(up: Arc<AtomicBool>) -> Url {
+        let port = pick_unused_port().unwrap();
+        let mut server = tide::with_state(up);
+        server
+            .at("/availability/healthcheck")
+            .get(|_: tide::Request<_>| async { tide::Body::from_json(&()) });
+        server.at("/availability/block/:height").get(
+            |req: tide::Request<Arc<AtomicBool>>| async move {
+                if req.state().load(Ordering::SeqCst) {
+                    Ok(tide::Body::from_json(&1)?)
+                } else {
+                    Err(tide::Error::from_str(
+                        tide::StatusCode::InternalServerError,
+                        "flapping",
+                    ))
+                }
+            },
+        );
+        server
+            .at("/availability/missing")
+            .get(|_: tide::Request<_>| async {
+                Err::<tide::Body, _>(tide::Error::from_str(tide::StatusCode::NotFound, "missing"))
+            });
+        async_std::task::spawn(server.listen(format!("0.0.0.0:{port}")));
+        format!("http://localhost:{port}").parse().unwrap()
+    }
+
+    #[async_std::test]
+    async fn test_circuit_breaker() {
+        let cooldown = Duration::from_millis(500);
+        let up = Arc::new(AtomicBool::new(true));
+        let url = flapping_replica(up.clone());
+        let status = StatusHandle::default();
+        let replicas = QueryReplicas::new(&[url])
+            .with_status(status.clone())
+            .with_breaker(3, cooldown);
+        assert!(replicas.connect(Some(Duration::from_secs(10))).await);
+        assert_eq!(replicas.get::<u64>("block/0").await.unwrap(), 1);
+        assert!(status.read().await.query_replicas[0]
+            .mean_latency_ms
+            .is_some());
+
+        // A rejected query was answered by a replica which is up, so it counts for nothing.
+        for _ in 0..5 {
+            let err = replicas.get::<u64>("missing").await.unwrap_err();
+            assert_eq!(err.status(), StatusCode::NotFound);
+        }
+        assert!(!status.read().await.query_replicas[0].breaker_open);
+
+        // The replica flaps: failures which are not in a row do not trip its breaker.
+        for _ in 0..3 {
+            up.store(false, Ordering::SeqCst);
+            for _ in 0..2 {
+                replicas.get::<u64>("block/0").await.unwrap_err();
+            }
+            up.store(true, Ordering::SeqCst);
+            assert_eq!(replicas.get::<u64>("block/0").await.unwrap(), 1);
+        }
+        assert_eq!(status.read().await.query_replicas[0].failures, 6);
+        assert!(!status.read().await.query_replicas[0].breaker_open);
+
+        // Three failures in a row trip it, and queries then fail at once, without reaching the
+        // replica, even though it is back.
+        up.store(false, Ordering::SeqCst);
+        for _ in 0..3 {
+            replicas.get::<u64>("block/0").await.unwrap_err();
+        }
+        assert!(status.read().await.query_replicas[0].breaker_open);
+        up.store(true, Ordering::SeqCst);
+        let err = replicas.get::<u64>("block/0").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::ServiceUnavailable);
+        assert_eq!(status.read().await.query_replicas[0].failures, 9);
+
+        // After the cooldown, the next query is sent, and closes the breaker once it is answered.
+        sleep(cooldown).await;
+        assert_eq!(replicas.get::<u64>("block/0").await.unwrap(), 1);
+        assert!(!status.read().await.query_replicas[0].breaker_open);
+
+        // A replica which fails the first query after its cooldown is skipped again at once.
+        up.store(false, Ordering::SeqCst);
+        for _ in 0..3 {
+            replicas.get::<u64>("block/0").await.unwrap_err();
+        }
+        sleep(cooldown).await;
+        let err = replicas.get::<u64>("block/0").await.unwrap_err();
+        assert_ne!(err.status(), StatusCode::ServiceUnavailable);
+        let err = replicas.get::<u64>("block/0").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::ServiceUnavailable);
+        assert_eq!(status.read().await.query_replicas[0].failures, 13);
+    }
+}
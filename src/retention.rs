@@ -0,0 +1,113 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! How long the node keeps the history of executed blocks.
+//!
+//! The state keeps the receipts, summary and proof of each recent block so the API can serve
+//! them. Left alone, these grow with every block, so the executor prunes them in the background
+//! according to a [`RetentionConfig`], and the snapshot writer prunes old snapshots. Nothing at or
+//! above the height confirmed on L1 is ever pruned, nor anything the executor may still need to
+//! prove a block, however small the retention.
+//!
+//! The state records how far each kind of history has been pruned, in [`PrunedHeights`], so the
+//! API can tell a block which has been pruned from one which was never executed, and a backfill
+//! does not fill in what pruning removed.
+
+use serde::{Deserialize, Serialize};
+
+/// Default number of recent blocks whose receipts are kept.
+pub const DEFAULT_RECEIPTS_BLOCKS: u64 = 256;
+
+/// Default number of recent blocks whose summaries and proofs are kept. A proof is about 150 bytes.
+pub const DEFAULT_HISTORY_BLOCKS: u64 = 1 << 16;
+
+/// How much history the node keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Number of recent blocks whose receipts are kept.
+    pub receipts_blocks: u64,
+    /// Number of recent blocks whose summaries are kept. At least the latest block's summary is
+    /// always kept.
+    pub history_blocks: u64,
+    /// Number of recent blocks whose proofs are kept.
+    pub proofs_blocks: u64,
+    /// Number of state snapshots kept on disk.
+    pub snapshots: usize,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            receipts_blocks: DEFAULT_RECEIPTS_BLOCKS,
+            history_blocks: DEFAULT_HISTORY_BLOCKS,
+            proofs_blocks: DEFAULT_HISTORY_BLOCKS,
+            snapshots: 3,
+        }
+    }
+}
+
+/// Heights below which each kind of block history has been pruned.
+///
+/// Snapshots written before history was pruned this way record nothing as pruned, although blocks
+/// older than the limits of the version which wrote them are missing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrunedHeights {
+    pub receipts: u64,
+    pub history: u64,
+    pub proofs: u64,
+}
+
+impl PrunedHeights {
+    /// How far each kind of history of a state at `height` may be pruned under `retention`,
+    /// keeping every block at or above `protected`. Never less than `self`, since what has been
+    /// pruned cannot come back.
+    pub fn advance(&self, retention: &RetentionConfig, height: u64, protected: u64) -> Self {
+        let horizon =
+            |keep: u64, pruned: u64| height.saturating_sub(keep).min(protected).max(pruned);
+        Self {
+            receipts: horizon(retention.receipts_blocks, self.receipts),
+            history: horizon(retention.history_blocks.max(1), self.history),
+            proofs: horizon(retention.proofs_blocks, self.proofs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance() {
+        let retention = RetentionConfig {
+            receipts_blocks: 2,
+            history_blocks: 0,
+            proofs_blocks: 5,
+            snapshots: 1,
+        };
+        let pruned = PrunedHeights::default().advance(&retention, 10, 100);
+        assert_eq!(
+            pruned,
+            PrunedHeights {
+                receipts: 8,
+                history: 9,
+                proofs: 5,
+            }
+        );
+
+        // Nothing at or above the protected height is pruned.
+        assert_eq!(
+            PrunedHeights::default().advance(&retention, 10, 6),
+            PrunedHeights {
+                receipts: 6,
+                history: 6,
+                proofs: 5,
+            }
+        );
+
+        // Pruning never goes backwards, even if the protected height does.
+        assert_eq!(pruned.advance(&retention, 10, 0), pruned);
+    }
+}
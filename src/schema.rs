@@ -0,0 +1,499 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Field-by-field checking of the bodies of API requests.
+//!
+//! The [JSON format](crate::json) is strict, but serde reports a body which does not fit it with a
+//! message written for Rust programmers, and only about the first problem it runs into in its own
+//! order. Before a body is deserialized, [`check_transaction`] walks it against the schema of the
+//! transaction kind it is, and reports the first field which does not fit as a [`FieldError`]:
+//! where the field is, what was expected there, and what was found instead. Fields the kind does
+//! not have are refused, and so is any value which is not of exactly the expected type.
+//!
+//! Clients written against the older format can be given time to move with [`Strictness::Relaxed`],
+//! which drops unknown fields and reads whole JSON numbers as quantities, rather than refusing
+//! them.
+
+use ethers::utils::hex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt::{self, Display, Formatter};
+
+use crate::address::parse_address;
+
+/// Most characters of a string value quoted in a [`FieldError`].
+const MAX_QUOTED_CHARS: usize = 64;
+
+/// How closely a request body must follow the JSON format.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Refuse fields the format does not have, and values of any type but the expected one.
+    #[default]
+    Strict,
+    /// Drop fields the format does not have, and read whole, non-negative JSON numbers where
+    /// quantities are expected, as the format accepted before quantities were strings.
+    Relaxed,
+}
+
+/// The first field of a request body which does not fit the JSON format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// Where the field is, such as `transaction.amount` or `signatures[1]`, or `body` for the body
+    /// itself.
+    pub field: String,
+    /// What the field should be.
+    pub expected: String,
+    /// What the field is.
+    pub got: String,
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.field, self.expected, self.got
+        )
+    }
+}
+
+/// What the value of a field must be.
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    /// A decimal string of a 64-bit number.
+    Quantity,
+    /// A 32-bit number, as a JSON number.
+    Count,
+    /// An EIP-55 checksummed address.
+    Address,
+    /// A 0x-prefixed hex string of 65 bytes.
+    Signature,
+    /// A 0x-prefixed hex string.
+    Bytes,
+    Object(&'static [Field]),
+    Array(&'static Kind),
+}
+
+impl Kind {
+    fn expected(&self) -> String {
+        match self {
+            Self::Quantity => "a decimal string, such as \"100\"".into(),
+            Self::Count => "a whole number, such as 2".into(),
+            Self::Address => "a 0x-prefixed, EIP-55 checksummed address".into(),
+            Self::Signature => "a 0x-prefixed hex string of 65 bytes".into(),
+            Self::Bytes => "a 0x-prefixed hex string".into(),
+            Self::Object(fields) => format!(
+                "an object with the fields {}",
+                fields
+                    .iter()
+                    .map(|field| field.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::Array(kind) => format!("an array, each element {}", kind.expected()),
+        }
+    }
+}
+
+/// Whether a field may be left out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Presence {
+    Required,
+    Optional,
+    /// May also be null.
+    Nullable,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Field {
+    name: &'static str,
+    kind: Kind,
+    presence: Presence,
+}
+
+const fn field(name: &'static str, kind: Kind, presence: Presence) -> Field {
+    Field {
+        name,
+        kind,
+        presence,
+    }
+}
+
+const TRANSACTION: &[Field] = &[
+    field("amount", Kind::Quantity, Presence::Required),
+    field("destination", Kind::Address, Presence::Required),
+    field("nonce", Kind::Quantity, Presence::Required),
+    field("priority", Kind::Quantity, Presence::Optional),
+    field("vm_id", Kind::Quantity, Presence::Optional),
+    field("asset", Kind::Address, Presence::Nullable),
+];
+
+const TRANSFER: &[Field] = &[
+    field("transaction", Kind::Object(TRANSACTION), Presence::Required),
+    field("signature", Kind::Signature, Presence::Required),
+];
+
+const REGISTER_MULTISIG: &[Field] = &[
+    field("signers", Kind::Array(&Kind::Address), Presence::Required),
+    field("threshold", Kind::Count, Presence::Required),
+];
+
+const MULTISIG_TRANSFER: &[Field] = &[
+    field("account", Kind::Address, Presence::Required),
+    field("transaction", Kind::Object(TRANSACTION), Presence::Required),
+    field(
+        "signatures",
+        Kind::Array(&Kind::Signature),
+        Presence::Required,
+    ),
+];
+
+const MESSAGE: &[Field] = &[
+    field("dest_vm", Kind::Quantity, Presence::Required),
+    field("recipient", Kind::Address, Presence::Required),
+    field("nonce", Kind::Quantity, Presence::Required),
+    field("payload", Kind::Bytes, Presence::Required),
+];
+
+const UNSIGNED_TRANSACTION: &[Field] = &[
+    field("sender", Kind::Address, Presence::Required),
+    field("amount", Kind::Quantity, Presence::Required),
+    field("destination", Kind::Address, Presence::Required),
+    field("nonce", Kind::Quantity, Presence::Nullable),
+    field("priority", Kind::Quantity, Presence::Nullable),
+    field("vm_id", Kind::Quantity, Presence::Nullable),
+    field("asset", Kind::Address, Presence::Nullable),
+];
+
+const OUTBOUND_MESSAGE: &[Field] = &[
+    field("message", Kind::Object(MESSAGE), Presence::Required),
+    field("signature", Kind::Signature, Presence::Required),
+];
+
+const SET_MIN_FEE: &[Field] = &[
+    field("value", Kind::Quantity, Presence::Required),
+    field("effective_height", Kind::Quantity, Presence::Required),
+    field("nonce", Kind::Quantity, Presence::Required),
+    field("vm_id", Kind::Quantity, Presence::Required),
+];
+
+const SIGNED_SET_MIN_FEE: &[Field] = &[
+    field("set_min_fee", Kind::Object(SET_MIN_FEE), Presence::Required),
+    field("signature", Kind::Signature, Presence::Required),
+];
+
+/// Check `body` against the JSON format of the transaction kind it is, which is told apart by its
+/// fields as [`RollupTransactionJson`](crate::json::RollupTransactionJson) does.
+///
+/// With [`Strictness::Relaxed`], what the relaxed format tolerates is rewritten in `body` into
+/// what the strict one expects, so that it deserializes.
+pub fn check_transaction(body: &mut Value, strictness: Strictness) -> Result<(), FieldError> {
+    let schema = match body.as_object() {
+        Some(fields) if fields.contains_key("signatures") => MULTISIG_TRANSFER,
+        Some(fields) if fields.contains_key("signers") => REGISTER_MULTISIG,
+        Some(fields) if fields.contains_key("message") => OUTBOUND_MESSAGE,
+        Some(fields) if fields.contains_key("set_min_fee") => SIGNED_SET_MIN_FEE,
+        _ => TRANSFER,
+    };
+    check(body, Kind::Object(schema), "body", strictness)
+}
+
+/// Check `body` against the JSON format of a transfer to be prepared for signing, as
+/// [`check_transaction`] does for a transaction.
+pub fn check_unsigned_transaction(
+    body: &mut Value,
+    strictness: Strictness,
+) -> Result<(), FieldError> {
+    check(body, Kind::Object(UNSIGNED_TRANSACTION), "body", strictness)
+}
+
+fn check(
+    value: &mut Value,
+    kind: Kind,
+    path: &str,
+    strictness: Strictness,
+) -> Result<(), FieldError> {
+    let mismatch = |value: &Value| FieldError {
+        field: path.into(),
+        expected: kind.expected(),
+        got: describe(value),
+    };
+    match kind {
+        Kind::Quantity => {
+            if strictness == Strictness::Relaxed {
+                if let Some(n) = value.as_u64() {
+                    *value = n.to_string().into();
+                }
+            }
+            match value.as_str() {
+                Some(s) if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) => {
+                    if s.parse::<u64>().is_err() {
+                        return Err(FieldError {
+                            expected: format!("a decimal string no greater than {}", u64::MAX),
+                            ..mismatch(value)
+                        });
+                    }
+                }
+                _ => return Err(mismatch(value)),
+            }
+        }
+        Kind::Count => {
+            if !value.as_u64().is_some_and(|n| n <= u32::MAX as u64) {
+                return Err(mismatch(value));
+            }
+        }
+        Kind::Address => {
+            let Some(s) = value.as_str() else {
+                return Err(mismatch(value));
+            };
+            if let Err(err) = parse_address(s) {
+                return Err(FieldError {
+                    got: format!("{} ({err})", describe(value)),
+                    ..mismatch(value)
+                });
+            }
+        }
+        Kind::Signature | Kind::Bytes => {
+            let bytes = value
+                .as_str()
+                .and_then(|s| s.strip_prefix("0x"))
+                .and_then(|digits| hex::decode(digits).ok());
+            match bytes {
+                Some(bytes) if matches!(kind, Kind::Bytes) || bytes.len() == 65 => {}
+                _ => return Err(mismatch(value)),
+            }
+        }
+        Kind::Object(fields) => match value {
+            Value::Object(object) => check_fields(object, fields, path, strictness)?,
+            _ => return Err(mismatch(value)),
+        },
+        Kind::Array(element) => match value {
+            Value::Array(elements) => {
+                for (i, value) in elements.iter_mut().enumerate() {
+                    check(value, *element, &format!("{path}[{i}]"), strictness)?;
+                }
+            }
+            _ => return Err(mismatch(value)),
+        },
+    }
+    Ok(())
+}
+
+fn check_fields(
+    object: &mut Map<String, Value>,
+    fields: &[Field],
+    path: &str,
+    strictness: Strictness,
+) -> Result<(), FieldError> {
+    // Fields of the body itself are named on their own, those of nested objects after the object.
+    let field_path = |name: &str| match path {
+        "body" => name.to_string(),
+        _ => format!("{path}.{name}"),
+    };
+    let unknown = object
+        .keys()
+        .filter(|name| !fields.iter().any(|field| field.name == *name))
+        .cloned()
+        .collect::<Vec<_>>();
+    for name in unknown {
+        if strictness == Strictness::Relaxed {
+            object.remove(&name);
+            continue;
+        }
+        return Err(FieldError {
+            field: field_path(&name),
+            expected: format!(
+                "no such field; {} has only {}",
+                path,
+                fields
+                    .iter()
+                    .map(|field| field.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            got: describe(&object[&name]),
+        });
+    }
+    for field in fields {
+        match object.get_mut(field.name) {
+            None if field.presence == Presence::Required => {
+                return Err(FieldError {
+                    field: field_path(field.name),
+                    expected: field.kind.expected(),
+                    got: "nothing, the field is missing".into(),
+                });
+            }
+            None => {}
+            Some(Value::Null) if field.presence == Presence::Nullable => {}
+            Some(value) => check(value, field.kind, &field_path(field.name), strictness)?,
+        }
+    }
+    Ok(())
+}
+
+/// A description of `value` for a [`FieldError`].
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".into(),
+        Value::Bool(b) => format!("the boolean {b}"),
+        Value::Number(n) => format!("the number {n}"),
+        Value::String(s) if s.chars().count() > MAX_QUOTED_CHARS => {
+            let start = s.chars().take(MAX_QUOTED_CHARS).collect::<String>();
+            format!("the string {start:?}...")
+        }
+        Value::String(s) => format!("the string {s:?}"),
+        Value::Array(elements) => format!("an array of {} elements", elements.len()),
+        Value::Object(_) => "an object".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::{RollupTransactionJson, UnsignedTransactionJson};
+    use serde_json::json;
+
+    const SIGNED_TRANSACTION: &str = include_str!("../fixtures/json/signed_transaction.json");
+
+    fn transfer() -> Value {
+        serde_json::from_str(SIGNED_TRANSACTION).unwrap()
+    }
+
+    fn field_error(mut body: Value) -> FieldError {
+        check_transaction(&mut body, Strictness::Strict).unwrap_err()
+    }
+
+    #[test]
+    fn test_well_formed() {
+        let mut body = transfer();
+        check_transaction(&mut body, Strictness::Strict).unwrap();
+        assert_eq!(body, transfer());
+        serde_json::from_value::<RollupTransactionJson>(body).unwrap();
+
+        let mut body = json!({
+            "signers": ["0x885EE92EebdA03540066a25A57cC625BbEE15d5A"],
+            "threshold": 1,
+        });
+        check_transaction(&mut body, Strictness::Strict).unwrap();
+    }
+
+    #[test]
+    fn test_unsigned_transaction() {
+        let mut body = json!({
+            "sender": "0xf23694f9c6D4837fc596c4Eb7c3c3d8a8BaE69Ca",
+            "amount": "100",
+            "destination": "0x885EE92EebdA03540066a25A57cC625BbEE15d5A",
+            "nonce": null,
+        });
+        check_unsigned_transaction(&mut body, Strictness::Strict).unwrap();
+        serde_json::from_value::<UnsignedTransactionJson>(body.clone()).unwrap();
+
+        body["amount"] = json!(100);
+        let err = check_unsigned_transaction(&mut body.clone(), Strictness::Strict).unwrap_err();
+        assert_eq!(err.field, "amount");
+        check_unsigned_transaction(&mut body, Strictness::Relaxed).unwrap();
+        assert_eq!(body["amount"], "100");
+
+        let mut body = json!({ "amount": "100" });
+        let err = check_unsigned_transaction(&mut body, Strictness::Strict).unwrap_err();
+        assert_eq!(err.field, "sender");
+    }
+
+    #[test]
+    fn test_malformed_bodies() {
+        // A fractional amount.
+        let mut body = transfer();
+        body["transaction"]["amount"] = json!(1.5);
+        assert_eq!(
+            field_error(body),
+            FieldError {
+                field: "transaction.amount".into(),
+                expected: "a decimal string, such as \"100\"".into(),
+                got: "the number 1.5".into(),
+            }
+        );
+
+        // An unknown field, at the top level and nested.
+        let mut body = transfer();
+        body["fee"] = json!("1");
+        let err = field_error(body);
+        assert_eq!(err.field, "fee");
+        assert!(err.expected.contains("transaction, signature"), "{err}");
+        assert_eq!(err.got, "the string \"1\"");
+        let mut body = transfer();
+        body["transaction"]["memo"] = json!("hi");
+        assert_eq!(field_error(body).field, "transaction.memo");
+
+        // A nonce as a number, or as a string which is not decimal.
+        let mut body = transfer();
+        body["transaction"]["nonce"] = json!(1);
+        let err = field_error(body);
+        assert_eq!(err.field, "transaction.nonce");
+        assert_eq!(err.got, "the number 1");
+        let mut body = transfer();
+        body["transaction"]["nonce"] = json!("0x01");
+        assert_eq!(field_error(body).got, "the string \"0x01\"");
+        let mut body = transfer();
+        body["transaction"]["amount"] = json!("18446744073709551616");
+        assert!(field_error(body).expected.contains("no greater than"));
+
+        // A signature without its prefix, or of the wrong length.
+        let mut body = transfer();
+        let signature = body["signature"].as_str().unwrap().to_string();
+        body["signature"] = signature[2..].into();
+        assert_eq!(
+            field_error(body.clone()).expected,
+            "a 0x-prefixed hex string of 65 bytes"
+        );
+        body["signature"] = signature[..signature.len() - 2].into();
+        assert_eq!(field_error(body).field, "signature");
+
+        // A missing field, and an address whose checksum does not match.
+        let mut body = transfer();
+        body["transaction"]
+            .as_object_mut()
+            .unwrap()
+            .remove("destination");
+        let err = field_error(body);
+        assert_eq!(err.field, "transaction.destination");
+        assert_eq!(err.got, "nothing, the field is missing");
+        let mut body = transfer();
+        body["transaction"]["destination"] = json!("0x885ee92EebdA03540066a25A57cC625BbEE15d5A");
+        assert!(field_error(body).got.contains("checksum"));
+
+        // Elements of arrays are named by index, and the body must be an object.
+        let body = json!({
+            "signers": ["0x885EE92EebdA03540066a25A57cC625BbEE15d5A", 7],
+            "threshold": "2",
+        });
+        let err = field_error(body);
+        assert_eq!(err.field, "signers[1]");
+        assert_eq!(err.got, "the number 7");
+        let err = field_error(json!(["not", "an", "object"]));
+        assert_eq!(err.field, "body");
+        assert_eq!(err.got, "an array of 3 elements");
+    }
+
+    #[test]
+    fn test_relaxed() {
+        // Numbers as quantities and unknown fields are tolerated, and rewritten so the body
+        // deserializes.
+        let mut body = transfer();
+        body["transaction"]["amount"] = json!(100);
+        body["transaction"]["nonce"] = json!(1);
+        body["fee"] = json!(3);
+        check_transaction(&mut body, Strictness::Relaxed).unwrap();
+        assert_eq!(body, transfer());
+
+        // But a fractional or negative number is still not a quantity.
+        for amount in [json!(1.5), json!(-1)] {
+            let mut body = transfer();
+            body["transaction"]["amount"] = amount;
+            let err = check_transaction(&mut body, Strictness::Relaxed).unwrap_err();
+            assert_eq!(err.field, "transaction.amount");
+        }
+    }
+}
@@ -5,8 +5,17 @@
 // along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
 
 use clap::ValueEnum;
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::Address,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+use crate::state::Amount;
+
 pub const INITIAL_BALANCE: u64 = 9999;
 
 #[derive(ValueEnum, Clone, Copy, Debug, EnumIter)]
@@ -16,3 +25,16 @@ pub enum SeedIdentity {
     Alice = 1,
     Charlie = 2,
 }
+
+impl SeedIdentity {
+    pub fn wallet(&self) -> LocalWallet {
+        LocalWallet::new(&mut ChaChaRng::seed_from_u64(*self as u64))
+    }
+}
+
+/// The balances the demo rollup starts with: [`INITIAL_BALANCE`] for each seed identity.
+pub fn initial_balances() -> Vec<(Address, Amount)> {
+    SeedIdentity::iter()
+        .map(|identity| (identity.wallet().address(), INITIAL_BALANCE))
+        .collect()
+}
@@ -0,0 +1,36 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Coordinated shutdown of the node when it is asked to stop.
+//!
+//! The parts of the node which must stop cleanly, the executor and the API, each hold a receiver of
+//! the same channel, such as [`ExecutorOptions::shutdown`](crate::executor::ExecutorOptions). The
+//! node shuts down once the channel is closed, or a message is sent on it; whichever part receives
+//! the message closes the channel, so the others see it too.
+
+use async_std::channel::Sender;
+use futures::StreamExt;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook_async_std::Signals;
+use std::io;
+
+use crate::logging::NODE;
+
+/// Close `stop` once the process receives SIGINT or SIGTERM.
+///
+/// A second signal exits the process immediately, in case the shutdown it started is stuck.
+pub async fn close_on_signal(stop: Sender<()>) -> io::Result<()> {
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    if let Some(signal) = signals.next().await {
+        tracing::info!(target: NODE, "received signal {signal}, shutting down");
+        stop.close();
+    }
+    if let Some(signal) = signals.next().await {
+        tracing::warn!(target: NODE, "received signal {signal} while shutting down, exiting now");
+        std::process::exit(128 + signal);
+    }
+    Ok(())
+}
@@ -0,0 +1,290 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Periodic snapshots of the rollup state.
+//!
+//! Without snapshots, a restarted node has to replay every block since genesis. The executor
+//! writes a snapshot every [`SnapshotOptions::interval`] blocks, and on startup the node loads the
+//! newest valid snapshot and only replays the blocks after it.
+//!
+//! Each snapshot is a separate file named after the height of the state it holds, stored with the
+//! state's commitment so that a damaged file is detected and skipped in favor of an older one, and
+//! with the build of the node that wrote it, so that loading it with another version is flagged.
+//! Snapshots in the format of an older version are upgraded by the [migrations](crate::migrations)
+//! as they are loaded.
+//!
+//! API nodes in other processes read the snapshots while the executor writes them, so a snapshot
+//! only appears under its name once it is complete: it is written and synced to a temporary file
+//! of its own, which is then renamed into place. A snapshot is never changed once it is in place,
+//! so a reader sees either the whole of a block's state or nothing of it.
+
+use async_std::task::spawn_blocking;
+use commit::{Commitment, Committable};
+use sequencer::Vm;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::build_info::BuildInfo;
+use crate::logging::STATE;
+use crate::migrations::{upgrade, Format, SNAPSHOT_FORMAT_VERSION};
+use crate::state::State;
+use crate::RollupVM;
+
+/// When and where the executor writes snapshots.
+#[derive(Clone, Debug)]
+pub struct SnapshotOptions {
+    pub dir: PathBuf,
+    /// Number of blocks executed between snapshots.
+    pub interval: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    format_version: u32,
+    commitment: Commitment<State>,
+    state: State,
+    /// The build which wrote the snapshot. Absent from snapshots written before it was recorded.
+    #[serde(default)]
+    build: Option<BuildInfo>,
+}
+
+/// Write a snapshot of `state` to `dir` and prune all but the newest `retain` snapshots.
+///
+/// Returns the path of the new snapshot.
+pub fn write_snapshot(dir: &Path, state: &State, retain: usize) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(snapshot_name(state.block_height()));
+    store_snapshot(&path, state)?;
+
+    let snapshots = list_snapshots(dir)?;
+    for (_, old) in &snapshots[..snapshots.len().saturating_sub(retain)] {
+        match fs::remove_file(old) {
+            Ok(()) => {}
+            // Another write may have pruned it first.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(path)
+}
+
+/// Number of temporary files this process has written snapshots to, which tells them apart.
+static TMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Write a snapshot of `state` to `path`, replacing any file there, in the format read by
+/// [`load_snapshot`].
+///
+/// The snapshot is only renamed to `path` once it is completely written and synced to disk, so
+/// whoever reads `path` sees either the old file or the whole new one.
+pub fn store_snapshot(path: &Path, state: &State) -> io::Result<()> {
+    // Two snapshots of the same height, such as a periodic one and the one written on shutdown,
+    // may be written at once, so each gets a temporary file of its own.
+    let tmp = path.with_extension(format!(
+        "{}.{}.tmp",
+        std::process::id(),
+        TMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let file = SnapshotFile {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        commitment: state.commit(),
+        state: state.clone(),
+        build: Some(BuildInfo::current()),
+    };
+    let result = (|| -> io::Result<()> {
+        let mut writer = io::BufWriter::new(fs::File::create(&tmp)?);
+        serde_json::to_writer(&mut writer, &file)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        fs::remove_file(&tmp).ok();
+    }
+    result?;
+    // Make the rename itself durable. Not every platform can open a directory to sync it.
+    if let Some(dir) = path.parent().and_then(|dir| fs::File::open(dir).ok()) {
+        dir.sync_all().ok();
+    }
+    Ok(())
+}
+
+/// Write a snapshot of `state` on a background thread, so block execution is not held up, and
+/// prune all but the newest `retain` snapshots.
+pub fn spawn_snapshot(opt: &SnapshotOptions, retain: usize, state: State) {
+    let opt = opt.clone();
+    spawn_blocking(move || match write_snapshot(&opt.dir, &state, retain) {
+        Ok(path) => tracing::info!(target: STATE, "wrote state snapshot {path:?}"),
+        Err(err) => tracing::warn!(
+            target: STATE,
+            "Unable to write snapshot of state at height {}: {err}",
+            state.block_height()
+        ),
+    });
+}
+
+/// Load the newest valid snapshot of the state of `vm` in `dir`, if there is one.
+///
+/// Snapshots which cannot be read, do not match their commitment, or belong to a different VM are
+/// skipped with a warning.
+pub fn load_latest_snapshot(dir: &Path, vm: &RollupVM) -> Option<State> {
+    let snapshots = match list_snapshots(dir) {
+        Ok(snapshots) => snapshots,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+        Err(err) => {
+            tracing::warn!(target: STATE, "Unable to list snapshots in {dir:?}: {err}");
+            return None;
+        }
+    };
+    for (_, path) in snapshots.into_iter().rev() {
+        match read_snapshot(&path, vm) {
+            Ok(state) => {
+                tracing::info!(target: STATE, "loaded state snapshot {path:?}");
+                return Some(state);
+            }
+            Err(reason) => {
+                tracing::warn!(target: STATE, "Skipping invalid snapshot {path:?}: {reason}")
+            }
+        }
+    }
+    None
+}
+
+/// Load the snapshot at `path`, checking it against its commitment.
+///
+/// A snapshot written by a different version of the node is still loaded, with a warning, unless
+/// its format is newer than this version supports.
+pub fn load_snapshot(path: &Path) -> Result<State, String> {
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+    let file = serde_json::from_slice(&bytes).map_err(|err| err.to_string())?;
+    let file = upgrade(Format::Snapshot, file).map_err(|err| err.to_string())?;
+    let file: SnapshotFile = serde_json::from_value(file).map_err(|err| err.to_string())?;
+    if file.state.commit() != file.commitment {
+        return Err("state does not match its commitment".into());
+    }
+    if let Some(warning) = version_mismatch(file.build.as_ref(), &BuildInfo::current()) {
+        tracing::warn!(target: STATE, "snapshot {path:?} {warning}");
+    }
+    Ok(file.state)
+}
+
+/// Describe how the build which wrote a snapshot differs in version from `current`, if it does.
+fn version_mismatch(written_by: Option<&BuildInfo>, current: &BuildInfo) -> Option<String> {
+    match written_by {
+        Some(build) if build.version == current.version => None,
+        Some(build) => Some(format!(
+            "was written by version {} (commit {}), loading with version {}",
+            build.version, build.git_commit, current.version
+        )),
+        None => Some(format!(
+            "was written by an unknown version, loading with version {}",
+            current.version
+        )),
+    }
+}
+
+/// Load the snapshot at `path`, checking that it belongs to `vm`.
+pub(crate) fn read_snapshot(path: &Path, vm: &RollupVM) -> Result<State, String> {
+    let state = load_snapshot(path)?;
+    if state.vm.id() != vm.id() {
+        return Err(format!("snapshot belongs to VM {:?}", state.vm.id()));
+    }
+    Ok(state)
+}
+
+/// Snapshots in `dir`, sorted by height.
+pub(crate) fn list_snapshots(dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut snapshots = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let height = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("state-"))
+            .and_then(|name| name.strip_suffix(".json"))
+            .and_then(|height| height.parse().ok());
+        if let Some(height) = height {
+            snapshots.push((height, path));
+        }
+    }
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// Name of the file in a snapshot directory holding the snapshot of the state at `height`.
+pub(crate) fn snapshot_name(height: u64) -> String {
+    format!("state-{height}.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_skip_invalid_snapshots() {
+        let dir = TempDir::new().unwrap();
+        let vm = RollupVM::new(1.into());
+        let state = State::from_initial_balances([(Default::default(), 100)], vm);
+        write_snapshot(dir.path(), &state, 3).unwrap();
+
+        // A newer snapshot which is damaged is skipped in favor of the older one.
+        fs::write(dir.path().join(snapshot_name(5)), "{").unwrap();
+        let loaded = load_latest_snapshot(dir.path(), &vm).unwrap();
+        assert_eq!(loaded.commit(), state.commit());
+
+        // Snapshots of other VMs are ignored.
+        assert!(load_latest_snapshot(dir.path(), &RollupVM::new(2.into())).is_none());
+
+        // So is a missing directory.
+        assert!(load_latest_snapshot(&dir.path().join("missing"), &vm).is_none());
+    }
+
+    #[test]
+    fn test_store_snapshot_replaces_whole_file() {
+        let dir = TempDir::new().unwrap();
+        let vm = RollupVM::new(1.into());
+        let state = State::from_initial_balances([(Default::default(), 100)], vm);
+        let path = dir.path().join(snapshot_name(0));
+        fs::write(&path, "{").unwrap();
+
+        // Writers of the same snapshot do not share a temporary file, and leave none behind.
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| store_snapshot(&path, &state).unwrap());
+            }
+        });
+        assert_eq!(load_snapshot(&path).unwrap().commit(), state.commit());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_version() {
+        let dir = TempDir::new().unwrap();
+        let vm = RollupVM::new(1.into());
+        let state = State::from_initial_balances([(Default::default(), 100)], vm);
+        let path = write_snapshot(dir.path(), &state, 3).unwrap();
+
+        let current = BuildInfo::current();
+        let file: SnapshotFile = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(file.build.as_ref(), Some(&current));
+        assert_eq!(version_mismatch(file.build.as_ref(), &current), None);
+
+        // Snapshots from other versions, or from before the version was recorded, are flagged but
+        // still load.
+        let other = BuildInfo {
+            version: "0.0.0".into(),
+            ..current.clone()
+        };
+        assert!(version_mismatch(Some(&other), &current).is_some());
+        assert!(version_mismatch(None, &current).is_some());
+        let old = serde_json::json!({ "commitment": file.commitment, "state": file.state });
+        fs::write(&path, old.to_string()).unwrap();
+        assert_eq!(load_snapshot(&path).unwrap().commit(), state.commit());
+    }
+}
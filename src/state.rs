@@ -5,19 +5,154 @@
 // along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
 
 use commit::{Commitment, Committable};
-use ethers::abi::Address;
+use ethers::{
+    abi::Address,
+    types::{H256, U256},
+};
 use jf_primitives::merkle_tree::namespaced_merkle_tree::NamespaceProof;
 use sequencer::{NMTRoot, NamespaceProofType, Vm};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::{Range, RangeInclusive};
+use std::sync::{Arc, OnceLock};
 
+use crate::balance_history::BalanceHistory;
+use crate::deposit::L1Deposits;
 use crate::error::RollupError;
+use crate::fee::{SetMinFee, SignedSetMinFee};
+use crate::forced::L1Queue;
+use crate::logging::{BRIDGE, STATE};
+use crate::message::{outbox, outbox_commitment, CrossVmMessage, Message, OutboundMessage};
+use crate::multisig::{MultisigConfig, MultisigTransfer, RegisterMultisig};
 use crate::prover::Proof;
-use crate::transaction::SignedTransaction;
+use crate::retention::{PrunedHeights, RetentionConfig};
+use crate::transaction::{RollupTransaction, SignedTransaction, Transaction};
+use crate::watchdog::{Phase, PhaseTimer};
 use crate::RollupVM;
 
-pub type Amount = u64;
-pub type Nonce = u64;
+pub use crate::transaction::{Amount, Nonce, Weight};
+
+/// Number of recent blocks whose transaction hashes are remembered.
+const RECENT_BLOCKS: usize = 256;
+
+/// Weight budget of a block under the default [`ChainConfig`]: about 400 transfers.
+pub const DEFAULT_MAX_BLOCK_WEIGHT: Weight = 10_000_000;
+
+/// Parameters of the rollup which every executor must agree on.
+///
+/// The configuration is part of the state, so the state commitment covers it and an executor
+/// configured differently computes different commitments.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainConfig {
+    /// Most weight the transactions of a block may consume.
+    ///
+    /// Transactions are executed in the consensus order until the next one would take the block
+    /// over its budget. That transaction and every one after it are skipped.
+    pub max_block_weight: Weight,
+    /// Assets which may be deposited into the rollup from L1, by the address of their ERC-20 token.
+    /// Ether is the zero address. Deposits of any other asset are ignored.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub assets: BTreeSet<Address>,
+    /// VM IDs of the rollups whose [messages](crate::message) to this rollup are delivered.
+    /// Messages from any other rollup are ignored. Which messages fit in a block of a source is
+    /// found with this rollup's [`max_block_weight`](Self::max_block_weight), so each source must
+    /// have the same one.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub message_sources: BTreeSet<u64>,
+    /// Whether transfers to the zero address, which no key can spend from, are rejected rather
+    /// than burning the amount. Clients which leave the destination unset send to it by mistake.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reject_zero_destination: bool,
+    /// Other addresses transfers to which are rejected, such as that of the rollup contract.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub denied_destinations: BTreeSet<Address>,
+    /// The account which may change the [minimum fee](MinFee), if any may.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_admin: Option<Address>,
+    /// Number of L1 blocks which must be built on the L1 block of a deposit before the deposit is
+    /// credited, counting back from the L1 head a rollup block references.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub deposit_confirmations: u64,
+    /// Whether transactions enqueued on the rollup contract are [forced in](crate::forced), so
+    /// that the sequencer cannot keep a transaction out of the rollup for good.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub forced_inclusion: bool,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            max_block_weight: DEFAULT_MAX_BLOCK_WEIGHT,
+            assets: BTreeSet::new(),
+            message_sources: BTreeSet::new(),
+            reject_zero_destination: false,
+            denied_destinations: BTreeSet::new(),
+            fee_admin: None,
+            deposit_confirmations: 0,
+            forced_inclusion: false,
+        }
+    }
+}
+
+impl ChainConfig {
+    /// Whether a transfer to `address` is rejected.
+    pub fn denies_destination(&self, address: &Address) -> bool {
+        (self.reject_zero_destination && address.is_zero())
+            || self.denied_destinations.contains(address)
+    }
+}
+
+/// The minimum fee transfers must pay as their priority, and the change to it the
+/// [fee admin](ChainConfig::fee_admin) has scheduled, if any, as described under
+/// [`fee`](crate::fee).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MinFee {
+    /// The minimum fee of the blocks executed so far.
+    pub current: Amount,
+    /// A change which takes effect at a later block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<ScheduledMinFee>,
+    /// Nonce of the last change applied, which the next change must exceed by one.
+    pub nonce: Nonce,
+}
+
+impl MinFee {
+    /// The minimum fee in force for the block at `height`: the scheduled value once `height` reaches
+    /// its effective height, the current one before that.
+    pub fn at(&self, height: u64) -> Amount {
+        match self.scheduled {
+            Some(change) if change.effective_height <= height => change.value,
+            _ => self.current,
+        }
+    }
+}
+
+/// A change to the minimum fee which has not taken effect yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledMinFee {
+    pub value: Amount,
+    /// Height of the first block whose transfers must pay `value`.
+    pub effective_height: u64,
+}
+
+/// What the API knows of the history of an account.
+///
+/// This is bookkeeping for the API rather than rollup state, so it is not part of the state
+/// commitment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountActivity {
+    /// Height of the first block which applied a transaction to or from the account, or 0 for an
+    /// account of the initial state.
+    pub first_seen: u64,
+    /// Number of applied transactions to or from the account.
+    pub transactions: u64,
+}
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Account {
@@ -25,25 +160,354 @@ pub struct Account {
     nonce: Nonce,
 }
 
-#[derive(Debug, Clone)]
+/// Where a [`BlockTimestamp`] came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimestampSource {
+    /// The timestamp HotShot assigned to the block.
+    HotShot,
+    /// The time at which the executor fetched the block, because HotShot did not provide one. This
+    /// differs between nodes and should not be relied on by anything that needs consensus.
+    Observed,
+}
+
+/// The time of a block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockTimestamp {
+    /// Seconds since the Unix epoch.
+    pub seconds: u64,
+    pub source: TimestampSource,
+}
+
+/// How much of a HotShot block belonged to this rollup.
+///
+/// The sequencer is shared, so most of a block may be other rollups' transactions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockCounts {
+    /// Transactions in the HotShot block, for every rollup.
+    pub total_payloads: u64,
+    /// Transactions in this rollup's namespace.
+    pub vm_payloads: u64,
+    /// Transactions in this rollup's namespace which decoded as rollup transactions.
+    pub decoded: u64,
+    /// Rollup transactions which were valid and applied to the state.
+    pub applied: u64,
+    /// Rollup transactions which were [duplicates](TransactionOutcome::duplicate). Zero in
+    /// summaries recorded before duplicates were recognized.
+    #[serde(default)]
+    pub duplicates: u64,
+    /// Rollup transactions [forced in](crate::forced) from L1 which decoded. They are executed
+    /// along with those of the namespace, but are not among its payloads.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub forced: u64,
+}
+
+impl std::ops::AddAssign for BlockCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.total_payloads += other.total_payloads;
+        self.vm_payloads += other.vm_payloads;
+        self.decoded += other.decoded;
+        self.applied += other.applied;
+        self.duplicates += other.duplicates;
+        self.forced += other.forced;
+    }
+}
+
+/// Metadata about an executed block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSummary {
+    /// Height of the HotShot block, which is also the height of the rollup block.
+    pub height: u64,
+    /// Time of the block. Never earlier than the time of the previous block.
+    pub timestamp: BlockTimestamp,
+    /// Commitment to the rollup state after executing the block.
+    pub state_commitment: Commitment<State>,
+    pub payloads: BlockCounts,
+    /// Weight consumed by the rollup transactions of the block. Zero in summaries recorded before
+    /// weights were.
+    #[serde(default)]
+    pub weight: Weight,
+    /// [Commitment](crate::message::outbox_commitment) to the messages the block sent to other
+    /// rollups, if it sent any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbox: Option<H256>,
+    /// The L1 transaction whose proof of the block the rollup contract accepted, once it has.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_tx: Option<H256>,
+}
+
+/// The rollup blocks proven by one L1 transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofTransaction {
+    pub l1_tx: H256,
+    /// L1 block in which the rollup contract accepted the proof.
+    pub l1_block: u64,
+    /// Height of the first block proven.
+    pub first_block: u64,
+    /// Number of blocks proven.
+    pub num_blocks: u64,
+}
+
+impl ProofTransaction {
+    /// Heights of the blocks proven.
+    pub fn heights(&self) -> Range<u64> {
+        self.first_block..self.first_block + self.num_blocks
+    }
+}
+
+/// A rollup transaction found in an executed block.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransactionOutcome {
+    pub hash: H256,
+    /// Position of the transaction among the payloads of its block's namespace, as sequenced.
+    /// Transactions are executed in a different order, by priority, so this is not the position
+    /// of the outcome among the block's outcomes. `None` for outcomes recorded before positions
+    /// were.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u64>,
+    /// The L1 block the transaction was enqueued in, for a transaction
+    /// [forced in](crate::forced) from L1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_block: Option<u64>,
+    /// The account the transaction spends from: the signer of a transfer or message, if a signer
+    /// could be recovered from its signature, or the multisig account of a multisig transfer. For
+    /// a fee change, the account which signed it.
+    pub sender: Option<Address>,
+    /// The transfer, or `None` for the registration of a multisig account, a message or a fee
+    /// change.
+    pub transaction: Option<Transaction>,
+    /// The configuration of the multisig account registered, for a registration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultisigConfig>,
+    /// The message to another rollup, for an outbound message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<Message>,
+    /// The change to the minimum fee, for a fee change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_fee: Option<SetMinFee>,
+    /// Weight the transaction consumed from its block's budget: its
+    /// [weight](RollupTransaction::weight) if it was executed, whether or not it was applied, and
+    /// zero if it was skipped because the budget was used up. Transactions forced in from L1 are
+    /// outside the budget, and record zero.
+    #[serde(default)]
+    pub weight: Weight,
+    /// Why the transaction was not applied, if it was not.
+    pub error: Option<String>,
+    /// Whether the transaction was not applied because it is a copy of one already applied, in
+    /// the same block or one of the last few hundred. The sequencer does not deduplicate, so a
+    /// client retrying a submission can have its transaction sequenced twice.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub duplicate: bool,
+}
+
+impl TransactionOutcome {
+    pub fn applied(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// What a block picks up from outside its own namespace, which the executor gathers before
+/// executing it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockInputs {
+    /// The [pending deposits](State::pending_deposits) the block credits.
+    pub deposits: L1Deposits,
+    /// Messages to this rollup sent in the same HotShot block by the rollups of
+    /// [`ChainConfig::message_sources`], in the order of those VM IDs and then in the order they
+    /// were sent.
+    pub messages: Vec<CrossVmMessage>,
+    /// The [pending forced transactions](State::pending_forced) the block applies.
+    pub forced: L1Queue,
+}
+
+/// The effects of executing a block: its summary and every rollup transaction in it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockResult {
+    pub summary: BlockSummary,
+    pub transactions: Vec<TransactionOutcome>,
+}
+
+/// Everything the executor executes a block from, so that [`State::replay_block`] can execute it
+/// again.
+#[derive(Clone, Debug)]
+pub struct BlockData {
+    pub nmt_root: NMTRoot,
+    /// Proof of the rollup's namespace in the block.
+    pub namespace_proof: NamespaceProofType,
+    pub timestamp: BlockTimestamp,
+    /// Number of transactions in the whole HotShot block, for every rollup.
+    pub total_payloads: u64,
+    pub inputs: BlockInputs,
+}
+
+/// How a block changed the balance of an account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceChange {
+    pub address: Address,
+    /// The deposited asset, or `None` for the native token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<Address>,
+    pub before: Amount,
+    pub after: Amount,
+}
+
+/// The amount of each token which should be in the balances of the accounts, as kept up to date by
+/// the transitions which create and destroy tokens, so that the [invariants](crate::invariants)
+/// can check that the balances add up.
+///
+/// Native tokens all exist from genesis, and are only destroyed as tips, which are burned.
+/// Deposited assets are only created by deposits from L1. Nothing mints or withdraws either. The
+/// amounts are 256-bit, since the balances of many accounts may add up to more than an [`Amount`]
+/// holds.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Supply {
+    /// Native tokens at genesis, or in the balances of a snapshot written before the supply was
+    /// kept.
+    pub genesis: U256,
+    /// Native tokens burned as tips.
+    pub burned: U256,
+    /// Amount of each asset credited by deposits, by asset.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub deposited: BTreeMap<Address, U256>,
+}
+
+impl Supply {
+    /// Native tokens which should be in the balances of the accounts.
+    pub fn native(&self) -> U256 {
+        self.genesis.saturating_sub(self.burned)
+    }
+
+    /// Amount of the deposited asset `asset` which should be in the balances of the accounts.
+    pub fn asset(&self, asset: &Address) -> U256 {
+        self.deposited.get(asset).copied().unwrap_or_default()
+    }
+}
+
+/// The state of the rollup.
+///
+/// Snapshots hold the state as it serializes, so adding, removing or changing a serialized field
+/// changes the snapshot format, which needs a [migration](crate::migrations).
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct State {
     // Account state, represented as a BTreeMap so that we can obtain a canonical serialization of the data structure for the state commitment
     // A live rollup would likely represent accounts as a Sparse Merkle Tree instead of a BTreeMap.
     // Rollup clients would then be able to use merkle proofs to authenticate a subset of user balances
     // without knowledge of the entire account state. Such "light clients" are less constrained by bandwidth
     // because they do not need to constantly sync up with a full node.
-    accounts: BTreeMap<Address, Account>,
+    //
+    // Shared between clones, such as the copies handed to block hooks, until one of them changes
+    // an account, so that cloning the state does not copy every account.
+    accounts: Arc<BTreeMap<Address, Account>>,
+    // Configurations of the registered multisig accounts, by account address.
+    multisigs: BTreeMap<Address, MultisigConfig>,
+    // Consensus parameters.
+    chain_config: ChainConfig,
+    // Balances of deposited assets, by asset and then by account, and the last L1 block whose
+    // deposits have been credited.
+    asset_balances: BTreeMap<Address, BTreeMap<Address, Amount>>,
+    deposits_through: u64,
+    // The last L1 block whose transactions enqueued for forced inclusion have been applied.
+    forced_through: u64,
+    // The minimum fee of transfers, and any change to it scheduled.
+    min_fee: MinFee,
+    // Messages delivered from other rollups, by recipient, and the nonce of the last message
+    // delivered from each sender, by source VM.
+    inbox: BTreeMap<Address, Vec<CrossVmMessage>>,
+    inbox_nonces: BTreeMap<u64, BTreeMap<Address, Nonce>>,
+    // Commitment to the messages the latest block sent to other rollups, if it sent any.
+    outbox: Option<H256>,
     nmt_comm: Option<Commitment<NMTRoot>>, // Commitment to the most recent transaction NMT
     prev_state_commitment: Option<Commitment<State>>, // Previous state commitment, used to create a chain linking state committments
     pub(crate) vm: RollupVM,
+    block_height: u64, // Number of blocks executed so far
+    // Hashes of the transactions included in recent blocks, and the height of the including
+    // block. This lets clients find out what happened to their transactions; it is bookkeeping
+    // for the API rather than rollup state, so it is not part of the state commitment.
+    recent_transactions: HashMap<H256, u64>,
+    recent_blocks: VecDeque<Vec<H256>>,
+    // Hashes of the transactions applied in the same recent blocks, and the height of the block
+    // which applied each. Unlike the bookkeeping above, this decides which transactions are
+    // duplicates, so every executor must keep the same set.
+    recent_applied: HashMap<H256, u64>,
+    // The history of each account, by address. Bookkeeping for the API, like the recent
+    // transactions, and shared between clones like the accounts.
+    activity: Arc<BTreeMap<Address, AccountActivity>>,
+    // Checkpoints of the balance of each account, for the API. Shared between clones like the
+    // accounts.
+    balance_history: Arc<BalanceHistory>,
+    // The amount of each token in the accounts above, as transitions account for it. Bookkeeping
+    // for the invariants, which only changes when balances do.
+    supply: Supply,
+    recent_proofs: BTreeMap<u64, Proof>, // Proofs of recent blocks, by block height
+    // Summaries of recent blocks, by block height.
+    recent_summaries: BTreeMap<u64, BlockSummary>,
+    // The L1 transactions which proved the recent blocks, by transaction hash. The summary of each
+    // block gives the transaction which proved it.
+    proof_transactions: HashMap<H256, ProofTransaction>,
+    // The rollup transactions in recent blocks, by block height.
+    recent_receipts: BTreeMap<u64, Vec<TransactionOutcome>>,
+    // How far the proofs, summaries and receipts above have been pruned.
+    pruned: PrunedHeights,
+    // Whether to recover the senders of a block's transactions up front, in parallel, and on how
+    // many threads, zero meaning one per core. Settings of this node rather than part of the state.
+    #[serde(skip)]
+    eager_recovery: bool,
+    #[serde(skip)]
+    recovery_threads: usize,
+    // The commitment to the state, computed the first time it is needed after each change rather
+    // than each time it is needed. Every method which changes a committed field clears it.
+    #[serde(skip)]
+    commitment: OnceLock<Commitment<State>>,
+    // The JSON encoding of each account as it appears in the encoding of all the accounts, kept up
+    // to date as accounts change so that committing to the state only encodes the accounts which
+    // changed. Built for a state restored from a snapshot by the first block it applies. Shared
+    // between clones like the accounts.
+    #[serde(skip)]
+    account_entries: Option<Arc<BTreeMap<Address, String>>>,
+}
+
+/// The entry of an account in the JSON encoding of every account, as committed to.
+fn account_entry(address: &Address, account: &Account) -> String {
+    let address = serde_json::to_string(address).expect("Serialization should not fail");
+    let account = serde_json::to_string(account).expect("Serialization should not fail");
+    format!("{address}:{account}")
+}
+
+/// Encode every account as [`State::compute_commitment`] does, from the entries of each.
+fn join_account_entries(entries: &BTreeMap<Address, String>) -> String {
+    let len = entries.values().map(|entry| entry.len() + 1).sum::<usize>();
+    let mut encoded = String::with_capacity(len + 2);
+    encoded.push('{');
+    for (i, entry) in entries.values().enumerate() {
+        if i > 0 {
+            encoded.push(',');
+        }
+        encoded.push_str(entry);
+    }
+    encoded.push('}');
+    encoded
 }
 
 impl Committable for State {
     fn commit(&self) -> Commitment<State> {
+        *self.commitment.get_or_init(|| match &self.account_entries {
+            Some(entries) => self.commitment_with_accounts(&join_account_entries(entries)),
+            None => self.compute_commitment(),
+        })
+    }
+}
+
+impl State {
+    /// Compute the commitment to the state from scratch, rather than returning the one cached by
+    /// [`commit`](Committable::commit) or building it from the cached encodings of the accounts.
+    pub(crate) fn compute_commitment(&self) -> Commitment<State> {
         let serialized_accounts =
-            serde_json::to_string(&self.accounts).expect("Serialization should not fail");
+            serde_json::to_string(&*self.accounts).expect("Serialization should not fail");
+        self.commitment_with_accounts(&serialized_accounts)
+    }
 
-        commit::RawCommitmentBuilder::new("State Commitment")
+    /// The commitment to the state, given the JSON encoding of its accounts.
+    fn commitment_with_accounts(&self, serialized_accounts: &str) -> Commitment<State> {
+        let builder = commit::RawCommitmentBuilder::new("State Commitment")
             .array_field(
                 "block_hash",
                 &self
@@ -63,8 +527,116 @@ impl Committable for State {
                     .collect::<Vec<_>>(),
             )
             .var_size_field("accounts", serialized_accounts.as_bytes())
-            .u64_field("VM ID", self.vm.id().into())
-            .finalize()
+            .u64_field("VM ID", self.vm.id().into());
+        // Multisig configurations are only committed to once there are any, each field of the
+        // chain configuration once it differs from the default, the minimum fee once it has been
+        // changed, deposits and forced transactions once any have been looked for, and messages
+        // once any have been sent or delivered, so that states from before any of them existed keep
+        // their commitments.
+        let builder = if self.multisigs.is_empty() {
+            builder
+        } else {
+            let serialized_multisigs =
+                serde_json::to_string(&self.multisigs).expect("Serialization should not fail");
+            builder.var_size_field("multisigs", serialized_multisigs.as_bytes())
+        };
+        let builder = if self.chain_config.max_block_weight == DEFAULT_MAX_BLOCK_WEIGHT {
+            builder
+        } else {
+            builder.u64_field("max_block_weight", self.chain_config.max_block_weight)
+        };
+        let builder = if self.chain_config.assets.is_empty() {
+            builder
+        } else {
+            let serialized_assets = serde_json::to_string(&self.chain_config.assets)
+                .expect("Serialization should not fail");
+            builder.var_size_field("assets", serialized_assets.as_bytes())
+        };
+        let builder = if self.chain_config.message_sources.is_empty() {
+            builder
+        } else {
+            let serialized_sources = serde_json::to_string(&self.chain_config.message_sources)
+                .expect("Serialization should not fail");
+            builder.var_size_field("message_sources", serialized_sources.as_bytes())
+        };
+        let builder = if self.chain_config.reject_zero_destination {
+            builder.u64_field("reject_zero_destination", 1)
+        } else {
+            builder
+        };
+        let builder = if self.chain_config.denied_destinations.is_empty() {
+            builder
+        } else {
+            let serialized_denied = serde_json::to_string(&self.chain_config.denied_destinations)
+                .expect("Serialization should not fail");
+            builder.var_size_field("denied_destinations", serialized_denied.as_bytes())
+        };
+        let builder = match &self.chain_config.fee_admin {
+            Some(admin) => builder.var_size_field("fee_admin", admin.as_bytes()),
+            None => builder,
+        };
+        let builder = if self.chain_config.deposit_confirmations == 0 {
+            builder
+        } else {
+            builder.u64_field(
+                "deposit_confirmations",
+                self.chain_config.deposit_confirmations,
+            )
+        };
+        let builder = if self.chain_config.forced_inclusion {
+            builder.u64_field("forced_inclusion", 1)
+        } else {
+            builder
+        };
+        let builder = if self.min_fee == MinFee::default() {
+            builder
+        } else {
+            let serialized_min_fee =
+                serde_json::to_string(&self.min_fee).expect("Serialization should not fail");
+            builder.var_size_field("min_fee", serialized_min_fee.as_bytes())
+        };
+        let builder = if self.asset_balances.is_empty() {
+            builder
+        } else {
+            let serialized_balances =
+                serde_json::to_string(&self.asset_balances).expect("Serialization should not fail");
+            builder.var_size_field("asset_balances", serialized_balances.as_bytes())
+        };
+        let builder = if self.deposits_through == 0 {
+            builder
+        } else {
+            builder.u64_field("deposits_through", self.deposits_through)
+        };
+        let builder = if self.forced_through == 0 {
+            builder
+        } else {
+            builder.u64_field("forced_through", self.forced_through)
+        };
+        let builder = if self.inbox.is_empty() {
+            builder
+        } else {
+            let serialized_inbox = serde_json::to_string(&(&self.inbox, &self.inbox_nonces))
+                .expect("Serialization should not fail");
+            builder.var_size_field("inbox", serialized_inbox.as_bytes())
+        };
+        match &self.outbox {
+            Some(outbox) => builder
+                .var_size_field("outbox", outbox.as_bytes())
+                .finalize(),
+            None => builder.finalize(),
+        }
+    }
+}
+
+impl AsRef<State> for State {
+    fn as_ref(&self) -> &State {
+        self
+    }
+}
+
+impl AsMut<State> for State {
+    fn as_mut(&mut self) -> &mut State {
+        self
     }
 }
 
@@ -75,7 +647,10 @@ impl State {
         vm: RollupVM,
     ) -> Self {
         let mut accounts = BTreeMap::new();
+        let mut activity = BTreeMap::new();
+        let mut balances = vec![];
         for (addr, amount) in initial_balances.into_iter() {
+            balances.push((addr, amount));
             accounts.insert(
                 addr,
                 Account {
@@ -83,35 +658,244 @@ impl State {
                     nonce: 0,
                 },
             );
+            activity.insert(addr, AccountActivity::default());
         }
-        State {
-            accounts,
+        let supply = Supply {
+            genesis: accounts.values().fold(U256::zero(), |total, account| {
+                total + U256::from(account.balance)
+            }),
+            ..Default::default()
+        };
+        let mut state = State {
+            accounts: Arc::new(accounts),
+            multisigs: BTreeMap::new(),
+            chain_config: ChainConfig::default(),
+            asset_balances: BTreeMap::new(),
+            deposits_through: 0,
+            forced_through: 0,
+            min_fee: MinFee::default(),
+            inbox: BTreeMap::new(),
+            inbox_nonces: BTreeMap::new(),
+            outbox: None,
             nmt_comm: None,
             prev_state_commitment: None,
             vm,
+            block_height: 0,
+            recent_transactions: HashMap::new(),
+            recent_applied: HashMap::new(),
+            activity: Arc::new(activity),
+            balance_history: Arc::new(BalanceHistory::new(0, balances)),
+            supply,
+            recent_blocks: VecDeque::new(),
+            recent_proofs: BTreeMap::new(),
+            recent_summaries: BTreeMap::new(),
+            proof_transactions: HashMap::new(),
+            recent_receipts: BTreeMap::new(),
+            pruned: PrunedHeights::default(),
+            eager_recovery: false,
+            recovery_threads: 0,
+            commitment: OnceLock::new(),
+            account_entries: None,
+        };
+        state.encode_accounts();
+        state
+    }
+
+    /// Encode every account for committing incrementally, unless they are already encoded.
+    fn encode_accounts(&mut self) {
+        if self.account_entries.is_none() {
+            let entries = self
+                .accounts
+                .iter()
+                .map(|(address, account)| (*address, account_entry(address, account)))
+                .collect();
+            self.account_entries = Some(Arc::new(entries));
+        }
+    }
+
+    /// Re-encode the entry of the account at `address` after changing it.
+    fn account_changed(&mut self, address: Address) {
+        if let (Some(entries), Some(account)) =
+            (&mut self.account_entries, self.accounts.get(&address))
+        {
+            Arc::make_mut(entries).insert(address, account_entry(&address, account));
         }
     }
 
+    /// Use `config` instead of the default chain configuration. Only meant for a genesis state.
+    pub fn with_chain_config(mut self, config: ChainConfig) -> Self {
+        self.chain_config = config;
+        self.commitment.take();
+        self
+    }
+
+    pub fn chain_config(&self) -> &ChainConfig {
+        &self.chain_config
+    }
+
+    /// The minimum fee, and any change to it scheduled. The minimum fee of the next block is
+    /// `min_fee().at(block_height())`.
+    pub fn min_fee(&self) -> &MinFee {
+        &self.min_fee
+    }
+
     /// If the transaction is valid, transition the state and return the new state with updated balances.
     ///
     /// A transaction is valid iff
-    /// 1) The signature on the transaction
+    /// 1) The signature on the transaction, which is for this rollup's VM
     /// 2) The nonce of the transaction is greater than the sender nonce (this prevent replay attacks)
-    /// 3) The sender has a high enough balance to cover the transfer amount
+    /// 3) The sender has a high enough balance to cover the transfer amount and the priority tip
+    /// 4) The recipient's balance does not overflow
+    ///
+    /// A transfer to a destination the [`ChainConfig`] denies is rejected as well, and so is one
+    /// whose priority is below the [minimum fee](Self::min_fee).
     pub fn apply_transaction(
         &mut self,
         transaction: &SignedTransaction,
     ) -> Result<(), RollupError> {
         // 1)
-        let sender = transaction.recover()?;
-        let destination = transaction.transaction.destination;
-        let next_nonce = transaction.transaction.nonce;
-        let transfer_amount = transaction.transaction.amount;
+        let sender = transaction.sender()?;
+        self.transfer(sender, &transaction.transaction)
+    }
+
+    /// Apply a transaction of any kind, if it is valid.
+    pub fn apply_rollup_transaction(
+        &mut self,
+        transaction: &RollupTransaction,
+    ) -> Result<(), RollupError> {
+        match transaction {
+            RollupTransaction::Transfer(txn) => self.apply_transaction(txn),
+            RollupTransaction::RegisterMultisig(txn) => self.apply_multisig_registration(txn),
+            RollupTransaction::MultisigTransfer(txn) => self.apply_multisig_transfer(txn),
+            RollupTransaction::OutboundMessage(txn) => txn.send(self.vm.id().into()).map(drop),
+            RollupTransaction::SetMinFee(txn) => self.apply_set_min_fee(txn),
+        }
+    }
+
+    /// Schedule a change to the minimum fee, if the fee admin signed it with the next nonce. It
+    /// replaces any change scheduled before which has not taken effect.
+    pub fn apply_set_min_fee(&mut self, transaction: &SignedSetMinFee) -> Result<(), RollupError> {
+        let signer = transaction.signer()?;
+        let change = &transaction.set_min_fee;
+        let vm_id: u64 = self.vm.id().into();
+        if change.vm_id != vm_id {
+            return Err(RollupError::WrongVm {
+                expected: vm_id,
+                actual: change.vm_id,
+            });
+        }
+        if self.chain_config.fee_admin != Some(signer) {
+            return Err(RollupError::NotFeeAdmin { address: signer });
+        }
+        if change.nonce != self.min_fee.nonce + 1 {
+            return Err(RollupError::InvalidNonce {
+                address: signer,
+                expected: self.min_fee.nonce + 1,
+                actual: change.nonce,
+            });
+        }
+        self.min_fee.nonce = change.nonce;
+        self.min_fee.scheduled = Some(ScheduledMinFee {
+            value: change.value,
+            effective_height: change.effective_height,
+        });
+        self.commitment.take();
+        tracing::info!(
+            target: STATE,
+            "Scheduled minimum fee {} from block {}",
+            change.value,
+            change.effective_height
+        );
+        Ok(())
+    }
+
+    /// Register a multisig account, if its configuration is valid and it is not registered yet.
+    pub fn apply_multisig_registration(
+        &mut self,
+        transaction: &RegisterMultisig,
+    ) -> Result<(), RollupError> {
+        let config = transaction.config()?;
+        let address = config.address();
+        if self.multisigs.contains_key(&address) {
+            return Err(RollupError::MultisigAlreadyRegistered { address });
+        }
+        self.multisigs.insert(address, config);
+        self.commitment.take();
+        tracing::info!(target: STATE, "Registered multisig account {address}");
+        Ok(())
+    }
+
+    /// Transfer from a multisig account, if enough of its signers signed and the transfer is
+    /// otherwise valid, as for [`apply_transaction`](Self::apply_transaction).
+    pub fn apply_multisig_transfer(
+        &mut self,
+        transaction: &MultisigTransfer,
+    ) -> Result<(), RollupError> {
+        self.apply_recovered_multisig_transfer(transaction, None)
+    }
+
+    /// Apply a multisig transfer whose signers may have been recovered ahead of time.
+    fn apply_recovered_multisig_transfer(
+        &mut self,
+        transaction: &MultisigTransfer,
+        recovered: Recovered,
+    ) -> Result<(), RollupError> {
+        let address = transaction.account;
+        let config = self
+            .multisigs
+            .get(&address)
+            .ok_or(RollupError::UnknownMultisig { address })?;
+        let signers = match recovered {
+            Some(signers) => signers?,
+            None => transaction.signers()?,
+        };
+        transaction.check_signers(&signers, config)?;
+        self.transfer(address, &transaction.transaction)
+    }
+
+    /// Move funds from `sender` as `transaction` says, once its signatures have been checked.
+    fn transfer(&mut self, sender: Address, transaction: &Transaction) -> Result<(), RollupError> {
+        let destination = transaction.destination;
+        let next_nonce = transaction.nonce;
+        let transfer_amount = transaction.amount;
+        let tip = transaction.priority;
+
+        // 1) A signature for another VM does not authorize a transfer on this one, even though the
+        // sender signed the same transfer.
+        let vm_id: u64 = self.vm.id().into();
+        if transaction.vm_id != vm_id {
+            return Err(RollupError::WrongVm {
+                expected: vm_id,
+                actual: transaction.vm_id,
+            });
+        }
+        if self.chain_config.denies_destination(&destination) {
+            return Err(RollupError::InvalidDestination {
+                address: destination,
+            });
+        }
+        if tip < self.min_fee.current {
+            return Err(RollupError::FeeTooLow {
+                fee: tip,
+                min: self.min_fee.current,
+            });
+        }
+        if let Some(asset) = transaction.asset {
+            return self.transfer_asset(sender, asset, transaction);
+        }
+
+        // Only copies the accounts if a clone of the state still shares them.
+        let accounts = Arc::make_mut(&mut self.accounts);
+        // How much the recipient's balance can grow. A transfer to the sender itself can never
+        // overflow, since the sender pays at least the amount first.
+        let headroom = match accounts.get(&destination) {
+            Some(account) if destination != sender => Amount::MAX - account.balance,
+            _ => Amount::MAX,
+        };
         let Account {
             nonce: prev_nonce,
             balance: sender_balance,
-        } = self
-            .accounts
+        } = accounts
             .get_mut(&sender)
             .ok_or(RollupError::InsufficientBalance { address: sender })?;
 
@@ -125,23 +909,120 @@ impl State {
         }
 
         // 3)
-        if transfer_amount > *sender_balance {
+        let cost = transfer_amount
+            .checked_add(tip)
+            .ok_or(RollupError::InsufficientBalance { address: sender })?;
+        if cost > *sender_balance {
             return Err(RollupError::InsufficientBalance { address: sender });
         }
 
-        // Transaction is valid, return the updated state
-        *sender_balance -= transfer_amount;
+        // 4) Checked rather than left to wrap or panic, which would depend on how the executor was
+        // built.
+        if transfer_amount > headroom {
+            return Err(RollupError::BalanceOverflow {
+                address: destination,
+            });
+        }
+
+        // Transaction is valid, return the updated state. The tip is burned.
+        *sender_balance -= cost;
         *prev_nonce = next_nonce;
         let Account {
             balance: destination_balance,
             ..
-        } = self.accounts.entry(destination).or_default();
+        } = accounts.entry(destination).or_default();
         *destination_balance += transfer_amount;
+        self.account_changed(sender);
+        self.account_changed(destination);
+        self.supply.burned += U256::from(tip);
+        self.commitment.take();
+
+        tracing::info!(target: STATE, "Applied transaction {next_nonce} for {sender}");
+        Ok(())
+    }
+
+    /// Move funds of the deposited asset `asset` from `sender`, as [`transfer`](Self::transfer)
+    /// does for the native asset. The nonce is the sender's native one, and the tip is still paid,
+    /// and burned, in the native asset.
+    fn transfer_asset(
+        &mut self,
+        sender: Address,
+        asset: Address,
+        transaction: &Transaction,
+    ) -> Result<(), RollupError> {
+        let destination = transaction.destination;
+        let next_nonce = transaction.nonce;
+        let transfer_amount = transaction.amount;
+        let tip = transaction.priority;
 
-        tracing::info!("Applied transaction {next_nonce} for {sender}");
+        // Checked first, so that a transfer of an asset the rollup does not have is rejected the
+        // same way whatever the sender's balances.
+        if !self.chain_config.assets.contains(&asset) {
+            return Err(RollupError::UnknownAsset { asset });
+        }
+
+        let Account {
+            nonce: prev_nonce,
+            balance: native_balance,
+        } = self.accounts.get(&sender).cloned().unwrap_or_default();
+        if next_nonce != prev_nonce + 1 {
+            return Err(RollupError::InvalidNonce {
+                address: sender,
+                expected: prev_nonce + 1,
+                actual: next_nonce,
+            });
+        }
+        if tip > native_balance || transfer_amount > self.asset_balance(&asset, &sender) {
+            return Err(RollupError::InsufficientBalance { address: sender });
+        }
+        if destination != sender
+            && transfer_amount > Amount::MAX - self.asset_balance(&asset, &destination)
+        {
+            return Err(RollupError::BalanceOverflow {
+                address: destination,
+            });
+        }
+
+        let account = Arc::make_mut(&mut self.accounts).entry(sender).or_default();
+        account.balance -= tip;
+        account.nonce = next_nonce;
+        let balances = self.asset_balances.entry(asset).or_default();
+        *balances.entry(sender).or_default() -= transfer_amount;
+        *balances.entry(destination).or_default() += transfer_amount;
+        self.account_changed(sender);
+        self.supply.burned += U256::from(tip);
+        self.commitment.take();
+
+        tracing::info!(
+            target: STATE,
+            "Applied transaction {next_nonce} for {sender} in asset {asset:?}"
+        );
         Ok(())
     }
 
+    /// Recover the senders of all of a block's transactions in parallel before applying them,
+    /// instead of one at a time as each is applied. The result is the same either way.
+    ///
+    /// Recovery is only spread over several threads with the `parallel` feature. Without it, the
+    /// senders are still recovered up front, on the executor's thread.
+    pub fn set_eager_recovery(&mut self, eager: bool) {
+        self.eager_recovery = eager;
+    }
+
+    /// Recover senders eagerly on `threads` threads, or on one thread per core if `threads` is
+    /// zero, which is the default. Only matters with [eager recovery](Self::set_eager_recovery).
+    pub fn set_recovery_threads(&mut self, threads: usize) {
+        self.recovery_threads = threads;
+    }
+
+    /// Checkpoint the balance of every account each `interval` blocks from now on, rather than at
+    /// the [default interval](crate::balance_history::DEFAULT_BALANCE_CHECKPOINT_INTERVAL).
+    pub fn set_balance_checkpoint_interval(&mut self, interval: u64) {
+        if interval != self.balance_history.interval() {
+            Arc::make_mut(&mut self.balance_history).set_interval(interval);
+        }
+    }
+
     /// Fetch the balance of an address
     pub fn get_balance(&self, address: &Address) -> Amount {
         self.accounts
@@ -158,89 +1039,3246 @@ impl State {
             .unwrap_or(0)
     }
 
-    pub(crate) async fn execute_block(
-        &mut self,
-        nmt_root: NMTRoot,
-        namespace_proof: NamespaceProofType,
-    ) -> Proof {
-        let state_commitment = self.commit();
-        let transactions = namespace_proof.get_namespace_leaves();
-        for txn in transactions {
-            if let Some(rollup_txn) = txn.as_vm(&self.vm) {
-                let res = self.apply_transaction(&rollup_txn);
-                if let Err(err) = res {
-                    tracing::error!("Transaction invalid: {}", err)
-                }
-            } else {
-                tracing::error!("NMT transaction is malformed")
-            }
-        }
-        self.nmt_comm = Some(nmt_root.commit());
-        self.prev_state_commitment = Some(state_commitment);
+    /// The history of the account at `address`, if any block has applied a transaction to or from
+    /// it, or it is an account of the initial state.
+    pub fn activity(&self, address: &Address) -> Option<AccountActivity> {
+        self.activity.get(address).copied()
+    }
 
-        Proof::generate(
-            nmt_root,
-            self.commit(),
-            self.prev_state_commitment.unwrap(),
-            namespace_proof,
-            &self.vm,
-        )
+    /// The checkpoints of the balances of accounts.
+    pub fn balance_history(&self) -> &BalanceHistory {
+        &self.balance_history
     }
-}
-#[cfg(test)]
-mod tests {
-    use crate::transaction::Transaction;
 
-    use ethers::signers::{LocalWallet, Signer};
+    /// Checkpoint the balance of `address` after every block which changes it. Returns whether it
+    /// was not watched already.
+    pub fn watch_balance(&mut self, address: Address) -> bool {
+        let balance = self.get_balance(&address);
+        Arc::make_mut(&mut self.balance_history).watch(address, self.block_height, balance)
+    }
 
-    use super::*;
-    #[async_std::test]
-    async fn smoke_test() {
-        let mut rng = rand::thread_rng();
-        let vm = RollupVM::new(1.into());
-        let alice = LocalWallet::new(&mut rng);
-        let bob = LocalWallet::new(&mut rng);
-        let seed_data = [(alice.address(), 100), (bob.address(), 100)];
-        let mut state = State::from_initial_balances(seed_data, vm);
-        let mut transaction = Transaction {
-            amount: 110,
-            destination: bob.address(),
-            nonce: 1,
-        };
+    /// Only checkpoint the balance of `address` with every other account's. Returns whether it was
+    /// watched.
+    pub fn unwatch_balance(&mut self, address: &Address) -> bool {
+        self.balance_history.watched().contains(address)
+            && Arc::make_mut(&mut self.balance_history).unwatch(address)
+    }
 
-        // Try to overspend
-        let mut signed_transaction = SignedTransaction::new(transaction.clone(), &alice).await;
-        let err = state
-            .clone()
-            .apply_transaction(&signed_transaction)
-            .expect_err("Invalid transaction should throw error.");
-        assert_eq!(
-            err,
-            RollupError::InsufficientBalance {
-                address: alice.address()
-            }
-        );
+    /// The balance of `address` in the deposited asset `asset`.
+    pub fn asset_balance(&self, asset: &Address, address: &Address) -> Amount {
+        self.asset_balances
+            .get(asset)
+            .and_then(|balances| balances.get(address))
+            .copied()
+            .unwrap_or(0)
+    }
 
-        // Now spend an valid amount
-        transaction.amount = 50;
-        signed_transaction = SignedTransaction::new(transaction, &alice).await;
-        state
-            .apply_transaction(&signed_transaction)
-            .expect("Valid transaction should transition state");
-        let bob_balance = state.get_balance(&bob.address());
-        assert_eq!(bob_balance, 150);
+    /// Every balance of a deposited asset, as the asset, the account and the balance, by asset and
+    /// then by account.
+    pub fn asset_holdings(&self) -> impl Iterator<Item = (Address, Address, Amount)> + '_ {
+        self.asset_balances.iter().flat_map(|(asset, balances)| {
+            balances
+                .iter()
+                .map(move |(address, balance)| (*asset, *address, *balance))
+        })
+    }
 
-        // Now try to replay the transaction
-        let err = state
-            .apply_transaction(&signed_transaction)
-            .expect_err("Invalid transaction should throw error.");
-        assert_eq!(
-            err,
-            RollupError::InvalidNonce {
-                address: alice.address(),
-                expected: 2,
-                actual: 1,
-            }
-        );
+    /// The amount of each token which should be in the balances of the accounts.
+    pub fn supply(&self) -> &Supply {
+        &self.supply
+    }
+
+    /// The balance of `address` in each asset of the chain configuration, including those it holds
+    /// none of. The native balance is [`get_balance`](Self::get_balance).
+    pub fn asset_balances(&self, address: &Address) -> BTreeMap<Address, Amount> {
+        self.chain_config
+            .assets
+            .iter()
+            .map(|asset| (*asset, self.asset_balance(asset, address)))
+            .collect()
+    }
+
+    /// Every reason a transfer of `transaction` signed by `sender` would not be applied, were it
+    /// executed against this state. Empty if it would be.
+    ///
+    /// The state is not changed, and the signature is not checked, so this can tell a client what
+    /// is wrong with a transfer before it is signed.
+    pub fn check_transfer(&self, sender: &Address, transaction: &Transaction) -> Vec<RollupError> {
+        let mut problems = vec![];
+        let vm_id: u64 = self.vm.id().into();
+        if transaction.vm_id != vm_id {
+            problems.push(RollupError::WrongVm {
+                expected: vm_id,
+                actual: transaction.vm_id,
+            });
+        }
+        if self
+            .chain_config
+            .denies_destination(&transaction.destination)
+        {
+            problems.push(RollupError::InvalidDestination {
+                address: transaction.destination,
+            });
+        }
+        let min = self.min_fee.at(self.block_height);
+        if transaction.priority < min {
+            problems.push(RollupError::FeeTooLow {
+                fee: transaction.priority,
+                min,
+            });
+        }
+        let expected = self.get_nonce(sender) + 1;
+        if transaction.nonce != expected {
+            problems.push(RollupError::InvalidNonce {
+                address: *sender,
+                expected,
+                actual: transaction.nonce,
+            });
+        }
+        let native = self.get_balance(sender);
+        let covered = match transaction.asset {
+            None => transaction
+                .amount
+                .checked_add(transaction.priority)
+                .is_some_and(|cost| cost <= native),
+            Some(asset) if !self.chain_config.assets.contains(&asset) => {
+                problems.push(RollupError::UnknownAsset { asset });
+                true
+            }
+            Some(asset) => {
+                transaction.priority <= native
+                    && transaction.amount <= self.asset_balance(&asset, sender)
+            }
+        };
+        if !covered {
+            problems.push(RollupError::InsufficientBalance { address: *sender });
+        }
+        problems
+    }
+
+    /// The range of L1 blocks whose deposits the next block credits, if any, given the L1 head and
+    /// the finalized L1 block the next block references.
+    ///
+    /// Deposits are only credited from finalized L1 blocks, so that every executor sees the same
+    /// ones, which are also [`deposit_confirmations`](ChainConfig::deposit_confirmations) blocks
+    /// below the head, and only once the chain configuration allows an asset to be deposited.
+    pub fn pending_deposits(
+        &self,
+        l1_head: u64,
+        l1_finalized: Option<u64>,
+    ) -> Option<RangeInclusive<u64>> {
+        let confirmed = l1_head.checked_sub(self.chain_config.deposit_confirmations)?;
+        let through = l1_finalized?.min(confirmed);
+        if self.chain_config.assets.is_empty() || through <= self.deposits_through {
+            return None;
+        }
+        Some(self.deposits_through + 1..=through)
+    }
+
+    /// The last L1 block whose deposits have been credited.
+    pub fn deposits_through(&self) -> u64 {
+        self.deposits_through
+    }
+
+    /// Credit the deposits made on L1 up to and including L1 block `deposits.through`.
+    ///
+    /// Deposits of tokens which are not assets of the [`ChainConfig`] are ignored, as are
+    /// deposits which do not fit in an [`Amount`] or would overflow the recipient's balance. The
+    /// tokens stay locked in the rollup contract either way.
+    pub fn credit_deposits(&mut self, deposits: &L1Deposits) {
+        if deposits.through <= self.deposits_through {
+            return;
+        }
+        for deposit in &deposits.deposits {
+            if deposit.l1_block <= self.deposits_through {
+                continue;
+            }
+            if !self.chain_config.assets.contains(&deposit.token) {
+                tracing::warn!(
+                    target: BRIDGE,
+                    "Ignoring deposit of unknown token {:?} to {:?} in L1 block {}",
+                    deposit.token,
+                    deposit.recipient,
+                    deposit.l1_block
+                );
+                continue;
+            }
+            let balance = self.asset_balance(&deposit.token, &deposit.recipient);
+            match Amount::try_from(deposit.amount)
+                .ok()
+                .and_then(|amount| balance.checked_add(amount))
+            {
+                Some(credited) => {
+                    self.asset_balances
+                        .entry(deposit.token)
+                        .or_default()
+                        .insert(deposit.recipient, credited);
+                    *self.supply.deposited.entry(deposit.token).or_default() +=
+                        U256::from(credited - balance);
+                }
+                None => tracing::warn!(
+                    target: BRIDGE,
+                    "Ignoring deposit of {} of token {:?} to {:?}, which overflows its balance",
+                    deposit.amount,
+                    deposit.token,
+                    deposit.recipient
+                ),
+            }
+        }
+        self.deposits_through = deposits.through;
+        self.commitment.take();
+    }
+
+    /// The range of L1 blocks whose transactions enqueued for [forced inclusion](crate::forced) the
+    /// next block applies, if any, given the finalized L1 block it references.
+    ///
+    /// As with deposits, only finalized L1 blocks are looked at, so that every executor applies the
+    /// same transactions, and only once the chain configuration enables forced inclusion.
+    pub fn pending_forced(&self, l1_finalized: Option<u64>) -> Option<RangeInclusive<u64>> {
+        let through = l1_finalized?;
+        if !self.chain_config.forced_inclusion || through <= self.forced_through {
+            return None;
+        }
+        Some(self.forced_through + 1..=through)
+    }
+
+    /// The last L1 block whose transactions enqueued for forced inclusion have been applied.
+    pub fn forced_through(&self) -> u64 {
+        self.forced_through
+    }
+
+    /// Take the transactions enqueued on L1 up to and including L1 block `queue.through` which
+    /// have not been taken yet, to be applied, each with the L1 block it was enqueued in.
+    ///
+    /// Transactions which do not decode are skipped, as are messages to other rollups, which only
+    /// see the messages sequenced in this rollup's namespace.
+    fn dequeue_forced(&mut self, queue: &L1Queue) -> Vec<(RollupTransaction, u64)> {
+        if !self.chain_config.forced_inclusion || queue.through <= self.forced_through {
+            return vec![];
+        }
+        let mut transactions = vec![];
+        for enqueued in &queue.transactions {
+            if enqueued.l1_block <= self.forced_through {
+                continue;
+            }
+            match enqueued.decode() {
+                Some(RollupTransaction::OutboundMessage(_)) => tracing::warn!(
+                    target: BRIDGE,
+                    "Ignoring message enqueued by {:?} in L1 block {}, which cannot be delivered",
+                    enqueued.sender,
+                    enqueued.l1_block
+                ),
+                Some(txn) => transactions.push((txn, enqueued.l1_block)),
+                None => tracing::warn!(
+                    target: BRIDGE,
+                    "Ignoring malformed transaction enqueued by {:?} in L1 block {}",
+                    enqueued.sender,
+                    enqueued.l1_block
+                ),
+            }
+        }
+        self.forced_through = queue.through;
+        self.commitment.take();
+        transactions
+    }
+
+    /// Deliver messages from other rollups to the inboxes of their recipients.
+    ///
+    /// Only messages to this rollup from one of the [`ChainConfig::message_sources`] are
+    /// delivered, and only if the nonce of each is greater than that of the last message delivered
+    /// from the same sender on the same rollup. Others are ignored.
+    pub fn deliver_messages(&mut self, messages: &[CrossVmMessage]) {
+        let vm: u64 = self.vm.id().into();
+        for message in messages {
+            if message.dest_vm != vm
+                || !self
+                    .chain_config
+                    .message_sources
+                    .contains(&message.source_vm)
+            {
+                tracing::warn!(
+                    target: BRIDGE,
+                    "Ignoring message from VM {} to VM {}",
+                    message.source_vm,
+                    message.dest_vm
+                );
+                continue;
+            }
+            let last = self
+                .inbox_nonces
+                .entry(message.source_vm)
+                .or_default()
+                .entry(message.sender)
+                .or_default();
+            if message.nonce <= *last {
+                tracing::warn!(
+                    target: BRIDGE,
+                    "Ignoring replayed message {} from {:?} on VM {}",
+                    message.nonce,
+                    message.sender,
+                    message.source_vm
+                );
+                continue;
+            }
+            *last = message.nonce;
+            self.inbox
+                .entry(message.recipient)
+                .or_default()
+                .push(message.clone());
+            self.commitment.take();
+        }
+    }
+
+    /// The messages delivered to `address` from other rollups, in the order they were delivered.
+    pub fn messages(&self, address: &Address) -> &[CrossVmMessage] {
+        self.inbox.get(address).map_or(&[], Vec::as_slice)
+    }
+
+    /// The configuration of the multisig account at `address`, if one is registered.
+    pub fn multisig(&self, address: &Address) -> Option<&MultisigConfig> {
+        self.multisigs.get(address)
+    }
+
+    /// Balances of every account the rollup knows about, in address order.
+    pub fn balances(&self) -> impl Iterator<Item = (Address, Amount)> + '_ {
+        self.accounts
+            .iter()
+            .map(|(address, account)| (*address, account.balance))
+    }
+
+    /// Number of blocks executed so far.
+    pub fn block_height(&self) -> u64 {
+        self.block_height
+    }
+
+    /// Height of the recent block which included the transaction with hash `hash`, if any.
+    pub fn transaction_block(&self, hash: &H256) -> Option<u64> {
+        self.recent_transactions.get(hash).copied()
+    }
+
+    /// Position of the transaction with hash `hash` in its block at `height`, if the block's
+    /// receipts are still kept and record it.
+    pub fn transaction_index(&self, height: u64, hash: &H256) -> Option<u64> {
+        self.block_receipts(height)?
+            .iter()
+            .find(|outcome| outcome.hash == *hash)?
+            .index
+    }
+
+    /// The proof generated when executing the recent block at `height`, if any.
+    pub fn proof(&self, height: u64) -> Option<&Proof> {
+        self.recent_proofs.get(&height)
+    }
+
+    /// The summary of the recent block at `height`, if any.
+    pub fn block_summary(&self, height: u64) -> Option<&BlockSummary> {
+        self.recent_summaries.get(&height)
+    }
+
+    /// The L1 transaction with hash `l1_tx`, if it proved recent blocks.
+    pub fn proof_transaction(&self, l1_tx: &H256) -> Option<&ProofTransaction> {
+        self.proof_transactions.get(l1_tx)
+    }
+
+    /// Record that the rollup contract accepted the proof of the blocks `proof` covers.
+    ///
+    /// The transaction is recorded in the summary of each of those blocks which is still kept,
+    /// replacing any transaction recorded before, such as one whose L1 block was reorged out.
+    pub(crate) fn record_proof_transaction(&mut self, proof: ProofTransaction) {
+        let mut recorded = false;
+        for (_, summary) in self.recent_summaries.range_mut(proof.heights()) {
+            if let Some(old) = summary.l1_tx.replace(proof.l1_tx) {
+                if old != proof.l1_tx {
+                    self.proof_transactions.remove(&old);
+                }
+            }
+            recorded = true;
+        }
+        if recorded {
+            self.proof_transactions.insert(proof.l1_tx, proof);
+        }
+    }
+
+    /// The rollup transactions in the recent block at `height`, and what became of each, if the
+    /// block is recent enough for them to still be kept.
+    pub fn block_receipts(&self, height: u64) -> Option<&[TransactionOutcome]> {
+        self.recent_receipts.get(&height).map(Vec::as_slice)
+    }
+
+    /// How far the history of executed blocks has been pruned.
+    pub fn pruned(&self) -> PrunedHeights {
+        self.pruned
+    }
+
+    /// Prune the receipts, summaries and proofs of the blocks older than `retention` allows,
+    /// keeping those of every block at or above `protected`. Returns how far each has now been
+    /// pruned.
+    pub(crate) fn prune(&mut self, retention: &RetentionConfig, protected: u64) -> PrunedHeights {
+        self.pruned = self.pruned.advance(retention, self.block_height, protected);
+        self.recent_receipts = self.recent_receipts.split_off(&self.pruned.receipts);
+        self.recent_summaries = self.recent_summaries.split_off(&self.pruned.history);
+        let history = self.pruned.history;
+        self.proof_transactions
+            .retain(|_, proof| proof.heights().end > history);
+        self.recent_proofs = self.recent_proofs.split_off(&self.pruned.proofs);
+        if self.balance_history.since() < self.pruned.history {
+            Arc::make_mut(&mut self.balance_history).prune(self.pruned.history);
+        }
+        self.pruned
+    }
+
+    fn record_block_transactions(&mut self, outcomes: &[TransactionOutcome]) {
+        for outcome in outcomes {
+            self.recent_transactions
+                .insert(outcome.hash, self.block_height);
+            if outcome.applied() && outcome.message.is_none() {
+                self.recent_applied.insert(outcome.hash, self.block_height);
+            }
+        }
+        self.recent_blocks
+            .push_back(outcomes.iter().map(|outcome| outcome.hash).collect());
+        if self.recent_blocks.len() > RECENT_BLOCKS {
+            // The hash may have been applied again in a later block, which is still recent.
+            let oldest = self.block_height.saturating_sub(RECENT_BLOCKS as u64 - 1);
+            for hash in self.recent_blocks.pop_front().unwrap_or_default() {
+                self.recent_transactions.remove(&hash);
+                if self
+                    .recent_applied
+                    .get(&hash)
+                    .is_some_and(|height| *height < oldest)
+                {
+                    self.recent_applied.remove(&hash);
+                }
+            }
+        }
+    }
+
+    fn record_activity(&mut self, outcomes: &[TransactionOutcome]) {
+        let applied = outcomes
+            .iter()
+            .filter(|outcome| outcome.applied())
+            .collect::<Vec<_>>();
+        if applied.is_empty() {
+            return;
+        }
+        let activity = Arc::make_mut(&mut self.activity);
+        for outcome in applied {
+            let mut addresses = outcome
+                .sender
+                .into_iter()
+                .chain(outcome.transaction.iter().map(|txn| txn.destination))
+                .chain(outcome.multisig.iter().map(MultisigConfig::address))
+                .collect::<Vec<_>>();
+            // A transfer to the sender itself is one transaction of the account.
+            addresses.dedup();
+            for address in addresses {
+                activity
+                    .entry(address)
+                    .or_insert(AccountActivity {
+                        first_seen: self.block_height,
+                        transactions: 0,
+                    })
+                    .transactions += 1;
+            }
+        }
+    }
+
+    /// Checkpoint the balances which changed since their last checkpoint: those of every account
+    /// at a checkpoint height, and otherwise those of the watched accounts.
+    fn record_balances(&mut self) {
+        let height = self.block_height;
+        let history = &self.balance_history;
+        let balances = if history.is_checkpoint(height) {
+            self.balances().collect::<Vec<_>>()
+        } else {
+            history
+                .watched()
+                .iter()
+                .map(|address| (*address, self.get_balance(address)))
+                .collect()
+        };
+        let changed = balances
+            .into_iter()
+            .filter(|(address, balance)| history.balance_at(address, height) != *balance)
+            .collect::<Vec<_>>();
+        if !changed.is_empty() {
+            Arc::make_mut(&mut self.balance_history).record(height, changed);
+        }
+    }
+
+    /// The height of the block which applied the transaction with hash `hash`, if it is `applied`
+    /// in the block being executed or was applied in a recent block.
+    fn duplicate_of(&self, hash: &H256, applied: &HashSet<H256>) -> Option<u64> {
+        if applied.contains(hash) {
+            Some(self.block_height)
+        } else {
+            self.recent_applied.get(hash).copied()
+        }
+    }
+
+    /// Apply the rollup transactions in a block, without generating a proof.
+    ///
+    /// Invalid transactions are skipped, exactly as when the executor executes the block, so this
+    /// can be used to replay the rollup from its initial state.
+    ///
+    /// Returns how many transactions were found and applied. Only this rollup's namespace is
+    /// available here, so the total is the number of transactions in the namespace.
+    ///
+    /// The L1 deposits, messages from other rollups and forced transactions in `inputs` are picked
+    /// up first, as when the executor executes the block. A replay only reproduces the executor's
+    /// states if `inputs` holds the ones it picked up.
+    pub fn apply_block(
+        &mut self,
+        nmt_root: &NMTRoot,
+        namespace_proof: &NamespaceProofType,
+        inputs: &BlockInputs,
+    ) -> BlockCounts {
+        self.apply_block_transactions(nmt_root, namespace_proof, inputs)
+            .0
+    }
+
+    /// Apply a block whose namespace holds `payloads`, decoding and filtering them exactly as
+    /// [`apply_block`](Self::apply_block) does.
+    ///
+    /// This needs no namespace proof, so a block can be built from arbitrary payloads, including
+    /// ones which are malformed or belong to other rollups, to check that every executor handles
+    /// them alike.
+    pub fn apply_block_payloads<T: Borrow<sequencer::Transaction>>(
+        &mut self,
+        block: Commitment<NMTRoot>,
+        payloads: impl IntoIterator<Item = T>,
+    ) -> BlockCounts {
+        self.apply_payloads(block, payloads, &BlockInputs::default())
+            .0
+    }
+
+    fn apply_block_transactions(
+        &mut self,
+        nmt_root: &NMTRoot,
+        namespace_proof: &NamespaceProofType,
+        inputs: &BlockInputs,
+    ) -> (BlockCounts, Vec<TransactionOutcome>) {
+        self.apply_payloads(
+            nmt_root.commit(),
+            namespace_proof.get_namespace_leaves(),
+            inputs,
+        )
+    }
+
+    fn apply_payloads<T: Borrow<sequencer::Transaction>>(
+        &mut self,
+        block: Commitment<NMTRoot>,
+        payloads: impl IntoIterator<Item = T>,
+        inputs: &BlockInputs,
+    ) -> (BlockCounts, Vec<TransactionOutcome>) {
+        let (counts, rollup_txns) = self.decode_payloads(payloads);
+        self.apply_decoded(block, inputs, counts, rollup_txns)
+    }
+
+    /// Decode the payloads of a block which belong to this rollup, counting them. Each is returned
+    /// with its position among the payloads.
+    fn decode_payloads<T: Borrow<sequencer::Transaction>>(
+        &self,
+        payloads: impl IntoIterator<Item = T>,
+    ) -> (BlockCounts, Vec<(RollupTransaction, u64, usize)>) {
+        let mut counts = BlockCounts::default();
+        let mut rollup_txns = vec![];
+        for txn in payloads {
+            let txn = txn.borrow();
+            let index = counts.total_payloads;
+            counts.total_payloads += 1;
+            if txn.vm() == self.vm.id() {
+                counts.vm_payloads += 1;
+            }
+            if let Some(rollup_txn) = txn.as_vm(&self.vm) {
+                counts.decoded += 1;
+                rollup_txns.push((rollup_txn, index, txn.payload().len()));
+            } else {
+                tracing::error!(target: STATE, "NMT transaction is malformed")
+            }
+        }
+        (counts, rollup_txns)
+    }
+
+    /// Apply the decoded transactions of a block, adding what became of them to `counts`.
+    fn apply_decoded(
+        &mut self,
+        block: Commitment<NMTRoot>,
+        inputs: &BlockInputs,
+        mut counts: BlockCounts,
+        rollup_txns: Vec<(RollupTransaction, u64, usize)>,
+    ) -> (BlockCounts, Vec<TransactionOutcome>) {
+        let (rollup_txns, positions): (Vec<_>, Vec<_>) = rollup_txns
+            .into_iter()
+            .map(|(txn, position, len)| ((txn, Some(len)), position))
+            .unzip();
+        let mut outcomes = self.apply_sequenced_block(block, inputs, rollup_txns);
+        // Positions among the decoded transactions become positions among all the payloads.
+        for outcome in &mut outcomes {
+            outcome.index = outcome.index.map(|index| positions[index as usize]);
+        }
+        counts.applied = outcomes.iter().filter(|outcome| outcome.applied()).count() as u64;
+        counts.duplicates = outcomes.iter().filter(|outcome| outcome.duplicate).count() as u64;
+        counts.forced = outcomes
+            .iter()
+            .filter(|outcome| outcome.l1_block.is_some())
+            .count() as u64;
+        (counts, outcomes)
+    }
+
+    /// Apply `transactions` as the rollup transactions of the block with commitment `block`,
+    /// advancing the block height and linking the new state to the old one exactly as
+    /// [`apply_block`](Self::apply_block) does.
+    ///
+    /// This needs no sequencer block, so it can build a deterministic chain of states from
+    /// scripted transactions, as the [golden vectors](crate::vectors) and the benchmarks do.
+    pub fn apply_block_with_transactions(
+        &mut self,
+        block: Commitment<NMTRoot>,
+        transactions: Vec<RollupTransaction>,
+    ) -> Vec<TransactionOutcome> {
+        self.apply_block_with_inputs(block, &BlockInputs::default(), transactions)
+    }
+
+    /// Apply a block as [`apply_block_with_transactions`](Self::apply_block_with_transactions)
+    /// does, first crediting the L1 deposits and delivering the messages it picks up, so that its
+    /// transactions can spend them, and applying the transactions it forces in from L1 ahead of
+    /// its own.
+    pub fn apply_block_with_inputs(
+        &mut self,
+        block: Commitment<NMTRoot>,
+        inputs: &BlockInputs,
+        transactions: Vec<RollupTransaction>,
+    ) -> Vec<TransactionOutcome> {
+        let transactions = transactions.into_iter().map(|txn| (txn, None)).collect();
+        self.apply_sequenced_block(block, inputs, transactions)
+    }
+
+    /// Apply a block as [`apply_block_with_inputs`](Self::apply_block_with_inputs) does, with the
+    /// length of the payload each transaction was sequenced in, if it was decoded from one.
+    fn apply_sequenced_block(
+        &mut self,
+        block: Commitment<NMTRoot>,
+        inputs: &BlockInputs,
+        transactions: Vec<(RollupTransaction, Option<usize>)>,
+    ) -> Vec<TransactionOutcome> {
+        self.encode_accounts();
+        let state_commitment = self.commit();
+        self.activate_min_fee();
+        self.credit_deposits(&inputs.deposits);
+        self.deliver_messages(&inputs.messages);
+        let forced = self.dequeue_forced(&inputs.forced);
+        let outcomes = self.apply_forced_and_sequenced(forced, transactions);
+        self.record_block_transactions(&outcomes);
+        self.record_activity(&outcomes);
+        self.block_height += 1;
+        self.record_balances();
+        self.nmt_comm = Some(block);
+        self.prev_state_commitment = Some(state_commitment);
+        self.commitment.take();
+        outcomes
+    }
+
+    /// Put the scheduled change to the minimum fee into effect, if it takes effect in the block
+    /// about to be executed.
+    fn activate_min_fee(&mut self) {
+        let Some(change) = self.min_fee.scheduled else {
+            return;
+        };
+        if change.effective_height <= self.block_height {
+            self.min_fee.current = change.value;
+            self.min_fee.scheduled = None;
+            self.commitment.take();
+            tracing::info!(target: STATE, "Minimum fee is now {}", change.value);
+        }
+    }
+
+    /// Apply the rollup transactions of a block, in the order the consensus rule requires.
+    ///
+    /// Every executor must apply the transactions in the same order: by decreasing priority, and
+    /// within a priority in the order the sequencer included them. Transactions are executed in
+    /// that order until the next one would exceed the block weight budget of the
+    /// [`ChainConfig`]; it and the rest are skipped. Returns what became of each transaction, in
+    /// the order they were applied, each with the [position](TransactionOutcome::index) of its
+    /// transaction in `transactions`. Unlike [`apply_block`](Self::apply_block), this does not
+    /// advance the block height.
+    ///
+    /// Messages to other rollups count against the budget like any other transaction, as described
+    /// under [`message`](crate::message). Those within it are sent in the order they were
+    /// sequenced, and become the outbox of the block.
+    ///
+    /// A copy of a transfer or registration already applied, earlier in the block or in one of the
+    /// last `RECENT_BLOCKS` blocks, is not applied again and is marked as a
+    /// [duplicate](TransactionOutcome::duplicate). It would fail on its nonce, or as an existing
+    /// registration, anyway, so it still counts against the budget as it always has, and
+    /// recognizing it changes no block's outcome. Copies of a message are all sent, and the
+    /// destination delivers only the first, by its nonce.
+    pub fn apply_transactions(
+        &mut self,
+        transactions: Vec<impl Into<RollupTransaction>>,
+    ) -> Vec<TransactionOutcome> {
+        let transactions = transactions
+            .into_iter()
+            .map(|txn| (txn.into(), None))
+            .collect();
+        self.apply_forced_and_sequenced(vec![], transactions)
+    }
+
+    /// Apply the transactions `forced` in from L1, each with the L1 block it was enqueued in, and
+    /// then the sequenced `transactions`, as [`apply_transactions`](Self::apply_transactions) does.
+    ///
+    /// Forced transactions are applied in the order they were enqueued, whatever their priority,
+    /// since the sequencer had no say in it, and ahead of the sequenced ones. They are outside the
+    /// block's budget, so that a sequencer filling blocks cannot crowd them out, and so that which
+    /// sequenced transactions fit in the budget, and so which messages are sent, still depends only
+    /// on the namespace: they consume none of it, and their outcomes record no weight. They share
+    /// the block's duplicate check.
+    ///
+    /// Each sequenced transaction comes with the length of the payload it was sequenced in, if it
+    /// was decoded from one, which its [weight](RollupTransaction::weight_of_payload) is charged
+    /// for. Others are charged for their canonical encoding.
+    fn apply_forced_and_sequenced(
+        &mut self,
+        forced: Vec<(RollupTransaction, u64)>,
+        transactions: Vec<(RollupTransaction, Option<usize>)>,
+    ) -> Vec<TransactionOutcome> {
+        let mut transactions = transactions
+            .into_iter()
+            .zip(0..)
+            .map(|((txn, len), index)| (txn, index, len))
+            .collect::<Vec<_>>();
+        // The sort is stable, so equal priorities keep their positions in the block.
+        transactions.sort_by_key(|(txn, _, _)| Reverse(txn.priority()));
+        let num_forced = forced.len();
+        let forced = forced
+            .into_iter()
+            .map(|(txn, l1_block)| (txn, l1_block, None));
+        let (mut outcomes, messages) =
+            self.apply_budgeted_transactions(forced.chain(transactions).collect(), num_forced);
+        self.send_messages(&mut outcomes, messages);
+        outcomes
+    }
+
+    /// Send the outbound messages of a block which fit in its budget, recording its outbox.
+    ///
+    /// `messages` are the messages with the positions of their outcomes in `outcomes`, which are
+    /// completed with what became of them.
+    fn send_messages(
+        &mut self,
+        outcomes: &mut [TransactionOutcome],
+        messages: Vec<(usize, OutboundMessage)>,
+    ) {
+        let (positions, messages): (Vec<_>, Vec<_>) = messages.into_iter().unzip();
+        let results = outbox(self.vm.id().into(), &messages);
+        let sent = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok().cloned())
+            .collect::<Vec<_>>();
+        let commitment = (!sent.is_empty()).then(|| outbox_commitment(&sent));
+        if commitment != self.outbox {
+            self.outbox = commitment;
+            self.commitment.take();
+        }
+        for ((position, txn), result) in positions.into_iter().zip(messages).zip(results) {
+            let outcome = &mut outcomes[position];
+            match result {
+                Ok(sent) => {
+                    tracing::info!(
+                        target: BRIDGE,
+                        "Sent message {} to VM {}",
+                        sent.nonce,
+                        sent.dest_vm
+                    );
+                    outcome.sender = Some(sent.sender);
+                }
+                Err(err) => {
+                    tracing::error!(target: BRIDGE, "Message invalid: {}", err);
+                    outcome.sender = txn.sender().ok();
+                    outcome.error = Some(err.to_string());
+                }
+            }
+        }
+    }
+
+    /// Apply the transactions of a block within its weight budget, in the order given, each with
+    /// the index to record in its outcome and the length of the payload it was sequenced in, if
+    /// known. The first `forced` were forced in from L1, and come with the L1 block they were
+    /// enqueued in instead; they are not charged against the budget.
+    ///
+    /// Messages within the budget are returned with the positions of their outcomes, to be
+    /// [sent](Self::send_messages) together once the budget has been spent.
+    fn apply_budgeted_transactions(
+        &mut self,
+        transactions: Vec<(RollupTransaction, u64, Option<usize>)>,
+        forced: usize,
+    ) -> (Vec<TransactionOutcome>, Vec<(usize, OutboundMessage)>) {
+        let (transactions, indices): (Vec<_>, Vec<_>) = transactions
+            .into_iter()
+            .map(|(txn, index, len)| (txn, (index, len)))
+            .unzip();
+        // Signature recovery does not depend on the state, so it can all be done before the
+        // transactions are applied in order.
+        let recovered = if self.eager_recovery {
+            recover_signers(&transactions, self.recovery_threads)
+        } else {
+            vec![None; transactions.len()]
+        };
+        let max = self.chain_config.max_block_weight;
+        let mut used: Weight = 0;
+        let mut exhausted = false;
+        let mut applied = HashSet::new();
+        let mut messages = vec![];
+        let outcomes = transactions
+            .into_iter()
+            .zip(recovered)
+            .zip(indices)
+            .enumerate()
+            .map(|(position, ((txn, recovered), (index, len)))| {
+                // Forced transactions come with their L1 blocks rather than positions.
+                let (index, l1_block) = if position < forced {
+                    (None, Some(index))
+                } else {
+                    (Some(index), None)
+                };
+                let (hash, txn_weight) = match len {
+                    Some(len) => (txn.hash(), txn.weight_of_payload(len)),
+                    None => txn.hash_and_weight(),
+                };
+                let charged = if l1_block.is_some() { 0 } else { txn_weight };
+                exhausted = exhausted || charged > max - used;
+                let executed = !exhausted;
+                let (weight, result) = if exhausted {
+                    let weight = txn_weight;
+                    (0, Err(RollupError::BlockWeightExhausted { max, weight }))
+                } else {
+                    used += charged;
+                    let result = match (&txn, self.duplicate_of(&hash, &applied)) {
+                        // Messages are sent once the budget has been spent, and copies are all
+                        // sent.
+                        (RollupTransaction::OutboundMessage(txn), _) => {
+                            messages.push((position, txn.clone()));
+                            Ok(())
+                        }
+                        (_, Some(height)) => Err(RollupError::DuplicateTransaction { height }),
+                        (RollupTransaction::MultisigTransfer(txn), None) => {
+                            self.apply_recovered_multisig_transfer(txn, recovered)
+                        }
+                        (txn, None) => self.apply_rollup_transaction(txn),
+                    };
+                    (charged, result)
+                };
+                let duplicate = matches!(result, Err(RollupError::DuplicateTransaction { .. }));
+                if result.is_ok() && !matches!(txn, RollupTransaction::OutboundMessage(_)) {
+                    applied.insert(hash);
+                }
+                let error = match result {
+                    Ok(()) => None,
+                    Err(err) => {
+                        tracing::error!(target: STATE, "Transaction invalid: {}", err);
+                        Some(err.to_string())
+                    }
+                };
+                match txn {
+                    RollupTransaction::Transfer(txn) => TransactionOutcome {
+                        hash,
+                        index,
+                        l1_block,
+                        // The signer of a skipped transfer is not recovered, since that is the
+                        // work the budget limits.
+                        sender: executed.then(|| txn.sender().ok()).flatten(),
+                        transaction: Some(txn.transaction),
+                        multisig: None,
+                        message: None,
+                        min_fee: None,
+                        weight,
+                        error,
+                        duplicate,
+                    },
+                    RollupTransaction::RegisterMultisig(txn) => TransactionOutcome {
+                        hash,
+                        index,
+                        l1_block,
+                        sender: None,
+                        transaction: None,
+                        multisig: txn.config().ok(),
+                        message: None,
+                        min_fee: None,
+                        weight,
+                        error,
+                        duplicate,
+                    },
+                    RollupTransaction::MultisigTransfer(txn) => TransactionOutcome {
+                        hash,
+                        index,
+                        l1_block,
+                        sender: Some(txn.account),
+                        transaction: Some(txn.transaction),
+                        multisig: None,
+                        message: None,
+                        min_fee: None,
+                        weight,
+                        error,
+                        duplicate,
+                    },
+                    RollupTransaction::SetMinFee(txn) => TransactionOutcome {
+                        hash,
+                        index,
+                        l1_block,
+                        sender: executed.then(|| txn.signer().ok()).flatten(),
+                        transaction: None,
+                        multisig: None,
+                        message: None,
+                        min_fee: Some(txn.set_min_fee),
+                        weight,
+                        error,
+                        duplicate,
+                    },
+                    RollupTransaction::OutboundMessage(txn) => TransactionOutcome {
+                        hash,
+                        index,
+                        l1_block,
+                        // The sender of a message within the budget is recovered when it is sent.
+                        sender: None,
+                        transaction: None,
+                        multisig: None,
+                        message: Some(txn.message),
+                        min_fee: None,
+                        weight,
+                        error,
+                        duplicate,
+                    },
+                }
+            })
+            .collect();
+        (outcomes, messages)
+    }
+
+    /// Execute a block, generating a proof and recording a summary of the block.
+    ///
+    /// `timestamp` is clamped to the timestamp of the previous block, so that block times never go
+    /// backwards even when some come from the executor's clock. `total_payloads` is the number of
+    /// transactions in the whole HotShot block, for every rollup. `inputs` are what the block picks
+    /// up from outside its namespace: its [pending deposits](Self::pending_deposits) and incoming
+    /// messages, which are credited and delivered before its transactions are applied, and its
+    /// [pending forced transactions](Self::pending_forced), which are applied ahead of them.
+    /// Each phase of the execution is timed by `timer`.
+    pub(crate) fn execute_block(
+        &mut self,
+        nmt_root: NMTRoot,
+        namespace_proof: NamespaceProofType,
+        timestamp: BlockTimestamp,
+        total_payloads: u64,
+        inputs: &BlockInputs,
+        timer: &PhaseTimer,
+    ) -> (Proof, BlockResult) {
+        let result = self.record_block_timed(
+            &nmt_root,
+            &namespace_proof,
+            timestamp,
+            total_payloads,
+            inputs,
+            timer,
+        );
+        timer.start(Phase::Prove);
+        let proof = Proof::generate(
+            nmt_root,
+            self.commit(),
+            self.prev_state_commitment.unwrap(),
+            namespace_proof,
+            &self.vm,
+        );
+
+        self.recent_proofs
+            .insert(result.summary.height, proof.clone());
+        timer.finish();
+        (proof, result)
+    }
+
+    /// Apply a block and record its summary and receipts, as [`execute_block`](Self::execute_block)
+    /// does, but without generating a proof.
+    pub(crate) fn record_block(
+        &mut self,
+        nmt_root: &NMTRoot,
+        namespace_proof: &NamespaceProofType,
+        timestamp: BlockTimestamp,
+        total_payloads: u64,
+        inputs: &BlockInputs,
+    ) -> BlockResult {
+        self.record_block_timed(
+            nmt_root,
+            namespace_proof,
+            timestamp,
+            total_payloads,
+            inputs,
+            &PhaseTimer::default(),
+        )
+    }
+
+    fn record_block_timed(
+        &mut self,
+        nmt_root: &NMTRoot,
+        namespace_proof: &NamespaceProofType,
+        mut timestamp: BlockTimestamp,
+        total_payloads: u64,
+        inputs: &BlockInputs,
+        timer: &PhaseTimer,
+    ) -> BlockResult {
+        let height = self.block_height;
+        timer.start(Phase::Decode);
+        let (counts, rollup_txns) = self.decode_payloads(namespace_proof.get_namespace_leaves());
+        timer.start(Phase::Apply);
+        let (mut payloads, transactions) =
+            self.apply_decoded(nmt_root.commit(), inputs, counts, rollup_txns);
+        timer.start(Phase::Commit);
+        payloads.total_payloads = total_payloads.max(payloads.vm_payloads);
+
+        if let Some((_, prev)) = self.recent_summaries.last_key_value() {
+            timestamp.seconds = timestamp.seconds.max(prev.timestamp.seconds);
+        }
+        let result = BlockResult {
+            summary: BlockSummary {
+                height,
+                timestamp,
+                state_commitment: self.commit(),
+                payloads,
+                weight: transactions.iter().map(|txn| txn.weight).sum(),
+                outbox: self.outbox,
+                l1_tx: None,
+            },
+            transactions,
+        };
+        self.insert_history(&result);
+        result
+    }
+
+    /// Execute `block` on a copy of `pre_state`, exactly as the executor executed it, but without
+    /// generating a proof.
+    ///
+    /// `pre_state` is left untouched, so a block can be replayed against a snapshot, or a state
+    /// still in use, as often as needed. If `pre_state` is the state the executor executed the
+    /// block on, the result matches what it recorded for the block.
+    pub fn replay_block(pre_state: &State, block: &BlockData) -> BlockResult {
+        Self::replay(pre_state, block).1
+    }
+
+    /// Replay `block` as [`replay_block`](Self::replay_block) does, also returning the state after
+    /// it.
+    pub(crate) fn replay(pre_state: &State, block: &BlockData) -> (State, BlockResult) {
+        let mut state = pre_state.clone();
+        let result = state.record_block(
+            &block.nmt_root,
+            &block.namespace_proof,
+            block.timestamp,
+            block.total_payloads,
+            &block.inputs,
+        );
+        (state, result)
+    }
+
+    /// Every balance, of the native token or of a deposited asset, which differs in `after` from
+    /// this state, by asset and then by address.
+    pub fn balance_changes(&self, after: &State) -> Vec<BalanceChange> {
+        let mut changes = vec![];
+        if !Arc::ptr_eq(&self.accounts, &after.accounts) {
+            let addresses = self
+                .accounts
+                .keys()
+                .chain(after.accounts.keys())
+                .collect::<BTreeSet<_>>();
+            for address in addresses {
+                let (before, after) = (self.get_balance(address), after.get_balance(address));
+                if before != after {
+                    changes.push(BalanceChange {
+                        address: *address,
+                        asset: None,
+                        before,
+                        after,
+                    });
+                }
+            }
+        }
+        let holdings = self
+            .asset_balances
+            .iter()
+            .chain(&after.asset_balances)
+            .flat_map(|(asset, balances)| balances.keys().map(move |address| (asset, address)))
+            .collect::<BTreeSet<_>>();
+        for (asset, address) in holdings {
+            let before = self.asset_balance(asset, address);
+            let after = after.asset_balance(asset, address);
+            if before != after {
+                changes.push(BalanceChange {
+                    address: *address,
+                    asset: Some(*asset),
+                    before,
+                    after,
+                });
+            }
+        }
+        changes
+    }
+
+    /// Record the summary and receipts of a block executed before this state was restored from a
+    /// snapshot which lacks them.
+    ///
+    /// Only fills gaps: the block must be below the current height, and whatever is already
+    /// recorded or has been pruned is left alone. Accounts are never touched. Returns whether
+    /// anything was recorded.
+    pub(crate) fn backfill_block(&mut self, result: &BlockResult) -> bool {
+        let height = result.summary.height;
+        if height >= self.block_height {
+            return false;
+        }
+        let mut recorded = false;
+        if height >= self.pruned.history && !self.recent_summaries.contains_key(&height) {
+            self.insert_summary(&result.summary);
+            recorded = true;
+        }
+        if height >= self.pruned.receipts && !self.recent_receipts.contains_key(&height) {
+            self.insert_receipts(height, &result.transactions);
+            recorded = true;
+        }
+        recorded
+    }
+
+    /// Record the proof of the block at `height`, filling a gap as
+    /// [`backfill_block`](Self::backfill_block) does. Returns whether it was recorded.
+    pub(crate) fn backfill_proof(&mut self, height: u64, proof: &Proof) -> bool {
+        if height >= self.block_height
+            || height < self.pruned.proofs
+            || self.recent_proofs.contains_key(&height)
+        {
+            return false;
+        }
+        self.recent_proofs.insert(height, proof.clone());
+        true
+    }
+
+    /// Set the native balance of `address` without a transaction, leaving the supply and the cached
+    /// commitment as they were, as a bug in a transition could.
+    #[cfg(test)]
+    pub(crate) fn corrupt_balance(&mut self, address: Address, balance: Amount) {
+        Arc::make_mut(&mut self.accounts)
+            .entry(address)
+            .or_default()
+            .balance = balance;
+    }
+
+    /// Set the balance of `address` in `asset` as [`corrupt_balance`](Self::corrupt_balance) does.
+    #[cfg(test)]
+    pub(crate) fn corrupt_asset_balance(
+        &mut self,
+        asset: Address,
+        address: Address,
+        balance: Amount,
+    ) {
+        self.asset_balances
+            .entry(asset)
+            .or_default()
+            .insert(address, balance);
+    }
+
+    /// Set the nonce of `address` as [`corrupt_balance`](Self::corrupt_balance) does.
+    #[cfg(test)]
+    pub(crate) fn corrupt_nonce(&mut self, address: Address, nonce: Nonce) {
+        Arc::make_mut(&mut self.accounts)
+            .entry(address)
+            .or_default()
+            .nonce = nonce;
+    }
+
+    /// Forget the summaries and receipts of executed blocks, as in a snapshot written before
+    /// they were kept.
+    #[cfg(test)]
+    pub(crate) fn forget_history(&mut self) {
+        self.recent_summaries.clear();
+        self.recent_receipts.clear();
+    }
+
+    /// Record a placeholder summary, an empty list of receipts and a proof for every block below
+    /// the current height, as if each had been executed.
+    #[cfg(test)]
+    pub(crate) fn fill_history(&mut self, block: Commitment<NMTRoot>) {
+        let commitment = self.commit();
+        for height in 0..self.block_height {
+            self.backfill_block(&BlockResult {
+                summary: BlockSummary {
+                    height,
+                    timestamp: BlockTimestamp {
+                        seconds: height,
+                        source: TimestampSource::Observed,
+                    },
+                    state_commitment: commitment,
+                    payloads: Default::default(),
+                    weight: 0,
+                    outbox: None,
+                    l1_tx: None,
+                },
+                transactions: vec![],
+            });
+            if height >= self.pruned.proofs {
+                self.recent_proofs.insert(
+                    height,
+                    Proof {
+                        block,
+                        old_state: commitment,
+                        new_state: commitment,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Apply a block of `transactions` and record its summary, receipts and proof, as
+    /// [`execute_block`](Self::execute_block) does, without a sequencer block.
+    #[cfg(test)]
+    pub(crate) fn execute_transactions(
+        &mut self,
+        transactions: Vec<RollupTransaction>,
+    ) -> BlockResult {
+        self.execute_transactions_with_inputs(&BlockInputs::default(), transactions)
+    }
+
+    /// Execute a block as [`execute_transactions`](Self::execute_transactions) does, first taking
+    /// in `inputs` as [`apply_block_with_inputs`](Self::apply_block_with_inputs) does.
+    #[cfg(test)]
+    pub(crate) fn execute_transactions_with_inputs(
+        &mut self,
+        inputs: &BlockInputs,
+        transactions: Vec<RollupTransaction>,
+    ) -> BlockResult {
+        let height = self.block_height;
+        let block = sequencer_utils::u256_to_commitment(height.into()).unwrap();
+        let old_state = self.commit();
+        let decoded = transactions.len() as u64;
+        let transactions = self.apply_block_with_inputs(block, inputs, transactions);
+        let result = BlockResult {
+            summary: BlockSummary {
+                height,
+                timestamp: BlockTimestamp {
+                    seconds: height,
+                    source: TimestampSource::Observed,
+                },
+                state_commitment: self.commit(),
+                payloads: BlockCounts {
+                    total_payloads: decoded,
+                    vm_payloads: decoded,
+                    decoded,
+                    applied: transactions.iter().filter(|txn| txn.applied()).count() as u64,
+                    ..Default::default()
+                },
+                weight: transactions.iter().map(|txn| txn.weight).sum(),
+                outbox: None,
+                l1_tx: None,
+            },
+            transactions,
+        };
+        self.insert_history(&result);
+        self.recent_proofs.insert(
+            height,
+            Proof {
+                block,
+                old_state,
+                new_state: self.commit(),
+            },
+        );
+        result
+    }
+
+    fn insert_history(&mut self, result: &BlockResult) {
+        self.insert_summary(&result.summary);
+        self.insert_receipts(result.summary.height, &result.transactions);
+    }
+
+    fn insert_summary(&mut self, summary: &BlockSummary) {
+        self.recent_summaries
+            .insert(summary.height, summary.clone());
+    }
+
+    fn insert_receipts(&mut self, height: u64, transactions: &[TransactionOutcome]) {
+        if height >= self.pruned.receipts {
+            self.recent_receipts.insert(height, transactions.to_vec());
+        }
+    }
+}
+
+/// The signers of a multisig transfer, if they were recovered before it was applied.
+type Recovered = Option<Result<Vec<Address>, RollupError>>;
+
+/// Recover the signers of each transaction before any is applied.
+///
+/// The sender of each transfer is cached in the transaction, and the signers of each multisig
+/// transfer are returned, in the order of `transactions`. With the `parallel` feature, the work is
+/// spread over `threads` threads, or one per core if `threads` is zero. Each signature is
+/// recovered exactly as it would be when its transaction is applied, so the results do not depend
+/// on the number of threads.
+fn recover_signers(transactions: &[RollupTransaction], threads: usize) -> Vec<Recovered> {
+    let recover = |chunk: &[RollupTransaction]| {
+        chunk
+            .iter()
+            .map(|txn| match txn {
+                RollupTransaction::Transfer(txn) => {
+                    let _ = txn.sender();
+                    None
+                }
+                RollupTransaction::RegisterMultisig(_)
+                | RollupTransaction::OutboundMessage(_)
+                | RollupTransaction::SetMinFee(_) => None,
+                RollupTransaction::MultisigTransfer(txn) => Some(txn.signers()),
+            })
+            .collect::<Vec<_>>()
+    };
+    let threads = match threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        threads => threads,
+    };
+    if !cfg!(feature = "parallel") || threads == 1 || transactions.len() < 2 {
+        return recover(transactions);
+    }
+    let chunk_size = (transactions.len() + threads - 1) / threads;
+    std::thread::scope(|scope| {
+        let chunks = transactions
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || recover(chunk)))
+            .collect::<Vec<_>>();
+        chunks
+            .into_iter()
+            .flat_map(|chunk| chunk.join().expect("signature recovery does not panic"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::balance_history::BalancePoint;
+    use crate::seed::SeedIdentity;
+    use crate::testing::fixtures::{Devnet, FixtureWallet};
+    use crate::transaction::{Transaction, DEFAULT_VM_ID, WEIGHT_PER_BYTE};
+
+    use ethers::signers::{LocalWallet, Signer};
+
+    use super::*;
+
+    #[test]
+    fn test_check_transfer() {
+        let alice = Address::random();
+        let asset = Address::random();
+        let mut state = State::from_initial_balances([(alice, 100)], RollupVM::new(1.into()));
+        state.chain_config.assets.insert(asset);
+        let transaction = Transaction {
+            amount: 90,
+            destination: Address::random(),
+            nonce: 1,
+            priority: 10,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        assert_eq!(state.check_transfer(&alice, &transaction), []);
+
+        // Every problem is reported, not only the first.
+        let problems = state.check_transfer(
+            &alice,
+            &Transaction {
+                nonce: 3,
+                priority: 11,
+                vm_id: 2,
+                ..transaction.clone()
+            },
+        );
+        assert_eq!(
+            problems,
+            [
+                RollupError::WrongVm {
+                    expected: 1,
+                    actual: 2
+                },
+                RollupError::InvalidNonce {
+                    address: alice,
+                    expected: 1,
+                    actual: 3
+                },
+                RollupError::InsufficientBalance { address: alice },
+            ]
+        );
+
+        // Alice holds none of the asset, and the rollup has no other.
+        let problems = state.check_transfer(
+            &alice,
+            &Transaction {
+                asset: Some(asset),
+                ..transaction.clone()
+            },
+        );
+        assert_eq!(
+            problems,
+            [RollupError::InsufficientBalance { address: alice }]
+        );
+        let unknown = Address::random();
+        let problems = state.check_transfer(
+            &alice,
+            &Transaction {
+                asset: Some(unknown),
+                ..transaction
+            },
+        );
+        assert_eq!(problems, [RollupError::UnknownAsset { asset: unknown }]);
+    }
+
+    #[async_std::test]
+    async fn test_invalid_destination() {
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let contract = Address::random();
+        let genesis = |reject_zero_destination| {
+            State::from_initial_balances([(alice.address(), 100)], RollupVM::new(1.into()))
+                .with_chain_config(ChainConfig {
+                    reject_zero_destination,
+                    denied_destinations: [contract].into(),
+                    ..Default::default()
+                })
+        };
+        let transfer = |destination, nonce| {
+            SignedTransaction::new_with_wallet(
+                Transaction {
+                    amount: 10,
+                    destination,
+                    nonce,
+                    priority: 0,
+                    vm_id: DEFAULT_VM_ID,
+                    asset: None,
+                },
+                &alice,
+            )
+        };
+        let to_zero = transfer(Address::zero(), 1).await;
+
+        // With the rule, nothing is burned and the nonce is not used up.
+        let mut state = genesis(true);
+        assert_eq!(
+            state.apply_transaction(&to_zero),
+            Err(RollupError::InvalidDestination {
+                address: Address::zero()
+            })
+        );
+        assert_eq!(state.get_balance(&alice.address()), 100);
+        assert_eq!(state.get_nonce(&alice.address()), 0);
+        assert_eq!(
+            state.check_transfer(&alice.address(), &to_zero.transaction),
+            [RollupError::InvalidDestination {
+                address: Address::zero()
+            }]
+        );
+        let to_contract = transfer(contract, 1).await;
+        assert_eq!(
+            state.apply_transaction(&to_contract),
+            Err(RollupError::InvalidDestination { address: contract })
+        );
+        assert_eq!(state.commit(), genesis(true).commit());
+
+        // Without it, a transfer to the zero address burns its amount as before.
+        let mut state = genesis(false);
+        state.apply_transaction(&to_zero).unwrap();
+        assert_eq!(state.get_balance(&alice.address()), 90);
+        assert_eq!(state.get_balance(&Address::zero()), 10);
+        assert_eq!(
+            state.apply_transaction(&transfer(contract, 2).await),
+            Err(RollupError::InvalidDestination { address: contract })
+        );
+
+        // The rule is part of the committed configuration.
+        assert_ne!(genesis(true).commit(), genesis(false).commit());
+    }
+
+    #[async_std::test]
+    async fn test_wrong_vm() {
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let bob = LocalWallet::new(&mut rng);
+        let genesis = |vm: u64| {
+            State::from_initial_balances([(alice.address(), 100)], RollupVM::new(vm.into()))
+        };
+        let transaction = Transaction {
+            amount: 10,
+            destination: bob.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: 5,
+            asset: None,
+        };
+        let signed = SignedTransaction::new_with_wallet(transaction.clone(), &alice).await;
+
+        // A transfer signed for VM 5 does not apply on VM 7, where the sender's nonce is untouched.
+        let mut other = genesis(7);
+        assert_eq!(
+            other.apply_transaction(&signed),
+            Err(RollupError::WrongVm {
+                expected: 7,
+                actual: 5
+            })
+        );
+        assert_eq!(other.get_nonce(&alice.address()), 0);
+        assert_eq!(other.commit(), genesis(7).commit());
+
+        // It does on VM 5.
+        let mut state = genesis(5);
+        state.apply_transaction(&signed).unwrap();
+        assert_eq!(state.get_balance(&bob.address()), 10);
+
+        // The same transfer for VM 7 is a different transaction, so it has a different hash.
+        let for_other = SignedTransaction::new_with_wallet(
+            Transaction {
+                vm_id: 7,
+                ..transaction
+            },
+            &alice,
+        )
+        .await;
+        assert_ne!(for_other.hash(), signed.hash());
+        other.apply_transaction(&for_other).unwrap();
+        assert_eq!(other.get_balance(&bob.address()), 10);
+    }
+
+    #[async_std::test]
+    async fn smoke_test() {
+        let mut rng = rand::thread_rng();
+        let vm = RollupVM::new(1.into());
+        let alice = LocalWallet::new(&mut rng);
+        let bob = LocalWallet::new(&mut rng);
+        let seed_data = [(alice.address(), 100), (bob.address(), 100)];
+        let mut state = State::from_initial_balances(seed_data, vm);
+        let mut transaction = Transaction {
+            amount: 110,
+            destination: bob.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+
+        // Try to overspend
+        let mut signed_transaction =
+            SignedTransaction::new_with_wallet(transaction.clone(), &alice).await;
+        let err = state
+            .clone()
+            .apply_transaction(&signed_transaction)
+            .expect_err("Invalid transaction should throw error.");
+        assert_eq!(
+            err,
+            RollupError::InsufficientBalance {
+                address: alice.address()
+            }
+        );
+
+        // Now spend an valid amount
+        transaction.amount = 50;
+        signed_transaction = SignedTransaction::new_with_wallet(transaction, &alice).await;
+        state
+            .apply_transaction(&signed_transaction)
+            .expect("Valid transaction should transition state");
+        let bob_balance = state.get_balance(&bob.address());
+        assert_eq!(bob_balance, 150);
+
+        // Now try to replay the transaction
+        let err = state
+            .apply_transaction(&signed_transaction)
+            .expect_err("Invalid transaction should throw error.");
+        assert_eq!(
+            err,
+            RollupError::InvalidNonce {
+                address: alice.address(),
+                expected: 2,
+                actual: 1,
+            }
+        );
+    }
+
+    #[async_std::test]
+    async fn test_priority_ordering() {
+        let mut rng = rand::thread_rng();
+        let vm = RollupVM::new(1.into());
+        let alice = LocalWallet::new(&mut rng);
+        let bob = LocalWallet::new(&mut rng);
+        let carol = LocalWallet::new(&mut rng);
+        let genesis = State::from_initial_balances([(alice.address(), 100)], vm);
+
+        // Two spends of the same balance, of which only the first to execute can succeed.
+        let spend = |destination: Address, priority| {
+            SignedTransaction::new_with_wallet(
+                Transaction {
+                    amount: 80,
+                    destination,
+                    nonce: 1,
+                    priority,
+                    vm_id: DEFAULT_VM_ID,
+                    asset: None,
+                },
+                &alice,
+            )
+        };
+        let to_bob = spend(bob.address(), 0).await;
+        let to_carol = spend(carol.address(), 5).await;
+
+        // The higher priority executes first, even though it was included later.
+        let mut state = genesis.clone();
+        let outcomes = state.apply_transactions(vec![to_bob.clone(), to_carol.clone()]);
+        assert_eq!(outcomes[0].hash, to_carol.hash());
+        assert!(outcomes[0].applied());
+        assert!(!outcomes[1].applied());
+        assert_eq!(state.get_balance(&carol.address()), 80);
+        assert_eq!(state.get_balance(&bob.address()), 0);
+        // The tip is burned along with the amount.
+        assert_eq!(state.get_balance(&alice.address()), 15);
+
+        // The order is the same wherever the transactions were included.
+        let mut reordered = genesis.clone();
+        reordered.apply_transactions(vec![to_carol, to_bob.clone()]);
+        assert_eq!(reordered.commit(), state.commit());
+
+        // Equal priorities execute in block order.
+        let to_carol = spend(carol.address(), 0).await;
+        let mut state = genesis;
+        let outcomes = state.apply_transactions(vec![to_bob, to_carol]);
+        assert!(outcomes[0].applied());
+        assert!(!outcomes[1].applied());
+        assert_eq!(state.get_balance(&bob.address()), 80);
+        assert_eq!(state.get_balance(&alice.address()), 20);
+    }
+
+    #[async_std::test]
+    async fn test_transaction_positions() {
+        let mut rng = rand::thread_rng();
+        let vm = RollupVM::new(1.into());
+        let alice = LocalWallet::new(&mut rng);
+        let bob = LocalWallet::new(&mut rng);
+        let mut state =
+            State::from_initial_balances([(alice.address(), 100), (bob.address(), 100)], vm);
+        async fn transfer(wallet: &LocalWallet, priority: Amount) -> SignedTransaction {
+            let transaction = Transaction {
+                amount: 10,
+                destination: Address::random(),
+                nonce: 1,
+                priority,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            };
+            SignedTransaction::new_with_wallet(transaction, wallet).await
+        }
+        let low = transfer(&alice, 0).await;
+        let high = transfer(&bob, 5).await;
+
+        // Payloads which are not transactions of the rollup still take up positions.
+        let payloads = [
+            vm.wrap(&low.clone().into()),
+            sequencer::Transaction::new(vm.id(), b"junk".to_vec()),
+            RollupVM::new(2.into()).wrap(&low.clone().into()),
+            vm.wrap(&high.clone().into()),
+        ];
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        let (payloads, outcomes) = state.apply_payloads(block, &payloads, &Default::default());
+
+        // The outcomes are in the order of execution, but each has its position as sequenced.
+        assert_eq!(outcomes[0].hash, high.hash());
+        assert_eq!(outcomes[0].index, Some(3));
+        assert_eq!(outcomes[1].hash, low.hash());
+        assert_eq!(outcomes[1].index, Some(0));
+
+        state.insert_history(&BlockResult {
+            summary: BlockSummary {
+                height: 0,
+                timestamp: BlockTimestamp {
+                    seconds: 0,
+                    source: TimestampSource::Observed,
+                },
+                state_commitment: state.commit(),
+                payloads,
+                weight: 0,
+                outbox: None,
+                l1_tx: None,
+            },
+            transactions: outcomes,
+        });
+        assert_eq!(state.transaction_index(0, &high.hash()), Some(3));
+        assert_eq!(state.transaction_index(0, &low.hash()), Some(0));
+        assert_eq!(state.transaction_index(1, &low.hash()), None);
+    }
+
+    #[async_std::test]
+    async fn test_duplicates() {
+        let mut rng = rand::thread_rng();
+        let vm = RollupVM::new(1.into());
+        let alice = LocalWallet::new(&mut rng);
+        let bob = LocalWallet::new(&mut rng);
+        let genesis = State::from_initial_balances([(alice.address(), 100)], vm);
+        let transfer = |nonce| {
+            SignedTransaction::new_with_wallet(
+                Transaction {
+                    amount: 10,
+                    destination: bob.address(),
+                    nonce,
+                    priority: 0,
+                    vm_id: DEFAULT_VM_ID,
+                    asset: None,
+                },
+                &alice,
+            )
+        };
+        let block = |height: u64| sequencer_utils::u256_to_commitment(height.into()).unwrap();
+        let first = transfer(1).await;
+
+        // The same payload twice in a block is applied once, and the copy is counted as such.
+        let mut state = genesis.clone();
+        let payload = vm.wrap(&first.clone().into());
+        let counts = state.apply_block_payloads(block(0), [&payload, &payload]);
+        assert_eq!(
+            (counts.decoded, counts.applied, counts.duplicates),
+            (2, 1, 1)
+        );
+        assert_eq!(state.get_balance(&bob.address()), 10);
+
+        // Its receipt says which block applied the original, and it pays the weight it did when it
+        // failed on its nonce, so the block's outcome is what it was.
+        let mut replay = genesis;
+        let outcomes =
+            replay.apply_block_with_transactions(block(0), vec![first.clone().into(); 2]);
+        assert!(outcomes[0].applied() && !outcomes[0].duplicate);
+        assert!(outcomes[1].duplicate);
+        assert_eq!(
+            outcomes[1].error,
+            Some(RollupError::DuplicateTransaction { height: 0 }.to_string())
+        );
+        assert_eq!(outcomes[1].weight, outcomes[0].weight);
+        assert_eq!(replay.commit(), state.commit());
+
+        // A copy in a later block is a duplicate too, but a transfer which failed is not when it is
+        // sequenced again, since it may since have become valid.
+        let early = transfer(3).await;
+        let outcomes = state.apply_block_with_transactions(
+            block(1),
+            vec![first.clone().into(), early.clone().into()],
+        );
+        assert!(outcomes[0].duplicate);
+        assert!(!outcomes[1].applied() && !outcomes[1].duplicate);
+        let outcomes = state
+            .apply_block_with_transactions(block(2), vec![transfer(2).await.into(), early.into()]);
+        assert!(outcomes.iter().all(TransactionOutcome::applied));
+        assert_eq!(state.get_balance(&bob.address()), 30);
+
+        // Once the block which applied it is no longer recent, a copy fails on its nonce again.
+        for height in 3..3 + RECENT_BLOCKS as u64 {
+            state.apply_block_with_transactions(block(height), vec![]);
+        }
+        let height = state.block_height();
+        let outcomes = state.apply_block_with_transactions(block(height), vec![first.into()]);
+        assert!(!outcomes[0].duplicate);
+        assert_eq!(
+            outcomes[0].error,
+            Some(
+                RollupError::InvalidNonce {
+                    address: alice.address(),
+                    expected: 4,
+                    actual: 1,
+                }
+                .to_string()
+            )
+        );
+    }
+
+    #[async_std::test]
+    async fn test_multisig() {
+        let mut rng = rand::thread_rng();
+        let vm = RollupVM::new(1.into());
+        let signers = (0..3)
+            .map(|_| LocalWallet::new(&mut rng))
+            .collect::<Vec<_>>();
+        let bob = LocalWallet::new(&mut rng);
+        let config =
+            MultisigConfig::new(signers.iter().map(|wallet| wallet.address()).collect(), 2)
+                .unwrap();
+        let account = config.address();
+        // The account can be funded before it is registered.
+        let mut state = State::from_initial_balances([(account, 100)], vm);
+        let genesis = state.commit();
+
+        let outcomes = state.apply_transactions(vec![RegisterMultisig::from(&config)]);
+        assert!(outcomes[0].applied());
+        assert_eq!(outcomes[0].multisig.as_ref(), Some(&config));
+        assert_eq!(state.multisig(&account), Some(&config));
+        // The configuration is part of the state commitment.
+        assert_ne!(state.commit(), genesis);
+        assert_eq!(
+            state.apply_multisig_registration(&RegisterMultisig::from(&config)),
+            Err(RollupError::MultisigAlreadyRegistered { address: account })
+        );
+
+        let transaction = Transaction {
+            amount: 30,
+            destination: bob.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let mut signatures = vec![];
+        for wallet in &signers {
+            signatures.push(
+                MultisigTransfer::sign(account, &transaction, wallet)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let spend = |signatures: Vec<_>| MultisigTransfer {
+            account,
+            transaction: transaction.clone(),
+            signatures,
+        };
+
+        // One signature is not enough.
+        assert_eq!(
+            state.apply_multisig_transfer(&spend(vec![signatures[0]])),
+            Err(RollupError::BelowThreshold {
+                address: account,
+                threshold: 2,
+                signatures: 1
+            })
+        );
+        // Nor is the same signature twice.
+        assert_eq!(
+            state.apply_multisig_transfer(&spend(vec![signatures[0], signatures[0]])),
+            Err(RollupError::DuplicateSigner {
+                address: signers[0].address()
+            })
+        );
+        assert_eq!(state.get_balance(&account), 100);
+
+        // Two of the three signers can spend.
+        let outcomes = state.apply_transactions(vec![spend(vec![signatures[2], signatures[0]])]);
+        assert!(outcomes[0].applied());
+        assert_eq!(outcomes[0].sender, Some(account));
+        assert_eq!(state.get_balance(&account), 70);
+        assert_eq!(state.get_balance(&bob.address()), 30);
+        assert_eq!(state.get_nonce(&account), 1);
+
+        // No single signer controls the account.
+        let signed = SignedTransaction::new_with_wallet(
+            Transaction {
+                nonce: 2,
+                ..transaction
+            },
+            &signers[0],
+        )
+        .await;
+        state.apply_transaction(&signed).unwrap_err();
+        assert_eq!(state.get_balance(&account), 70);
+    }
+
+    #[async_std::test]
+    async fn test_block_weight_budget() {
+        let mut rng = rand::thread_rng();
+        let vm = RollupVM::new(1.into());
+        let wallets = (0..4)
+            .map(|_| LocalWallet::new(&mut rng))
+            .collect::<Vec<_>>();
+        let genesis =
+            State::from_initial_balances(wallets.iter().map(|wallet| (wallet.address(), 1000)), vm);
+        let mut transactions = vec![];
+        for (i, wallet) in wallets.iter().enumerate() {
+            let transaction = Transaction {
+                // The third transfer encodes a longer amount, so it is heavier than the fourth.
+                amount: if i == 2 { 100 } else { 1 },
+                destination: Address::random(),
+                nonce: 1,
+                priority: 0,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            };
+            transactions.push(SignedTransaction::new_with_wallet(transaction, wallet).await);
+        }
+        let weights = transactions
+            .iter()
+            .map(|txn| RollupTransaction::from(txn.clone()).weight())
+            .collect::<Vec<_>>();
+        assert!(weights[2] > weights[3]);
+        let three = weights[..3].iter().sum::<Weight>();
+
+        let apply = |max_block_weight| {
+            let mut state = genesis.clone().with_chain_config(ChainConfig {
+                max_block_weight,
+                ..Default::default()
+            });
+            let outcomes = state.apply_transactions(transactions.clone());
+            (state, outcomes)
+        };
+        let applied = |outcomes: &[TransactionOutcome]| {
+            outcomes
+                .iter()
+                .map(TransactionOutcome::applied)
+                .collect::<Vec<_>>()
+        };
+
+        // A budget of exactly three transactions executes three and skips the fourth.
+        let (state, outcomes) = apply(three);
+        assert_eq!(applied(&outcomes), [true, true, true, false]);
+        assert_eq!(
+            outcomes
+                .iter()
+                .map(|outcome| outcome.weight)
+                .sum::<Weight>(),
+            three
+        );
+        assert_eq!(outcomes[3].weight, 0);
+        assert_eq!(outcomes[3].sender, None);
+        assert_eq!(
+            outcomes[3].error,
+            Some(
+                RollupError::BlockWeightExhausted {
+                    max: three,
+                    weight: weights[3]
+                }
+                .to_string()
+            )
+        );
+        assert_eq!(state.get_balance(&wallets[3].address()), 1000);
+
+        // One unit less and the third no longer fits. The fourth would fit in what is left, but
+        // the block is cut off at the first transaction which does not fit.
+        let (under, outcomes) = apply(three - 1);
+        assert_eq!(applied(&outcomes), [true, true, false, false]);
+
+        // The cutoff is the same for every executor.
+        let (again, outcomes_again) = apply(three - 1);
+        assert_eq!(under.commit(), again.commit());
+        assert_eq!(
+            outcomes
+                .iter()
+                .map(|outcome| (outcome.hash, outcome.weight, outcome.error.clone()))
+                .collect::<Vec<_>>(),
+            outcomes_again
+                .iter()
+                .map(|outcome| (outcome.hash, outcome.weight, outcome.error.clone()))
+                .collect::<Vec<_>>()
+        );
+
+        // With room for all four, all are applied.
+        let (over, outcomes) = apply(three + weights[3]);
+        assert_eq!(applied(&outcomes), [true; 4]);
+
+        // The budget is part of the state commitment, unless it is the default.
+        let mut default = genesis.clone();
+        default.apply_transactions(transactions);
+        assert_ne!(default.commit(), over.commit());
+        assert_eq!(
+            genesis.commit(),
+            genesis
+                .clone()
+                .with_chain_config(ChainConfig::default())
+                .commit()
+        );
+    }
+
+    #[async_std::test]
+    async fn test_payload_weight() {
+        let mut rng = rand::thread_rng();
+        let vm = RollupVM::new(1.into());
+        let alice = LocalWallet::new(&mut rng);
+        let mut state = State::from_initial_balances([(alice.address(), 100)], vm);
+        let transaction = Transaction {
+            amount: 10,
+            destination: Address::random(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let txn: RollupTransaction = SignedTransaction::new_with_wallet(transaction, &alice)
+            .await
+            .into();
+
+        // A payload padded with whitespace decodes to the same transaction, but every byte
+        // sequenced is charged.
+        let mut padded = b"    ".to_vec();
+        padded.extend(txn.encode());
+        let payloads = [sequencer::Transaction::new(vm.id(), padded.clone())];
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        let (_, outcomes) = state.apply_payloads(block, &payloads, &Default::default());
+        assert!(outcomes[0].applied());
+        assert_eq!(outcomes[0].hash, txn.hash());
+        assert_eq!(outcomes[0].weight, txn.weight_of_payload(padded.len()));
+        assert_eq!(outcomes[0].weight, txn.weight() + 4 * WEIGHT_PER_BYTE);
+    }
+
+    #[async_std::test]
+    async fn test_eager_recovery() {
+        let mut rng = rand::thread_rng();
+        let vm = RollupVM::new(1.into());
+        let wallets = (0..10)
+            .map(|_| LocalWallet::new(&mut rng))
+            .collect::<Vec<_>>();
+        let genesis =
+            State::from_initial_balances(wallets.iter().map(|wallet| (wallet.address(), 10)), vm);
+        let mut transactions = vec![];
+        for (i, wallet) in wallets.iter().enumerate() {
+            let transaction = Transaction {
+                amount: 5 + i as u64,
+                destination: wallets[(i + 1) % wallets.len()].address(),
+                nonce: 1,
+                priority: (i % 3) as u64,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            };
+            transactions.push(SignedTransaction::new_with_wallet(transaction, wallet).await);
+        }
+
+        // Recovering senders up front gives exactly the same result as recovering them lazily.
+        let mut lazy = genesis.clone();
+        let lazy_outcomes = lazy.apply_transactions(transactions.clone());
+        let mut eager = genesis;
+        eager.set_eager_recovery(true);
+        let eager_outcomes = eager.apply_transactions(transactions);
+        assert_eq!(lazy.commit(), eager.commit());
+        assert_eq!(
+            lazy_outcomes
+                .iter()
+                .map(|outcome| (outcome.hash, outcome.sender, outcome.error.clone()))
+                .collect::<Vec<_>>(),
+            eager_outcomes
+                .iter()
+                .map(|outcome| (outcome.hash, outcome.sender, outcome.error.clone()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[async_std::test]
+    async fn test_parallel_recovery() {
+        let mut rng = rand::thread_rng();
+        let vm = RollupVM::new(1.into());
+        let wallets = (0..12)
+            .map(|_| LocalWallet::new(&mut rng))
+            .collect::<Vec<_>>();
+        let config =
+            MultisigConfig::new(wallets[..3].iter().map(|w| w.address()).collect(), 2).unwrap();
+        let account = config.address();
+        let mut genesis = State::from_initial_balances(
+            wallets
+                .iter()
+                .map(|wallet| (wallet.address(), 10))
+                .chain([(account, 100)]),
+            vm,
+        );
+        genesis
+            .apply_multisig_registration(&RegisterMultisig::from(&config))
+            .unwrap();
+
+        let mut transactions: Vec<RollupTransaction> = vec![];
+        for (i, wallet) in wallets.iter().enumerate() {
+            let transaction = Transaction {
+                amount: 5 + i as u64,
+                destination: wallets[(i + 1) % wallets.len()].address(),
+                nonce: 1,
+                priority: (i % 3) as u64,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            };
+            let signed = SignedTransaction::new_with_wallet(transaction, wallet).await;
+            if i % 5 == 4 {
+                // Signed by someone else.
+                let signature =
+                    *SignedTransaction::new_with_wallet(signed.transaction.clone(), &wallets[0])
+                        .await
+                        .signature();
+                let forged = Transaction {
+                    amount: 1,
+                    ..signed.transaction.clone()
+                };
+                transactions.push(SignedTransaction::from_parts(forged, signature).into());
+            } else {
+                transactions.push(signed.into());
+            }
+        }
+        for (nonce, signers) in [(1, &wallets[..2]), (2, &wallets[..1]), (2, &wallets[1..3])] {
+            let transaction = Transaction {
+                amount: 20,
+                destination: wallets[5].address(),
+                nonce,
+                priority: 1,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            };
+            let mut signatures = vec![];
+            for wallet in signers {
+                signatures.push(
+                    MultisigTransfer::sign(account, &transaction, wallet)
+                        .await
+                        .unwrap(),
+                );
+            }
+            transactions.push(
+                MultisigTransfer {
+                    account,
+                    transaction,
+                    signatures,
+                }
+                .into(),
+            );
+        }
+
+        // Recovering signers up front, on any number of threads, gives exactly the same result as
+        // recovering them as each transaction is applied.
+        let outcomes = |outcomes: Vec<TransactionOutcome>| {
+            outcomes
+                .into_iter()
+                .map(|outcome| (outcome.hash, outcome.sender, outcome.error))
+                .collect::<Vec<_>>()
+        };
+        let mut sequential = genesis.clone();
+        let expected = outcomes(sequential.apply_transactions(transactions.clone()));
+        assert!(expected.iter().any(|(_, _, error)| error.is_some()));
+        assert!(
+            expected
+                .iter()
+                .filter(|(_, _, error)| error.is_none())
+                .count()
+                > 1
+        );
+        for threads in [0, 1, 2, 3, 8, 64] {
+            let mut state = genesis.clone();
+            state.set_eager_recovery(true);
+            state.set_recovery_threads(threads);
+            let actual = outcomes(state.apply_transactions(transactions.clone()));
+            assert_eq!(actual, expected, "{threads} threads");
+            assert_eq!(state.commit(), sequential.commit(), "{threads} threads");
+        }
+    }
+
+    #[async_std::test]
+    async fn test_commitment_cache() {
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let bob = LocalWallet::new(&mut rng);
+        let mut state = State::from_initial_balances(
+            [(alice.address(), 100), (bob.address(), 100)],
+            RollupVM::new(1.into()),
+        );
+        let genesis = state.clone();
+        let genesis_commitment = state.commit();
+
+        // Every change to the state is reflected in its commitment, which always matches a
+        // commitment computed from scratch.
+        let transaction = Transaction {
+            amount: 10,
+            destination: bob.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let signed = SignedTransaction::new_with_wallet(transaction, &alice).await;
+        state.apply_transaction(&signed).unwrap();
+        assert_ne!(state.commit(), genesis_commitment);
+        assert_eq!(state.commit(), state.compute_commitment());
+
+        let config = MultisigConfig::new(vec![alice.address(), bob.address()], 1).unwrap();
+        let after_transfer = state.commit();
+        state
+            .apply_multisig_registration(&RegisterMultisig::from(&config))
+            .unwrap();
+        assert_ne!(state.commit(), after_transfer);
+        assert_eq!(state.commit(), state.compute_commitment());
+
+        let before_block = state.commit();
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        state.apply_block_with_transactions(block, vec![]);
+        assert_ne!(state.commit(), before_block);
+        assert_eq!(state.commit(), state.compute_commitment());
+
+        // A state restored from a snapshot has no encodings of its accounts to update, until its
+        // first block encodes them again.
+        let mut restored: State =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        assert_eq!(restored.commit(), state.commit());
+        let transaction = Transaction {
+            amount: 5,
+            destination: alice.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let signed = SignedTransaction::new_with_wallet(transaction, &bob).await;
+        restored.apply_block_with_transactions(block, vec![signed.into()]);
+        assert_eq!(restored.get_balance(&alice.address()), 95);
+        assert_eq!(restored.commit(), restored.compute_commitment());
+
+        // A clone shares the accounts only until it changes them.
+        assert_eq!(genesis.get_balance(&alice.address()), 100);
+        assert_eq!(genesis.commit(), genesis_commitment);
+        assert_eq!(genesis.commit(), genesis.compute_commitment());
+    }
+
+    #[test]
+    fn test_balance_history() {
+        let mut devnet = Devnet::default();
+        devnet.state_mut().set_balance_checkpoint_interval(3);
+        let bob = FixtureWallet::Bob.address();
+        assert!(devnet.state_mut().watch_balance(bob));
+
+        // Each block has one transfer, changing the balances of two of the three wallets.
+        let mut balances = vec![devnet.state().balances().collect::<BTreeMap<_, _>>()];
+        for _ in 0..10 {
+            devnet.produce_blocks(1, 1);
+            balances.push(devnet.state().balances().collect());
+        }
+
+        // The series matches the recomputed balances at every checkpoint height, and at every
+        // height for the watched account.
+        let history = devnet.state().balance_history();
+        for wallet in FixtureWallet::ALL {
+            let step = if wallet == FixtureWallet::Bob { 1 } else { 3 };
+            let expected = (0..=10)
+                .step_by(step)
+                .map(|height| BalancePoint {
+                    height: height as u64,
+                    balance: balances[height][&wallet.address()],
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                history.series(&wallet.address(), 0, 10, step as u64),
+                expected,
+                "{wallet:?}"
+            );
+        }
+        // Between checkpoints, the others have the balance of their last checkpoint.
+        let alice = FixtureWallet::Alice.address();
+        assert_ne!(balances[1][&alice], balances[0][&alice]);
+        assert_eq!(history.balance_at(&alice, 2), balances[0][&alice]);
+
+        // Pruning the summaries prunes the checkpoints, but not the balances at the horizon.
+        let retention = RetentionConfig {
+            history_blocks: 4,
+            ..Default::default()
+        };
+        devnet.state_mut().prune(&retention, 10);
+        let history = devnet.state().balance_history();
+        assert_eq!(history.since(), 6);
+        assert_eq!(
+            history.series(&bob, 6, 10, 2),
+            [6, 8, 10].map(|height| BalancePoint {
+                height,
+                balance: balances[height as usize][&bob],
+            })
+        );
+        assert_eq!(history.balance_at(&alice, 6), balances[6][&alice]);
+
+        assert!(devnet.state_mut().unwatch_balance(&bob));
+        assert!(!devnet.state_mut().unwatch_balance(&bob));
+    }
+
+    #[test]
+    fn test_balance_changes() {
+        let mut devnet = Devnet::default();
+        let before = devnet.state().clone();
+        assert_eq!(before.balance_changes(&before), []);
+
+        // A transfer changes the balances of its sender and its receiver.
+        devnet.produce_blocks(1, 1);
+        let after = devnet.state();
+        let changes = before.balance_changes(after);
+        assert_eq!(changes.len(), 2, "{changes:?}");
+        for change in changes {
+            assert_eq!(change.asset, None);
+            assert_eq!(change.before, before.get_balance(&change.address));
+            assert_eq!(change.after, after.get_balance(&change.address));
+            assert_ne!(change.before, change.after);
+        }
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut state = State::from_initial_balances([], RollupVM::new(1.into()));
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        for _ in 0..6 {
+            state.apply_block_with_transactions(block, vec![]);
+        }
+        state.fill_history(block);
+        let retention = RetentionConfig {
+            receipts_blocks: 1,
+            history_blocks: 3,
+            proofs_blocks: 0,
+            snapshots: 1,
+        };
+
+        // Nothing at or above the protected height is pruned.
+        let pruned = state.prune(&retention, 4);
+        assert_eq!(
+            pruned,
+            PrunedHeights {
+                receipts: 4,
+                history: 3,
+                proofs: 4,
+            }
+        );
+        assert_eq!(state.pruned(), pruned);
+        assert!(state.block_receipts(3).is_none());
+        assert!(state.block_receipts(4).is_some());
+        assert!(state.block_summary(2).is_none());
+        assert!(state.block_summary(3).is_some());
+        assert!(state.proof(3).is_none());
+        assert!(state.proof(4).is_some());
+
+        // What has been pruned is not filled in again, and pruning never goes backwards.
+        state.fill_history(block);
+        assert!(state.block_receipts(3).is_none());
+        assert!(state.block_summary(2).is_none());
+        assert!(state.proof(3).is_none());
+        assert_eq!(state.prune(&retention, 0), pruned);
+
+        let pruned = state.prune(&retention, 6);
+        assert_eq!(
+            pruned,
+            PrunedHeights {
+                receipts: 5,
+                history: 3,
+                proofs: 6,
+            }
+        );
+        assert!(state.block_receipts(5).is_some());
+        assert!(state.proof(5).is_none());
+        // The commitment does not depend on how much history is kept.
+        assert_eq!(state.commit(), state.compute_commitment());
+    }
+
+    #[test]
+    fn test_proof_transactions() {
+        let mut state = State::from_initial_balances([], RollupVM::new(1.into()));
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        for _ in 0..6 {
+            state.apply_block_with_transactions(block, vec![]);
+        }
+        let proof = |byte, first_block, num_blocks| ProofTransaction {
+            l1_tx: H256::repeat_byte(byte),
+            l1_block: 100 + byte as u64,
+            first_block,
+            num_blocks,
+        };
+
+        // One transaction proves a batch of blocks, and resolves back to all of them.
+        state.record_proof_transaction(proof(1, 0, 3));
+        state.record_proof_transaction(proof(2, 3, 2));
+        let batch = state.proof_transaction(&H256::repeat_byte(1)).unwrap();
+        assert_eq!(batch.heights(), 0..3);
+        for height in 0..3 {
+            assert_eq!(
+                state.block_summary(height).unwrap().l1_tx,
+                Some(H256::repeat_byte(1))
+            );
+        }
+        assert_eq!(state.block_summary(5).unwrap().l1_tx, None);
+        assert_eq!(state.proof_transaction(&H256::repeat_byte(9)), None);
+
+        // A transaction proving the same blocks again, after an L1 reorg, replaces the first.
+        state.record_proof_transaction(proof(3, 3, 2));
+        assert_eq!(state.proof_transaction(&H256::repeat_byte(2)), None);
+        assert_eq!(
+            state.block_summary(4).unwrap().l1_tx,
+            Some(H256::repeat_byte(3))
+        );
+
+        // Transactions are forgotten once every block they proved has been pruned.
+        let retention = RetentionConfig {
+            history_blocks: 3,
+            ..Default::default()
+        };
+        state.prune(&retention, 6);
+        assert_eq!(state.proof_transaction(&H256::repeat_byte(1)), None);
+        assert!(state.proof_transaction(&H256::repeat_byte(3)).is_some());
+        state.record_proof_transaction(proof(4, 0, 2));
+        assert_eq!(state.proof_transaction(&H256::repeat_byte(4)), None);
+    }
+
+    #[test]
+    fn test_deposits() {
+        use crate::deposit::{Deposit, L1Deposits};
+        use ethers::types::U256;
+
+        let [token, unknown, sender, alice] = [(); 4].map(|_| Address::random());
+        let vm = RollupVM::new(1.into());
+        let genesis = State::from_initial_balances([], vm);
+        let mut state = State::from_initial_balances([], vm).with_chain_config(ChainConfig {
+            assets: [token].into(),
+            ..Default::default()
+        });
+        // Listing assets changes the genesis commitment, but only when there are any.
+        assert_ne!(state.commit(), genesis.commit());
+        assert_eq!(
+            genesis.commit(),
+            genesis
+                .clone()
+                .with_chain_config(ChainConfig::default())
+                .commit()
+        );
+        // Without assets there is nothing to deposit.
+        assert_eq!(genesis.pending_deposits(20, Some(10)), None);
+        assert_eq!(state.pending_deposits(20, None), None);
+        assert_eq!(state.pending_deposits(20, Some(10)), Some(1..=10));
+
+        let deposit = |token, amount: U256, l1_block| Deposit {
+            token,
+            sender,
+            recipient: alice,
+            amount,
+            l1_block,
+        };
+        let deposits = L1Deposits {
+            through: 10,
+            deposits: vec![
+                deposit(token, 30.into(), 3),
+                deposit(unknown, 50.into(), 4),
+                deposit(token, U256::from(u64::MAX) + 1, 5),
+                deposit(token, 12.into(), 9),
+            ],
+        };
+        let before = state.commit();
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        state.apply_block_with_inputs(
+            block,
+            &BlockInputs {
+                deposits: deposits.clone(),
+                ..Default::default()
+            },
+            vec![],
+        );
+        assert_eq!(state.asset_balance(&token, &alice), 42);
+        assert_eq!(state.asset_balance(&unknown, &alice), 0);
+        // Deposits are not native balances.
+        assert_eq!(state.get_balance(&alice), 0);
+        assert_ne!(state.commit(), before);
+        assert_eq!(state.commit(), state.compute_commitment());
+
+        // Deposits are credited once, whichever range they are fetched with again.
+        assert_eq!(state.pending_deposits(20, Some(10)), None);
+        assert_eq!(state.pending_deposits(20, Some(12)), Some(11..=12));
+        let mut replayed = state.clone();
+        replayed.credit_deposits(&deposits);
+        replayed.credit_deposits(&L1Deposits {
+            through: 12,
+            deposits: vec![deposit(token, 30.into(), 3), deposit(token, 1.into(), 12)],
+        });
+        assert_eq!(replayed.asset_balance(&token, &alice), 43);
+
+        // A balance which would overflow is left alone.
+        state.credit_deposits(&L1Deposits {
+            through: 11,
+            deposits: vec![deposit(token, u64::MAX.into(), 11)],
+        });
+        assert_eq!(state.asset_balance(&token, &alice), 42);
+    }
+
+    #[async_std::test]
+    async fn test_forced_inclusion() {
+        use crate::forced::{EnqueuedTransaction, L1Queue};
+        use crate::message::{Message, OutboundMessage};
+
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let bob = Address::random();
+        let vm = RollupVM::new(1.into());
+        let genesis = State::from_initial_balances([(alice.address(), 100)], vm);
+        let mut state = genesis.clone().with_chain_config(ChainConfig {
+            forced_inclusion: true,
+            ..Default::default()
+        });
+        // Enabling forced inclusion changes the genesis commitment.
+        assert_ne!(state.commit(), genesis.commit());
+        // Without it there is nothing to look for.
+        assert_eq!(genesis.pending_forced(Some(10)), None);
+        assert_eq!(state.pending_forced(None), None);
+        assert_eq!(state.pending_forced(Some(10)), Some(1..=10));
+
+        let transfer = |nonce, priority| {
+            SignedTransaction::new_with_wallet(
+                Transaction {
+                    amount: 10,
+                    destination: bob,
+                    nonce,
+                    priority,
+                    vm_id: DEFAULT_VM_ID,
+                    asset: None,
+                },
+                &alice,
+            )
+        };
+        let forced = RollupTransaction::from(transfer(1, 0).await);
+        let message = OutboundMessage::new(
+            Message {
+                dest_vm: 2,
+                recipient: bob,
+                nonce: 1,
+                payload: Default::default(),
+            },
+            &alice,
+        )
+        .await
+        .unwrap();
+        let enqueue = |transaction: Vec<u8>, l1_block| EnqueuedTransaction {
+            sender: Address::random(),
+            transaction: transaction.into(),
+            l1_block,
+        };
+        let queue = L1Queue {
+            through: 10,
+            transactions: vec![
+                enqueue(forced.encode(), 3),
+                // Malformed transactions and messages to other rollups are skipped.
+                enqueue(b"not a transaction".to_vec(), 4),
+                enqueue(RollupTransaction::from(message).encode(), 5),
+            ],
+        };
+        // The sequenced transfer pays a higher priority, but the forced one still goes first, and
+        // a sequenced copy of it is a duplicate.
+        let sequenced = RollupTransaction::from(transfer(2, 5).await);
+        let before = state.commit();
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        let outcomes = state.apply_block_with_inputs(
+            block,
+            &BlockInputs {
+                forced: queue.clone(),
+                ..Default::default()
+            },
+            vec![forced.clone(), sequenced.clone()],
+        );
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(outcomes[0].hash, forced.hash());
+        assert_eq!(outcomes[0].l1_block, Some(3));
+        assert!(outcomes[0].applied());
+        assert_eq!(outcomes[1].hash, sequenced.hash());
+        assert_eq!(outcomes[1].l1_block, None);
+        assert!(outcomes[1].applied());
+        assert_eq!(outcomes[2].hash, forced.hash());
+        assert!(outcomes[2].duplicate);
+        assert_eq!(state.get_balance(&bob), 20);
+        assert_eq!(state.forced_through(), 10);
+        assert_ne!(state.commit(), before);
+        assert_eq!(state.commit(), state.compute_commitment());
+
+        // Forced transactions are applied once, whichever range they are fetched with again.
+        assert_eq!(state.pending_forced(Some(10)), None);
+        assert_eq!(state.pending_forced(Some(12)), Some(11..=12));
+        let block = sequencer_utils::u256_to_commitment(2.into()).unwrap();
+        let outcomes = state.apply_block_with_inputs(
+            block,
+            &BlockInputs {
+                forced: L1Queue {
+                    through: 12,
+                    transactions: [queue.transactions, vec![enqueue(forced.encode(), 12)]].concat(),
+                },
+                ..Default::default()
+            },
+            vec![],
+        );
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].l1_block, Some(12));
+        assert!(outcomes[0].duplicate);
+        assert_eq!(state.get_balance(&bob), 20);
+
+        // A rollup without forced inclusion ignores the queue.
+        let mut disabled = genesis.clone();
+        let outcomes = disabled.apply_block_with_inputs(
+            block,
+            &BlockInputs {
+                forced: L1Queue {
+                    through: 3,
+                    transactions: vec![enqueue(forced.encode(), 3)],
+                },
+                ..Default::default()
+            },
+            vec![],
+        );
+        assert!(outcomes.is_empty());
+        assert_eq!(disabled.forced_through(), 0);
+
+        // Forced transactions are outside the weight budget, which only sequenced ones count
+        // against.
+        let mut full = genesis.with_chain_config(ChainConfig {
+            max_block_weight: 0,
+            forced_inclusion: true,
+            ..Default::default()
+        });
+        let outcomes = full.apply_block_with_inputs(
+            block,
+            &BlockInputs {
+                forced: L1Queue {
+                    through: 3,
+                    transactions: vec![enqueue(forced.encode(), 3)],
+                },
+                ..Default::default()
+            },
+            vec![sequenced.clone()],
+        );
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes[0].applied());
+        assert_eq!(outcomes[0].weight, 0);
+        assert!(!outcomes[1].applied());
+        assert_eq!(outcomes[1].weight, 0);
+    }
+
+    #[test]
+    fn test_deposit_confirmations() {
+        let token = Address::random();
+        let vm = RollupVM::new(1.into());
+        let config = ChainConfig {
+            assets: [token].into(),
+            ..Default::default()
+        };
+        let state = State::from_initial_balances([], vm).with_chain_config(config.clone());
+        let confirmed = State::from_initial_balances([], vm).with_chain_config(ChainConfig {
+            deposit_confirmations: 6,
+            ..config
+        });
+        assert_ne!(confirmed.commit(), state.commit());
+
+        // Deposits are credited through the finalized block or the last confirmed one, whichever
+        // is older.
+        assert_eq!(state.pending_deposits(12, Some(10)), Some(1..=10));
+        assert_eq!(confirmed.pending_deposits(12, Some(10)), Some(1..=6));
+        assert_eq!(confirmed.pending_deposits(20, Some(10)), Some(1..=10));
+        // Until the L1 is deeper than the confirmations, nothing is.
+        assert_eq!(confirmed.pending_deposits(6, Some(5)), None);
+        assert_eq!(confirmed.pending_deposits(5, Some(5)), None);
+    }
+
+    #[async_std::test]
+    async fn test_asset_transfers() {
+        use crate::deposit::{Deposit, L1Deposits};
+
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let bob = Address::random();
+
+        let [usdc, weth, unknown] = [(); 3].map(|_| Address::random());
+        let mut state =
+            State::from_initial_balances([(alice.address(), 100)], RollupVM::new(1.into()))
+                .with_chain_config(ChainConfig {
+                    assets: [usdc, weth].into(),
+                    ..Default::default()
+                });
+        let deposit = |token, amount: u64| Deposit {
+            token,
+            sender: Address::random(),
+            recipient: alice.address(),
+            amount: amount.into(),
+            l1_block: 1,
+        };
+        state.credit_deposits(&L1Deposits {
+            through: 1,
+            deposits: vec![deposit(usdc, 50), deposit(weth, 7)],
+        });
+
+        let transfer = |nonce, amount, priority, asset| Transaction {
+            amount,
+            destination: bob,
+            nonce,
+            priority,
+            vm_id: DEFAULT_VM_ID,
+            asset,
+        };
+        let mut transactions = vec![];
+        for transaction in [
+            transfer(1, 20, 3, Some(usdc)),
+            transfer(2, 5, 0, Some(weth)),
+            transfer(3, 1, 0, Some(unknown)),
+            transfer(3, 8, 0, Some(weth)),
+            transfer(3, 10, 0, None),
+        ] {
+            transactions.push(
+                SignedTransaction::new_with_wallet(transaction, &alice)
+                    .await
+                    .into(),
+            );
+        }
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        let receipts = state.apply_block_with_transactions(block, transactions);
+
+        // Each asset moves on its own, and the tip is paid in the native asset.
+        assert_eq!(state.asset_balance(&usdc, &alice.address()), 30);
+        assert_eq!(state.asset_balance(&usdc, &bob), 20);
+        assert_eq!(state.asset_balance(&weth, &alice.address()), 2);
+        assert_eq!(state.asset_balance(&weth, &bob), 5);
+        assert_eq!(state.get_balance(&alice.address()), 87);
+        assert_eq!(state.get_balance(&bob), 10);
+        assert_eq!(
+            state.asset_balances(&bob),
+            [(usdc, 20), (weth, 5)].into_iter().collect()
+        );
+        // Transfers of every asset share the sender's nonce.
+        assert_eq!(state.get_nonce(&alice.address()), 3);
+        assert_eq!(state.commit(), state.compute_commitment());
+
+        // The receipts carry the asset of each transfer. A transfer of an asset the rollup does not
+        // have is rejected, as is one of more than the sender has.
+        assert_eq!(
+            receipts
+                .iter()
+                .map(|receipt| receipt.transaction.as_ref().unwrap().asset)
+                .collect::<Vec<_>>(),
+            [Some(usdc), Some(weth), Some(unknown), Some(weth), None]
+        );
+        assert_eq!(
+            receipts
+                .iter()
+                .map(|receipt| receipt.error.clone())
+                .collect::<Vec<_>>(),
+            [
+                None,
+                None,
+                Some(RollupError::UnknownAsset { asset: unknown }.to_string()),
+                Some(
+                    RollupError::InsufficientBalance {
+                        address: alice.address()
+                    }
+                    .to_string()
+                ),
+                None,
+            ]
+        );
+
+        // Native transfers, which leave out the asset, keep their encoding.
+        assert!(!String::from_utf8(transfer(1, 1, 0, None).encode())
+            .unwrap()
+            .contains("asset"));
+    }
+
+    #[async_std::test]
+    async fn test_min_fee() {
+        use crate::fee::{SetMinFee, SignedSetMinFee};
+
+        let mut rng = rand::thread_rng();
+        let [admin, alice] = [(); 2].map(|_| LocalWallet::new(&mut rng));
+        let genesis =
+            State::from_initial_balances([(alice.address(), 100)], RollupVM::new(1.into()));
+        let mut state = genesis.clone().with_chain_config(ChainConfig {
+            fee_admin: Some(admin.address()),
+            ..Default::default()
+        });
+        // Naming a fee admin changes the genesis commitment, but only when there is one.
+        assert_ne!(state.commit(), genesis.commit());
+
+        let change = |nonce, wallet| {
+            SignedSetMinFee::new(
+                SetMinFee {
+                    value: 5,
+                    effective_height: 2,
+                    nonce,
+                    vm_id: DEFAULT_VM_ID,
+                },
+                wallet,
+            )
+        };
+        let transfer = |nonce, priority| {
+            SignedTransaction::new_with_wallet(
+                Transaction {
+                    amount: 10,
+                    destination: Address::random(),
+                    nonce,
+                    priority,
+                    vm_id: DEFAULT_VM_ID,
+                    asset: None,
+                },
+                &alice,
+            )
+        };
+
+        // Only the fee admin may change the minimum fee.
+        let forged = change(1, &alice).await.unwrap();
+        assert_eq!(
+            state.apply_set_min_fee(&forged),
+            Err(RollupError::NotFeeAdmin {
+                address: alice.address()
+            })
+        );
+        assert_eq!(*state.min_fee(), MinFee::default());
+
+        // A change does not take effect in the block which applies it, nor before its effective
+        // height.
+        let raise = change(1, &admin).await.unwrap();
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        let outcomes = state.apply_block_with_transactions(
+            block,
+            vec![raise.clone().into(), transfer(1, 0).await.into()],
+        );
+        assert!(outcomes.iter().all(TransactionOutcome::applied));
+        assert_eq!(outcomes[0].sender, Some(admin.address()));
+        assert_eq!(outcomes[0].min_fee, Some(raise.set_min_fee.clone()));
+        assert_eq!(state.min_fee().current, 0);
+        assert_eq!(state.min_fee().at(1), 0);
+        assert_eq!(state.min_fee().at(2), 5);
+
+        // Nor can it be applied twice.
+        assert_eq!(
+            state.apply_set_min_fee(&raise),
+            Err(RollupError::InvalidNonce {
+                address: admin.address(),
+                expected: 2,
+                actual: 1,
+            })
+        );
+
+        let outcomes =
+            state.apply_block_with_transactions(block, vec![transfer(2, 0).await.into()]);
+        assert!(outcomes[0].applied());
+
+        // From the effective height, transfers paying less than the new minimum are rejected.
+        let cheap = transfer(3, 0).await;
+        assert_eq!(
+            state.check_transfer(&alice.address(), &cheap.transaction),
+            [RollupError::FeeTooLow { fee: 0, min: 5 }]
+        );
+        let outcomes = state
+            .apply_block_with_transactions(block, vec![cheap.into(), transfer(3, 5).await.into()]);
+        assert_eq!(
+            outcomes[0].error,
+            Some(RollupError::FeeTooLow { fee: 0, min: 5 }.to_string())
+        );
+        assert!(outcomes[1].applied());
+        assert_eq!(
+            *state.min_fee(),
+            MinFee {
+                current: 5,
+                scheduled: None,
+                nonce: 1,
+            }
+        );
+        assert_eq!(state.get_balance(&alice.address()), 65);
+        assert_eq!(state.commit(), state.compute_commitment());
+    }
+
+    #[async_std::test]
+    async fn test_messages() {
+        use crate::message::{Message, OutboundMessage, MAX_OUTBOX_MESSAGES};
+
+        let vm = RollupVM::new(1.into());
+        let genesis = State::from_initial_balances([], vm);
+        let mut state = State::from_initial_balances([], vm).with_chain_config(ChainConfig {
+            message_sources: [2].into(),
+            ..Default::default()
+        });
+        // Listing message sources changes the genesis commitment, but only when there are any.
+        assert_ne!(state.commit(), genesis.commit());
+
+        let [sender, alice] = [(); 2].map(|_| Address::random());
+        let message = |source_vm, dest_vm, nonce| CrossVmMessage {
+            source_vm,
+            sender,
+            dest_vm,
+            recipient: alice,
+            nonce,
+            payload: b"hi".to_vec().into(),
+        };
+        let before = state.commit();
+        let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+        state.apply_block_with_inputs(
+            block,
+            &BlockInputs {
+                messages: vec![
+                    message(2, 1, 2),
+                    // Messages for another rollup, or from a rollup not listed, are ignored.
+                    message(2, 3, 3),
+                    message(3, 1, 3),
+                    // So are messages replayed, or sent out of order.
+                    message(2, 1, 2),
+                    message(2, 1, 1),
+                    message(2, 1, 5),
+                ],
+                ..Default::default()
+            },
+            vec![],
+        );
+        assert_eq!(state.messages(&alice), [message(2, 1, 2), message(2, 1, 5)]);
+        assert!(state.messages(&sender).is_empty());
+        assert_ne!(state.commit(), before);
+        assert_eq!(state.commit(), state.compute_commitment());
+
+        // Sending messages records the outbox of the block.
+        let wallet = LocalWallet::new(&mut rand::thread_rng());
+        let mut transactions = vec![];
+        for nonce in 1..=MAX_OUTBOX_MESSAGES as Nonce + 1 {
+            let outbound = OutboundMessage::new(
+                Message {
+                    dest_vm: 2,
+                    recipient: alice,
+                    nonce,
+                    payload: Default::default(),
+                },
+                &wallet,
+            )
+            .await
+            .unwrap();
+            transactions.push(RollupTransaction::from(outbound));
+        }
+        let outcomes = state.apply_block_with_transactions(block, transactions.clone());
+        assert_eq!(outcomes.len(), MAX_OUTBOX_MESSAGES + 1);
+        assert!(outcomes[..MAX_OUTBOX_MESSAGES]
+            .iter()
+            .all(|outcome| outcome.applied() && outcome.sender == Some(wallet.address())));
+        assert!(!outcomes[MAX_OUTBOX_MESSAGES].applied());
+        let sent = crate::message::outbox(
+            1,
+            transactions.iter().filter_map(|txn| match txn {
+                RollupTransaction::OutboundMessage(txn) => Some(txn),
+                _ => None,
+            }),
+        )
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        assert_eq!(state.outbox, Some(outbox_commitment(&sent)));
+        assert_eq!(state.commit(), state.compute_commitment());
+
+        // A block sending nothing has no outbox.
+        state.apply_block_with_transactions(block, vec![]);
+        assert_eq!(state.outbox, None);
+
+        // Messages count against the weight budget, after transactions with a higher priority,
+        // and those which do not fit in it are not sent.
+        let bob = LocalWallet::new(&mut rand::thread_rng());
+        let transfer = RollupTransaction::from(
+            SignedTransaction::new_with_wallet(
+                Transaction {
+                    amount: 1,
+                    destination: alice,
+                    nonce: 1,
+                    priority: 1,
+                    vm_id: DEFAULT_VM_ID,
+                    asset: None,
+                },
+                &bob,
+            )
+            .await,
+        );
+        let mut block_txns = transactions[..3].to_vec();
+        block_txns.push(transfer.clone());
+        let [message_weight, transfer_weight] =
+            [&block_txns[0], &transfer].map(RollupTransaction::weight);
+        let max = transfer_weight + 2 * message_weight;
+        let mut state = State::from_initial_balances([(bob.address(), 100)], vm).with_chain_config(
+            ChainConfig {
+                max_block_weight: max,
+                ..Default::default()
+            },
+        );
+        let outcomes = state.apply_block_with_transactions(block, block_txns.clone());
+        assert_eq!(outcomes.len(), 4);
+        // The transfer is applied first, so only two of the messages fit after it.
+        assert_eq!(outcomes[0].hash, transfer.hash());
+        assert_eq!(outcomes[0].weight, transfer_weight);
+        assert!(outcomes[..3].iter().all(|outcome| outcome.applied()));
+        assert!(outcomes[1..3].iter().all(|outcome| {
+            outcome.weight == message_weight && outcome.sender == Some(wallet.address())
+        }));
+        assert_eq!(outcomes[3].hash, block_txns[2].hash());
+        assert_eq!(outcomes[3].weight, 0);
+        assert_eq!(
+            outcomes[3].error,
+            Some(
+                RollupError::BlockWeightExhausted {
+                    max,
+                    weight: message_weight,
+                }
+                .to_string()
+            )
+        );
+        // The receiving rollup finds the same messages from the block alone.
+        let budgeted =
+            crate::message::budgeted_messages(&block_txns, state.chain_config().max_block_weight);
+        assert_eq!(budgeted.len(), 2);
+        let sent = crate::message::outbox(1, budgeted)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        assert_eq!(state.outbox, Some(outbox_commitment(&sent)));
+        assert_eq!(state.commit(), state.compute_commitment());
+    }
+
+    #[test]
+    fn test_balance_overflow() {
+        let alice = SeedIdentity::Alice.wallet();
+        let bob = SeedIdentity::Bob.wallet();
+        let mut state = State::from_initial_balances(
+            [(alice.address(), 10), (bob.address(), Amount::MAX - 5)],
+            RollupVM::new(1.into()),
+        );
+        let transfer = |amount, destination, nonce| {
+            let transaction = Transaction {
+                amount,
+                destination,
+                nonce,
+                priority: 0,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            };
+            let signature = alice.sign_hash(transaction.signing_hash()).unwrap();
+            SignedTransaction::from_signature(transaction, signature)
+        };
+
+        // A transfer which would take the recipient past the largest amount is rejected, rather
+        // than wrapping in a release build and panicking in a debug one.
+        let genesis = state.commit();
+        assert_eq!(
+            state.apply_transaction(&transfer(6, bob.address(), 1)),
+            Err(RollupError::BalanceOverflow {
+                address: bob.address()
+            })
+        );
+        assert_eq!(state.commit(), genesis);
+        state
+            .apply_transaction(&transfer(5, bob.address(), 1))
+            .unwrap();
+        assert_eq!(state.get_balance(&bob.address()), Amount::MAX);
+
+        // Sending everything to oneself can never overflow.
+        state
+            .apply_transaction(&transfer(5, alice.address(), 2))
+            .unwrap();
+        assert_eq!(state.get_balance(&alice.address()), 5);
+    }
+
+    /// Two executors fed the same blocks must always agree on the state.
+    ///
+    /// One executor decodes and filters the raw payloads of each block itself, recovering signers
+    /// eagerly on several threads, as a node does. The other is handed the decoded transactions of
+    /// its own namespace, recovers signers lazily, and is restarted from a snapshot of itself after
+    /// every block. The blocks mix valid transfers with forged and garbled signatures, bad nonces,
+    /// garbage, payloads of other rollups and amounts at the limits of their type.
+    mod consensus {
+        use super::*;
+        use ethers::types::{Signature, U256};
+        use proptest::prelude::*;
+        use rand::SeedableRng;
+        use rand_chacha::ChaChaRng;
+        use sequencer_utils::u256_to_commitment;
+
+        /// Accounts the blocks move funds between: the seed identities, then an account which
+        /// starts out empty.
+        const ACCOUNTS: usize = 4;
+
+        fn wallets() -> &'static [LocalWallet] {
+            static WALLETS: OnceLock<Vec<LocalWallet>> = OnceLock::new();
+            WALLETS.get_or_init(|| {
+                let mut wallets = [
+                    SeedIdentity::Bob,
+                    SeedIdentity::Alice,
+                    SeedIdentity::Charlie,
+                ]
+                .iter()
+                .map(SeedIdentity::wallet)
+                .collect::<Vec<_>>();
+                wallets.push(LocalWallet::new(&mut ChaChaRng::seed_from_u64(
+                    ACCOUNTS as u64,
+                )));
+                wallets
+            })
+        }
+
+        /// How a generated transfer is signed.
+        #[derive(Clone, Debug)]
+        enum Signing {
+            /// By the sender.
+            Valid,
+            /// By the sender, for a different amount.
+            Forged,
+            /// Not a signature at all.
+            Garbled { r: u64, s: u64, v: u64 },
+        }
+
+        /// A payload of a generated block. Kept as a description, rather than as bytes, so that
+        /// shrunk counterexamples are readable.
+        #[derive(Clone, Debug)]
+        enum Payload {
+            Transfer {
+                from: usize,
+                to: usize,
+                amount: Amount,
+                nonce: Nonce,
+                priority: Amount,
+                signing: Signing,
+            },
+            /// Bytes in this rollup's namespace which are not a transaction.
+            Garbage(Vec<u8>),
+            /// A valid transfer of another rollup, which this rollup must ignore.
+            Foreign { vm: u64, nonce: Nonce },
+        }
+
+        impl Payload {
+            fn transaction(&self) -> Option<RollupTransaction> {
+                let Payload::Transfer {
+                    from,
+                    to,
+                    amount,
+                    nonce,
+                    priority,
+                    signing,
+                } = self
+                else {
+                    return None;
+                };
+                let transaction = Transaction {
+                    amount: *amount,
+                    destination: wallets()[*to].address(),
+                    nonce: *nonce,
+                    priority: *priority,
+                    vm_id: DEFAULT_VM_ID,
+                    asset: None,
+                };
+                let sign = |transaction: &Transaction| {
+                    wallets()[*from]
+                        .sign_hash(transaction.signing_hash())
+                        .unwrap()
+                };
+                let signed = match signing {
+                    Signing::Valid => {
+                        SignedTransaction::from_signature(transaction.clone(), sign(&transaction))
+                    }
+                    Signing::Forged => {
+                        let signature = sign(&Transaction {
+                            amount: amount.wrapping_add(1),
+                            ..transaction.clone()
+                        });
+                        SignedTransaction::from_signature(transaction, signature)
+                    }
+                    Signing::Garbled { r, s, v } => SignedTransaction::from_parts(
+                        transaction,
+                        Signature {
+                            r: U256::from(*r),
+                            s: U256::from(*s),
+                            v: *v,
+                        },
+                    ),
+                };
+                Some(signed.into())
+            }
+
+            fn wrap(&self, vm: &RollupVM) -> sequencer::Transaction {
+                match self {
+                    Payload::Transfer { .. } => vm.wrap(&self.transaction().unwrap()),
+                    Payload::Garbage(bytes) => sequencer::Transaction::new(vm.id(), bytes.clone()),
+                    Payload::Foreign { vm, nonce } => {
+                        let transfer = Payload::Transfer {
+                            from: 0,
+                            to: 1,
+                            amount: 1,
+                            nonce: *nonce,
+                            priority: 0,
+                            signing: Signing::Valid,
+                        };
+                        RollupVM::new((*vm).into()).wrap(&transfer.transaction().unwrap())
+                    }
+                }
+            }
+        }
+
+        /// Amounts, biased towards the edges of the type and of the generated balances.
+        fn amount() -> impl Strategy<Value = Amount> {
+            prop_oneof![
+                0..20u64,
+                Just(Amount::MAX),
+                Just(Amount::MAX - 1),
+                Just(Amount::MAX / 2 + 1),
+                any::<Amount>(),
+            ]
+        }
+
+        fn payload() -> impl Strategy<Value = Payload> {
+            let signing = prop_oneof![
+                6 => Just(Signing::Valid),
+                1 => Just(Signing::Forged),
+                1 => (any::<u64>(), any::<u64>(), 25..30u64)
+                    .prop_map(|(r, s, v)| Signing::Garbled { r, s, v }),
+            ];
+            let transfer = (
+                0..ACCOUNTS - 1,
+                0..ACCOUNTS,
+                amount(),
+                prop_oneof![1..4u64, Just(0), Just(Nonce::MAX)],
+                prop_oneof![Just(0), amount()],
+                signing,
+            )
+                .prop_map(|(from, to, amount, nonce, priority, signing)| {
+                    Payload::Transfer {
+                        from,
+                        to,
+                        amount,
+                        nonce,
+                        priority,
+                        signing,
+                    }
+                });
+            let garbage = prop_oneof![
+                prop::collection::vec(any::<u8>(), 0..64),
+                Just(b"{}".to_vec()),
+                Just(b"null".to_vec()),
+            ]
+            .prop_map(Payload::Garbage);
+            let foreign = (2..4u64, 1..3u64).prop_map(|(vm, nonce)| Payload::Foreign { vm, nonce });
+            prop_oneof![8 => transfer, 1 => garbage, 1 => foreign]
+        }
+
+        fn genesis() -> impl Strategy<Value = State> {
+            let weight = prop_oneof![Just(DEFAULT_MAX_BLOCK_WEIGHT), 0..100_000u64,];
+            (prop::collection::vec(amount(), ACCOUNTS - 1), weight).prop_map(
+                |(balances, max_block_weight)| {
+                    let balances = wallets()
+                        .iter()
+                        .map(|wallet| wallet.address())
+                        .zip(balances);
+                    State::from_initial_balances(balances, RollupVM::new(1.into()))
+                        .with_chain_config(ChainConfig {
+                            max_block_weight,
+                            ..Default::default()
+                        })
+                },
+            )
+        }
+
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(64))]
+
+            #[test]
+            fn test_executors_agree(
+                genesis in genesis(),
+                blocks in prop::collection::vec(prop::collection::vec(payload(), 0..8), 1..6),
+            ) {
+                let mut node = genesis.clone();
+                node.set_eager_recovery(true);
+                node.set_recovery_threads(2);
+                let mut replay = genesis;
+                for (height, payloads) in blocks.iter().enumerate() {
+                    let block = u256_to_commitment(U256::from(height + 1)).unwrap();
+
+                    let wrapped = payloads
+                        .iter()
+                        .map(|payload| payload.wrap(&node.vm))
+                        .collect::<Vec<_>>();
+                    let counts = node.apply_block_payloads(block, &wrapped);
+
+                    let transactions = payloads
+                        .iter()
+                        .filter_map(|payload| match payload {
+                            Payload::Garbage(bytes) => RollupTransaction::decode(bytes),
+                            payload => payload.transaction(),
+                        })
+                        .collect::<Vec<_>>();
+                    let outcomes = replay.apply_block_with_transactions(block, transactions);
+                    replay = serde_json::from_str(&serde_json::to_string(&replay).unwrap())
+                        .unwrap();
+
+                    prop_assert_eq!(counts.total_payloads, payloads.len() as u64);
+                    prop_assert_eq!(counts.decoded, outcomes.len() as u64);
+                    prop_assert_eq!(
+                        counts.applied,
+                        outcomes.iter().filter(|outcome| outcome.applied()).count() as u64
+                    );
+                    prop_assert_eq!(node.block_height(), replay.block_height());
+                    prop_assert_eq!(node.commit(), replay.commit(), "block {}", height);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,807 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+use async_std::sync::{Arc, RwLock};
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+use surf_disco::Url;
+
+use crate::build_info::BuildInfo;
+use crate::cache::CacheStats;
+use crate::deposit::CreditedDeposit;
+use crate::l1_updates::L1Update;
+use crate::state::{Amount, BlockCounts};
+use crate::submission::ForwardingStats;
+use crate::watchdog::QuarantinedBlock;
+
+/// Number of recent proofs whose cost is averaged to estimate the runway of the operator account.
+pub const RUNWAY_WINDOW: usize = 10;
+
+/// Period over which the rate at which the executor executes blocks is measured.
+pub const SYNC_RATE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Progress of the executor, shared with the API so it can tell clients how fresh its data is.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExecutorStatus {
+    /// Number of rollup blocks executed by this node.
+    pub local_height: u64,
+    /// Number of rollup blocks already verified by the rollup contract when the executor started.
+    pub target_height: u64,
+    /// Progress of the executor towards the latest block committed to the HotShot contract.
+    pub sync: SyncProgress,
+    /// Number of blocks committed to the HotShot contract which this node has not executed yet,
+    /// as of when the executor last read the contract.
+    pub sequencing_lag: u64,
+    /// Number of blocks this node has executed which the rollup contract has not verified yet.
+    pub verification_lag: u64,
+    /// Number of times the executor has been restarted after a failure.
+    pub restarts: u64,
+    /// Why the executor was last restarted.
+    pub last_failure: Option<String>,
+    /// Chain ID of the L1 the executor is connected to, once it has connected.
+    pub l1_chain_id: Option<u64>,
+    /// Transactions seen in the blocks executed since this node started, by whether they belonged
+    /// to this rollup and were applied.
+    pub payloads: BlockCounts,
+    /// Number of blocks proven by each proof the executor sends to L1. Blocks are verified by the
+    /// rollup contract in strides of this many, or fewer when the executor has been idle.
+    pub proof_interval: u64,
+    /// Number of rollup blocks this node has seen the rollup contract verify.
+    pub verified_height: u64,
+    /// Seconds a verified state update can be challenged before it becomes final. Zero if the
+    /// rollup contract runs in validity mode, where every verified block is final.
+    pub challenge_window: u64,
+    /// Number of rollup blocks this node has seen the rollup contract finalize.
+    pub finalized_height: u64,
+    /// Progress of the backfill of older blocks, while one is running.
+    pub backfill: Option<BackfillStatus>,
+    /// Number of blocks certified by the HotShot contract whose contents the query service did not
+    /// have yet, so the executor waited for them.
+    pub unavailable_blocks: u64,
+    /// Health of each replica of the query service the executor reads blocks from, the primary
+    /// first.
+    pub query_replicas: Vec<ReplicaStatus>,
+    /// Balance in wei of the L1 account proofs are sent from, when it was last checked.
+    pub operator_balance: Option<U256>,
+    /// Number of blocks the operator account can pay to prove at the average cost of the last
+    /// few proofs, once a proof has been sent.
+    pub runway_blocks: Option<u64>,
+    /// Size and calldata cost of the last proof accepted by the rollup contract.
+    pub last_proof: Option<ProofCalldata>,
+    /// Calldata gas of every proof the rollup contract accepted since the executor started.
+    pub proof_calldata_gas: u64,
+    /// Number of executed blocks whose proofs were not sent to L1 before the executor shut down.
+    pub dropped_proofs: u64,
+    /// The build of the node running the executor.
+    pub build: BuildInfo,
+    /// How often the API's read cache has answered reads, if it is enabled. Filled in by the API
+    /// when it reports this status.
+    pub read_cache: Option<CacheStats>,
+    /// How forwarding submitted transactions to the sequencer is going, if the API forwards them.
+    /// Filled in by the API when it reports this status.
+    pub forwarding: Option<ForwardingStats>,
+    /// How the state computed by a read-only replica compares with the state updates proven to
+    /// the rollup contract. Only reported by read-only replicas.
+    pub contract_agreement: Option<ContractAgreement>,
+    /// What the executor has seen of the state updates accepted by the rollup contract.
+    pub l1_updates: L1UpdateStats,
+    /// Events of the HotShot contract which the executor's log subscription missed.
+    pub l1_events: L1EventStats,
+    /// Health of the HotShot commitment task, if this node runs it.
+    pub commitment_task: Option<CommitmentTaskStatus>,
+    /// Deposits credited by the rollup which L1 reorgs have since removed from L1, as seen since
+    /// the executor started.
+    pub reorged_deposits: Vec<CreditedDeposit>,
+    /// Number of blocks committed to the HotShot contract, as last read. Unlike the target of
+    /// [`sync`](Self::sync), this can go down, if the contract is read from an L1 node which has
+    /// just seen a reorg.
+    #[serde(skip)]
+    hotshot_height: u64,
+    /// Blocks covered by each of the last [`RUNWAY_WINDOW`] proofs, and what they cost in wei.
+    #[serde(skip)]
+    proof_costs: VecDeque<(u64, U256)>,
+    /// Balances after the last block finalized by the rollup contract whose balances are known.
+    #[serde(skip)]
+    confirmed: ConfirmedState,
+    /// Verified heights still within their challenge window, with the balances after each if they
+    /// are known.
+    #[serde(skip)]
+    unfinalized: BTreeMap<u64, Option<BTreeMap<Address, Amount>>>,
+    /// L1 verification status of each batch of blocks proven together, by first block height.
+    #[serde(skip)]
+    batches: BTreeMap<u64, BatchVerification>,
+    /// The block the executor gave up on and stopped at, if it did. Only served to the operator.
+    #[serde(skip)]
+    quarantined: Option<QuarantinedBlock>,
+}
+
+/// Health of the HotShot commitment task run by the node. See
+/// [`commitment_task`](crate::commitment_task).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentTaskStatus {
+    /// Number of blocks committed to the HotShot contract, as last read.
+    pub committed_height: u64,
+    /// When the HotShot contract was last seen to commit more blocks, in seconds since the Unix
+    /// epoch.
+    pub last_commitment_at: Option<u64>,
+    /// Seconds for which the query service has had the next block to commit without the HotShot
+    /// contract committing it. Zero while the task keeps up.
+    pub lag_secs: u64,
+    /// Number of times the task has been restarted after it stopped.
+    pub restarts: u64,
+    /// Why the task was last restarted.
+    pub last_failure: Option<String>,
+}
+
+/// Whether the proof covering a rollup block has been accepted by the rollup contract.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockVerification {
+    /// No proof covering this block has been sent to L1 yet.
+    Unproven,
+    /// A proof was sent to L1 in transaction `l1_tx`, which has not been mined yet.
+    Submitted { l1_tx: H256 },
+    /// The proof was accepted by the rollup contract in L1 block `l1_block`.
+    Verified { l1_tx: H256, l1_block: u64 },
+}
+
+/// Whether a rollup block can still be reverted by a fraud proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockFinality {
+    /// The rollup contract has not verified the block yet.
+    NotVerified,
+    /// The block has been verified, but is still within the challenge window of an optimistic
+    /// rollup contract.
+    Challengeable,
+    /// The block has been finalized by the rollup contract and can no longer be reverted.
+    Final,
+}
+
+/// The size of a proof sent to L1, and what carrying it as calldata costs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofCalldata {
+    /// Height of the first block proven.
+    pub first_block: u64,
+    pub num_blocks: u64,
+    /// Size of the ABI-encoded batch proof.
+    pub proof_bytes: u64,
+    /// Size of the calldata of the whole call, including the proof.
+    pub calldata_bytes: u64,
+    /// Gas charged for the calldata: 16 for each nonzero byte and 4 for each zero byte.
+    pub calldata_gas: u64,
+}
+
+impl ProofCalldata {
+    /// Measure the call `calldata` sending a proof of `proof_bytes` bytes.
+    pub fn measure(first_block: u64, num_blocks: u64, proof_bytes: usize, calldata: &[u8]) -> Self {
+        let calldata_gas = calldata
+            .iter()
+            .map(|byte| if *byte == 0 { 4 } else { 16 })
+            .sum();
+        Self {
+            first_block,
+            num_blocks,
+            proof_bytes: proof_bytes as u64,
+            calldata_bytes: calldata.len() as u64,
+            calldata_gas,
+        }
+    }
+}
+
+/// Progress of the executor towards the latest block committed to the HotShot contract, and how
+/// long it is expected to take to get there.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncProgress {
+    /// Number of blocks executed when the executor started.
+    pub start_height: u64,
+    /// Number of blocks committed to the HotShot contract, as of when the executor last checked.
+    pub target_height: u64,
+    /// Number of blocks executed.
+    pub current_height: u64,
+    /// Blocks executed per second over the last [`SYNC_RATE_WINDOW`].
+    pub blocks_per_second: f64,
+    /// Estimated number of seconds until the executor reaches the target height, unless it has
+    /// not executed any blocks recently.
+    pub eta_secs: Option<u64>,
+    /// Times at which recent heights were reached, the oldest first.
+    #[serde(skip)]
+    samples: VecDeque<(Instant, u64)>,
+}
+
+/// Progress of filling in summaries and receipts of blocks executed before the node started from a
+/// snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackfillStatus {
+    /// The next block to be backfilled. Every block below this has been covered.
+    pub next_height: u64,
+    /// Height of the snapshot the node started from, where the backfill will stop.
+    pub target_height: u64,
+}
+
+/// How the state computed by a read-only replica compares with the state updates the operator has
+/// proven to the rollup contract.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractAgreement {
+    /// Number of blocks covered by the state updates compared so far.
+    pub checked_height: u64,
+    /// Number of state updates whose commitment matched the one computed by the replica. Updates
+    /// after blocks whose summaries the replica does not have are not compared.
+    pub matched_updates: u64,
+    /// The first state update whose commitment differed from the one computed by the replica.
+    pub mismatch: Option<CommitmentMismatch>,
+}
+
+/// The state updates the executor has recorded in its
+/// [history](crate::l1_updates::L1UpdateHistory), and any it did not expect.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1UpdateStats {
+    /// Number of state updates recorded.
+    pub recorded: u64,
+    /// Number of state updates accepted while this executor was the only one sending them, but
+    /// which it did not send. Each means another account holding a submitter key is proving
+    /// blocks.
+    pub unexpected: u64,
+    /// The latest of the unexpected updates.
+    pub last_unexpected: Option<L1Update>,
+}
+
+/// Events of the HotShot contract which the executor's log subscription missed, and which it found
+/// by querying the L1 instead. See [`l1_events`](crate::l1_events).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1EventStats {
+    /// Number of events found by querying the L1 before the subscription delivered them.
+    pub backfilled: u64,
+    /// Number of times an event showed that the subscription had missed the ones before it.
+    pub gaps: u64,
+}
+
+/// A state update in the rollup contract which does not match the state computed locally.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentMismatch {
+    /// Number of blocks after which the states differ.
+    pub height: u64,
+    pub ours: U256,
+    pub theirs: U256,
+}
+
+/// Health of a replica of the query service.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicaStatus {
+    pub url: Url,
+    /// Whether the last query sent to this replica was answered.
+    pub healthy: bool,
+    /// Whether queries currently go to this replica first.
+    pub active: bool,
+    /// Number of queries this replica has failed to answer.
+    pub failures: u64,
+    /// Why the last query this replica failed to answer failed, unless it has answered one since.
+    pub last_error: Option<String>,
+    /// Whether the replica has failed too many queries in a row, so that queries skip it until its
+    /// cooldown is over.
+    pub breaker_open: bool,
+    /// Number of queries this replica has answered.
+    pub answered: u64,
+    /// How long, in milliseconds, the replica took to answer the last query it answered.
+    pub last_latency_ms: Option<u64>,
+    /// How long, in milliseconds, the replica has taken to answer queries, on average.
+    pub mean_latency_ms: Option<u64>,
+    /// Total time the replica has taken to answer queries.
+    #[serde(skip)]
+    total_latency: Duration,
+}
+
+impl ReplicaStatus {
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            healthy: true,
+            active: false,
+            failures: 0,
+            last_error: None,
+            breaker_open: false,
+            answered: 0,
+            last_latency_ms: None,
+            mean_latency_ms: None,
+            total_latency: Duration::ZERO,
+        }
+    }
+
+    /// Record that the replica answered a query in `latency`.
+    pub fn record_latency(&mut self, latency: Duration) {
+        self.answered += 1;
+        self.total_latency += latency;
+        self.last_latency_ms = Some(latency.as_millis() as u64);
+        self.mean_latency_ms =
+            Some((self.total_latency.as_millis() / self.answered as u128) as u64);
+    }
+}
+
+/// A balance as of the last rollup block finalized by the rollup contract.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfirmedBalance {
+    /// Number of blocks finalized when the balance was confirmed.
+    pub height: u64,
+    pub balance: Amount,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct ConfirmedState {
+    height: u64,
+    balances: BTreeMap<Address, Amount>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct BatchVerification {
+    num_blocks: u64,
+    verification: BlockVerification,
+}
+
+impl SyncProgress {
+    /// Start measuring progress from `start_height` towards `target_height`.
+    pub fn new(start_height: u64, target_height: u64, now: Instant) -> Self {
+        let mut progress = Self {
+            start_height,
+            target_height: target_height.max(start_height),
+            ..Default::default()
+        };
+        progress.record(start_height, now);
+        progress
+    }
+
+    /// Record that the HotShot contract has committed `target_height` blocks.
+    pub fn set_target(&mut self, target_height: u64) {
+        self.target_height = self.target_height.max(target_height);
+        self.update_eta();
+    }
+
+    /// Record that the executor had executed `height` blocks at `now`.
+    pub fn record(&mut self, height: u64, now: Instant) {
+        self.current_height = height;
+        self.target_height = self.target_height.max(height);
+        self.samples.push_back((now, height));
+        // Keep one sample from before the window, to measure the whole window from.
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= SYNC_RATE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.blocks_per_second = match (self.samples.front(), self.samples.back()) {
+            (Some((first_time, first_height)), Some((last_time, last_height)))
+                if last_time > first_time =>
+            {
+                last_height.saturating_sub(*first_height) as f64
+                    / last_time.duration_since(*first_time).as_secs_f64()
+            }
+            _ => 0.,
+        };
+        self.update_eta();
+    }
+
+    fn update_eta(&mut self) {
+        let remaining = self.target_height - self.current_height;
+        self.eta_secs = if remaining == 0 {
+            Some(0)
+        } else if self.blocks_per_second > 0. {
+            Some((remaining as f64 / self.blocks_per_second).ceil() as u64)
+        } else {
+            None
+        };
+    }
+}
+
+impl ExecutorStatus {
+    /// Whether the local state is within `max_lag` blocks of the target height.
+    pub fn is_synced(&self, max_lag: u64) -> bool {
+        self.local_height.saturating_add(max_lag) >= self.target_height
+    }
+
+    /// Record that the executor had executed `height` blocks at `now`.
+    pub fn set_local_height(&mut self, height: u64, now: Instant) {
+        self.local_height = height;
+        self.sync.record(height, now);
+        self.update_lag();
+    }
+
+    /// Record that the HotShot contract has committed `height` blocks.
+    pub fn set_hotshot_height(&mut self, height: u64) {
+        self.hotshot_height = height;
+        self.sync.set_target(height);
+        self.update_lag();
+    }
+
+    fn update_lag(&mut self) {
+        // Either height may be ahead of the local one: the contract's after a reorg, and the
+        // verified one while the executor catches up on blocks proven before it started.
+        self.sequencing_lag = self.hotshot_height.saturating_sub(self.local_height);
+        self.verification_lag = self.local_height.saturating_sub(self.verified_height);
+    }
+
+    /// Whether the summary and receipts of the block at `height` are still to be backfilled.
+    pub fn is_backfilling(&self, height: u64) -> bool {
+        matches!(
+            self.backfill,
+            Some(backfill) if (backfill.next_height..backfill.target_height).contains(&height)
+        )
+    }
+
+    /// Record the verification status of the `num_blocks` blocks starting at `first_block`.
+    pub fn set_verification(
+        &mut self,
+        first_block: u64,
+        num_blocks: u64,
+        verification: BlockVerification,
+    ) {
+        self.batches.insert(
+            first_block,
+            BatchVerification {
+                num_blocks,
+                verification,
+            },
+        );
+    }
+
+    /// Record that the rollup contract has verified the first `height` blocks, after which the
+    /// balances were `balances`, if they are known.
+    ///
+    /// Without a challenge window the blocks are final at once. Otherwise they, and their
+    /// balances, wait for [`set_finalized`](Self::set_finalized).
+    pub fn set_verified(&mut self, height: u64, balances: Option<Vec<(Address, Amount)>>) {
+        self.verified_height = self.verified_height.max(height);
+        self.update_lag();
+        let balances = balances.map(|balances| balances.into_iter().collect());
+        if self.challenge_window == 0 {
+            self.finalized_height = self.finalized_height.max(height);
+            if let Some(balances) = balances {
+                self.confirm(height, balances);
+            }
+        } else if height > self.finalized_height {
+            let entry = self.unfinalized.entry(height).or_default();
+            if balances.is_some() {
+                *entry = balances;
+            }
+        }
+    }
+
+    /// Record that the rollup contract has finalized the first `height` blocks.
+    ///
+    /// The balances after the last of them which are known become confirmed.
+    pub fn set_finalized(&mut self, height: u64) {
+        self.finalized_height = self.finalized_height.max(height);
+        let later = self.unfinalized.split_off(&(height + 1));
+        let finalized = std::mem::replace(&mut self.unfinalized, later);
+        if let Some((height, balances)) = finalized
+            .into_iter()
+            .rev()
+            .find_map(|(height, balances)| Some((height, balances?)))
+        {
+            self.confirm(height, balances);
+        }
+    }
+
+    /// Record the balance of the operator account.
+    pub fn set_operator_balance(&mut self, balance: U256) {
+        self.operator_balance = Some(balance);
+        self.update_runway();
+    }
+
+    /// Record that a proof of `num_blocks` blocks cost the operator account `cost` wei.
+    pub fn record_proof_cost(&mut self, num_blocks: u64, cost: U256) {
+        if self.proof_costs.len() == RUNWAY_WINDOW {
+            self.proof_costs.pop_front();
+        }
+        self.proof_costs.push_back((num_blocks, cost));
+        self.update_runway();
+    }
+
+    /// Record the calldata of a proof the rollup contract accepted.
+    pub fn record_proof_calldata(&mut self, calldata: ProofCalldata) {
+        self.proof_calldata_gas = self
+            .proof_calldata_gas
+            .saturating_add(calldata.calldata_gas);
+        self.last_proof = Some(calldata);
+    }
+
+    fn update_runway(&mut self) {
+        let blocks: u64 = self.proof_costs.iter().map(|(blocks, _)| blocks).sum();
+        let cost = self
+            .proof_costs
+            .iter()
+            .fold(U256::zero(), |total, (_, cost)| total.saturating_add(*cost));
+        self.runway_blocks = match self.operator_balance {
+            Some(balance) if !cost.is_zero() => {
+                let runway = balance.saturating_mul(blocks.into()) / cost;
+                Some(if runway > u64::MAX.into() {
+                    u64::MAX
+                } else {
+                    runway.as_u64()
+                })
+            }
+            _ => None,
+        };
+    }
+
+    /// Verified heights which have not been finalized yet, in increasing order.
+    pub fn unfinalized_heights(&self) -> Vec<u64> {
+        self.unfinalized.keys().copied().collect()
+    }
+
+    fn confirm(&mut self, height: u64, balances: BTreeMap<Address, Amount>) {
+        if height >= self.confirmed.height {
+            self.confirmed = ConfirmedState { height, balances };
+        }
+    }
+
+    /// Whether the block at `height` can still be reverted.
+    pub fn finality(&self, height: u64) -> BlockFinality {
+        if height < self.finalized_height {
+            BlockFinality::Final
+        } else if height < self.verified_height {
+            BlockFinality::Challengeable
+        } else {
+            BlockFinality::NotVerified
+        }
+    }
+
+    /// The balance of `address` after the last finalized block whose balances are known.
+    pub fn confirmed_balance(&self, address: &Address) -> ConfirmedBalance {
+        ConfirmedBalance {
+            height: self.confirmed.height,
+            balance: self
+                .confirmed
+                .balances
+                .get(address)
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The verification status of the block at `height`.
+    ///
+    /// Returns `None` if the block has not been executed and no proof for it is known.
+    pub fn verification(&self, height: u64) -> Option<BlockVerification> {
+        let batch = self
+            .batches
+            .range(..=height)
+            .next_back()
+            .filter(|(first_block, batch)| height < *first_block + batch.num_blocks);
+        match batch {
+            Some((_, batch)) => Some(batch.verification),
+            None if height < self.local_height => Some(BlockVerification::Unproven),
+            None => None,
+        }
+    }
+
+    /// The block the executor stopped at after it took too long to execute, if any.
+    pub fn quarantined(&self) -> Option<&QuarantinedBlock> {
+        self.quarantined.as_ref()
+    }
+
+    pub(crate) fn quarantine(&mut self, block: QuarantinedBlock) {
+        self.quarantined = Some(block);
+    }
+}
+
+pub type StatusHandle = Arc<RwLock<ExecutorStatus>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verification() {
+        let mut status = ExecutorStatus {
+            local_height: 5,
+            ..Default::default()
+        };
+        let l1_tx = H256::repeat_byte(1);
+        status.set_verification(0, 2, BlockVerification::Submitted { l1_tx });
+        assert_eq!(
+            status.verification(1),
+            Some(BlockVerification::Submitted { l1_tx })
+        );
+        assert_eq!(status.verification(2), Some(BlockVerification::Unproven));
+        assert_eq!(status.verification(5), None);
+
+        let verified = BlockVerification::Verified { l1_tx, l1_block: 7 };
+        status.set_verification(0, 2, verified);
+        assert_eq!(status.verification(0), Some(verified));
+    }
+
+    #[test]
+    fn test_confirmed_balance() {
+        let mut status = ExecutorStatus::default();
+        let alice = Address::repeat_byte(1);
+        assert_eq!(
+            status.confirmed_balance(&alice),
+            ConfirmedBalance {
+                height: 0,
+                balance: 0
+            }
+        );
+
+        status.set_verified(5, Some(vec![(alice, 100)]));
+        assert_eq!(status.verified_height, 5);
+        assert_eq!(
+            status.confirmed_balance(&alice),
+            ConfirmedBalance {
+                height: 5,
+                balance: 100
+            }
+        );
+
+        // A later verification without balances advances the verified height only.
+        status.set_verified(10, None);
+        assert_eq!(status.verified_height, 10);
+        assert_eq!(status.confirmed_balance(&alice).height, 5);
+
+        // Balances confirmed out of order do not replace newer ones.
+        status.set_verified(15, Some(vec![(alice, 50)]));
+        status.set_verified(10, Some(vec![(alice, 75)]));
+        assert_eq!(
+            status.confirmed_balance(&alice),
+            ConfirmedBalance {
+                height: 15,
+                balance: 50
+            }
+        );
+    }
+
+    #[test]
+    fn test_finality() {
+        let mut status = ExecutorStatus {
+            challenge_window: 60,
+            ..Default::default()
+        };
+        let alice = Address::repeat_byte(1);
+
+        // Verified blocks are not confirmed until they are finalized.
+        status.set_verified(5, Some(vec![(alice, 100)]));
+        status.set_verified(10, None);
+        status.set_verified(15, Some(vec![(alice, 50)]));
+        assert_eq!(status.unfinalized_heights(), vec![5, 10, 15]);
+        assert_eq!(status.finality(4), BlockFinality::Challengeable);
+        assert_eq!(status.finality(15), BlockFinality::NotVerified);
+        assert_eq!(status.confirmed_balance(&alice).height, 0);
+
+        // Finalizing a height whose balances are unknown confirms the last known ones below it.
+        status.set_finalized(10);
+        assert_eq!(status.unfinalized_heights(), vec![15]);
+        assert_eq!(status.finality(9), BlockFinality::Final);
+        assert_eq!(status.finality(10), BlockFinality::Challengeable);
+        assert_eq!(
+            status.confirmed_balance(&alice),
+            ConfirmedBalance {
+                height: 5,
+                balance: 100
+            }
+        );
+
+        status.set_finalized(15);
+        assert!(status.unfinalized_heights().is_empty());
+        assert_eq!(
+            status.confirmed_balance(&alice),
+            ConfirmedBalance {
+                height: 15,
+                balance: 50
+            }
+        );
+
+        // Heights which are already final are not tracked again.
+        status.set_verified(15, None);
+        assert!(status.unfinalized_heights().is_empty());
+    }
+
+    #[test]
+    fn test_runway() {
+        let mut status = ExecutorStatus::default();
+        status.set_operator_balance(1000.into());
+        assert_eq!(status.runway_blocks, None);
+
+        // 20 wei per block on average.
+        status.record_proof_cost(2, 60.into());
+        status.record_proof_cost(3, 40.into());
+        assert_eq!(status.runway_blocks, Some(50));
+        status.set_operator_balance(100.into());
+        assert_eq!(status.runway_blocks, Some(5));
+
+        // Only the most recent proofs count.
+        for _ in 0..RUNWAY_WINDOW {
+            status.record_proof_cost(1, 50.into());
+        }
+        assert_eq!(status.runway_blocks, Some(2));
+    }
+
+    #[test]
+    fn test_proof_calldata() {
+        let mut status = ExecutorStatus::default();
+        let first = ProofCalldata::measure(0, 2, 2, &[0, 0, 1, 0xff]);
+        assert_eq!(first.calldata_bytes, 4);
+        assert_eq!(first.calldata_gas, 4 + 4 + 16 + 16);
+        status.record_proof_calldata(first);
+        let second = ProofCalldata::measure(2, 1, 1, &[7]);
+        status.record_proof_calldata(second);
+        assert_eq!(status.last_proof, Some(second));
+        assert_eq!(status.proof_calldata_gas, 40 + 16);
+    }
+
+    #[test]
+    fn test_sync_progress() {
+        let start = Instant::now();
+        let mut progress = SyncProgress::new(0, 100, start);
+        assert_eq!(progress.eta_secs, None);
+
+        // Catch up on a backlog of 100 blocks, speeding up half way through.
+        let mut now = start;
+        let mut last = progress.clone();
+        for height in 1..=100 {
+            now += if height <= 50 {
+                Duration::from_millis(500)
+            } else {
+                Duration::from_millis(100)
+            };
+            progress.record(height, now);
+            assert_eq!(progress.current_height, height);
+            assert!(progress.blocks_per_second > 0.);
+            let eta = progress.eta_secs.unwrap();
+            if let Some(last_eta) = last.eta_secs {
+                assert!(eta <= last_eta, "ETA went from {last_eta} to {eta}");
+            }
+            if height > 50 {
+                assert!(progress.blocks_per_second >= last.blocks_per_second);
+            }
+            last = progress.clone();
+        }
+        assert_eq!(progress.eta_secs, Some(0));
+        // The whole backlog took 30 seconds, all of it within the window.
+        assert_eq!(progress.blocks_per_second, 100. / 30.);
+
+        // New blocks committed to the HotShot contract put the target out of reach again.
+        progress.set_target(120);
+        assert_eq!(progress.eta_secs, Some(6));
+
+        // A rate is only measured over the last window.
+        progress.record(101, now + SYNC_RATE_WINDOW * 2);
+        assert_eq!(
+            progress.blocks_per_second,
+            1. / (SYNC_RATE_WINDOW * 2).as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn test_lag() {
+        let mut status = ExecutorStatus::default();
+        let now = Instant::now();
+
+        // Catching up: the contract had verified 20 of the 50 blocks committed before we started.
+        status.set_verified(20, None);
+        status.set_hotshot_height(50);
+        for height in [0, 10, 20] {
+            status.set_local_height(height, now);
+            assert_eq!(status.sequencing_lag, 50 - height);
+            assert_eq!(status.verification_lag, 0);
+        }
+        status.set_local_height(50, now);
+        assert_eq!(status.sequencing_lag, 0);
+        assert_eq!(status.verification_lag, 30);
+
+        // Steady state: blocks are executed as they are committed, and proven a few behind.
+        for height in 51..=60 {
+            status.set_hotshot_height(height);
+            assert_eq!(status.sequencing_lag, 1);
+            status.set_local_height(height, now);
+            assert_eq!(status.sequencing_lag, 0);
+            status.set_verified(height - 2, None);
+            assert_eq!(status.verification_lag, 2);
+        }
+
+        // The contract momentarily reports fewer blocks during a reorg, which is no lag at all,
+        // but the sync target does not go back.
+        status.set_hotshot_height(55);
+        assert_eq!(status.sequencing_lag, 0);
+        assert_eq!(status.sync.target_height, 60);
+        status.set_hotshot_height(62);
+        assert_eq!(status.sequencing_lag, 2);
+    }
+}
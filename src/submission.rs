@@ -0,0 +1,628 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Forwarding of submitted transactions to the sequencer, and tracking of their progress.
+
+use async_std::{
+    channel::{self, Receiver, Sender, TrySendError},
+    sync::{Arc, RwLock},
+    task::{sleep, spawn},
+};
+use ethers::types::{Address, H256};
+use futures::future::{BoxFuture, FutureExt};
+use rand::Rng;
+use sequencer::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use surf_disco::Url;
+use tide_disco::{error::Error as _, StatusCode};
+
+use crate::error::ApiError;
+use crate::forced::L1Fallback;
+use crate::hooks::BlockHook;
+use crate::logging::API;
+use crate::state::{BlockResult, BlockTimestamp, State};
+use crate::status::BlockVerification;
+
+/// Maximum number of submissions whose status is remembered. The oldest are forgotten first.
+const MAX_TRACKED_SUBMISSIONS: usize = 1 << 16;
+
+/// Number of workers forwarding submitted transactions to the sequencer, by default.
+pub const DEFAULT_FORWARD_WORKERS: usize = 4;
+
+/// Number of transactions one sender may have pending before further submissions are refused, by
+/// default.
+pub const DEFAULT_MAX_PENDING_PER_SENDER: usize = 16;
+
+/// Time after which a transaction no longer counts as pending for its sender, whether or not it
+/// was executed. The sequencer gives no word of the transactions it drops, so without this a
+/// sender could be locked out for good.
+const PENDING_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
+/// Number of attempts made to forward a queued transaction before giving up on it.
+const MAX_FORWARD_ATTEMPTS: u32 = 5;
+
+/// Seconds a client is asked to wait before retrying when the forwarding queue is full.
+const QUEUE_FULL_RETRY_AFTER_SECS: u64 = 1;
+
+/// Least time to wait before retrying a request which could not reach the sequencer.
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Most random time added to [`CONNECT_RETRY_DELAY`], so that nodes which lost the sequencer at
+/// the same time do not all retry at once.
+const CONNECT_RETRY_JITTER_MS: u64 = 100;
+
+/// Progress of a transaction submitted through this node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionStatus {
+    /// Accepted by the API and waiting to be forwarded to the sequencer.
+    Queued,
+    /// Forwarded to the sequencer, but not yet seen in an executed block.
+    Pending,
+    /// Included in the rollup block at this height, with the time of the block if this node still
+    /// has it. The transaction is final once `verification` is `Verified`. Since proofs may cover
+    /// several blocks, this can be some time after the block was executed.
+    Sequenced {
+        block: u64,
+        /// Position of the transaction among the payloads of the block's namespace, as sequenced,
+        /// if this node knows it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index: Option<u64>,
+        timestamp: Option<BlockTimestamp>,
+        verification: Option<BlockVerification>,
+    },
+    /// Could not be forwarded to the sequencer.
+    Failed { reason: String },
+    /// Not known to this node.
+    Unknown,
+}
+
+/// Receipt for a transaction accepted for asynchronous forwarding.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionTicket {
+    pub hash: H256,
+    pub status: TransactionStatus,
+}
+
+/// The response of the submit route.
+///
+/// A submission which did not ask for a fallback, or did not need one, serializes as it always
+/// has: `null`, or the ticket of a queued transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SubmitResponse {
+    /// The transaction was forwarded to the sequencer, or queued to be, with its ticket.
+    Submitted(Option<SubmissionTicket>),
+    /// The sequencer could not be reached, and the transaction can be enqueued on L1 instead.
+    Fallback { l1_fallback: L1Fallback },
+}
+
+/// Statuses of transactions submitted through this node.
+///
+/// Run as a [`BlockHook`] of the executor, the tracker records the block and position of each
+/// transaction it tracks as the transaction is executed. Otherwise a transaction's inclusion is
+/// only known for as long as the state keeps its recent transactions.
+#[derive(Clone, Debug, Default)]
+pub struct SubmissionTracker(Arc<RwLock<TrackerInner>>);
+
+#[derive(Debug, Default)]
+struct TrackerInner {
+    statuses: HashMap<H256, TransactionStatus>,
+    order: VecDeque<H256>,
+    /// The transactions each sender has pending, with the time each was submitted.
+    by_sender: HashMap<Address, Vec<(H256, Instant)>>,
+}
+
+impl TrackerInner {
+    /// Whether the transaction `hash`, submitted at `since`, still counts as pending for its
+    /// sender.
+    fn counts_as_pending(
+        &self,
+        hash: &H256,
+        since: Instant,
+        executed: &impl Fn(&H256) -> bool,
+    ) -> bool {
+        since.elapsed() < PENDING_EXPIRY
+            && !matches!(
+                self.statuses.get(hash),
+                Some(TransactionStatus::Failed { .. } | TransactionStatus::Sequenced { .. })
+            )
+            && !executed(hash)
+    }
+}
+
+impl SubmissionTracker {
+    pub(crate) async fn set(&self, hash: H256, status: TransactionStatus) {
+        let mut inner = self.0.write().await;
+        if inner.statuses.insert(hash, status).is_none() {
+            inner.order.push_back(hash);
+        }
+        while inner.order.len() > MAX_TRACKED_SUBMISSIONS {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.statuses.remove(&oldest);
+            }
+        }
+    }
+
+    pub(crate) async fn get(&self, hash: &H256) -> TransactionStatus {
+        self.0
+            .read()
+            .await
+            .statuses
+            .get(hash)
+            .cloned()
+            .unwrap_or(TransactionStatus::Unknown)
+    }
+
+    /// Record that the tracked transactions among those of `block` were sequenced in it.
+    ///
+    /// Transactions this node does not track are left alone, so the tracker only grows with
+    /// submissions.
+    pub(crate) async fn record_block(&self, block: &BlockResult) {
+        let mut inner = self.0.write().await;
+        for outcome in &block.transactions {
+            if let Some(status) = inner.statuses.get_mut(&outcome.hash) {
+                // A copy sequenced again later does not move the transaction.
+                if !matches!(status, TransactionStatus::Sequenced { .. }) {
+                    *status = TransactionStatus::Sequenced {
+                        block: block.summary.height,
+                        index: outcome.index,
+                        timestamp: Some(block.summary.timestamp),
+                        verification: None,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Count the transaction `hash` as pending for `sender`, unless `sender` already has `limit`
+    /// transactions pending.
+    ///
+    /// A transaction stops counting once `executed` says it has been executed, once forwarding it
+    /// fails, or [`PENDING_EXPIRY`] after it was submitted. Returns whether `hash` was newly
+    /// counted, which it is not if it is a resubmission of a transaction still pending.
+    pub(crate) async fn reserve(
+        &self,
+        sender: Address,
+        hash: H256,
+        limit: usize,
+        executed: impl Fn(&H256) -> bool,
+    ) -> Result<bool, ApiError> {
+        let mut inner = self.0.write().await;
+        let mut pending = inner.by_sender.remove(&sender).unwrap_or_default();
+        pending.retain(|(hash, since)| inner.counts_as_pending(hash, *since, &executed));
+        let result = if pending.iter().any(|(pending, _)| *pending == hash) {
+            Ok(false)
+        } else if pending.len() >= limit {
+            Err(ApiError::TooManyPending { sender, limit })
+        } else {
+            pending.push((hash, Instant::now()));
+            Ok(true)
+        };
+        if !pending.is_empty() {
+            inner.by_sender.insert(sender, pending);
+        }
+        // Senders are otherwise only looked at when they submit again, so forget those whose
+        // transactions have all expired before they add up.
+        if inner.by_sender.len() > MAX_TRACKED_SUBMISSIONS {
+            inner.by_sender.retain(|_, pending| {
+                pending.retain(|(_, since)| since.elapsed() < PENDING_EXPIRY);
+                !pending.is_empty()
+            });
+        }
+        result
+    }
+
+    /// Stop counting the transaction `hash` as pending for `sender`, if it was never forwarded.
+    pub(crate) async fn release(&self, sender: Address, hash: &H256) {
+        let mut inner = self.0.write().await;
+        if let Some(pending) = inner.by_sender.get_mut(&sender) {
+            pending.retain(|(pending, _)| pending != hash);
+            if pending.is_empty() {
+                inner.by_sender.remove(&sender);
+            }
+        }
+    }
+
+    /// Number of transactions `sender` has pending, as counted by [`reserve`](Self::reserve).
+    pub(crate) async fn pending(
+        &self,
+        sender: &Address,
+        executed: impl Fn(&H256) -> bool,
+    ) -> usize {
+        let inner = self.0.read().await;
+        inner.by_sender.get(sender).map_or(0, |pending| {
+            pending
+                .iter()
+                .filter(|(hash, since)| inner.counts_as_pending(hash, *since, &executed))
+                .count()
+        })
+    }
+}
+
+impl BlockHook for SubmissionTracker {
+    fn on_block<'a>(
+        &'a self,
+        _height: u64,
+        block: &'a BlockResult,
+        _state: &'a State,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            self.record_block(block).await;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn name(&self) -> String {
+        "submission tracker".into()
+    }
+}
+
+/// Send a transaction to the sequencer with `client` and wait, for at most `limit`, for it to be
+/// accepted.
+///
+/// Failures of the sequencer are told apart from each other, and from mistakes of the client: a
+/// sequencer which cannot be reached or is failing is
+/// [unavailable](ApiError::SequencerUnavailable), one which refuses the transaction has
+/// [rejected](ApiError::SequencerRejected) it, and one which does not answer in time has
+/// [timed out](ApiError::SequencerTimeout). A request which could not reach the sequencer is
+/// retried once, after a short random delay.
+pub(crate) async fn forward_transaction(
+    client: &surf::Client,
+    sequencer_url: &Url,
+    txn: &Transaction,
+    limit: Duration,
+) -> Result<(), ApiError> {
+    let url = scrub_credentials(sequencer_url);
+    let body = serde_json::to_vec(txn)
+        .map_err(|err| ApiError::catch_all(StatusCode::InternalServerError, err.to_string()))?;
+    let forward = async {
+        match send_transaction(client, sequencer_url, &body).await {
+            Err(SendError::Unreachable { reason }) => {
+                let jitter = rand::thread_rng().gen_range(0..=CONNECT_RETRY_JITTER_MS);
+                tracing::info!(
+                    target: API,
+                    "Could not reach the sequencer at {url}, retrying: {reason}"
+                );
+                sleep(CONNECT_RETRY_DELAY + Duration::from_millis(jitter)).await;
+                send_transaction(client, sequencer_url, &body).await
+            }
+            result => result,
+        }
+    };
+    match async_std::future::timeout(limit, forward).await {
+        Ok(result) => result.map_err(|err| err.into_api_error(url)),
+        Err(_) => Err(ApiError::SequencerTimeout {
+            url,
+            reason: format!("no response within {} ms", limit.as_millis()),
+        }),
+    }
+}
+
+/// Why a single request to the sequencer failed.
+enum SendError {
+    /// The request did not get a response, for example because the connection was refused or the
+    /// host name could not be resolved.
+    Unreachable { reason: String },
+    /// The sequencer responded with an error.
+    Status { status: u16, message: String },
+}
+
+impl SendError {
+    fn into_api_error(self, url: String) -> ApiError {
+        match self {
+            Self::Unreachable { reason } => ApiError::SequencerUnavailable { url, reason },
+            Self::Status {
+                status: 408 | 504,
+                message,
+            } => ApiError::SequencerTimeout {
+                url,
+                reason: message,
+            },
+            Self::Status { status, message } if (400..500).contains(&status) => {
+                ApiError::SequencerRejected {
+                    url,
+                    status,
+                    message,
+                }
+            }
+            Self::Status { status, message } => ApiError::SequencerUnavailable {
+                url,
+                reason: format!("responded with {status}: {message}"),
+            },
+        }
+    }
+}
+
+async fn send_transaction(
+    client: &surf::Client,
+    sequencer_url: &Url,
+    body: &[u8],
+) -> Result<(), SendError> {
+    let url = sequencer_url
+        .join("submit/submit")
+        .map_err(|err| SendError::Unreachable {
+            reason: err.to_string(),
+        })?;
+    let mut res = client
+        .post(url)
+        .content_type(surf::http::mime::JSON)
+        .body(body)
+        .await
+        .map_err(|err| SendError::Unreachable {
+            reason: err.to_string(),
+        })?;
+    if res.status().is_success() {
+        return Ok(());
+    }
+    Err(SendError::Status {
+        status: res.status().into(),
+        message: res.body_string().await.unwrap_or_default(),
+    })
+}
+
+/// `url` as a string without the user name and password it may carry, so that it can be shown to
+/// clients and in logs.
+pub(crate) fn scrub_credentials(url: &Url) -> String {
+    let mut url = url.clone();
+    // These only fail for URLs which cannot have credentials in the first place.
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.to_string()
+}
+
+/// How forwarding to the sequencer is going, as reported in the executor status.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForwardingStats {
+    /// Transactions waiting for a worker to forward them.
+    pub queue_depth: usize,
+    /// Transactions the sequencer accepted.
+    pub forwarded: u64,
+    /// Attempts which failed because the sequencer could not be reached or was failing.
+    pub unavailable: u64,
+    /// Attempts the sequencer refused.
+    pub rejected: u64,
+    /// Attempts the sequencer did not answer in time.
+    pub timed_out: u64,
+    /// Milliseconds the sequencer took to accept the last transaction it accepted, if any.
+    pub last_latency_ms: Option<u64>,
+    /// Mean milliseconds the sequencer took to accept each transaction it accepted, if any.
+    pub mean_latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    forwarded: AtomicU64,
+    unavailable: AtomicU64,
+    rejected: AtomicU64,
+    timed_out: AtomicU64,
+    last_latency_ms: AtomicU64,
+    total_latency_ms: AtomicU64,
+}
+
+impl Counters {
+    fn record(&self, result: &Result<(), ApiError>, latency: Duration) {
+        let counter = match result {
+            Ok(()) => {
+                let latency = latency.as_millis() as u64;
+                self.last_latency_ms.store(latency, Ordering::Relaxed);
+                self.total_latency_ms.fetch_add(latency, Ordering::Relaxed);
+                &self.forwarded
+            }
+            Err(ApiError::SequencerRejected { .. }) => &self.rejected,
+            Err(ApiError::SequencerTimeout { .. }) => &self.timed_out,
+            Err(_) => &self.unavailable,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A transaction waiting to be forwarded.
+struct Submission {
+    hash: H256,
+    txn: Transaction,
+    /// Where to send the result, for a submitter waiting for it, which is the only attempt made.
+    /// Queued transactions are retried instead, and their result is recorded in the tracker.
+    reply: Option<Sender<Result<(), ApiError>>>,
+    /// When a waiting submitter gives up.
+    deadline: Instant,
+}
+
+/// A bounded queue of transactions, drained to the sequencer by a pool of workers.
+///
+/// The workers share one HTTP client, so connections to the sequencer are reused from one
+/// transaction to the next rather than set up for each.
+#[derive(Clone, Debug)]
+pub(crate) struct ForwardingQueue {
+    sender: Sender<Submission>,
+    tracker: SubmissionTracker,
+    url: String,
+    timeout: Duration,
+    counters: Arc<Counters>,
+}
+
+impl ForwardingQueue {
+    /// Create a queue holding at most `capacity` transactions and spawn `workers` tasks draining
+    /// it, each of which waits at most `timeout` for the sequencer to accept a transaction.
+    pub(crate) fn spawn(
+        sequencer_url: Url,
+        capacity: usize,
+        workers: usize,
+        timeout: Duration,
+        tracker: SubmissionTracker,
+    ) -> Self {
+        let (sender, receiver) = channel::bounded(capacity);
+        let forwarder = Forwarder {
+            client: surf::Client::new(),
+            url: sequencer_url.clone(),
+            counters: Default::default(),
+        };
+        for _ in 0..workers.max(1) {
+            spawn(drain(
+                forwarder.clone(),
+                receiver.clone(),
+                timeout,
+                tracker.clone(),
+            ));
+        }
+        Self {
+            sender,
+            tracker,
+            url: scrub_credentials(&sequencer_url),
+            timeout,
+            counters: forwarder.counters,
+        }
+    }
+
+    /// Forward a transaction and wait, for at most the queue's timeout, for the sequencer to
+    /// accept it. The transaction waits its turn in the queue like any other, and is not forwarded
+    /// at all if the timeout passes first.
+    pub(crate) async fn forward(&self, hash: H256, txn: Transaction) -> Result<(), ApiError> {
+        let (reply, result) = channel::bounded(1);
+        let submission = Submission {
+            hash,
+            txn,
+            reply: Some(reply),
+            deadline: Instant::now() + self.timeout,
+        };
+        if self.sender.try_send(submission).is_err() {
+            return Err(ApiError::QueueFull {
+                retry_after_secs: QUEUE_FULL_RETRY_AFTER_SECS,
+            });
+        }
+        match async_std::future::timeout(self.timeout, result.recv()).await {
+            Ok(Ok(result)) => result,
+            // The workers only stop once the queue is dropped, which it is not while it is used.
+            Ok(Err(_)) => Err(ApiError::catch_all(
+                StatusCode::InternalServerError,
+                "transaction dropped by the forwarding queue".into(),
+            )),
+            Err(_) => Err(ApiError::SequencerTimeout {
+                url: self.url.clone(),
+                reason: format!("no response within {} ms", self.timeout.as_millis()),
+            }),
+        }
+    }
+
+    /// Queue a transaction for forwarding without waiting for the sequencer.
+    pub(crate) async fn enqueue(
+        &self,
+        hash: H256,
+        txn: Transaction,
+    ) -> Result<SubmissionTicket, ApiError> {
+        // Mark the transaction queued before a worker can possibly pick it up, so its status only
+        // ever moves forward.
+        let previous = self.tracker.get(&hash).await;
+        self.tracker.set(hash, TransactionStatus::Queued).await;
+        let submission = Submission {
+            hash,
+            txn,
+            reply: None,
+            deadline: Instant::now(),
+        };
+        match self.sender.try_send(submission) {
+            Ok(()) => Ok(SubmissionTicket {
+                hash,
+                status: TransactionStatus::Queued,
+            }),
+            Err(TrySendError::Full(_)) | Err(TrySendError::Closed(_)) => {
+                // A resubmission must not erase what we knew about the original.
+                match previous {
+                    TransactionStatus::Unknown => self.tracker.remove(&hash).await,
+                    previous => self.tracker.set(hash, previous).await,
+                }
+                Err(ApiError::QueueFull {
+                    retry_after_secs: QUEUE_FULL_RETRY_AFTER_SECS,
+                })
+            }
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ForwardingStats {
+        let forwarded = self.counters.forwarded.load(Ordering::Relaxed);
+        let total_latency_ms = self.counters.total_latency_ms.load(Ordering::Relaxed);
+        ForwardingStats {
+            queue_depth: self.sender.len(),
+            forwarded,
+            unavailable: self.counters.unavailable.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+            timed_out: self.counters.timed_out.load(Ordering::Relaxed),
+            last_latency_ms: (forwarded > 0)
+                .then(|| self.counters.last_latency_ms.load(Ordering::Relaxed)),
+            mean_latency_ms: total_latency_ms.checked_div(forwarded),
+        }
+    }
+}
+
+/// What the workers of a [`ForwardingQueue`] share.
+#[derive(Clone, Debug)]
+struct Forwarder {
+    client: surf::Client,
+    url: Url,
+    counters: Arc<Counters>,
+}
+
+impl Forwarder {
+    async fn forward(&self, txn: &Transaction, limit: Duration) -> Result<(), ApiError> {
+        let start = Instant::now();
+        let result = forward_transaction(&self.client, &self.url, txn, limit).await;
+        self.counters.record(&result, start.elapsed());
+        result
+    }
+}
+
+async fn drain(
+    forwarder: Forwarder,
+    receiver: Receiver<Submission>,
+    timeout: Duration,
+    tracker: SubmissionTracker,
+) {
+    while let Ok(submission) = receiver.recv().await {
+        let Submission {
+            hash,
+            txn,
+            reply,
+            deadline,
+        } = submission;
+        if let Some(reply) = reply {
+            // Only forward while the submitter is still waiting, and only for as long as it waits.
+            let limit = deadline.saturating_duration_since(Instant::now());
+            if reply.is_closed() || limit.is_zero() {
+                continue;
+            }
+            reply.try_send(forwarder.forward(&txn, limit).await).ok();
+            continue;
+        }
+
+        let mut attempt = 1;
+        let status = loop {
+            match forwarder.forward(&txn, timeout).await {
+                Ok(()) => break TransactionStatus::Pending,
+                Err(err) if attempt < MAX_FORWARD_ATTEMPTS => {
+                    tracing::warn!(
+                        target: API,
+                        "Failed to forward transaction {hash:?}, retrying: {err}"
+                    );
+                    sleep(Duration::from_millis(100 << attempt)).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    tracing::error!(
+                        target: API,
+                        "Giving up forwarding transaction {hash:?}: {err}"
+                    );
+                    break TransactionStatus::Failed {
+                        reason: err.to_string(),
+                    };
+                }
+            }
+        };
+        tracker.set(hash, status).await;
+    }
+}
@@ -0,0 +1,289 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! A scriptable stand-in for the sequencer, for fast executor tests.
+//!
+//! [`MockQueryService`] serves the availability API from a short chain of blocks held in memory,
+//! and can be told to withhold blocks, fail requests, or go down altogether. [`commit_blocks`]
+//! posts the commitments of those blocks to a HotShot contract on Anvil, as the commitment task
+//! would, so that an executor can run against the pair without any consensus running.
+//!
+//! The blocks are real: they are recorded once per test run, by [`mock_chain`], from a query
+//! service in front of an in-process HotShot network, so that headers, namespace proofs and
+//! commitments are all consistent with each other. Every test after the first which uses the mock
+//! therefore starts in milliseconds.
+
+use async_std::task::{sleep, spawn};
+use commit::Committable;
+use ethers::types::{Bytes, U256};
+use futures::{future::ready, FutureExt};
+use hotshot::types::SystemContextHandle;
+use hotshot_contract_bindings::hot_shot::HotShot;
+use hotshot_query_service::availability::BlockQueryData;
+use portpicker::pick_unused_port;
+use sequencer::{
+    api::{
+        endpoints::NamespaceProofQueryData,
+        options::{Http, Options},
+    },
+    context::SequencerContext,
+    network,
+    persistence::fs,
+    testing::init_hotshot_handles,
+    Header, Node, SeqTypes,
+};
+use sequencer_utils::Signer;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use surf_disco::Url;
+use tempfile::TempDir;
+use tide::{Body, Response, StatusCode};
+
+use crate::commitments::commitment_to_u256;
+
+pub(crate) mod fixtures;
+
+/// Number of blocks in the chain served by [`MockQueryService`].
+pub(crate) const MOCK_CHAIN_LEN: usize = 5;
+
+/// The rollup whose namespace proofs [`MockQueryService`] serves. Namespaces of other rollups are
+/// never available.
+pub(crate) const MOCK_VM_ID: u64 = 1000;
+
+/// A block as served by the availability API.
+#[derive(Clone, Debug)]
+pub(crate) struct MockBlock {
+    /// The commitment of the header, as the HotShot contract stores it.
+    pub commitment: U256,
+    pub header: Value,
+    block: Value,
+    namespace: Value,
+}
+
+/// The chain served by [`MockQueryService`], recorded the first time it is needed.
+pub(crate) fn mock_chain() -> &'static [MockBlock] {
+    static CHAIN: OnceLock<Vec<MockBlock>> = OnceLock::new();
+    // Record on a thread of its own, since this may be called from within a test's executor.
+    CHAIN.get_or_init(|| {
+        std::thread::spawn(|| async_std::task::block_on(record_chain(MOCK_CHAIN_LEN)))
+            .join()
+            .unwrap()
+    })
+}
+
+async fn record_chain(len: usize) -> Vec<MockBlock> {
+    let port = pick_unused_port().unwrap();
+    let nodes = init_hotshot_handles().await;
+    let tmp_dir = TempDir::new().unwrap();
+    start_query_service(port, tmp_dir.path().join("tmp_storage"), nodes[0].clone()).await;
+    for node in &nodes {
+        node.hotshot.start_consensus().await;
+    }
+
+    let url: Url = format!("http://localhost:{port}/availability")
+        .parse()
+        .unwrap();
+    let client = surf_disco::Client::<hotshot_query_service::Error>::new(url);
+    client.connect(None).await;
+    let mut chain = vec![];
+    for height in 0..len {
+        let header = loop {
+            match client
+                .get::<Header>(&format!("header/{height}"))
+                .send()
+                .await
+            {
+                Ok(header) => break header,
+                Err(_) => sleep(Duration::from_millis(100)).await,
+            }
+        };
+        let block: BlockQueryData<SeqTypes> =
+            client.get(&format!("block/{height}")).send().await.unwrap();
+        let namespace: NamespaceProofQueryData = client
+            .get(&format!("block/{height}/namespace/{MOCK_VM_ID}"))
+            .send()
+            .await
+            .unwrap();
+        chain.push(MockBlock {
+            commitment: commitment_to_u256(header.commit()),
+            header: serde_json::to_value(header).unwrap(),
+            block: serde_json::to_value(block).unwrap(),
+            namespace: serde_json::to_value(namespace).unwrap(),
+        });
+    }
+    chain
+}
+
+/// Serve the APIs of the sequencer node `node` on `port`, storing its data under `storage_path`.
+pub(crate) async fn start_query_service<N: network::Type>(
+    port: u16,
+    storage_path: PathBuf,
+    node: SystemContextHandle<SeqTypes, Node<N>>,
+) {
+    let init_handle = Box::new(move |_| {
+        ready(SequencerContext::new(
+            node,
+            0,
+            Default::default(),
+            Default::default(),
+            None,
+        ))
+        .boxed()
+    });
+    Options::from(Http { port })
+        .submit(Default::default())
+        .status(Default::default())
+        .query_fs(Default::default(), fs::Options { path: storage_path })
+        .serve(init_handle)
+        .await
+        .unwrap();
+}
+
+/// Post the commitments of `blocks` to `hotshot`, as the commitment task would once they were
+/// decided.
+pub(crate) async fn commit_blocks(hotshot: &HotShot<Signer>, blocks: &[MockBlock]) {
+    hotshot
+        .new_blocks(
+            blocks.iter().map(|block| block.commitment).collect(),
+            vec![Bytes::default(); blocks.len()],
+        )
+        .send()
+        .await
+        .unwrap()
+        .await
+        .unwrap();
+}
+
+#[derive(Debug)]
+struct MockState {
+    blocks: Vec<MockBlock>,
+    available: usize,
+    failures: VecDeque<StatusCode>,
+    down: bool,
+    requests: usize,
+}
+
+/// An availability API serving [`mock_chain`], with faults injected on demand.
+#[derive(Clone, Debug)]
+pub(crate) struct MockQueryService {
+    url: Url,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockQueryService {
+    /// Start serving `blocks`, all of them available.
+    pub fn start(blocks: &[MockBlock]) -> Self {
+        let state = Arc::new(Mutex::new(MockState {
+            blocks: blocks.to_vec(),
+            available: blocks.len(),
+            failures: Default::default(),
+            down: false,
+            requests: 0,
+        }));
+        let port = pick_unused_port().unwrap();
+        let mut server = tide::with_state(state.clone());
+        server.at("/availability/*path").get(serve);
+        spawn(server.listen(format!("0.0.0.0:{port}")));
+        Self {
+            url: format!("http://localhost:{port}").parse().unwrap(),
+            state,
+        }
+    }
+
+    /// The URL to configure the executor with, as for a sequencer node.
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// Only serve the first `n` blocks. The rest are not found, as when the query service lags
+    /// behind the HotShot contract.
+    pub fn set_available(&self, n: usize) {
+        self.state.lock().unwrap().available = n;
+    }
+
+    /// Fail the next `n` requests for block data with `status`.
+    pub fn fail_next(&self, n: usize, status: StatusCode) {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .extend(std::iter::repeat(status).take(n));
+    }
+
+    /// Fail every request, including health checks, until brought back up.
+    pub fn set_down(&self, down: bool) {
+        self.state.lock().unwrap().down = down;
+    }
+
+    /// Number of requests for block data received so far, including failed ones.
+    pub fn requests(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+}
+
+async fn serve(req: tide::Request<Arc<Mutex<MockState>>>) -> tide::Result {
+    let path = req.param("path")?;
+    let mut state = req.state().lock().unwrap();
+    if state.down {
+        return Ok(Response::new(StatusCode::ServiceUnavailable));
+    }
+    if path == "healthcheck" {
+        return Ok(Body::from_json(&())?.into());
+    }
+
+    state.requests += 1;
+    if let Some(status) = state.failures.pop_front() {
+        return Ok(Response::new(status));
+    }
+    let segments = path.split('/').collect::<Vec<_>>();
+    let (height, field): (&str, fn(&MockBlock) -> &Value) = match segments[..] {
+        ["header", height] => (height, |block: &MockBlock| &block.header),
+        ["block", height] => (height, |block: &MockBlock| &block.block),
+        ["block", height, "namespace", vm_id] if vm_id == MOCK_VM_ID.to_string() => {
+            (height, |block: &MockBlock| &block.namespace)
+        }
+        _ => return Ok(Response::new(StatusCode::NotFound)),
+    };
+    match height
+        .parse::<usize>()
+        .ok()
+        .filter(|height| *height < state.available)
+        .and_then(|height| state.blocks.get(height))
+    {
+        Some(block) => Ok(Body::from_json(field(block))?.into()),
+        None => Ok(Response::new(StatusCode::NotFound)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn test_mock_query_service_faults() {
+        let mock = MockQueryService::start(&[]);
+        let client = surf_disco::Client::<hotshot_query_service::Error>::new(
+            mock.url().join("availability").unwrap(),
+        );
+        assert!(client.connect(Some(Duration::from_secs(10))).await);
+
+        mock.fail_next(1, StatusCode::InternalServerError);
+        let err = client.get::<Value>("header/0").send().await.unwrap_err();
+        assert_eq!(
+            tide_disco::error::Error::status(&err),
+            StatusCode::InternalServerError
+        );
+        let err = client.get::<Value>("header/0").send().await.unwrap_err();
+        assert_eq!(tide_disco::error::Error::status(&err), StatusCode::NotFound);
+        assert_eq!(mock.requests(), 2);
+
+        mock.set_down(true);
+        assert!(!client.connect(Some(Duration::from_secs(1))).await);
+    }
+}
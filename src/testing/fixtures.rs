@@ -0,0 +1,247 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! A deterministic devnet, for tests which must reproduce exactly.
+//!
+//! The wallets are fixed: [`FixtureWallet::Alice`], [`FixtureWallet::Bob`] and
+//! [`FixtureWallet::Carol`] hold the private keys 1, 2 and 3, whose addresses are well known and
+//! documented on each of them. [`genesis`] gives each of them [`GENESIS_BALANCE`], and [`Devnet`]
+//! produces an exact sequence of blocks of transfers between them with [`Devnet::produce_blocks`].
+//!
+//! HotShot blocks depend on consensus timing, so devnet blocks are identified by fixed block
+//! commitments instead, as the [golden vectors](crate::vectors) are. Signatures are deterministic
+//! (RFC 6979), so the same calls produce the same transactions, receipts and state commitments on
+//! every run. The commitments of a short sequence are pinned in `fixtures/vectors/devnet.json`,
+//! which changes under the same rules as the golden vectors.
+
+use commit::{Commitment, Committable};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Address, U256},
+};
+use sequencer::NMTRoot;
+
+use crate::commitments::u256_to_commitment;
+use crate::state::{Amount, Nonce, State, TransactionOutcome};
+use crate::testing::MOCK_VM_ID;
+use crate::transaction::{SignedTransaction, Transaction};
+use crate::RollupVM;
+
+/// Balance of each fixture wallet at genesis.
+pub(crate) const GENESIS_BALANCE: Amount = 1_000_000;
+
+/// A wallet with a fixed key. These keys are public; never use them outside tests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FixtureWallet {
+    /// Private key 1, address `0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf`.
+    Alice,
+    /// Private key 2, address `0x2B5AD5c4795c026514f8317c7a215E218DcCD6cF`.
+    Bob,
+    /// Private key 3, address `0x6813Eb9362372EEF6200f3b1dbC3f819671cBA69`.
+    Carol,
+}
+
+impl FixtureWallet {
+    pub(crate) const ALL: [Self; 3] = [Self::Alice, Self::Bob, Self::Carol];
+
+    /// The private key, hex encoded.
+    pub(crate) fn key(self) -> String {
+        format!("{:064x}", self as u64 + 1)
+    }
+
+    pub(crate) fn wallet(self) -> LocalWallet {
+        self.key().parse().expect("fixture keys are valid")
+    }
+
+    pub(crate) fn address(self) -> Address {
+        self.wallet().address()
+    }
+}
+
+/// The allocation of the devnet: [`GENESIS_BALANCE`] for each fixture wallet.
+pub(crate) fn genesis_balances() -> Vec<(Address, Amount)> {
+    FixtureWallet::ALL
+        .iter()
+        .map(|wallet| (wallet.address(), GENESIS_BALANCE))
+        .collect()
+}
+
+/// The state of the devnet before any block, for the rollup served by the mock query service.
+pub(crate) fn genesis() -> State {
+    State::from_initial_balances(genesis_balances(), RollupVM::new(MOCK_VM_ID.into()))
+}
+
+/// A block produced by [`Devnet::produce_blocks`].
+#[derive(Clone, Debug)]
+pub(crate) struct DevnetBlock {
+    pub height: u64,
+    /// The block commitment, which is `height + 1`.
+    pub block: Commitment<NMTRoot>,
+    /// What became of each transaction, in the order they were applied.
+    pub receipts: Vec<TransactionOutcome>,
+    /// The state commitment after the block.
+    pub state_commitment: Commitment<State>,
+}
+
+/// A scripted sequencer, executing each block it produces on the devnet state.
+///
+/// The `k`th transfer, counting from 0 across all blocks, sends `k + 1` from the `k % 3`th fixture
+/// wallet to the next one, so Alice pays Bob, Bob pays Carol and Carol pays Alice in turn.
+#[derive(Clone, Debug)]
+pub(crate) struct Devnet {
+    state: State,
+    transfers: u64,
+    nonces: [Nonce; 3],
+}
+
+impl Default for Devnet {
+    fn default() -> Self {
+        Self {
+            state: genesis(),
+            transfers: 0,
+            nonces: [0; 3],
+        }
+    }
+}
+
+impl Devnet {
+    pub(crate) fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// The devnet state, to change the settings of the node executing it. Changing the rollup
+    /// state itself would break the scripted transfers.
+    pub(crate) fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    /// Produce and execute `n` blocks of `txs_per_block` transfers each.
+    pub(crate) fn produce_blocks(&mut self, txs_per_block: usize, n: usize) -> Vec<DevnetBlock> {
+        let mut blocks = vec![];
+        for _ in 0..n {
+            let mut transactions = vec![];
+            for _ in 0..txs_per_block {
+                transactions.push(self.next_transfer().into());
+            }
+            let height = self.state.block_height();
+            let block = u256_to_commitment(U256::from(height + 1))
+                .expect("any nonzero 32 bytes are a commitment");
+            let receipts = self
+                .state
+                .apply_block_with_transactions(block, transactions);
+            blocks.push(DevnetBlock {
+                height,
+                block,
+                receipts,
+                state_commitment: self.state.commit(),
+            });
+        }
+        blocks
+    }
+
+    fn next_transfer(&mut self) -> SignedTransaction {
+        let k = self.transfers;
+        self.transfers += 1;
+        let sender = (k % 3) as usize;
+        self.nonces[sender] += 1;
+        let transaction = Transaction {
+            amount: k + 1,
+            destination: FixtureWallet::ALL[(sender + 1) % 3].address(),
+            nonce: self.nonces[sender],
+            priority: 0,
+            vm_id: MOCK_VM_ID,
+            asset: None,
+        };
+        let signature = FixtureWallet::ALL[sender]
+            .wallet()
+            .sign_hash(transaction.signing_hash())
+            .expect("signing with a local key does not fail");
+        SignedTransaction::from_signature(transaction, signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitments::commitment_to_u256;
+    use crate::vectors::{tests::check, BlockVector, FORMAT_VERSION};
+    use serde_json::json;
+
+    #[test]
+    fn test_fixture_wallets() {
+        let addresses = FixtureWallet::ALL.map(|wallet| format!("{:?}", wallet.address()));
+        assert_eq!(
+            addresses,
+            [
+                "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf",
+                "0x2b5ad5c4795c026514f8317c7a215e218dccd6cf",
+                "0x6813eb9362372eef6200f3b1dbc3f819671cba69",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_devnet_deterministic() {
+        let mut devnet = Devnet::default();
+        let blocks = devnet.produce_blocks(2, 3);
+
+        // Transfers of 1 to 6 go round the three wallets twice, and all of them are applied.
+        assert_eq!(
+            blocks.iter().map(|block| block.height).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        for (k, receipt) in blocks.iter().flat_map(|block| &block.receipts).enumerate() {
+            assert!(receipt.applied(), "{receipt:?}");
+            assert_eq!(receipt.sender, Some(FixtureWallet::ALL[k % 3].address()));
+            assert_eq!(receipt.transaction.as_ref().unwrap().amount, k as u64 + 1);
+        }
+        let state = devnet.state();
+        for (wallet, balance) in [
+            (FixtureWallet::Alice, GENESIS_BALANCE + 4),
+            (FixtureWallet::Bob, GENESIS_BALANCE - 2),
+            (FixtureWallet::Carol, GENESIS_BALANCE - 2),
+        ] {
+            assert_eq!(state.get_balance(&wallet.address()), balance, "{wallet:?}");
+            assert_eq!(state.get_nonce(&wallet.address()), 2, "{wallet:?}");
+        }
+
+        // A second devnet reaches the same commitments, which are pinned like the golden vectors:
+        // a change to any of them is a change to the state format.
+        let mut again = Devnet::default();
+        let replayed = again.produce_blocks(2, 3);
+        assert_eq!(
+            replayed
+                .iter()
+                .map(|block| block.state_commitment)
+                .collect::<Vec<_>>(),
+            blocks
+                .iter()
+                .map(|block| block.state_commitment)
+                .collect::<Vec<_>>()
+        );
+        let vectors = blocks
+            .iter()
+            .map(|block| BlockVector {
+                block: commitment_to_u256(block.block),
+                transactions: block.receipts.iter().map(|receipt| receipt.hash).collect(),
+                applied: block
+                    .receipts
+                    .iter()
+                    .map(|receipt| receipt.applied())
+                    .collect(),
+                state_commitment: commitment_to_u256(block.state_commitment),
+            })
+            .collect::<Vec<_>>();
+        check(
+            "devnet.json",
+            json!({
+                "version": FORMAT_VERSION,
+                "genesis_commitment": commitment_to_u256(genesis().commit()),
+                "blocks": vectors,
+            }),
+        );
+    }
+}
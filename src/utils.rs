@@ -4,24 +4,195 @@
 // You should have received a copy of the MIT License
 // along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
 
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::commitments::commitment_to_u256;
+use crate::logging::NODE;
 use crate::state::State;
 use commit::Commitment;
 use contract_bindings::example_rollup::ExampleRollup;
-use ethers::{prelude::*, providers::Provider};
-use sequencer_utils::{commitment_to_u256, test_utils::TestL1System, Signer};
+use ethers::{
+    prelude::*,
+    providers::Provider,
+    signers::{coins_bip39::English, MnemonicBuilder},
+};
+use hotshot_contract_bindings::hot_shot::HotShot;
+use sequencer_utils::{connect_rpc, test_utils::TestL1System, Signer};
+use snafu::Snafu;
 use surf_disco::Url;
 
 pub type ExampleRollupContract = ExampleRollup<Signer>;
 
+/// How to deploy the rollup contract, and the HotShot contract it reads from if there is none yet.
+#[derive(Clone, Debug)]
+pub struct DeployOptions {
+    /// HotShot contract of the shared sequencer. If not set, a new one is deployed.
+    pub hotshot: Option<Address>,
+    /// Mnemonic of the account which deploys, and so owns, the contracts.
+    pub deployer_mnemonic: String,
+    pub deployer_account_index: u32,
+    /// Number of L1 blocks each deployment must be buried under before it is used.
+    pub confirmations: usize,
+    pub initial_state: Commitment<State>,
+    /// Seconds each state update can be challenged, or zero for a validity rollup. See
+    /// [`deploy_example_contract`].
+    pub challenge_window: u64,
+    /// The only account allowed to submit proofs, or zero for any account.
+    pub submitter: Address,
+}
+
+/// Contracts deployed by [`deploy_contracts`] or [`deploy_rollup_only`].
+#[derive(Clone, Debug)]
+pub struct Deployment {
+    pub hotshot: HotShot<Signer>,
+    pub rollup: ExampleRollupContract,
+    /// The transaction which deployed the HotShot contract, unless an existing one was used.
+    pub hotshot_tx: Option<H256>,
+    pub rollup_tx: H256,
+}
+
+/// An error deploying the contracts.
+#[derive(Clone, Debug, Snafu)]
+pub enum DeployError {
+    #[snafu(display("Unable to connect to the L1 at {url} with the deployer's key."))]
+    Connect { url: Url },
+    #[snafu(display("No HotShot contract to deploy the rollup contract against."))]
+    NoHotShot,
+    #[snafu(display("Error deploying the {contract} contract: {reason}"))]
+    Deploy {
+        contract: &'static str,
+        reason: String,
+    },
+    #[snafu(display(
+        "Rollup contract reads HotShot commitments from {actual:?} rather than {expected:?}."
+    ))]
+    WrongHotShot { expected: Address, actual: Address },
+}
+
+/// Deploy the rollup contract, and a new HotShot contract for it unless `opt.hotshot` names one.
+pub async fn deploy_contracts(
+    l1_url: &Url,
+    opt: &DeployOptions,
+) -> Result<Deployment, DeployError> {
+    if opt.hotshot.is_some() {
+        return deploy_rollup_only(l1_url, opt).await;
+    }
+    let deployer = connect_deployer(l1_url, opt).await?;
+    let deploy_err = |err: String| DeployError::Deploy {
+        contract: "HotShot",
+        reason: err,
+    };
+    let (hotshot, receipt) = HotShot::deploy(deployer, ())
+        .map_err(|err| deploy_err(err.to_string()))?
+        .confirmations(opt.confirmations)
+        .send_with_receipt()
+        .await
+        .map_err(|err| deploy_err(err.to_string()))?;
+    tracing::info!(target: NODE, "deployed HotShot contract at {:?}", hotshot.address());
+    let deployment = deploy_rollup_only(
+        l1_url,
+        &DeployOptions {
+            hotshot: Some(hotshot.address()),
+            ..opt.clone()
+        },
+    )
+    .await?;
+    Ok(Deployment {
+        hotshot_tx: Some(receipt.transaction_hash),
+        ..deployment
+    })
+}
+
+/// Deploy only the rollup contract, reading from the existing HotShot contract `opt.hotshot`.
+///
+/// Once the deployment is confirmed, the HotShot address stored by the new contract is checked
+/// against `opt.hotshot`.
+pub async fn deploy_rollup_only(
+    l1_url: &Url,
+    opt: &DeployOptions,
+) -> Result<Deployment, DeployError> {
+    let hotshot_address = opt.hotshot.ok_or(DeployError::NoHotShot)?;
+    let deployer = connect_deployer(l1_url, opt).await?;
+    let deploy_err = |err: String| DeployError::Deploy {
+        contract: "ExampleRollup",
+        reason: err,
+    };
+    let (rollup, receipt) = ExampleRollup::deploy(
+        deployer.clone(),
+        (
+            hotshot_address,
+            commitment_to_u256(opt.initial_state),
+            U256::from(opt.challenge_window),
+            opt.submitter,
+        ),
+    )
+    .map_err(|err| deploy_err(err.to_string()))?
+    .confirmations(opt.confirmations)
+    .send_with_receipt()
+    .await
+    .map_err(|err| deploy_err(err.to_string()))?;
+    tracing::info!(
+        target: NODE,
+        "deployed rollup contract at {:?} against HotShot contract {hotshot_address:?}",
+        rollup.address()
+    );
+
+    let actual = rollup
+        .hotshot()
+        .call()
+        .await
+        .map_err(|err| deploy_err(err.to_string()))?;
+    if actual != hotshot_address {
+        return Err(DeployError::WrongHotShot {
+            expected: hotshot_address,
+            actual,
+        });
+    }
+    Ok(Deployment {
+        hotshot: HotShot::new(hotshot_address, deployer),
+        rollup,
+        hotshot_tx: None,
+        rollup_tx: receipt.transaction_hash,
+    })
+}
+
+async fn connect_deployer(l1_url: &Url, opt: &DeployOptions) -> Result<Arc<Signer>, DeployError> {
+    let deployer = connect_rpc(
+        l1_url,
+        &opt.deployer_mnemonic,
+        opt.deployer_account_index,
+        None,
+    )
+    .await
+    .ok_or_else(|| DeployError::Connect {
+        url: l1_url.clone(),
+    })?;
+    Ok(Arc::new(deployer))
+}
+
+/// Deploy a rollup contract starting from `initial_state`.
+///
+/// With a `challenge_window` of zero seconds, each verified state update is final at once.
+/// Otherwise the contract runs in optimistic mode, and an update is only final once it has gone
+/// unchallenged for that long and someone calls `finalizeBlock`.
+///
+/// Only `submitter` may submit proofs, until the deployer, which owns the contract, rotates it with
+/// `setSubmitter`. The zero address lets any account submit.
 pub async fn deploy_example_contract(
     test_l1: &TestL1System,
     initial_state: Commitment<State>,
+    challenge_window: u64,
+    submitter: Address,
 ) -> ExampleRollupContract {
     ExampleRollup::deploy(
         test_l1.clients.deployer.provider.clone(),
-        (test_l1.hotshot.address(), commitment_to_u256(initial_state)),
+        (
+            test_l1.hotshot.address(),
+            commitment_to_u256(initial_state),
+            U256::from(challenge_window),
+            submitter,
+        ),
     )
     .unwrap()
     .send()
@@ -29,8 +200,266 @@ pub async fn deploy_example_contract(
     .unwrap()
 }
 
+/// The address of the account at `index` of `mnemonic`, as the executor derives its L1 wallet.
+pub fn wallet_address(mnemonic: &str, index: u32) -> Address {
+    MnemonicBuilder::<English>::default()
+        .phrase(mnemonic)
+        .index(index)
+        .expect("invalid account index")
+        .build()
+        .expect("invalid mnemonic")
+        .address()
+}
+
 pub fn create_provider(l1_url: &Url) -> Provider<Http> {
     let mut provider = Provider::try_from(l1_url.to_string()).unwrap();
     provider.set_interval(Duration::from_millis(10));
     provider
 }
+
+/// How to run a local Anvil L1 for tests.
+#[cfg(test)]
+#[derive(Clone, Debug)]
+pub(crate) struct AnvilConfig {
+    /// Mine a block this often, rounded down to whole seconds. If not set, a block is mined for
+    /// each transaction.
+    pub block_time: Option<Duration>,
+    /// Chain ID to report, instead of Anvil's default.
+    pub chain_id: Option<u64>,
+    /// JSON-RPC endpoint of a network to fork, instead of starting from an empty chain.
+    pub fork_url: Option<Url>,
+    /// Number of funded accounts, derived from Anvil's default mnemonic.
+    pub accounts: usize,
+}
+
+#[cfg(test)]
+impl Default for AnvilConfig {
+    fn default() -> Self {
+        Self {
+            block_time: Some(Duration::from_secs(1)),
+            chain_id: None,
+            fork_url: None,
+            accounts: 10,
+        }
+    }
+}
+
+/// A running Anvil L1, stopped when it is dropped.
+#[cfg(test)]
+pub(crate) struct TestAnvil {
+    instance: ethers::utils::AnvilInstance,
+    /// A wallet for each funded account, signing for the chain Anvil reports.
+    pub wallets: Vec<LocalWallet>,
+}
+
+#[cfg(test)]
+impl TestAnvil {
+    pub fn url(&self) -> Url {
+        self.instance.endpoint().parse().unwrap()
+    }
+
+    pub fn ws_url(&self) -> Url {
+        self.instance.ws_endpoint().parse().unwrap()
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.instance.chain_id()
+    }
+
+    /// Mine a block every `block_time`, rounded down to whole seconds, from now on.
+    pub async fn set_block_time(&self, block_time: Duration) {
+        create_provider(&self.url())
+            .request::<_, serde_json::Value>("evm_setIntervalMining", [block_time.as_secs()])
+            .await
+            .unwrap();
+    }
+}
+
+/// Start an Anvil L1 configured by `config`, and wait until it can serve transactions.
+#[cfg(test)]
+pub(crate) async fn spawn_anvil(config: AnvilConfig) -> TestAnvil {
+    use ethers::signers::Signer as _;
+
+    let mut anvil = ethers::utils::Anvil::new()
+        .arg("--accounts")
+        .arg(config.accounts.to_string());
+    if let Some(block_time) = config.block_time {
+        anvil = anvil.block_time(block_time.as_secs().max(1));
+    }
+    if let Some(chain_id) = config.chain_id {
+        anvil = anvil.chain_id(chain_id);
+    }
+    if let Some(fork_url) = &config.fork_url {
+        anvil = anvil.fork(fork_url.as_str());
+    }
+    let instance = anvil.spawn();
+
+    // Some endpoints, such as the fee history, which transactions cannot be filled in without, do
+    // not work until at least one block has been mined. Without a block time, mine it ourselves.
+    let provider = create_provider(&instance.endpoint().parse().unwrap());
+    while let Err(err) = provider.fee_history(1, BlockNumber::Latest, &[]).await {
+        tracing::warn!(target: NODE, "Anvil is not ready: {err}");
+        if config.block_time.is_none() {
+            provider
+                .request::<_, serde_json::Value>("evm_mine", ())
+                .await
+                .ok();
+        }
+        async_std::task::sleep(Duration::from_millis(100)).await;
+    }
+
+    let chain_id = instance.chain_id();
+    let wallets = instance
+        .keys()
+        .iter()
+        .map(|key| LocalWallet::from(key.clone()).with_chain_id(chain_id))
+        .collect();
+    TestAnvil { instance, wallets }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RollupVM;
+    use async_std::task::sleep;
+    use commit::Committable;
+    use ethers::signers::Signer as _;
+
+    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    fn options(hotshot: Option<Address>) -> DeployOptions {
+        DeployOptions {
+            hotshot,
+            deployer_mnemonic: TEST_MNEMONIC.to_string(),
+            deployer_account_index: 0,
+            confirmations: 2,
+            initial_state: State::from_initial_balances([], RollupVM::new(1.into())).commit(),
+            challenge_window: 0,
+            submitter: Address::zero(),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_deploy_fresh() {
+        let anvil = spawn_anvil(Default::default()).await;
+        let deployment = deploy_contracts(&anvil.url(), &options(None))
+            .await
+            .unwrap();
+        assert!(deployment.hotshot_tx.is_some());
+        assert_eq!(
+            deployment.rollup.hotshot().call().await.unwrap(),
+            deployment.hotshot.address()
+        );
+        assert_eq!(
+            deployment.rollup.owner().call().await.unwrap(),
+            wallet_address(TEST_MNEMONIC, 0)
+        );
+
+        // The deployment was confirmed before it was returned.
+        let provider = create_provider(&anvil.url());
+        let receipt = provider
+            .get_transaction_receipt(deployment.rollup_tx)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(provider.get_block_number().await.unwrap() > receipt.block_number.unwrap());
+    }
+
+    #[async_std::test]
+    async fn test_deploy_attach() {
+        let anvil = spawn_anvil(Default::default()).await;
+        let existing = TestL1System::deploy(create_provider(&anvil.url()))
+            .await
+            .unwrap()
+            .hotshot
+            .address();
+
+        let deployment = deploy_rollup_only(&anvil.url(), &options(Some(existing)))
+            .await
+            .unwrap();
+        assert_eq!(deployment.hotshot.address(), existing);
+        assert_eq!(deployment.hotshot_tx, None);
+        assert_eq!(deployment.rollup.hotshot().call().await.unwrap(), existing);
+
+        // Given a HotShot contract, a full deployment attaches to it too.
+        let deployment = deploy_contracts(&anvil.url(), &options(Some(existing)))
+            .await
+            .unwrap();
+        assert_eq!(deployment.hotshot.address(), existing);
+        assert_eq!(deployment.hotshot_tx, None);
+
+        assert!(matches!(
+            deploy_rollup_only(&anvil.url(), &options(None)).await,
+            Err(DeployError::NoHotShot)
+        ));
+    }
+
+    #[async_std::test]
+    async fn test_anvil_interval_mining() {
+        // Blocks are mined on an interval, with no transactions to prompt them.
+        let anvil = spawn_anvil(AnvilConfig {
+            block_time: Some(Duration::from_secs(1)),
+            ..Default::default()
+        })
+        .await;
+        let provider = create_provider(&anvil.url());
+        let start = provider.get_block_number().await.unwrap();
+        sleep(Duration::from_secs(3)).await;
+        assert!(provider.get_block_number().await.unwrap() >= start + 2);
+
+        // Without a block time, blocks are only mined for transactions.
+        let anvil = spawn_anvil(AnvilConfig {
+            block_time: None,
+            accounts: 2,
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(anvil.wallets.len(), 2);
+        let provider = create_provider(&anvil.url());
+        let start = provider.get_block_number().await.unwrap();
+        sleep(Duration::from_secs(2)).await;
+        assert_eq!(provider.get_block_number().await.unwrap(), start);
+        let client = SignerMiddleware::new(provider.clone(), anvil.wallets[0].clone());
+        client
+            .send_transaction(
+                TransactionRequest::new()
+                    .to(anvil.wallets[1].address())
+                    .value(1),
+                None,
+            )
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(provider.get_block_number().await.unwrap(), start + 1);
+    }
+
+    #[async_std::test]
+    async fn test_anvil_chain_id() {
+        let anvil = spawn_anvil(AnvilConfig {
+            chain_id: Some(1234),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(anvil.chain_id(), 1234);
+        let provider = create_provider(&anvil.url());
+        assert_eq!(provider.get_chainid().await.unwrap(), 1234.into());
+
+        // The wallets sign for the configured chain, so their transactions are accepted.
+        assert_eq!(anvil.wallets[0].chain_id(), 1234);
+        let client = SignerMiddleware::new(provider, anvil.wallets[0].clone());
+        let receipt = client
+            .send_transaction(
+                TransactionRequest::new()
+                    .to(anvil.wallets[1].address())
+                    .value(1),
+                None,
+            )
+            .await
+            .unwrap()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.status, Some(1.into()));
+    }
+}
@@ -0,0 +1,347 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Golden vectors: fixed inputs, and the outputs which every executor and the rollup contract must
+//! agree on byte for byte.
+//!
+//! The vectors are built from the keys of the [seed identities](crate::seed::SeedIdentity). Their
+//! signatures are deterministic (RFC 6979), so the vectors only change if an encoding, a hash, a
+//! weight or a commitment does. [`transaction_vectors`] covers the canonical encoding of each kind
+//! of transaction, and [`state_vectors`] the genesis state commitment, the commitments after each
+//! block of a scripted sequence, and the calldata of the proof of that sequence sent to L1.
+//!
+//! The tests compare them with the fixtures in `fixtures/vectors`, which other implementations can
+//! check themselves against as well.
+//!
+//! # Changing a format
+//!
+//! A change which alters any vector breaks every other executor, and every signature or proof
+//! made with the old format, so it must be deliberate:
+//! 1. Bump [`FORMAT_VERSION`].
+//! 2. Run `UPDATE_VECTORS=1 cargo test --lib vectors` to rewrite the fixtures.
+//! 3. Commit the new fixtures along with the change, saying why the format changed.
+//!
+//! Without the version bump, the tests refuse to rewrite the fixtures and fail with the
+//! difference instead. A missing fixture is a failure too, unless `UPDATE_VECTORS` is set to
+//! record it, so a deleted or never committed fixture cannot silently pass.
+
+use commit::{Commitment, Committable};
+use contract_bindings::example_rollup::{self, VerifyBlocksCall};
+use ethers::{
+    abi::AbiEncode,
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, Signature, H256, U256},
+};
+use sequencer::NMTRoot;
+use serde::{Deserialize, Serialize};
+
+use crate::commitments::{commitment_to_u256, u256_to_commitment};
+use crate::multisig::{MultisigConfig, MultisigTransfer, RegisterMultisig};
+use crate::prover::{BatchProof, Proof};
+use crate::seed::{initial_balances, SeedIdentity};
+use crate::state::{Amount, Nonce, State, Weight};
+use crate::transaction::{RollupTransaction, SignedTransaction, Transaction};
+use crate::RollupVM;
+
+/// Version of the formats pinned by the vectors. Bumped with every intentional change to them.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// VM ID of the rollup the vectors are built for, which is the one the demo uses.
+pub const VECTOR_VM_ID: u64 = 1;
+
+/// The canonical encoding of a transaction and what is derived from it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionVector {
+    pub name: String,
+    /// The canonical encoding, which is valid UTF-8.
+    pub encoding: String,
+    pub hash: H256,
+    pub weight: Weight,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionVectors {
+    pub version: u32,
+    /// The address of the multisig account the multisig vectors register and spend from.
+    pub multisig_address: Address,
+    pub transactions: Vec<TransactionVector>,
+}
+
+/// A block of the scripted sequence and the state after it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockVector {
+    /// The block commitment, which is fixed rather than taken from a sequencer block.
+    pub block: U256,
+    /// Hashes of the block's transactions, in the order they were executed.
+    pub transactions: Vec<H256>,
+    /// Whether each transaction was applied, in the same order.
+    pub applied: Vec<bool>,
+    pub state_commitment: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateVectors {
+    pub version: u32,
+    /// The commitment to the initial state of the demo rollup.
+    pub genesis_commitment: U256,
+    pub blocks: Vec<BlockVector>,
+    /// Calldata of the `verifyBlocks` call proving all of the blocks.
+    pub proof_calldata: Bytes,
+}
+
+/// A transfer signed with the key of a seed identity.
+fn transfer(
+    from: SeedIdentity,
+    to: SeedIdentity,
+    amount: Amount,
+    nonce: Nonce,
+    priority: Amount,
+) -> SignedTransaction {
+    let transaction = Transaction {
+        amount,
+        destination: to.wallet().address(),
+        nonce,
+        priority,
+        vm_id: VECTOR_VM_ID,
+        asset: None,
+    };
+    let signature = sign(&from.wallet(), transaction.signing_hash());
+    SignedTransaction::from_signature(transaction, signature)
+}
+
+fn sign(wallet: &LocalWallet, digest: H256) -> Signature {
+    wallet
+        .sign_hash(digest)
+        .expect("signing with a local key does not fail")
+}
+
+/// The multisig account of the vectors: any two of the three seed identities.
+fn multisig() -> MultisigConfig {
+    MultisigConfig::new(
+        [
+            SeedIdentity::Bob,
+            SeedIdentity::Alice,
+            SeedIdentity::Charlie,
+        ]
+        .iter()
+        .map(|identity| identity.wallet().address())
+        .collect(),
+        2,
+    )
+    .expect("the vector configuration is valid")
+}
+
+/// A transfer from the multisig account, signed by Alice and Charlie.
+fn multisig_transfer(amount: Amount, nonce: Nonce) -> MultisigTransfer {
+    let account = multisig().address();
+    let transaction = Transaction {
+        amount,
+        destination: SeedIdentity::Charlie.wallet().address(),
+        nonce,
+        priority: 0,
+        vm_id: VECTOR_VM_ID,
+        asset: None,
+    };
+    let digest = MultisigTransfer::signing_hash(account, &transaction);
+    MultisigTransfer {
+        account,
+        signatures: [SeedIdentity::Alice, SeedIdentity::Charlie]
+            .iter()
+            .map(|identity| sign(&identity.wallet(), digest))
+            .collect(),
+        transaction,
+    }
+}
+
+/// The named transactions of the vectors, one or more of each kind.
+fn transactions() -> Vec<(&'static str, RollupTransaction)> {
+    use SeedIdentity::*;
+    vec![
+        ("transfer", transfer(Bob, Alice, 100, 1, 0).into()),
+        (
+            "transfer_with_priority",
+            transfer(Alice, Charlie, 50, 1, 3).into(),
+        ),
+        (
+            "register_multisig",
+            RegisterMultisig::from(&multisig()).into(),
+        ),
+        ("multisig_transfer", multisig_transfer(200, 1).into()),
+    ]
+}
+
+pub fn transaction_vectors() -> TransactionVectors {
+    TransactionVectors {
+        version: FORMAT_VERSION,
+        multisig_address: multisig().address(),
+        transactions: transactions()
+            .into_iter()
+            .map(|(name, txn)| TransactionVector {
+                name: name.into(),
+                encoding: String::from_utf8(txn.encode()).expect("encodings are JSON"),
+                hash: txn.hash(),
+                weight: txn.weight(),
+            })
+            .collect(),
+    }
+}
+
+/// The state of the demo rollup before any block, with the VM ID of the vectors.
+pub fn genesis() -> State {
+    State::from_initial_balances(initial_balances(), RollupVM::new(VECTOR_VM_ID.into()))
+}
+
+/// The scripted sequence of blocks: transfers ordered by priority, the registration and funding of
+/// the multisig account, then a spend from it along with a replayed transfer, which is rejected.
+fn blocks() -> Vec<Vec<RollupTransaction>> {
+    use SeedIdentity::*;
+    let funding = Transaction {
+        amount: 500,
+        destination: multisig().address(),
+        nonce: 2,
+        priority: 0,
+        vm_id: VECTOR_VM_ID,
+        asset: None,
+    };
+    let signature = sign(&Bob.wallet(), funding.signing_hash());
+    vec![
+        vec![
+            transfer(Bob, Alice, 100, 1, 0).into(),
+            transfer(Alice, Charlie, 50, 1, 3).into(),
+        ],
+        vec![
+            RegisterMultisig::from(&multisig()).into(),
+            SignedTransaction::from_signature(funding, signature).into(),
+        ],
+        vec![
+            multisig_transfer(200, 1).into(),
+            transfer(Bob, Alice, 100, 1, 0).into(),
+        ],
+    ]
+}
+
+pub fn state_vectors() -> StateVectors {
+    let mut state = genesis();
+    let genesis_commitment = commitment_to_u256(state.commit());
+    let mut vectors = vec![];
+    let mut proofs = vec![];
+    for (i, transactions) in blocks().into_iter().enumerate() {
+        let block = U256::from(i + 1);
+        let block_commitment: Commitment<NMTRoot> =
+            u256_to_commitment(block).expect("any nonzero 32 bytes are a commitment");
+        let old_state = state.commit();
+        let outcomes = state.apply_block_with_transactions(block_commitment, transactions);
+        proofs.push(Proof {
+            block: block_commitment,
+            old_state,
+            new_state: state.commit(),
+        });
+        vectors.push(BlockVector {
+            block,
+            transactions: outcomes.iter().map(|outcome| outcome.hash).collect(),
+            applied: outcomes.iter().map(|outcome| outcome.applied()).collect(),
+            state_commitment: commitment_to_u256(state.commit()),
+        });
+    }
+
+    let batch = BatchProof::generate(&proofs).expect("the proofs form a chain");
+    let call = VerifyBlocksCall {
+        height: U256::zero(),
+        count: proofs.len() as u64,
+        next_state_commitment: commitment_to_u256(state.commit()),
+        proof: example_rollup::BatchProof::from(batch),
+    };
+    StateVectors {
+        version: FORMAT_VERSION,
+        genesis_commitment,
+        blocks: vectors,
+        proof_calldata: call.encode().into(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::{fs, io::ErrorKind, path::PathBuf};
+
+    fn fixture(file: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/vectors")
+            .join(file)
+    }
+
+    fn record(file: &str, vectors: &Value) {
+        let path = fixture(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, serde_json::to_string_pretty(vectors).unwrap() + "\n").unwrap();
+    }
+
+    /// Compare `vectors` with the fixture `file`, following the procedure in the module
+    /// documentation to change it. A missing fixture is only recorded with `UPDATE_VECTORS`.
+    pub(crate) fn check(file: &str, vectors: impl Serialize) {
+        let actual = serde_json::to_value(vectors).unwrap();
+        let expected: Value = match fs::read_to_string(fixture(file)) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap(),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                assert!(
+                    std::env::var_os("UPDATE_VECTORS").is_some(),
+                    "Golden vectors in {file} are missing. Rerun with UPDATE_VECTORS=1 to record \
+                    them, and commit them."
+                );
+                record(file, &actual);
+                return;
+            }
+            Err(err) => panic!("unable to read {file}: {err}"),
+        };
+        if actual == expected {
+            return;
+        }
+        if expected["version"] != actual["version"] && std::env::var_os("UPDATE_VECTORS").is_some()
+        {
+            record(file, &actual);
+            return;
+        }
+        panic!(
+            "Golden vectors in {file} have changed. Other executors and the rollup contract will \
+            disagree with this one. If the change is intentional, bump FORMAT_VERSION and rerun \
+            with UPDATE_VECTORS=1 (see the `vectors` module).\n\
+            expected: {expected:#}\n\
+            actual: {actual:#}"
+        );
+    }
+
+    #[test]
+    fn test_transaction_vectors() {
+        check("transactions.json", transaction_vectors());
+    }
+
+    #[test]
+    fn test_state_vectors() {
+        let vectors = state_vectors();
+        // The script exercises what it is meant to: the priority transfer goes first, and only
+        // the replayed transfer is rejected.
+        assert_eq!(
+            vectors.blocks[0].transactions[0],
+            transactions()[1].1.hash()
+        );
+        assert_eq!(
+            vectors
+                .blocks
+                .iter()
+                .map(|block| block.applied.clone())
+                .collect::<Vec<_>>(),
+            [[true, true], [true, true], [true, false]]
+        );
+        check("state.json", vectors);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        assert_eq!(transaction_vectors(), transaction_vectors());
+        assert_eq!(state_vectors(), state_vectors());
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Watching how long the executor takes to execute each block.
+//!
+//! Executing a block goes through the [`Phase`]s of decoding its payloads, applying its
+//! transactions, computing the new state commitment and generating its proof, and a [`PhaseTimer`]
+//! times each of them. A block which takes longer than the
+//! [slow block threshold](WatchdogOptions::slow_block) is logged as a warning with the time spent
+//! in each phase.
+//!
+//! With a [hard limit](WatchdogOptions::block_limit), blocks are executed on a copy of the state,
+//! which only replaces the state once the block is done. A block which takes longer than that is
+//! abandoned and recorded as a [`QuarantinedBlock`], which the API serves to the operator, and a
+//! critical alert is raised. The executor then stops executing blocks, since skipping one would
+//! fork the state from every other executor of the rollup; the node keeps serving the state it
+//! had before the block until it is restarted.
+
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a block may take to execute before it is logged as slow, by default.
+pub const DEFAULT_SLOW_BLOCK_THRESHOLD: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug)]
+pub struct WatchdogOptions {
+    /// Warn about each block which takes longer than this to execute. Never, if not set.
+    pub slow_block: Option<Duration>,
+    /// Quarantine a block which takes longer than this to execute, and stop executing. Blocks are
+    /// executed in place, without a limit, if not set.
+    pub block_limit: Option<Duration>,
+}
+
+impl Default for WatchdogOptions {
+    fn default() -> Self {
+        Self {
+            slow_block: Some(DEFAULT_SLOW_BLOCK_THRESHOLD),
+            block_limit: None,
+        }
+    }
+}
+
+/// A step in executing a block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Phase {
+    /// Decoding the payloads in the rollup's namespace as rollup transactions.
+    Decode,
+    /// Crediting deposits, delivering messages and applying the transactions.
+    Apply,
+    /// Computing the state commitment after the block and recording its summary.
+    Commit,
+    /// Generating the proof of the block.
+    Prove,
+}
+
+/// Milliseconds spent in each phase of executing a block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    pub decode_ms: u64,
+    pub apply_ms: u64,
+    pub commit_ms: u64,
+    pub prove_ms: u64,
+}
+
+impl PhaseTimings {
+    pub fn total_ms(&self) -> u64 {
+        self.decode_ms + self.apply_ms + self.commit_ms + self.prove_ms
+    }
+
+    fn add(&mut self, phase: Phase, elapsed: Duration) {
+        let ms = match phase {
+            Phase::Decode => &mut self.decode_ms,
+            Phase::Apply => &mut self.apply_ms,
+            Phase::Commit => &mut self.commit_ms,
+            Phase::Prove => &mut self.prove_ms,
+        };
+        *ms += elapsed.as_millis() as u64;
+    }
+}
+
+impl Display for PhaseTimings {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "decode {} ms, apply {} ms, commit {} ms, prove {} ms",
+            self.decode_ms, self.apply_ms, self.commit_ms, self.prove_ms
+        )
+    }
+}
+
+/// Times the phases of a block as it is executed.
+///
+/// Clones share their timings, so the executor can tell how far a block it gave up on had got
+/// while another thread is still executing it.
+#[derive(Clone, Debug, Default)]
+pub struct PhaseTimer {
+    inner: Arc<Mutex<TimerState>>,
+    /// Time added to a phase, so tests can make a block slow.
+    #[cfg(test)]
+    delay: Option<(Phase, Duration)>,
+}
+
+#[derive(Debug, Default)]
+struct TimerState {
+    timings: PhaseTimings,
+    current: Option<(Phase, Instant)>,
+}
+
+impl PhaseTimer {
+    /// A timer which sleeps for `delay` at the start of `phase`.
+    #[cfg(test)]
+    pub(crate) fn delayed(phase: Phase, delay: Duration) -> Self {
+        Self {
+            delay: Some((phase, delay)),
+            ..Default::default()
+        }
+    }
+
+    /// End the current phase, if any, and start `phase`.
+    pub(crate) fn start(&self, phase: Phase) {
+        {
+            let mut inner = self.lock();
+            inner.end(Instant::now());
+            inner.current = Some((phase, Instant::now()));
+        }
+        #[cfg(test)]
+        if let Some((delayed, delay)) = self.delay {
+            if delayed == phase {
+                std::thread::sleep(delay);
+            }
+        }
+    }
+
+    /// End the current phase.
+    pub(crate) fn finish(&self) {
+        self.lock().end(Instant::now());
+    }
+
+    /// The phase in progress, if the block is not done.
+    pub fn phase(&self) -> Option<Phase> {
+        self.lock().current.map(|(phase, _)| phase)
+    }
+
+    /// Time spent in each phase so far, including the one in progress.
+    pub fn timings(&self) -> PhaseTimings {
+        let inner = self.lock();
+        let mut timings = inner.timings;
+        if let Some((phase, start)) = inner.current {
+            timings.add(phase, start.elapsed());
+        }
+        timings
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TimerState> {
+        // The timings are only added to, so they are fine to use after a panic.
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl TimerState {
+    fn end(&mut self, now: Instant) {
+        if let Some((phase, start)) = self.current.take() {
+            self.timings.add(phase, now.duration_since(start));
+        }
+    }
+}
+
+/// A block the executor gave up on after it exceeded the hard limit on execution time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinedBlock {
+    /// Height of the rollup block.
+    pub height: u64,
+    /// Commitment of the block's transactions, as in its HotShot header.
+    pub block: U256,
+    /// Path on the query service of the rollup's transactions in the block, with the proof that
+    /// they are all of them, to reproduce the execution.
+    pub namespace_path: String,
+    /// The phase the block was in when it was abandoned.
+    pub phase: Option<Phase>,
+    /// Time spent in each phase until the block was abandoned.
+    pub timings: PhaseTimings,
+    /// The limit the block exceeded, in milliseconds.
+    pub limit_ms: u64,
+    /// When the block was abandoned, in seconds since the Unix epoch.
+    pub quarantined_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_timer() {
+        let timer = PhaseTimer::delayed(Phase::Apply, Duration::from_millis(50));
+        assert_eq!(timer.phase(), None);
+        timer.start(Phase::Decode);
+        timer.start(Phase::Apply);
+        assert_eq!(timer.phase(), Some(Phase::Apply));
+
+        // The phase in progress counts, and a clone sees the same timings.
+        let watcher = timer.clone();
+        assert!(watcher.timings().apply_ms >= 50);
+        timer.start(Phase::Commit);
+        timer.finish();
+        assert_eq!(watcher.phase(), None);
+        let timings = watcher.timings();
+        assert!(timings.apply_ms >= 50);
+        assert!(timings.total_ms() >= timings.apply_ms);
+        assert_eq!(timings.prove_ms, 0);
+        assert_eq!(timer.timings(), timings);
+    }
+}
@@ -0,0 +1,640 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Push notifications of executor events to HTTP endpoints.
+//!
+//! Each configured webhook receives a JSON `POST` for every event it subscribes to. The body is
+//! signed with HMAC-SHA256 under the webhook's shared secret, and the hex-encoded signature is sent
+//! in the [`SIGNATURE_HEADER`] header as `sha256=<signature>`, so receivers can check that a
+//! notification came from this node.
+//!
+//! Notifications are queued and delivered by a background task per webhook, so a slow or failing
+//! endpoint never holds up the executor. Failed deliveries are retried with backoff. Notifications
+//! which still cannot be delivered, or which do not fit in the queue, are appended to a dead-letter
+//! log instead.
+
+use async_std::{
+    channel::{self, Receiver, Sender, TrySendError},
+    fs::OpenOptions,
+    io::WriteExt,
+    sync::{Arc, Mutex},
+    task::{sleep, spawn},
+};
+use ethers::{
+    types::{Address, H256},
+    utils::hex,
+};
+use futures::future::{BoxFuture, FutureExt};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use surf_disco::Url;
+
+use crate::alert::{Alert, AlertSink};
+use crate::hooks::BlockHook;
+use crate::logging::EXECUTOR;
+use crate::state::{Amount, BlockResult, BlockSummary, State};
+
+/// Header carrying the signature of a notification.
+pub const SIGNATURE_HEADER: &str = "X-Rollup-Signature";
+
+/// Number of notifications which may wait to be delivered to each webhook.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Number of attempts made to deliver a notification before giving up on it.
+const MAX_DELIVERY_ATTEMPTS: u32 = 4;
+
+/// Delay before the first retry of a failed delivery. Each later retry waits twice as long.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// An event a webhook can subscribe to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    /// A block was executed.
+    BlockExecuted,
+    /// The rollup contract accepted a proof sent by this node.
+    ProofVerified,
+    /// A transfer of at least `threshold` of the native asset was applied.
+    LargeTransfer { threshold: Amount },
+    /// The executor raised an [`Alert`].
+    Alert,
+}
+
+impl Display for WebhookEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BlockExecuted => write!(f, "block-executed"),
+            Self::ProofVerified => write!(f, "proof-verified"),
+            Self::LargeTransfer { threshold } => write!(f, "large-transfer:{threshold}"),
+            Self::Alert => write!(f, "alert"),
+        }
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = String;
+
+    /// Parse `block-executed`, `proof-verified`, `large-transfer:<threshold>` or `alert`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "block-executed" => Ok(Self::BlockExecuted),
+            None if s == "proof-verified" => Ok(Self::ProofVerified),
+            None if s == "alert" => Ok(Self::Alert),
+            Some(("large-transfer", threshold)) => Ok(Self::LargeTransfer {
+                threshold: threshold
+                    .parse()
+                    .map_err(|err| format!("invalid large transfer threshold: {err}"))?,
+            }),
+            _ => Err(format!(
+                "unknown webhook event {s}, expected block-executed, proof-verified, \
+                large-transfer:<threshold> or alert"
+            )),
+        }
+    }
+}
+
+/// An endpoint to notify, and the events it subscribes to.
+#[derive(Clone)]
+pub struct WebhookConfig {
+    pub url: Url,
+    pub events: Vec<WebhookEvent>,
+    /// Key under which notifications are signed.
+    pub secret: String,
+}
+
+impl Debug for WebhookConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("url", &self.url)
+            .field("events", &self.events)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+/// The body of a webhook notification.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event")]
+pub enum Notification {
+    BlockExecuted {
+        summary: BlockSummary,
+    },
+    ProofVerified {
+        first_block: u64,
+        num_blocks: u64,
+        l1_tx: H256,
+        l1_block: u64,
+    },
+    LargeTransfer {
+        height: u64,
+        hash: H256,
+        sender: Option<Address>,
+        destination: Address,
+        amount: Amount,
+    },
+    Alert {
+        alert: Alert,
+    },
+}
+
+/// The signature of `body` under `secret`, hex encoded.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Queues notifications for delivery to the configured webhooks.
+#[derive(Clone, Debug)]
+pub struct WebhookSender {
+    endpoints: Arc<Vec<Endpoint>>,
+    dead_letters: DeadLetters,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: Url,
+    events: Vec<WebhookEvent>,
+    queue: Sender<Vec<u8>>,
+}
+
+impl WebhookSender {
+    /// Start delivering notifications to `webhooks`, logging undeliverable ones to
+    /// `dead_letter_path`.
+    ///
+    /// Delivery tasks finish once every clone of the sender has been dropped and their queues are
+    /// empty.
+    pub fn new(webhooks: &[WebhookConfig], dead_letter_path: PathBuf) -> Self {
+        let dead_letters = DeadLetters::new(dead_letter_path);
+        let endpoints = webhooks
+            .iter()
+            .map(|webhook| {
+                let (queue, pending) = channel::bounded(QUEUE_CAPACITY);
+                spawn(deliver(webhook.clone(), pending, dead_letters.clone()));
+                Endpoint {
+                    url: webhook.url.clone(),
+                    events: webhook.events.clone(),
+                    queue,
+                }
+            })
+            .collect();
+        Self {
+            endpoints: Arc::new(endpoints),
+            dead_letters,
+        }
+    }
+
+    /// Queue a notification for every webhook subscribed to an event matching `matches`.
+    async fn notify(&self, notification: &Notification, matches: impl Fn(&WebhookEvent) -> bool) {
+        let body = serde_json::to_vec(notification).expect("Serialization should not fail");
+        for endpoint in self.endpoints.iter() {
+            if !endpoint.events.iter().any(&matches) {
+                continue;
+            }
+            match endpoint.queue.try_send(body.clone()) {
+                Ok(()) => {}
+                Err(TrySendError::Full(body)) => {
+                    self.dead_letters
+                        .record(&endpoint.url, &body, "delivery queue is full")
+                        .await
+                }
+                Err(TrySendError::Closed(body)) => {
+                    self.dead_letters
+                        .record(&endpoint.url, &body, "delivery task has stopped")
+                        .await
+                }
+            }
+        }
+    }
+
+    /// Notify subscribers that the rollup contract accepted a proof of the `num_blocks` blocks
+    /// starting at `first_block`.
+    pub async fn proof_verified(
+        &self,
+        first_block: u64,
+        num_blocks: u64,
+        l1_tx: H256,
+        l1_block: u64,
+    ) {
+        let notification = Notification::ProofVerified {
+            first_block,
+            num_blocks,
+            l1_tx,
+            l1_block,
+        };
+        self.notify(&notification, |event| *event == WebhookEvent::ProofVerified)
+            .await;
+    }
+}
+
+impl BlockHook for WebhookSender {
+    fn on_block<'a>(
+        &'a self,
+        height: u64,
+        block: &'a BlockResult,
+        _state: &'a State,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            let notification = Notification::BlockExecuted {
+                summary: block.summary.clone(),
+            };
+            self.notify(&notification, |event| *event == WebhookEvent::BlockExecuted)
+                .await;
+
+            for txn in block.transactions.iter().filter(|txn| txn.applied()) {
+                // Thresholds are in the native asset, so transfers of other assets are not
+                // compared with them.
+                let Some(transfer) = txn
+                    .transaction
+                    .as_ref()
+                    .filter(|transfer| transfer.asset.is_none())
+                else {
+                    continue;
+                };
+                let amount = transfer.amount;
+                let notification = Notification::LargeTransfer {
+                    height,
+                    hash: txn.hash,
+                    sender: txn.sender,
+                    destination: transfer.destination,
+                    amount,
+                };
+                self.notify(&notification, |event| match event {
+                    WebhookEvent::LargeTransfer { threshold } => amount >= *threshold,
+                    _ => false,
+                })
+                .await;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn name(&self) -> String {
+        "webhooks".into()
+    }
+}
+
+impl AlertSink for WebhookSender {
+    fn emit(&self, alert: &Alert) {
+        // Queueing only waits when the queue is full and the notification goes to the dead-letter
+        // log instead, which the executor should not wait for either.
+        let webhooks = self.clone();
+        let notification = Notification::Alert {
+            alert: alert.clone(),
+        };
+        spawn(async move {
+            webhooks
+                .notify(&notification, |event| *event == WebhookEvent::Alert)
+                .await
+        });
+    }
+
+    fn name(&self) -> String {
+        "webhooks".into()
+    }
+}
+
+/// Deliver each queued notification to `webhook`, retrying failures.
+async fn deliver(webhook: WebhookConfig, pending: Receiver<Vec<u8>>, dead_letters: DeadLetters) {
+    let client = surf::Client::new();
+    while let Ok(body) = pending.recv().await {
+        let signature = format!("sha256={}", sign(&webhook.secret, &body));
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let req = client
+                .post(webhook.url.clone())
+                .header(SIGNATURE_HEADER, signature.as_str())
+                .content_type(surf::http::mime::JSON)
+                .body(body.clone());
+            let err = match req.await {
+                Ok(res) if res.status().is_success() => break,
+                Ok(res) => format!("webhook responded with {}", res.status()),
+                Err(err) => err.to_string(),
+            };
+            if attempt >= MAX_DELIVERY_ATTEMPTS {
+                tracing::warn!(
+                    target: EXECUTOR,
+                    "giving up on webhook notification to {} after {attempt} attempts: {err}",
+                    webhook.url
+                );
+                dead_letters.record(&webhook.url, &body, &err).await;
+                break;
+            }
+            tracing::info!(
+                target: EXECUTOR,
+                "webhook notification to {} failed, retrying in {delay:?}: {err}",
+                webhook.url
+            );
+            sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+/// A notification which could not be delivered.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub url: String,
+    pub notification: Notification,
+    pub error: String,
+}
+
+/// Log of undeliverable notifications, one JSON [`DeadLetter`] per line.
+#[derive(Clone, Debug)]
+struct DeadLetters {
+    path: Arc<PathBuf>,
+    lock: Arc<Mutex<()>>,
+}
+
+impl DeadLetters {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path: Arc::new(path),
+            lock: Default::default(),
+        }
+    }
+
+    async fn record(&self, url: &Url, body: &[u8], error: &str) {
+        let letter = DeadLetter {
+            url: url.to_string(),
+            notification: serde_json::from_slice(body).expect("notification should deserialize"),
+            error: error.to_string(),
+        };
+        let mut line = serde_json::to_string(&letter).expect("Serialization should not fail");
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        let res: std::io::Result<()> = async {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path.as_ref())
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+            file.flush().await
+        }
+        .await;
+        if let Err(err) = res {
+            tracing::error!(
+                target: EXECUTOR,
+                "unable to record undelivered webhook notification in {:?}: {err}",
+                self.path
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{BlockTimestamp, TimestampSource, TransactionOutcome};
+    use crate::transaction::{Transaction, DEFAULT_VM_ID};
+    use crate::RollupVM;
+    use async_std::future::timeout;
+    use commit::Committable;
+    use portpicker::pick_unused_port;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tempfile::TempDir;
+
+    /// Requests received by a [`sink`]: the signature header and the body of each.
+    type Received = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+    /// Serve an endpoint which records every notification it receives, failing the first
+    /// `failures` requests with a server error.
+    fn sink(failures: u32) -> (Url, Received) {
+        let received = Received::default();
+        let attempts = Arc::new(AtomicU32::new(0));
+        let port = pick_unused_port().expect("No ports free");
+        let mut server = tide::new();
+        let requests = received.clone();
+        server.at("/hook").post(move |mut req: tide::Request<()>| {
+            let requests = requests.clone();
+            let attempts = attempts.clone();
+            async move {
+                if attempts.fetch_add(1, Ordering::SeqCst) < failures {
+                    return Ok(tide::Response::new(500));
+                }
+                let signature = req
+                    .header(SIGNATURE_HEADER)
+                    .map(|values| values.as_str().to_string())
+                    .unwrap_or_default();
+                let body = req.body_bytes().await?;
+                requests.lock().await.push((signature, body));
+                Ok(tide::Response::new(200))
+            }
+        });
+        spawn(server.listen(format!("0.0.0.0:{port}")));
+        (
+            format!("http://localhost:{port}/hook").parse().unwrap(),
+            received,
+        )
+    }
+
+    async fn wait_for(received: &Received, count: usize) -> Vec<(String, Vec<u8>)> {
+        timeout(Duration::from_secs(30), async {
+            loop {
+                let received = received.lock().await;
+                if received.len() >= count {
+                    break received.clone();
+                }
+                drop(received);
+                sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("notifications were not delivered")
+    }
+
+    fn transfer(amount: Amount, error: Option<&str>) -> TransactionOutcome {
+        TransactionOutcome {
+            hash: H256::from_low_u64_be(amount),
+            index: Some(0),
+            l1_block: None,
+            sender: Some(Address::repeat_byte(1)),
+            transaction: Some(Transaction {
+                amount,
+                destination: Address::repeat_byte(2),
+                nonce: 1,
+                priority: 0,
+                vm_id: DEFAULT_VM_ID,
+                asset: None,
+            }),
+            multisig: None,
+            message: None,
+            min_fee: None,
+            weight: 25_000,
+            error: error.map(String::from),
+            duplicate: false,
+        }
+    }
+
+    fn block(height: u64, transactions: Vec<TransactionOutcome>) -> (BlockResult, State) {
+        let state = State::from_initial_balances([], RollupVM::new(1.into()));
+        let block = BlockResult {
+            summary: BlockSummary {
+                height,
+                timestamp: BlockTimestamp {
+                    seconds: 1_700_000_000,
+                    source: TimestampSource::HotShot,
+                },
+                state_commitment: state.commit(),
+                payloads: Default::default(),
+                weight: 0,
+                outbox: None,
+                l1_tx: None,
+            },
+            transactions,
+        };
+        (block, state)
+    }
+
+    #[test]
+    fn test_parse_events() {
+        for event in [
+            WebhookEvent::BlockExecuted,
+            WebhookEvent::ProofVerified,
+            WebhookEvent::LargeTransfer { threshold: 1000 },
+            WebhookEvent::Alert,
+        ] {
+            assert_eq!(event.to_string().parse::<WebhookEvent>().unwrap(), event);
+        }
+        "large-transfer".parse::<WebhookEvent>().unwrap_err();
+        "large-transfer:lots".parse::<WebhookEvent>().unwrap_err();
+        "block-finalized".parse::<WebhookEvent>().unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_notifications() {
+        let (url, received) = sink(0);
+        let dir = TempDir::new().unwrap();
+        let webhooks = WebhookSender::new(
+            &[WebhookConfig {
+                url,
+                events: vec![
+                    WebhookEvent::BlockExecuted,
+                    WebhookEvent::ProofVerified,
+                    WebhookEvent::LargeTransfer { threshold: 50 },
+                ],
+                secret: "shh".into(),
+            }],
+            dir.path().join("dead-letters.jsonl"),
+        );
+
+        // Only applied transfers over the threshold are reported.
+        let (block, state) = block(
+            3,
+            vec![
+                transfer(100, None),
+                transfer(10, None),
+                transfer(200, Some("insufficient balance")),
+            ],
+        );
+        webhooks.on_block(3, &block, &state).await.unwrap();
+        let l1_tx = H256::repeat_byte(7);
+        webhooks.proof_verified(0, 4, l1_tx, 12).await;
+
+        let received = wait_for(&received, 3).await;
+        for (signature, body) in &received {
+            assert_eq!(*signature, format!("sha256={}", sign("shh", body)));
+        }
+        let notifications = received
+            .iter()
+            .map(|(_, body)| serde_json::from_slice::<Notification>(body).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            notifications,
+            [
+                Notification::BlockExecuted {
+                    summary: block.summary.clone()
+                },
+                Notification::LargeTransfer {
+                    height: 3,
+                    hash: H256::from_low_u64_be(100),
+                    sender: Some(Address::repeat_byte(1)),
+                    destination: Address::repeat_byte(2),
+                    amount: 100,
+                },
+                Notification::ProofVerified {
+                    first_block: 0,
+                    num_blocks: 4,
+                    l1_tx,
+                    l1_block: 12,
+                },
+            ]
+        );
+    }
+
+    #[async_std::test]
+    async fn test_retries_and_dead_letters() {
+        // One endpoint recovers after a couple of failures, the other never answers.
+        let (flaky, received) = sink(2);
+        let down: Url = format!("http://localhost:{}/hook", pick_unused_port().unwrap())
+            .parse()
+            .unwrap();
+        let dir = TempDir::new().unwrap();
+        let dead_letter_path = dir.path().join("dead-letters.jsonl");
+        let webhooks = WebhookSender::new(
+            &[
+                WebhookConfig {
+                    url: flaky,
+                    events: vec![WebhookEvent::ProofVerified],
+                    secret: "shh".into(),
+                },
+                WebhookConfig {
+                    url: down.clone(),
+                    events: vec![WebhookEvent::ProofVerified],
+                    secret: "shh".into(),
+                },
+            ],
+            dead_letter_path.clone(),
+        );
+
+        // Notifying does not wait for either endpoint.
+        timeout(
+            Duration::from_secs(1),
+            webhooks.proof_verified(0, 1, H256::zero(), 1),
+        )
+        .await
+        .unwrap();
+
+        let received = wait_for(&received, 1).await;
+        assert_eq!(received.len(), 1);
+
+        let notification = Notification::ProofVerified {
+            first_block: 0,
+            num_blocks: 1,
+            l1_tx: H256::zero(),
+            l1_block: 1,
+        };
+        let dead_letters = timeout(Duration::from_secs(30), async {
+            loop {
+                if let Ok(log) = async_std::fs::read_to_string(&dead_letter_path).await {
+                    if !log.is_empty() {
+                        break log;
+                    }
+                }
+                sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let dead_letters = dead_letters
+            .lines()
+            .map(|line| serde_json::from_str::<DeadLetter>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].url, down.to_string());
+        assert_eq!(dead_letters[0].notification, notification);
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! A small load test against the rollup API, as a smoke test of the load generator.
+
+#![cfg(feature = "client")]
+
+use async_std::{sync::RwLock, task::spawn};
+use ethers::{signers::Signer, types::U256};
+use example_l2::{
+    api::{serve, APIOptions},
+    client::RollupClient,
+    loadgen::{load_wallets, run, LoadError, LoadOptions, Thresholds},
+    seed::SeedIdentity,
+    state::State,
+    RollupVM,
+};
+use portpicker::pick_unused_port;
+use sequencer_utils::u256_to_commitment;
+use std::sync::Arc;
+use std::time::Duration;
+use surf_disco::Url;
+
+/// Stand in for the sequencer and the executor: each transaction submitted is executed right away,
+/// as a block of its own.
+fn executing_sequencer(state: Arc<RwLock<State>>) -> Url {
+    let port = pick_unused_port().unwrap();
+    let mut server = tide::with_state(state);
+    server
+        .at("/submit/submit")
+        .post(|mut req: tide::Request<Arc<RwLock<State>>>| async move {
+            let txn: sequencer::Transaction = req.body_json().await?;
+            let mut state = req.state().write().await;
+            let block = u256_to_commitment(U256::from(state.block_height() + 1)).unwrap();
+            state.apply_block_payloads(block, [txn]);
+            Ok(tide::Body::from_json(&())?)
+        });
+    spawn(server.listen(format!("0.0.0.0:{port}")));
+    format!("http://localhost:{port}").parse().unwrap()
+}
+
+#[async_std::test]
+async fn test_loadgen_smoke() {
+    let funder = SeedIdentity::Alice.wallet();
+    let state = Arc::new(RwLock::new(State::from_initial_balances(
+        [(funder.address(), 1_000_000)],
+        RollupVM::new(1.into()),
+    )));
+    let sequencer_url = executing_sequencer(state.clone());
+    let port = pick_unused_port().unwrap();
+    let options = APIOptions::new(port, sequencer_url);
+    let api_state = state.clone();
+    spawn(async move { serve(&options, api_state, Default::default()).await });
+    let client = RollupClient::new(format!("http://localhost:{port}").parse().unwrap());
+    assert!(client.connect(Some(Duration::from_secs(10))).await);
+
+    let load = LoadOptions {
+        wallets: 4,
+        rate: 50.,
+        transactions: Some(40),
+        execution_timeout: Duration::from_secs(10),
+        funding: 100,
+        poll_interval: Duration::from_millis(20),
+        ..Default::default()
+    };
+    let report = run(&client, &funder, &load).await.unwrap();
+
+    assert_eq!(report.submitted, 40);
+    assert_eq!(report.execution.count, 40, "{:?}", report.errors);
+    assert_eq!(report.error_count(), 0);
+    assert_eq!(report.verification.count, 0);
+    report.check(&Thresholds::default()).unwrap();
+
+    // Every transfer was applied, and moved funds between the wallets.
+    let state = state.read().await;
+    let wallets = load_wallets(&load);
+    let nonces = wallets
+        .iter()
+        .map(|wallet| state.get_nonce(&wallet.address()))
+        .sum::<u64>();
+    let balances = wallets
+        .iter()
+        .map(|wallet| state.get_balance(&wallet.address()))
+        .sum::<u64>();
+    assert_eq!(nonces, 40);
+    assert_eq!(balances, 400);
+    assert_eq!(state.get_balance(&funder.address()), 1_000_000 - 400);
+
+    // A load with no end is refused before anything is submitted.
+    let unbounded = LoadOptions {
+        transactions: None,
+        ..load
+    };
+    let err = run(&client, &funder, &unbounded).await.unwrap_err();
+    assert!(matches!(err, LoadError::Unbounded), "{err}");
+}
@@ -0,0 +1,235 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Run the executor and the API in separate processes sharing a data directory.
+
+#![cfg(unix)]
+
+use async_std::task::{sleep, spawn};
+use ethers::{signers::Signer, types::Address, utils::Anvil};
+use example_l2::{
+    seed::{initial_balances, SeedIdentity, INITIAL_BALANCE},
+    snapshot::write_snapshot,
+    state::State,
+    transaction::{SignedTransaction, Transaction, DEFAULT_VM_ID},
+    utils::create_provider,
+    RollupVM,
+};
+use portpicker::pick_unused_port;
+use sequencer_utils::test_utils::TestL1System;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use tempfile::{NamedTempFile, TempDir};
+
+/// A query service which has no blocks yet, which is all the node needs to start.
+fn empty_query_service() -> String {
+    let port = pick_unused_port().unwrap();
+    let mut server = tide::new();
+    server
+        .at("/availability/healthcheck")
+        .get(|_: tide::Request<()>| async { tide::Body::from_json(&()) });
+    spawn(server.listen(format!("0.0.0.0:{port}")));
+    format!("http://localhost:{port}")
+}
+
+/// Start the node in `mode`, serving its API on `api_port` and keeping its data in `data_dir`.
+fn start_node(mode: &str, api_port: u16, data_dir: &Path, log: &NamedTempFile) -> Command {
+    let mut node = Command::new(env!("CARGO_BIN_EXE_example-l2"));
+    node.env("RUST_LOG", "info")
+        .env("ESPRESSO_DEMO_ROLLUP_MODE", mode)
+        .env("ESPRESSO_DEMO_ROLLUP_PORT", api_port.to_string())
+        .env("ESPRESSO_DEMO_ROLLUP_SNAPSHOT_DIR", data_dir)
+        .env("ESPRESSO_DEMO_ROLLUP_FOLLOW_INTERVAL_MS", "100")
+        .stdout(Stdio::from(log.reopen().unwrap()))
+        .stderr(Stdio::from(log.reopen().unwrap()));
+    node
+}
+
+/// Wait until the node on `api_port` reports `expected` as the balance of `address`.
+async fn wait_for_balance(node: &mut Child, api_port: u16, address: Address, expected: u64) {
+    let url = format!("http://localhost:{api_port}/rollup/balance/{address:?}");
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let mut balance = None;
+    while Instant::now() < deadline {
+        assert!(node.try_wait().unwrap().is_none(), "node exited early");
+        balance = surf::get(&url).recv_json::<u64>().await.ok();
+        if balance == Some(expected) {
+            return;
+        }
+        sleep(Duration::from_millis(200)).await;
+    }
+    panic!("balance of {address:?} on port {api_port} is {balance:?}, expected {expected}");
+}
+
+#[async_std::test]
+async fn test_separate_executor_and_api() {
+    let anvil = Anvil::new().block_time(1u64).spawn();
+    let provider = create_provider(&anvil.endpoint().parse().unwrap());
+    let test_l1 = TestL1System::deploy(provider).await.unwrap();
+    let sequencer_url = empty_query_service();
+    let data_dir = TempDir::new().unwrap();
+    let executor_log = NamedTempFile::new().unwrap();
+    let api_log = NamedTempFile::new().unwrap();
+
+    let executor_port = pick_unused_port().unwrap();
+    let mut executor = start_node("executor", executor_port, data_dir.path(), &executor_log)
+        .env("ESPRESSO_SEQUENCER_URL", &sequencer_url)
+        .env("ESPRESSO_DEMO_L1_HTTP_PROVIDER", anvil.endpoint())
+        .env("ESPRESSO_DEMO_L1_WS_PROVIDER", anvil.ws_endpoint())
+        .env(
+            "ESPRESSO_DEMO_HOTSHOT_ADDRESS",
+            format!("{:?}", test_l1.hotshot.address()),
+        )
+        .spawn()
+        .unwrap();
+    // The API node needs neither L1 nor the rollup contract.
+    let api_port = pick_unused_port().unwrap();
+    let mut api = start_node("api", api_port, data_dir.path(), &api_log)
+        .env("ESPRESSO_SEQUENCER_URL", &sequencer_url)
+        .spawn()
+        .unwrap();
+
+    // Both serve the initial balances.
+    let alice = SeedIdentity::Alice.wallet();
+    let bob = SeedIdentity::Bob.wallet().address();
+    wait_for_balance(
+        &mut executor,
+        executor_port,
+        alice.address(),
+        INITIAL_BALANCE,
+    )
+    .await;
+    wait_for_balance(&mut api, api_port, alice.address(), INITIAL_BALANCE).await;
+
+    // The executor refuses submissions, which are for the API nodes to forward.
+    let refused = surf::post(format!("http://localhost:{executor_port}/rollup/submit"))
+        .body_json(&())
+        .unwrap()
+        .await
+        .unwrap();
+    assert!(!refused.status().is_success());
+
+    // The API node moves to each newer snapshot in the shared directory.
+    let mut state = State::from_initial_balances(initial_balances(), RollupVM::new(1.into()));
+    let transfer = Transaction {
+        amount: 100,
+        destination: bob,
+        nonce: 1,
+        priority: 0,
+        vm_id: DEFAULT_VM_ID,
+        asset: None,
+    };
+    let transfer = SignedTransaction::new_with_wallet(transfer, &alice).await;
+    let block = sequencer_utils::u256_to_commitment(1.into()).unwrap();
+    state.apply_block_with_transactions(block, vec![transfer.into()]);
+    write_snapshot(data_dir.path(), &state, 3).unwrap();
+    wait_for_balance(&mut api, api_port, alice.address(), INITIAL_BALANCE - 100).await;
+    wait_for_balance(&mut api, api_port, bob, INITIAL_BALANCE + 100).await;
+
+    executor.kill().unwrap();
+    api.kill().unwrap();
+}
+
+#[async_std::test]
+async fn test_api_reads_whole_blocks() {
+    const BLOCKS: u64 = 40;
+    const READERS: usize = 8;
+
+    let sequencer_url = empty_query_service();
+    let data_dir = TempDir::new().unwrap();
+    let api_log = NamedTempFile::new().unwrap();
+    let api_port = pick_unused_port().unwrap();
+    let mut api = start_node("api", api_port, data_dir.path(), &api_log)
+        .env("ESPRESSO_SEQUENCER_URL", &sequencer_url)
+        .spawn()
+        .unwrap();
+    let alice = SeedIdentity::Alice.wallet();
+    wait_for_balance(&mut api, api_port, alice.address(), INITIAL_BALANCE).await;
+
+    // Readers check that every response shows the state of a single block, and that the node
+    // never goes back to an older one.
+    let url = format!(
+        "http://localhost:{api_port}/rollup/account/{:?}",
+        alice.address()
+    );
+    let readers = (0..READERS)
+        .map(|_| {
+            let url = url.clone();
+            spawn(async move {
+                let mut last_height = 0;
+                let mut reads = 0;
+                let deadline = Instant::now() + Duration::from_secs(60);
+                while last_height < BLOCKS {
+                    assert!(Instant::now() < deadline, "stuck at height {last_height}");
+                    let mut res = surf::get(&url).await.unwrap();
+                    assert!(res.status().is_success(), "{}", res.status());
+                    let header: u64 = res
+                        .header("X-Rollup-Height")
+                        .unwrap()
+                        .as_str()
+                        .parse()
+                        .unwrap();
+                    let account: serde_json::Value = res.body_json().await.unwrap();
+                    let height = account["height"].as_u64().unwrap();
+                    let balance = account["balance"].as_u64().unwrap();
+                    let next_nonce = account["next_nonce"].as_u64().unwrap();
+                    // Each block transfers 1 from alice.
+                    assert_eq!(height, next_nonce - 1, "{account}");
+                    assert_eq!(balance, INITIAL_BALANCE - height, "{account}");
+                    assert_eq!(header, height, "{account}");
+                    assert!(
+                        height >= last_height,
+                        "went back from {last_height}, {account}"
+                    );
+                    last_height = height;
+                    reads += 1;
+                }
+                reads
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let bob = SeedIdentity::Bob.wallet().address();
+    let mut state = State::from_initial_balances(initial_balances(), RollupVM::new(1.into()));
+    for height in 1..=BLOCKS {
+        let transfer = Transaction {
+            amount: 1,
+            destination: bob,
+            nonce: height,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let transfer = SignedTransaction::new_with_wallet(transfer, &alice).await;
+        let block = sequencer_utils::u256_to_commitment(height.into()).unwrap();
+        state.apply_block_with_transactions(block, vec![transfer.into()]);
+        write_snapshot(data_dir.path(), &state, 2).unwrap();
+        assert!(api.try_wait().unwrap().is_none(), "node exited early");
+        sleep(Duration::from_millis(50)).await;
+    }
+
+    for reader in readers {
+        assert!(reader.await > 0);
+    }
+    api.kill().unwrap();
+}
+
+#[test]
+fn test_mode_options_checked_at_startup() {
+    let data_dir = TempDir::new().unwrap();
+    let log = NamedTempFile::new().unwrap();
+    let exit = start_node("api", pick_unused_port().unwrap(), data_dir.path(), &log)
+        .env(
+            "ESPRESSO_DEMO_ROLLUP_CURSOR_PATH",
+            data_dir.path().join("cursor"),
+        )
+        .status()
+        .unwrap();
+    let output = std::fs::read_to_string(log.path()).unwrap();
+    assert_eq!(exit.code(), Some(1), "{output}");
+    assert!(output.contains("--cursor-path"), "{output}");
+}
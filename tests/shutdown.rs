@@ -0,0 +1,90 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Shut down the node binary with a signal, as a process supervisor would.
+
+#![cfg(unix)]
+
+use async_std::task::{sleep, spawn};
+use ethers::utils::Anvil;
+use example_l2::utils::create_provider;
+use portpicker::pick_unused_port;
+use sequencer_utils::test_utils::TestL1System;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+
+/// A query service which has no blocks yet, which is all the node needs to start.
+fn empty_query_service() -> String {
+    let port = pick_unused_port().unwrap();
+    let mut server = tide::new();
+    server
+        .at("/availability/healthcheck")
+        .get(|_: tide::Request<()>| async { tide::Body::from_json(&()) });
+    spawn(server.listen(format!("0.0.0.0:{port}")));
+    format!("http://localhost:{port}")
+}
+
+#[async_std::test]
+async fn test_sigterm_shutdown() {
+    let anvil = Anvil::new().block_time(1u64).spawn();
+    let provider = create_provider(&anvil.endpoint().parse().unwrap());
+    let test_l1 = TestL1System::deploy(provider).await.unwrap();
+    let api_port = pick_unused_port().unwrap();
+    let log = NamedTempFile::new().unwrap();
+    let mut node = Command::new(env!("CARGO_BIN_EXE_example-l2"))
+        .env("RUST_LOG", "info")
+        .env("ESPRESSO_DEMO_ROLLUP_PORT", api_port.to_string())
+        .env("ESPRESSO_SEQUENCER_URL", empty_query_service())
+        .env("ESPRESSO_DEMO_L1_HTTP_PROVIDER", anvil.endpoint())
+        .env("ESPRESSO_DEMO_L1_WS_PROVIDER", anvil.ws_endpoint())
+        .env(
+            "ESPRESSO_DEMO_HOTSHOT_ADDRESS",
+            format!("{:?}", test_l1.hotshot.address()),
+        )
+        .stdout(Stdio::from(log.reopen().unwrap()))
+        .stderr(Stdio::from(log.reopen().unwrap()))
+        .spawn()
+        .unwrap();
+
+    // Wait until the executor is waiting for blocks, which it reports by setting its proof interval
+    // once everything else is set up.
+    let status_url = format!("http://localhost:{api_port}/rollup/executor");
+    loop {
+        assert!(node.try_wait().unwrap().is_none(), "node exited early");
+        if let Ok(status) = surf::get(&status_url)
+            .recv_json::<serde_json::Value>()
+            .await
+        {
+            if status["proof_interval"].as_u64().unwrap_or_default() > 0 {
+                break;
+            }
+        }
+        sleep(Duration::from_millis(500)).await;
+    }
+
+    let killed = Command::new("kill")
+        .args(["-TERM", &node.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let exit = loop {
+        if let Some(exit) = node.try_wait().unwrap() {
+            break exit;
+        }
+        if Instant::now() > deadline {
+            node.kill().unwrap();
+            panic!("node did not exit after SIGTERM");
+        }
+        sleep(Duration::from_millis(100)).await;
+    };
+
+    let output = std::fs::read_to_string(log.path()).unwrap();
+    assert!(exit.success(), "node exited with {exit}:\n{output}");
+    assert!(output.contains("shutdown complete"), "{output}");
+    assert!(output.contains("dropped_proofs=0"), "{output}");
+}
@@ -0,0 +1,138 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Strict parsing of Ethereum addresses.
+//!
+//! `Address::from_str` accepts any 40 hex digits, so a typo in a checksummed address silently
+//! names a different account. The parser here additionally verifies the
+//! [EIP-55](https://eips.ethereum.org/EIPS/eip-55) checksum of mixed-case addresses.
+
+use ethers_core::{types::Address, utils::to_checksum};
+use serde::{Deserialize, Deserializer, Serialize};
+use snafu::Snafu;
+
+#[derive(Clone, Debug, PartialEq, Eq, Snafu, Serialize, Deserialize)]
+pub enum AddressError {
+    #[snafu(display("Address must start with 0x."))]
+    MissingPrefix,
+    #[snafu(display("Address must be 40 hex digits, found {length}."))]
+    InvalidLength { length: usize },
+    #[snafu(display("Address contains characters which are not hex digits."))]
+    InvalidHex,
+    #[snafu(display(
+        "Address checksum does not match. Did you mean {expected}? Use all lowercase to skip \
+        checksum validation."
+    ))]
+    ChecksumMismatch { expected: String },
+}
+
+/// Parse a `0x`-prefixed hex address.
+///
+/// All-lowercase and all-uppercase addresses are accepted as is. Mixed-case addresses must carry
+/// a valid EIP-55 checksum.
+pub fn parse_address(s: &str) -> Result<Address, AddressError> {
+    let digits = s.strip_prefix("0x").ok_or(AddressError::MissingPrefix)?;
+    if digits.len() != 40 {
+        return Err(AddressError::InvalidLength {
+            length: digits.len(),
+        });
+    }
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AddressError::InvalidHex);
+    }
+    let address: Address = digits.parse().map_err(|_| AddressError::InvalidHex)?;
+
+    let is_mixed_case = digits.chars().any(|c| c.is_ascii_lowercase())
+        && digits.chars().any(|c| c.is_ascii_uppercase());
+    let expected = checksummed(&address);
+    if is_mixed_case && expected != s {
+        return Err(AddressError::ChecksumMismatch { expected });
+    }
+    Ok(address)
+}
+
+/// The EIP-55 checksummed form of `address`, which is how addresses are shown to users.
+pub fn checksummed(address: &Address) -> String {
+    to_checksum(address, None)
+}
+
+/// Deserialize an address with [`parse_address`], for use with `#[serde(deserialize_with)]`.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Address, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_address(&s).map_err(serde::de::Error::custom)
+}
+
+/// Deserialize a list of addresses, each as [`deserialize`] does.
+pub fn deserialize_vec<'de, D>(deserializer: D) -> Result<Vec<Address>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| parse_address(s).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An example address from EIP-55.
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn test_lowercase() {
+        let address = parse_address(&CHECKSUMMED.to_lowercase()).unwrap();
+        assert_eq!(checksummed(&address), CHECKSUMMED);
+    }
+
+    #[test]
+    fn test_valid_checksum() {
+        let address = parse_address(CHECKSUMMED).unwrap();
+        assert_eq!(checksummed(&address), CHECKSUMMED);
+    }
+
+    #[test]
+    fn test_invalid_checksum() {
+        // Flip the case of a single letter.
+        let typo = CHECKSUMMED.replacen("aA", "Aa", 1);
+        assert_eq!(
+            parse_address(&typo),
+            Err(AddressError::ChecksumMismatch {
+                expected: CHECKSUMMED.to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_wrong_length() {
+        assert_eq!(
+            parse_address(&CHECKSUMMED[..40]),
+            Err(AddressError::InvalidLength { length: 38 })
+        );
+        assert_eq!(
+            parse_address(&format!("{CHECKSUMMED}00")),
+            Err(AddressError::InvalidLength { length: 42 })
+        );
+    }
+
+    #[test]
+    fn test_missing_prefix() {
+        assert_eq!(
+            parse_address(&CHECKSUMMED[2..]),
+            Err(AddressError::MissingPrefix)
+        );
+    }
+
+    #[test]
+    fn test_invalid_hex() {
+        let bad = CHECKSUMMED.replacen('5', "g", 1);
+        assert_eq!(parse_address(&bad), Err(AddressError::InvalidHex));
+    }
+}
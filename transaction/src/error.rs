@@ -0,0 +1,87 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+use ethers_core::types::Address;
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+
+use crate::address::checksummed;
+use crate::transaction::{Amount, Nonce, Weight};
+
+#[derive(Clone, Snafu, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum RollupError {
+    #[snafu(display("Error validating the transaction signature."))]
+    SignatureError,
+    #[snafu(display("Signature is not in canonical low-s form."))]
+    MalleableSignature,
+    #[snafu(display("Invalid signature recovery id {v}. Expected 27 or 28."))]
+    InvalidRecoveryId { v: u64 },
+    #[snafu(display("Signature recovers to the zero address."))]
+    ZeroAddressSigner,
+    #[snafu(display("Insufficient balance for sender: {}.", checksummed(address)))]
+    InsufficientBalance { address: Address },
+    #[snafu(display("Balance of {} would exceed the largest amount.", checksummed(address)))]
+    BalanceOverflow { address: Address },
+    #[snafu(display(
+        "Invalid nonce for sender {}. Found {actual}, expected {expected}",
+        checksummed(address)
+    ))]
+    InvalidNonce {
+        address: Address,
+        expected: Nonce,
+        actual: Nonce,
+    },
+    #[snafu(display(
+        "Invalid multisig threshold {threshold} for {signers} signers. Expected between 1 and the \
+        number of signers."
+    ))]
+    InvalidThreshold { threshold: u32, signers: usize },
+    #[snafu(display("A multisig account has at most {max} signers, found {signers}."))]
+    TooManySigners { signers: usize, max: usize },
+    #[snafu(display("Signer {} appears more than once.", checksummed(address)))]
+    DuplicateSigner { address: Address },
+    #[snafu(display("Multisig account {} is already registered.", checksummed(address)))]
+    MultisigAlreadyRegistered { address: Address },
+    #[snafu(display("No multisig account is registered at {}.", checksummed(address)))]
+    UnknownMultisig { address: Address },
+    #[snafu(display(
+        "{} is not a signer of multisig account {}.",
+        checksummed(signer),
+        checksummed(address)
+    ))]
+    NotASigner { address: Address, signer: Address },
+    #[snafu(display(
+        "Multisig account {} needs {threshold} signatures, found {signatures}.",
+        checksummed(address)
+    ))]
+    BelowThreshold {
+        address: Address,
+        threshold: u32,
+        signatures: usize,
+    },
+    #[snafu(display(
+        "The block weight budget of {max} was used up before this transaction, of weight {weight}."
+    ))]
+    BlockWeightExhausted { max: Weight, weight: Weight },
+    #[snafu(display("Message payload of {size} bytes exceeds the limit of {max}."))]
+    PayloadTooLarge { size: usize, max: usize },
+    #[snafu(display("Message is addressed to the rollup sending it, VM {vm}."))]
+    MessageToSelf { vm: u64 },
+    #[snafu(display("A block sends at most {max} messages."))]
+    OutboxFull { max: usize },
+    #[snafu(display("Transaction is a copy of one already applied in block {height}."))]
+    DuplicateTransaction { height: u64 },
+    #[snafu(display("Transaction is for VM {actual}, not this rollup, VM {expected}."))]
+    WrongVm { expected: u64, actual: u64 },
+    #[snafu(display("Asset {} is not an asset of this rollup.", checksummed(asset)))]
+    UnknownAsset { asset: Address },
+    #[snafu(display("Funds sent to {} could never be spent.", checksummed(address)))]
+    InvalidDestination { address: Address },
+    #[snafu(display("Fee {fee} is below the minimum fee of {min}."))]
+    FeeTooLow { fee: Amount, min: Amount },
+    #[snafu(display("{} may not change the minimum fee.", checksummed(address)))]
+    NotFeeAdmin { address: Address },
+}
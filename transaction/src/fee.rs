@@ -0,0 +1,113 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Changes to the minimum fee of the rollup.
+//!
+//! Transfers pay their [priority](crate::transaction::Transaction::priority) as a fee, which is
+//! burned, and the rollup rejects transfers which pay less than its minimum fee. The minimum is
+//! part of the rollup state, and only the fee admin named in the rollup's chain configuration can
+//! change it, with a [`SignedSetMinFee`] transaction signed as it would sign a transfer.
+//!
+//! A change does not take effect in the block which applies it, but in the first block at or
+//! after its [effective height](SetMinFee::effective_height), so that transfers already signed
+//! under the old minimum, and waiting to be sequenced, are not rejected by a change they could not
+//! have seen. A later change replaces one which has not taken effect yet.
+
+use crate::error::RollupError;
+use crate::transaction::{normalize, recover, Amount, Nonce};
+use ethers_core::{
+    types::{Address, Signature, H256},
+    utils::hash_message,
+};
+use ethers_signers::Signer;
+use serde::{Deserialize, Serialize};
+
+/// A change to the minimum fee, as signed by the fee admin.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetMinFee {
+    /// The new minimum fee.
+    pub value: Amount,
+    /// Height of the first block whose transfers must pay `value`.
+    pub effective_height: u64,
+    /// One more than the nonce of the last change applied, so that a change cannot be applied
+    /// twice.
+    pub nonce: Nonce,
+    /// VM ID of the rollup whose minimum fee is changed.
+    pub vm_id: u64,
+}
+
+impl SetMinFee {
+    /// The canonical encoding of the change, which is what is signed.
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Serialization should not fail")
+    }
+
+    /// The digest a signature on this change signs: the EIP-191 personal message hash of the
+    /// encoded change.
+    pub fn signing_hash(&self) -> H256 {
+        hash_message(self.encode())
+    }
+}
+
+/// A transaction changing the minimum fee.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignedSetMinFee {
+    pub set_min_fee: SetMinFee,
+    pub signature: Signature,
+}
+
+impl SignedSetMinFee {
+    /// Sign `set_min_fee` with any [`Signer`].
+    pub async fn new<S: Signer>(set_min_fee: SetMinFee, signer: &S) -> Result<Self, S::Error> {
+        let signature = signer.sign_message(set_min_fee.encode()).await?;
+        Ok(Self {
+            set_min_fee,
+            signature: normalize(signature),
+        })
+    }
+
+    /// The account which signed the change, recovered from a signature in canonical form as for
+    /// a [`SignedTransaction`](crate::transaction::SignedTransaction).
+    pub fn signer(&self) -> Result<Address, RollupError> {
+        recover(&self.signature, self.set_min_fee.encode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::RollupTransaction;
+    use ethers_signers::LocalWallet;
+
+    #[async_std::test]
+    async fn test_set_min_fee() {
+        let wallet = LocalWallet::new(&mut rand::thread_rng());
+        let change = SetMinFee {
+            value: 10,
+            effective_height: 5,
+            nonce: 1,
+            vm_id: 1,
+        };
+        let signed = SignedSetMinFee::new(change, &wallet).await.unwrap();
+        assert_eq!(signed.signer(), Ok(wallet.address()));
+
+        // The signature covers the change.
+        let mut raised = signed.clone();
+        raised.set_min_fee.value = 20;
+        assert_ne!(raised.signer(), Ok(wallet.address()));
+
+        // A fee change is its own kind of rollup transaction, with no fee of its own.
+        let txn = RollupTransaction::from(signed.clone());
+        assert_eq!(txn.priority(), 0);
+        assert_eq!(txn.vm_id(), Some(1));
+        assert!(matches!(
+            RollupTransaction::decode(&txn.encode()),
+            Some(RollupTransaction::SetMinFee(decoded)) if decoded == signed
+        ));
+    }
+}
@@ -0,0 +1,24 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Transactions of the example rollup: their canonical encoding, hashing, signing and
+//! verification.
+//!
+//! This crate has none of the node's dependencies, so that clients can construct and sign
+//! transactions anywhere, including in the browser: it builds for `wasm32-unknown-unknown`, and
+//! with the `wasm` feature it exports JavaScript bindings (see [`wasm`]). The rollup node enables
+//! the `vm` feature, which implements the sequencer's `VmTransaction` trait.
+
+pub mod address;
+pub mod error;
+pub mod fee;
+pub mod message;
+pub mod multisig;
+pub mod transaction;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use error::RollupError;
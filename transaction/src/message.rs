@@ -0,0 +1,334 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Messages between rollups sharing the sequencer.
+//!
+//! An account sends a [`Message`] to an account of another rollup with an [`OutboundMessage`]
+//! transaction, which it signs as it would a transfer. The messages a block sends are its outbox,
+//! found by [`outbox`] from the sending rollup's namespace alone, so that the rollup they are
+//! addressed to can read them from the same block, check the namespace against the block, and
+//! deliver them. Both sides run this code, so any two instances of the rollup with different VM
+//! IDs agree on what was sent.
+//!
+//! # Format
+//!
+//! A message as delivered is a [`CrossVmMessage`]. Its [hash](CrossVmMessage::hash) is the
+//! keccak256 hash of its JSON encoding, with the fields in the order they are declared, VM IDs and
+//! the nonce as numbers, and addresses and the payload as lowercase `0x`-prefixed hex strings. The
+//! [commitment](outbox_commitment) to the outbox of a block is the keccak256 hash of
+//! [`OUTBOX_DOMAIN`] followed by the hash of each message, in the order the block sent them.
+//!
+//! # Validity
+//!
+//! Whether a message is sent depends only on the block, never on the state of the sending rollup,
+//! which the receiving rollup does not have: the signature must be valid, the payload at most
+//! [`MAX_PAYLOAD_BYTES`], the destination another rollup, and at most [`MAX_OUTBOX_MESSAGES`]
+//! messages are sent per block, in the order they were sequenced. Messages count against the weight
+//! budget of the block like any other transaction, and which of them fit in it also depends only on
+//! the block, as found by [`budgeted_messages`]. The receiving rollup finds them with its own
+//! budget, so rollups exchanging messages must share one. The receiving rollup delivers a message
+//! only if its nonce is greater than that of the last message it delivered from the same sender on
+//! the same rollup, so a message replayed in a later block is dropped.
+
+use crate::error::RollupError;
+use crate::transaction::{normalize, recover, Nonce, RollupTransaction, Weight};
+use ethers_core::{
+    types::{Address, Bytes, Signature, H256},
+    utils::{hash_message, keccak256},
+};
+use ethers_signers::Signer;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+/// Largest payload a message may carry, in bytes.
+pub const MAX_PAYLOAD_BYTES: usize = 1024;
+
+/// Most messages a block may send. Later ones are rejected.
+pub const MAX_OUTBOX_MESSAGES: usize = 16;
+
+/// Prefix of the preimage of an outbox commitment, so it cannot collide with other hashes.
+pub const OUTBOX_DOMAIN: &[u8] = b"example-l2 outbox";
+
+/// A message to an account of another rollup, as signed by its sender.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Message {
+    /// VM ID of the rollup the message is for.
+    pub dest_vm: u64,
+    #[serde(deserialize_with = "crate::address::deserialize")]
+    pub recipient: Address,
+    /// Chosen by the sender, and increasing with each message it sends to the same rollup.
+    pub nonce: Nonce,
+    pub payload: Bytes,
+}
+
+impl Message {
+    /// The canonical encoding of the message, which is what is signed.
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Serialization should not fail")
+    }
+
+    /// The digest a signature on this message signs: the EIP-191 personal message hash of the
+    /// encoded message.
+    pub fn signing_hash(&self) -> H256 {
+        hash_message(self.encode())
+    }
+}
+
+/// A transaction sending a [`Message`] to another rollup.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutboundMessage {
+    pub message: Message,
+    pub signature: Signature,
+}
+
+impl OutboundMessage {
+    /// Sign `message` with any [`Signer`].
+    pub async fn new<S: Signer>(message: Message, signer: &S) -> Result<Self, S::Error> {
+        let signature = signer.sign_message(message.encode()).await?;
+        Ok(Self {
+            message,
+            signature: normalize(signature),
+        })
+    }
+
+    /// The sender of the message, recovered from a signature in canonical form as for a
+    /// [`SignedTransaction`](crate::transaction::SignedTransaction).
+    pub fn sender(&self) -> Result<Address, RollupError> {
+        recover(&self.signature, self.message.encode())
+    }
+
+    /// Check everything about the message which does not depend on the rollup sending it, and
+    /// recover its sender.
+    pub fn verify(&self) -> Result<Address, RollupError> {
+        let size = self.message.payload.len();
+        if size > MAX_PAYLOAD_BYTES {
+            return Err(RollupError::PayloadTooLarge {
+                size,
+                max: MAX_PAYLOAD_BYTES,
+            });
+        }
+        self.sender()
+    }
+
+    /// The message as delivered, if it is valid when sent from the rollup with VM ID `source_vm`.
+    pub fn send(&self, source_vm: u64) -> Result<CrossVmMessage, RollupError> {
+        if self.message.dest_vm == source_vm {
+            return Err(RollupError::MessageToSelf { vm: source_vm });
+        }
+        let sender = self.verify()?;
+        let Message {
+            dest_vm,
+            recipient,
+            nonce,
+            payload,
+        } = self.message.clone();
+        Ok(CrossVmMessage {
+            source_vm,
+            sender,
+            dest_vm,
+            recipient,
+            nonce,
+            payload,
+        })
+    }
+}
+
+/// A message sent from an account of one rollup to an account of another.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrossVmMessage {
+    pub source_vm: u64,
+    pub sender: Address,
+    pub dest_vm: u64,
+    pub recipient: Address,
+    pub nonce: Nonce,
+    pub payload: Bytes,
+}
+
+impl CrossVmMessage {
+    /// Hash of the encoded message, which identifies it in the outbox commitment.
+    pub fn hash(&self) -> H256 {
+        H256(keccak256(
+            serde_json::to_vec(self).expect("Serialization should not fail"),
+        ))
+    }
+}
+
+/// What became of each of the `messages` a block of the rollup with VM ID `source_vm` tried to
+/// send, in the order they were sequenced. The messages sent are the block's outbox.
+pub fn outbox<'a>(
+    source_vm: u64,
+    messages: impl IntoIterator<Item = &'a OutboundMessage>,
+) -> Vec<Result<CrossVmMessage, RollupError>> {
+    let mut sent = 0;
+    messages
+        .into_iter()
+        .map(|message| {
+            if sent == MAX_OUTBOX_MESSAGES {
+                return Err(RollupError::OutboxFull {
+                    max: MAX_OUTBOX_MESSAGES,
+                });
+            }
+            let message = message.send(source_vm)?;
+            sent += 1;
+            Ok(message)
+        })
+        .collect()
+}
+
+/// The messages among the `transactions` of a block which fit in its weight budget of
+/// `max_block_weight`, in the order they were sequenced.
+///
+/// Transactions are charged against the budget in order of decreasing
+/// [priority](RollupTransaction::priority), in their order in the block among equal priorities,
+/// until the next one would exceed it; it and every one after it are skipped. This is the order in
+/// which the sending rollup applies the block, so these are the messages it tries to send.
+pub fn budgeted_messages<'a>(
+    transactions: impl IntoIterator<Item = &'a RollupTransaction>,
+    max_block_weight: Weight,
+) -> Vec<&'a OutboundMessage> {
+    let mut transactions = transactions.into_iter().collect::<Vec<_>>();
+    // The sort is stable, so equal priorities keep their positions in the block.
+    transactions.sort_by_key(|txn| Reverse(txn.priority()));
+    let mut used: Weight = 0;
+    transactions
+        .into_iter()
+        .map_while(|txn| {
+            used = used
+                .checked_add(txn.weight())
+                .filter(|used| *used <= max_block_weight)?;
+            Some(txn)
+        })
+        .filter_map(|txn| match txn {
+            RollupTransaction::OutboundMessage(message) => Some(message),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Commitment to the messages a block sent, in the order it sent them.
+pub fn outbox_commitment(messages: &[CrossVmMessage]) -> H256 {
+    let mut preimage = OUTBOX_DOMAIN.to_vec();
+    for message in messages {
+        preimage.extend_from_slice(message.hash().as_bytes());
+    }
+    H256(keccak256(preimage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_signers::LocalWallet;
+
+    fn message(dest_vm: u64, nonce: Nonce, payload: &[u8]) -> Message {
+        Message {
+            dest_vm,
+            recipient: Address::repeat_byte(2),
+            nonce,
+            payload: payload.to_vec().into(),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_send() {
+        let wallet = LocalWallet::new(&mut rand::thread_rng());
+        let outbound = OutboundMessage::new(message(2, 1, b"hello"), &wallet)
+            .await
+            .unwrap();
+        let sent = outbound.send(1).unwrap();
+        assert_eq!(sent.sender, wallet.address());
+        assert_eq!((sent.source_vm, sent.dest_vm), (1, 2));
+        assert_eq!(outbound.send(2), Err(RollupError::MessageToSelf { vm: 2 }));
+
+        // The signature covers the destination.
+        let mut redirected = outbound.clone();
+        redirected.message.dest_vm = 3;
+        assert_ne!(
+            redirected.send(1).map(|sent| sent.sender),
+            Ok(wallet.address())
+        );
+
+        let large = OutboundMessage::new(message(2, 2, &[0; MAX_PAYLOAD_BYTES + 1]), &wallet)
+            .await
+            .unwrap();
+        assert_eq!(
+            large.send(1),
+            Err(RollupError::PayloadTooLarge {
+                size: MAX_PAYLOAD_BYTES + 1,
+                max: MAX_PAYLOAD_BYTES
+            })
+        );
+
+        // An outbound message is its own kind of rollup transaction.
+        let txn = RollupTransaction::from(outbound.clone());
+        assert!(matches!(
+            RollupTransaction::decode(&txn.encode()),
+            Some(RollupTransaction::OutboundMessage(decoded)) if decoded == outbound
+        ));
+    }
+
+    #[async_std::test]
+    async fn test_outbox() {
+        let wallet = LocalWallet::new(&mut rand::thread_rng());
+        let mut messages = vec![];
+        for nonce in 1..=MAX_OUTBOX_MESSAGES as Nonce + 2 {
+            messages.push(
+                OutboundMessage::new(message(2, nonce, b"hi"), &wallet)
+                    .await
+                    .unwrap(),
+            );
+        }
+        // An invalid message does not take up a place in the outbox.
+        messages[0].message.dest_vm = 1;
+
+        let results = outbox(1, &messages);
+        assert_eq!(results[0], Err(RollupError::MessageToSelf { vm: 1 }));
+        assert!(results[1..=MAX_OUTBOX_MESSAGES].iter().all(Result::is_ok));
+        assert_eq!(
+            results[MAX_OUTBOX_MESSAGES + 1],
+            Err(RollupError::OutboxFull {
+                max: MAX_OUTBOX_MESSAGES
+            })
+        );
+
+        // The commitment depends on the order of the messages.
+        let sent = results.into_iter().flatten().collect::<Vec<_>>();
+        let mut reordered = sent.clone();
+        reordered.swap(0, 1);
+        assert_ne!(outbox_commitment(&sent), outbox_commitment(&reordered));
+        assert_ne!(outbox_commitment(&sent), outbox_commitment(&[]));
+    }
+
+    #[test]
+    fn test_message_hash_vector() {
+        // Pins the format, which every rollup exchanging messages must agree on.
+        let message = CrossVmMessage {
+            source_vm: 1,
+            sender: Address::repeat_byte(1),
+            dest_vm: 2,
+            recipient: Address::repeat_byte(2),
+            nonce: 1,
+            payload: b"hi".to_vec().into(),
+        };
+        assert_eq!(
+            String::from_utf8(serde_json::to_vec(&message).unwrap()).unwrap(),
+            r#"{"source_vm":1,"sender":"0x0101010101010101010101010101010101010101","dest_vm":2,"recipient":"0x0202020202020202020202020202020202020202","nonce":1,"payload":"0x6869"}"#
+        );
+        assert_eq!(
+            message.hash(),
+            "0x04e9e4b3f88c6643645c74938ff732772ca31c25fe23950aea42da07654b7f80"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(
+            outbox_commitment(&[message]),
+            "0x328b9c58abf1d5651d2c22a52bf6994e239362a3b941018aafc278651103dcce"
+                .parse()
+                .unwrap()
+        );
+    }
+}
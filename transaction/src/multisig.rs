@@ -0,0 +1,370 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Accounts controlled by m of n keys.
+//!
+//! A multisig account is created by a [`RegisterMultisig`] transaction, which names the signers
+//! and how many of them must sign. The address of the account is derived from that configuration,
+//! so it can be funded before it is registered, and registering it needs no signature: whoever
+//! registers a configuration only creates the account those signers would control anyway.
+//!
+//! The account spends with a [`MultisigTransfer`], which carries signatures by at least `threshold`
+//! distinct signers over the same payload: the account and the transfer.
+
+use crate::error::RollupError;
+use crate::transaction::{normalize, recover, Transaction};
+use ethers_core::{
+    types::{Address, Signature, H256},
+    utils::{hash_message, keccak256},
+};
+use ethers_signers::Signer;
+use serde::{Deserialize, Serialize};
+
+/// Most signers a multisig account may have, which bounds the signatures recovered per transfer.
+pub const MAX_SIGNERS: usize = 16;
+
+/// Prefix of the preimage of a multisig address, so it cannot collide with other hashes.
+const ADDRESS_DOMAIN: &[u8] = b"example-l2 multisig";
+
+/// The signers of a multisig account and how many of them must sign.
+///
+/// Signers are kept sorted, so a configuration has one encoding and one address whatever order its
+/// signers were listed in.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigConfig {
+    signers: Vec<Address>,
+    threshold: u32,
+}
+
+impl MultisigConfig {
+    /// A configuration requiring `threshold` of `signers`.
+    ///
+    /// There must be at most [`MAX_SIGNERS`] signers, with no duplicates, and the threshold must
+    /// be at least one and at most the number of signers.
+    pub fn new(mut signers: Vec<Address>, threshold: u32) -> Result<Self, RollupError> {
+        if signers.len() > MAX_SIGNERS {
+            return Err(RollupError::TooManySigners {
+                signers: signers.len(),
+                max: MAX_SIGNERS,
+            });
+        }
+        signers.sort();
+        if let Some(pair) = signers.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(RollupError::DuplicateSigner { address: pair[0] });
+        }
+        if threshold == 0 || threshold as usize > signers.len() {
+            return Err(RollupError::InvalidThreshold {
+                threshold,
+                signers: signers.len(),
+            });
+        }
+        Ok(Self { signers, threshold })
+    }
+
+    pub fn signers(&self) -> &[Address] {
+        &self.signers
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// The address of the account with this configuration.
+    pub fn address(&self) -> Address {
+        let encoded = serde_json::to_vec(self).expect("Serialization should not fail");
+        Address::from_slice(&keccak256([ADDRESS_DOMAIN, &encoded].concat())[12..])
+    }
+}
+
+/// A transaction creating the multisig account of a configuration.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisterMultisig {
+    #[serde(deserialize_with = "crate::address::deserialize_vec")]
+    pub signers: Vec<Address>,
+    pub threshold: u32,
+}
+
+impl RegisterMultisig {
+    /// The configuration to register, if it is valid.
+    pub fn config(&self) -> Result<MultisigConfig, RollupError> {
+        MultisigConfig::new(self.signers.clone(), self.threshold)
+    }
+}
+
+impl From<&MultisigConfig> for RegisterMultisig {
+    fn from(config: &MultisigConfig) -> Self {
+        Self {
+            signers: config.signers.clone(),
+            threshold: config.threshold,
+        }
+    }
+}
+
+/// What each signer of a [`MultisigTransfer`] signs. Including the account means a signature
+/// cannot be replayed to spend from another account the same key signs for.
+#[derive(Serialize)]
+struct MultisigPayload<'a> {
+    account: &'a Address,
+    transaction: &'a Transaction,
+}
+
+/// A transfer from a multisig account, signed by some of its signers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultisigTransfer {
+    #[serde(deserialize_with = "crate::address::deserialize")]
+    pub account: Address,
+    pub transaction: Transaction,
+    pub signatures: Vec<Signature>,
+}
+
+impl MultisigTransfer {
+    /// The canonical encoding of what each signer signs.
+    pub fn signing_payload(account: Address, transaction: &Transaction) -> Vec<u8> {
+        serde_json::to_vec(&MultisigPayload {
+            account: &account,
+            transaction,
+        })
+        .expect("Serialization should not fail")
+    }
+
+    /// The digest each signer signs: the EIP-191 personal message hash of the
+    /// [`signing_payload`](Self::signing_payload).
+    pub fn signing_hash(account: Address, transaction: &Transaction) -> H256 {
+        hash_message(Self::signing_payload(account, transaction))
+    }
+
+    /// Sign a transfer from `account` with any [`Signer`], returning one of the signatures to
+    /// collect into a [`MultisigTransfer`].
+    pub async fn sign<S: Signer>(
+        account: Address,
+        transaction: &Transaction,
+        signer: &S,
+    ) -> Result<Signature, S::Error> {
+        let signature = signer
+            .sign_message(Self::signing_payload(account, transaction))
+            .await?;
+        Ok(normalize(signature))
+    }
+
+    /// The distinct signers of the transfer.
+    ///
+    /// Each signature must be valid and in canonical form, as for a
+    /// [`SignedTransaction`](crate::transaction::SignedTransaction), and no key may sign twice.
+    pub fn signers(&self) -> Result<Vec<Address>, RollupError> {
+        if self.signatures.len() > MAX_SIGNERS {
+            return Err(RollupError::TooManySigners {
+                signers: self.signatures.len(),
+                max: MAX_SIGNERS,
+            });
+        }
+        // Every signer signs the same digest, so it is only computed once.
+        let digest = Self::signing_hash(self.account, &self.transaction);
+        let mut signers = Vec::with_capacity(self.signatures.len());
+        for signature in &self.signatures {
+            let signer = recover(signature, digest)?;
+            if signers.contains(&signer) {
+                return Err(RollupError::DuplicateSigner { address: signer });
+            }
+            signers.push(signer);
+        }
+        Ok(signers)
+    }
+
+    /// Check that the transfer is signed by at least `threshold` distinct signers of `config`.
+    pub fn verify(&self, config: &MultisigConfig) -> Result<(), RollupError> {
+        self.check_signers(&self.signers()?, config)
+    }
+
+    /// Check that `signers`, as recovered by [`signers`](Self::signers), are at least `threshold`
+    /// distinct signers of `config`. Lets the signers be recovered ahead of time, for instance in
+    /// parallel with those of other transactions.
+    pub fn check_signers(
+        &self,
+        signers: &[Address],
+        config: &MultisigConfig,
+    ) -> Result<(), RollupError> {
+        if let Some(signer) = signers
+            .iter()
+            .find(|signer| config.signers.binary_search(signer).is_err())
+        {
+            return Err(RollupError::NotASigner {
+                address: self.account,
+                signer: *signer,
+            });
+        }
+        if signers.len() < config.threshold as usize {
+            return Err(RollupError::BelowThreshold {
+                address: self.account,
+                threshold: config.threshold,
+                signatures: signers.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{RollupTransaction, SignedTransaction, DEFAULT_VM_ID};
+    use ethers_signers::LocalWallet;
+
+    fn wallets(n: usize) -> Vec<LocalWallet> {
+        let mut rng = rand::thread_rng();
+        (0..n).map(|_| LocalWallet::new(&mut rng)).collect()
+    }
+
+    #[test]
+    fn test_config() {
+        let [a, b, c] = [Address::random(), Address::random(), Address::random()];
+        let config = MultisigConfig::new(vec![c, a, b], 2).unwrap();
+        assert_eq!(
+            config.address(),
+            MultisigConfig::new(vec![a, b, c], 2).unwrap().address()
+        );
+        assert_ne!(
+            config.address(),
+            MultisigConfig::new(vec![a, b, c], 3).unwrap().address()
+        );
+
+        assert_eq!(
+            MultisigConfig::new(vec![a, b, a], 2),
+            Err(RollupError::DuplicateSigner { address: a })
+        );
+        assert_eq!(
+            MultisigConfig::new(vec![a, b], 0),
+            Err(RollupError::InvalidThreshold {
+                threshold: 0,
+                signers: 2
+            })
+        );
+        assert_eq!(
+            MultisigConfig::new(vec![a, b], 3),
+            Err(RollupError::InvalidThreshold {
+                threshold: 3,
+                signers: 2
+            })
+        );
+        assert!(matches!(
+            MultisigConfig::new(vec![Address::random(); MAX_SIGNERS + 1], 1),
+            Err(RollupError::TooManySigners { .. })
+        ));
+    }
+
+    #[async_std::test]
+    async fn test_verify() {
+        let wallets = wallets(4);
+        let config = MultisigConfig::new(
+            wallets[..3].iter().map(|wallet| wallet.address()).collect(),
+            2,
+        )
+        .unwrap();
+        let account = config.address();
+        let transaction = Transaction {
+            amount: 10,
+            destination: Address::random(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let mut signatures = vec![];
+        for wallet in &wallets {
+            signatures.push(
+                MultisigTransfer::sign(account, &transaction, wallet)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let transfer = |signatures: Vec<Signature>| MultisigTransfer {
+            account,
+            transaction: transaction.clone(),
+            signatures,
+        };
+
+        assert_eq!(
+            transfer(vec![signatures[0], signatures[2]]).verify(&config),
+            Ok(())
+        );
+        assert_eq!(
+            transfer(vec![signatures[1]]).verify(&config),
+            Err(RollupError::BelowThreshold {
+                address: account,
+                threshold: 2,
+                signatures: 1
+            })
+        );
+        assert_eq!(
+            transfer(vec![signatures[1], signatures[1]]).verify(&config),
+            Err(RollupError::DuplicateSigner {
+                address: wallets[1].address()
+            })
+        );
+        assert_eq!(
+            transfer(vec![signatures[0], signatures[3]]).verify(&config),
+            Err(RollupError::NotASigner {
+                address: account,
+                signer: wallets[3].address()
+            })
+        );
+
+        // A signature for one account does not spend from another.
+        let other = MultisigConfig::new(config.signers().to_vec(), 1).unwrap();
+        let replayed = MultisigTransfer {
+            account: other.address(),
+            ..transfer(vec![signatures[0]])
+        };
+        assert!(replayed.verify(&other).is_err());
+    }
+
+    #[async_std::test]
+    async fn test_encoding() {
+        let wallet = &wallets(1)[0];
+        let transaction = Transaction {
+            amount: 10,
+            destination: Address::random(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+
+        // A transfer is encoded exactly as a signed transaction.
+        let signed = SignedTransaction::new_with_wallet(transaction.clone(), wallet).await;
+        let txn = RollupTransaction::from(signed.clone());
+        assert_eq!(txn.encode(), signed.encode());
+        assert_eq!(txn.hash(), signed.hash());
+
+        let config = MultisigConfig::new(vec![wallet.address()], 1).unwrap();
+        let register = RegisterMultisig::from(&config);
+        let account = config.address();
+        let spend = MultisigTransfer {
+            account,
+            signatures: vec![MultisigTransfer::sign(account, &transaction, wallet)
+                .await
+                .unwrap()],
+            transaction,
+        };
+        for txn in [
+            RollupTransaction::from(signed),
+            register.clone().into(),
+            spend.clone().into(),
+        ] {
+            let decoded = RollupTransaction::decode(&txn.encode()).unwrap();
+            assert_eq!(decoded.encode(), txn.encode());
+        }
+        assert!(matches!(
+            RollupTransaction::decode(&RollupTransaction::from(register).encode()),
+            Some(RollupTransaction::RegisterMultisig(_))
+        ));
+        assert!(matches!(
+            RollupTransaction::decode(&RollupTransaction::from(spend).encode()),
+            Some(RollupTransaction::MultisigTransfer(_))
+        ));
+    }
+}
@@ -0,0 +1,701 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Rollup transactions, their canonical encoding and their signatures.
+//!
+//! Transfers are [`SignedTransaction`]s. Every kind of transaction the rollup sequences, including
+//! those of [`multisig`](crate::multisig) accounts, [messages](crate::message) to other rollups
+//! and changes to the [minimum fee](crate::fee), is a [`RollupTransaction`].
+
+use crate::error::RollupError;
+use crate::fee::SignedSetMinFee;
+use crate::message::OutboundMessage;
+use crate::multisig::{MultisigTransfer, RegisterMultisig};
+use ethers_core::{
+    types::{Address, RecoveryMessage, Signature, H256, U256},
+    utils::{hash_message, keccak256},
+};
+use ethers_signers::{LocalWallet, Signer};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+pub type Amount = u64;
+pub type Nonce = u64;
+
+/// The cost of executing a transaction, in abstract units. See [`RollupTransaction::weight`].
+pub type Weight = u64;
+
+/// Weight of every transaction, for decoding it and updating accounts.
+pub const BASE_WEIGHT: Weight = 1_000;
+/// Weight of each byte of the encoded transaction.
+pub const WEIGHT_PER_BYTE: Weight = 10;
+/// Weight of recovering the signer of a signature, the most expensive step of execution.
+pub const WEIGHT_PER_SIGNATURE: Weight = 20_000;
+/// Weight of storing each signer of a multisig account when it is registered.
+pub const WEIGHT_PER_SIGNER: Weight = 5_000;
+
+/// The VM a transaction is for when it does not say, which is the VM of every transaction signed
+/// before transactions named their VM.
+pub const DEFAULT_VM_ID: u64 = 1;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Transaction {
+    pub amount: Amount,
+    #[serde(deserialize_with = "crate::address::deserialize")]
+    pub destination: Address,
+    pub nonce: Nonce,
+    /// A tip paid by the sender, on top of `amount`, for an earlier position in the block.
+    ///
+    /// Within a block, rollup transactions execute in order of decreasing priority, and
+    /// transactions with equal priority in the order the sequencer included them. The tip is
+    /// burned, and must be at least the rollup's [minimum fee](crate::fee). It is left out of the
+    /// encoding when zero, so transactions signed before priorities existed keep their signatures
+    /// and hashes.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: Amount,
+    /// The rollup the transaction is for. It is signed along with the rest of the transaction, so a
+    /// transaction for one rollup cannot be replayed on another sharing the sequencer. It is left
+    /// out of the encoding when it is [`DEFAULT_VM_ID`], so transactions signed before it existed
+    /// keep their signatures and hashes.
+    #[serde(default = "default_vm_id", skip_serializing_if = "is_default_vm_id")]
+    pub vm_id: u64,
+    /// The asset transferred: a token deposited from L1 and listed in the rollup's chain
+    /// configuration, or the native asset if `None`. Fees, such as the priority tip, are always
+    /// paid in the native asset, whatever is transferred. It is left out of the encoding when
+    /// `None`, so transactions signed before it existed keep their signatures and hashes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<Address>,
+}
+
+fn is_zero(priority: &Amount) -> bool {
+    *priority == 0
+}
+
+fn default_vm_id() -> u64 {
+    DEFAULT_VM_ID
+}
+
+fn is_default_vm_id(vm_id: &u64) -> bool {
+    *vm_id == DEFAULT_VM_ID
+}
+
+impl Transaction {
+    /// The digest a signature on this transaction signs.
+    ///
+    /// This is the EIP-191 personal message hash of the encoded transaction, which is what
+    /// [`Signer::sign_message`] signs. Signers which do not implement [`Signer`] can sign this
+    /// digest directly and build the transaction with [`SignedTransaction::from_signature`].
+    pub fn signing_hash(&self) -> H256 {
+        hash_message(self.encode())
+    }
+
+    /// The canonical encoding of the transaction, which is what is signed.
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_string(&self)
+            .expect("Serialization should not fail")
+            .as_bytes()
+            .to_vec()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+#[cfg(feature = "vm")]
+impl sequencer::VmTransaction for Transaction {
+    fn encode(&self) -> Vec<u8> {
+        Transaction::encode(self)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Transaction::decode(bytes)
+    }
+}
+
+#[cfg(feature = "vm")]
+impl sequencer::VmTransaction for SignedTransaction {
+    fn encode(&self) -> Vec<u8> {
+        SignedTransaction::encode(self)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        SignedTransaction::decode(bytes)
+    }
+}
+
+#[cfg(feature = "vm")]
+impl sequencer::VmTransaction for RollupTransaction {
+    fn encode(&self) -> Vec<u8> {
+        RollupTransaction::encode(self)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        RollupTransaction::decode(bytes)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedTransaction {
+    pub transaction: Transaction,
+    signature: Signature,
+    // The result of verifying the signature, with the transaction it was verified for, so that it
+    // is not reused if the transaction is changed afterwards. Never serialized, so whoever sends a
+    // transaction cannot supply it.
+    #[serde(skip)]
+    sender: OnceLock<(Transaction, Result<Address, RollupError>)>,
+}
+
+/// Order of the secp256k1 group.
+const CURVE_ORDER: U256 = U256([
+    0xBFD25E8CD0364141,
+    0xBAAEDCE6AF48A03B,
+    0xFFFFFFFFFFFFFFFE,
+    0xFFFFFFFFFFFFFFFF,
+]);
+
+/// Half the order of the secp256k1 group. Every ECDSA signature has a twin, with `s` replaced by
+/// the order minus `s`, which is just as valid; only the twin with `s` at most this is accepted.
+const HALF_CURVE_ORDER: U256 = U256([
+    0xDFE92F46681B20A0,
+    0x5D576E7357A4501D,
+    0xFFFFFFFFFFFFFFFF,
+    0x7FFFFFFFFFFFFFFF,
+]);
+
+impl SignedTransaction {
+    /// Check the signature and recover the signer.
+    ///
+    /// Only one encoding of each signature is accepted, so that a transaction cannot be re-encoded
+    /// under a different hash: `s` must be in the lower half of the curve order and `v` must be 27
+    /// or 28. Signatures which recover to the zero address are rejected as well.
+    pub fn verify(&self) -> Result<Address, RollupError> {
+        recover(&self.signature, self.transaction.encode())
+    }
+
+    /// The signer of the transaction, as found by [`verify`](Self::verify).
+    ///
+    /// Recovering the signer is the most expensive part of processing a transaction, so the result
+    /// is remembered and later calls are cheap.
+    pub fn sender(&self) -> Result<Address, RollupError> {
+        let (transaction, sender) = self
+            .sender
+            .get_or_init(|| (self.transaction.clone(), self.verify()));
+        if *transaction == self.transaction {
+            sender.clone()
+        } else {
+            self.verify()
+        }
+    }
+
+    /// The canonical encoding of the signed transaction, which is what is sequenced.
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_string(&self)
+            .expect("Serialization should not fail")
+            .as_bytes()
+            .to_vec()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice(bytes).ok()
+    }
+
+    /// Hash of the encoded transaction, which identifies it to clients.
+    pub fn hash(&self) -> H256 {
+        H256(keccak256(self.encode()))
+    }
+
+    /// Sign `transaction` with any [`Signer`], such as a hardware wallet or a remote key service.
+    pub async fn new<S: Signer>(transaction: Transaction, signer: &S) -> Result<Self, S::Error> {
+        let signature = signer.sign_message(transaction.encode()).await?;
+        Ok(Self::from_signature(transaction, signature))
+    }
+
+    /// Sign `transaction` with a key held in memory, which cannot fail.
+    pub async fn new_with_wallet(transaction: Transaction, wallet: &LocalWallet) -> Self {
+        Self::new(transaction, wallet)
+            .await
+            .expect("signing with a local key does not fail")
+    }
+
+    /// Attach a signature of [`Transaction::signing_hash`] made elsewhere.
+    ///
+    /// The signature is stored in its canonical low-s form. Whether it is valid is checked by
+    /// [`verify`](Self::verify).
+    pub fn from_signature(transaction: Transaction, signature: Signature) -> Self {
+        Self {
+            signature: normalize(signature),
+            transaction,
+            sender: OnceLock::new(),
+        }
+    }
+
+    /// Attach a signature exactly as given, even if it is not in canonical form, so that
+    /// [`verify`](Self::verify) rejects it. Used for transactions received from clients.
+    pub fn from_parts(transaction: Transaction, signature: Signature) -> Self {
+        Self {
+            transaction,
+            signature,
+            sender: OnceLock::new(),
+        }
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+}
+
+/// Any transaction of the rollup, as sequenced.
+///
+/// The kinds are told apart by their fields rather than by a tag, so a transfer is encoded exactly
+/// as a [`SignedTransaction`], and transfers signed before other kinds existed keep their encodings
+/// and hashes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RollupTransaction {
+    Transfer(SignedTransaction),
+    RegisterMultisig(RegisterMultisig),
+    MultisigTransfer(MultisigTransfer),
+    OutboundMessage(OutboundMessage),
+    SetMinFee(SignedSetMinFee),
+}
+
+impl RollupTransaction {
+    /// Check everything about the transaction which does not depend on the state: the signatures
+    /// of a transfer, message or fee change, or the configuration a registration would create.
+    pub fn verify(&self) -> Result<(), RollupError> {
+        match self {
+            Self::Transfer(txn) => txn.sender().map(drop),
+            Self::RegisterMultisig(txn) => txn.config().map(drop),
+            Self::MultisigTransfer(txn) => txn.signers().map(drop),
+            Self::OutboundMessage(txn) => txn.verify().map(drop),
+            Self::SetMinFee(txn) => txn.signer().map(drop),
+        }
+    }
+
+    /// The priority of the transaction. Registrations, messages and fee changes have none.
+    pub fn priority(&self) -> Amount {
+        match self {
+            Self::Transfer(txn) => txn.transaction.priority,
+            Self::RegisterMultisig(_) | Self::OutboundMessage(_) | Self::SetMinFee(_) => 0,
+            Self::MultisigTransfer(txn) => txn.transaction.priority,
+        }
+    }
+
+    /// The VM a transfer or fee change is for. Registrations and messages are not signed for a VM.
+    pub fn vm_id(&self) -> Option<u64> {
+        match self {
+            Self::Transfer(txn) => Some(txn.transaction.vm_id),
+            Self::RegisterMultisig(_) | Self::OutboundMessage(_) => None,
+            Self::MultisigTransfer(txn) => Some(txn.transaction.vm_id),
+            Self::SetMinFee(txn) => Some(txn.set_min_fee.vm_id),
+        }
+    }
+
+    /// The account a transfer pays. Registrations, messages and fee changes pay no account of this
+    /// rollup.
+    pub fn destination(&self) -> Option<Address> {
+        match self {
+            Self::Transfer(txn) => Some(txn.transaction.destination),
+            Self::RegisterMultisig(_) | Self::OutboundMessage(_) | Self::SetMinFee(_) => None,
+            Self::MultisigTransfer(txn) => Some(txn.transaction.destination),
+        }
+    }
+
+    /// The cost of executing the transaction, which counts against the weight budget of its block.
+    ///
+    /// Every transaction pays [`BASE_WEIGHT`] and [`WEIGHT_PER_BYTE`] of its encoding. Transfers,
+    /// messages and fee changes also pay [`WEIGHT_PER_SIGNATURE`] for each signature they carry, and
+    /// registrations [`WEIGHT_PER_SIGNER`] for each signer they store. The weight only depends on
+    /// the transaction itself, so every executor computes the same weight whether or not the
+    /// transaction turns out to be valid. Messages count against the budget of their block like
+    /// any other transaction.
+    ///
+    /// This is the weight of the canonical encoding. A transaction sequenced in a longer payload
+    /// pays for every byte of it; see [`weight_of_payload`](Self::weight_of_payload).
+    pub fn weight(&self) -> Weight {
+        self.weight_of_payload(self.encode().len())
+    }
+
+    /// The [hash](Self::hash) and [weight](Self::weight) of the transaction, encoding it only once.
+    pub fn hash_and_weight(&self) -> (H256, Weight) {
+        let encoded = self.encode();
+        (
+            H256(keccak256(&encoded)),
+            self.weight_of_payload(encoded.len()),
+        )
+    }
+
+    /// The weight of the transaction as sequenced in a payload of `len` bytes.
+    ///
+    /// A payload can decode to the transaction and still be longer than its canonical encoding,
+    /// with whitespace or fields the transaction does not have. Executing it means reading every
+    /// byte, so every byte is charged.
+    pub fn weight_of_payload(&self, len: usize) -> Weight {
+        let kind = match self {
+            Self::Transfer(_) | Self::OutboundMessage(_) | Self::SetMinFee(_) => {
+                WEIGHT_PER_SIGNATURE
+            }
+            Self::RegisterMultisig(txn) => WEIGHT_PER_SIGNER * txn.signers.len() as Weight,
+            Self::MultisigTransfer(txn) => WEIGHT_PER_SIGNATURE * txn.signatures.len() as Weight,
+        };
+        BASE_WEIGHT + WEIGHT_PER_BYTE * len as Weight + kind
+    }
+
+    /// The canonical encoding of the transaction, which is what is sequenced.
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_string(&self)
+            .expect("Serialization should not fail")
+            .as_bytes()
+            .to_vec()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice(bytes).ok()
+    }
+
+    /// Hash of the encoded transaction, which identifies it to clients.
+    pub fn hash(&self) -> H256 {
+        H256(keccak256(self.encode()))
+    }
+}
+
+impl From<SignedTransaction> for RollupTransaction {
+    fn from(txn: SignedTransaction) -> Self {
+        Self::Transfer(txn)
+    }
+}
+
+impl From<RegisterMultisig> for RollupTransaction {
+    fn from(txn: RegisterMultisig) -> Self {
+        Self::RegisterMultisig(txn)
+    }
+}
+
+impl From<MultisigTransfer> for RollupTransaction {
+    fn from(txn: MultisigTransfer) -> Self {
+        Self::MultisigTransfer(txn)
+    }
+}
+
+impl From<OutboundMessage> for RollupTransaction {
+    fn from(txn: OutboundMessage) -> Self {
+        Self::OutboundMessage(txn)
+    }
+}
+
+impl From<SignedSetMinFee> for RollupTransaction {
+    fn from(txn: SignedSetMinFee) -> Self {
+        Self::SetMinFee(txn)
+    }
+}
+
+/// Check that `signature` is in canonical form and recover the address which signed `message`,
+/// as described under [`SignedTransaction::verify`].
+pub(crate) fn recover(
+    signature: &Signature,
+    message: impl Into<RecoveryMessage>,
+) -> Result<Address, RollupError> {
+    let Signature { s, v, .. } = *signature;
+    if s > HALF_CURVE_ORDER {
+        return Err(RollupError::MalleableSignature);
+    }
+    if v != 27 && v != 28 {
+        return Err(RollupError::InvalidRecoveryId { v });
+    }
+    let signer = signature
+        .recover(message)
+        .map_err(|_| RollupError::SignatureError)?;
+    if signer.is_zero() {
+        return Err(RollupError::ZeroAddressSigner);
+    }
+    Ok(signer)
+}
+
+/// The low-s twin of `signature`, which is the only form [`SignedTransaction::verify`] accepts.
+pub(crate) fn normalize(mut signature: Signature) -> Signature {
+    if signature.s > HALF_CURVE_ORDER {
+        signature.s = CURVE_ORDER - signature.s;
+        signature.v = if signature.v == 27 { 28 } else { 27 };
+    }
+    signature
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transaction::Transaction;
+    use async_std::task::sleep;
+    use async_trait::async_trait;
+    use ethers_core::types::transaction::{eip2718::TypedTransaction, eip712::Eip712};
+    use ethers_signers::{LocalWallet, Signer, WalletError};
+    use std::time::Duration;
+
+    use super::*;
+    #[async_std::test]
+    async fn test_transaction_signature() {
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let transaction = Transaction {
+            amount: 100,
+            destination: alice.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let signed_transaction = SignedTransaction::new_with_wallet(transaction, &alice).await;
+        let recovered_address = signed_transaction.verify().expect("Should recover address");
+        assert_eq!(recovered_address, alice.address());
+    }
+
+    #[test]
+    fn test_destination_checksum() {
+        let valid =
+            r#"{"amount":1,"destination":"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed","nonce":1}"#;
+        assert!(Transaction::decode(valid.as_bytes()).is_some());
+        assert!(Transaction::decode(valid.to_lowercase().as_bytes()).is_some());
+
+        let typo = valid.replacen("aA", "Aa", 1);
+        let err = serde_json::from_str::<Transaction>(&typo).unwrap_err();
+        assert!(err.to_string().contains("checksum"));
+    }
+
+    #[async_std::test]
+    async fn test_priority_is_signed() {
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let transaction = Transaction {
+            amount: 100,
+            destination: alice.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+
+        // A zero priority is left out, so the encoding is the same as before priorities existed.
+        assert!(!String::from_utf8(transaction.encode())
+            .unwrap()
+            .contains("priority"));
+
+        // Raising the priority of a signed transaction invalidates the signature.
+        let mut signed = SignedTransaction::new_with_wallet(transaction, &alice).await;
+        signed.transaction.priority = 5;
+        assert_ne!(signed.verify(), Ok(alice.address()));
+    }
+
+    #[async_std::test]
+    async fn test_vm_id_is_signed() {
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let transaction = Transaction {
+            amount: 100,
+            destination: alice.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+
+        // The default VM is left out, so the encoding is the same as before transactions named
+        // their VM.
+        assert!(!String::from_utf8(transaction.encode())
+            .unwrap()
+            .contains("vm_id"));
+
+        // The same transfer for another VM is a different transaction, and the signature for one VM
+        // does not verify for the other.
+        let other = Transaction {
+            vm_id: 5,
+            ..transaction.clone()
+        };
+        assert_ne!(other.signing_hash(), transaction.signing_hash());
+        let mut signed = SignedTransaction::new_with_wallet(other, &alice).await;
+        assert_eq!(signed.verify(), Ok(alice.address()));
+        signed.transaction.vm_id = 7;
+        assert_ne!(signed.verify(), Ok(alice.address()));
+    }
+
+    #[async_std::test]
+    async fn test_sender_cache() {
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let transaction = Transaction {
+            amount: 100,
+            destination: alice.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let mut signed = SignedTransaction::new_with_wallet(transaction, &alice).await;
+        assert_eq!(signed.sender(), Ok(alice.address()));
+        assert_eq!(signed.sender(), Ok(alice.address()));
+
+        // The cache is not part of the encoding, so it cannot be sent along with a transaction.
+        let encoded = signed.encode();
+        assert_eq!(
+            SignedTransaction::decode(&encoded).unwrap().encode(),
+            encoded
+        );
+        assert!(SignedTransaction::decode(&encoded)
+            .unwrap()
+            .sender
+            .get()
+            .is_none());
+
+        // Changing the transaction after the sender was cached does not reuse the cached sender.
+        signed.transaction.amount = 1_000_000;
+        assert_ne!(signed.sender(), Ok(alice.address()));
+        assert_eq!(signed.sender(), signed.verify());
+    }
+
+    /// A signer whose key is held elsewhere, answering after a delay.
+    #[derive(Debug)]
+    struct RemoteSigner(LocalWallet);
+
+    #[async_trait]
+    impl Signer for RemoteSigner {
+        type Error = WalletError;
+
+        async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+            &self,
+            message: S,
+        ) -> Result<Signature, Self::Error> {
+            sleep(Duration::from_millis(50)).await;
+            self.0.sign_message(message).await
+        }
+
+        async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+            self.0.sign_transaction(tx).await
+        }
+
+        async fn sign_typed_data<T: Eip712 + Send + Sync>(
+            &self,
+            payload: &T,
+        ) -> Result<Signature, Self::Error> {
+            self.0.sign_typed_data(payload).await
+        }
+
+        fn address(&self) -> Address {
+            self.0.address()
+        }
+
+        fn chain_id(&self) -> u64 {
+            self.0.chain_id()
+        }
+
+        fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+            Self(self.0.with_chain_id(chain_id))
+        }
+    }
+
+    #[async_std::test]
+    async fn test_external_signers() {
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let transaction = Transaction {
+            amount: 100,
+            destination: alice.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+
+        let local = SignedTransaction::new(transaction.clone(), &alice)
+            .await
+            .unwrap();
+        assert_eq!(local.verify(), Ok(alice.address()));
+
+        let remote = RemoteSigner(alice.clone());
+        let signed = SignedTransaction::new(transaction.clone(), &remote)
+            .await
+            .unwrap();
+        assert_eq!(signed.verify(), Ok(alice.address()));
+        assert_eq!(signed.hash(), local.hash());
+
+        // A signer outside ethers only needs the digest.
+        let signature = alice.sign_hash(transaction.signing_hash()).unwrap();
+        let signed = SignedTransaction::from_signature(transaction, signature);
+        assert_eq!(signed.verify(), Ok(alice.address()));
+        assert_eq!(signed.hash(), local.hash());
+    }
+
+    /// The high-s twin of a signature, which signs the same message with the same key.
+    fn malleate(signed: &SignedTransaction) -> SignedTransaction {
+        let mut twin = signed.clone();
+        twin.signature.s = CURVE_ORDER - twin.signature.s;
+        twin.signature.v = if twin.signature.v == 27 { 28 } else { 27 };
+        twin.sender = OnceLock::new();
+        twin
+    }
+
+    #[async_std::test]
+    async fn test_malleable_signature() {
+        let mut rng = rand::thread_rng();
+        let alice = LocalWallet::new(&mut rng);
+        let transaction = Transaction {
+            amount: 100,
+            destination: alice.address(),
+            nonce: 1,
+            priority: 0,
+            vm_id: DEFAULT_VM_ID,
+            asset: None,
+        };
+        let signed = SignedTransaction::new_with_wallet(transaction, &alice).await;
+        assert!(signed.signature.s <= HALF_CURVE_ORDER);
+        assert_eq!(signed.verify(), Ok(alice.address()));
+
+        // The twin is a valid ECDSA signature by the same key, with a different hash.
+        let twin = malleate(&signed);
+        assert_eq!(
+            twin.signature.recover(twin.transaction.encode()).unwrap(),
+            alice.address()
+        );
+        assert_ne!(twin.hash(), signed.hash());
+        assert_eq!(twin.verify(), Err(RollupError::MalleableSignature));
+        assert_eq!(normalize(twin.signature), signed.signature);
+
+        let mut bad_v = signed.clone();
+        bad_v.signature.v = 1;
+        assert_eq!(bad_v.verify(), Err(RollupError::InvalidRecoveryId { v: 1 }));
+        bad_v.signature.v = 37;
+        assert_eq!(
+            bad_v.verify(),
+            Err(RollupError::InvalidRecoveryId { v: 37 })
+        );
+    }
+
+    #[test]
+    fn test_signature_vector() {
+        // A transfer from the genesis account signed with ethers, as in the README.
+        let signed: SignedTransaction = serde_json::from_str(
+            r#"{
+                "transaction": {
+                    "amount": 100,
+                    "destination": "0x885ee92eebda03540066a25a57cc625bbee15d5a",
+                    "nonce": 1
+                },
+                "signature": {
+                    "r": "0x61395b25cf41321bc1242ec301c0aa5a5e5ff47b697f80119a20ce3e5be66f9e",
+                    "s": "0x447cf03a5ddb28b9a189d108a8e91efa523fd3fb37cebab1cad610d82a8edbb0",
+                    "v": 27
+                }
+            }"#,
+        )
+        .unwrap();
+        let genesis: Address = "0xf23694f9c6d4837fc596c4eb7c3c3d8a8bae69ca"
+            .parse()
+            .unwrap();
+        assert_eq!(signed.verify(), Ok(genesis));
+        assert_eq!(normalize(signed.signature), signed.signature);
+        assert_eq!(
+            malleate(&signed).verify(),
+            Err(RollupError::MalleableSignature)
+        );
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright (c) 2023 Espresso Systems (espressosys.com)
+// This file is part of the sequencer-example-l2 repository.
+
+// You should have received a copy of the MIT License
+// along with the sequencer-example-l2 repository. If not, see <https://mit-license.org/>.
+
+//! Run with `wasm-pack test --node transaction -- --features wasm`.
+
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use ethers_core::utils::hex;
+use example_l2_transaction::{
+    address::parse_address,
+    transaction::SignedTransaction,
+    wasm::{recover_signer, sign_transfer},
+};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+// The transfer from the README, signed natively by the first genesis account.
+const SIGNED_TRANSFER: &str = include_str!("fixtures/signed_transfer.json");
+const GENESIS_KEY: &str = "0xb2f7f581d6de3c06a822fd6e7e8265fbc00f8401696a5bdc34f5a6d2ff3f922f";
+const GENESIS: &str = "0xf23694f9c6D4837fc596c4Eb7c3c3d8a8BaE69Ca";
+const DESTINATION: &str = "0x885EE92EebdA03540066a25A57cC625BbEE15d5A";
+
+#[wasm_bindgen_test]
+fn signed_in_wasm_matches_native() {
+    let signed = sign_transfer(GENESIS_KEY, DESTINATION, 100, 1).unwrap();
+    assert_eq!(signed, format!("0x{}", hex::encode(SIGNED_TRANSFER)));
+}
+
+#[wasm_bindgen_test]
+fn signed_natively_verifies_in_wasm() {
+    let signed = SignedTransaction::decode(SIGNED_TRANSFER.as_bytes()).unwrap();
+    assert_eq!(signed.verify().unwrap(), parse_address(GENESIS).unwrap());
+    assert_eq!(
+        recover_signer(&format!("0x{}", hex::encode(SIGNED_TRANSFER))).unwrap(),
+        GENESIS
+    );
+}